@@ -0,0 +1,156 @@
+// Shared setup for the benchmarks, along with the JSON report.
+//
+// Set `WISPY_BENCH_JSON=<path>` to write a JSON summary of every benchmark run in this invocation
+// to `<path>`. Entries already in the file (for example, from other bench targets) are kept, so
+// `cargo bench --features bench` produces a single report across all targets.
+
+#![allow(dead_code)]
+
+use ark_bn254::Fr as F;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::Boolean};
+use ark_relations::r1cs::Result as ArkResult;
+use serde_json::{json, Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use zk_callbacks::{
+    generic::{
+        interaction::{Callback, Interaction},
+        object::{Id, Time},
+        user::{User, UserVar},
+    },
+    impls::centralized::crypto::NoSigOTP,
+    scannable_zk_object,
+};
+
+#[scannable_zk_object(F)]
+#[derive(Default)]
+pub struct BenchData {
+    pub karma: F,
+    pub count: F,
+}
+
+pub type U = User<F, BenchData>;
+pub type UV = UserVar<F, BenchData>;
+pub type Cr = NoSigOTP<F>;
+pub type CB = Callback<F, BenchData, F, FpVar<F>>;
+pub type Int<const N: usize> = Interaction<F, BenchData, (), (), (), (), F, FpVar<F>, N>;
+
+// Every interaction increments the count.
+fn count(tu: &U, _pub_args: (), _priv_args: ()) -> U {
+    let mut out = tu.clone();
+    out.data.count += F::from(1);
+    out
+}
+
+fn count_pred(tu_old: &UV, tu_new: &UV, _pub_args: (), _priv_args: ()) -> ArkResult<Boolean<F>> {
+    let count = tu_new
+        .data
+        .count
+        .is_eq(&(tu_old.data.count.clone() + FpVar::Constant(F::from(1))))?;
+    let karma = tu_new.data.karma.is_eq(&tu_old.data.karma)?;
+    Ok(count & karma)
+}
+
+fn add_karma(tu: &U, args: F) -> U {
+    let mut out = tu.clone();
+    out.data.karma += args;
+    out
+}
+
+fn add_karma_pred(tu_old: &UV, args: FpVar<F>) -> ArkResult<UV> {
+    let mut tu_new = tu_old.clone();
+    tu_new.data.karma = tu_new.data.karma + args;
+    Ok(tu_new)
+}
+
+pub fn callback() -> CB {
+    Callback {
+        method_id: Id::from(0),
+        expirable: false,
+        expiration: Time::from(0),
+        method: add_karma,
+        predicate: add_karma_pred,
+    }
+}
+
+// An interaction handing out `N` callbacks.
+pub fn interaction<const N: usize>() -> Int<N> {
+    Interaction {
+        meth: (count, count_pred),
+        callbacks: core::array::from_fn(|_| callback()),
+    }
+}
+
+// Write the JSON report, if asked for, with every benchmark which finished after `since`.
+pub fn json_report(since: SystemTime) {
+    let Ok(path) = std::env::var("WISPY_BENCH_JSON") else {
+        return;
+    };
+
+    let mut report = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Map<String, Value>>(&s).ok())
+        .unwrap_or_default();
+    collect(&criterion_dir(), since, &mut report);
+
+    fs::write(&path, serde_json::to_string_pretty(&report).unwrap())
+        .expect("failed to write the benchmark report");
+}
+
+fn criterion_dir() -> PathBuf {
+    if let Ok(home) = std::env::var("CRITERION_HOME") {
+        return home.into();
+    }
+    let target = std::env::var("CARGO_TARGET_DIR").unwrap_or_else(|_| "target".to_string());
+    Path::new(&target).join("criterion")
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn collect(dir: &Path, since: SystemTime, report: &mut Map<String, Value>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() || path.ends_with("report") {
+            continue;
+        }
+        let new = path.join("new");
+        let estimates = new.join("estimates.json");
+        if !estimates.exists() {
+            collect(&path, since, report);
+            continue;
+        }
+        let fresh = fs::metadata(&estimates)
+            .and_then(|m| m.modified())
+            .is_ok_and(|t| t >= since);
+        if !fresh {
+            continue;
+        }
+        let (Some(bench), Some(est)) = (
+            read_json(&new.join("benchmark.json")),
+            read_json(&estimates),
+        ) else {
+            continue;
+        };
+        let Some(id) = bench["full_id"].as_str() else {
+            continue;
+        };
+        report.insert(
+            id.to_string(),
+            json!({
+                "mean_ns": est["mean"]["point_estimate"],
+                "lower_ns": est["mean"]["confidence_interval"]["lower_bound"],
+                "upper_ns": est["mean"]["confidence_interval"]["upper_bound"],
+                "std_dev_ns": est["std_dev"]["point_estimate"],
+                "median_ns": est["median"]["point_estimate"],
+            }),
+        );
+    }
+}
@@ -0,0 +1,80 @@
+// Poseidon against Poseidon2: committing to a user natively, and proving an interaction with a
+// single callback (where the hash is used for commitments, nullifiers, and the callback chain).
+
+mod common;
+
+use ark_bn254::{Bn254 as E, Fr as F};
+use ark_groth16::Groth16;
+use ark_r1cs_std::fields::fp::FpVar;
+use common::{interaction, BenchData, Cr, U};
+use criterion::{criterion_group, Criterion};
+use rand::thread_rng;
+use std::time::SystemTime;
+use zk_callbacks::{
+    crypto::hash::FieldHash,
+    generic::{bulletin::JoinableBulletin, object::Time, user::User},
+    impls::{
+        centralized::{crypto::FakeSigPubkey, ds::sigstore::GRSchnorrObjStore},
+        hash::{Poseidon, Poseidon2},
+    },
+};
+
+type Snark = Groth16<E>;
+
+fn bench_commit<H: FieldHash<F>>(c: &mut Criterion, name: &str) {
+    let u: U = User::create(BenchData::default(), &mut thread_rng());
+    c.benchmark_group("commit")
+        .bench_function(name, |b| b.iter(|| u.commit::<H>()));
+}
+
+fn bench_interact<H: FieldHash<F>>(c: &mut Criterion, name: &str) {
+    let mut rng = thread_rng();
+    let int = interaction::<1>();
+
+    let mut bul = GRSchnorrObjStore::new(&mut rng);
+    let (pk, _) = int.generate_keys::<H, Snark, Cr, GRSchnorrObjStore>(
+        &mut rng,
+        Some(bul.get_pubkey()),
+        None,
+        false,
+    );
+    let u: U = User::create(BenchData::default(), &mut rng);
+    <GRSchnorrObjStore as JoinableBulletin<F, BenchData>>::join_bul(&mut bul, u.commit::<H>(), ())
+        .unwrap();
+
+    c.benchmark_group("hash_interact")
+        .sample_size(10)
+        .bench_function(name, |b| {
+            b.iter(|| {
+                u.clone()
+                    .exec_method_create_cb::<H, (), (), (), (), F, FpVar<F>, Cr, Snark, GRSchnorrObjStore, 1>(
+                        &mut rng,
+                        int.clone(),
+                        [FakeSigPubkey::pk()],
+                        Time::from(0),
+                        &bul,
+                        true,
+                        &pk,
+                        (),
+                        (),
+                    )
+                    .unwrap()
+            })
+        });
+}
+
+fn hash(c: &mut Criterion) {
+    bench_commit::<Poseidon<2>>(c, "poseidon");
+    bench_commit::<Poseidon2<2>>(c, "poseidon2");
+    bench_interact::<Poseidon<2>>(c, "poseidon");
+    bench_interact::<Poseidon2<2>>(c, "poseidon2");
+}
+
+criterion_group!(benches, hash);
+
+fn main() {
+    let start = SystemTime::now();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    common::json_report(start);
+}
@@ -0,0 +1,108 @@
+// Proving interactions: with 0, 1, and 4 callbacks against a signature bulletin, and with a single
+// callback against a signature bulletin and a Merkle bulletin.
+
+mod common;
+
+use ark_bn254::{Bn254 as E, Fr as F};
+use ark_groth16::Groth16;
+use ark_r1cs_std::fields::fp::FpVar;
+use common::{interaction, BenchData, Cr, U};
+use criterion::{criterion_group, BenchmarkId, Criterion};
+use rand::thread_rng;
+use std::time::SystemTime;
+use zk_callbacks::{
+    generic::{bulletin::JoinableBulletin, object::Time, user::User},
+    impls::{
+        centralized::{crypto::FakeSigPubkey, ds::sigstore::GRSchnorrObjStore},
+        decentralized::ds::treestore::MerkleObjStore,
+        hash::Poseidon,
+    },
+};
+
+type H = Poseidon<2>;
+type Snark = Groth16<E>;
+type Merkle = MerkleObjStore<F, 10>;
+
+fn bench_sig<const N: usize>(c: &mut Criterion, group: &str, id: BenchmarkId) {
+    let mut rng = thread_rng();
+    let int = interaction::<N>();
+
+    let mut bul = GRSchnorrObjStore::new(&mut rng);
+    let (pk, _) = int.generate_keys::<H, Snark, Cr, GRSchnorrObjStore>(
+        &mut rng,
+        Some(bul.get_pubkey()),
+        None,
+        false,
+    );
+    let u: U = User::create(BenchData::default(), &mut rng);
+    <GRSchnorrObjStore as JoinableBulletin<F, BenchData>>::join_bul(&mut bul, u.commit::<H>(), ())
+        .unwrap();
+
+    c.benchmark_group(group).sample_size(10).bench_function(id, |b| {
+        b.iter(|| {
+            u.clone()
+                .exec_method_create_cb::<H, (), (), (), (), F, FpVar<F>, Cr, Snark, GRSchnorrObjStore, N>(
+                    &mut rng,
+                    int.clone(),
+                    core::array::from_fn(|_| FakeSigPubkey::pk()),
+                    Time::from(0),
+                    &bul,
+                    true,
+                    &pk,
+                    (),
+                    (),
+                )
+                .unwrap()
+        })
+    });
+}
+
+fn bench_merkle(c: &mut Criterion) {
+    let mut rng = thread_rng();
+    let int = interaction::<1>();
+
+    let mut bul = Merkle::new();
+    let (pk, _) = int.generate_keys::<H, Snark, Cr, Merkle>(&mut rng, None, None, false);
+    let u: U = User::create(BenchData::default(), &mut rng);
+    <Merkle as JoinableBulletin<F, BenchData>>::join_bul(&mut bul, u.commit::<H>(), ()).unwrap();
+
+    c.benchmark_group("bulletin")
+        .sample_size(10)
+        .bench_function("merkle", |b| {
+            b.iter(|| {
+                u.clone()
+                    .exec_method_create_cb::<H, (), (), (), (), F, FpVar<F>, Cr, Snark, Merkle, 1>(
+                        &mut rng,
+                        int.clone(),
+                        [FakeSigPubkey::pk()],
+                        Time::from(0),
+                        &bul,
+                        false,
+                        &pk,
+                        (),
+                        (),
+                    )
+                    .unwrap()
+            })
+        });
+}
+
+fn interact(c: &mut Criterion) {
+    bench_sig::<0>(c, "interact", BenchmarkId::new("callbacks", 0));
+    bench_sig::<1>(c, "interact", BenchmarkId::new("callbacks", 1));
+    bench_sig::<4>(c, "interact", BenchmarkId::new("callbacks", 4));
+}
+
+fn bulletin(c: &mut Criterion) {
+    bench_sig::<1>(c, "bulletin", BenchmarkId::from_parameter("signature"));
+    bench_merkle(c);
+}
+
+criterion_group!(benches, interact, bulletin);
+
+fn main() {
+    let start = SystemTime::now();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    common::json_report(start);
+}
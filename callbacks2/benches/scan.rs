@@ -0,0 +1,138 @@
+// Proving scans of 1, 4, and 8 callback tickets against a signature bulletin.
+
+mod common;
+
+use ark_bn254::{Bn254 as E, Fr as F};
+use ark_groth16::Groth16;
+use ark_r1cs_std::fields::fp::FpVar;
+use common::{callback, interaction, BenchData, Cr, U};
+use criterion::{criterion_group, BenchmarkId, Criterion};
+use rand::thread_rng;
+use std::time::SystemTime;
+use zk_callbacks::{
+    generic::{
+        bulletin::{JoinableBulletin, UserBul},
+        object::Time,
+        scan::{get_scan_interaction, PubScanArgs},
+        user::User,
+    },
+    impls::{
+        centralized::{
+            crypto::FakeSigPubkey,
+            ds::sigstore::{GRSchnorrCallbackStore, GRSchnorrObjStore, GRSchnorrStore},
+        },
+        hash::Poseidon,
+    },
+};
+
+type H = Poseidon<2>;
+type Snark = Groth16<E>;
+type CBul = GRSchnorrCallbackStore<F>;
+
+// A store, and a user in it holding 8 callback tickets.
+fn setup() -> (GRSchnorrStore<F>, U) {
+    let mut rng = thread_rng();
+    let int = interaction::<4>();
+
+    let mut store = GRSchnorrStore::new(&mut rng);
+    let (pk, vk) = int.generate_keys::<H, Snark, Cr, GRSchnorrObjStore>(
+        &mut rng,
+        Some(store.obj_bul.get_pubkey()),
+        None,
+        false,
+    );
+    let mut u: U = User::create(BenchData::default(), &mut rng);
+    <GRSchnorrObjStore as JoinableBulletin<F, BenchData>>::join_bul(
+        &mut store.obj_bul,
+        u.commit::<H>(),
+        (),
+    )
+    .unwrap();
+
+    for _ in 0..2 {
+        let exec = u
+            .exec_method_create_cb::<H, (), (), (), (), F, FpVar<F>, Cr, Snark, GRSchnorrObjStore, 4>(
+                &mut rng,
+                int.clone(),
+                core::array::from_fn(|_| FakeSigPubkey::pk()),
+                Time::from(0),
+                &store.obj_bul,
+                true,
+                &pk,
+                (),
+                (),
+            )
+            .unwrap();
+        <GRSchnorrObjStore as UserBul<F, BenchData>>::verify_interact_and_append::<(), Snark, 4>(
+            &mut store.obj_bul,
+            exec.new_object,
+            exec.old_nullifier,
+            (),
+            exec.cb_com_list,
+            exec.proof,
+            None,
+            &vk,
+        )
+        .unwrap();
+    }
+    store.callback_bul.update_epoch(&mut rng);
+
+    (store, u)
+}
+
+fn bench_scan<const N: usize>(c: &mut Criterion, store: &GRSchnorrStore<F>, u: &U) {
+    let mut rng = thread_rng();
+    let cb_methods = vec![callback()];
+
+    let ex: PubScanArgs<F, BenchData, F, FpVar<F>, Cr, CBul, N> = PubScanArgs {
+        memb_pub: core::array::from_fn(|_| store.callback_bul.get_pubkey()),
+        is_memb_data_const: true,
+        nmemb_pub: core::array::from_fn(|_| store.callback_bul.nmemb_bul.get_pubkey()),
+        is_nmemb_data_const: true,
+        cur_time: store.callback_bul.get_epoch(),
+        bulletin: store.callback_bul.clone(),
+        cb_methods: cb_methods.clone(),
+    };
+    let (pks, _) = get_scan_interaction::<_, _, _, _, _, _, H, N>()
+        .generate_keys::<H, Snark, Cr, GRSchnorrObjStore>(
+            &mut rng,
+            Some(store.obj_bul.get_pubkey()),
+            Some(ex),
+            true,
+        );
+
+    c.benchmark_group("scan")
+        .sample_size(10)
+        .bench_function(BenchmarkId::new("tickets", N), |b| {
+            b.iter(|| {
+                u.clone()
+                    .scan_callbacks::<H, F, FpVar<F>, Cr, CBul, Snark, GRSchnorrObjStore, N>(
+                        &mut rng,
+                        &store.obj_bul,
+                        true,
+                        &pks,
+                        &store.callback_bul,
+                        (true, true),
+                        store.callback_bul.get_epoch(),
+                        cb_methods.clone(),
+                    )
+                    .unwrap()
+            })
+        });
+}
+
+fn scan(c: &mut Criterion) {
+    let (store, u) = setup();
+    bench_scan::<1>(c, &store, &u);
+    bench_scan::<4>(c, &store, &u);
+    bench_scan::<8>(c, &store, &u);
+}
+
+criterion_group!(benches, scan);
+
+fn main() {
+    let start = SystemTime::now();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    common::json_report(start);
+}
@@ -0,0 +1,278 @@
+use ark_bn254::{Bn254 as E, Fr as F};
+use ark_groth16::Groth16;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::Boolean};
+use ark_relations::r1cs::Result as ArkResult;
+use ark_snark::SNARK;
+use rand::thread_rng;
+use std::time::SystemTime;
+use zk_callbacks::{
+    crypto::enc::AECipherSigZK,
+    generic::{
+        bulletin::{CallbackBul, JoinableBulletin, UserBul},
+        callbacks::CallbackCom,
+        interaction::{Callback, Interaction},
+        object::{Id, Time},
+        scan::{get_scan_interaction, PubScanArgs},
+        service::ServiceProvider,
+        user::{ExecutedMethod, User, UserData, UserVar},
+    },
+    impls::{
+        centralized::crypto::{FakeSigPrivkey, FakeSigPubkey, NoSigOTP},
+        decentralized::ds::treestore::{IndexedCallbackStore, MerkleObjStore},
+        hash::Poseidon,
+    },
+    scannable_zk_object,
+};
+
+// In the decentralized setting, there is no trusted server holding a signing key. Both bulletins
+// are Merkle trees (which could be maintained by a smart contract, or any consensus protocol), and
+// users prove membership with Merkle paths against a root.
+//
+// Since the root changes with every append, the membership data can *not* be baked into the
+// proving keys as a constant: the root is a public input to every proof instead.
+
+#[scannable_zk_object(F)]
+#[derive(Default)]
+pub struct ForumData {
+    pub karma: F,
+    pub posts: F,
+}
+
+// The depth of both Merkle trees (so each holds up to 2^D leaves).
+const D: usize = 10;
+
+// How many callbacks are scanned in a single scan.
+const NUMSCANS: usize = 1;
+
+type U = User<F, ForumData>;
+type UV = UserVar<F, ForumData>;
+
+// Tickets are plain keys and arguments are one-time padded: a service only needs to post to the
+// callback bulletin, so no signatures are needed.
+type Cr = NoSigOTP<F>;
+
+// The object bulletin, and the callback bulletin (an indexed Merkle tree, supporting
+// nonmembership proofs without any trusted signer).
+type OBul = MerkleObjStore<F, D>;
+type CBul = IndexedCallbackStore<F, F, D>;
+
+type CB = Callback<F, ForumData, F, FpVar<F>>;
+type PostInt = Interaction<F, ForumData, (), (), (), (), F, FpVar<F>, 1>;
+type PubScan = PubScanArgs<F, ForumData, F, FpVar<F>, Cr, CBul, NUMSCANS>;
+
+// A forum which stores the callback tickets handed to it with each post.
+#[derive(Default)]
+struct Forum {
+    posts: Vec<(
+        String,
+        (CallbackCom<F, F, Cr>, <Cr as AECipherSigZK<F, F>>::Rand),
+    )>,
+}
+
+impl ServiceProvider<F, F, FpVar<F>, Cr> for Forum {
+    type Error = ();
+
+    type InteractionData = String;
+
+    fn has_never_received_tik(&self, tik: FakeSigPubkey<F>) -> bool {
+        self.posts.iter().all(|(_, (cb, _))| cb.cb_entry.tik != tik)
+    }
+
+    fn store_interaction<T: UserData<F>, Snark: SNARK<F>, const NUMCBS: usize>(
+        &mut self,
+        interaction: ExecutedMethod<F, Snark, F, Cr, NUMCBS>,
+        data: String,
+    ) -> Result<(), ()> {
+        for tik in interaction.cb_tik_list {
+            self.posts.push((data.clone(), tik));
+        }
+        Ok(())
+    }
+}
+
+// Making a post increments the post count.
+fn post(tu: &U, _pub_args: (), _priv_args: ()) -> U {
+    let mut out = tu.clone();
+    out.data.posts += F::from(1);
+    out
+}
+
+fn post_pred(tu_old: &UV, tu_new: &UV, _pub_args: (), _priv_args: ()) -> ArkResult<Boolean<F>> {
+    let posts = tu_new
+        .data
+        .posts
+        .is_eq(&(tu_old.data.posts.clone() + FpVar::Constant(F::from(1))))?;
+    let karma = tu_new.data.karma.is_eq(&tu_old.data.karma)?;
+    Ok(posts & karma)
+}
+
+// The forum may add to (or subtract from) the karma of the author of a post.
+fn add_karma(tu: &U, args: F) -> U {
+    let mut out = tu.clone();
+    out.data.karma += args;
+    out
+}
+
+fn add_karma_pred(tu_old: &UV, args: FpVar<F>) -> ArkResult<UV> {
+    let mut tu_new = tu_old.clone();
+    tu_new.data.karma = tu_new.data.karma + args;
+    Ok(tu_new)
+}
+
+fn main() {
+    // SETUP
+    let mut rng = thread_rng();
+
+    let cb: CB = Callback {
+        method_id: Id::from(0),
+        expirable: false,
+        expiration: Time::from(0),
+        method: add_karma,
+        predicate: add_karma_pred,
+    };
+    let cb_methods = vec![cb.clone()];
+
+    let interaction: PostInt = Interaction {
+        meth: (post, post_pred),
+        callbacks: [cb.clone()],
+    };
+
+    let mut obul = OBul::new();
+    let mut cbul = CBul::new();
+    let mut forum = Forum::default();
+
+    println!("[SETUP] PROOF KEY GENERATION...");
+    let start = SystemTime::now();
+
+    // No membership data is passed in, so the Merkle root is a public input of the proof.
+    let (pk, vk) =
+        interaction.generate_keys::<Poseidon<2>, Groth16<E>, Cr, OBul>(&mut rng, None, None, false);
+
+    // The scan proves membership and nonmembership of tickets against the root of the callback
+    // tree, which is also a public input. The example arguments only fix the shape of the circuit.
+    let ex: PubScan = PubScanArgs {
+        memb_pub: [cbul.get_root(); NUMSCANS],
+        is_memb_data_const: false,
+        nmemb_pub: [cbul.get_root(); NUMSCANS],
+        is_nmemb_data_const: false,
+        cur_time: Time::from(0),
+        bulletin: cbul.clone(),
+        cb_methods: cb_methods.clone(),
+    };
+    let (pks, vks) = get_scan_interaction::<_, _, _, _, _, _, Poseidon<2>, NUMSCANS>()
+        .generate_keys::<Poseidon<2>, Groth16<E>, Cr, OBul>(&mut rng, None, Some(ex), true);
+
+    println!(
+        "\t (time) Generated proof keys: {:?}",
+        start.elapsed().unwrap()
+    );
+
+    // JOIN
+    println!("[USER] Creating and joining...");
+    let mut u = User::create(ForumData::default(), &mut rng);
+    <OBul as JoinableBulletin<F, ForumData>>::join_bul(&mut obul, u.commit::<Poseidon<2>>(), ())
+        .unwrap();
+    println!("[USER] Joined! Root: {}\n", obul.get_root());
+
+    // INTERACT
+    println!("[USER] Posting (proving)...");
+    let start = SystemTime::now();
+
+    // The user proves membership against the current root.
+    let root = obul.get_root();
+    let exec = u
+        .exec_method_create_cb::<Poseidon<2>, (), (), (), (), F, FpVar<F>, Cr, Groth16<E>, OBul, 1>(
+            &mut rng,
+            interaction.clone(),
+            [FakeSigPubkey::pk()],
+            Time::from(0),
+            &obul,
+            false,
+            &pk,
+            (),
+            (),
+        )
+        .unwrap();
+
+    println!("\t (time) Proving time: {:?}", start.elapsed().unwrap());
+
+    // The bulletin only accepts proofs against a root it has had.
+    let out = <OBul as UserBul<F, ForumData>>::verify_interact_and_append::<(), Groth16<E>, 1>(
+        &mut obul,
+        exec.new_object,
+        exec.old_nullifier,
+        (),
+        exec.cb_com_list,
+        exec.proof.clone(),
+        Some(root),
+        &vk,
+    );
+    println!("[BULLETIN] Verified and appended: {:?}", out);
+
+    let res = forum
+        .approve_interaction_and_store::<ForumData, Groth16<E>, (), OBul, Poseidon<2>, 1>(
+            exec,
+            FakeSigPrivkey::sk(),
+            (),
+            &obul,
+            cb_methods.clone(),
+            Time::from(0),
+            root,
+            false,
+            &vk,
+            "hello, world".to_string(),
+        );
+    println!("[FORUM] Approved and stored post: {:?}\n", res);
+
+    // CALL
+    println!("[FORUM] Rewarding the post...");
+    let (_, (ticket, _)) = forum.posts[0].clone();
+    let called = forum
+        .call(ticket, F::from(10), FakeSigPrivkey::sk())
+        .unwrap();
+    <CBul as CallbackBul<F, F, Cr>>::verify_call_and_append(
+        &mut cbul,
+        called.0,
+        called.1,
+        called.2,
+        Time::from(1),
+    )
+    .unwrap();
+    println!("[BULLETIN] Callback root: {}\n", cbul.get_root());
+
+    // SCAN
+    println!("[USER] Scanning (proving)...");
+    let start = SystemTime::now();
+
+    let root = obul.get_root();
+    let (ps, scan) = u
+        .scan_callbacks::<Poseidon<2>, F, FpVar<F>, Cr, CBul, Groth16<E>, OBul, NUMSCANS>(
+            &mut rng,
+            &obul,
+            false,
+            &pks,
+            &cbul,
+            (false, false),
+            Time::from(1),
+            cb_methods.clone(),
+        )
+        .unwrap();
+
+    println!("\t (time) Proving time: {:?}", start.elapsed().unwrap());
+
+    let out = <OBul as UserBul<F, ForumData>>::verify_interact_and_append::<PubScan, Groth16<E>, 0>(
+        &mut obul,
+        scan.new_object,
+        scan.old_nullifier,
+        ps,
+        scan.cb_com_list,
+        scan.proof,
+        Some(root),
+        &vks,
+    );
+    println!("[BULLETIN] Verified and appended scan: {:?}", out);
+
+    assert_eq!(u.data.karma, F::from(10));
+    assert_eq!(u.data.posts, F::from(1));
+    println!("[USER] Done! User: {:o}", u);
+}
@@ -14,8 +14,8 @@ use rand::thread_rng;
 use std::time::SystemTime;
 use zk_callbacks::{
     generic::{
-        bulletin::{JoinableBulletin, PublicCallbackBul, UserBul},
-        fold::{FoldSer, FoldableUserData, FoldingScan},
+        bulletin::{JoinableBulletin, UserBul},
+        fold::{verify_folded_scan, FoldSer, FoldableUserData, FoldingScan},
         interaction::{Callback, Interaction},
         object::{Id, Time},
         scan::{
@@ -303,7 +303,7 @@ fn main() {
         true,
     >;
 
-    // Setup a scan for a single callback (the first one in the list)
+    // Setup the public arguments for each scan step, shared by every callback
     let ps = PubScanArgs {
         // Create the public scanning arguments
         memb_pub: [store.callback_bul.get_pubkey()], // Public membership data (pubkey)
@@ -322,77 +322,42 @@ fn main() {
     let nova_preprocess_params = PreprocessorParam::new(poseidon_config, f_circ.clone());
     let nova_params = NF::preprocess(&mut rng, &nova_preprocess_params).unwrap();
 
-    let init_state = vec![u.commit::<Poseidon<2>>()];
-
-    let cb = u.get_cb(0);
-    let tik: FakeSigPubkey<F> = cb.get_ticket();
-
-    let x =
-        <CSt as PublicCallbackBul<F, F, NoSigOTP<F>>>::verify_in(&store.callback_bul, tik.clone());
-
-    let prs1: PrivScanArgs<F, CBArg, NoSigOTP<F>, CSt, 1> = PrivScanArgs {
-        priv_n_tickets: [cb],
-        post_times: [x.map_or(F::from(0), |(_, p2)| p2)],
-        enc_args: [x.map_or(F::from(0), |(p1, _)| p1)],
-        memb_priv: [store
-            .callback_bul
-            .get_memb_witness(&tik)
-            .unwrap_or_default()],
-        nmemb_priv: [store
-            .callback_bul
-            .get_nmemb_witness(&tik)
-            .unwrap_or_default()],
-    };
-
-    let cb = u.get_cb(1);
-    let tik: FakeSigPubkey<F> = cb.get_ticket();
-
-    let x =
-        <CSt as PublicCallbackBul<F, F, NoSigOTP<F>>>::verify_in(&store.callback_bul, tik.clone());
-
-    let prs2: PrivScanArgs<F, CBArg, NoSigOTP<F>, CSt, 1> = PrivScanArgs {
-        priv_n_tickets: [cb],
-        post_times: [x.map_or(F::from(0), |(_, p2)| p2)],
-        enc_args: [x.map_or(F::from(0), |(p1, _)| p1)],
-        memb_priv: [store
-            .callback_bul
-            .get_memb_witness(&tik)
-            .unwrap_or_default()],
-        nmemb_priv: [store
-            .callback_bul
-            .get_nmemb_witness(&tik)
-            .unwrap_or_default()],
-    };
-
-    let mut folding_scheme: NF = NF::init(&nova_params, f_circ, init_state.clone()).unwrap();
+    let old_com = u.commit::<Poseidon<2>>();
 
     let start = SystemTime::now();
 
-    folding_scheme
-        .prove_step(
+    // Fold a scan step for each of the two callbacks
+    let folded = u
+        .fold_scan_callbacks::<Poseidon<2>, CBArg, CBArgVar, NoSigOTP<F>, CSt, Projective, Projective2, NF>(
             &mut rng,
-            [u.to_fold_repr(), prs1.to_fold_repr()].concat(),
-            None,
+            &nova_params,
+            ps.clone(),
+            2,
         )
         .unwrap();
 
-    println!("Fold step time: {:?}", start.elapsed().unwrap());
+    println!("Folding time: {:?}", start.elapsed().unwrap());
+
+    assert_eq!(folded.old_com, old_com);
+    assert_eq!(folded.new_com, u.commit::<Poseidon<2>>());
 
     let start = SystemTime::now();
 
-    folding_scheme
-        .prove_step(
-            &mut rng,
-            [u.to_fold_repr(), prs2.to_fold_repr()].concat(),
-            None,
+    assert!(
+        verify_folded_scan::<_, _, _, _, _, _, Poseidon<2>, _, _, NF>(
+            nova_params.clone(),
+            ps.clone(),
+            folded.proof.clone(),
+            old_com,
+            folded.new_com,
         )
-        .unwrap();
+    );
 
-    println!("Fold step time: {:?}", start.elapsed().unwrap());
+    println!("Verification time: {:?}", start.elapsed().unwrap());
 
     let start = SystemTime::now();
 
-    let _proof = RandomizedIVCProof::new(&folding_scheme, &mut rng).unwrap();
+    let _proof = RandomizedIVCProof::new(&folded.folding, &mut rng).unwrap();
 
     println!("Finalizing proof time: {:?}", start.elapsed().unwrap());
 
@@ -0,0 +1,309 @@
+use crate::{
+    crypto::{
+        enc::{AECipherSigZK, CPACipher},
+        hash::FieldHash,
+    },
+    generic::predicates::is_cmp_bounded,
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::AllocVar, boolean::Boolean, convert::ToConstraintFieldGadget, eq::EqGadget,
+    fields::fp::FpVar,
+};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_snark::{CircuitSpecificSetupSNARK, SNARK};
+use rand::{CryptoRng, RngCore};
+use std::{cmp::Ordering, marker::PhantomData};
+
+/// Inclusive bounds `[lo, hi]` on each field element of some arguments.
+///
+/// Bounds are checked on the difference from `lo`, so they may wrap around the field. For
+/// example, the bounds `[-10, 10]` contain both `-3` and `3`. The width `hi - lo` must be at
+/// most `MODULUS_BIT_SIZE - 2` bits.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct ArgBounds<F: PrimeField>(pub Vec<(F, F)>);
+
+impl<F: PrimeField> ArgBounds<F> {
+    /// Returns if all elements are within their bounds.
+    pub fn contains(&self, elems: &[F]) -> bool {
+        elems.len() == self.0.len()
+            && elems
+                .iter()
+                .zip(self.0.iter())
+                .all(|(e, (lo, hi))| *e - lo <= *hi - lo)
+    }
+
+    /// Enforce in-circuit that all elements are within their bounds.
+    pub fn enforce_contains(&self, elems: &[FpVar<F>]) -> Result<(), SynthesisError> {
+        if elems.len() != self.0.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        for (e, (lo, hi)) in elems.iter().zip(self.0.iter()) {
+            // The width is a constant, which the checked comparison gadgets cannot take, so bound
+            // the difference by the bit length of the width instead.
+            let width = *hi - lo;
+            let bits = (width.into_bigint().num_bits() as usize).max(1);
+            is_cmp_bounded(
+                &(e - *lo),
+                &FpVar::Constant(width),
+                Ordering::Less,
+                true,
+                bits,
+            )?
+            .enforce_equal(&Boolean::TRUE)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error when producing a proof of bounded arguments.
+#[derive(Debug)]
+pub enum AuditError<E> {
+    /// The arguments are not within the bounds.
+    OutOfRange,
+    /// The proof could not be produced.
+    Snark(E),
+}
+
+/// A circuit proving that a ciphertext decrypts to arguments within some bounds, under a key
+/// committed to with `H`.
+///
+/// The public inputs are the ciphertext followed by the key commitment. The bounds are constants
+/// of the circuit, so keys are specific to the bounds.
+#[derive(Clone)]
+pub struct ArgRangeCircuit<
+    F: PrimeField + Absorb,
+    H: FieldHash<F>,
+    Args: Clone,
+    Crypto: AECipherSigZK<F, Args>,
+> {
+    /// The encryption key.
+    pub priv_key: Crypto::EncKey,
+    /// The ciphertext.
+    pub pub_ct: Crypto::Ct,
+    /// The commitment to the encryption key.
+    pub pub_key_com: F,
+    /// The allowed bounds on the arguments.
+    pub bounds: ArgBounds<F>,
+    /// The hash used for the key commitment.
+    pub _phantom_hash: PhantomData<H>,
+}
+
+impl<F: PrimeField + Absorb, H: FieldHash<F>, Args: Clone, Crypto: AuditableCipher<F, Args>>
+    ConstraintSynthesizer<F> for ArgRangeCircuit<F, H, Args, Crypto>
+{
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let key_var = Crypto::EncKeyVar::new_witness(ns!(cs, "key"), || Ok(&self.priv_key))?;
+        let ct_var =
+            <Crypto::EncKey as CPACipher<F>>::CV::new_input(ns!(cs, "ct"), || Ok(&self.pub_ct))?;
+        let key_com_var = FpVar::new_input(ns!(cs, "key_com"), || Ok(self.pub_key_com))?;
+
+        // Enforce the key matches the commitment
+        H::hash_in_zk(&key_var.to_constraint_field()?)?.enforce_equal(&key_com_var)?;
+
+        // Enforce the decrypted arguments are within bounds
+        let args = <Crypto::EncKey as CPACipher<F>>::decrypt_in_zk(key_var, ct_var)?;
+        self.bounds
+            .enforce_contains(&Crypto::args_to_fields_zk(&args)?)
+    }
+}
+
+/// An optional mode of [`AECipherSigZK`], producing a proof that the posted ciphertext decrypts to
+/// arguments within some allowed bounds.
+///
+/// With this, a service calling a callback can show anyone holding the commitment to the
+/// encryption key (for example an auditor, or the user) that the arguments are within policy,
+/// without revealing the arguments themselves. For example, a moderator may prove a karma penalty
+/// is within `[-10, 0]`, so they cannot apply a penalty of `-1000`.
+pub trait AuditableCipher<F: PrimeField + Absorb, Args: Clone>: AECipherSigZK<F, Args> {
+    /// Convert arguments into field elements, to be checked against bounds.
+    fn args_to_fields(args: &Args) -> Vec<F>;
+
+    /// Convert arguments into field elements in-circuit, to be checked against bounds.
+    fn args_to_fields_zk(args: &Self::AV) -> Result<Vec<FpVar<F>>, SynthesisError>;
+
+    /// Commit to an encryption key.
+    fn commit_key<H: FieldHash<F>>(enc_key: &Self::EncKey) -> F {
+        H::hash(&enc_key.to_field_elements().unwrap())
+    }
+
+    /// Generate proving and verifying keys for proofs of arguments within some bounds.
+    fn generate_range_keys<H: FieldHash<F>, Snark: CircuitSpecificSetupSNARK<F>>(
+        rng: &mut (impl CryptoRng + RngCore),
+        bounds: ArgBounds<F>,
+    ) -> (Snark::ProvingKey, Snark::VerifyingKey) {
+        let circ = ArgRangeCircuit::<F, H, Args, Self> {
+            priv_key: Self::EncKey::default(),
+            pub_ct: Self::Ct::default(),
+            pub_key_com: F::default(),
+            bounds,
+            _phantom_hash: PhantomData,
+        };
+        Snark::circuit_specific_setup(circ, rng).unwrap()
+    }
+
+    /// Encrypt and sign arguments as in [`AECipherSigZK::encrypt_and_sign`], and additionally
+    /// prove the ciphertext decrypts to arguments within the bounds.
+    #[allow(clippy::type_complexity)]
+    fn encrypt_sign_and_prove<H: FieldHash<F>, Snark: SNARK<F>>(
+        message: Args,
+        enc_key: Self::EncKey,
+        sig_sk: Self::SigSK,
+        bounds: ArgBounds<F>,
+        pk: &Snark::ProvingKey,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<(Self::Ct, Self::Sig, Snark::Proof), AuditError<Snark::Error>> {
+        if !bounds.contains(&Self::args_to_fields(&message)) {
+            return Err(AuditError::OutOfRange);
+        }
+        let key_com = Self::commit_key::<H>(&enc_key);
+        let (ct, sig) = Self::encrypt_and_sign(message, enc_key.clone(), sig_sk);
+        let circ = ArgRangeCircuit::<F, H, Args, Self> {
+            priv_key: enc_key,
+            pub_ct: ct.clone(),
+            pub_key_com: key_com,
+            bounds,
+            _phantom_hash: PhantomData,
+        };
+        let proof = Snark::prove(pk, circ, rng).map_err(AuditError::Snark)?;
+        Ok((ct, sig, proof))
+    }
+
+    /// Verify a proof that a ciphertext decrypts to arguments within the bounds of the verifying
+    /// key, under the key with commitment `key_com`.
+    fn verify_range<Snark: SNARK<F>>(
+        ct: &Self::Ct,
+        key_com: F,
+        vk: &Snark::VerifyingKey,
+        proof: &Snark::Proof,
+    ) -> bool
+    where
+        Self::Ct: ToConstraintField<F>,
+    {
+        let mut pub_inputs = match ct.to_field_elements() {
+            Some(v) => v,
+            None => return false,
+        };
+        pub_inputs.push(key_com);
+        Snark::verify(vk, &pub_inputs, proof).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        crypto::rr::RRSigner,
+        impls::{
+            decentralized::crypto::{
+                Ciphertext, SchnorrPrivkey, SchnorrPubkey, SchnorrSig, StreamKey, StreamSchnorr,
+            },
+            hash::Poseidon,
+        },
+    };
+    use ark_bn254::{Bn254, Fr};
+    use ark_ec::PrimeGroup;
+    use ark_groth16::Groth16;
+    use ark_grumpkin::Projective as Grumpkin;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::thread_rng;
+
+    type H = Poseidon<2>;
+    type Cr = StreamSchnorr<Fr, Grumpkin, 2>;
+
+    // Bounds on a penalty within [-10, 0], and a count within [0, 5]
+    fn bounds() -> ArgBounds<Fr> {
+        ArgBounds(vec![
+            (-Fr::from(10), Fr::from(0)),
+            (Fr::from(0), Fr::from(5)),
+        ])
+    }
+
+    fn signing_key(rng: &mut (impl CryptoRng + RngCore)) -> SchnorrPrivkey<Grumpkin> {
+        <SchnorrPrivkey<Grumpkin> as RRSigner<
+            SchnorrSig<Grumpkin>,
+            Ciphertext<Fr, 3>,
+            <Grumpkin as PrimeGroup>::ScalarField,
+            SchnorrPubkey<Grumpkin>,
+        >>::gen(rng)
+    }
+
+    fn satisfied(elems: &[Fr]) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let vars: Vec<_> = elems
+            .iter()
+            .map(|e| FpVar::new_witness(cs.clone(), || Ok(*e)).unwrap())
+            .collect();
+        bounds().enforce_contains(&vars).is_ok() && cs.is_satisfied().unwrap()
+    }
+
+    // Tests bounds natively and in-circuit, including bounds which wrap around the field
+    #[test]
+    fn arg_bounds_contains() {
+        for (elems, inside) in [
+            ([-Fr::from(3), Fr::from(5)], true),
+            ([-Fr::from(10), Fr::from(0)], true),
+            ([Fr::from(0), Fr::from(2)], true),
+            ([Fr::from(1), Fr::from(2)], false),
+            ([-Fr::from(11), Fr::from(2)], false),
+            ([-Fr::from(3), Fr::from(6)], false),
+            ([-Fr::from(3), -Fr::from(1)], false),
+        ] {
+            assert_eq!(bounds().contains(&elems), inside);
+            assert_eq!(satisfied(&elems), inside);
+        }
+        assert!(!bounds().contains(&[Fr::from(0)]));
+        assert!(!satisfied(&[Fr::from(0)]));
+    }
+
+    // Tests that a range proof verifies only under the committed key and the posted ciphertext
+    #[test]
+    fn range_proof_verifies() {
+        let mut rng = thread_rng();
+        let (pk, vk) = Cr::generate_range_keys::<H, Groth16<Bn254>>(&mut rng, bounds());
+        let key = StreamKey::new(Fr::from(1234));
+        let key_com = Cr::commit_key::<H>(&key);
+
+        let (ct, _, proof) = Cr::encrypt_sign_and_prove::<H, Groth16<Bn254>>(
+            [-Fr::from(4), Fr::from(1)],
+            key.clone(),
+            signing_key(&mut rng),
+            bounds(),
+            &pk,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(Cr::verify_range::<Groth16<Bn254>>(
+            &ct, key_com, &vk, &proof
+        ));
+        assert!(!Cr::verify_range::<Groth16<Bn254>>(
+            &ct,
+            key_com + Fr::from(1),
+            &vk,
+            &proof
+        ));
+
+        let mut other = ct.clone();
+        other.0[0] += Fr::from(1);
+        assert!(!Cr::verify_range::<Groth16<Bn254>>(
+            &other, key_com, &vk, &proof
+        ));
+
+        // Arguments outside the bounds are refused before proving
+        assert!(matches!(
+            Cr::encrypt_sign_and_prove::<H, Groth16<Bn254>>(
+                [-Fr::from(1000), Fr::from(1)],
+                key,
+                signing_key(&mut rng),
+                bounds(),
+                &pk,
+                &mut rng,
+            ),
+            Err(AuditError::OutOfRange)
+        ));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::crypto::rr::{RRSigner, RRVerifier};
 use ark_ff::{PrimeField, ToConstraintField};
-use ark_r1cs_std::{convert::ToConstraintFieldGadget, prelude::AllocVar};
+use ark_r1cs_std::{convert::ToConstraintFieldGadget, fields::fp::FpVar, prelude::AllocVar};
 use ark_relations::r1cs::SynthesisError;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use rand::{CryptoRng, RngCore};
@@ -189,3 +189,244 @@ pub trait AECipherSigZK<F: PrimeField, Args: Clone>: Clone + std::fmt::Debug {
         (enc, sig)
     }
 }
+
+/// Callback arguments made of several field elements.
+///
+/// Ciphers such as [`ArgsOTP`](`crate::impls::centralized::crypto::ArgsOTP`) encrypt arguments
+/// element by element, so any type implementing this trait may be used as the arguments to a
+/// callback. This is implemented for single field elements and arrays of field elements, and the
+/// [`callback_args`](`crate::callback_args`) macro implements it for structs of named field
+/// elements.
+pub trait FieldArgs<F: PrimeField>: Clone + Default + std::fmt::Debug {
+    /// The arguments in-circuit.
+    type Var: AllocVar<Self, F> + Clone;
+
+    /// The arguments as field elements.
+    fn to_fields(&self) -> Vec<F>;
+
+    /// Reconstruct the arguments from field elements, as output by
+    /// [`to_fields`](`FieldArgs::to_fields`).
+    fn from_fields(fields: &[F]) -> Self;
+
+    /// The in-circuit arguments as field elements.
+    fn to_fields_zk(var: &Self::Var) -> Result<Vec<FpVar<F>>, SynthesisError>;
+
+    /// Reconstruct the in-circuit arguments from field elements.
+    fn from_fields_zk(vars: &[FpVar<F>]) -> Result<Self::Var, SynthesisError>;
+}
+
+impl<F: PrimeField> FieldArgs<F> for F {
+    type Var = FpVar<F>;
+
+    fn to_fields(&self) -> Vec<F> {
+        vec![*self]
+    }
+
+    fn from_fields(fields: &[F]) -> Self {
+        fields[0]
+    }
+
+    fn to_fields_zk(var: &Self::Var) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(vec![var.clone()])
+    }
+
+    fn from_fields_zk(vars: &[FpVar<F>]) -> Result<Self::Var, SynthesisError> {
+        Ok(vars[0].clone())
+    }
+}
+
+impl<F: PrimeField, const N: usize> FieldArgs<F> for [F; N]
+where
+    [F; N]: Default,
+{
+    type Var = [FpVar<F>; N];
+
+    fn to_fields(&self) -> Vec<F> {
+        self.to_vec()
+    }
+
+    fn from_fields(fields: &[F]) -> Self {
+        fields[..N].try_into().unwrap()
+    }
+
+    fn to_fields_zk(var: &Self::Var) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(var.to_vec())
+    }
+
+    fn from_fields_zk(vars: &[FpVar<F>]) -> Result<Self::Var, SynthesisError> {
+        vars[..N]
+            .to_vec()
+            .try_into()
+            .map_err(|_| SynthesisError::Unsatisfiable)
+    }
+}
+
+/// Define a struct of named field elements to use as callback arguments.
+///
+/// This defines the struct, generic over the field, along with its in-circuit representation,
+/// and implements [`FieldArgs`] for it. The struct also implements `CanonicalSerialize` and
+/// `CanonicalDeserialize`, so the calling crate must depend on `ark-serialize`, along with
+/// `ark-ff`, `ark-r1cs-std`, and `ark-relations`.
+///
+/// # Example
+///
+/// A moderation callback, which carries a penalty, a reason code, and an expiry:
+///
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use zk_callbacks::callback_args;
+/// # use zk_callbacks::crypto::enc::FieldArgs;
+/// callback_args! {
+///     /// Arguments to a moderation callback.
+///     pub struct Moderation, ModerationVar {
+///         penalty,
+///         reason,
+///         expiry,
+///     }
+/// }
+///
+/// let args = Moderation::<Fr> {
+///     penalty: Fr::from(5),
+///     reason: Fr::from(2),
+///     expiry: Fr::from(100),
+/// };
+/// assert_eq!(Moderation::from_fields(&args.to_fields()), args);
+/// ```
+#[macro_export]
+macro_rules! callback_args {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident, $var:ident {
+            $($field:ident),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(
+            Clone,
+            Debug,
+            Default,
+            PartialEq,
+            Eq,
+        )]
+        $vis struct $name<F: ark_ff::PrimeField> {
+            $(
+                #[allow(missing_docs)]
+                pub $field: F,
+            )*
+        }
+
+        impl<F: ark_ff::PrimeField> ark_serialize::CanonicalSerialize for $name<F> {
+            fn serialize_with_mode<W: ark_serialize::Write>(
+                &self,
+                mut writer: W,
+                compress: ark_serialize::Compress,
+            ) -> Result<(), ark_serialize::SerializationError> {
+                $(
+                    ark_serialize::CanonicalSerialize::serialize_with_mode(
+                        &self.$field,
+                        &mut writer,
+                        compress,
+                    )?;
+                )*
+                Ok(())
+            }
+
+            fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+                0 $(+ ark_serialize::CanonicalSerialize::serialized_size(&self.$field, compress))*
+            }
+        }
+
+        impl<F: ark_ff::PrimeField> ark_serialize::Valid for $name<F> {
+            fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+                $(ark_serialize::Valid::check(&self.$field)?;)*
+                Ok(())
+            }
+        }
+
+        impl<F: ark_ff::PrimeField> ark_serialize::CanonicalDeserialize for $name<F> {
+            fn deserialize_with_mode<R: ark_serialize::Read>(
+                mut reader: R,
+                compress: ark_serialize::Compress,
+                validate: ark_serialize::Validate,
+            ) -> Result<Self, ark_serialize::SerializationError> {
+                Ok(Self {
+                    $(
+                        $field: ark_serialize::CanonicalDeserialize::deserialize_with_mode(
+                            &mut reader,
+                            compress,
+                            validate,
+                        )?,
+                    )*
+                })
+            }
+        }
+
+        #[doc = concat!("[`", stringify!($name), "`] in-circuit.")]
+        #[derive(Clone)]
+        $vis struct $var<F: ark_ff::PrimeField> {
+            $(
+                #[allow(missing_docs)]
+                pub $field: ark_r1cs_std::fields::fp::FpVar<F>,
+            )*
+        }
+
+        impl<F: ark_ff::PrimeField> ark_r1cs_std::alloc::AllocVar<$name<F>, F> for $var<F> {
+            fn new_variable<T: core::borrow::Borrow<$name<F>>>(
+                cs: impl Into<ark_relations::r1cs::Namespace<F>>,
+                f: impl FnOnce() -> Result<T, ark_relations::r1cs::SynthesisError>,
+                mode: ark_r1cs_std::alloc::AllocationMode,
+            ) -> Result<Self, ark_relations::r1cs::SynthesisError> {
+                let ns = cs.into();
+                let cs = ns.cs();
+                let res = f();
+                res.and_then(|rec| {
+                    let rec = rec.borrow();
+                    Ok(Self {
+                        $(
+                            $field: ark_r1cs_std::fields::fp::FpVar::new_variable(
+                                ark_relations::ns!(cs, stringify!($field)),
+                                || Ok(rec.$field),
+                                mode,
+                            )?,
+                        )*
+                    })
+                })
+            }
+        }
+
+        impl<F: ark_ff::PrimeField> $crate::crypto::enc::FieldArgs<F> for $name<F> {
+            type Var = $var<F>;
+
+            fn to_fields(&self) -> Vec<F> {
+                vec![$(self.$field),*]
+            }
+
+            fn from_fields(fields: &[F]) -> Self {
+                let mut fields = fields.iter();
+                Self {
+                    $($field: *fields.next().unwrap(),)*
+                }
+            }
+
+            fn to_fields_zk(
+                var: &Self::Var,
+            ) -> Result<Vec<ark_r1cs_std::fields::fp::FpVar<F>>, ark_relations::r1cs::SynthesisError> {
+                Ok(vec![$(var.$field.clone()),*])
+            }
+
+            fn from_fields_zk(
+                vars: &[ark_r1cs_std::fields::fp::FpVar<F>],
+            ) -> Result<Self::Var, ark_relations::r1cs::SynthesisError> {
+                let mut vars = vars.iter();
+                Ok($var {
+                    $(
+                        $field: vars
+                            .next()
+                            .ok_or(ark_relations::r1cs::SynthesisError::Unsatisfiable)?
+                            .clone(),
+                    )*
+                })
+            }
+        }
+    };
+}
@@ -4,11 +4,20 @@
 //! system. For example, zk-callbacks relies on rerandomizable public keys for callbacks, along
 //! with IND-CPA encryption (which can also be done in zero-knowledge).
 
+/// Verifiable encryption of callback arguments within allowed bounds.
+pub mod audit;
+
 /// Traits for IND-CPA encryption and authenticated encryption with signatures.
 pub mod enc;
 
 /// Traits for hashing in zero knowledge.
 pub mod hash;
 
+/// Traits for multi-scalar multiplication backends used when proving.
+pub mod msm;
+
 /// Traits for public key rerandomizable signatures.
 pub mod rr;
+
+/// Traits for verifiable random functions, such as for deriving pseudonyms.
+pub mod vrf;
@@ -0,0 +1,16 @@
+use ark_ec::pairing::Pairing;
+
+/// A backend for the multi-scalar multiplications (MSMs) done by a Groth16 prover.
+///
+/// The MSMs over the proving key dominate the cost of a proof, so this is the point where a GPU
+/// (or otherwise accelerated) implementation may be plugged in. A prover using a backend is
+/// [`MsmGroth16`](`crate::impls::msm::MsmGroth16`), which produces ordinary Groth16 proofs.
+///
+/// `bases` and `scalars` always have the same length.
+pub trait MsmBackend<E: Pairing> {
+    /// Compute `sum_i scalars[i] * bases[i]` in the first source group.
+    fn msm_g1(bases: &[E::G1Affine], scalars: &[E::ScalarField]) -> E::G1;
+
+    /// Compute `sum_i scalars[i] * bases[i]` in the second source group.
+    fn msm_g2(bases: &[E::G2Affine], scalars: &[E::ScalarField]) -> E::G2;
+}
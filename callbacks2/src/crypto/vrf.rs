@@ -0,0 +1,129 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_snark::{CircuitSpecificSetupSNARK, SNARK};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+
+/// A verifiable random function, which can also be evaluated in zero knowledge.
+///
+/// A VRF maps a secret key and an input (such as a context) to a pseudorandom output, which is
+/// unique for the key and input. Along with a public key, anyone can check an output is correct,
+/// without learning the secret key.
+///
+/// Outputs are single field elements, so they can be used directly as pseudonyms or poll tickets
+/// within predicates: a user may prove `claimed = VRF(sk, context)` for their secret key, with
+/// [`VrfZK::is_output_in_zk`].
+///
+/// Standalone evaluations are proven with a SNARK over [`VrfCircuit`], see
+/// [`VrfZK::prove_output`] and [`VrfZK::verify_output`].
+pub trait VrfZK<F: PrimeField>: Clone + Send + Sync {
+    /// The secret key.
+    type Sk: Clone + Default + CanonicalSerialize + CanonicalDeserialize;
+
+    /// The secret key in-circuit.
+    type SkVar: AllocVar<Self::Sk, F> + Clone;
+
+    /// Generate a random secret key.
+    fn keygen(rng: &mut (impl CryptoRng + RngCore)) -> Self::Sk;
+
+    /// Get the public key of a secret key.
+    fn pubkey(sk: &Self::Sk) -> F;
+
+    /// Get the public key of a secret key in-circuit.
+    fn pubkey_in_zk(sk: &Self::SkVar) -> Result<FpVar<F>, SynthesisError>;
+
+    /// Evaluate the VRF on an input.
+    fn evaluate(sk: &Self::Sk, input: &[F]) -> F;
+
+    /// Evaluate the VRF on an input in-circuit.
+    fn evaluate_in_zk(sk: &Self::SkVar, input: &[FpVar<F>]) -> Result<FpVar<F>, SynthesisError>;
+
+    /// Check in-circuit that `claimed` is the output of the VRF on an input.
+    fn is_output_in_zk(
+        sk: &Self::SkVar,
+        input: &[FpVar<F>],
+        claimed: &FpVar<F>,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        Self::evaluate_in_zk(sk, input)?.is_eq(claimed)
+    }
+
+    /// Generate proving and verifying keys for proofs of outputs on inputs of length `input_len`.
+    fn generate_vrf_keys<Snark: CircuitSpecificSetupSNARK<F>>(
+        rng: &mut (impl CryptoRng + RngCore),
+        input_len: usize,
+    ) -> (Snark::ProvingKey, Snark::VerifyingKey) {
+        let circ = VrfCircuit::<F, Self> {
+            priv_sk: Self::Sk::default(),
+            pub_pk: F::default(),
+            pub_input: vec![F::default(); input_len],
+            pub_output: F::default(),
+            _phantom: PhantomData,
+        };
+        Snark::circuit_specific_setup(circ, rng).unwrap()
+    }
+
+    /// Evaluate the VRF on an input, and prove the output is correct under the public key.
+    fn prove_output<Snark: SNARK<F>>(
+        sk: &Self::Sk,
+        input: &[F],
+        pk: &Snark::ProvingKey,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<(F, Snark::Proof), Snark::Error> {
+        let output = Self::evaluate(sk, input);
+        let circ = VrfCircuit::<F, Self> {
+            priv_sk: sk.clone(),
+            pub_pk: Self::pubkey(sk),
+            pub_input: input.to_vec(),
+            pub_output: output,
+            _phantom: PhantomData,
+        };
+        Ok((output, Snark::prove(pk, circ, rng)?))
+    }
+
+    /// Verify a proof that `output` is the output of the VRF on an input, under a public key.
+    fn verify_output<Snark: SNARK<F>>(
+        vrf_pk: F,
+        input: &[F],
+        output: F,
+        vk: &Snark::VerifyingKey,
+        proof: &Snark::Proof,
+    ) -> bool {
+        let pub_inputs = [vec![vrf_pk], input.to_vec(), vec![output]].concat();
+        Snark::verify(vk, &pub_inputs, proof).unwrap_or(false)
+    }
+}
+
+/// A circuit proving that an output is the evaluation of a VRF on an input, for the secret key of
+/// a public key.
+///
+/// The public inputs are the public key, the input, and then the output.
+#[derive(Clone)]
+pub struct VrfCircuit<F: PrimeField, V: VrfZK<F>> {
+    /// The secret key.
+    pub priv_sk: V::Sk,
+    /// The public key.
+    pub pub_pk: F,
+    /// The input to the VRF.
+    pub pub_input: Vec<F>,
+    /// The claimed output.
+    pub pub_output: F,
+    /// The VRF.
+    pub _phantom: PhantomData<V>,
+}
+
+impl<F: PrimeField, V: VrfZK<F>> ConstraintSynthesizer<F> for VrfCircuit<F, V> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let pk = FpVar::new_input(ns!(cs, "pk"), || Ok(self.pub_pk))?;
+        let input = Vec::<FpVar<F>>::new_input(ns!(cs, "input"), || Ok(self.pub_input))?;
+        let output = FpVar::new_input(ns!(cs, "output"), || Ok(self.pub_output))?;
+        let sk = V::SkVar::new_witness(ns!(cs, "sk"), || Ok(self.priv_sk))?;
+
+        V::pubkey_in_zk(&sk)?.enforce_equal(&pk)?;
+        V::is_output_in_zk(&sk, &input, &output)?.enforce_equal(&Boolean::TRUE)
+    }
+}
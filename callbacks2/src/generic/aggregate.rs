@@ -0,0 +1,386 @@
+use ark_crypto_primitives::snark::{BooleanInputVar, SNARKGadget};
+use ark_ec::pairing::Pairing;
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{
+    constraints::{Groth16VerifierGadget, ProofVar, VerifyingKeyVar},
+    Groth16, Proof, VerifyingKey,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, pairing::PairingVar, prelude::Boolean,
+};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_snark::{CircuitSpecificSetupSNARK, SNARK};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+
+/// An error when aggregating proofs.
+#[derive(Debug)]
+pub enum AggregateError<E> {
+    /// The number of proofs or public inputs does not match the aggregation keys.
+    WrongShape,
+    /// The proof at this index in the batch does not verify.
+    InvalidProof(usize),
+    /// The aggregated proof could not be produced.
+    Snark(E),
+}
+
+/// Get the little-endian bits of a field element, as verified by the Groth16 gadget.
+fn field_bits<F: PrimeField>(elem: &F) -> Vec<bool> {
+    let mut bits = elem.into_bigint().to_bits_le();
+    bits.truncate(F::MODULUS_BIT_SIZE as usize);
+    bits
+}
+
+/// Pack the public inputs of a batch of proofs into the public inputs of the aggregated proof.
+///
+/// The bits of all inputs are concatenated, and packed into chunks of `CF::MODULUS_BIT_SIZE - 1`
+/// bits.
+pub fn pack_inputs<F: PrimeField, CF: PrimeField>(inputs: &[Vec<F>]) -> Vec<CF> {
+    let bits: Vec<bool> = inputs.iter().flatten().flat_map(field_bits).collect();
+    bits.chunks(CF::MODULUS_BIT_SIZE as usize - 1)
+        .map(|chunk| CF::from_bigint(CF::BigInt::from_bits_le(chunk)).unwrap())
+        .collect()
+}
+
+/// A circuit verifying a batch of Groth16 proofs under the same verifying key.
+///
+/// The verifying key is a constant of the circuit, and the proofs are witnesses. The public inputs
+/// to the proofs are packed into the public inputs of the circuit (see [`pack_inputs`]). This
+/// circuit is over the base field of the pairing `E`, so proofs of this circuit must be made with a
+/// SNARK over a curve with scalar field `E::BaseField` (for example, BW6-761 for BLS12-377).
+#[derive(Clone)]
+pub struct AggregationCircuit<E: Pairing, P: PairingVar<E>> {
+    /// The verifying key of the proofs.
+    pub vk: VerifyingKey<E>,
+    /// The proofs.
+    pub proofs: Vec<Proof<E>>,
+    /// The public inputs of each proof.
+    pub inputs: Vec<Vec<E::ScalarField>>,
+    /// The pairing gadget.
+    pub _phantom_pairing: PhantomData<P>,
+}
+
+impl<E: Pairing, P: PairingVar<E>> ConstraintSynthesizer<E::BaseField>
+    for AggregationCircuit<E, P>
+{
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<E::BaseField>,
+    ) -> Result<(), SynthesisError> {
+        if self.proofs.len() != self.inputs.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let vk_var = VerifyingKeyVar::<E, P>::new_constant(ns!(cs, "vk"), &self.vk)?;
+
+        let mut all_bits = vec![];
+        for (proof, inputs) in self.proofs.iter().zip(self.inputs.iter()) {
+            let proof_var = ProofVar::<E, P>::new_witness(ns!(cs, "proof"), || Ok(proof))?;
+            let input_bits = inputs
+                .iter()
+                .map(|x| {
+                    Vec::<Boolean<E::BaseField>>::new_witness(ns!(cs, "input"), || {
+                        Ok(field_bits(x))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            all_bits.extend(input_bits.iter().flatten().cloned());
+
+            Groth16VerifierGadget::<E, P>::verify(
+                &vk_var,
+                &BooleanInputVar::new(input_bits),
+                &proof_var,
+            )?
+            .enforce_equal(&Boolean::TRUE)?;
+        }
+
+        // Enforce the packed public inputs match the inputs to the proofs
+        let packed = pack_inputs::<E::ScalarField, E::BaseField>(&self.inputs);
+        let cap = E::BaseField::MODULUS_BIT_SIZE as usize - 1;
+        for (chunk, x) in all_bits.chunks(cap).zip(packed) {
+            let x_var = FpVar::new_input(ns!(cs, "packed_input"), || Ok(x))?;
+            Boolean::le_bits_to_fp(chunk)?.enforce_equal(&x_var)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An aggregated proof of a batch of Groth16 proofs.
+#[derive(Clone)]
+pub struct AggregateProof<E: Pairing, S: SNARK<E::BaseField>> {
+    /// The public inputs of each aggregated proof, in order.
+    pub inputs: Vec<Vec<E::ScalarField>>,
+    /// The proof of the aggregation circuit.
+    pub proof: S::Proof,
+}
+
+/// Generate keys for aggregating `num_proofs` proofs with `num_inputs` public inputs each, all
+/// under the verifying key `vk`.
+pub fn generate_aggregation_keys<
+    E: Pairing,
+    P: PairingVar<E>,
+    S: CircuitSpecificSetupSNARK<E::BaseField>,
+>(
+    rng: &mut (impl CryptoRng + RngCore),
+    vk: &VerifyingKey<E>,
+    num_proofs: usize,
+    num_inputs: usize,
+) -> (S::ProvingKey, S::VerifyingKey) {
+    let circ = AggregationCircuit::<E, P> {
+        vk: vk.clone(),
+        proofs: vec![Proof::default(); num_proofs],
+        inputs: vec![vec![E::ScalarField::default(); num_inputs]; num_proofs],
+        _phantom_pairing: PhantomData,
+    };
+    S::circuit_specific_setup(circ, rng).unwrap()
+}
+
+/// Aggregate a batch of Groth16 proofs under the verifying key `vk` into one proof.
+///
+/// Each element of the batch is the public inputs of a proof along with the proof. For an
+/// [`ExecutedMethod`](`crate::generic::user::ExecutedMethod`), the inputs are obtained with
+/// [`Interaction::prepare_public_inputs`](`crate::generic::interaction::Interaction::prepare_public_inputs`).
+/// The batch must have the number of proofs and inputs the aggregation keys were generated for.
+///
+/// Each proof is checked before aggregating, so an invalid proof is reported by its index.
+///
+/// # Example
+/// ```rust,no_run
+/// # use ark_bls12_377::{constraints::PairingVar, Bls12_377, Fr};
+/// # use ark_bw6_761::BW6_761;
+/// # use ark_groth16::Groth16;
+/// # use ark_r1cs_std::{fields::fp::FpVar, prelude::Boolean};
+/// # use ark_relations::r1cs::SynthesisError;
+/// # use rand::thread_rng;
+/// # use zk_callbacks::generic::aggregate::{aggregate, generate_aggregation_keys, verify_aggregate};
+/// # use zk_callbacks::generic::interaction::Interaction;
+/// # use zk_callbacks::generic::object::Time;
+/// # use zk_callbacks::generic::user::{User, UserVar};
+/// # use zk_callbacks::impls::centralized::crypto::NoSigOTP;
+/// # use zk_callbacks::impls::dummy::DummyStore;
+/// # use zk_callbacks::impls::hash::Poseidon;
+/// # type Groth = Groth16<Bls12_377>;
+/// fn method(old_user: &User<Fr, Fr>, _pub: (), _priv: ()) -> User<Fr, Fr> {
+///     old_user.clone()
+/// }
+///
+/// fn predicate(_old: &UserVar<Fr, Fr>, _new: &UserVar<Fr, Fr>, _pub: (), _priv: ()) -> Result<Boolean<Fr>, SynthesisError> {
+///     Ok(Boolean::TRUE)
+/// }
+///
+/// fn main() {
+///     let mut rng = thread_rng();
+///
+///     // Interactions over BLS12-377, all under the same verifying key
+///     let int = Interaction::<Fr, Fr, (), (), (), (), Fr, FpVar<Fr>, 0> {
+///         meth: (method, predicate),
+///         callbacks: [],
+///     };
+///     let (pk, vk) = int.generate_keys::<Poseidon<2>, Groth, NoSigOTP<Fr>, DummyStore>(&mut rng, Some(()), None, false);
+///
+///     let batch: Vec<_> = (0..4)
+///         .map(|_| {
+///             let mut u = User::create(Fr::from(0), &mut rng);
+///             let exec = u.interact::<Poseidon<2>, (), (), (), (), Fr, FpVar<Fr>, NoSigOTP<Fr>, Groth, DummyStore, 0>(&mut rng, int.clone(), [], Time::from(0), ((), ()), true, &pk, (), (), false).unwrap();
+///             let inputs = int.prepare_public_inputs::<Groth, NoSigOTP<Fr>, DummyStore>(&exec, &(), None).unwrap();
+///             (inputs, exec.proof)
+///         })
+///         .collect();
+///
+///     // Proofs over BLS12-377 are verified in a circuit over BW6-761
+///     let (agg_pk, agg_vk) = generate_aggregation_keys::<Bls12_377, PairingVar, Groth16<BW6_761>>(&mut rng, &vk, batch.len(), batch[0].0.len());
+///
+///     let agg = aggregate::<Bls12_377, PairingVar, Groth16<BW6_761>>(&mut rng, &agg_pk, &vk, batch).unwrap();
+///     assert!(verify_aggregate::<Bls12_377, Groth16<BW6_761>>(&agg_vk, &agg));
+/// }
+/// ```
+///
+/// This example is not run, as proving over BW6-761 takes several minutes.
+#[allow(clippy::type_complexity)]
+pub fn aggregate<E: Pairing, P: PairingVar<E>, S: SNARK<E::BaseField>>(
+    rng: &mut (impl CryptoRng + RngCore),
+    pk: &S::ProvingKey,
+    vk: &VerifyingKey<E>,
+    batch: Vec<(Vec<E::ScalarField>, Proof<E>)>,
+) -> Result<AggregateProof<E, S>, AggregateError<S::Error>> {
+    let (inputs, proofs): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+
+    for (i, (x, proof)) in inputs.iter().zip(proofs.iter()).enumerate() {
+        if x.len() + 1 != vk.gamma_abc_g1.len() {
+            return Err(AggregateError::WrongShape);
+        }
+        if !Groth16::<E>::verify(vk, x, proof).unwrap_or(false) {
+            return Err(AggregateError::InvalidProof(i));
+        }
+    }
+
+    let circ = AggregationCircuit::<E, P> {
+        vk: vk.clone(),
+        proofs,
+        inputs: inputs.clone(),
+        _phantom_pairing: PhantomData,
+    };
+    let proof = S::prove(pk, circ, rng).map_err(AggregateError::Snark)?;
+    Ok(AggregateProof { inputs, proof })
+}
+
+/// Verify an aggregated proof.
+///
+/// If this succeeds, every proof in the batch verified under the verifying key the aggregation
+/// keys were generated for, with the public inputs in `agg.inputs`.
+pub fn verify_aggregate<E: Pairing, S: SNARK<E::BaseField>>(
+    agg_vk: &S::VerifyingKey,
+    agg: &AggregateProof<E, S>,
+) -> bool {
+    let packed = pack_inputs::<E::ScalarField, E::BaseField>(&agg.inputs);
+    S::verify(agg_vk, &packed, &agg.proof).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        generic::{
+            interaction::Interaction,
+            object::Time,
+            user::{User, UserVar},
+        },
+        impls::{centralized::crypto::NoSigOTP, dummy::DummyStore, hash::Poseidon},
+    };
+    use ark_bls12_377::{constraints::PairingVar, Bls12_377, Fr};
+    use ark_bw6_761::BW6_761;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::thread_rng;
+
+    type H = Poseidon<2>;
+    type Groth = Groth16<Bls12_377>;
+    type Int = Interaction<Fr, Fr, (), (), (), (), Fr, FpVar<Fr>, 0>;
+    type Batch = Vec<(Vec<Fr>, Proof<Bls12_377>)>;
+
+    fn method(old_user: &User<Fr, Fr>, _pub: (), _priv: ()) -> User<Fr, Fr> {
+        let mut u = old_user.clone();
+        u.data += Fr::from(1);
+        u
+    }
+
+    fn predicate(
+        old_user: &UserVar<Fr, Fr>,
+        new_user: &UserVar<Fr, Fr>,
+        _pub: (),
+        _priv: (),
+    ) -> Result<Boolean<Fr>, SynthesisError> {
+        new_user
+            .data
+            .is_eq(&(old_user.data.clone() + FpVar::Constant(Fr::from(1))))
+    }
+
+    // Produces the verifying key of an interaction, and a batch of interactions under it
+    fn batch(num: usize) -> (VerifyingKey<Bls12_377>, Batch) {
+        let mut rng = thread_rng();
+        let int = Int {
+            meth: (method, predicate),
+            callbacks: [],
+        };
+        let (pk, vk) = int.generate_keys::<H, Groth, NoSigOTP<Fr>, DummyStore>(
+            &mut rng,
+            Some(()),
+            None,
+            false,
+        );
+
+        let batch = (0..num)
+            .map(|i| {
+                let mut u = User::create(Fr::from(i as u64), &mut rng);
+                let exec = u
+                    .interact::<H, (), (), (), (), Fr, FpVar<Fr>, NoSigOTP<Fr>, Groth, DummyStore, 0>(
+                        &mut rng,
+                        int.clone(),
+                        [],
+                        Time::from(0),
+                        ((), ()),
+                        true,
+                        &pk,
+                        (),
+                        (),
+                        false,
+                    )
+                    .unwrap();
+                let inputs = int
+                    .prepare_public_inputs::<Groth, NoSigOTP<Fr>, DummyStore>(&exec, &(), None)
+                    .unwrap();
+                (inputs, exec.proof)
+            })
+            .collect();
+        (vk, batch)
+    }
+
+    fn circuit_satisfied(vk: &VerifyingKey<Bls12_377>, batch: &Batch) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        AggregationCircuit::<Bls12_377, PairingVar> {
+            vk: vk.clone(),
+            proofs: batch.iter().map(|(_, p)| p.clone()).collect(),
+            inputs: batch.iter().map(|(x, _)| x.clone()).collect(),
+            _phantom_pairing: PhantomData,
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    // Tests the aggregation circuit verifies each proof against its public inputs
+    #[test]
+    fn aggregation_circuit() {
+        let (vk, mut batch) = batch(2);
+        assert!(circuit_satisfied(&vk, &batch));
+
+        batch[1].0[0] += Fr::from(1);
+        assert!(!circuit_satisfied(&vk, &batch));
+
+        batch[1].0[0] -= Fr::from(1);
+        batch.swap(0, 1);
+        batch[0].1 = batch[1].1.clone();
+        assert!(!circuit_satisfied(&vk, &batch));
+    }
+
+    // Tests a batch of interaction proofs is aggregated recursively into one BW6-761 proof, which
+    // verifies only against the inputs of the batch
+    #[test]
+    fn aggregate_prove_verify() {
+        let mut rng = thread_rng();
+        let (vk, batch) = batch(2);
+        let num_inputs = batch[0].0.len();
+        let (agg_pk, agg_vk) = generate_aggregation_keys::<Bls12_377, PairingVar, Groth16<BW6_761>>(
+            &mut rng, &vk, 2, num_inputs,
+        );
+
+        // Invalid proofs and batches of the wrong shape are rejected before proving
+        let mut bad = batch.clone();
+        bad[1].1 = bad[0].1.clone();
+        assert!(matches!(
+            aggregate::<Bls12_377, PairingVar, Groth16<BW6_761>>(&mut rng, &agg_pk, &vk, bad),
+            Err(AggregateError::InvalidProof(1))
+        ));
+        let mut bad = batch.clone();
+        bad[0].0.pop();
+        assert!(matches!(
+            aggregate::<Bls12_377, PairingVar, Groth16<BW6_761>>(&mut rng, &agg_pk, &vk, bad),
+            Err(AggregateError::WrongShape)
+        ));
+
+        let mut agg =
+            aggregate::<Bls12_377, PairingVar, Groth16<BW6_761>>(&mut rng, &agg_pk, &vk, batch)
+                .unwrap();
+        assert!(verify_aggregate::<Bls12_377, Groth16<BW6_761>>(
+            &agg_vk, &agg
+        ));
+
+        agg.inputs.swap(0, 1);
+        assert!(!verify_aggregate::<Bls12_377, Groth16<BW6_761>>(
+            &agg_vk, &agg
+        ));
+    }
+}
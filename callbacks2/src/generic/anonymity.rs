@@ -0,0 +1,145 @@
+use crate::generic::{bulletin::PublicUserBul, object::Time, user::UserData};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use std::collections::BTreeMap;
+
+/// What to do when an interaction would not be `k`-anonymous.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Guardrail {
+    /// Report the problem, but still interact.
+    #[default]
+    Warn,
+    /// Refuse to interact.
+    Refuse,
+}
+
+/// The reason an interaction is not `k`-anonymous.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnonymityError {
+    /// The bulletin does not report the size of its anonymity set.
+    Unknown,
+    /// The anonymity set is smaller than `k`.
+    BelowK {
+        /// The size of the anonymity set.
+        size: u64,
+        /// The minimum size required.
+        k: u64,
+    },
+}
+
+impl std::fmt::Display for AnonymityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnonymityError::Unknown => write!(f, "bulletin does not report its anonymity set"),
+            AnonymityError::BelowK { size, k } => {
+                write!(f, "anonymity set of {} users is below k = {}", size, k)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnonymityError {}
+
+/// A client-side check that interactions hide the user among at least `k` users.
+///
+/// An interaction proves membership of *some* valid object in the bulletin. If only a handful of
+/// users have joined, this reveals little, so a careful client checks
+/// [`PublicUserBul::anonymity_set_size`] before proving.
+///
+/// # Example
+/// ```rust
+/// # use zk_callbacks::generic::anonymity::{AnonymityError, AnonymityGuard, Guardrail};
+/// let guard = AnonymityGuard::new(5, Guardrail::Refuse);
+/// assert_eq!(guard.check_size(Some(8)), Ok(8));
+/// assert_eq!(guard.check_size(Some(3)), Err(AnonymityError::BelowK { size: 3, k: 5 }));
+/// assert!(!guard.permits(&guard.check_size(None)));
+///
+/// let lenient = AnonymityGuard::new(5, Guardrail::Warn);
+/// assert!(lenient.permits(&lenient.check_size(Some(3))));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AnonymityGuard {
+    /// The minimum size of the anonymity set.
+    pub k: u64,
+    /// What to do if the anonymity set is too small, or unknown.
+    pub mode: Guardrail,
+}
+
+impl AnonymityGuard {
+    /// Create a new guard requiring an anonymity set of at least `k`.
+    pub fn new(k: u64, mode: Guardrail) -> Self {
+        Self { k, mode }
+    }
+
+    /// Check a reported anonymity set size against the guard.
+    ///
+    /// Outputs the size if it is at least `k`. An unknown size is an error unless `k <= 1`.
+    pub fn check_size(&self, size: Option<u64>) -> Result<u64, AnonymityError> {
+        match size {
+            Some(size) if size >= self.k => Ok(size),
+            Some(size) => Err(AnonymityError::BelowK { size, k: self.k }),
+            None if self.k <= 1 => Ok(0),
+            None => Err(AnonymityError::Unknown),
+        }
+    }
+
+    /// Check the anonymity set of a bulletin against the guard.
+    pub fn check<F: PrimeField + Absorb, U: UserData<F>, Bul: PublicUserBul<F, U>>(
+        &self,
+        bul: &Bul,
+    ) -> Result<u64, AnonymityError> {
+        self.check_size(bul.anonymity_set_size())
+    }
+
+    /// Output true if an interaction should proceed given the result of a check.
+    pub fn permits(&self, result: &Result<u64, AnonymityError>) -> bool {
+        result.is_ok() || self.mode == Guardrail::Warn
+    }
+}
+
+/// A record of the anonymity set size of a bulletin in each epoch.
+///
+/// Bulletins (or services) record the size whenever it changes, and clients may then query the
+/// effective anonymity set for any epoch. The size for an epoch is the latest size recorded at or
+/// before it.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use zk_callbacks::generic::anonymity::AnonymityLog;
+/// let mut log = AnonymityLog::<Fr>::new();
+/// log.record(Fr::from(1), 3);
+/// log.record(Fr::from(4), 10);
+/// assert_eq!(log.size_at(Fr::from(0)), None);
+/// assert_eq!(log.size_at(Fr::from(2)), Some(3));
+/// assert_eq!(log.size_at(Fr::from(4)), Some(10));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AnonymityLog<F: PrimeField> {
+    /// The recorded sizes, keyed by epoch.
+    pub sizes: BTreeMap<Time<F>, u64>,
+}
+
+impl<F: PrimeField> AnonymityLog<F> {
+    /// Create a new empty log.
+    pub fn new() -> Self {
+        Self {
+            sizes: BTreeMap::new(),
+        }
+    }
+
+    /// Record the anonymity set size in an epoch, replacing any earlier record for that epoch.
+    pub fn record(&mut self, epoch: Time<F>, size: u64) {
+        self.sizes.insert(epoch, size);
+    }
+
+    /// Get the anonymity set size in an epoch.
+    pub fn size_at(&self, epoch: Time<F>) -> Option<u64> {
+        self.sizes.range(..=epoch).next_back().map(|(_, s)| *s)
+    }
+
+    /// Get the sizes recorded in each epoch, in order.
+    pub fn epochs(&self) -> impl Iterator<Item = (Time<F>, u64)> + '_ {
+        self.sizes.iter().map(|(e, s)| (*e, *s))
+    }
+}
@@ -0,0 +1,446 @@
+use crate::generic::{
+    object::{Id, IdVar, SerVar, Time, TimeVar},
+    user::{UserData, UserVar},
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    select::CondSelectGadget,
+};
+use ark_relations::{
+    ns,
+    r1cs::{Namespace, Result as ArkResult, SynthesisError},
+};
+use std::borrow::Borrow;
+
+/// A record of the last `N` callbacks applied to a user.
+///
+/// Each entry is a pair of a callback method id and the epoch in which it was applied. Callback
+/// methods append to the history with [`CallbackHistory::record`] (and in-circuit with
+/// [`CallbackHistoryVar::record`]), so the history is updated whenever a callback is ingested by a
+/// scan. Once full, the oldest entry is dropped.
+///
+/// Since the history is part of the committed user data, a user may later prove that some callback
+/// was applied to them with [`appeal_predicate`], without revealing which ticket was called.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use zk_callbacks::zk_object;
+/// # use zk_callbacks::generic::user::{User, UserVar};
+/// # use zk_callbacks::generic::appeal::{Appealable, CallbackHistory, CallbackHistoryVar};
+/// # use ark_relations::r1cs::SynthesisError;
+/// # use ark_r1cs_std::fields::fp::FpVar;
+/// # use ark_r1cs_std::fields::FieldVar;
+/// #[zk_object(Fr)]
+/// #[derive(Default)]
+/// struct Data {
+///     pub rep: Fr,
+///     pub history: CallbackHistory<Fr, 4>,
+/// }
+///
+/// impl Appealable<Fr, 4> for Data {
+///     fn history(&self) -> &CallbackHistory<Fr, 4> {
+///         &self.history
+///     }
+///
+///     fn history_var(var: &DataZKVar) -> &CallbackHistoryVar<Fr, 4> {
+///         &var.history
+///     }
+/// }
+///
+/// // A callback with method id 7, which takes the current epoch as its argument.
+/// fn penalize<'a>(old: &'a User<Fr, Data>, epoch: Fr) -> User<Fr, Data> {
+///     let mut new = old.clone();
+///     new.data.rep -= Fr::from(1);
+///     new.data.history.record(Fr::from(7), epoch);
+///     new
+/// }
+///
+/// fn enforce_penalize<'a>(old: &'a UserVar<Fr, Data>, epoch: FpVar<Fr>) -> Result<UserVar<Fr, Data>, SynthesisError> {
+///     let mut new = old.clone();
+///     new.data.rep = new.data.rep - FpVar::one();
+///     new.data.history = new.data.history.record(&FpVar::Constant(Fr::from(7)), &epoch)?;
+///     Ok(new)
+/// }
+///
+/// let mut history = CallbackHistory::<Fr, 4>::default();
+/// history.record(Fr::from(7), Fr::from(3));
+/// assert!(history.contains(Fr::from(7), Fr::from(3)));
+/// assert!(!history.contains(Fr::from(7), Fr::from(4)));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallbackHistory<F: PrimeField, const N: usize> {
+    /// The method ids of the recorded callbacks.
+    pub method_ids: [Id<F>; N],
+    /// The epochs in which the callbacks were applied.
+    pub epochs: [Time<F>; N],
+    /// Whether each entry holds a recorded callback.
+    pub filled: [bool; N],
+}
+
+impl<F: PrimeField, const N: usize> Default for CallbackHistory<F, N> {
+    fn default() -> Self {
+        Self {
+            method_ids: [F::zero(); N],
+            epochs: [F::zero(); N],
+            filled: [false; N],
+        }
+    }
+}
+
+impl<F: PrimeField, const N: usize> CallbackHistory<F, N> {
+    /// Record a callback applied in some epoch, dropping the oldest entry.
+    pub fn record(&mut self, method_id: Id<F>, epoch: Time<F>) {
+        if N == 0 {
+            return;
+        }
+        self.method_ids.rotate_left(1);
+        self.epochs.rotate_left(1);
+        self.filled.rotate_left(1);
+        self.method_ids[N - 1] = method_id;
+        self.epochs[N - 1] = epoch;
+        self.filled[N - 1] = true;
+    }
+
+    /// Check if a callback with this method id was recorded in this epoch.
+    pub fn contains(&self, method_id: Id<F>, epoch: Time<F>) -> bool {
+        (0..N).any(|i| self.filled[i] && self.method_ids[i] == method_id && self.epochs[i] == epoch)
+    }
+
+    /// The number of callbacks recorded, up to `N`.
+    pub fn len(&self) -> usize {
+        self.filled.iter().filter(|b| **b).count()
+    }
+
+    /// Whether no callback has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The in-circuit representation of a [`CallbackHistory`].
+#[derive(Clone)]
+pub struct CallbackHistoryVar<F: PrimeField, const N: usize> {
+    /// The method ids of the recorded callbacks.
+    pub method_ids: [IdVar<F>; N],
+    /// The epochs in which the callbacks were applied.
+    pub epochs: [TimeVar<F>; N],
+    /// Whether each entry holds a recorded callback.
+    pub filled: [Boolean<F>; N],
+}
+
+impl<F: PrimeField, const N: usize> CallbackHistoryVar<F, N> {
+    /// Record a callback applied in some epoch in-circuit, dropping the oldest entry.
+    ///
+    /// This mirrors [`CallbackHistory::record`], and should be called from the in-circuit
+    /// predicate of a callback.
+    pub fn record(&self, method_id: &IdVar<F>, epoch: &TimeVar<F>) -> ArkResult<Self> {
+        let mut out = self.clone();
+        if N == 0 {
+            return Ok(out);
+        }
+        out.method_ids.rotate_left(1);
+        out.epochs.rotate_left(1);
+        out.filled.rotate_left(1);
+        out.method_ids[N - 1] = method_id.clone();
+        out.epochs[N - 1] = epoch.clone();
+        out.filled[N - 1] = Boolean::TRUE;
+        Ok(out)
+    }
+
+    /// Output true if some entry of the history matches the method id and epoch.
+    ///
+    /// The circuit checks every entry, so the proof does not reveal which entry matched.
+    pub fn contains(&self, method_id: &IdVar<F>, epoch: &TimeVar<F>) -> ArkResult<Boolean<F>> {
+        let mut found = Boolean::FALSE;
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..N {
+            let m = self.method_ids[i].is_eq(method_id)?;
+            let e = self.epochs[i].is_eq(epoch)?;
+            found |= m & e & self.filled[i].clone();
+        }
+        Ok(found)
+    }
+}
+
+impl<F: PrimeField, const N: usize> AllocVar<CallbackHistory<F, N>, F>
+    for CallbackHistoryVar<F, N>
+{
+    fn new_variable<T: Borrow<CallbackHistory<F, N>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let method_ids = <[IdVar<F>; N] as AllocVar<[Id<F>; N], F>>::new_variable(
+                ns!(cs, "method_ids"),
+                || Ok(rec.method_ids),
+                mode,
+            )?;
+            let epochs = <[TimeVar<F>; N] as AllocVar<[Time<F>; N], F>>::new_variable(
+                ns!(cs, "epochs"),
+                || Ok(rec.epochs),
+                mode,
+            )?;
+            let filled = <[Boolean<F>; N] as AllocVar<[bool; N], F>>::new_variable(
+                ns!(cs, "filled"),
+                || Ok(rec.filled),
+                mode,
+            )?;
+            Ok(CallbackHistoryVar {
+                method_ids,
+                epochs,
+                filled,
+            })
+        })
+    }
+}
+
+impl<F: PrimeField, const N: usize> CondSelectGadget<F> for CallbackHistoryVar<F, N> {
+    fn conditionally_select(
+        cond: &Boolean<F>,
+        true_value: &Self,
+        false_value: &Self,
+    ) -> ArkResult<Self> {
+        let mut out = false_value.clone();
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..N {
+            out.method_ids[i] = FpVar::conditionally_select(
+                cond,
+                &true_value.method_ids[i],
+                &false_value.method_ids[i],
+            )?;
+            out.epochs[i] =
+                FpVar::conditionally_select(cond, &true_value.epochs[i], &false_value.epochs[i])?;
+            out.filled[i] =
+                Boolean::conditionally_select(cond, &true_value.filled[i], &false_value.filled[i])?;
+        }
+        Ok(out)
+    }
+}
+
+impl<F: PrimeField, const N: usize> EqGadget<F> for CallbackHistoryVar<F, N> {
+    fn is_eq(&self, other: &Self) -> ArkResult<Boolean<F>> {
+        let mut b = Boolean::TRUE;
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..N {
+            b &= self.method_ids[i].is_eq(&other.method_ids[i])?;
+            b &= self.epochs[i].is_eq(&other.epochs[i])?;
+            b &= self.filled[i].is_eq(&other.filled[i])?;
+        }
+        Ok(b)
+    }
+}
+
+impl<F: PrimeField + Absorb, const N: usize> UserData<F> for CallbackHistory<F, N> {
+    type UserDataVar = CallbackHistoryVar<F, N>;
+
+    fn serialize_elements(&self) -> Vec<crate::generic::object::Ser<F>> {
+        let mut buf: Vec<F> = Vec::new();
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..N {
+            buf.push(self.method_ids[i]);
+            buf.push(self.epochs[i]);
+            buf.push(F::from(self.filled[i]));
+        }
+        buf
+    }
+
+    fn serialize_in_zk(user_var: Self::UserDataVar) -> Result<Vec<SerVar<F>>, SynthesisError> {
+        let mut buf: Vec<FpVar<F>> = Vec::new();
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..N {
+            buf.push(user_var.method_ids[i].clone());
+            buf.push(user_var.epochs[i].clone());
+            buf.push(FpVar::from(user_var.filled[i].clone()));
+        }
+        Ok(buf)
+    }
+}
+
+/// User data which keeps a [`CallbackHistory`], and so may appeal callbacks.
+pub trait Appealable<F: PrimeField + Absorb, const N: usize>: UserData<F> {
+    /// Get the callback history of the user.
+    fn history(&self) -> &CallbackHistory<F, N>;
+
+    /// Get the callback history of the user in-circuit.
+    fn history_var(var: &Self::UserDataVar) -> &CallbackHistoryVar<F, N>;
+}
+
+/// The public arguments to an appeal.
+///
+/// An appeal proves that a callback with `method_id` was applied to the user in `epoch`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AppealArgs<F: PrimeField> {
+    /// The method id of the appealed callback.
+    pub method_id: Id<F>,
+    /// The epoch in which the appealed callback was applied.
+    pub epoch: Time<F>,
+}
+
+impl<F: PrimeField> ToConstraintField<F> for AppealArgs<F> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(vec![self.method_id, self.epoch])
+    }
+}
+
+/// The in-circuit representation of [`AppealArgs`].
+#[derive(Clone)]
+pub struct AppealArgsVar<F: PrimeField> {
+    /// The method id of the appealed callback.
+    pub method_id: IdVar<F>,
+    /// The epoch in which the appealed callback was applied.
+    pub epoch: TimeVar<F>,
+}
+
+impl<F: PrimeField> AllocVar<AppealArgs<F>, F> for AppealArgsVar<F> {
+    fn new_variable<T: Borrow<AppealArgs<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let method_id = IdVar::new_variable(ns!(cs, "method_id"), || Ok(rec.method_id), mode)?;
+            let epoch = TimeVar::new_variable(ns!(cs, "epoch"), || Ok(rec.epoch), mode)?;
+            Ok(AppealArgsVar { method_id, epoch })
+        })
+    }
+}
+
+/// A singular predicate proving a callback was applied to the user.
+///
+/// This outputs true if the callback history of the user contains the method id and epoch in the
+/// public [`AppealArgs`]. Use this with
+/// [`User::prove_statement_and_in`](`crate::generic::user::User::prove_statement_and_in`), so the
+/// proof shows a user on the bulletin was penalized by the callback, without revealing the user or
+/// the ticket. Keys are generated with
+/// [`generate_keys_for_statement_in`](`crate::generic::interaction::generate_keys_for_statement_in`).
+///
+/// Note that a user may appeal the same callback more than once; services should deduplicate
+/// appeals if necessary (for example, by appending a rate-limit tag).
+pub fn appeal_predicate<F: PrimeField + Absorb, U: Appealable<F, N>, const N: usize>(
+    user: &UserVar<F, U>,
+    _com: &FpVar<F>,
+    args: AppealArgsVar<F>,
+    _priv: (),
+) -> ArkResult<Boolean<F>> {
+    U::history_var(&user.data).contains(&args.method_id, &args.epoch)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        generic::{interaction::generate_keys_for_statement_in, user::User},
+        impls::{dummy::DummyStore, hash::Poseidon},
+    };
+    use ark_bn254::{Bn254, Fr};
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+    use rand::thread_rng;
+
+    type H = Poseidon<2>;
+    type History = CallbackHistory<Fr, 2>;
+
+    impl Appealable<Fr, 2> for History {
+        fn history(&self) -> &History {
+            self
+        }
+
+        fn history_var(var: &CallbackHistoryVar<Fr, 2>) -> &CallbackHistoryVar<Fr, 2> {
+            var
+        }
+    }
+
+    fn args(method_id: u64, epoch: u64) -> AppealArgs<Fr> {
+        AppealArgs {
+            method_id: Fr::from(method_id),
+            epoch: Fr::from(epoch),
+        }
+    }
+
+    // A user which was penalized by method 7 in epochs 1, 3 and 4, so epoch 1 has been dropped
+    fn penalized() -> User<Fr, History> {
+        let mut history = History::default();
+        for epoch in [1, 3, 4] {
+            history.record(Fr::from(7), Fr::from(epoch));
+        }
+        User::create(history, &mut thread_rng())
+    }
+
+    // Checks whether the appeal circuit is satisfied for a user
+    fn satisfied(user: &User<Fr, History>, args: AppealArgs<Fr>) -> bool {
+        user.constraint_prove_statement_and_in::<H, _, AppealArgsVar<Fr>, (), (), DummyStore>(
+            appeal_predicate::<Fr, History, 2>,
+            ((), ()),
+            true,
+            args,
+            (),
+        )
+        .unwrap()
+        .is_satisfied()
+        .unwrap()
+    }
+
+    // Tests that an appeal for a recorded callback is granted, and the proof only verifies for
+    // that callback
+    #[test]
+    fn appeal_granted() {
+        let mut rng = thread_rng();
+        let user = penalized();
+        let (pk, vk) = generate_keys_for_statement_in::<
+            Fr,
+            H,
+            History,
+            AppealArgs<Fr>,
+            AppealArgsVar<Fr>,
+            (),
+            (),
+            Groth16<Bn254>,
+            DummyStore,
+        >(&mut rng, appeal_predicate::<Fr, History, 2>, Some(()), None);
+
+        let proof = user
+            .prove_statement_and_in::<H, _, AppealArgsVar<Fr>, (), (), Groth16<Bn254>, DummyStore>(
+                &mut rng,
+                appeal_predicate::<Fr, History, 2>,
+                &pk,
+                ((), ()),
+                true,
+                args(7, 3),
+                (),
+            )
+            .unwrap();
+        let inputs = |a: AppealArgs<Fr>| a.to_field_elements().unwrap();
+        assert!(Groth16::<Bn254>::verify(&vk, &inputs(args(7, 3)), &proof).unwrap());
+        assert!(!Groth16::<Bn254>::verify(&vk, &inputs(args(7, 4)), &proof).unwrap());
+    }
+
+    // Tests that an appeal is refused for a callback which was never recorded, or has since been
+    // dropped from the history
+    #[test]
+    fn appeal_refused() {
+        let user = penalized();
+        assert!(satisfied(&user, args(7, 4)));
+        assert!(!satisfied(&user, args(7, 2)));
+        assert!(!satisfied(&user, args(8, 3)));
+        assert!(!satisfied(&user, args(7, 1)));
+        assert!(!satisfied(
+            &User::create(History::default(), &mut thread_rng()),
+            args(0, 0)
+        ));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    crypto::{
+        enc::{AECipherSigZK, CPACipher},
+        rr::RRVerifier,
+    },
+    generic::{
+        bulletin::PublicCallbackBul,
+        callbacks::{CallbackCom, CallbackTicket},
+        object::ZKFields,
+        scan::{PrivScanArgs, PubScanArgs},
+        user::{User, UserData},
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use ark_r1cs_std::alloc::AllocVar;
+use proptest::{collection::vec, prelude::*};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// A strategy for arbitrary field elements.
+///
+/// Elements are reduced from uniformly random bytes, so every element of the field may occur.
+pub fn arb_field<F: PrimeField>() -> impl Strategy<Value = F> {
+    vec(any::<u8>(), (F::MODULUS_BIT_SIZE as usize).div_ceil(8) + 8)
+        .prop_map(|bytes| F::from_le_bytes_mod_order(&bytes))
+}
+
+impl<F: PrimeField> Arbitrary for ZKFields<F> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: ()) -> Self::Strategy {
+        (
+            arb_field(),
+            arb_field(),
+            arb_field(),
+            arb_field(),
+            arb_field(),
+            any::<bool>(),
+        )
+            .prop_map(
+                |(
+                    nul,
+                    com_rand,
+                    callback_hash,
+                    new_in_progress_callback_hash,
+                    old_in_progress_callback_hash,
+                    is_ingest_over,
+                )| ZKFields {
+                    nul,
+                    com_rand,
+                    callback_hash,
+                    new_in_progress_callback_hash,
+                    old_in_progress_callback_hash,
+                    is_ingest_over,
+                },
+            )
+            .boxed()
+    }
+}
+
+/// Arbitrary users.
+///
+/// The stored callbacks are arbitrary bytes, not serialized callback tickets, so these users are
+/// meant for testing encodings rather than scanning.
+impl<F: PrimeField + Absorb, U: UserData<F> + Arbitrary + 'static> Arbitrary for User<F, U> {
+    type Parameters = U::Parameters;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(args: U::Parameters) -> Self::Strategy {
+        (
+            any_with::<U>(args),
+            any::<ZKFields<F>>(),
+            vec(vec(any::<u8>(), 0..64), 0..4),
+            any::<Option<usize>>(),
+            vec(vec(any::<u8>(), 0..64), 0..4),
+        )
+            .prop_map(
+                |(data, zk_fields, callbacks, scan_index, in_progress_cbs)| User {
+                    data,
+                    zk_fields,
+                    callbacks,
+                    scan_index,
+                    in_progress_cbs,
+                },
+            )
+            .boxed()
+    }
+}
+
+/// Arbitrary opened callback tickets.
+///
+/// The parameter is a base public key, which is rerandomized into each ticket. Encryption keys are
+/// freshly generated.
+impl<
+        F: PrimeField + Absorb,
+        Args: Clone + std::fmt::Debug + 'static,
+        Crypto: AECipherSigZK<F, Args> + 'static,
+    > Arbitrary for CallbackCom<F, Args, Crypto>
+{
+    type Parameters = Crypto::SigPK;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(base: Crypto::SigPK) -> Self::Strategy {
+        (
+            any::<[u8; 32]>(),
+            arb_field(),
+            any::<bool>(),
+            arb_field(),
+            arb_field(),
+        )
+            .prop_map(
+                move |(seed, cb_method_id, expirable, expiration, com_rand)| {
+                    let mut rng = StdRng::from_seed(seed);
+                    let (_, tik) = base.rerand(&mut rng);
+                    CallbackCom {
+                        cb_entry: CallbackTicket {
+                            tik,
+                            cb_method_id,
+                            expirable,
+                            expiration,
+                            enc_key: Crypto::EncKey::keygen(&mut rng),
+                        },
+                        com_rand,
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+/// Arbitrary public scan arguments.
+///
+/// The time and whether membership data is constant are arbitrary. The membership data, bulletin,
+/// and callback methods are the defaults.
+impl<
+        F: PrimeField + Absorb,
+        U: UserData<F> + 'static,
+        CBArgs: Clone + 'static,
+        CBArgsVar: AllocVar<CBArgs, F> + 'static,
+        Crypto: AECipherSigZK<F, CBArgs> + 'static,
+        CBul: PublicCallbackBul<F, CBArgs, Crypto> + Default + 'static,
+        const NUMCBS: usize,
+    > Arbitrary for PubScanArgs<F, U, CBArgs, CBArgsVar, Crypto, CBul, NUMCBS>
+where
+    CBul::MembershipPub: Default,
+    CBul::NonMembershipPub: Default,
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: ()) -> Self::Strategy {
+        (arb_field(), any::<bool>(), any::<bool>())
+            .prop_map(
+                |(cur_time, is_memb_data_const, is_nmemb_data_const)| PubScanArgs {
+                    is_memb_data_const,
+                    is_nmemb_data_const,
+                    cur_time,
+                    ..Default::default()
+                },
+            )
+            .boxed()
+    }
+}
+
+/// Arbitrary private scan arguments.
+///
+/// The callback tickets and post times are arbitrary, and the parameter is the base public key of
+/// the tickets (see [`CallbackCom`]). Ciphertexts and (non)membership witnesses are the defaults.
+impl<
+        F: PrimeField + Absorb,
+        CBArgs: Clone + Default + std::fmt::Debug + 'static,
+        Crypto: AECipherSigZK<F, CBArgs> + Default + 'static,
+        CBul: PublicCallbackBul<F, CBArgs, Crypto> + 'static,
+        const NUMCBS: usize,
+    > Arbitrary for PrivScanArgs<F, CBArgs, Crypto, CBul, NUMCBS>
+where
+    CBul::MembershipWitness: Default,
+    CBul::NonMembershipWitness: Default,
+{
+    type Parameters = Crypto::SigPK;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(base: Crypto::SigPK) -> Self::Strategy {
+        (
+            vec(
+                CallbackCom::<F, CBArgs, Crypto>::arbitrary_with(base),
+                NUMCBS,
+            ),
+            vec(arb_field(), NUMCBS),
+        )
+            .prop_map(|(tickets, times)| PrivScanArgs {
+                priv_n_tickets: tickets
+                    .try_into()
+                    .unwrap_or_else(|_| panic!("Wrong number of tickets.")),
+                post_times: times
+                    .try_into()
+                    .unwrap_or_else(|_| panic!("Wrong number of post times.")),
+                ..Default::default()
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        generic::{
+            callbacks::CallbackCom,
+            object::ZKFields,
+            scan::{PrivScanArgs, PubScanArgs},
+            user::User,
+        },
+        impls::{centralized::crypto::NoSigOTP, dummy::DummyStore},
+    };
+    use ark_bn254::Fr;
+    use ark_ff::ToConstraintField;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress};
+    use proptest::prelude::*;
+
+    type CB = CallbackCom<Fr, Fr, NoSigOTP<Fr>>;
+    type PubScan = PubScanArgs<Fr, bool, Fr, FpVar<Fr>, NoSigOTP<Fr>, DummyStore, 2>;
+    type PrivScan = PrivScanArgs<Fr, Fr, NoSigOTP<Fr>, DummyStore, 2>;
+
+    fn round_trip<T: CanonicalSerialize + CanonicalDeserialize + PartialEq + std::fmt::Debug>(
+        value: &T,
+    ) {
+        for compress in [Compress::Yes, Compress::No] {
+            let mut bytes = vec![];
+            value.serialize_with_mode(&mut bytes, compress).unwrap();
+            assert_eq!(bytes.len(), value.serialized_size(compress));
+            let out = T::deserialize_with_mode(&bytes[..], compress, ark_serialize::Validate::Yes)
+                .unwrap();
+            assert_eq!(&out, value);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn zk_fields_round_trip(fields in any::<ZKFields<Fr>>()) {
+            round_trip(&fields);
+        }
+
+        #[test]
+        fn user_round_trip(user in any::<User<Fr, bool>>()) {
+            round_trip(&user);
+        }
+
+        #[test]
+        fn callback_com_round_trip(cb in any::<CB>()) {
+            round_trip(&cb);
+        }
+
+        #[test]
+        fn priv_scan_tickets_round_trip(args in any::<PrivScan>()) {
+            for (cb, time) in args.priv_n_tickets.iter().zip(args.post_times) {
+                round_trip(cb);
+                round_trip(&time);
+            }
+        }
+
+        #[test]
+        fn pub_scan_time_is_last_input(args in any::<PubScan>()) {
+            let inputs = args.to_field_elements().unwrap();
+            prop_assert_eq!(inputs.last(), Some(&args.cur_time));
+        }
+    }
+}
@@ -4,6 +4,10 @@ use crate::{
         rr::RRVerifier,
     },
     generic::{
+        bulletin::{
+            PublicCallbackBul as SyncPublicCallbackBul, PublicUserBul as SyncPublicUserBul,
+        },
+        interaction::interaction_public_inputs,
         object::{Com, ComVar, Nul, Time, TimeVar},
         user::UserData,
     },
@@ -16,21 +20,36 @@ use ark_r1cs_std::{
 };
 use ark_relations::r1cs::SynthesisError;
 use ark_snark::SNARK;
+use core::marker::PhantomData;
 
+/// An error indicating something went wrong with an asynchronous bulletin.
 #[derive(Debug, Clone)]
 pub enum BulError<E> {
+    /// A proof verification failed (returned false).
     VerifyError,
+    /// Appending to the bulletin failed.
     AppendError(E),
 }
 
+/// An asynchronous public user bulletin.
+///
+/// This is the asynchronous counterpart of
+/// [`PublicUserBul`](`crate::generic::bulletin::PublicUserBul`), for bulletins which are accessed
+/// through some network handle.
 pub trait PublicUserBul<F: PrimeField + Absorb, U: UserData<F>> {
+    /// An error type for failed network requests or failed appends.
     type Error;
 
+    /// The private witness proving membership of an object.
     type MembershipWitness: Clone + Default;
+    /// The membership witness in-circuit.
     type MembershipWitnessVar: AllocVar<Self::MembershipWitness, F> + Clone;
+    /// Public data to verify membership of an object.
     type MembershipPub: Clone + Default + ToConstraintField<F>;
+    /// The public membership data in-circuit.
     type MembershipPubVar: AllocVar<Self::MembershipPub, F> + Clone;
 
+    /// Checks if an object is within the bulletin.
     #[allow(clippy::too_many_arguments)]
     async fn verify_in<PubArgs, Snark: SNARK<F>, const NUMCBS: usize>(
         &self,
@@ -43,6 +62,15 @@ pub trait PublicUserBul<F: PrimeField + Absorb, U: UserData<F>> {
         verif_key: &Snark::VerifyingKey,
     ) -> bool;
 
+    /// Fetch the membership data for an object.
+    ///
+    /// Returns `None` if the object is not in the bulletin.
+    async fn get_membership_data(
+        &self,
+        object: Com<F>,
+    ) -> Option<(Self::MembershipPub, Self::MembershipWitness)>;
+
+    /// Enforce membership of an object in-circuit.
     fn enforce_membership_of(
         data_var: ComVar<F>,
         extra_witness: Self::MembershipWitnessVar,
@@ -50,9 +78,14 @@ pub trait PublicUserBul<F: PrimeField + Absorb, U: UserData<F>> {
     ) -> Result<Boolean<F>, SynthesisError>;
 }
 
+/// An asynchronous user bulletin, which can append objects.
+///
+/// See [`UserBul`](`crate::generic::bulletin::UserBul`).
 pub trait UserBul<F: PrimeField + Absorb, U: UserData<F>>: PublicUserBul<F, U> {
-    async fn has_never_recieved_nul(&self, nul: &Nul<F>) -> bool;
+    /// Checks if a nullifier has never been seen before.
+    async fn has_never_received_nul(&self, nul: &Nul<F>) -> bool;
 
+    /// Append an object to the bulletin, without checking the proof.
     #[allow(clippy::too_many_arguments)]
     async fn append_value<PubArgs, Snark: SNARK<F>, const NUMCBS: usize>(
         &mut self,
@@ -65,6 +98,7 @@ pub trait UserBul<F: PrimeField + Absorb, U: UserData<F>>: PublicUserBul<F, U> {
         verif_key: &Snark::VerifyingKey,
     ) -> Result<(), Self::Error>;
 
+    /// Verify the proof of an interaction, and check the nullifier is fresh.
     #[allow(clippy::too_many_arguments)]
     async fn verify_interaction<
         PubArgs: ToConstraintField<F>,
@@ -80,20 +114,24 @@ pub trait UserBul<F: PrimeField + Absorb, U: UserData<F>>: PublicUserBul<F, U> {
         memb_data: Option<Self::MembershipPub>,
         verif_key: &Snark::VerifyingKey,
     ) -> bool {
-        if !self.has_never_recieved_nul(&old_nul).await {
+        if !self.has_never_received_nul(&old_nul).await {
             return false;
         }
 
-        let mut pub_inputs = vec![object, old_nul];
-        pub_inputs.extend::<Vec<F>>(args.to_field_elements().unwrap());
-        pub_inputs.extend::<Vec<F>>(cb_com_list.to_field_elements().unwrap());
-        if let Some(a) = memb_data {
-            pub_inputs.extend::<Vec<F>>(a.to_field_elements().unwrap());
-        }
+        let pub_inputs = interaction_public_inputs(
+            object,
+            old_nul,
+            &args,
+            &cb_com_list,
+            memb_data.as_ref(),
+            None,
+        )
+        .unwrap();
 
         Snark::verify(verif_key, &pub_inputs, &proof).unwrap_or(false)
     }
 
+    /// Verify an interaction and append the new object to the bulletin.
     #[allow(clippy::too_many_arguments)]
     async fn verify_interact_and_append<
         PubArgs: ToConstraintField<F> + Clone,
@@ -141,23 +179,53 @@ pub trait UserBul<F: PrimeField + Absorb, U: UserData<F>>: PublicUserBul<F, U> {
     }
 }
 
+/// An asynchronous public callback bulletin.
+///
+/// This is the asynchronous counterpart of
+/// [`PublicCallbackBul`](`crate::generic::bulletin::PublicCallbackBul`).
 pub trait PublicCallbackBul<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>> {
+    /// An error type for failed network requests or failed appends.
     type Error;
 
-    type MembershipWitness: Clone;
-    type MembershipWitnessVar: AllocVar<Self::MembershipWitness, F>;
-    type NonMembershipWitness: Clone;
-    type NonMembershipWitnessVar: AllocVar<Self::NonMembershipWitness, F>;
+    /// The membership witness for called tickets.
+    type MembershipWitness: Clone + Default;
+    /// The membership witness in-circuit.
+    type MembershipWitnessVar: Clone + AllocVar<Self::MembershipWitness, F>;
+    /// The nonmembership witness for tickets which have not been called.
+    type NonMembershipWitness: Clone + Default;
+    /// The nonmembership witness in-circuit.
+    type NonMembershipWitnessVar: Clone + AllocVar<Self::NonMembershipWitness, F>;
 
-    type MembershipPub: Clone;
-    type MembershipPubVar: AllocVar<Self::MembershipPub, F>;
-    type NonMembershipPub: Clone;
-    type NonMembershipPubVar: AllocVar<Self::NonMembershipPub, F>;
+    /// Public data to verify membership.
+    type MembershipPub: Clone + Default;
+    /// The public membership data in-circuit.
+    type MembershipPubVar: Clone + AllocVar<Self::MembershipPub, F>;
+    /// Public data to verify nonmembership.
+    type NonMembershipPub: Clone + Default;
+    /// The public nonmembership data in-circuit.
+    type NonMembershipPubVar: Clone + AllocVar<Self::NonMembershipPub, F>;
 
+    /// Checks if a ticket has been called. If so, returns the encrypted arguments, the signature,
+    /// and the time the ticket was posted.
     async fn verify_in(&self, tik: Crypto::SigPK) -> Option<(Crypto::Ct, Crypto::Sig, Time<F>)>;
 
+    /// Checks if a ticket has not been called.
     async fn verify_not_in(&self, tik: Crypto::SigPK) -> bool;
 
+    /// Fetch the membership and nonmembership data for a ticket.
+    ///
+    /// See [`get_membership_data`](`crate::generic::bulletin::PublicCallbackBul::get_membership_data`).
+    async fn get_membership_data(
+        &self,
+        tik: Crypto::SigPK,
+    ) -> (
+        Self::MembershipPub,
+        Self::MembershipWitness,
+        Self::NonMembershipPub,
+        Self::NonMembershipWitness,
+    );
+
+    /// Enforce membership of a called ticket in-circuit.
     fn enforce_membership_of(
         tikvar: (
             Crypto::SigPKV,
@@ -168,12 +236,14 @@ pub trait PublicCallbackBul<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<
         extra_pub: Self::MembershipPubVar,
     ) -> Result<Boolean<F>, SynthesisError>;
 
+    /// Enforce nonmembership of a ticket in-circuit.
     fn enforce_nonmembership_of(
         tikvar: Crypto::SigPKV,
         extra_witness: Self::NonMembershipWitnessVar,
         extra_pub: Self::NonMembershipPubVar,
     ) -> Result<Boolean<F>, SynthesisError>;
 
+    /// Enforce that a ticket is either a member or a nonmember in-circuit, and output membership.
     fn enforce_memb_nmemb(
         tikvar: (
             Crypto::SigPKV,
@@ -191,11 +261,16 @@ pub trait PublicCallbackBul<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<
     }
 }
 
+/// An asynchronous callback bulletin, which can append called tickets.
+///
+/// See [`CallbackBul`](`crate::generic::bulletin::CallbackBul`).
 pub trait CallbackBulletin<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>>:
     PublicCallbackBul<F, CBArgs, Crypto>
 {
-    async fn has_never_recieved_tik(&self, tik: &Crypto::SigPK) -> bool;
+    /// Checks if a ticket has never been posted.
+    async fn has_never_received_tik(&self, tik: &Crypto::SigPK) -> bool;
 
+    /// Append a called ticket to the bulletin, without checking the signature.
     async fn append_value(
         &mut self,
         tik: Crypto::SigPK,
@@ -204,18 +279,20 @@ pub trait CallbackBulletin<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F
         time: Time<F>,
     ) -> Result<(), Self::Error>;
 
+    /// Verify a called ticket is fresh and is signed.
     async fn verify_call(
         &self,
         tik: Crypto::SigPK,
         enc_args: Crypto::Ct,
         signature: Crypto::Sig,
     ) -> bool {
-        if !self.has_never_recieved_tik(&tik).await {
+        if !self.has_never_received_tik(&tik).await {
             return false;
         }
         tik.verify(enc_args.clone(), signature)
     }
 
+    /// Verify a called ticket and append it to the bulletin.
     async fn verify_call_and_append(
         &mut self,
         tik: Crypto::SigPK,
@@ -239,12 +316,250 @@ pub trait CallbackBulletin<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F
     }
 }
 
+/// An asynchronous bulletin which users may join.
 pub trait JoinableBulletin<F: PrimeField + Absorb, U: UserData<F>>: UserBul<F, U> {
+    /// Public data necessary to join.
     type PubData;
 
+    /// Add a new object to the bulletin.
     async fn join_bul(
         &mut self,
         object: Com<F>,
         pub_data: Self::PubData,
     ) -> Result<(), Self::Error>;
 }
+
+/// A snapshot of the membership data of a single object, fetched from an asynchronous bulletin.
+///
+/// This implements the synchronous [`PublicUserBul`](`crate::generic::bulletin::PublicUserBul`)
+/// with the same in-circuit types as `B`, so it may be used to generate keys and proofs for `B`.
+///
+/// Note that a snapshot cannot verify proofs, and [`verify_in`](`SyncPublicUserBul::verify_in`)
+/// always returns false. Use the underlying bulletin instead.
+pub struct UserBulSnapshot<F: PrimeField + Absorb, U: UserData<F>, B: PublicUserBul<F, U>> {
+    /// The object the snapshot was taken for.
+    pub object: Com<F>,
+    /// The membership data of the object, if the object is in the bulletin.
+    pub memb_data: Option<(B::MembershipPub, B::MembershipWitness)>,
+    _phantom: PhantomData<fn() -> U>,
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, B: PublicUserBul<F, U>> UserBulSnapshot<F, U, B> {
+    /// Fetch the membership data of `object` from the bulletin.
+    pub async fn fetch(bul: &B, object: Com<F>) -> Self {
+        Self {
+            object,
+            memb_data: bul.get_membership_data(object).await,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, B: PublicUserBul<F, U>> Clone
+    for UserBulSnapshot<F, U, B>
+{
+    fn clone(&self) -> Self {
+        Self {
+            object: self.object,
+            memb_data: self.memb_data.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, B: PublicUserBul<F, U>> Default
+    for UserBulSnapshot<F, U, B>
+{
+    fn default() -> Self {
+        Self {
+            object: Com::<F>::default(),
+            memb_data: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, B: PublicUserBul<F, U>> SyncPublicUserBul<F, U>
+    for UserBulSnapshot<F, U, B>
+{
+    type MembershipWitness = B::MembershipWitness;
+    type MembershipWitnessVar = B::MembershipWitnessVar;
+    type MembershipPub = B::MembershipPub;
+    type MembershipPubVar = B::MembershipPubVar;
+
+    fn verify_in<PubArgs: ToConstraintField<F>, Snark: SNARK<F>, const NUMCBS: usize>(
+        &self,
+        _object: Com<F>,
+        _old_nul: Nul<F>,
+        _cb_com_list: [Com<F>; NUMCBS],
+        _args: PubArgs,
+        _proof: Snark::Proof,
+        _memb_data: Self::MembershipPub,
+        _verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        false
+    }
+
+    fn get_membership_data(
+        &self,
+        object: Com<F>,
+    ) -> Option<(Self::MembershipPub, Self::MembershipWitness)> {
+        if object != self.object {
+            return None;
+        }
+        self.memb_data.clone()
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_membership_of(data_var, extra_witness, extra_pub)
+    }
+}
+
+type TicketSnapshot<F, CBArgs, Crypto, B> = (
+    <Crypto as AECipherSigZK<F, CBArgs>>::SigPK,
+    Option<(<Crypto as AECipherSigZK<F, CBArgs>>::Ct, Time<F>)>,
+    bool,
+    (
+        <B as PublicCallbackBul<F, CBArgs, Crypto>>::MembershipPub,
+        <B as PublicCallbackBul<F, CBArgs, Crypto>>::MembershipWitness,
+        <B as PublicCallbackBul<F, CBArgs, Crypto>>::NonMembershipPub,
+        <B as PublicCallbackBul<F, CBArgs, Crypto>>::NonMembershipWitness,
+    ),
+);
+
+/// A snapshot of the state of a set of tickets, fetched from an asynchronous callback bulletin.
+///
+/// This implements the synchronous
+/// [`PublicCallbackBul`](`crate::generic::bulletin::PublicCallbackBul`) with the same in-circuit
+/// types as `B`, so it may be used to scan tickets and generate scan keys for `B`.
+///
+/// Only the tickets in the snapshot may be queried; any other ticket is reported as neither
+/// called nor uncalled.
+pub struct CallbackBulSnapshot<
+    F: PrimeField,
+    CBArgs: Clone,
+    Crypto: AECipherSigZK<F, CBArgs>,
+    B: PublicCallbackBul<F, CBArgs, Crypto>,
+> {
+    entries: Vec<TicketSnapshot<F, CBArgs, Crypto, B>>,
+}
+
+impl<
+        F: PrimeField,
+        CBArgs: Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        B: PublicCallbackBul<F, CBArgs, Crypto>,
+    > CallbackBulSnapshot<F, CBArgs, Crypto, B>
+{
+    /// Fetch the state of each ticket in `tiks` from the bulletin.
+    pub async fn fetch(bul: &B, tiks: Vec<Crypto::SigPK>) -> Self {
+        let mut entries = vec![];
+        for tik in tiks {
+            let called = bul.verify_in(tik.clone()).await.map(|(ct, _, t)| (ct, t));
+            let not_called = bul.verify_not_in(tik.clone()).await;
+            let data = bul.get_membership_data(tik.clone()).await;
+            entries.push((tik, called, not_called, data));
+        }
+        Self { entries }
+    }
+
+    fn get(&self, tik: &Crypto::SigPK) -> Option<&TicketSnapshot<F, CBArgs, Crypto, B>> {
+        self.entries.iter().find(|(t, _, _, _)| t == tik)
+    }
+}
+
+impl<
+        F: PrimeField,
+        CBArgs: Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        B: PublicCallbackBul<F, CBArgs, Crypto>,
+    > Clone for CallbackBulSnapshot<F, CBArgs, Crypto, B>
+{
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<
+        F: PrimeField,
+        CBArgs: Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        B: PublicCallbackBul<F, CBArgs, Crypto>,
+    > Default for CallbackBulSnapshot<F, CBArgs, Crypto, B>
+{
+    fn default() -> Self {
+        Self { entries: vec![] }
+    }
+}
+
+impl<
+        F: PrimeField,
+        CBArgs: Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        B: PublicCallbackBul<F, CBArgs, Crypto>,
+    > SyncPublicCallbackBul<F, CBArgs, Crypto> for CallbackBulSnapshot<F, CBArgs, Crypto, B>
+{
+    type MembershipWitness = B::MembershipWitness;
+    type MembershipWitnessVar = B::MembershipWitnessVar;
+    type NonMembershipWitness = B::NonMembershipWitness;
+    type NonMembershipWitnessVar = B::NonMembershipWitnessVar;
+    type MembershipPub = B::MembershipPub;
+    type MembershipPubVar = B::MembershipPubVar;
+    type NonMembershipPub = B::NonMembershipPub;
+    type NonMembershipPubVar = B::NonMembershipPubVar;
+
+    fn verify_in(&self, tik: Crypto::SigPK) -> Option<(Crypto::Ct, Time<F>)> {
+        self.get(&tik).and_then(|(_, called, _, _)| called.clone())
+    }
+
+    fn verify_not_in(&self, tik: Crypto::SigPK) -> bool {
+        self.get(&tik)
+            .is_some_and(|(_, _, not_called, _)| *not_called)
+    }
+
+    fn get_membership_data(
+        &self,
+        tik: Crypto::SigPK,
+    ) -> (
+        Self::MembershipPub,
+        Self::MembershipWitness,
+        Self::NonMembershipPub,
+        Self::NonMembershipWitness,
+    ) {
+        match self.get(&tik) {
+            Some((_, _, _, data)) => data.clone(),
+            None => (
+                B::MembershipPub::default(),
+                B::MembershipWitness::default(),
+                B::NonMembershipPub::default(),
+                B::NonMembershipWitness::default(),
+            ),
+        }
+    }
+
+    fn enforce_membership_of(
+        tikvar: (
+            Crypto::SigPKV,
+            <Crypto::EncKey as CPACipher<F>>::CV,
+            TimeVar<F>,
+        ),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_membership_of(tikvar, extra_witness, extra_pub)
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: Crypto::SigPKV,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_nonmembership_of(tikvar, extra_witness, extra_pub)
+    }
+}
@@ -1,4 +1,11 @@
-#![allow(dead_code)]
+#![allow(async_fn_in_trait)]
 
+/// Asynchronous traits for object and callback bulletins, along with snapshots to use them in
+/// proofs.
 pub mod bulletin;
+
+/// Asynchronous traits for services.
 pub mod service;
+
+/// Asynchronous interactions for users.
+pub mod user;
@@ -3,6 +3,8 @@ use crate::{
     generic::{
         asynchr::bulletin::{BulError, PublicUserBul},
         callbacks::CallbackCom,
+        interaction::interaction_public_inputs,
+        service::Called,
         user::{ExecutedMethod, UserData},
     },
 };
@@ -10,17 +12,17 @@ use ark_crypto_primitives::sponge::Absorb;
 use ark_ff::{PrimeField, ToConstraintField};
 use ark_snark::SNARK;
 
-type Called<F, A, Crypto> = (
-    <Crypto as AECipherSigZK<F, A>>::SigPK,
-    <Crypto as AECipherSigZK<F, A>>::Ct,
-    <Crypto as AECipherSigZK<F, A>>::Sig,
-);
-
+/// An asynchronous service provider.
+///
+/// See [`ServiceProvider`](`crate::generic::service::ServiceProvider`).
 pub trait ServiceProvider<F: PrimeField + Absorb, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>> {
+    /// An error type for failed network requests or failed stores.
     type Error;
 
+    /// Extra data stored along with an interaction.
     type InteractionData;
 
+    /// Call a callback ticket, by encrypting and signing the arguments with the ticket key.
     fn call(
         &self,
         ticket: CallbackCom<F, CBArgs, Crypto>,
@@ -31,14 +33,19 @@ pub trait ServiceProvider<F: PrimeField + Absorb, CBArgs: Clone, Crypto: AECiphe
         Ok((ticket.cb_entry.tik, enc, sig))
     }
 
-    async fn has_never_recieved_tik(&self, ticket: Crypto::SigPK) -> bool;
+    /// Checks that a ticket has never been received in an interaction.
+    async fn has_never_received_tik(&self, ticket: Crypto::SigPK) -> bool;
 
+    /// Store an interaction along with some extra data.
     async fn store_interaction<U: UserData<F>, Snark: SNARK<F>, const NUMCBS: usize>(
         &mut self,
         interaction: ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>,
         data: Self::InteractionData,
     ) -> Result<(), Self::Error>;
 
+    /// Approve an interaction: checks the object is in the bulletin, the tickets are fresh and
+    /// rerandomized from the service key, and that the proof verifies.
+    #[allow(clippy::too_many_arguments)]
     async fn approve_interaction<
         U: UserData<F>,
         Snark: SNARK<F>,
@@ -78,23 +85,25 @@ pub trait ServiceProvider<F: PrimeField + Absorb, CBArgs: Clone, Crypto: AECiphe
                 return false;
             }
 
-            if !self.has_never_recieved_tik(cb.cb_entry.tik).await {
+            if !self.has_never_received_tik(cb.cb_entry.tik).await {
                 return false;
             }
         }
 
-        let mut pub_inputs = vec![
+        let pub_inputs = interaction_public_inputs(
             interaction_request.new_object,
             interaction_request.old_nullifier,
-        ];
-        pub_inputs.extend::<Vec<F>>(args.to_field_elements().unwrap());
-        pub_inputs.extend::<Vec<F>>(interaction_request.cb_com_list.to_field_elements().unwrap());
-        if !is_memb_data_const {
-            pub_inputs.extend(memb_data.to_field_elements().unwrap());
-        }
+            &args,
+            &interaction_request.cb_com_list,
+            (!is_memb_data_const).then_some(&memb_data),
+            interaction_request.rate_limit_tag.as_ref(),
+        )
+        .unwrap();
         Snark::verify(verif_key, &pub_inputs, &interaction_request.proof).unwrap_or(false)
     }
 
+    /// Approve an interaction and store it.
+    #[allow(clippy::too_many_arguments)]
     async fn approve_interaction_and_store<
         U: UserData<F>,
         Snark: SNARK<F>,
@@ -0,0 +1,418 @@
+use crate::{
+    crypto::{enc::AECipherSigZK, hash::FieldHash},
+    generic::{
+        asynchr::bulletin::{
+            CallbackBulSnapshot, PublicCallbackBul, PublicUserBul, UserBulSnapshot,
+        },
+        callbacks::CallbackCom,
+        interaction::{Callback, Interaction, SingularPredicate},
+        object::{ComVar, Time},
+        scan::PubScanArgs,
+        user::{ExecutedMethod, User, UserData, UserVar},
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, select::CondSelectGadget};
+use ark_relations::r1cs::SynthesisError;
+use ark_snark::SNARK;
+use rand::{
+    distributions::{Distribution, Standard},
+    CryptoRng, RngCore,
+};
+
+impl<F: PrimeField + Absorb, U: UserData<F>> User<F, U>
+where
+    Standard: Distribution<F>,
+{
+    /// Execute a method and create callbacks, fetching membership data from an asynchronous
+    /// bulletin.
+    ///
+    /// This is the asynchronous counterpart of [`User::exec_method_create_cb`]. The membership
+    /// data for the user is awaited from `bul`, and the proof is then produced against a
+    /// [`UserBulSnapshot`]. Keys should therefore be generated with `UserBulSnapshot<F, U, Bul>`
+    /// as the bulletin type.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn exec_method_create_cb_async<
+        H: FieldHash<F>,
+        PubArgs: Clone + std::fmt::Debug,
+        PubArgsVar: AllocVar<PubArgs, F> + Clone,
+        PrivArgs: Clone + std::fmt::Debug,
+        PrivArgsVar: AllocVar<PrivArgs, F> + Clone,
+        CBArgs: Clone + std::fmt::Debug,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        method: Interaction<
+            F,
+            U,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            NUMCBS,
+        >,
+        rpks: [Crypto::SigPK; NUMCBS],
+        cur_time: Time<F>,
+        bul: &Bul,
+        is_memb_data_const: bool,
+        pk: &Snark::ProvingKey,
+        pub_args: PubArgs,
+        priv_args: PrivArgs,
+    ) -> Result<ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>, SynthesisError> {
+        let snapshot = UserBulSnapshot::<F, U, Bul>::fetch(bul, self.commit::<H>()).await;
+
+        self.exec_method_create_cb::<
+            H,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            Crypto,
+            Snark,
+            UserBulSnapshot<F, U, Bul>,
+            NUMCBS,
+        >(
+            rng,
+            method,
+            rpks,
+            cur_time,
+            &snapshot,
+            is_memb_data_const,
+            pk,
+            pub_args,
+            priv_args,
+        )
+    }
+
+    /// Scan callbacks, fetching membership data from asynchronous bulletins.
+    ///
+    /// This is the asynchronous counterpart of [`User::scan_callbacks`]. The state of the next
+    /// `NUMSCANS` tickets is awaited from `cbul`, and the membership data for the user is awaited
+    /// from `bul`. The scan is then performed against the fetched snapshots.
+    ///
+    /// Keys should be generated with `UserBulSnapshot<F, U, Bul>` and
+    /// `CallbackBulSnapshot<F, CBArgs, Crypto, CBul>` as the bulletin types.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn scan_callbacks_async<
+        H: FieldHash<F>,
+        CBArgs: Clone + std::fmt::Debug + PartialEq + Eq,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs, AV = CBArgsVar> + PartialEq + Eq,
+        CBul: PublicCallbackBul<F, CBArgs, Crypto>,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U>,
+        const NUMSCANS: usize,
+    >(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        bul: &Bul,
+        is_memb_data_const: bool,
+        pk: &Snark::ProvingKey,
+        cbul: &CBul,
+        is_memb_nmemb_const: (bool, bool),
+        cur_time: Time<F>,
+        cb_methods: Vec<Callback<F, U, CBArgs, CBArgsVar>>,
+    ) -> Result<
+        (
+            PubScanArgs<
+                F,
+                U,
+                CBArgs,
+                CBArgsVar,
+                Crypto,
+                CallbackBulSnapshot<F, CBArgs, Crypto, CBul>,
+                NUMSCANS,
+            >,
+            ExecutedMethod<F, Snark, CBArgs, Crypto, 0>,
+        ),
+        SynthesisError,
+    >
+    where
+        U::UserDataVar: CondSelectGadget<F> + EqGadget<F>,
+        CBul::MembershipPub: std::fmt::Debug,
+        CBul::NonMembershipPub: std::fmt::Debug,
+    {
+        let start_ind = self.scan_index.unwrap_or(0);
+
+        let tiks = (start_ind..(start_ind + NUMSCANS).min(self.callbacks.len()))
+            .map(|i| {
+                let cb: CallbackCom<F, CBArgs, Crypto> = self.get_cb::<CBArgs, Crypto>(i);
+                cb.get_ticket()
+            })
+            .collect();
+
+        let cb_snapshot = CallbackBulSnapshot::<F, CBArgs, Crypto, CBul>::fetch(cbul, tiks).await;
+        let snapshot = UserBulSnapshot::<F, U, Bul>::fetch(bul, self.commit::<H>()).await;
+
+        self.scan_callbacks::<
+            H,
+            CBArgs,
+            CBArgsVar,
+            Crypto,
+            CallbackBulSnapshot<F, CBArgs, Crypto, CBul>,
+            Snark,
+            UserBulSnapshot<F, U, Bul>,
+            NUMSCANS,
+        >(
+            rng,
+            &snapshot,
+            is_memb_data_const,
+            pk,
+            &cb_snapshot,
+            is_memb_nmemb_const,
+            cur_time,
+            cb_methods,
+        )
+    }
+
+    /// Prove a statement about the user along with membership, fetching membership data from an
+    /// asynchronous bulletin.
+    ///
+    /// This is the asynchronous counterpart of [`User::prove_statement_and_in`]. If the user is
+    /// not in the bulletin, this returns [`SynthesisError::AssignmentMissing`].
+    ///
+    /// Keys should be generated with `UserBulSnapshot<F, U, Bul>` as the bulletin type.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn prove_statement_and_in_async<
+        H: FieldHash<F>,
+        PubArgs: Clone,
+        PubArgsVar: AllocVar<PubArgs, F> + Clone,
+        PrivArgs: Clone,
+        PrivArgsVar: AllocVar<PrivArgs, F> + Clone,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        predicate: SingularPredicate<F, UserVar<F, U>, ComVar<F>, PubArgsVar, PrivArgsVar>,
+        pk: &Snark::ProvingKey,
+        bul: &Bul,
+        is_memb_data_const: bool,
+        pub_args: PubArgs,
+        priv_args: PrivArgs,
+    ) -> Result<Snark::Proof, SynthesisError> {
+        let (memb_pub, memb_witness) = bul
+            .get_membership_data(self.commit::<H>())
+            .await
+            .ok_or(SynthesisError::AssignmentMissing)?;
+
+        self.prove_statement_and_in::<
+            H,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            Snark,
+            UserBulSnapshot<F, U, Bul>,
+        >(
+            rng,
+            predicate,
+            pk,
+            (memb_witness, memb_pub),
+            is_memb_data_const,
+            pub_args,
+            priv_args,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        generic::{
+            interaction::interaction_public_inputs,
+            object::{Com, Nul},
+        },
+        impls::{centralized::crypto::NoSigOTP, hash::Poseidon},
+    };
+    use ark_bn254::{Bn254, Fr};
+    use ark_groth16::Groth16;
+    use ark_r1cs_std::{fields::fp::FpVar, prelude::Boolean};
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+    use rand::thread_rng;
+
+    type H = Poseidon<2>;
+
+    type Int = Interaction<Fr, Fr, (), (), (), (), Fr, FpVar<Fr>, 0>;
+
+    // An asynchronous bulletin which either contains every object or none
+    struct AsyncStore {
+        contains: bool,
+    }
+
+    impl PublicUserBul<Fr, Fr> for AsyncStore {
+        type Error = ();
+
+        type MembershipWitness = ();
+        type MembershipWitnessVar = ();
+        type MembershipPub = ();
+        type MembershipPubVar = ();
+
+        async fn verify_in<PubArgs, Snark: SNARK<Fr>, const NUMCBS: usize>(
+            &self,
+            _object: Com<Fr>,
+            _old_nul: Nul<Fr>,
+            _cb_com_list: [Com<Fr>; NUMCBS],
+            _args: PubArgs,
+            _proof: Snark::Proof,
+            _memb_data: (),
+            _verif_key: &Snark::VerifyingKey,
+        ) -> bool {
+            self.contains
+        }
+
+        async fn get_membership_data(&self, _object: Com<Fr>) -> Option<((), ())> {
+            self.contains.then_some(((), ()))
+        }
+
+        fn enforce_membership_of(
+            _data_var: ComVar<Fr>,
+            _extra_witness: (),
+            _extra_pub: (),
+        ) -> Result<Boolean<Fr>, SynthesisError> {
+            Ok(Boolean::TRUE)
+        }
+    }
+
+    // The futures in these tests never pend, so they are polled to completion directly
+    fn block_on<T>(fut: impl Future<Output = T>) -> T {
+        let mut fut = pin!(fut);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                return out;
+            }
+        }
+    }
+
+    fn method(old_user: &User<Fr, Fr>, _pub: (), _priv: ()) -> User<Fr, Fr> {
+        let mut u = old_user.clone();
+        u.data += Fr::from(1);
+        u
+    }
+
+    fn predicate(
+        old_user: &UserVar<Fr, Fr>,
+        new_user: &UserVar<Fr, Fr>,
+        _pub: (),
+        _priv: (),
+    ) -> Result<Boolean<Fr>, SynthesisError> {
+        new_user.data.is_eq(&(old_user.data.clone() + Fr::from(1)))
+    }
+
+    fn interaction() -> Int {
+        Interaction {
+            meth: (method, predicate),
+            callbacks: [],
+        }
+    }
+
+    fn statement(
+        _user: &UserVar<Fr, Fr>,
+        _com: &ComVar<Fr>,
+        _pub: (),
+        _priv: (),
+    ) -> Result<Boolean<Fr>, SynthesisError> {
+        Ok(Boolean::TRUE)
+    }
+
+    // Tests that a method executed against an asynchronous bulletin produces a valid proof
+    #[test]
+    fn exec_method_async_verifies() {
+        let mut rng = thread_rng();
+        let int = interaction();
+        let (pk, vk) = int
+            .generate_keys::<H, Groth16<Bn254>, NoSigOTP<Fr>, UserBulSnapshot<Fr, Fr, AsyncStore>>(
+                &mut rng,
+                Some(()),
+                None,
+                false,
+            );
+
+        let mut u = User::create(Fr::from(5), &mut rng);
+        let bul = AsyncStore { contains: true };
+        let exec = block_on(u.exec_method_create_cb_async::<
+            H,
+            (),
+            (),
+            (),
+            (),
+            Fr,
+            FpVar<Fr>,
+            NoSigOTP<Fr>,
+            Groth16<Bn254>,
+            AsyncStore,
+            0,
+        >(
+            &mut rng,
+            int,
+            [],
+            Time::from(0),
+            &bul,
+            true,
+            &pk,
+            (),
+            (),
+        ))
+        .unwrap();
+
+        assert_eq!(u.data, Fr::from(6));
+        assert_eq!(exec.new_object, u.commit::<H>());
+
+        let pub_inputs = interaction_public_inputs::<Fr, (), (), 0>(
+            exec.new_object,
+            exec.old_nullifier,
+            &(),
+            &exec.cb_com_list,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(Groth16::<Bn254>::verify(&vk, &pub_inputs, &exec.proof).unwrap());
+    }
+
+    // Tests that proving membership fails when the bulletin does not contain the user
+    #[test]
+    fn prove_statement_missing_user() {
+        let mut rng = thread_rng();
+        let int = interaction();
+        let (pk, _) = int
+            .generate_keys::<H, Groth16<Bn254>, NoSigOTP<Fr>, UserBulSnapshot<Fr, Fr, AsyncStore>>(
+                &mut rng,
+                Some(()),
+                None,
+                false,
+            );
+
+        let u = User::create(Fr::from(5), &mut rng);
+        let bul = AsyncStore { contains: false };
+        let out = block_on(
+            u.prove_statement_and_in_async::<H, (), (), (), (), Groth16<Bn254>, AsyncStore>(
+                &mut rng,
+                statement,
+                &pk,
+                &bul,
+                true,
+                (),
+                (),
+            ),
+        );
+
+        assert!(matches!(out, Err(SynthesisError::AssignmentMissing)));
+    }
+}
@@ -0,0 +1,273 @@
+use crate::generic::{
+    object::ComVar,
+    user::{User, UserData, UserVar},
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    select::CondSelectGadget,
+};
+use ark_relations::{
+    ns,
+    r1cs::{Namespace, Result as ArkResult, SynthesisError},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::borrow::Borrow;
+
+/// User data holding `N` badge slots.
+///
+/// Badges are field elements stored in an array of `N` slots within the user, where a zero badge
+/// denotes an empty slot. Services issue badges through callbacks with [`issue_badge`] and
+/// [`issue_badge_in_zk`], and users prove possession of a badge with [`badge_predicate`] or
+/// [`any_badge_predicate`].
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::fields::fp::FpVar;
+/// # use rand::thread_rng;
+/// # use zk_callbacks::zk_object;
+/// # use zk_callbacks::generic::user::User;
+/// # use zk_callbacks::generic::interaction::Callback;
+/// # use zk_callbacks::generic::badges::{issue_badge, issue_badge_in_zk, BadgeGrant, BadgeGrantVar, HasBadges};
+/// #[zk_object(Fr)]
+/// #[derive(Default)]
+/// struct Data {
+///     pub rep: Fr,
+///     pub badges: [Fr; 3],
+/// }
+///
+/// impl HasBadges<Fr, 3> for Data {
+///     fn badges(&self) -> &[Fr; 3] {
+///         &self.badges
+///     }
+///
+///     fn badges_mut(&mut self) -> &mut [Fr; 3] {
+///         &mut self.badges
+///     }
+///
+///     fn badges_var(var: &DataZKVar) -> &[FpVar<Fr>; 3] {
+///         &var.badges
+///     }
+///
+///     fn badges_var_mut(var: &mut DataZKVar) -> &mut [FpVar<Fr>; 3] {
+///         &mut var.badges
+///     }
+/// }
+///
+/// let cb: Callback<Fr, Data, BadgeGrant<Fr>, BadgeGrantVar<Fr>> = Callback {
+///     method_id: Fr::from(9),
+///     expirable: false,
+///     expiration: Fr::from(0),
+///     method: issue_badge::<Fr, Data, 3>,
+///     predicate: issue_badge_in_zk::<Fr, Data, 3>,
+/// };
+///
+/// let user = User::create(Data::default(), &mut thread_rng());
+/// let grant = BadgeGrant { slot: Fr::from(1), value: Fr::from(77) };
+/// let user = (cb.method)(&user, grant);
+/// assert_eq!(user.data.badges, [Fr::from(0), Fr::from(77), Fr::from(0)]);
+/// ```
+pub trait HasBadges<F: PrimeField + Absorb, const N: usize>: UserData<F> {
+    /// Get the badges of the user.
+    fn badges(&self) -> &[F; N];
+
+    /// Get the badges of the user mutably.
+    fn badges_mut(&mut self) -> &mut [F; N];
+
+    /// Get the badges of the user in-circuit.
+    fn badges_var(var: &Self::UserDataVar) -> &[FpVar<F>; N];
+
+    /// Get the badges of the user in-circuit mutably.
+    fn badges_var_mut(var: &mut Self::UserDataVar) -> &mut [FpVar<F>; N];
+}
+
+crate::callback_args! {
+    /// Arguments to a callback issuing a badge, which sets the badge in `slot` to `value`.
+    pub struct BadgeGrant, BadgeGrantVar {
+        slot,
+        value,
+    }
+}
+
+/// A callback method issuing a badge to a user.
+///
+/// Slots out of range leave the user unchanged, to match [`issue_badge_in_zk`].
+pub fn issue_badge<F: PrimeField + Absorb, U: HasBadges<F, N>, const N: usize>(
+    user: &User<F, U>,
+    args: BadgeGrant<F>,
+) -> User<F, U> {
+    let mut out = user.clone();
+    let badges = out.data.badges_mut();
+    for (i, badge) in badges.iter_mut().enumerate() {
+        if args.slot == F::from(i as u64) {
+            *badge = args.value;
+        }
+    }
+    out
+}
+
+/// The in-circuit method of a callback issuing a badge to a user.
+pub fn issue_badge_in_zk<F: PrimeField + Absorb, U: HasBadges<F, N>, const N: usize>(
+    user: &UserVar<F, U>,
+    args: BadgeGrantVar<F>,
+) -> ArkResult<UserVar<F, U>> {
+    let mut out = user.clone();
+    let badges = U::badges_var_mut(&mut out.data);
+    for (i, badge) in badges.iter_mut().enumerate() {
+        let hit = args.slot.is_eq(&FpVar::Constant(F::from(i as u64)))?;
+        *badge = FpVar::conditionally_select(&hit, &args.value, badge)?;
+    }
+    Ok(out)
+}
+
+/// Select the badge in a slot in-circuit.
+///
+/// Outputs the badge, and whether the slot is in range. The badge is zero if the slot is out of
+/// range.
+pub fn select_badge<F: PrimeField, const N: usize>(
+    badges: &[FpVar<F>; N],
+    slot: &FpVar<F>,
+) -> ArkResult<(FpVar<F>, Boolean<F>)> {
+    let mut out = FpVar::Constant(F::zero());
+    let mut valid = Boolean::FALSE;
+    for (i, badge) in badges.iter().enumerate() {
+        let hit = slot.is_eq(&FpVar::Constant(F::from(i as u64)))?;
+        out = FpVar::conditionally_select(&hit, badge, &out)?;
+        valid |= hit;
+    }
+    Ok((out, valid))
+}
+
+/// The public arguments to prove possession of a badge in a slot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BadgeArgs<F: PrimeField> {
+    /// The slot of the badge.
+    pub slot: F,
+    /// The claimed badge.
+    pub claimed: F,
+}
+
+impl<F: PrimeField> ToConstraintField<F> for BadgeArgs<F> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(vec![self.slot, self.claimed])
+    }
+}
+
+/// The in-circuit representation of [`BadgeArgs`].
+#[derive(Clone)]
+pub struct BadgeArgsVar<F: PrimeField> {
+    /// The slot of the badge.
+    pub slot: FpVar<F>,
+    /// The claimed badge.
+    pub claimed: FpVar<F>,
+}
+
+impl<F: PrimeField> AllocVar<BadgeArgs<F>, F> for BadgeArgsVar<F> {
+    fn new_variable<T: Borrow<BadgeArgs<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let slot = FpVar::new_variable(ns!(cs, "slot"), || Ok(rec.slot), mode)?;
+            let claimed = FpVar::new_variable(ns!(cs, "claimed"), || Ok(rec.claimed), mode)?;
+            Ok(BadgeArgsVar { slot, claimed })
+        })
+    }
+}
+
+/// A singular predicate proving the user holds the claimed badge in a slot.
+///
+/// The other badges of the user are not revealed. Empty (zero) badges may not be claimed.
+pub fn badge_predicate<F: PrimeField + Absorb, U: HasBadges<F, N>, const N: usize>(
+    user: &UserVar<F, U>,
+    _com: &ComVar<F>,
+    args: BadgeArgsVar<F>,
+    _priv: (),
+) -> ArkResult<Boolean<F>> {
+    let (badge, valid) = select_badge(U::badges_var(&user.data), &args.slot)?;
+    let nonzero = args.claimed.is_neq(&FpVar::Constant(F::zero()))?;
+    Ok(valid & nonzero & badge.is_eq(&args.claimed)?)
+}
+
+/// The public arguments to prove possession of any badge within a set of `M` badges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BadgeSetArgs<F: PrimeField, const M: usize> {
+    /// The set of accepted badges.
+    pub accepted: [F; M],
+}
+
+impl<F: PrimeField, const M: usize> Default for BadgeSetArgs<F, M> {
+    fn default() -> Self {
+        Self {
+            accepted: [F::zero(); M],
+        }
+    }
+}
+
+impl<F: PrimeField, const M: usize> ToConstraintField<F> for BadgeSetArgs<F, M> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(self.accepted.to_vec())
+    }
+}
+
+/// The in-circuit representation of [`BadgeSetArgs`].
+#[derive(Clone)]
+pub struct BadgeSetArgsVar<F: PrimeField, const M: usize> {
+    /// The set of accepted badges.
+    pub accepted: [FpVar<F>; M],
+}
+
+impl<F: PrimeField, const M: usize> AllocVar<BadgeSetArgs<F, M>, F> for BadgeSetArgsVar<F, M> {
+    fn new_variable<T: Borrow<BadgeSetArgs<F, M>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let accepted = <[FpVar<F>; M] as AllocVar<[F; M], F>>::new_variable(
+                ns!(cs, "accepted"),
+                || Ok(rec.accepted),
+                mode,
+            )?;
+            Ok(BadgeSetArgsVar { accepted })
+        })
+    }
+}
+
+/// A singular predicate proving the user holds some badge within the accepted set.
+///
+/// Neither the matching badge nor its slot is revealed. Empty (zero) badges never match.
+pub fn any_badge_predicate<
+    F: PrimeField + Absorb,
+    U: HasBadges<F, N>,
+    const N: usize,
+    const M: usize,
+>(
+    user: &UserVar<F, U>,
+    _com: &ComVar<F>,
+    args: BadgeSetArgsVar<F, M>,
+    _priv: (),
+) -> ArkResult<Boolean<F>> {
+    let mut found = Boolean::FALSE;
+    for badge in U::badges_var(&user.data).iter() {
+        let nonzero = badge.is_neq(&FpVar::Constant(F::zero()))?;
+        for accepted in args.accepted.iter() {
+            found |= nonzero.clone() & badge.is_eq(accepted)?;
+        }
+    }
+    Ok(found)
+}
@@ -4,9 +4,12 @@ use crate::{
         rr::RRVerifier,
     },
     generic::{
+        interaction::{interaction_public_inputs, RateLimitTag},
+        keystore::{CircuitHash, DigestedKey},
         object::{Com, ComVar, Nul},
         user::UserData,
     },
+    util::span,
 };
 use ark_crypto_primitives::sponge::Absorb;
 use ark_ff::{PrimeField, ToConstraintField};
@@ -15,7 +18,10 @@ use ark_r1cs_std::{
     prelude::{AllocVar, Boolean},
 };
 use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Valid};
 use ark_snark::SNARK;
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
 
 use crate::generic::object::{Time, TimeVar};
 
@@ -26,8 +32,92 @@ pub enum BulError<E> {
     VerifyError,
     /// Appending to the bulletin failed.
     AppendError(E),
+    /// The proof was made for a different circuit than the verifying key.
+    CircuitMismatch,
 }
 
+/// The updates to a user bulletin between two versions.
+///
+/// See [`PublicUserBul::get_updates_since`].
+#[derive(Clone, Debug, Default)]
+pub struct BulletinDelta<F: PrimeField, MembPub, MembWitness> {
+    /// The version the delta applies on top of.
+    pub from_version: u64,
+    /// The version of the bulletin after applying the delta.
+    pub to_version: u64,
+    /// The current public membership data of the bulletin.
+    pub memb_pub: MembPub,
+    /// The new entries, as (object, old nullifier, callback commitments, membership witness).
+    pub entries: Vec<(Com<F>, Nul<F>, Vec<Com<F>>, MembWitness)>,
+}
+
+impl<F: PrimeField, MembPub: CanonicalSerialize, MembWitness: CanonicalSerialize> CanonicalSerialize
+    for BulletinDelta<F, MembPub, MembWitness>
+{
+    fn serialize_with_mode<W: std::io::Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        self.from_version
+            .serialize_with_mode(&mut writer, compress)?;
+        self.to_version.serialize_with_mode(&mut writer, compress)?;
+        self.memb_pub.serialize_with_mode(&mut writer, compress)?;
+        self.entries.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.from_version.serialized_size(compress)
+            + self.to_version.serialized_size(compress)
+            + self.memb_pub.serialized_size(compress)
+            + self.entries.serialized_size(compress)
+    }
+}
+
+impl<F: PrimeField, MembPub: Valid, MembWitness: Valid> Valid
+    for BulletinDelta<F, MembPub, MembWitness>
+{
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        self.memb_pub.check()?;
+        self.entries.check()
+    }
+}
+
+impl<F: PrimeField, MembPub: CanonicalDeserialize, MembWitness: CanonicalDeserialize>
+    CanonicalDeserialize for BulletinDelta<F, MembPub, MembWitness>
+{
+    fn deserialize_with_mode<R: std::io::Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        Ok(Self {
+            from_version: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            to_version: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            memb_pub: MembPub::deserialize_with_mode(&mut reader, compress, validate)?,
+            entries: <Vec<(Com<F>, Nul<F>, Vec<Com<F>>, MembWitness)>>::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+        })
+    }
+}
+
+/// A single interaction submitted to a user bulletin in a batch.
+///
+/// This consists of the new object, the old nullifier, the public arguments, the callback
+/// commitments, the proof, and the membership data for the prior object (see
+/// [`UserBul::verify_interact_and_append`]).
+pub type BatchedInteraction<F, PubArgs, Snark, MembPub, const NUMCBS: usize> = (
+    Com<F>,
+    Nul<F>,
+    PubArgs,
+    [Com<F>; NUMCBS],
+    <Snark as SNARK<F>>::Proof,
+    Option<MembPub>,
+);
+
 /// Methods which users can perform by viewing a public user bulletin.
 ///
 /// This trait allows for users to verify membership of an object within a bulletin. Additionally, it allows for a user to prove
@@ -292,6 +382,32 @@ pub trait PublicUserBul<F: PrimeField + Absorb, U: UserData<F>> {
         extra_witness: Self::MembershipWitnessVar,
         extra_pub: Self::MembershipPubVar,
     ) -> Result<Boolean<F>, SynthesisError>;
+
+    /// Get all updates to the bulletin since some version.
+    ///
+    /// This allows thin clients to keep a local mirror of the bulletin without downloading the
+    /// entire bulletin each time. The version is the number of entries the client has already
+    /// seen, so a client with an empty mirror should request version `0`.
+    ///
+    /// This returns `None` if the bulletin does not support delta updates, or if the version is
+    /// ahead of the bulletin.
+    fn get_updates_since(
+        &self,
+        _version: u64,
+    ) -> Option<BulletinDelta<F, Self::MembershipPub, Self::MembershipWitness>> {
+        None
+    }
+
+    /// Get the size of the anonymity set of an interaction against the bulletin.
+    ///
+    /// This is the number of distinct users holding a valid object, so an interaction proof hides
+    /// the user among this many users. Clients may check this with an
+    /// [`AnonymityGuard`](`crate::generic::anonymity::AnonymityGuard`) before interacting.
+    ///
+    /// This returns `None` if the bulletin does not track its users.
+    fn anonymity_set_size(&self) -> Option<u64> {
+        None
+    }
 }
 
 /// A user bulletin.
@@ -514,14 +630,62 @@ pub trait UserBul<F: PrimeField + Absorb, U: UserData<F>>: PublicUserBul<F, U> {
             return false;
         }
 
-        let mut pub_inputs = vec![object, old_nul];
-        pub_inputs.extend::<Vec<F>>(args.to_field_elements().unwrap());
-        pub_inputs.extend::<Vec<F>>(cb_com_list.to_field_elements().unwrap());
-        if let Some(a) = memb_data {
-            pub_inputs.extend::<Vec<F>>(a.to_field_elements().unwrap());
+        let pub_inputs = interaction_public_inputs(
+            object,
+            old_nul,
+            &args,
+            &cb_com_list,
+            memb_data.as_ref(),
+            None,
+        )
+        .unwrap();
+
+        let out = span!("verify", callbacks = NUMCBS)
+            .in_scope(|| Snark::verify(verif_key, &pub_inputs, &proof));
+
+        out.unwrap_or(false)
+    }
+
+    /// Verifies a rate limited interaction.
+    ///
+    /// This is the same as [`UserBul::verify_interaction`], but also checks the proof against the
+    /// rate-limit tag output by [`User::interact_rate_limited`](`crate::generic::user::User::interact_rate_limited`).
+    ///
+    /// Note that this **does not** check whether the tag was seen before. The caller must check
+    /// that `rate_limit_tag.epoch` is the current epoch, and that the tag has not already been
+    /// used within the epoch.
+    #[allow(clippy::too_many_arguments)]
+    fn verify_rate_limited_interaction<
+        PubArgs: ToConstraintField<F>,
+        Snark: SNARK<F>,
+        const NUMCBS: usize,
+    >(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        args: PubArgs,
+        cb_com_list: [Com<F>; NUMCBS],
+        proof: Snark::Proof,
+        memb_data: Option<Self::MembershipPub>,
+        rate_limit_tag: RateLimitTag<F>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        if !self.has_never_received_nul(&old_nul) {
+            return false;
         }
 
-        let out = Snark::verify(verif_key, &pub_inputs, &proof);
+        let pub_inputs = interaction_public_inputs(
+            object,
+            old_nul,
+            &args,
+            &cb_com_list,
+            memb_data.as_ref(),
+            Some(&rate_limit_tag),
+        )
+        .unwrap();
+
+        let out = span!("verify", callbacks = NUMCBS)
+            .in_scope(|| Snark::verify(verif_key, &pub_inputs, &proof));
 
         out.unwrap_or(false)
     }
@@ -676,6 +840,79 @@ pub trait UserBul<F: PrimeField + Absorb, U: UserData<F>>: PublicUserBul<F, U> {
 
         Ok(())
     }
+
+    /// Verifies a user's interaction against a verifying key tagged with the digest of its
+    /// circuit, and appends the new object to the bulletin.
+    ///
+    /// This is the same as [`UserBul::verify_interact_and_append`], but if `circuit_digest` (from
+    /// [`ExecutedMethod::circuit_digest`](`crate::generic::user::ExecutedMethod::circuit_digest`))
+    /// is set and differs from the digest of the key, this returns [`BulError::CircuitMismatch`]
+    /// without checking the proof.
+    #[allow(clippy::too_many_arguments)]
+    fn verify_digested_interact_and_append<
+        PubArgs: ToConstraintField<F> + Clone,
+        Snark: SNARK<F>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        args: PubArgs,
+        cb_com_list: [Com<F>; NUMCBS],
+        proof: Snark::Proof,
+        memb_data: Option<Self::MembershipPub>,
+        circuit_digest: Option<CircuitHash>,
+        verif_key: &DigestedKey<Snark::VerifyingKey>,
+    ) -> Result<(), BulError<Self::Error>> {
+        if circuit_digest.is_some_and(|d| d != verif_key.digest) {
+            return Err(BulError::CircuitMismatch);
+        }
+        self.verify_interact_and_append::<PubArgs, Snark, NUMCBS>(
+            object,
+            old_nul,
+            args,
+            cb_com_list,
+            proof,
+            memb_data,
+            &verif_key.key,
+        )
+    }
+
+    /// Verify and append a batch of interactions.
+    ///
+    /// This is the same as calling [`UserBul::verify_interact_and_append`] on each interaction in
+    /// order, and returns one result per interaction. All interactions must be with respect to the
+    /// same `verif_key`.
+    ///
+    /// Interactions are processed in order, so if two interactions in the batch share a
+    /// nullifier, only the first one may succeed.
+    ///
+    /// Bulletins which can amortize work across appends (for example, by signing commitments in a
+    /// batch) should override this.
+    fn verify_interact_and_append_batch<
+        PubArgs: ToConstraintField<F> + Clone,
+        Snark: SNARK<F>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        batch: Vec<BatchedInteraction<F, PubArgs, Snark, Self::MembershipPub, NUMCBS>>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> Vec<Result<(), BulError<Self::Error>>> {
+        batch
+            .into_iter()
+            .map(|(object, old_nul, args, cb_com_list, proof, memb_data)| {
+                self.verify_interact_and_append::<PubArgs, Snark, NUMCBS>(
+                    object,
+                    old_nul,
+                    args,
+                    cb_com_list,
+                    proof,
+                    memb_data,
+                    verif_key,
+                )
+            })
+            .collect()
+    }
 }
 
 /// Methods which users can perform by viewing a public callback bulletin.
@@ -1256,6 +1493,186 @@ pub trait CallbackBul<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F, CBA
 
         Ok(())
     }
+
+    /// Prune called tickets from before the start of an epoch.
+    ///
+    /// A bulletin may drop the arguments and membership data of old tickets, so it does not grow
+    /// forever. However, it must keep enough to reject pruned tickets in
+    /// [`CallbackBul::has_never_received_tik`] and in proofs of nonmembership, as otherwise a
+    /// pruned ticket could be called again (or scanned as uncalled).
+    ///
+    /// Returns the number of pruned tickets. By default, the bulletin keeps every ticket and this
+    /// returns 0.
+    fn prune_before(&mut self, _epoch: F) -> Result<usize, Self::Error> {
+        Ok(0)
+    }
+}
+
+/// Methods for viewing a public callback bulletin at a fixed epoch.
+///
+/// A callback bulletin may keep appending tickets while users scan. Since nonmembership data
+/// changes when a ticket is appended, a user scanning against the live bulletin may observe
+/// inconsistent data. Instead, the bulletin may group its history into epochs, and keep the
+/// membership and nonmembership data for each past epoch.
+///
+/// Each epoch is associated with a root, which commits to the state of the bulletin at the
+/// beginning of the epoch. Users can then produce scans against an epoch with
+/// [`PinnedCallbackBul`], while the service continues appending tickets.
+pub trait PublicEpochCallbackBul<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>>:
+    PublicCallbackBul<F, CBArgs, Crypto>
+{
+    /// A commitment to the state of the bulletin at an epoch.
+    type EpochRoot: Clone + PartialEq + std::fmt::Debug;
+
+    /// Get the current epoch.
+    fn get_epoch(&self) -> F;
+
+    /// Get the root of the bulletin at some epoch.
+    ///
+    /// This returns None if the bulletin does not keep the epoch (or the epoch has not started).
+    fn get_epoch_root(&self, epoch: F) -> Option<Self::EpochRoot>;
+
+    /// Verify that a callback ticket was in the bulletin at the start of an epoch.
+    ///
+    /// This is the same as [`PublicCallbackBul::verify_in`], but ignores any tickets appended
+    /// after the epoch started.
+    fn verify_in_at(&self, tik: Crypto::SigPK, epoch: F) -> Option<(Crypto::Ct, Time<F>)>;
+
+    /// Checks whether a ticket was not contained in the bulletin at the start of an epoch.
+    fn verify_not_in_at(&self, tik: Crypto::SigPK, epoch: F) -> bool;
+
+    /// Given a ticket, get the membership data associated to that ticket at some epoch.
+    ///
+    /// This is the same as [`PublicCallbackBul::get_membership_data`], but is pinned to the
+    /// state of the bulletin at the start of the epoch. This returns None if the bulletin does not
+    /// keep the epoch.
+    #[allow(clippy::type_complexity)]
+    fn get_membership_data_at(
+        &self,
+        tik: Crypto::SigPK,
+        epoch: F,
+    ) -> Option<(
+        Self::MembershipPub,
+        Self::MembershipWitness,
+        Self::NonMembershipPub,
+        Self::NonMembershipWitness,
+    )>;
+}
+
+/// A callback bulletin with explicit epochs.
+///
+/// Along with appending tickets, the bulletin may advance the epoch, which fixes the state of the
+/// bulletin for the new epoch.
+pub trait EpochCallbackBul<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>>:
+    CallbackBul<F, CBArgs, Crypto> + PublicEpochCallbackBul<F, CBArgs, Crypto>
+{
+    /// Advance the epoch.
+    ///
+    /// All tickets appended before this call will be members at the new epoch, and any tickets
+    /// appended after will not be members until the next epoch.
+    fn advance_epoch(&mut self, rng: &mut (impl CryptoRng + RngCore)) -> Result<(), Self::Error>;
+}
+
+/// A view of a callback bulletin pinned to an epoch.
+///
+/// This implements [`PublicCallbackBul`] by forwarding all queries to
+/// [`PublicEpochCallbackBul`] at a fixed epoch, so it may be passed to
+/// [`User::scan_callbacks`](`crate::generic::user::User::scan_callbacks`) to scan against a
+/// stable snapshot of the bulletin.
+///
+/// The in-circuit checks are those of the underlying bulletin, so any keys generated with the
+/// underlying bulletin may be used with the pinned view.
+pub struct PinnedCallbackBul<'a, F, CBArgs, Crypto, B> {
+    /// The underlying bulletin.
+    pub bul: &'a B,
+    /// The epoch which all queries are made at.
+    pub epoch: F,
+    _phantom: PhantomData<(CBArgs, Crypto)>,
+}
+
+impl<'a, F, CBArgs, Crypto, B> PinnedCallbackBul<'a, F, CBArgs, Crypto, B> {
+    /// Pin a bulletin to an epoch.
+    pub fn new(bul: &'a B, epoch: F) -> Self {
+        Self {
+            bul,
+            epoch,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<
+        F: PrimeField,
+        CBArgs: Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        B: PublicEpochCallbackBul<F, CBArgs, Crypto>,
+    > PublicCallbackBul<F, CBArgs, Crypto> for PinnedCallbackBul<'_, F, CBArgs, Crypto, B>
+{
+    type MembershipWitness = B::MembershipWitness;
+    type MembershipWitnessVar = B::MembershipWitnessVar;
+    type NonMembershipWitness = B::NonMembershipWitness;
+    type NonMembershipWitnessVar = B::NonMembershipWitnessVar;
+    type MembershipPub = B::MembershipPub;
+    type MembershipPubVar = B::MembershipPubVar;
+    type NonMembershipPub = B::NonMembershipPub;
+    type NonMembershipPubVar = B::NonMembershipPubVar;
+
+    fn verify_in(&self, tik: Crypto::SigPK) -> Option<(Crypto::Ct, Time<F>)> {
+        self.bul.verify_in_at(tik, self.epoch)
+    }
+
+    fn verify_not_in(&self, tik: Crypto::SigPK) -> bool {
+        self.bul.verify_not_in_at(tik, self.epoch)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the underlying bulletin does not keep the pinned epoch.
+    fn get_membership_data(
+        &self,
+        tik: Crypto::SigPK,
+    ) -> (
+        Self::MembershipPub,
+        Self::MembershipWitness,
+        Self::NonMembershipPub,
+        Self::NonMembershipWitness,
+    ) {
+        self.bul
+            .get_membership_data_at(tik, self.epoch)
+            .expect("The bulletin does not keep the pinned epoch.")
+    }
+
+    fn enforce_membership_of(
+        tikvar: (
+            Crypto::SigPKV,
+            <Crypto::EncKey as CPACipher<F>>::CV,
+            TimeVar<F>,
+        ),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_membership_of(tikvar, extra_witness, extra_pub)
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: Crypto::SigPKV,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_nonmembership_of(tikvar, extra_witness, extra_pub)
+    }
+
+    fn enforce_memb_nmemb(
+        tikvar: (
+            Crypto::SigPKV,
+            <Crypto::EncKey as CPACipher<F>>::CV,
+            TimeVar<F>,
+        ),
+        ewitness: (Self::MembershipWitnessVar, Self::NonMembershipWitnessVar),
+        epub: (Self::MembershipPubVar, Self::NonMembershipPubVar),
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_memb_nmemb(tikvar, ewitness, epub)
+    }
 }
 
 /// A bulletin where a user can also join.
@@ -1275,4 +1692,60 @@ pub trait JoinableBulletin<F: PrimeField + Absorb, U: UserData<F>>: UserBul<F, U
 
     /// Decide and append a new user object to a bulletin based on some public data.
     fn join_bul(&mut self, object: Com<F>, pub_data: Self::PubData) -> Result<(), Self::Error>;
+
+    /// Append a new user object to a bulletin, if the object is admitted by a [`JoinPolicy`].
+    ///
+    /// The policy is checked before [`join_bul`](`JoinableBulletin::join_bul`) is called, so
+    /// objects rejected by the policy never enter the bulletin.
+    fn join_with_policy<P: JoinPolicy<F>>(
+        &mut self,
+        policy: &mut P,
+        object: Com<F>,
+        credential: &P::Credential,
+        pub_data: Self::PubData,
+    ) -> Result<(), JoinError<P::Error, Self::Error>> {
+        policy
+            .admit(&object, credential)
+            .map_err(JoinError::Rejected)?;
+        self.join_bul(object, pub_data).map_err(JoinError::Bulletin)
+    }
+}
+
+/// A policy deciding which user objects may join a bulletin.
+///
+/// Bulletins which admit anyone give no Sybil resistance: a single person could join many times,
+/// shrinking the anonymity set of honest users. A join policy checks some credential presented
+/// alongside the new committed user, such as an invite signed by an existing member, a one-time
+/// token from an identity provider, or simply a rate limit.
+///
+/// Policies are checked with [`JoinableBulletin::join_with_policy`].
+pub trait JoinPolicy<F: PrimeField> {
+    /// The credential presented by a joining user.
+    type Credential;
+
+    /// The reason a user was rejected.
+    type Error: std::fmt::Debug;
+
+    /// Admit or reject a new user object, updating any internal state (such as spent tokens).
+    fn admit(&mut self, object: &Com<F>, credential: &Self::Credential) -> Result<(), Self::Error>;
+}
+
+/// The policy admitting every user.
+impl<F: PrimeField> JoinPolicy<F> for () {
+    type Credential = ();
+
+    type Error = std::convert::Infallible;
+
+    fn admit(&mut self, _object: &Com<F>, _credential: &()) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// An error from joining a bulletin with a [`JoinPolicy`].
+#[derive(Debug, Clone)]
+pub enum JoinError<P, B> {
+    /// The policy rejected the user.
+    Rejected(P),
+    /// The bulletin failed to append the user.
+    Bulletin(B),
 }
@@ -0,0 +1,135 @@
+use ::base64::{engine::general_purpose::STANDARD, Engine};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+fn to_canonical_bytes<T: CanonicalSerialize, E: serde::ser::Error>(
+    value: &T,
+) -> Result<Vec<u8>, E> {
+    let mut bytes = Vec::with_capacity(value.serialized_size(Compress::Yes));
+    value
+        .serialize_with_mode(&mut bytes, Compress::Yes)
+        .map_err(E::custom)?;
+    Ok(bytes)
+}
+
+fn from_canonical_bytes<T: CanonicalDeserialize, E: serde::de::Error>(
+    bytes: &[u8],
+) -> Result<T, E> {
+    T::deserialize_with_mode(bytes, Compress::Yes, Validate::Yes).map_err(E::custom)
+}
+
+/// Encode canonically serializable values as base64 strings.
+///
+/// For use with `#[serde(with = "zk_callbacks::generic::encoding::base64")]` on fields such as
+/// commitments, tickets, or proofs.
+pub mod base64 {
+    use super::*;
+    use serde::de::Error as _;
+
+    /// Serialize a value as the base64 of its compressed canonical serialization.
+    pub fn serialize<T: CanonicalSerialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(to_canonical_bytes::<T, S::Error>(value)?))
+    }
+
+    /// Deserialize a value written by [`serialize`].
+    pub fn deserialize<'de, T: CanonicalDeserialize, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = STANDARD.decode(s).map_err(D::Error::custom)?;
+        from_canonical_bytes(&bytes)
+    }
+}
+
+/// Encode canonically serializable values as hex strings.
+///
+/// For use with `#[serde(with = "zk_callbacks::generic::encoding::hex")]`.
+pub mod hex {
+    use super::*;
+    use serde::de::Error as _;
+
+    /// Serialize a value as the hex of its compressed canonical serialization.
+    pub fn serialize<T: CanonicalSerialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&::hex::encode(to_canonical_bytes::<T, S::Error>(value)?))
+    }
+
+    /// Deserialize a value written by [`serialize`].
+    pub fn deserialize<'de, T: CanonicalDeserialize, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = ::hex::decode(s).map_err(D::Error::custom)?;
+        from_canonical_bytes(&bytes)
+    }
+}
+
+/// Encode byte strings (such as [wire envelopes](`crate::generic::wire`)) as base64 strings,
+/// rather than as arrays of integers.
+///
+/// For use with `#[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]` on `Vec<u8>`
+/// fields.
+pub mod base64_bytes {
+    use super::*;
+    use serde::de::Error as _;
+
+    /// Serialize bytes as base64.
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    /// Deserialize bytes written by [`serialize`].
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        STANDARD.decode(s).map_err(D::Error::custom)
+    }
+}
+
+/// A value encoded in JSON as a base64 string. See [`base64`].
+///
+/// # Example
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use zk_callbacks::generic::encoding::B64;
+/// let com = B64(Fr::from(5));
+/// let json = serde_json::to_string(&com).unwrap();
+/// assert!(json.starts_with('"'));
+///
+/// let back: B64<Fr> = serde_json::from_str(&json).unwrap();
+/// assert_eq!(back.0, Fr::from(5));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct B64<T>(pub T);
+
+impl<T: CanonicalSerialize> Serialize for B64<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        base64::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T: CanonicalDeserialize> Deserialize<'de> for B64<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        base64::deserialize(deserializer).map(B64)
+    }
+}
+
+/// A value encoded in JSON as a hex string. See [`hex`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Hex<T>(pub T);
+
+impl<T: CanonicalSerialize> Serialize for Hex<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hex::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T: CanonicalDeserialize> Deserialize<'de> for Hex<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        hex::deserialize(deserializer).map(Hex)
+    }
+}
@@ -3,10 +3,15 @@ use std::marker::PhantomData;
 use ark_r1cs_std::{convert::ToConstraintFieldGadget, select::CondSelectGadget};
 
 use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::CurveGroup;
 use ark_ff::PrimeField;
 use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
 use ark_relations::r1cs::SynthesisError;
-use folding_schemes::frontend::FCircuit;
+use folding_schemes::{frontend::FCircuit, Decider, FoldingScheme};
+use rand::{
+    distributions::{Distribution, Standard},
+    CryptoRng, RngCore,
+};
 
 use crate::{
     crypto::{
@@ -15,6 +20,7 @@ use crate::{
     },
     generic::{
         callbacks::{CallbackCom, CallbackTicket},
+        interaction::enforce_rate_limit_key_kept,
         scan::{scan_apply_method_zk, scan_method, PubScanArgsVar},
     },
 };
@@ -22,7 +28,7 @@ use crate::{
 use crate::generic::{
     bulletin::PublicCallbackBul,
     callbacks::{CallbackComVar, CallbackTicketVar},
-    object::{Ser, SerVar, ZKFields, ZKFieldsVar},
+    object::{Com, Ser, SerVar, ZKFields, ZKFieldsVar},
     scan::{PrivScanArgs, PrivScanArgsVar, PubScanArgs},
     user::{User, UserData, UserVar},
 };
@@ -340,6 +346,10 @@ where
 ///
 /// At each folding step, [`PrivScanArgs`] are deserialized from the folding representation. This
 /// struct will always have a callback count of `1`, as we only fold the scan one step at a time.
+///
+/// The folded state is `[old_com, cur_com]`: the commitment to the user before the scan, which is
+/// carried through every step unchanged, and the commitment to the user after the steps so far.
+/// The initial state is therefore `[com, com]` for the commitment `com` of the scanning user.
 #[derive(Clone)]
 pub struct FoldingScan<
     F: PrimeField + Absorb,
@@ -403,7 +413,7 @@ where
     }
 
     fn state_len(&self) -> usize {
-        1
+        2
     }
 
     fn external_inputs_len(&self) -> usize {
@@ -415,10 +425,12 @@ where
         // can hold a state if needed to store data to compute the next state.
         &self,
         _i: usize,
-        _z_i: Vec<F>,
+        z_i: Vec<F>,
         external_inputs: Vec<F>, // inputs that are not part of the state
     ) -> Result<Vec<F>, folding_schemes::Error> {
-        let u = User::<F, U>::from_fold_repr(&external_inputs[0..User::<F, U>::repr_len()]);
+        let mut u = User::<F, U>::from_fold_repr(&external_inputs[0..User::<F, U>::repr_len()]);
+        // The scan index is not part of the serialized user, and only tracks native bookkeeping
+        u.scan_index.get_or_insert(0);
         let priv_args = <PrivScanArgs<F, CBArgs, Crypto, CBul, 1>>::from_fold_repr(
             &external_inputs[User::<F, U>::repr_len()..],
         );
@@ -427,7 +439,7 @@ where
             self.const_args.clone(),
             priv_args,
         );
-        Ok(vec![new_user.commit::<H>()])
+        Ok(vec![z_i[0], new_user.commit::<H>()])
     }
 
     fn generate_step_constraints(
@@ -440,13 +452,184 @@ where
         external_inputs: Vec<ark_r1cs_std::fields::fp::FpVar<F>>, // inputs that are not part of the state
     ) -> Result<Vec<ark_r1cs_std::fields::fp::FpVar<F>>, ark_relations::r1cs::SynthesisError> {
         let u = User::<F, U>::from_fold_repr_zk(&external_inputs[0..User::<F, U>::repr_len()])?;
-        User::commit_in_zk::<H>(u.clone())?.enforce_equal(&z_i[0])?;
+        User::commit_in_zk::<H>(u.clone())?.enforce_equal(&z_i[1])?;
         let priv_args = <PrivScanArgs<F, CBArgs, Crypto, CBul, 1>>::from_fold_repr_zk(
             &external_inputs[User::<F, U>::repr_len()..],
         )?;
         let p = PubScanArgsVar::new_constant(cs.clone(), self.const_args.clone())?;
         let new_user =
             scan_apply_method_zk::<F, U, CBArgs, CBArgsVar, Crypto, CBul, H, 1>(&u, p, priv_args)?;
-        Ok(vec![User::commit_in_zk::<H>(new_user)?])
+        enforce_rate_limit_key_kept::<F, U>(&u.data, &new_user.data)?;
+        Ok(vec![z_i[0].clone(), User::commit_in_zk::<H>(new_user)?])
+    }
+}
+
+/// The output of a folded scan, produced by [`User::fold_scan_callbacks`].
+///
+/// This holds a single IVC proof for all scan steps, which is checked with [`verify_folded_scan`].
+/// The folding scheme itself is also kept, so the proof may be compressed further with a decider
+/// (see [`FoldedScan::compress`]).
+pub struct FoldedScan<C1, C2, FC, FS>
+where
+    C1: CurveGroup<BaseField = C2::ScalarField, ScalarField = C2::BaseField>,
+    C2: CurveGroup,
+    C2::BaseField: PrimeField,
+    FC: FCircuit<C1::ScalarField>,
+    FS: FoldingScheme<C1, C2, FC>,
+{
+    /// The folding scheme after all scan steps.
+    pub folding: FS,
+    /// The IVC proof of all scan steps.
+    pub proof: FS::IVCProof,
+    /// The commitment to the user before the scan.
+    pub old_com: Com<C1::ScalarField>,
+    /// The commitment to the user after the scan.
+    pub new_com: Com<C1::ScalarField>,
+    /// The number of callbacks scanned.
+    pub num_steps: usize,
+}
+
+impl<C1, C2, FC, FS> FoldedScan<C1, C2, FC, FS>
+where
+    C1: CurveGroup<BaseField = C2::ScalarField, ScalarField = C2::BaseField>,
+    C2: CurveGroup,
+    C2::BaseField: PrimeField,
+    FC: FCircuit<C1::ScalarField>,
+    FS: FoldingScheme<C1, C2, FC>,
+{
+    /// Compress the folded scan into a single succinct proof with a decider.
+    ///
+    /// For example, with Nova this produces a Groth16 proof of the final folded instance, which is
+    /// small enough to be verified on-chain.
+    pub fn compress<D: Decider<C1, C2, FC, FS>>(
+        self,
+        rng: &mut (impl CryptoRng + RngCore),
+        pp: D::ProverParam,
+    ) -> Result<D::Proof, folding_schemes::Error> {
+        D::prove(rng, pp, self.folding)
+    }
+}
+
+impl<F: PrimeField + Absorb, U: FoldableUserData<F>> User<F, U>
+where
+    Standard: Distribution<F>,
+{
+    /// Scan callbacks by folding, rather than producing one proof per scan.
+    ///
+    /// This folds `num_scans` single callback scan steps with the folding scheme `FS` (for
+    /// example, Nova or HyperNova), and returns a single proof that the scanned user commitment
+    /// is the result of scanning from the current user commitment. On success, the user is
+    /// updated to the scanned user.
+    ///
+    /// The public scan arguments are constants of the step circuit, so `params` must be
+    /// preprocessed from a [`FoldingScan`] constructed with the same `pub_args`. In particular,
+    /// the public membership and nonmembership data for callbacks must be the same for every
+    /// callback scanned, so should be constant (as with signature based bulletins).
+    ///
+    /// # Panics
+    ///
+    /// If `num_scans` is larger than the number of callbacks left to scan.
+    #[allow(clippy::type_complexity)]
+    pub fn fold_scan_callbacks<
+        H: FieldHash<F>,
+        CBArgs: Clone + std::fmt::Debug + PartialEq + Eq,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs, AV = CBArgsVar> + PartialEq + Eq,
+        CBul: PublicCallbackBul<F, CBArgs, Crypto> + Clone + std::fmt::Debug,
+        C1: CurveGroup<ScalarField = F, BaseField = C2::ScalarField>,
+        C2: CurveGroup<BaseField = F>,
+        FS: FoldingScheme<C1, C2, FoldingScan<F, U, CBArgs, CBArgsVar, Crypto, CBul, H>>,
+    >(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        params: &(FS::ProverParam, FS::VerifierParam),
+        pub_args: PubScanArgs<F, U, CBArgs, CBArgsVar, Crypto, CBul, 1>,
+        num_scans: usize,
+    ) -> Result<
+        FoldedScan<C1, C2, FoldingScan<F, U, CBArgs, CBArgsVar, Crypto, CBul, H>, FS>,
+        folding_schemes::Error,
+    >
+    where
+        Crypto::SigPK: FoldSer<F, Crypto::SigPKV>,
+        Crypto::EncKey: FoldSer<F, Crypto::EncKeyVar>,
+        Crypto::Ct: FoldSer<F, <Crypto::EncKey as CPACipher<F>>::CV>,
+        CBul::MembershipWitness: FoldSer<F, CBul::MembershipWitnessVar>,
+        CBul::NonMembershipWitness: FoldSer<F, CBul::NonMembershipWitnessVar>,
+        U::UserDataVar: CondSelectGadget<F> + EqGadget<F>,
+    {
+        let old_com = self.commit::<H>();
+        let circ = FoldingScan::new(pub_args.clone())?;
+        let mut folding = FS::init(params, circ, vec![old_com, old_com])?;
+
+        let mut user = self.clone();
+        for _ in 0..num_scans {
+            let (_, prs) = user.get_scan_arguments::<CBArgs, CBArgsVar, Crypto, CBul, 1>(
+                &pub_args.bulletin,
+                (pub_args.is_memb_data_const, pub_args.is_nmemb_data_const),
+                pub_args.cur_time,
+                pub_args.cb_methods.clone(),
+            );
+            folding.prove_step(
+                &mut *rng,
+                [user.to_fold_repr(), prs.to_fold_repr()].concat(),
+                None,
+            )?;
+            user = scan_method::<F, U, CBArgs, CBArgsVar, Crypto, CBul, H, 1>(
+                &user,
+                pub_args.clone(),
+                prs,
+            );
+        }
+
+        let new_com = user.commit::<H>();
+        *self = user;
+
+        Ok(FoldedScan {
+            proof: folding.ivc_proof(),
+            folding,
+            old_com,
+            new_com,
+            num_steps: num_scans,
+        })
+    }
+}
+
+/// Verify a folded scan from [`User::fold_scan_callbacks`].
+///
+/// Checks the IVC proof, and that it proves a scan from the user commitment `old_com` to the user
+/// commitment `new_com`, under the public scan arguments `pub_args`.
+#[allow(clippy::type_complexity)]
+pub fn verify_folded_scan<
+    F: PrimeField + Absorb,
+    U: FoldableUserData<F>,
+    CBArgs: Clone + std::fmt::Debug,
+    CBArgsVar: AllocVar<CBArgs, F> + Clone,
+    Crypto: AECipherSigZK<F, CBArgs, AV = CBArgsVar>,
+    CBul: PublicCallbackBul<F, CBArgs, Crypto> + Clone + std::fmt::Debug,
+    H: FieldHash<F>,
+    C1: CurveGroup<ScalarField = F, BaseField = C2::ScalarField>,
+    C2: CurveGroup<BaseField = F>,
+    FS: FoldingScheme<C1, C2, FoldingScan<F, U, CBArgs, CBArgsVar, Crypto, CBul, H>>,
+>(
+    params: (FS::ProverParam, FS::VerifierParam),
+    pub_args: PubScanArgs<F, U, CBArgs, CBArgsVar, Crypto, CBul, 1>,
+    proof: FS::IVCProof,
+    old_com: Com<F>,
+    new_com: Com<F>,
+) -> bool
+where
+    Crypto::SigPK: FoldSer<F, Crypto::SigPKV>,
+    Crypto::EncKey: FoldSer<F, Crypto::EncKeyVar>,
+    Crypto::Ct: FoldSer<F, <Crypto::EncKey as CPACipher<F>>::CV>,
+    CBul::MembershipWitness: FoldSer<F, CBul::MembershipWitnessVar>,
+    CBul::NonMembershipWitness: FoldSer<F, CBul::NonMembershipWitnessVar>,
+    U::UserDataVar: CondSelectGadget<F> + EqGadget<F>,
+{
+    if FS::verify(params.1.clone(), proof.clone()).is_err() {
+        return false;
+    }
+    match FS::from_ivc_proof(proof, pub_args, params) {
+        Ok(folding) => folding.state() == vec![old_com, new_com],
+        Err(_) => false,
     }
 }
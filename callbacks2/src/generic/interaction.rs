@@ -3,25 +3,37 @@ use crate::{
     generic::{
         bulletin::{PublicCallbackBul, PublicUserBul},
         callbacks::{add_ticket_to_hc_zk, create_defaults, CallbackCom, CallbackComVar},
-        object::{Com, ComVar, Id, Nul, NulVar, Time},
+        keystore::{circuit_hash, CircuitHash, DigestedKey},
+        object::{Com, ComVar, Id, Nul, NulVar, PrfKey, PrfKeyVar, Time, TimeVar},
+        predicates::{is_cmp_bounded, CMP_BITS},
         scan::{get_scan_interaction, PubScanArgs},
-        user::{User, UserData, UserVar},
+        user::{ExecutedMethod, ProveResult, User, UserData, UserVar},
     },
     util::ArrayVar,
 };
 use ark_crypto_primitives::sponge::Absorb;
-use ark_ff::PrimeField;
-use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, select::CondSelectGadget};
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar, select::CondSelectGadget,
+};
 use ark_relations::{
     ns,
-    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, Result as ArkResult},
+    r1cs::{
+        ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal,
+        Result as ArkResult, SynthesisError, SynthesisMode,
+    },
 };
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_snark::SNARK;
-use core::marker::PhantomData;
+use core::{cmp::Ordering, marker::PhantomData};
 use rand::{
     distributions::{Distribution, Standard},
-    CryptoRng, RngCore,
+    thread_rng, CryptoRng, RngCore,
 };
+use std::time::Duration;
+
+#[cfg(feature = "fs")]
+use crate::generic::keystore::{KeyStore, KeyStoreError};
 
 /// A predicate.
 ///
@@ -488,6 +500,350 @@ where
         aux_data: Option<PubArgs>,
         is_scan: bool,
     ) -> (Snark::ProvingKey, Snark::VerifyingKey) {
+        self.generate_keys_with_limit::<H, Snark, Crypto, Bul>(
+            rng, memb_data, aux_data, is_scan, None,
+        )
+    }
+
+    /// Compute a digest of the interaction circuit, as in [`circuit_hash`].
+    ///
+    /// The arguments are as in [`Interaction::generate_keys`], as constant bulletin data and
+    /// public arguments are part of the circuit. Keys generated with the same arguments have this
+    /// digest.
+    pub fn circuit_digest<
+        H: FieldHash<F>,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        memb_data: Option<Bul::MembershipPub>,
+        aux_data: Option<PubArgs>,
+        is_scan: bool,
+    ) -> Result<CircuitHash, SynthesisError> {
+        circuit_hash(self.keygen_circuit::<H, Crypto, Bul>(
+            &mut thread_rng(),
+            memb_data,
+            aux_data,
+            is_scan,
+            None,
+        ))
+    }
+
+    /// Profile the interaction circuit, as in [`CircuitProfile::of`].
+    ///
+    /// The arguments are as in [`Interaction::generate_keys`]. This is cheap compared to key
+    /// generation, so one may profile an interaction for a few choices of `NUMCBS` (or `NUMSCANS`
+    /// for scans) before deciding what to deploy.
+    pub fn profile<H: FieldHash<F>, Crypto: AECipherSigZK<F, CBArgs>, Bul: PublicUserBul<F, U>>(
+        &self,
+        memb_data: Option<Bul::MembershipPub>,
+        aux_data: Option<PubArgs>,
+        is_scan: bool,
+    ) -> Result<CircuitProfile, SynthesisError> {
+        CircuitProfile::of(self.keygen_circuit::<H, Crypto, Bul>(
+            &mut thread_rng(),
+            memb_data,
+            aux_data,
+            is_scan,
+            None,
+        ))
+    }
+
+    /// Generate keys for the interaction as in [`Interaction::generate_keys`], tagged with the
+    /// [`circuit_digest`](`Interaction::circuit_digest`) of the interaction.
+    ///
+    /// Interactions proven with the tagged proving key (see
+    /// [`User::interact_digested`](`crate::generic::user::User::interact_digested`)) and checked
+    /// with [`UserBul::verify_digested_interact_and_append`](`crate::generic::bulletin::UserBul::verify_digested_interact_and_append`)
+    /// report a mismatched key as [`BulError::CircuitMismatch`](`crate::generic::bulletin::BulError::CircuitMismatch`).
+    #[allow(clippy::type_complexity)]
+    pub fn generate_digested_keys<
+        H: FieldHash<F>,
+        Snark: SNARK<F>,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        memb_data: Option<Bul::MembershipPub>,
+        aux_data: Option<PubArgs>,
+        is_scan: bool,
+    ) -> (
+        DigestedKey<Snark::ProvingKey>,
+        DigestedKey<Snark::VerifyingKey>,
+    ) {
+        let digest = self
+            .circuit_digest::<H, Crypto, Bul>(memb_data.clone(), aux_data.clone(), is_scan)
+            .unwrap();
+        let (pk, vk) =
+            self.generate_keys::<H, Snark, Crypto, Bul>(rng, memb_data, aux_data, is_scan);
+        (
+            DigestedKey { digest, key: pk },
+            DigestedKey { digest, key: vk },
+        )
+    }
+
+    /// Generate keys for the interaction as in [`Interaction::generate_keys`], loading them from
+    /// a [`KeyStore`] if they were already generated for the same circuit.
+    ///
+    /// The keys are stored under `id`. If the stored keys were generated for a different circuit
+    /// (for example, the interaction or the constant bulletin data changed), they are regenerated
+    /// and overwritten.
+    #[cfg(feature = "fs")]
+    #[cfg(any(feature = "fs", doc))]
+    #[doc(cfg(feature = "fs"))]
+    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::type_complexity)]
+    pub fn generate_keys_cached<
+        H: FieldHash<F>,
+        Snark: SNARK<F>,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        store: &KeyStore,
+        id: &str,
+        rng: &mut (impl CryptoRng + RngCore),
+        memb_data: Option<Bul::MembershipPub>,
+        aux_data: Option<PubArgs>,
+        is_scan: bool,
+    ) -> Result<(Snark::ProvingKey, Snark::VerifyingKey), KeyStoreError> {
+        store.get_or_generate::<F, Snark, _>(id, rng, || {
+            self.keygen_circuit::<H, Crypto, Bul>(
+                &mut thread_rng(),
+                memb_data.clone(),
+                aux_data.clone(),
+                is_scan,
+                None,
+            )
+        })
+    }
+
+    /// Load the verifying key for the interaction from a [`KeyStore`], as stored by
+    /// [`Interaction::generate_keys_cached`].
+    ///
+    /// Returns `None` if no keys are stored under `id` for this interaction.
+    #[cfg(feature = "fs")]
+    #[cfg(any(feature = "fs", doc))]
+    #[doc(cfg(feature = "fs"))]
+    pub fn load_verifying_key<
+        H: FieldHash<F>,
+        Snark: SNARK<F>,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        store: &KeyStore,
+        id: &str,
+        memb_data: Option<Bul::MembershipPub>,
+        aux_data: Option<PubArgs>,
+        is_scan: bool,
+    ) -> Result<Option<Snark::VerifyingKey>, KeyStoreError> {
+        store.get_verifying_key::<F, Snark, _>(
+            id,
+            self.keygen_circuit::<H, Crypto, Bul>(
+                &mut thread_rng(),
+                memb_data,
+                aux_data,
+                is_scan,
+                None,
+            ),
+        )
+    }
+
+    /// Wrap the interaction with an epoch-scoped rate limit.
+    ///
+    /// A user interacting through a rate limited interaction reveals a tag `H(k, epoch, c)`, where
+    /// `k` is the rate-limit key of the user (see [`UserData::rate_limit_key`]) and `c < max` is a
+    /// counter chosen by the user. Tags are unlinkable across epochs and counters, but a user can
+    /// only produce `max` distinct tags within a single epoch. A service which rejects repeated
+    /// tags therefore caps each user to `max` interactions per epoch without learning which user
+    /// interacted.
+    ///
+    /// The user data must have a rate-limit key, which every method and scan of the service keeps
+    /// unchanged, so a user can not reset its counters by picking a new key.
+    ///
+    /// The maximum is encoded into the keys, while the epoch is a public input: the same keys may
+    /// be used for every epoch.
+    pub fn with_rate_limit(
+        self,
+        epoch: Time<F>,
+        max: u64,
+    ) -> RateLimitedInteraction<
+        F,
+        U,
+        PubArgs,
+        PubArgsVar,
+        PrivArgs,
+        PrivArgsVar,
+        CBArgs,
+        CBArgsVar,
+        NUMCBS,
+    > {
+        RateLimitedInteraction {
+            interaction: self,
+            rate_limit: RateLimit { epoch, max },
+        }
+    }
+
+    /// Assemble the public inputs to verify an executed interaction against.
+    ///
+    /// The inputs are laid out exactly as the interaction circuit allocates them: the new
+    /// commitment, old nullifier, public arguments, callback commitments, the membership data (if
+    /// it is not constant), and the rate-limit tag (if the interaction was rate limited). The
+    /// result may be passed to `Snark::verify` with the proof in `exec`.
+    ///
+    /// If the membership data is constant (and so encoded within the key), `memb_data` should be
+    /// `None`. Scans are verified the same way, with the [`PubScanArgs`] as the public arguments.
+    ///
+    /// Returns `None` if the arguments or membership data can not be converted to field elements.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use zk_callbacks::zk_object;
+    /// # use zk_callbacks::generic::user::User;
+    /// # use ark_snark::SNARK;
+    /// # use rand::thread_rng;
+    /// # use ark_bn254::{Bn254 as E, Fr};
+    /// # use ark_r1cs_std::eq::EqGadget;
+    /// # use zk_callbacks::generic::interaction::Interaction;
+    /// # use zk_callbacks::generic::interaction::Callback;
+    /// # use zk_callbacks::generic::object::Id;
+    /// # use zk_callbacks::generic::object::Time;
+    /// # use ark_relations::r1cs::SynthesisError;
+    /// # use zk_callbacks::generic::user::UserVar;
+    /// # use ark_r1cs_std::fields::fp::FpVar;
+    /// # use ark_groth16::Groth16;
+    /// # use ark_r1cs_std::prelude::Boolean;
+    /// # use zk_callbacks::impls::hash::Poseidon;
+    /// # use zk_callbacks::impls::dummy::DummyStore;
+    /// # use zk_callbacks::impls::centralized::crypto::{FakeSigPubkey, NoSigOTP};
+    /// # type Groth = Groth16<E>;
+    ///#  #[zk_object(Fr)]
+    ///#  #[derive(Default)]
+    ///#  struct Data {
+    ///#      karma: Fr,
+    ///#      is_banned: bool,
+    ///#  }
+    ///#
+    ///#  fn method<'a>(old_user: &'a User<Fr, Data>, _pub: (), _priv: ()) -> User<Fr, Data> {
+    ///#      old_user.clone()
+    ///#  }
+    ///#
+    ///#  fn predicate<'a>(old_user: &'a UserVar<Fr, Data>, new_user: &'a UserVar<Fr, Data>, _pub: (), _priv: ()) -> Result<Boolean<Fr>, SynthesisError> {
+    ///#      let o1 = old_user.data.karma.is_eq(&new_user.data.karma)?;
+    ///#      let o2 = old_user.data.is_banned.is_eq(&new_user.data.is_banned)?;
+    ///#      Ok(o1 & o2)
+    ///#  }
+    ///#
+    ///#  fn callback<'a>(old_user: &'a User<Fr, Data>, args: Fr) -> User<Fr, Data> {
+    ///#      let mut u = old_user.clone();
+    ///#      u.data.karma = args;
+    ///#      u
+    ///#  }
+    ///#
+    ///#  fn enforce_callback<'a>(old_user: &'a UserVar<Fr, Data>, args: FpVar<Fr>) -> Result<UserVar<Fr, Data>, SynthesisError> {
+    ///#      let mut u = old_user.clone();
+    ///#      u.data.karma = args;
+    ///#      Ok(u)
+    ///#  }
+    ///#
+    ///#
+    /// fn main () {
+    ///     let cb = Callback {
+    ///         method_id: Id::from(0),
+    ///         expirable: false,
+    ///         expiration: Time::from(10),
+    ///         method: callback,
+    ///         predicate: enforce_callback
+    ///     };
+    ///
+    ///     let int = Interaction {
+    ///         meth: (method, predicate),
+    ///         callbacks: [cb.clone()],
+    ///     };
+    ///
+    ///     let mut rng = thread_rng();
+    ///     let (pk, vk) = int.generate_keys::<Poseidon<2>, Groth, NoSigOTP<Fr>, DummyStore>(&mut rng, Some(()), None, false);
+    ///
+    ///     let mut u = User::create(Data::default(), &mut rng);
+    ///     let exec = u.interact::<Poseidon<2>, (), (), (), (), Fr, FpVar<Fr>, NoSigOTP<Fr>, Groth, DummyStore, 1>(&mut rng, int.clone(), [FakeSigPubkey::pk()], Time::from(0), ((), ()), true, &pk, (), (), false).unwrap();
+    ///
+    ///     let pub_inputs = int.prepare_public_inputs::<Groth, NoSigOTP<Fr>, DummyStore>(&exec, &(), None).unwrap();
+    ///     assert!(Groth::verify(&vk, &pub_inputs, &exec.proof).unwrap());
+    /// }
+    /// ```
+    pub fn prepare_public_inputs<
+        Snark: SNARK<F>,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        exec: &ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>,
+        pub_args: &PubArgs,
+        memb_data: Option<&Bul::MembershipPub>,
+    ) -> Option<Vec<F>>
+    where
+        PubArgs: ToConstraintField<F>,
+    {
+        interaction_public_inputs(
+            exec.new_object,
+            exec.old_nullifier,
+            pub_args,
+            &exec.cb_com_list,
+            memb_data,
+            exec.rate_limit_tag.as_ref(),
+        )
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "generate_keys", skip_all, fields(callbacks = NUMCBS, scan = is_scan))
+    )]
+    pub(crate) fn generate_keys_with_limit<
+        H: FieldHash<F>,
+        Snark: SNARK<F>,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        memb_data: Option<Bul::MembershipPub>,
+        aux_data: Option<PubArgs>,
+        is_scan: bool,
+        rate_limit: Option<RateLimit<F>>,
+    ) -> (Snark::ProvingKey, Snark::VerifyingKey) {
+        let out =
+            self.keygen_circuit::<H, Crypto, Bul>(rng, memb_data, aux_data, is_scan, rate_limit);
+        Snark::circuit_specific_setup(out, rng).unwrap()
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn keygen_circuit<
+        H: FieldHash<F>,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        memb_data: Option<Bul::MembershipPub>,
+        aux_data: Option<PubArgs>,
+        is_scan: bool,
+        rate_limit: Option<RateLimit<F>>,
+    ) -> ExecMethodCircuit<
+        F,
+        H,
+        U,
+        PubArgs,
+        PubArgsVar,
+        PrivArgs,
+        PrivArgsVar,
+        CBArgs,
+        CBArgsVar,
+        Crypto,
+        Bul,
+        NUMCBS,
+    > {
         let u = User::create(U::default(), rng);
 
         let cbs: [CallbackCom<F, CBArgs, Crypto>; NUMCBS] =
@@ -495,20 +851,7 @@ where
 
         let x = (*self).clone();
 
-        let out: ExecMethodCircuit<
-            F,
-            H,
-            U,
-            PubArgs,
-            PubArgsVar,
-            PrivArgs,
-            PrivArgsVar,
-            CBArgs,
-            CBArgsVar,
-            Crypto,
-            Bul,
-            NUMCBS,
-        > = ExecMethodCircuit {
+        ExecMethodCircuit {
             priv_old_user: u.clone(),
             priv_new_user: u.clone(),
             priv_issued_callbacks: cbs.clone(),
@@ -523,10 +866,385 @@ where
             is_scan,
             bul_memb_is_const: memb_data.is_some(),
             pub_bul_membership_data: memb_data.unwrap_or_default(),
+            priv_rate_limit_counter: 0,
+            pub_rate_limit_tag: rate_limit
+                .map(|r| r.tag::<H>(u.data.rate_limit_key().unwrap_or_default(), 0))
+                .unwrap_or_default(),
+            rate_limit,
             _phantom_hash: PhantomData,
-        };
+        }
+    }
+}
 
-        Snark::circuit_specific_setup(out, rng).unwrap()
+/// The size of a circuit, along with rough estimates of the cost of a Groth16 proof.
+///
+/// The counts are exact. The estimates assume a single-threaded Groth16 prover over a pairing
+/// curve whose base field is about 1.5 times the size of the scalar field (as for BN254 and
+/// BLS12-381), and should only be used to compare parameter choices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitProfile {
+    /// The number of constraints.
+    pub constraints: usize,
+    /// The number of witness (private) variables.
+    pub witness_variables: usize,
+    /// The number of public inputs, not including the constant `1`.
+    pub public_inputs: usize,
+    /// The number of nonzero entries in the R1CS matrices.
+    pub nonzero_entries: usize,
+    /// The estimated time to produce a proof.
+    pub estimated_proving_time: Duration,
+    /// The estimated peak memory of the prover in bytes, including the proving key.
+    pub estimated_peak_memory: usize,
+}
+
+/// The estimated time taken per base in a multi-scalar multiplication.
+const PROVING_NS_PER_BASE: u64 = 10_000;
+
+impl CircuitProfile {
+    /// Profile a circuit.
+    ///
+    /// The circuit is synthesized in setup mode, so no witness is needed.
+    pub fn of<F: PrimeField, C: ConstraintSynthesizer<F>>(
+        circuit: C,
+    ) -> Result<Self, SynthesisError> {
+        let cs = ConstraintSystem::<F>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        cs.set_mode(SynthesisMode::Setup);
+        circuit.generate_constraints(cs.clone())?;
+        cs.finalize();
+        let m = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+
+        let vars = m.num_instance_variables + m.num_witness_variables;
+        let nonzero = m.a_num_non_zero + m.b_num_non_zero + m.c_num_non_zero;
+        let domain = (m.num_constraints + m.num_instance_variables).next_power_of_two();
+        let scalar = F::MODULUS_BIT_SIZE.div_ceil(64) as usize * 8;
+        let point = 3 * scalar;
+
+        // The prover does MSMs over the A, B (in both groups), and L queries of the proving key,
+        // and one over the H query of the size of the evaluation domain.
+        let bases = 4 * vars + domain;
+        let key_bytes = bases * point;
+        let matrix_bytes = nonzero * (scalar + std::mem::size_of::<usize>());
+        let qap_bytes = 7 * domain * scalar;
+
+        Ok(Self {
+            constraints: m.num_constraints,
+            witness_variables: m.num_witness_variables,
+            public_inputs: m.num_instance_variables - 1,
+            nonzero_entries: nonzero,
+            estimated_proving_time: Duration::from_nanos(bases as u64 * PROVING_NS_PER_BASE),
+            estimated_peak_memory: key_bytes + matrix_bytes + qap_bytes + vars * scalar,
+        })
+    }
+}
+
+impl std::fmt::Display for CircuitProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} constraints, {} witness variables, {} public inputs (~{:.1?} to prove, ~{} MiB)",
+            self.constraints,
+            self.witness_variables,
+            self.public_inputs,
+            self.estimated_proving_time,
+            self.estimated_peak_memory >> 20,
+        )
+    }
+}
+
+/// An error when registering an interaction in an [`InteractionRegistry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InteractionRegistryError {
+    /// Another interaction is registered with this id.
+    DuplicateId(u64),
+    /// Another interaction is registered with this name.
+    DuplicateName(String),
+    /// No interaction is registered with this id.
+    UnknownId(u64),
+}
+
+impl std::fmt::Display for InteractionRegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InteractionRegistryError::DuplicateId(id) => {
+                write!(f, "interaction id {} is already registered", id)
+            }
+            InteractionRegistryError::DuplicateName(name) => {
+                write!(f, "interaction {} is already registered", name)
+            }
+            InteractionRegistryError::UnknownId(id) => write!(f, "unknown interaction id {}", id),
+        }
+    }
+}
+
+impl std::error::Error for InteractionRegistryError {}
+
+/// An interaction or callback in an [`InteractionRegistry`].
+#[derive(Clone, Debug)]
+pub struct RegisteredInteraction<VK> {
+    /// A human readable name, also used as the key name in a key store.
+    pub name: String,
+    /// The verifying key for the interaction, once it has been generated.
+    pub verifying_key: Option<VK>,
+}
+
+/// A catalog of interactions and callbacks, each with a stable numeric id and a name.
+///
+/// Services identify interactions by id when storing them (see
+/// [`ServiceProvider::store_interaction`](`super::service::ServiceProvider::store_interaction`)),
+/// and callbacks by their method id in-circuit. Registering every interaction in one place keeps
+/// these ids consistent between the client and server, and lets the server look up the verifying
+/// key for an interaction by its id.
+///
+/// # Example
+///
+/// ```rust
+/// # use ark_bn254::{Bn254, Fr};
+/// # use ark_groth16::VerifyingKey;
+/// # use zk_callbacks::generic::interaction::InteractionRegistry;
+/// let mut registry = InteractionRegistry::<VerifyingKey<Bn254>>::new();
+/// registry.register(1, "post").unwrap();
+/// registry.register(2, "scan").unwrap();
+/// assert!(registry.register(2, "reply").is_err());
+///
+/// assert_eq!(registry.id("scan"), Some(2));
+/// assert_eq!(registry.method_id::<Fr>(1), Fr::from(1));
+/// assert!(registry.verifying_key(1).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct InteractionRegistry<VK> {
+    entries: std::collections::BTreeMap<u64, RegisteredInteraction<VK>>,
+}
+
+impl<VK> Default for InteractionRegistry<VK> {
+    fn default() -> Self {
+        Self {
+            entries: std::collections::BTreeMap::new(),
+        }
+    }
+}
+
+impl<VK> InteractionRegistry<VK> {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an interaction or callback with an id and a name, both of which must be unique.
+    pub fn register(&mut self, id: u64, name: &str) -> Result<(), InteractionRegistryError> {
+        if self.entries.contains_key(&id) {
+            return Err(InteractionRegistryError::DuplicateId(id));
+        }
+        if self.id(name).is_some() {
+            return Err(InteractionRegistryError::DuplicateName(name.to_string()));
+        }
+        self.entries.insert(
+            id,
+            RegisteredInteraction {
+                name: name.to_string(),
+                verifying_key: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Get the id of an interaction by name.
+    pub fn id(&self, name: &str) -> Option<u64> {
+        self.entries
+            .iter()
+            .find(|(_, e)| e.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Get the name of an interaction, which is also the name of its keys in a
+    /// [`KeyStore`](`super::keystore::KeyStore`).
+    pub fn name(&self, id: u64) -> Option<&str> {
+        self.entries.get(&id).map(|e| e.name.as_str())
+    }
+
+    /// The in-circuit method id for a callback with id `id`.
+    pub fn method_id<F: PrimeField>(&self, id: u64) -> Id<F> {
+        F::from(id)
+    }
+
+    /// Set the verifying key for an interaction.
+    pub fn set_verifying_key(&mut self, id: u64, vk: VK) -> Result<(), InteractionRegistryError> {
+        let entry = self
+            .entries
+            .get_mut(&id)
+            .ok_or(InteractionRegistryError::UnknownId(id))?;
+        entry.verifying_key = Some(vk);
+        Ok(())
+    }
+
+    /// Get the verifying key for an interaction, if it has been set.
+    pub fn verifying_key(&self, id: u64) -> Option<&VK> {
+        self.entries.get(&id).and_then(|e| e.verifying_key.as_ref())
+    }
+
+    /// Iterate over the registered interactions, in order of id.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &RegisteredInteraction<VK>)> {
+        self.entries.iter().map(|(id, e)| (*id, e))
+    }
+}
+
+/// An epoch-scoped rate limit on an interaction.
+///
+/// Within an epoch, a user may interact at most `max` times. See
+/// [`Interaction::with_rate_limit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RateLimit<F: PrimeField> {
+    /// The current epoch.
+    pub epoch: Time<F>,
+    /// The maximum number of interactions allowed per epoch.
+    pub max: u64,
+}
+
+impl<F: PrimeField> RateLimit<F> {
+    /// Compute the rate-limit tag `H(k, epoch, counter)` for a user secret `k`.
+    pub fn tag<H: FieldHash<F>>(&self, prf_key: PrfKey<F>, counter: u64) -> F {
+        H::hash(&[prf_key, self.epoch, F::from(counter)])
+    }
+
+    /// Compute the rate-limit tag in-circuit.
+    pub fn tag_in_zk<H: FieldHash<F>>(
+        prf_key: PrfKeyVar<F>,
+        epoch: TimeVar<F>,
+        counter: FpVar<F>,
+    ) -> ArkResult<FpVar<F>> {
+        H::hash_in_zk(&[prf_key, epoch, counter])
+    }
+}
+
+// The public inputs of the interaction circuit, in allocation order.
+pub(crate) fn interaction_public_inputs<
+    F: PrimeField,
+    PubArgs: ToConstraintField<F>,
+    MembPub: ToConstraintField<F>,
+    const NUMCBS: usize,
+>(
+    new_object: Com<F>,
+    old_nul: Nul<F>,
+    pub_args: &PubArgs,
+    cb_com_list: &[Com<F>; NUMCBS],
+    memb_data: Option<&MembPub>,
+    rate_limit_tag: Option<&RateLimitTag<F>>,
+) -> Option<Vec<F>> {
+    let mut pub_inputs = vec![new_object, old_nul];
+    pub_inputs.extend(pub_args.to_field_elements()?);
+    pub_inputs.extend(cb_com_list.to_field_elements()?);
+    if let Some(a) = memb_data {
+        pub_inputs.extend(a.to_field_elements()?);
+    }
+    if let Some(t) = rate_limit_tag {
+        pub_inputs.push(t.epoch);
+        pub_inputs.push(t.tag);
+    }
+    Some(pub_inputs)
+}
+
+/// Enforce that a method or scan keeps the rate-limit key of the user (see
+/// [`UserData::rate_limit_key`]), returning the key if the user has one.
+pub(crate) fn enforce_rate_limit_key_kept<F: PrimeField + Absorb, U: UserData<F>>(
+    old_data: &U::UserDataVar,
+    new_data: &U::UserDataVar,
+) -> ArkResult<Option<PrfKeyVar<F>>> {
+    let Some(prf_key) = U::rate_limit_key_in_zk(old_data)? else {
+        return Ok(None);
+    };
+    let new_key = U::rate_limit_key_in_zk(new_data)?.ok_or(SynthesisError::Unsatisfiable)?;
+    prf_key.enforce_equal(&new_key)?;
+    Ok(Some(prf_key))
+}
+
+/// The rate-limit tag revealed by a rate limited interaction.
+///
+/// A service should keep track of every tag received within an epoch, and reject any interaction
+/// which reuses a tag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RateLimitTag<F: PrimeField> {
+    /// The epoch the tag was produced for.
+    pub epoch: Time<F>,
+    /// The tag.
+    pub tag: F,
+}
+
+/// An interaction along with an epoch-scoped rate limit.
+///
+/// This is produced by [`Interaction::with_rate_limit`], and is executed through
+/// [`User::interact_rate_limited`].
+#[derive(Clone)]
+pub struct RateLimitedInteraction<
+    F: PrimeField + Absorb,
+    U: UserData<F>,
+    PubArgs: Clone,
+    PubArgsVar: AllocVar<PubArgs, F>,
+    PrivArgs: Clone,
+    PrivArgsVar: AllocVar<PrivArgs, F>,
+    CBArgs: Clone,
+    CBArgsVar: AllocVar<CBArgs, F>,
+    const NUMCBS: usize,
+> {
+    /// The underlying interaction.
+    pub interaction:
+        Interaction<F, U, PubArgs, PubArgsVar, PrivArgs, PrivArgsVar, CBArgs, CBArgsVar, NUMCBS>,
+    /// The rate limit.
+    pub rate_limit: RateLimit<F>,
+}
+
+impl<
+        F: PrimeField + Absorb,
+        U: UserData<F> + Default,
+        PubArgs: Clone + Default + std::fmt::Debug,
+        PubArgsVar: AllocVar<PubArgs, F> + Clone,
+        PrivArgs: Clone + Default + std::fmt::Debug,
+        PrivArgsVar: AllocVar<PrivArgs, F> + Clone,
+        CBArgs: Clone + Default + std::fmt::Debug,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        const NUMCBS: usize,
+    >
+    RateLimitedInteraction<
+        F,
+        U,
+        PubArgs,
+        PubArgsVar,
+        PrivArgs,
+        PrivArgsVar,
+        CBArgs,
+        CBArgsVar,
+        NUMCBS,
+    >
+where
+    Standard: Distribution<F>,
+{
+    /// Generate proving and verification keys for a rate limited interaction.
+    ///
+    /// See [`Interaction::generate_keys`]. The maximum number of interactions per epoch is encoded
+    /// in the keys, so keys must be regenerated if the maximum changes.
+    pub fn generate_keys<
+        H: FieldHash<F>,
+        Snark: SNARK<F>,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Bul: PublicUserBul<F, U>,
+    >(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        memb_data: Option<Bul::MembershipPub>,
+        aux_data: Option<PubArgs>,
+    ) -> (Snark::ProvingKey, Snark::VerifyingKey) {
+        self.interaction
+            .generate_keys_with_limit::<H, Snark, Crypto, Bul>(
+                rng,
+                memb_data,
+                aux_data,
+                false,
+                Some(self.rate_limit),
+            )
+    }
+
+    /// Move the interaction to a new epoch. The keys remain valid.
+    pub fn set_epoch(&mut self, epoch: Time<F>) {
+        self.rate_limit.epoch = epoch;
     }
 }
 
@@ -570,6 +1288,12 @@ pub struct ExecMethodCircuit<
     pub pub_bul_membership_data: Bul::MembershipPub,
     /// If the public membership data is constant.
     pub bul_memb_is_const: bool,
+    /// The counter used to derive the rate-limit tag. Must be less than the maximum.
+    pub priv_rate_limit_counter: u64,
+    /// The rate-limit tag, if the interaction is rate limited.
+    pub pub_rate_limit_tag: F,
+    /// The rate limit on the interaction, if any.
+    pub rate_limit: Option<RateLimit<F>>,
 
     /// The method.
     pub associated_method:
@@ -700,6 +1424,32 @@ impl<
                 .enforce_equal(&old_zk_fields.is_ingest_over)?;
         }
 
+        // Enforce the rate-limit key is kept, whether or not this interaction is rate limited
+        let prf_key = enforce_rate_limit_key_kept::<F, U>(&old_user_var.data, &new_user_var.data)?;
+
+        // Enforce the tag is well-formed with counter < max
+        if let Some(rate_limit) = self.rate_limit {
+            let prf_key = prf_key.ok_or(SynthesisError::Unsatisfiable)?;
+            let epoch_var =
+                TimeVar::new_input(ns!(cs, "rate_limit_epoch"), || Ok(rate_limit.epoch))?;
+            let tag_var =
+                FpVar::new_input(ns!(cs, "rate_limit_tag"), || Ok(self.pub_rate_limit_tag))?;
+            let counter_var = FpVar::new_witness(ns!(cs, "rate_limit_counter"), || {
+                Ok(F::from(self.priv_rate_limit_counter))
+            })?;
+
+            is_cmp_bounded(
+                &counter_var,
+                &FpVar::Constant(F::from(rate_limit.max)),
+                Ordering::Less,
+                false,
+                CMP_BITS,
+            )?
+            .enforce_equal(&Boolean::TRUE)?;
+
+            tag_var.enforce_equal(&RateLimit::tag_in_zk::<H>(prf_key, epoch_var, counter_var)?)?;
+        }
+
         // Enforce that Com(new_user) == new_com
         let com = User::commit_in_zk::<H>(new_user_var)?;
 
@@ -752,6 +1502,9 @@ impl<
             pub_args: self.pub_args.clone(),
             pub_bul_membership_data: self.pub_bul_membership_data.clone(),
             bul_memb_is_const: self.bul_memb_is_const,
+            priv_rate_limit_counter: self.priv_rate_limit_counter,
+            pub_rate_limit_tag: self.pub_rate_limit_tag,
+            rate_limit: self.rate_limit,
 
             is_scan: self.is_scan,
             associated_method: self.associated_method.clone(),
@@ -815,6 +1568,10 @@ impl<
 ///     let (pk, vk) = generate_keys_for_statement::<Fr, Poseidon<2>, Data, _, _, _, _, Groth>(&mut rng, predicate, None);
 /// }
 /// ```
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "generate_keys", skip_all, fields(statement = true))
+)]
 pub fn generate_keys_for_statement<
     F: PrimeField + Absorb,
     H: FieldHash<F>,
@@ -954,6 +1711,10 @@ impl<
 ///     let (pk, vk) = generate_keys_for_statement_in::<Fr, Poseidon<2>, Data, _, _, _, _, Groth, UOVObjStore<Fr>>(&mut rng, predicate, Some(obj_store.get_pubkey()), None);
 /// }
 /// ```
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "generate_keys", skip_all, fields(statement = true))
+)]
 pub fn generate_keys_for_statement_in<
     F: PrimeField + Absorb,
     H: FieldHash<F>,
@@ -971,23 +1732,126 @@ pub fn generate_keys_for_statement_in<
 
     aux_data: Option<PubArgs>,
 ) -> (Snark::ProvingKey, Snark::VerifyingKey)
+where
+    Standard: Distribution<F>,
+{
+    let out = statement_in_circuit::<F, H, U, PubArgs, PubArgsVar, PrivArgs, PrivArgsVar, Bul>(
+        rng, pred, memb_data, aux_data,
+    );
+    Snark::circuit_specific_setup(out, rng).unwrap()
+}
+
+/// Assemble the public inputs to verify a statement proven with
+/// [`User::prove_statement`](`crate::generic::user::User::prove_statement`).
+///
+/// The inputs are the user commitment followed by the public arguments, as allocated by the
+/// statement circuit. Returns `None` if the arguments can not be converted to field elements.
+pub fn statement_public_inputs<
+    F: PrimeField + Absorb,
+    Snark: SNARK<F>,
+    PubArgs: ToConstraintField<F>,
+>(
+    result: &ProveResult<F, Snark>,
+    pub_args: &PubArgs,
+) -> Option<Vec<F>> {
+    let mut pub_inputs = vec![result.object];
+    pub_inputs.extend(pub_args.to_field_elements()?);
+    Some(pub_inputs)
+}
+
+/// Assemble the public inputs to verify a statement proven with
+/// [`User::prove_statement_and_in`](`crate::generic::user::User::prove_statement_and_in`).
+///
+/// The inputs are the public arguments, followed by the membership data if it is not constant.
+/// As in [`generate_keys_for_statement_in`], constant membership data is encoded within the key,
+/// so `memb_data` should then be `None`. Returns `None` if the arguments or membership data can
+/// not be converted to field elements.
+pub fn statement_in_public_inputs<
+    F: PrimeField + Absorb,
+    U: UserData<F>,
+    PubArgs: ToConstraintField<F>,
+    Bul: PublicUserBul<F, U>,
+>(
+    pub_args: &PubArgs,
+    memb_data: Option<&Bul::MembershipPub>,
+) -> Option<Vec<F>> {
+    let mut pub_inputs = pub_args.to_field_elements()?;
+    if let Some(a) = memb_data {
+        pub_inputs.extend(a.to_field_elements()?);
+    }
+    Some(pub_inputs)
+}
+
+/// Generate keys for a statement with membership as in [`generate_keys_for_statement_in`],
+/// loading them from a [`KeyStore`] if they were already generated for the same circuit.
+///
+/// See [`Interaction::generate_keys_cached`].
+#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", doc))]
+#[doc(cfg(feature = "fs"))]
+#[allow(clippy::type_complexity)]
+pub fn generate_keys_for_statement_in_cached<
+    F: PrimeField + Absorb,
+    H: FieldHash<F>,
+    U: UserData<F> + Default,
+    PubArgs: Clone + Default,
+    PubArgsVar: AllocVar<PubArgs, F>,
+    PrivArgs: Clone + Default,
+    PrivArgsVar: AllocVar<PrivArgs, F>,
+    Snark: SNARK<F>,
+    Bul: PublicUserBul<F, U>,
+>(
+    store: &KeyStore,
+    id: &str,
+    rng: &mut (impl CryptoRng + RngCore),
+    pred: SingularPredicate<F, UserVar<F, U>, ComVar<F>, PubArgsVar, PrivArgsVar>,
+    memb_data: Option<Bul::MembershipPub>,
+    aux_data: Option<PubArgs>,
+) -> Result<(Snark::ProvingKey, Snark::VerifyingKey), KeyStoreError>
+where
+    Standard: Distribution<F>,
+{
+    store.get_or_generate::<F, Snark, _>(id, rng, || {
+        statement_in_circuit::<F, H, U, PubArgs, PubArgsVar, PrivArgs, PrivArgsVar, Bul>(
+            &mut thread_rng(),
+            pred,
+            memb_data.clone(),
+            aux_data.clone(),
+        )
+    })
+}
+
+#[allow(clippy::type_complexity)]
+fn statement_in_circuit<
+    F: PrimeField + Absorb,
+    H: FieldHash<F>,
+    U: UserData<F> + Default,
+    PubArgs: Clone + Default,
+    PubArgsVar: AllocVar<PubArgs, F>,
+    PrivArgs: Clone + Default,
+    PrivArgsVar: AllocVar<PrivArgs, F>,
+    Bul: PublicUserBul<F, U>,
+>(
+    rng: &mut (impl CryptoRng + RngCore),
+    pred: SingularPredicate<F, UserVar<F, U>, ComVar<F>, PubArgsVar, PrivArgsVar>,
+    memb_data: Option<Bul::MembershipPub>,
+    aux_data: Option<PubArgs>,
+) -> ProvePredInCircuit<F, H, U, PubArgs, PubArgsVar, PrivArgs, PrivArgsVar, Bul>
 where
     Standard: Distribution<F>,
 {
     let u = User::create(U::default(), rng);
-    let out: ProvePredInCircuit<F, H, U, PubArgs, PubArgsVar, PrivArgs, PrivArgsVar, Bul> =
-        ProvePredInCircuit {
-            priv_user: u.clone(),
-            priv_extra_membership_data: Bul::MembershipWitness::default(),
-            pub_args: aux_data.unwrap_or_default(),
-            priv_args: PrivArgs::default(),
-            bul_memb_is_const: memb_data.is_some(),
-            pub_extra_membership_data: memb_data.unwrap_or_default(),
-            associated_method: pred,
+    ProvePredInCircuit {
+        priv_user: u.clone(),
+        priv_extra_membership_data: Bul::MembershipWitness::default(),
+        pub_args: aux_data.unwrap_or_default(),
+        priv_args: PrivArgs::default(),
+        bul_memb_is_const: memb_data.is_some(),
+        pub_extra_membership_data: memb_data.unwrap_or_default(),
+        associated_method: pred,
 
-            _phantom_hash: PhantomData,
-        };
-    Snark::circuit_specific_setup(out, rng).unwrap()
+        _phantom_hash: PhantomData,
+    }
 }
 
 /// The circuit used to generating proofs of some predicate and membership. This is not necessary for use with the base system.
@@ -1240,3 +2104,197 @@ where
     get_scan_interaction::<_, _, _, _, _, _, H, NUMSCANS>()
         .generate_keys::<H, Snark, Crypto, Bul>(rng, memb_data, aux_data, true)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        generic::object::{Ser, SerVar},
+        impls::{centralized::crypto::NoSigOTP, dummy::DummyStore, hash::Poseidon},
+    };
+    use ark_bn254::{Bn254, Fr};
+    use ark_groth16::Groth16;
+    use ark_r1cs_std::alloc::AllocationMode;
+    use ark_relations::r1cs::Namespace;
+    use std::borrow::Borrow;
+
+    type H = Poseidon<2>;
+
+    // The user data is a single field element, which is the rate-limit key
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+    struct Keyed(Fr);
+
+    impl AllocVar<Keyed, Fr> for FpVar<Fr> {
+        fn new_variable<T: Borrow<Keyed>>(
+            cs: impl Into<Namespace<Fr>>,
+            f: impl FnOnce() -> Result<T, SynthesisError>,
+            mode: AllocationMode,
+        ) -> ArkResult<Self> {
+            FpVar::new_variable(cs, || f().map(|k| k.borrow().0), mode)
+        }
+    }
+
+    impl UserData<Fr> for Keyed {
+        type UserDataVar = FpVar<Fr>;
+
+        fn serialize_elements(&self) -> Vec<Ser<Fr>> {
+            vec![self.0]
+        }
+
+        fn serialize_in_zk(user_var: FpVar<Fr>) -> ArkResult<Vec<SerVar<Fr>>> {
+            Ok(vec![user_var])
+        }
+
+        fn rate_limit_key(&self) -> Option<PrfKey<Fr>> {
+            Some(self.0)
+        }
+
+        fn rate_limit_key_in_zk(user_var: &FpVar<Fr>) -> ArkResult<Option<PrfKeyVar<Fr>>> {
+            Ok(Some(user_var.clone()))
+        }
+    }
+
+    type Plain = Interaction<Fr, Keyed, (), (), (), (), Fr, FpVar<Fr>, 0>;
+    type RateLimited = RateLimitedInteraction<Fr, Keyed, (), (), (), (), Fr, FpVar<Fr>, 0>;
+
+    fn method(old_user: &User<Fr, Keyed>, _pub: (), _priv: ()) -> User<Fr, Keyed> {
+        old_user.clone()
+    }
+
+    fn predicate(
+        _old_user: &UserVar<Fr, Keyed>,
+        _new_user: &UserVar<Fr, Keyed>,
+        _pub: (),
+        _priv: (),
+    ) -> ArkResult<Boolean<Fr>> {
+        Ok(Boolean::TRUE)
+    }
+
+    fn plain() -> Plain {
+        Interaction {
+            meth: (method, predicate),
+            callbacks: [],
+        }
+    }
+
+    fn rate_limited(max: u64) -> RateLimited {
+        plain().with_rate_limit(Fr::from(7), max)
+    }
+
+    // Checks whether the interaction circuit is satisfied for a user with a given counter
+    fn satisfied(int: &RateLimited, counter: u64, new_key: Option<Fr>) -> bool {
+        satisfied_with(&int.interaction, Some(int.rate_limit), counter, new_key)
+    }
+
+    fn satisfied_with(
+        int: &Plain,
+        rate_limit: Option<RateLimit<Fr>>,
+        counter: u64,
+        new_key: Option<Fr>,
+    ) -> bool {
+        let mut rng = thread_rng();
+        let mut circ = int.keygen_circuit::<H, NoSigOTP<Fr>, DummyStore>(
+            &mut rng,
+            Some(()),
+            None,
+            false,
+            rate_limit,
+        );
+        circ.priv_old_user.data = Keyed(Fr::from(1234));
+        circ.priv_new_user.data = Keyed(new_key.unwrap_or(Fr::from(1234)));
+        circ.pub_new_com = circ.priv_new_user.commit::<H>();
+        circ.priv_rate_limit_counter = counter;
+        circ.pub_rate_limit_tag = rate_limit
+            .map(|r| r.tag::<H>(Fr::from(1234), counter))
+            .unwrap_or_default();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circ.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    // Tests that the circuit accepts every counter below the maximum
+    #[test]
+    fn rate_limit_under_max() {
+        let int = rate_limited(3);
+        for counter in 0..3 {
+            assert!(satisfied(&int, counter, None));
+        }
+    }
+
+    // Tests that the circuit rejects counters at or past the maximum, including ones which wrap
+    // around the field
+    #[test]
+    fn rate_limit_past_max() {
+        let int = rate_limited(3);
+        assert!(!satisfied(&int, 3, None));
+        assert!(!satisfied(&int, 4, None));
+        assert!(!satisfied(&int, u64::MAX, None));
+    }
+
+    // Tests that the rate-limit key can not be changed, by a rate limited interaction or any other
+    #[test]
+    fn rate_limit_key_kept() {
+        let int = rate_limited(3);
+        assert!(!satisfied(&int, 0, Some(Fr::from(4321))));
+
+        assert!(satisfied_with(&plain(), None, 0, None));
+        assert!(!satisfied_with(&plain(), None, 0, Some(Fr::from(4321))));
+    }
+
+    // Tests that rate limited interactions prove and verify, revealing distinct tags per counter
+    #[test]
+    fn rate_limit_prove() {
+        let mut rng = thread_rng();
+        let int = rate_limited(2);
+        let (pk, vk) = int.generate_keys::<H, Groth16<Bn254>, NoSigOTP<Fr>, DummyStore>(
+            &mut rng,
+            Some(()),
+            None,
+        );
+
+        let mut u = User::create(Keyed(Fr::from(1234)), &mut rng);
+        let mut tags = vec![];
+        for counter in 0..2 {
+            let exec = u
+                .interact_rate_limited::<H, (), (), (), (), Fr, FpVar<Fr>, NoSigOTP<Fr>, Groth16<Bn254>, DummyStore, 0>(
+                    &mut rng,
+                    int.clone(),
+                    counter,
+                    [],
+                    Fr::from(0),
+                    ((), ()),
+                    true,
+                    &pk,
+                    (),
+                    (),
+                )
+                .unwrap();
+            let tag = exec.rate_limit_tag.unwrap();
+            assert_eq!(tag.tag, int.rate_limit.tag::<H>(Fr::from(1234), counter));
+
+            let pub_inputs = int
+                .interaction
+                .prepare_public_inputs::<Groth16<Bn254>, NoSigOTP<Fr>, DummyStore>(&exec, &(), None)
+                .unwrap();
+            assert!(Groth16::<Bn254>::verify(&vk, &pub_inputs, &exec.proof).unwrap());
+            tags.push(tag.tag);
+        }
+        assert_ne!(tags[0], tags[1]);
+
+        assert!(u
+            .interact_rate_limited::<H, (), (), (), (), Fr, FpVar<Fr>, NoSigOTP<Fr>, Groth16<Bn254>, DummyStore, 0>(
+                &mut rng,
+                int.clone(),
+                2,
+                [],
+                Fr::from(0),
+                ((), ()),
+                true,
+                &pk,
+                (),
+                (),
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,560 @@
+use ark_ff::{BigInteger, PrimeField};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode,
+};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate,
+};
+use blake2::{Blake2s256 as Blake, Digest};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+#[cfg(any(feature = "fs", feature = "mmap"))]
+use std::{fs, path::Path};
+
+#[cfg(feature = "fs")]
+use ark_snark::SNARK;
+#[cfg(feature = "fs")]
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "fs")]
+use std::{
+    io::{Seek, SeekFrom},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+/// A hash of the shape of a circuit.
+pub type CircuitHash = [u8; 32];
+
+/// Compute the hash of the R1CS matrices of a circuit.
+///
+/// The circuit is synthesized in setup mode, so no witness is needed. Any constants baked into the
+/// circuit (for example, constant bulletin public keys or public arguments) appear as
+/// coefficients of the matrices, so two circuits have the same hash exactly when keys for one may
+/// be used for the other.
+pub fn circuit_hash<F: PrimeField, C: ConstraintSynthesizer<F>>(
+    circuit: C,
+) -> Result<CircuitHash, SynthesisError> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    let m = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+
+    let mut h = Blake::new();
+    h.update((m.num_instance_variables as u64).to_le_bytes());
+    h.update((m.num_witness_variables as u64).to_le_bytes());
+    h.update((m.num_constraints as u64).to_le_bytes());
+    for mat in [&m.a, &m.b, &m.c] {
+        for row in mat {
+            h.update((row.len() as u64).to_le_bytes());
+            for (coeff, ind) in row {
+                h.update(coeff.into_bigint().to_bytes_le());
+                h.update((*ind as u64).to_le_bytes());
+            }
+        }
+    }
+    Ok(h.finalize().into())
+}
+
+/// A key tagged with the [`circuit_hash`] of the circuit it was generated for.
+///
+/// Keys are serialized along with their digest, so a proof made for one circuit and checked
+/// against the key of another is reported as a mismatch, rather than as an invalid proof.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct DigestedKey<K: CanonicalSerialize + CanonicalDeserialize> {
+    /// The hash of the circuit the key was generated for.
+    pub digest: CircuitHash,
+    /// The key.
+    pub key: K,
+}
+
+/// How a key is encoded by [`write_key`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum KeyEncoding {
+    /// The uncompressed canonical serialization of the key.
+    #[default]
+    Raw,
+    /// The uncompressed serialization, compressed with zstd at the given level.
+    ///
+    /// Proving keys consist of uncompressed curve points, which zstd typically shrinks by a
+    /// third. Unlike `Compress::Yes`, loading does not need to decompress every point.
+    #[cfg(feature = "zstd")]
+    #[cfg(any(feature = "zstd", doc))]
+    #[doc(cfg(feature = "zstd"))]
+    Zstd(i32),
+}
+
+/// The magic number starting every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The size of the chunks keys are written and read in.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Hashes everything read or written through it.
+struct Hashing<T> {
+    inner: T,
+    hasher: Blake,
+}
+
+impl<T> Hashing<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            hasher: Blake::new(),
+        }
+    }
+
+    fn finish(self) -> [u8; 32] {
+        self.hasher.finalize().into()
+    }
+}
+
+impl<W: Write> Write for Hashing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Hashing<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Serializes a key into `writer`, returning the digest of the uncompressed serialization.
+fn encode_key<K: CanonicalSerialize>(
+    key: &K,
+    writer: impl Write,
+    encoding: KeyEncoding,
+) -> Result<[u8; 32], KeyStoreError> {
+    let mut w = BufWriter::with_capacity(CHUNK_SIZE, writer);
+    let digest = match encoding {
+        KeyEncoding::Raw => {
+            let mut h = Hashing::new(&mut w);
+            key.serialize_with_mode(&mut h, Compress::No)?;
+            h.finish()
+        }
+        #[cfg(feature = "zstd")]
+        KeyEncoding::Zstd(level) => {
+            let mut enc = zstd::Encoder::new(&mut w, level)?;
+            let mut h = Hashing::new(&mut enc);
+            key.serialize_with_mode(&mut h, Compress::No)?;
+            let digest = h.finish();
+            enc.finish()?;
+            digest
+        }
+    };
+    w.flush()?;
+    Ok(digest)
+}
+
+/// Deserializes a key from `reader` in either encoding, returning the digest of the uncompressed
+/// serialization.
+fn decode_key<K: CanonicalDeserialize>(
+    reader: impl Read,
+    validate: Validate,
+) -> Result<(K, [u8; 32]), KeyStoreError> {
+    let mut r = BufReader::with_capacity(CHUNK_SIZE, reader);
+    if io::BufRead::fill_buf(&mut r)?.starts_with(&ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        {
+            let mut h = Hashing::new(zstd::Decoder::with_buffer(r)?);
+            let key = K::deserialize_with_mode(&mut h, Compress::No, validate)?;
+            return Ok((key, h.finish()));
+        }
+        #[cfg(not(feature = "zstd"))]
+        return Err(KeyStoreError::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "key is zstd compressed, but the zstd feature is disabled",
+        )));
+    }
+    let mut h = Hashing::new(r);
+    let key = K::deserialize_with_mode(&mut h, Compress::No, validate)?;
+    Ok((key, h.finish()))
+}
+
+/// Serialize a key into a writer, in chunks.
+///
+/// Unlike serializing into a `Vec` first, the key is never held in memory twice, which matters
+/// for proving keys of hundreds of megabytes. Keys written with any encoding may be read with
+/// [`read_key`].
+pub fn write_key<K: CanonicalSerialize>(
+    key: &K,
+    writer: impl Write,
+    encoding: KeyEncoding,
+) -> Result<(), KeyStoreError> {
+    encode_key(key, writer, encoding).map(|_| ())
+}
+
+/// Deserialize a key written by [`write_key`] from a reader, in chunks.
+///
+/// The encoding is detected from the first bytes, so raw keys (such as those serialized by
+/// `serialize_with_mode(_, Compress::No)`) may be read as well.
+pub fn read_key<K: CanonicalDeserialize>(
+    reader: impl Read,
+    validate: Validate,
+) -> Result<K, KeyStoreError> {
+    decode_key(reader, validate).map(|(k, _)| k)
+}
+
+/// Load a key written by [`write_key`] from a file, by mapping the file into memory.
+///
+/// The key is deserialized straight from the mapped pages, so a downloaded key is loaded without
+/// first reading the whole file into a buffer.
+#[cfg(feature = "mmap")]
+#[cfg(any(feature = "mmap", doc))]
+#[doc(cfg(feature = "mmap"))]
+pub fn load_key_mmap<K: CanonicalDeserialize>(
+    path: impl AsRef<Path>,
+    validate: Validate,
+) -> Result<K, KeyStoreError> {
+    let file = fs::File::open(path)?;
+    // SAFETY: The map is only read while deserializing. Files written by `KeyStore` are replaced
+    // by a rename rather than modified in place.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    if map.starts_with(&ZSTD_MAGIC) {
+        return read_key(&map[..], validate);
+    }
+    Ok(K::deserialize_with_mode(&map[..], Compress::No, validate)?)
+}
+
+/// An error when loading or storing keys.
+#[derive(Debug)]
+pub enum KeyStoreError {
+    /// Reading or writing a key file failed.
+    Io(io::Error),
+    /// A key could not be serialized or deserialized.
+    Serialization(SerializationError),
+    /// The circuit could not be synthesized to compute its hash.
+    Synthesis(SynthesisError),
+    /// Key generation failed.
+    Setup(String),
+}
+
+impl std::fmt::Display for KeyStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyStoreError::Io(e) => write!(f, "key file error: {}", e),
+            KeyStoreError::Serialization(e) => write!(f, "key serialization failed: {}", e),
+            KeyStoreError::Synthesis(e) => write!(f, "circuit synthesis failed: {}", e),
+            KeyStoreError::Setup(e) => write!(f, "key generation failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for KeyStoreError {}
+
+impl From<io::Error> for KeyStoreError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<SerializationError> for KeyStoreError {
+    fn from(value: SerializationError) -> Self {
+        Self::Serialization(value)
+    }
+}
+
+impl From<SynthesisError> for KeyStoreError {
+    fn from(value: SynthesisError) -> Self {
+        Self::Synthesis(value)
+    }
+}
+
+/// The header of the keys stored under an id in a [`KeyStore`], along with when they were
+/// generated.
+///
+/// Services may publish this so clients can check they hold the same keys as the verifier,
+/// without downloading the verifying key.
+#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", doc))]
+#[doc(cfg(feature = "fs"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyMeta {
+    /// The [`circuit_hash`] of the circuit the keys were generated for.
+    pub circuit: CircuitHash,
+    /// The digest of the uncompressed serialization of the proving key.
+    pub proving_key_digest: [u8; 32],
+    /// The digest of the uncompressed serialization of the verifying key.
+    pub verifying_key_digest: [u8; 32],
+    /// When the keys were written to the store.
+    pub generated: SystemTime,
+}
+
+/// An on-disk cache of proving and verifying keys.
+///
+/// Keys are stored by an id (for example, the name of an interaction) in a directory, with the
+/// proving and verifying keys in separate files so verifiers need only load the verifying key.
+/// Each file is prefixed with the [`circuit_hash`] of the circuit the key was generated for, and a
+/// digest of the key bytes. Keys are streamed to and from disk with the
+/// [`KeyEncoding`] of the store (see [`KeyStore::with_encoding`]).
+///
+/// When loading, a key is only returned if the circuit hash matches the circuit being asked for,
+/// and the digest matches the stored bytes. So if the circuit changes (a different interaction,
+/// different `NUMCBS`, or different bulletin constants), or a file is corrupted, the keys are
+/// regenerated rather than silently producing proofs which never verify.
+///
+/// # Example
+/// ```rust
+/// # use zk_callbacks::zk_object;
+/// # use rand::thread_rng;
+/// # use ark_bn254::{Bn254 as E, Fr};
+/// # use ark_r1cs_std::eq::EqGadget;
+/// # use ark_r1cs_std::cmp::CmpGadget;
+/// # use zk_callbacks::generic::interaction::Interaction;
+/// # use zk_callbacks::generic::interaction::Callback;
+/// # use zk_callbacks::generic::keystore::KeyStore;
+/// # use zk_callbacks::generic::object::Id;
+/// # use zk_callbacks::generic::object::Time;
+/// # use zk_callbacks::generic::object::TimeVar;
+/// # use ark_relations::r1cs::SynthesisError;
+/// # use zk_callbacks::generic::user::{User, UserVar};
+/// # use ark_r1cs_std::fields::fp::FpVar;
+/// # use ark_groth16::Groth16;
+/// # use ark_r1cs_std::prelude::Boolean;
+/// # use zk_callbacks::impls::hash::Poseidon;
+/// # use zk_callbacks::impls::dummy::DummyStore;
+/// # use zk_callbacks::impls::centralized::crypto::NoSigOTP;
+/// # type Groth = Groth16<E>;
+/// #[zk_object(Fr)]
+/// #[derive(Default)]
+/// struct Data {
+///     pub num_visits: Fr,
+/// }
+///
+/// fn method<'a>(old_user: &'a User<Fr, Data>, _pub: (), _priv: ()) -> User<Fr, Data> {
+///     let mut new = old_user.clone();
+///     new.data.num_visits += Fr::from(1);
+///     new
+/// }
+///
+/// fn predicate<'a>(old_user: &'a UserVar<Fr, Data>, new_user: &'a UserVar<Fr, Data>, _pub: (), _priv: ()) -> Result<Boolean<Fr>, SynthesisError> {
+///     new_user.data.num_visits.is_eq(&(old_user.data.num_visits.clone() + FpVar::Constant(Fr::from(1))))
+/// }
+///
+/// fn callback<'a>(old_user: &'a User<Fr, Data>, _args: Fr) -> User<Fr, Data> {
+///     old_user.clone()
+/// }
+///
+/// fn enforce_callback<'a>(old_user: &'a UserVar<Fr, Data>, _args: FpVar<Fr>) -> Result<UserVar<Fr, Data>, SynthesisError> {
+///     Ok(old_user.clone())
+/// }
+///
+/// fn main () {
+///     let cb = Callback {
+///         method_id: Id::from(0),
+///         expirable: false,
+///         expiration: Time::from(10),
+///         method: callback,
+///         predicate: enforce_callback
+///     };
+///
+///     let int = Interaction {
+///         meth: (method, predicate),
+///         callbacks: [cb.clone()],
+///     };
+///
+///     let mut rng = thread_rng();
+///     let dir = std::env::temp_dir().join("zk-callbacks-keystore-doc");
+///     let store = KeyStore::new(&dir).unwrap();
+///
+///     // Generated on the first call, and loaded from disk afterwards
+///     let (_pk, vk) = int.generate_keys_cached::<Poseidon<2>, Groth, NoSigOTP<Fr>, DummyStore>(&store, "visit", &mut rng, Some(()), None, false).unwrap();
+///     let (_pk2, vk2) = int.generate_keys_cached::<Poseidon<2>, Groth, NoSigOTP<Fr>, DummyStore>(&store, "visit", &mut rng, Some(()), None, false).unwrap();
+///     assert_eq!(vk, vk2);
+///
+///     // A verifier only loads the verifying key
+///     let vk3 = int.load_verifying_key::<Poseidon<2>, Groth, NoSigOTP<Fr>, DummyStore>(&store, "visit", Some(()), None, false).unwrap();
+///     assert_eq!(Some(vk), vk3);
+///
+///     // The headers of the stored keys may be published without the keys themselves
+///     let meta = store.meta("visit").unwrap().unwrap();
+///     assert_ne!(meta.proving_key_digest, meta.verifying_key_digest);
+///     assert!(store.meta("missing").unwrap().is_none());
+///
+///     std::fs::remove_dir_all(&dir).unwrap();
+/// }
+/// ```
+#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", doc))]
+#[doc(cfg(feature = "fs"))]
+#[derive(Clone, Debug)]
+pub struct KeyStore {
+    dir: PathBuf,
+    encoding: KeyEncoding,
+}
+
+#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", doc))]
+#[doc(cfg(feature = "fs"))]
+impl KeyStore {
+    /// Open a key store in a directory, creating the directory if it does not exist.
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, KeyStoreError> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            encoding: KeyEncoding::Raw,
+        })
+    }
+
+    /// Set the encoding keys are written with. Keys already stored in any encoding may still be
+    /// loaded.
+    pub fn with_encoding(mut self, encoding: KeyEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    fn path(&self, id: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, ext))
+    }
+
+    fn write_key<K: CanonicalSerialize>(
+        &self,
+        id: &str,
+        ext: &str,
+        hash: &CircuitHash,
+        key: &K,
+    ) -> Result<(), KeyStoreError> {
+        // Write to a temporary file first, so an interrupted write never leaves a partial key
+        let tmp = self.path(id, &format!("{}.tmp", ext));
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(hash)?;
+        file.write_all(&[0u8; 32])?;
+        let digest = encode_key(key, &mut file, self.encoding)?;
+
+        // The digest is only known once the key is written
+        file.seek(SeekFrom::Start(32))?;
+        file.write_all(&digest)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(tmp, self.path(id, ext))?;
+        Ok(())
+    }
+
+    fn read_header(&self, id: &str, ext: &str) -> Result<Option<[u8; 64]>, KeyStoreError> {
+        let mut file = match fs::File::open(self.path(id, ext)) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut header = [0u8; 64];
+        match file.read_exact(&mut header) {
+            Ok(()) => Ok(Some(header)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn read_key<K: CanonicalDeserialize>(
+        &self,
+        id: &str,
+        ext: &str,
+        hash: &CircuitHash,
+    ) -> Result<Option<K>, KeyStoreError> {
+        let mut file = match fs::File::open(self.path(id, ext)) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut header = [0u8; 64];
+        match file.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        if header[..32] != hash[..] {
+            return Ok(None);
+        }
+        // The key is only returned if the digest matches, so it is as written and need not be
+        // validated. A corrupted key which fails to deserialize is treated as missing.
+        match decode_key::<K>(file, Validate::No) {
+            Ok((key, digest)) if digest[..] == header[32..] => Ok(Some(key)),
+            Ok(_) | Err(KeyStoreError::Serialization(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Load the keys stored under `id` for a circuit, or generate and store them if they are
+    /// missing or were generated for a different circuit.
+    ///
+    /// The circuit is built twice with `circuit`: once to compute its hash, and once to generate
+    /// keys if needed.
+    pub fn get_or_generate<F: PrimeField, Snark: SNARK<F>, C: ConstraintSynthesizer<F>>(
+        &self,
+        id: &str,
+        rng: &mut (impl CryptoRng + RngCore),
+        circuit: impl Fn() -> C,
+    ) -> Result<(Snark::ProvingKey, Snark::VerifyingKey), KeyStoreError> {
+        let hash = circuit_hash(circuit())?;
+        if let (Some(pk), Some(vk)) = (
+            self.read_key::<Snark::ProvingKey>(id, "pk", &hash)?,
+            self.read_key::<Snark::VerifyingKey>(id, "vk", &hash)?,
+        ) {
+            return Ok((pk, vk));
+        }
+
+        let (pk, vk) = Snark::circuit_specific_setup(circuit(), rng)
+            .map_err(|e| KeyStoreError::Setup(e.to_string()))?;
+        self.write_key(id, "pk", &hash, &pk)?;
+        self.write_key(id, "vk", &hash, &vk)?;
+        Ok((pk, vk))
+    }
+
+    /// Load the verifying key stored under `id` for a circuit.
+    ///
+    /// Returns `None` if there is no key stored for the circuit.
+    pub fn get_verifying_key<F: PrimeField, Snark: SNARK<F>, C: ConstraintSynthesizer<F>>(
+        &self,
+        id: &str,
+        circuit: C,
+    ) -> Result<Option<Snark::VerifyingKey>, KeyStoreError> {
+        let hash = circuit_hash(circuit)?;
+        self.read_key::<Snark::VerifyingKey>(id, "vk", &hash)
+    }
+
+    /// Get the header of the keys stored under `id`, and when they were generated.
+    ///
+    /// Returns `None` if either key is missing, or the keys were generated for different
+    /// circuits. The keys themselves are not read, so a corrupted key is only detected when
+    /// loading it.
+    pub fn meta(&self, id: &str) -> Result<Option<KeyMeta>, KeyStoreError> {
+        let (Some(pk), Some(vk)) = (self.read_header(id, "pk")?, self.read_header(id, "vk")?)
+        else {
+            return Ok(None);
+        };
+        if pk[..32] != vk[..32] {
+            return Ok(None);
+        }
+        let mut meta = KeyMeta {
+            circuit: [0u8; 32],
+            proving_key_digest: [0u8; 32],
+            verifying_key_digest: [0u8; 32],
+            generated: fs::metadata(self.path(id, "vk"))?.modified()?,
+        };
+        meta.circuit.copy_from_slice(&vk[..32]);
+        meta.proving_key_digest.copy_from_slice(&pk[32..]);
+        meta.verifying_key_digest.copy_from_slice(&vk[32..]);
+        Ok(Some(meta))
+    }
+
+    /// Remove the keys stored under `id`.
+    pub fn remove(&self, id: &str) -> Result<(), KeyStoreError> {
+        for ext in ["pk", "vk"] {
+            match fs::remove_file(self.path(id, ext)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -20,10 +20,60 @@
 //!* Sending a proof with a callback and interacting with a service.
 //!
 
+/// Aggregation of interaction proofs by recursion.
+///
+/// This module verifies a batch of Groth16 proofs under the same verifying key in a circuit over
+/// the base field of the pairing, producing one proof for the whole batch. Bulletins with a high
+/// throughput of interactions may then check a single proof with
+/// [`verify_aggregate`](`aggregate::verify_aggregate`), rather than one proof per interaction.
+pub mod aggregate;
+
+/// Anonymity set metrics and `k`-anonymity guardrails.
+///
+/// Bulletins report the number of users an interaction hides among through
+/// [`PublicUserBul::anonymity_set_size`](`bulletin::PublicUserBul::anonymity_set_size`). Clients
+/// check this against an [`AnonymityGuard`](`anonymity::AnonymityGuard`) before interacting, and
+/// services may keep an [`AnonymityLog`](`anonymity::AnonymityLog`) of the size in each epoch.
+pub mod anonymity;
+
+/// Appeals of called callbacks.
+///
+/// User objects may keep a [`CallbackHistory`](`appeal::CallbackHistory`) of the callbacks
+/// applied to them, updated by callback methods as they are ingested. A user may then prove that
+/// a callback with some method id was applied to them in some epoch with
+/// [`appeal_predicate`](`appeal::appeal_predicate`), without revealing which ticket was called.
+/// Services may use this for appeals or counter-moderation.
+pub mod appeal;
+
+/// [`proptest`](https://docs.rs/proptest) strategies for users, callback tickets, and scan
+/// arguments.
+///
+/// With these, property tests (such as encoding round trips) cover every field of the core types,
+/// including fields added later.
+#[cfg(feature = "proptest")]
+#[cfg(any(feature = "proptest", doc))]
+#[doc(cfg(feature = "proptest"))]
+pub mod arbitrary;
+
+/// Asynchronous bulletins and interactions.
+///
+/// This module mirrors the bulletin and service traits for network-backed handles. Users may
+/// drive interactions end-to-end against asynchronous bulletins with
+/// [`User::exec_method_create_cb_async`](`user::User::exec_method_create_cb_async`),
+/// [`User::scan_callbacks_async`](`user::User::scan_callbacks_async`), and
+/// [`User::prove_statement_and_in_async`](`user::User::prove_statement_and_in_async`).
 #[cfg(feature = "asynchr")]
 #[cfg(any(feature = "asynchr", doc))]
 #[doc(cfg(feature = "asynchr"))]
-mod asynchr;
+pub mod asynchr;
+
+/// Badges and attributes issued to users.
+///
+/// User data implementing [`HasBadges`](`badges::HasBadges`) holds a fixed number of badge
+/// slots. Services issue badges with callbacks built from [`issue_badge`](`badges::issue_badge`),
+/// and users prove they hold a badge (or any badge within a set) without revealing their other
+/// badges.
+pub mod badges;
 
 /// Traits for implementing bulletins for objects and callbacks.
 ///
@@ -44,6 +94,16 @@ pub mod callbacks;
 #[doc(cfg(feature = "folding"))]
 pub mod fold;
 
+/// Serde encodings of commitments, tickets, and proofs as base64 or hex strings.
+///
+/// Arkworks types do not implement serde, so JSON bodies would otherwise carry their bytes as
+/// arrays of integers. The [`B64`](`encoding::B64`) and [`Hex`](`encoding::Hex`) wrappers, and
+/// the `with` modules for fields, encode them as strings instead.
+#[cfg(feature = "serde")]
+#[cfg(any(feature = "serde", doc))]
+#[doc(cfg(feature = "serde"))]
+pub mod encoding;
+
 /// Structs and abstractions associated with interactions.
 ///
 /// The main objects are [`Callback`](`interaction::Callback`) and
@@ -52,6 +112,25 @@ pub mod fold;
 /// predicate, and created callback tickets.
 pub mod interaction;
 
+/// An on-disk cache for proving and verifying keys.
+///
+/// Generating keys for large interactions takes minutes, so services should generate them once
+/// and load them on later starts. The [`KeyStore`](`keystore::KeyStore`) checks a hash of the
+/// circuit before returning keys, so keys generated for a different circuit are never used.
+pub mod keystore;
+
+/// Multi-threaded scans and proving.
+///
+/// With the `parallel` feature, the proof system splits its work across threads, and users may
+/// run interactions within a bounded [`rayon::ThreadPool`] with
+/// [`User::interact_in_pool`](`user::User::interact_in_pool`). Scans additionally fetch each
+/// ticket from the callback bulletin in parallel, with
+/// [`User::scan_callbacks_in_pool`](`user::User::scan_callbacks_in_pool`).
+#[cfg(feature = "parallel")]
+#[cfg(any(feature = "parallel", doc))]
+#[doc(cfg(feature = "parallel"))]
+pub mod parallel;
+
 /// Types and structs for use within zero knowledge objects.
 ///
 /// These types are used within zk-objects and the callbacks system frequently to ensure users
@@ -61,6 +140,45 @@ pub mod interaction;
 /// under the hood.
 pub mod object;
 
+/// Reusable gadgets for predicates.
+///
+/// Most applications check the same properties of users within their predicates: counters are
+/// incremented, reputation is above a threshold, the user is not banned, or enough time has passed
+/// since the last interaction. This module provides these checks as gadgets outputting a
+/// [`Boolean`](`ark_r1cs_std::boolean::Boolean`), which may be combined with `&` and `|`.
+pub mod predicates;
+
+/// Per-context pseudonyms derived from a user secret.
+///
+/// A [`PseudonymScheme`](`pseudonym::PseudonymScheme`) derives a pseudonym for each context from
+/// a secret stored within the user, both natively and in-circuit. User data implementing
+/// [`HasPseudonyms`](`pseudonym::HasPseudonyms`) may prove ownership of a pseudonym with
+/// [`pseudonym_predicate`](`pseudonym::pseudonym_predicate`), or that several pseudonyms share an
+/// author with [`same_author_predicate`](`pseudonym::same_author_predicate`).
+pub mod pseudonym;
+
+/// A source of randomness with a deterministic mode for tests.
+///
+/// Functions producing randomness take the RNG as an argument. Passing a seeded
+/// [`ZkRng`](`rng::ZkRng`) makes commitments, nullifiers, tickets, and keys reproducible across
+/// runs and machines, for integration tests and debugging.
+pub mod rng;
+
+/// Routing of callbacks to several services.
+///
+/// A [`ServiceRegistry`](`registry::ServiceRegistry`) holds the public key of each service and
+/// the callback methods it calls. Users get the keys to create tickets for each callback from
+/// the registry, so a single user object can be shared across several services.
+pub mod registry;
+
+/// Reputation values which saturate rather than wrap around the field.
+///
+/// A [`Reputation`](`reputation::Reputation`) may be used as a field of a user object. Its
+/// arithmetic (natively and in-circuit) clamps to configured
+/// [`ReputationBounds`](`reputation::ReputationBounds`), so a large penalty or reward within a
+/// callback cannot wrap around and, for example, reset a banned user.
+pub mod reputation;
+
 /// Structs and functions associated to scanning user objects.
 ///
 /// These structs provide the public and private arguments to prove a scan occured. Additionally,
@@ -75,11 +193,34 @@ pub mod scan;
 /// 1. call a callback function (in other words, force an update on a user).
 /// 2. Store interactions (when a user makes a post, a service must log that post for future
 ///    updates).
+/// 3. Schedule calls for a later time, with the [`CallbackQueue`](`service::CallbackQueue`).
 pub mod service;
 
+/// Attested time for interactions.
+///
+/// A [`TimeProvider`](`time::TimeProvider`) hands out timestamps with an attestation, such as a
+/// service signature on the current epoch. Users prove the time of an interaction was attested
+/// with [`attested_time_predicate`](`time::attested_time_predicate`), so expirations and rate
+/// limits can not be gamed by picking an arbitrary time.
+pub mod time;
+
+/// A versioned wire format for proofs and executed methods.
+///
+/// Executed methods and proofs sent between clients and servers are wrapped in an envelope with
+/// a magic number, format version, and the digest of the circuit. A peer running an incompatible
+/// version then gets a [`WireError`](`wire::WireError`) instead of misreading the bytes.
+pub mod wire;
+
 /// Contains structs associated to users and results of proofs done on user objects.
 ///
 /// Specifically,
 /// this module contains the [`User`](`user::User`) object and the [`UserData`](`user::UserData`) trait, which are integral to the
 /// system.
 pub mod user;
+
+/// Verification of executed methods without a bulletin.
+///
+/// Relying parties which only check proofs may call
+/// [`verify_execution`](`verify::verify_execution`) with a verifying key and an executed method,
+/// rather than implementing [`UserBul`](`bulletin::UserBul`).
+pub mod verify;
@@ -22,6 +22,10 @@ pub type NulVar<F> = FpVar<F>;
 pub type ComRand<F> = F;
 /// Represents commitment randomness in zero knowledge.
 pub type ComRandVar<F> = FpVar<F>;
+/// A long-term user secret, used as the key for PRF outputs (such as rate-limit tags).
+pub type PrfKey<F> = F;
+/// A long-term user secret in zero knowledge.
+pub type PrfKeyVar<F> = FpVar<F>;
 /// A callback list hash chain.
 pub type CBHash<F> = F;
 /// A callback list hash chain in zero knowledge.
@@ -0,0 +1,288 @@
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, prelude::CondSelectGadget};
+use ark_relations::r1cs::SynthesisError;
+use ark_snark::SNARK;
+use rand::{
+    distributions::{Distribution, Standard},
+    CryptoRng, RngCore,
+};
+use rayon::{prelude::*, ThreadPool};
+
+use crate::{
+    crypto::{enc::AECipherSigZK, hash::FieldHash},
+    generic::{
+        bulletin::{PublicCallbackBul, PublicUserBul},
+        callbacks::CallbackCom,
+        interaction::{Callback, Interaction},
+        object::Time,
+        scan::{get_scan_interaction, PrivScanArgs, PrivScanArgsVar, PubScanArgs, PubScanArgsVar},
+        user::{ExecutedMethod, User, UserData},
+    },
+};
+
+/// The data fetched from a callback bulletin for a single ticket in a scan.
+type TicketData<F, CBArgs, Crypto, CBul> = (
+    CallbackCom<F, CBArgs, Crypto>,
+    <CBul as PublicCallbackBul<F, CBArgs, Crypto>>::MembershipPub,
+    <CBul as PublicCallbackBul<F, CBArgs, Crypto>>::MembershipWitness,
+    <CBul as PublicCallbackBul<F, CBArgs, Crypto>>::NonMembershipPub,
+    <CBul as PublicCallbackBul<F, CBArgs, Crypto>>::NonMembershipWitness,
+    <Crypto as AECipherSigZK<F, CBArgs>>::Ct,
+    Time<F>,
+);
+
+impl<F: PrimeField + Absorb, U: UserData<F> + Send + Sync> User<F, U>
+where
+    Standard: Distribution<F>,
+{
+    /// Gets the arguments for a scan, fetching the data for each ticket in parallel.
+    ///
+    /// This is the same as [`User::get_scan_arguments`], but every ticket is looked up on the
+    /// callback bulletin on its own thread, since the lookups are independent.
+    pub fn get_scan_arguments_par<
+        CBArgs: Clone + std::fmt::Debug + PartialEq + Eq,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs, AV = CBArgsVar> + PartialEq + Eq,
+        CBul: PublicCallbackBul<F, CBArgs, Crypto> + Clone + Sync,
+        const NUMSCANS: usize,
+    >(
+        &self,
+        cbul: &CBul,
+        is_memb_nmemb_const: (bool, bool),
+        cur_time: Time<F>,
+        cb_methods: Vec<Callback<F, U, CBArgs, CBArgsVar>>,
+    ) -> (
+        PubScanArgs<F, U, CBArgs, CBArgsVar, Crypto, CBul, NUMSCANS>,
+        PrivScanArgs<F, CBArgs, Crypto, CBul, NUMSCANS>,
+    )
+    where
+        TicketData<F, CBArgs, Crypto, CBul>: Send,
+    {
+        let start_ind = match self.scan_index {
+            Some(ind) => {
+                assert!(NUMSCANS + ind <= self.callbacks.len());
+                ind
+            }
+            None => {
+                assert!(NUMSCANS <= self.callbacks.len());
+                0
+            }
+        };
+
+        let tickets: Vec<TicketData<F, CBArgs, Crypto, CBul>> = (start_ind..start_ind + NUMSCANS)
+            .into_par_iter()
+            .map(|i| {
+                let cb: CallbackCom<F, CBArgs, Crypto> = self.get_cb::<CBArgs, Crypto>(i);
+                let data = cbul.get_membership_data(cb.get_ticket());
+                let (enc, time) = match cbul.verify_in(cb.get_ticket()) {
+                    Some((e, t)) => (e, t),
+                    None => (Crypto::Ct::default(), Time::default()),
+                };
+                (cb, data.0, data.1, data.2, data.3, enc, time)
+            })
+            .collect();
+
+        let mut vec_cbs = vec![];
+        let mut vec_memb_pub = vec![];
+        let mut vec_nmemb_pub = vec![];
+        let mut vec_memb_priv = vec![];
+        let mut vec_nmemb_priv = vec![];
+        let mut vec_enc = vec![];
+        let mut vec_times = vec![];
+
+        for (cb, memb_pub, memb_priv, nmemb_pub, nmemb_priv, enc, time) in tickets {
+            vec_cbs.push(cb);
+            vec_memb_pub.push(memb_pub);
+            vec_memb_priv.push(memb_priv);
+            vec_nmemb_pub.push(nmemb_pub);
+            vec_nmemb_priv.push(nmemb_priv);
+            vec_enc.push(enc);
+            vec_times.push(time);
+        }
+
+        let ps: PubScanArgs<F, U, CBArgs, CBArgsVar, Crypto, CBul, NUMSCANS> = PubScanArgs {
+            memb_pub: vec_memb_pub
+                .try_into()
+                .unwrap_or_else(|_| panic!("Unexpected failure.")),
+            nmemb_pub: vec_nmemb_pub
+                .try_into()
+                .unwrap_or_else(|_| panic!("Unexpected failure.")),
+            bulletin: cbul.clone(),
+            is_memb_data_const: is_memb_nmemb_const.0,
+            is_nmemb_data_const: is_memb_nmemb_const.1,
+            cur_time,
+            cb_methods,
+        };
+
+        let prs: PrivScanArgs<F, CBArgs, Crypto, CBul, NUMSCANS> = PrivScanArgs {
+            priv_n_tickets: vec_cbs
+                .try_into()
+                .unwrap_or_else(|_| panic!("Unexpected failure.")),
+            post_times: vec_times
+                .try_into()
+                .unwrap_or_else(|_| panic!("Unexpected failure.")),
+            enc_args: vec_enc
+                .try_into()
+                .unwrap_or_else(|_| panic!("Unexpected failure.")),
+            memb_priv: vec_memb_priv
+                .try_into()
+                .unwrap_or_else(|_| panic!("Unexpected failure.")),
+            nmemb_priv: vec_nmemb_priv
+                .try_into()
+                .unwrap_or_else(|_| panic!("Unexpected failure.")),
+        };
+
+        (ps, prs)
+    }
+
+    /// Execute an interaction as in [`User::interact`], running on the threads of `pool`.
+    ///
+    /// With the `parallel` feature, the multi-scalar multiplications and FFTs of the proof system
+    /// are split across the threads of the pool the prover runs in. This lets a client bound the
+    /// number of threads used for proving, rather than taking over every core.
+    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::type_complexity)]
+    pub fn interact_in_pool<
+        H: FieldHash<F>,
+        PubArgs: Clone + std::fmt::Debug + Send,
+        PubArgsVar: AllocVar<PubArgs, F> + Clone,
+        PrivArgs: Clone + std::fmt::Debug + Send,
+        PrivArgsVar: AllocVar<PrivArgs, F> + Clone,
+        CBArgs: Clone + std::fmt::Debug,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        pool: &ThreadPool,
+        rng: &mut (impl CryptoRng + RngCore + Send),
+        method: Interaction<
+            F,
+            U,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            NUMCBS,
+        >,
+        rpks: [Crypto::SigPK; NUMCBS],
+        cur_time: Time<F>,
+        bul_data: (Bul::MembershipPub, Bul::MembershipWitness),
+        is_memb_data_const: bool,
+        pk: &Snark::ProvingKey,
+        pub_args: PubArgs,
+        priv_args: PrivArgs,
+        is_scan: bool,
+    ) -> Result<ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>, SynthesisError>
+    where
+        Crypto::SigPK: Send,
+        Bul::MembershipPub: Send,
+        Bul::MembershipWitness: Send,
+        Snark::ProvingKey: Sync,
+        ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>: Send,
+    {
+        pool.install(|| {
+            self.interact::<
+                H,
+                PubArgs,
+                PubArgsVar,
+                PrivArgs,
+                PrivArgsVar,
+                CBArgs,
+                CBArgsVar,
+                Crypto,
+                Snark,
+                Bul,
+                NUMCBS,
+            >(
+                rng,
+                method,
+                rpks,
+                cur_time,
+                bul_data,
+                is_memb_data_const,
+                pk,
+                pub_args,
+                priv_args,
+                is_scan,
+            )
+        })
+    }
+
+    /// Scan callbacks as in [`User::scan_callbacks`], running on the threads of `pool`.
+    ///
+    /// The tickets are fetched from the callback bulletin in parallel (see
+    /// [`User::get_scan_arguments_par`]), and the proof is made in the pool as in
+    /// [`User::interact_in_pool`]. This is most useful for large `NUMSCANS`, where the scan is
+    /// otherwise bound to a single core.
+    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::type_complexity)]
+    pub fn scan_callbacks_in_pool<
+        H: FieldHash<F>,
+        CBArgs: Clone + std::fmt::Debug + PartialEq + Eq,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs, AV = CBArgsVar> + PartialEq + Eq,
+        CBul: PublicCallbackBul<F, CBArgs, Crypto> + Clone + Sync,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U> + Sync,
+        const NUMSCANS: usize,
+    >(
+        &mut self,
+        pool: &ThreadPool,
+        rng: &mut (impl CryptoRng + RngCore + Send),
+        bul: &Bul,
+        is_memb_data_const: bool,
+        pk: &Snark::ProvingKey,
+        cbul: &CBul,
+        is_memb_nmemb_const: (bool, bool),
+        cur_time: Time<F>,
+        cb_methods: Vec<Callback<F, U, CBArgs, CBArgsVar>>,
+    ) -> Result<
+        (
+            PubScanArgs<F, U, CBArgs, CBArgsVar, Crypto, CBul, NUMSCANS>,
+            ExecutedMethod<F, Snark, CBArgs, Crypto, 0>,
+        ),
+        SynthesisError,
+    >
+    where
+        U::UserDataVar: CondSelectGadget<F> + EqGadget<F>,
+        CBul::MembershipPub: std::fmt::Debug,
+        CBul::NonMembershipPub: std::fmt::Debug,
+        TicketData<F, CBArgs, Crypto, CBul>: Send,
+        Snark::ProvingKey: Sync,
+        PubScanArgs<F, U, CBArgs, CBArgsVar, Crypto, CBul, NUMSCANS>: Send,
+        ExecutedMethod<F, Snark, CBArgs, Crypto, 0>: Send,
+    {
+        pool.install(|| {
+            let bul_data = bul.get_membership_data(self.commit::<H>()).unwrap();
+
+            let (ps, prs) = self
+                .get_scan_arguments_par::<CBArgs, CBArgsVar, Crypto, CBul, NUMSCANS>(
+                    cbul,
+                    is_memb_nmemb_const,
+                    cur_time,
+                    cb_methods,
+                );
+
+            let out = self.interact::<H, PubScanArgs<F, U, CBArgs, CBArgsVar, Crypto, CBul, NUMSCANS>, PubScanArgsVar<F, U, CBArgs, CBArgsVar, Crypto, CBul, NUMSCANS>, PrivScanArgs<F, CBArgs, Crypto, CBul, NUMSCANS>, PrivScanArgsVar<F, CBArgs, Crypto, CBul, NUMSCANS>, CBArgs, CBArgsVar, Crypto, Snark, Bul, 0>(
+                rng,
+                get_scan_interaction::<F, U, CBArgs, CBArgsVar, Crypto, CBul, H, NUMSCANS>(),
+                [],
+                cur_time,
+                bul_data,
+                is_memb_data_const,
+                pk,
+                ps.clone(),
+                prs,
+                true,
+            )?;
+
+            Ok((ps, out))
+        })
+    }
+}
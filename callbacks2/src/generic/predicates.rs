@@ -0,0 +1,300 @@
+use crate::generic::object::TimeVar;
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar, R1CSVar};
+use ark_relations::r1cs::{Result as ArkResult, SynthesisError};
+use core::cmp::Ordering;
+
+/// The number of bits the operands of every comparison in this module are bounded to.
+///
+/// Each comparison first enforces its operands fit in `CMP_BITS` bits, so a value which wrapped
+/// around the field (for example, a "negative" karma) makes the circuit unsatisfiable instead of
+/// comparing as a large number.
+pub const CMP_BITS: usize = 64;
+
+/// Enforce `value < 2^bits`, by decomposing `value` into `bits` bits.
+///
+/// For a constant `value`, this checks the bound natively and returns
+/// [`SynthesisError::Unsatisfiable`] if it does not hold.
+///
+/// # Panics
+///
+/// Panics if `bits + 1` is not smaller than the bit size of the field, so that every bounded value
+/// is at most `(p - 1) / 2`.
+pub fn enforce_bit_length<F: PrimeField>(value: &FpVar<F>, bits: usize) -> ArkResult<()> {
+    assert!(bits + 1 < F::MODULUS_BIT_SIZE as usize);
+
+    if let FpVar::Constant(c) = value {
+        return if c.into_bigint().num_bits() as usize <= bits {
+            Ok(())
+        } else {
+            Err(SynthesisError::Unsatisfiable)
+        };
+    }
+
+    let cs = value.cs();
+    let decomposed = (0..bits)
+        .map(|i| {
+            Boolean::new_witness(cs.clone(), || {
+                value.value().map(|v| v.into_bigint().get_bit(i))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Boolean::le_bits_to_fp(&decomposed)?.enforce_equal(value)
+}
+
+/// Compare `a` to `b`, after enforcing both are at most `bits` bits.
+///
+/// If `or_equal` is set, this also outputs true when `a == b`. Since the bound keeps both operands
+/// below `(p - 1) / 2`, this uses [`FpVar::is_cmp_unchecked`] instead of repeating the full
+/// decomposition of [`FpVar::is_cmp`].
+pub fn is_cmp_bounded<F: PrimeField>(
+    a: &FpVar<F>,
+    b: &FpVar<F>,
+    ordering: Ordering,
+    or_equal: bool,
+    bits: usize,
+) -> ArkResult<Boolean<F>> {
+    enforce_bit_length(a, bits)?;
+    enforce_bit_length(b, bits)?;
+    a.is_cmp_unchecked(b, ordering, or_equal)
+}
+
+/// Output true if `lo <= value <= hi`.
+///
+/// All operands are enforced to be at most [`CMP_BITS`] bits.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+/// # use ark_relations::r1cs::ConstraintSystem;
+/// # use zk_callbacks::generic::predicates::in_range;
+/// let cs = ConstraintSystem::<Fr>::new_ref();
+/// let v = FpVar::new_witness(cs.clone(), || Ok(Fr::from(5))).unwrap();
+/// let lo = FpVar::Constant(Fr::from(1));
+/// let hi = FpVar::Constant(Fr::from(10));
+/// assert!(in_range(&v, &lo, &hi).unwrap().value().unwrap());
+/// assert!(!in_range(&v, &hi, &hi).unwrap().value().unwrap());
+/// ```
+pub fn in_range<F: PrimeField>(
+    value: &FpVar<F>,
+    lo: &FpVar<F>,
+    hi: &FpVar<F>,
+) -> ArkResult<Boolean<F>> {
+    Ok(
+        is_cmp_bounded(value, lo, Ordering::Greater, true, CMP_BITS)?
+            & is_cmp_bounded(value, hi, Ordering::Less, true, CMP_BITS)?,
+    )
+}
+
+/// Output true if `new = old + step`.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+/// # use ark_relations::r1cs::ConstraintSystem;
+/// # use zk_callbacks::generic::predicates::incremented_by;
+/// let cs = ConstraintSystem::<Fr>::new_ref();
+/// let old = FpVar::new_witness(cs.clone(), || Ok(Fr::from(3))).unwrap();
+/// let new = FpVar::new_witness(cs.clone(), || Ok(Fr::from(4))).unwrap();
+/// let one = FpVar::Constant(Fr::from(1));
+/// assert!(incremented_by(&old, &new, &one).unwrap().value().unwrap());
+/// assert!(!incremented_by(&new, &old, &one).unwrap().value().unwrap());
+/// ```
+pub fn incremented_by<F: PrimeField>(
+    old: &FpVar<F>,
+    new: &FpVar<F>,
+    step: &FpVar<F>,
+) -> ArkResult<Boolean<F>> {
+    new.is_eq(&(old + step))
+}
+
+/// Output true if a counter was incremented by one, and has not reached `max`.
+///
+/// This is the usual check for counters bounding the number of interactions between scans. The
+/// new counter is enforced to be at most [`CMP_BITS`] bits.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+/// # use ark_relations::r1cs::ConstraintSystem;
+/// # use zk_callbacks::generic::predicates::counter_below;
+/// let cs = ConstraintSystem::<Fr>::new_ref();
+/// let old = FpVar::new_witness(cs.clone(), || Ok(Fr::from(8))).unwrap();
+/// let new = FpVar::new_witness(cs.clone(), || Ok(Fr::from(9))).unwrap();
+/// assert!(counter_below(&old, &new, 10).unwrap().value().unwrap());
+/// assert!(!counter_below(&old, &new, 9).unwrap().value().unwrap());
+/// ```
+pub fn counter_below<F: PrimeField>(
+    old: &FpVar<F>,
+    new: &FpVar<F>,
+    max: u64,
+) -> ArkResult<Boolean<F>> {
+    let inc = incremented_by(old, new, &FpVar::Constant(F::one()))?;
+    let below = is_cmp_bounded(
+        new,
+        &FpVar::Constant(F::from(max)),
+        Ordering::Less,
+        false,
+        CMP_BITS,
+    )?;
+    Ok(inc & below)
+}
+
+/// Output true if `new >= old`. Both values are enforced to be at most [`CMP_BITS`] bits.
+pub fn non_decreasing<F: PrimeField>(old: &FpVar<F>, new: &FpVar<F>) -> ArkResult<Boolean<F>> {
+    is_cmp_bounded(new, old, Ordering::Greater, true, CMP_BITS)
+}
+
+/// Output true if at least `gap` time has passed between `last` and `now`.
+///
+/// This may be used to rate limit interactions, by storing the time of the last interaction within
+/// the user. Both times (and `last + gap`) are enforced to be at most [`CMP_BITS`] bits.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+/// # use ark_relations::r1cs::ConstraintSystem;
+/// # use zk_callbacks::generic::predicates::time_since_at_least;
+/// let cs = ConstraintSystem::<Fr>::new_ref();
+/// let last = FpVar::new_witness(cs.clone(), || Ok(Fr::from(100))).unwrap();
+/// let now = FpVar::new_input(cs.clone(), || Ok(Fr::from(160))).unwrap();
+/// assert!(time_since_at_least(&last, &now, 60).unwrap().value().unwrap());
+/// assert!(!time_since_at_least(&last, &now, 61).unwrap().value().unwrap());
+/// ```
+pub fn time_since_at_least<F: PrimeField>(
+    last: &TimeVar<F>,
+    now: &TimeVar<F>,
+    gap: u64,
+) -> ArkResult<Boolean<F>> {
+    is_cmp_bounded(
+        now,
+        &(last + FpVar::Constant(F::from(gap))),
+        Ordering::Greater,
+        true,
+        CMP_BITS,
+    )
+}
+
+/// Output true if at most `gap` time has passed between `last` and `now`, and `now >= last`.
+pub fn time_since_at_most<F: PrimeField>(
+    last: &TimeVar<F>,
+    now: &TimeVar<F>,
+    gap: u64,
+) -> ArkResult<Boolean<F>> {
+    in_range(now, last, &(last + FpVar::Constant(F::from(gap))))
+}
+
+/// Output true if the karma (or reputation) of a user is at least `threshold`.
+///
+/// Both values are enforced to be at most [`CMP_BITS`] bits, so a karma which went below zero
+/// makes the circuit unsatisfiable.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+/// # use ark_relations::r1cs::ConstraintSystem;
+/// # use zk_callbacks::generic::predicates::karma_at_least;
+/// let cs = ConstraintSystem::<Fr>::new_ref();
+/// let karma = FpVar::new_witness(cs.clone(), || Ok(Fr::from(20))).unwrap();
+/// assert!(karma_at_least(&karma, &FpVar::Constant(Fr::from(20))).unwrap().value().unwrap());
+/// assert!(!karma_at_least(&karma, &FpVar::Constant(Fr::from(21))).unwrap().value().unwrap());
+/// ```
+pub fn karma_at_least<F: PrimeField>(
+    karma: &FpVar<F>,
+    threshold: &FpVar<F>,
+) -> ArkResult<Boolean<F>> {
+    is_cmp_bounded(karma, threshold, Ordering::Greater, true, CMP_BITS)
+}
+
+/// Output true if a banned flag (stored as a field element) is zero.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+/// # use ark_relations::r1cs::ConstraintSystem;
+/// # use zk_callbacks::generic::predicates::not_banned;
+/// let cs = ConstraintSystem::<Fr>::new_ref();
+/// let ok = FpVar::new_witness(cs.clone(), || Ok(Fr::from(0))).unwrap();
+/// let banned = FpVar::new_witness(cs.clone(), || Ok(Fr::from(1))).unwrap();
+/// assert!(not_banned(&ok).unwrap().value().unwrap());
+/// assert!(!not_banned(&banned).unwrap().value().unwrap());
+/// ```
+pub fn not_banned<F: PrimeField>(banned: &FpVar<F>) -> ArkResult<Boolean<F>> {
+    banned.is_eq(&FpVar::Constant(F::zero()))
+}
+
+/// Output true if every pair of old and new values are equal.
+///
+/// This is useful to enforce that a method leaves the listed fields of a user unchanged.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::{fields::fp::FpVar, R1CSVar};
+/// # use zk_callbacks::generic::predicates::unchanged;
+/// let a = FpVar::Constant(Fr::from(1));
+/// let b = FpVar::Constant(Fr::from(2));
+/// assert!(unchanged(&[(&a, &a), (&b, &b)]).unwrap().value().unwrap());
+/// assert!(!unchanged(&[(&a, &a), (&a, &b)]).unwrap().value().unwrap());
+/// ```
+pub fn unchanged<F: PrimeField, T: EqGadget<F>>(pairs: &[(&T, &T)]) -> ArkResult<Boolean<F>> {
+    let mut out = Boolean::TRUE;
+    for (old, new) in pairs {
+        out &= old.is_eq(new)?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_ff::Field;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    // Tests that comparisons hold for values right at the bit bound
+    #[test]
+    fn bounded_values_compare() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let max = Fr::from(u64::MAX);
+        let v = FpVar::new_witness(cs.clone(), || Ok(max)).unwrap();
+        let lo = FpVar::new_witness(cs.clone(), || Ok(Fr::from(0u8))).unwrap();
+        assert!(in_range(&v, &lo, &v).unwrap().value().unwrap());
+        assert!(!karma_at_least(&lo, &v).unwrap().value().unwrap());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    // Tests that an operand past the bit bound makes the circuit unsatisfiable, even when the
+    // comparison would otherwise hold
+    #[test]
+    fn oversized_operand_unsatisfiable() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let big = Fr::from(2u8).pow([CMP_BITS as u64]);
+        let karma = FpVar::new_witness(cs.clone(), || Ok(big)).unwrap();
+        let _ = karma_at_least(&karma, &FpVar::Constant(Fr::from(10u8))).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // A karma which went below zero
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let karma = FpVar::new_witness(cs.clone(), || Ok(-Fr::from(1u8))).unwrap();
+        let _ = karma_at_least(&karma, &FpVar::Constant(Fr::from(10u8))).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    // Tests that an oversized constant operand is rejected while building the circuit
+    #[test]
+    fn oversized_constant_rejected() {
+        let big = FpVar::Constant(-Fr::from(1u8));
+        assert!(matches!(
+            enforce_bit_length(&big, CMP_BITS),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+        assert!(enforce_bit_length(&FpVar::Constant(Fr::from(u64::MAX)), CMP_BITS).is_ok());
+    }
+}
@@ -0,0 +1,351 @@
+use crate::{
+    crypto::vrf::VrfZK,
+    generic::{
+        object::ComVar,
+        user::{UserData, UserVar},
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+};
+use ark_relations::{
+    ns,
+    r1cs::{Namespace, Result as ArkResult, SynthesisError},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use blake2::{Blake2s256 as Blake, Digest};
+use std::borrow::Borrow;
+
+/// A scheme deriving per-context pseudonyms from a user secret.
+///
+/// A pseudonym is a single field element, derived deterministically from a secret and a context
+/// (such as a thread or a poll). The same user always has the same pseudonym within a context,
+/// while pseudonyms in different contexts are unlinkable.
+///
+/// This is implemented for any [`VrfZK`], where the pseudonym is the VRF output on the context.
+pub trait PseudonymScheme<F: PrimeField> {
+    /// The user secret.
+    type Secret: Clone + Default;
+
+    /// The user secret in-circuit.
+    type SecretVar: AllocVar<Self::Secret, F> + Clone;
+
+    /// Derive the pseudonym for a context.
+    fn derive(secret: &Self::Secret, context: &[F]) -> F;
+
+    /// Derive the pseudonym for a context in-circuit.
+    fn derive_in_zk(secret: &Self::SecretVar, context: &[FpVar<F>]) -> ArkResult<FpVar<F>>;
+}
+
+impl<F: PrimeField, V: VrfZK<F>> PseudonymScheme<F> for V {
+    type Secret = V::Sk;
+
+    type SecretVar = V::SkVar;
+
+    fn derive(secret: &V::Sk, context: &[F]) -> F {
+        V::evaluate(secret, context)
+    }
+
+    fn derive_in_zk(secret: &V::SkVar, context: &[FpVar<F>]) -> ArkResult<FpVar<F>> {
+        V::evaluate_in_zk(secret, context)
+    }
+}
+
+/// User data holding a pseudonym secret.
+///
+/// Implementing this trait on user data gives access to the predicates in this module.
+pub trait HasPseudonyms<F: PrimeField + Absorb, P: PseudonymScheme<F>>: UserData<F> {
+    /// Get the pseudonym secret of the user.
+    fn pseudonym_secret(&self) -> &P::Secret;
+
+    /// Get the pseudonym secret of the user in-circuit.
+    fn pseudonym_secret_var(var: &Self::UserDataVar) -> &P::SecretVar;
+}
+
+/// Derive a context from a label, such as a thread name or poll identifier.
+///
+/// The label is hashed with Blake2s and reduced into the field, so services and users agree on the
+/// context for a label without coordination.
+pub fn context_from_label<F: PrimeField>(label: &[u8]) -> F {
+    let mut h = Blake::new();
+    h.update(b"zk-callbacks-pseudonym-context");
+    h.update(label);
+    F::from_le_bytes_mod_order(&h.finalize())
+}
+
+/// The public arguments for a pseudonym: a context and the claimed pseudonym within it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PseudonymArgs<F: PrimeField> {
+    /// The context of the pseudonym.
+    pub context: F,
+    /// The claimed pseudonym.
+    pub claimed: F,
+}
+
+impl<F: PrimeField> PseudonymArgs<F> {
+    /// Derive the pseudonym of a secret for a context.
+    pub fn derive<P: PseudonymScheme<F>>(secret: &P::Secret, context: F) -> Self {
+        Self {
+            context,
+            claimed: P::derive(secret, &[context]),
+        }
+    }
+}
+
+impl<F: PrimeField> ToConstraintField<F> for PseudonymArgs<F> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(vec![self.context, self.claimed])
+    }
+}
+
+/// The in-circuit representation of [`PseudonymArgs`].
+#[derive(Clone)]
+pub struct PseudonymArgsVar<F: PrimeField> {
+    /// The context of the pseudonym.
+    pub context: FpVar<F>,
+    /// The claimed pseudonym.
+    pub claimed: FpVar<F>,
+}
+
+impl<F: PrimeField> AllocVar<PseudonymArgs<F>, F> for PseudonymArgsVar<F> {
+    fn new_variable<T: Borrow<PseudonymArgs<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let context = FpVar::new_variable(ns!(cs, "context"), || Ok(rec.context), mode)?;
+            let claimed = FpVar::new_variable(ns!(cs, "claimed"), || Ok(rec.claimed), mode)?;
+            Ok(PseudonymArgsVar { context, claimed })
+        })
+    }
+}
+
+/// The public arguments for a rate limited pseudonym.
+///
+/// A user has `max` pseudonyms within each context, indexed from `0`. The pseudonym with index `i`
+/// is derived on the input `(context, i)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RatedPseudonymArgs<F: PrimeField> {
+    /// The context of the pseudonym.
+    pub context: F,
+    /// The claimed pseudonym.
+    pub claimed: F,
+    /// The index of the pseudonym within the context.
+    pub index: F,
+}
+
+impl<F: PrimeField> RatedPseudonymArgs<F> {
+    /// Derive the pseudonym with some index of a secret for a context.
+    pub fn derive<P: PseudonymScheme<F>>(secret: &P::Secret, context: F, index: F) -> Self {
+        Self {
+            context,
+            claimed: P::derive(secret, &[context, index]),
+            index,
+        }
+    }
+}
+
+impl<F: PrimeField> ToConstraintField<F> for RatedPseudonymArgs<F> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(vec![self.context, self.claimed, self.index])
+    }
+}
+
+/// The in-circuit representation of [`RatedPseudonymArgs`].
+#[derive(Clone)]
+pub struct RatedPseudonymArgsVar<F: PrimeField> {
+    /// The context of the pseudonym.
+    pub context: FpVar<F>,
+    /// The claimed pseudonym.
+    pub claimed: FpVar<F>,
+    /// The index of the pseudonym within the context.
+    pub index: FpVar<F>,
+}
+
+impl<F: PrimeField> AllocVar<RatedPseudonymArgs<F>, F> for RatedPseudonymArgsVar<F> {
+    fn new_variable<T: Borrow<RatedPseudonymArgs<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let context = FpVar::new_variable(ns!(cs, "context"), || Ok(rec.context), mode)?;
+            let claimed = FpVar::new_variable(ns!(cs, "claimed"), || Ok(rec.claimed), mode)?;
+            let index = FpVar::new_variable(ns!(cs, "index"), || Ok(rec.index), mode)?;
+            Ok(RatedPseudonymArgsVar {
+                context,
+                claimed,
+                index,
+            })
+        })
+    }
+}
+
+/// The public arguments for a proof that `N` pseudonyms belong to the same author.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SameAuthorArgs<F: PrimeField, const N: usize> {
+    /// The pseudonyms, each with their own context.
+    pub pseudonyms: [PseudonymArgs<F>; N],
+}
+
+impl<F: PrimeField, const N: usize> Default for SameAuthorArgs<F, N> {
+    fn default() -> Self {
+        Self {
+            pseudonyms: [PseudonymArgs::default(); N],
+        }
+    }
+}
+
+impl<F: PrimeField, const N: usize> ToConstraintField<F> for SameAuthorArgs<F, N> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(
+            self.pseudonyms
+                .iter()
+                .flat_map(|p| [p.context, p.claimed])
+                .collect(),
+        )
+    }
+}
+
+/// The in-circuit representation of [`SameAuthorArgs`].
+#[derive(Clone)]
+pub struct SameAuthorArgsVar<F: PrimeField, const N: usize> {
+    /// The pseudonyms, each with their own context.
+    pub pseudonyms: [PseudonymArgsVar<F>; N],
+}
+
+impl<F: PrimeField, const N: usize> AllocVar<SameAuthorArgs<F, N>, F> for SameAuthorArgsVar<F, N> {
+    fn new_variable<T: Borrow<SameAuthorArgs<F, N>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let pseudonyms =
+                <[PseudonymArgsVar<F>; N] as AllocVar<[PseudonymArgs<F>; N], F>>::new_variable(
+                    ns!(cs, "pseudonyms"),
+                    || Ok(rec.pseudonyms),
+                    mode,
+                )?;
+            Ok(SameAuthorArgsVar { pseudonyms })
+        })
+    }
+}
+
+/// Output true if the pseudonym in the arguments is derived from the secret.
+pub fn owns_pseudonym<F: PrimeField, P: PseudonymScheme<F>>(
+    secret: &P::SecretVar,
+    args: &PseudonymArgsVar<F>,
+) -> ArkResult<Boolean<F>> {
+    P::derive_in_zk(secret, std::slice::from_ref(&args.context))?.is_eq(&args.claimed)
+}
+
+/// Output true if the rate limited pseudonym is derived from the secret, and its index is less
+/// than `max`.
+pub fn owns_rated_pseudonym<F: PrimeField, P: PseudonymScheme<F>>(
+    secret: &P::SecretVar,
+    args: &RatedPseudonymArgsVar<F>,
+    max: u64,
+) -> ArkResult<Boolean<F>> {
+    let in_range = crate::generic::predicates::in_range(
+        &args.index,
+        &FpVar::Constant(F::zero()),
+        &FpVar::Constant(F::from(max.saturating_sub(1))),
+    )?;
+    let derived = P::derive_in_zk(secret, &[args.context.clone(), args.index.clone()])?;
+    Ok(in_range & derived.is_eq(&args.claimed)? & Boolean::constant(max > 0))
+}
+
+/// A singular predicate proving the user owns a pseudonym.
+///
+/// Use with
+/// [`User::prove_statement_and_in`](`crate::generic::user::User::prove_statement_and_in`) to show
+/// a pseudonym belongs to some user on the bulletin.
+pub fn pseudonym_predicate<
+    F: PrimeField + Absorb,
+    P: PseudonymScheme<F>,
+    U: HasPseudonyms<F, P>,
+>(
+    user: &UserVar<F, U>,
+    _com: &ComVar<F>,
+    args: PseudonymArgsVar<F>,
+    _priv: (),
+) -> ArkResult<Boolean<F>> {
+    owns_pseudonym::<F, P>(U::pseudonym_secret_var(&user.data), &args)
+}
+
+/// A singular predicate proving that `N` pseudonyms (in different contexts) have the same author.
+///
+/// The proof shows a single user derived every pseudonym, without revealing the user.
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::fields::fp::FpVar;
+/// # use zk_callbacks::zk_object;
+/// # use zk_callbacks::generic::pseudonym::{same_author_predicate, HasPseudonyms, PseudonymArgs, SameAuthorArgs};
+/// # use zk_callbacks::impls::{hash::Poseidon, vrf::PoseidonVrf};
+/// type Vrf = PoseidonVrf<Poseidon<2>, Fr>;
+///
+/// #[zk_object(Fr)]
+/// #[derive(Default)]
+/// struct Data {
+///     pub sk: Fr,
+/// }
+///
+/// impl HasPseudonyms<Fr, Vrf> for Data {
+///     fn pseudonym_secret(&self) -> &Fr {
+///         &self.sk
+///     }
+///
+///     fn pseudonym_secret_var(var: &DataZKVar) -> &FpVar<Fr> {
+///         &var.sk
+///     }
+/// }
+///
+/// let sk = Fr::from(42);
+/// let args = SameAuthorArgs {
+///     pseudonyms: [
+///         PseudonymArgs::derive::<Vrf>(&sk, Fr::from(1)),
+///         PseudonymArgs::derive::<Vrf>(&sk, Fr::from(2)),
+///     ],
+/// };
+/// // `same_author_predicate::<Fr, Vrf, Data, 2>` may now be used with `prove_statement_and_in`.
+/// let _pred = same_author_predicate::<Fr, Vrf, Data, 2>;
+/// ```
+pub fn same_author_predicate<
+    F: PrimeField + Absorb,
+    P: PseudonymScheme<F>,
+    U: HasPseudonyms<F, P>,
+    const N: usize,
+>(
+    user: &UserVar<F, U>,
+    _com: &ComVar<F>,
+    args: SameAuthorArgsVar<F, N>,
+    _priv: (),
+) -> ArkResult<Boolean<F>> {
+    let secret = U::pseudonym_secret_var(&user.data);
+    let mut out = Boolean::TRUE;
+    for p in args.pseudonyms.iter() {
+        out &= owns_pseudonym::<F, P>(secret, p)?;
+    }
+    Ok(out)
+}
@@ -0,0 +1,166 @@
+use crate::{
+    crypto::enc::AECipherSigZK,
+    generic::{
+        interaction::{Callback, Interaction},
+        object::Id,
+        user::UserData,
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use ark_r1cs_std::alloc::AllocVar;
+use std::collections::BTreeMap;
+
+/// An error when routing callbacks to services.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    /// No service is registered under this name.
+    UnknownService(String),
+    /// No service is registered for the callback method with this id.
+    UnroutedMethod(String),
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::UnknownService(name) => write!(f, "unknown service {}", name),
+            RegistryError::UnroutedMethod(id) => {
+                write!(f, "no service handles callback method {}", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// A set of services, each with their own signing key, and the callback methods they call.
+///
+/// A single user may interact with several services (forums, moderators) which each call their
+/// own callbacks. Every callback method is routed to one service, and tickets for that callback
+/// are rerandomized from the service's public key, so only that service can call it. Scans need no
+/// routing, as a called ticket is checked against the key it was rerandomized from.
+///
+/// A client uses [`rpks_for`](`ServiceRegistry::rpks_for`) to get the keys to pass to
+/// [`User::interact`](`super::user::User::interact`), and each service uses
+/// [`owned_by`](`ServiceRegistry::owned_by`) with
+/// [`approve_interaction_owned`](`super::service::ServiceProvider::approve_interaction_owned`) to
+/// check the tickets made for it.
+///
+/// # Example
+///
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use zk_callbacks::generic::registry::ServiceRegistry;
+/// # use zk_callbacks::impls::centralized::crypto::{FakeSigPubkey, NoSigOTP};
+/// let mut registry = ServiceRegistry::<Fr, Fr, NoSigOTP<Fr>>::new();
+/// registry.register("forum", FakeSigPubkey::pk());
+/// registry.register("moderation", FakeSigPubkey::pk());
+/// registry.route(Fr::from(0), "forum").unwrap();
+/// registry.route(Fr::from(1), "moderation").unwrap();
+///
+/// assert_eq!(registry.service_for(Fr::from(1)), Some("moderation"));
+/// assert!(registry.route(Fr::from(2), "wiki").is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ServiceRegistry<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>> {
+    services: BTreeMap<String, Crypto::SigPK>,
+    routes: BTreeMap<Id<F>, String>,
+}
+
+impl<F: PrimeField, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>> Default
+    for ServiceRegistry<F, CBArgs, Crypto>
+{
+    fn default() -> Self {
+        Self {
+            services: BTreeMap::new(),
+            routes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<F: PrimeField + Absorb, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>>
+    ServiceRegistry<F, CBArgs, Crypto>
+{
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a service with its public key, returning the previous key if the service was
+    /// already registered.
+    pub fn register(&mut self, name: &str, pk: Crypto::SigPK) -> Option<Crypto::SigPK> {
+        self.services.insert(name.to_string(), pk)
+    }
+
+    /// Route the callback method `method_id` to a registered service.
+    pub fn route(&mut self, method_id: Id<F>, service: &str) -> Result<(), RegistryError> {
+        if !self.services.contains_key(service) {
+            return Err(RegistryError::UnknownService(service.to_string()));
+        }
+        self.routes.insert(method_id, service.to_string());
+        Ok(())
+    }
+
+    /// Get the public key of a service.
+    pub fn service_key(&self, service: &str) -> Option<&Crypto::SigPK> {
+        self.services.get(service)
+    }
+
+    /// Get the service a callback method is routed to.
+    pub fn service_for(&self, method_id: Id<F>) -> Option<&str> {
+        self.routes.get(&method_id).map(String::as_str)
+    }
+
+    /// Get the service keys for each callback in an interaction, to pass to
+    /// [`User::interact`](`super::user::User::interact`).
+    pub fn rpks_for<
+        U: UserData<F>,
+        PubArgs: Clone,
+        PubArgsVar: AllocVar<PubArgs, F>,
+        PrivArgs: Clone,
+        PrivArgsVar: AllocVar<PrivArgs, F>,
+        CBArgsVar: AllocVar<CBArgs, F>,
+        const NUMCBS: usize,
+    >(
+        &self,
+        method: &Interaction<
+            F,
+            U,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            NUMCBS,
+        >,
+    ) -> Result<[Crypto::SigPK; NUMCBS], RegistryError> {
+        let keys = method
+            .callbacks
+            .iter()
+            .map(|cb| {
+                let service = self
+                    .service_for(cb.method_id)
+                    .ok_or_else(|| RegistryError::UnroutedMethod(cb.method_id.to_string()))?;
+                Ok(self.services[service].clone())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(keys
+            .try_into()
+            .unwrap_or_else(|_| panic!("Unexpected failure.")))
+    }
+
+    /// Mark the callbacks in `cb_list` which are routed to `service`, for
+    /// [`approve_interaction_owned`](`super::service::ServiceProvider::approve_interaction_owned`).
+    pub fn owned_by<U: UserData<F>, CBArgsVar: AllocVar<CBArgs, F>, const NUMCBS: usize>(
+        &self,
+        service: &str,
+        cb_list: &[Callback<F, U, CBArgs, CBArgsVar>],
+    ) -> [bool; NUMCBS] {
+        let mut owned = [false; NUMCBS];
+        for (o, cb) in owned.iter_mut().zip(cb_list) {
+            *o = self.service_for(cb.method_id) == Some(service);
+        }
+        owned
+    }
+}
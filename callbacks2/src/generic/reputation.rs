@@ -0,0 +1,284 @@
+use crate::generic::{
+    object::{Time, TimeVar},
+    predicates::{is_cmp_bounded, CMP_BITS},
+    user::UserData,
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    convert::ToBitsGadget,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    select::CondSelectGadget,
+    R1CSVar,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, Namespace, Result as ArkResult, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use core::cmp::Ordering;
+use std::borrow::Borrow;
+
+/// The number of bits the operands of comparisons in [`ReputationVar`] are bounded to.
+///
+/// This is twice [`CMP_BITS`] plus one, so that the sum of two `u64` values, or a `u64` decay rate
+/// times a `u64` number of epochs, still compares correctly.
+pub const REP_CMP_BITS: usize = 2 * CMP_BITS + 1;
+
+/// The bounds of a reputation value.
+///
+/// All arithmetic on [`Reputation`] and [`ReputationVar`] clamps results to `[min, max]`, so
+/// updates never wrap around the field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReputationBounds {
+    /// The minimum reputation.
+    pub min: u64,
+    /// The maximum reputation.
+    pub max: u64,
+}
+
+impl ReputationBounds {
+    /// Create new bounds. Panics if `min > max`.
+    pub const fn new(min: u64, max: u64) -> Self {
+        assert!(min <= max);
+        Self { min, max }
+    }
+}
+
+/// A reputation (or karma) value stored within a user.
+///
+/// The value is a field element, but all updates saturate at the configured
+/// [`ReputationBounds`]. This may be used directly as a field within a
+/// [`zk_object`](`crate::zk_object`).
+///
+/// # Example
+/// ```rust
+/// # use ark_bls12_381::Fr;
+/// # use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+/// # use ark_relations::r1cs::ConstraintSystem;
+/// # use zk_callbacks::generic::reputation::{Reputation, ReputationBounds, ReputationVar};
+/// const BOUNDS: ReputationBounds = ReputationBounds::new(0, 100);
+///
+/// let rep = Reputation::<Fr>::new(95);
+/// assert_eq!(rep.saturating_add(Fr::from(10), &BOUNDS), Reputation::new(100));
+/// assert_eq!(rep.saturating_sub(Fr::from(200), &BOUNDS), Reputation::new(0));
+///
+/// let cs = ConstraintSystem::<Fr>::new_ref();
+/// let rep_var = ReputationVar::new_witness(cs.clone(), || Ok(rep)).unwrap();
+/// let delta = FpVar::new_witness(cs.clone(), || Ok(Fr::from(10))).unwrap();
+/// let out = rep_var.saturating_add(&delta, &BOUNDS).unwrap();
+/// assert_eq!(out.value().unwrap(), Reputation::new(100));
+/// assert!(cs.is_satisfied().unwrap());
+/// ```
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, CanonicalSerialize, CanonicalDeserialize,
+)]
+pub struct Reputation<F: PrimeField>(pub F);
+
+impl<F: PrimeField> Reputation<F> {
+    /// Create a reputation from an integer.
+    pub fn new(value: u64) -> Self {
+        Self(F::from(value))
+    }
+
+    /// Clamp the reputation to the bounds.
+    pub fn clamp(self, bounds: &ReputationBounds) -> Self {
+        let min = F::from(bounds.min);
+        let max = F::from(bounds.max);
+        if self.0 < min {
+            Self(min)
+        } else if self.0 > max {
+            Self(max)
+        } else {
+            self
+        }
+    }
+
+    /// Add to the reputation, saturating at the maximum.
+    pub fn saturating_add(self, delta: F, bounds: &ReputationBounds) -> Self {
+        Self(self.0 + delta).clamp(bounds)
+    }
+
+    /// Subtract from the reputation, saturating at the minimum.
+    pub fn saturating_sub(self, delta: F, bounds: &ReputationBounds) -> Self {
+        if self.0 < F::from(bounds.min) + delta {
+            Self(F::from(bounds.min))
+        } else {
+            Self(self.0 - delta).clamp(bounds)
+        }
+    }
+
+    /// Add a signed delta to the reputation, saturating at the bounds.
+    ///
+    /// A delta whose negation fits within 64 bits (such as `F::from(-5)`) is treated as negative
+    /// and subtracted.
+    pub fn saturating_add_signed(self, delta: F, bounds: &ReputationBounds) -> Self {
+        let neg = -delta;
+        if neg.into_bigint().num_bits() <= 64 {
+            self.saturating_sub(neg, bounds)
+        } else {
+            self.saturating_add(delta, bounds)
+        }
+    }
+
+    /// Decay the reputation by `per_epoch` for every epoch between `last` and `now`, saturating at
+    /// the minimum.
+    pub fn decay(
+        self,
+        last: Time<F>,
+        now: Time<F>,
+        per_epoch: u64,
+        bounds: &ReputationBounds,
+    ) -> Self {
+        if now <= last {
+            return self.clamp(bounds);
+        }
+        self.saturating_sub((now - last) * F::from(per_epoch), bounds)
+    }
+}
+
+/// The in-circuit representation of a [`Reputation`].
+///
+/// Reputations, deltas and times are expected to fit in a `u64`. Comparisons within these gadgets
+/// enforce their operands are at most [`REP_CMP_BITS`] bits, which fits a sum of two such values or
+/// the decay over any number of epochs, so an out of range value makes the proof unsatisfiable
+/// rather than wrapping.
+#[derive(Clone, Debug)]
+pub struct ReputationVar<F: PrimeField>(pub FpVar<F>);
+
+impl<F: PrimeField> ReputationVar<F> {
+    /// Clamp the reputation to the bounds in-circuit.
+    pub fn clamp(&self, bounds: &ReputationBounds) -> ArkResult<Self> {
+        let min = FpVar::Constant(F::from(bounds.min));
+        let max = FpVar::Constant(F::from(bounds.max));
+        let below = is_cmp_bounded(&self.0, &min, Ordering::Less, false, REP_CMP_BITS)?;
+        let v = FpVar::conditionally_select(&below, &min, &self.0)?;
+        let above = is_cmp_bounded(&v, &max, Ordering::Greater, false, REP_CMP_BITS)?;
+        let v = FpVar::conditionally_select(&above, &max, &v)?;
+        Ok(Self(v))
+    }
+
+    /// Add to the reputation in-circuit, saturating at the maximum.
+    pub fn saturating_add(&self, delta: &FpVar<F>, bounds: &ReputationBounds) -> ArkResult<Self> {
+        Self(&self.0 + delta).clamp(bounds)
+    }
+
+    /// Subtract from the reputation in-circuit, saturating at the minimum.
+    pub fn saturating_sub(&self, delta: &FpVar<F>, bounds: &ReputationBounds) -> ArkResult<Self> {
+        let min = FpVar::Constant(F::from(bounds.min));
+        let under = is_cmp_bounded(
+            &self.0,
+            &(&min + delta),
+            Ordering::Less,
+            false,
+            REP_CMP_BITS,
+        )?;
+        let v = FpVar::conditionally_select(&under, &min, &(&self.0 - delta))?;
+        Self(v).clamp(bounds)
+    }
+
+    /// Add a signed delta to the reputation in-circuit, saturating at the bounds.
+    ///
+    /// This mirrors [`Reputation::saturating_add_signed`].
+    pub fn saturating_add_signed(
+        &self,
+        delta: &FpVar<F>,
+        bounds: &ReputationBounds,
+    ) -> ArkResult<Self> {
+        let neg = FpVar::Constant(F::zero()) - delta;
+        let mut high = Boolean::FALSE;
+        for b in neg.to_bits_le()?.iter().skip(64) {
+            high |= b;
+        }
+        let is_neg = !high;
+        let abs = FpVar::conditionally_select(&is_neg, &neg, delta)?;
+        let added = self.saturating_add(&abs, bounds)?;
+        let subbed = self.saturating_sub(&abs, bounds)?;
+        Self::conditionally_select(&is_neg, &subbed, &added)
+    }
+
+    /// Decay the reputation in-circuit by `per_epoch` for every epoch between `last` and `now`,
+    /// saturating at the minimum.
+    pub fn decay(
+        &self,
+        last: &TimeVar<F>,
+        now: &TimeVar<F>,
+        per_epoch: u64,
+        bounds: &ReputationBounds,
+    ) -> ArkResult<Self> {
+        let elapsed = FpVar::conditionally_select(
+            &is_cmp_bounded(now, last, Ordering::Less, true, CMP_BITS)?,
+            &FpVar::Constant(F::zero()),
+            &(now - last),
+        )?;
+        self.saturating_sub(&(elapsed * FpVar::Constant(F::from(per_epoch))), bounds)
+    }
+
+    /// Output true if the reputation is at least `threshold`.
+    pub fn is_at_least(&self, threshold: u64) -> ArkResult<Boolean<F>> {
+        is_cmp_bounded(
+            &self.0,
+            &FpVar::Constant(F::from(threshold)),
+            Ordering::Greater,
+            true,
+            REP_CMP_BITS,
+        )
+    }
+}
+
+impl<F: PrimeField> R1CSVar<F> for ReputationVar<F> {
+    type Value = Reputation<F>;
+
+    fn cs(&self) -> ConstraintSystemRef<F> {
+        self.0.cs()
+    }
+
+    fn value(&self) -> Result<Self::Value, SynthesisError> {
+        Ok(Reputation(self.0.value()?))
+    }
+}
+
+impl<F: PrimeField> AllocVar<Reputation<F>, F> for ReputationVar<F> {
+    fn new_variable<T: Borrow<Reputation<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let v = FpVar::new_variable(cs, || f().map(|r| r.borrow().0), mode)?;
+        Ok(Self(v))
+    }
+}
+
+impl<F: PrimeField> CondSelectGadget<F> for ReputationVar<F> {
+    fn conditionally_select(
+        cond: &Boolean<F>,
+        true_value: &Self,
+        false_value: &Self,
+    ) -> ArkResult<Self> {
+        Ok(Self(FpVar::conditionally_select(
+            cond,
+            &true_value.0,
+            &false_value.0,
+        )?))
+    }
+}
+
+impl<F: PrimeField> EqGadget<F> for ReputationVar<F> {
+    fn is_eq(&self, other: &Self) -> ArkResult<Boolean<F>> {
+        self.0.is_eq(&other.0)
+    }
+}
+
+impl<F: PrimeField + Absorb> UserData<F> for Reputation<F> {
+    type UserDataVar = ReputationVar<F>;
+
+    fn serialize_elements(&self) -> Vec<crate::generic::object::Ser<F>> {
+        vec![self.0]
+    }
+
+    fn serialize_in_zk(
+        user_var: Self::UserDataVar,
+    ) -> Result<Vec<crate::generic::object::SerVar<F>>, SynthesisError> {
+        Ok(vec![user_var.0])
+    }
+}
@@ -0,0 +1,152 @@
+use rand::{rngs::StdRng, CryptoRng, Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// The environment variable read by [`ZkRng::from_env`].
+pub const SEED_VAR: &str = "ZK_CALLBACKS_SEED";
+
+/// A source of randomness for users, interactions, and key generation.
+///
+/// Every function producing randomness in zk-callbacks (for example,
+/// [`User::create`](`crate::generic::user::User::create`),
+/// [`User::interact`](`crate::generic::user::User::interact`), and
+/// [`Interaction::generate_keys`](`crate::generic::interaction::Interaction::generate_keys`))
+/// takes the RNG as an argument, so passing a `ZkRng` fixes every commitment, nullifier, ticket,
+/// and key drawn from it.
+///
+/// In production, use [`ZkRng::os`] (or [`ZkRng::from_env`] with the variable unset), which
+/// draws fresh entropy. In tests, or to reproduce a run on another machine, use
+/// [`ZkRng::seeded`], which is a ChaCha20 stream over a fixed seed. The same seed produces the
+/// same values on every platform.
+///
+/// A seeded `ZkRng` is **not** secure; it must never be used outside of tests and debugging.
+///
+/// # Example
+/// ```rust
+/// # use zk_callbacks::generic::rng::ZkRng;
+/// # use zk_callbacks::generic::user::User;
+/// # use zk_callbacks::impls::hash::Poseidon;
+/// # use ark_bn254::Fr;
+/// let u1: User<Fr, bool> = User::create(true, &mut ZkRng::seeded(7));
+/// let u2: User<Fr, bool> = User::create(true, &mut ZkRng::seeded(7));
+/// let u3: User<Fr, bool> = User::create(true, &mut ZkRng::seeded(8));
+///
+/// assert_eq!(u1.commit::<Poseidon<2>>(), u2.commit::<Poseidon<2>>());
+/// assert_eq!(u1.zk_fields.nul, u2.zk_fields.nul);
+/// assert_ne!(u1.commit::<Poseidon<2>>(), u3.commit::<Poseidon<2>>());
+/// ```
+#[derive(Clone, Debug)]
+pub enum ZkRng {
+    /// Fresh entropy from the operating system.
+    Os(StdRng),
+    /// A deterministic ChaCha20 stream, for tests.
+    Seeded(ChaCha20Rng),
+}
+
+impl ZkRng {
+    /// An RNG seeded from the operating system.
+    pub fn os() -> Self {
+        Self::Os(StdRng::from_entropy())
+    }
+
+    /// A deterministic RNG from a short seed.
+    pub fn seeded(seed: u64) -> Self {
+        Self::Seeded(ChaCha20Rng::seed_from_u64(seed))
+    }
+
+    /// A deterministic RNG from a full 32 byte seed.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self::Seeded(ChaCha20Rng::from_seed(seed))
+    }
+
+    /// A deterministic RNG if [`SEED_VAR`] is set, and an RNG seeded by the operating system
+    /// otherwise.
+    ///
+    /// The variable holds either a decimal `u64` (as in [`ZkRng::seeded`]) or 64 hex characters
+    /// (as in [`ZkRng::from_seed`]). Binaries which take their randomness from here can be rerun
+    /// with the same variable to reproduce a failing run.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variable is set to something other than a seed.
+    pub fn from_env() -> Self {
+        match std::env::var(SEED_VAR) {
+            Ok(seed) => Self::parse_seed(&seed)
+                .unwrap_or_else(|| panic!("{} is not a u64 or 32 byte hex seed.", SEED_VAR)),
+            Err(_) => Self::os(),
+        }
+    }
+
+    fn parse_seed(seed: &str) -> Option<Self> {
+        let seed = seed.trim();
+        if let Ok(n) = seed.parse::<u64>() {
+            return Some(Self::seeded(n));
+        }
+        if seed.len() != 64 {
+            return None;
+        }
+        let mut bytes = [0u8; 32];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = u8::from_str_radix(seed.get(2 * i..2 * i + 2)?, 16).ok()?;
+        }
+        Some(Self::from_seed(bytes))
+    }
+
+    /// Whether the RNG is deterministic.
+    pub fn is_seeded(&self) -> bool {
+        matches!(self, Self::Seeded(_))
+    }
+
+    /// Split off an independent RNG.
+    ///
+    /// A seeded RNG produces a seeded child (determined by the parent), so separate components,
+    /// such as the client and the server in an integration test, can each hold their own RNG while
+    /// the whole run stays reproducible.
+    pub fn fork(&mut self) -> Self {
+        match self {
+            Self::Os(_) => Self::os(),
+            Self::Seeded(rng) => {
+                let mut seed = [0u8; 32];
+                rng.fill_bytes(&mut seed);
+                Self::from_seed(seed)
+            }
+        }
+    }
+}
+
+impl Default for ZkRng {
+    fn default() -> Self {
+        Self::os()
+    }
+}
+
+impl RngCore for ZkRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            Self::Os(rng) => rng.next_u32(),
+            Self::Seeded(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            Self::Os(rng) => rng.next_u64(),
+            Self::Seeded(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            Self::Os(rng) => rng.fill_bytes(dest),
+            Self::Seeded(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        match self {
+            Self::Os(rng) => rng.try_fill_bytes(dest),
+            Self::Seeded(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+impl CryptoRng for ZkRng {}
@@ -1,16 +1,18 @@
 use crate::{
     crypto::{enc::AECipherSigZK, hash::FieldHash, rr::RRSigner},
     generic::{
-        bulletin::{BulError, PublicUserBul},
+        bulletin::{BulError, CallbackBul, PublicUserBul},
         callbacks::CallbackCom,
-        interaction::Callback,
+        interaction::{interaction_public_inputs, Callback},
         object::Time,
         user::{ExecutedMethod, UserData},
     },
+    util::span,
 };
 use ark_crypto_primitives::sponge::Absorb;
 use ark_ff::{PrimeField, ToConstraintField};
 use ark_r1cs_std::alloc::AllocVar;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_snark::SNARK;
 
 /// A called callback.
@@ -127,6 +129,26 @@ pub trait ServiceProvider<
         Ok((ticket.cb_entry.tik, enc, sig))
     }
 
+    /// Call a callback and post it to a callback bulletin at time `time`.
+    ///
+    /// This is [`call`](`ServiceProvider::call`) followed by
+    /// [`verify_call_and_append`](`CallbackBul::verify_call_and_append`).
+    fn call_and_post<CBul: CallbackBul<F, CBArgs, Crypto>>(
+        &self,
+        ticket: CallbackCom<F, CBArgs, Crypto>,
+        arguments: CBArgs,
+        sk: Crypto::SigSK,
+        cbul: &mut CBul,
+        time: Time<F>,
+    ) -> Result<Called<F, CBArgs, Crypto>, CallError<Self::Error, CBul::Error>> {
+        let (tik, enc, sig) = self
+            .call(ticket, arguments, sk)
+            .map_err(CallError::Service)?;
+        cbul.verify_call_and_append(tik.clone(), enc.clone(), sig.clone(), time)
+            .map_err(CallError::Bulletin)?;
+        Ok((tik, enc, sig))
+    }
+
     /// Check if the service has ever received a specific ticket before, as otherwise a service may
     /// receive overlapping callbacks.
     fn has_never_received_tik(&self, ticket: Crypto::SigPK) -> bool;
@@ -184,6 +206,48 @@ pub trait ServiceProvider<
         memb_data: Bul::MembershipPub,
         is_memb_data_const: bool,
         verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        self.approve_interaction_owned::<U, Snark, PubArgs, Bul, H, NUMCBS>(
+            interaction_request,
+            sk,
+            args,
+            bul,
+            cb_list,
+            cur_time,
+            memb_data,
+            is_memb_data_const,
+            verif_key,
+            &[true; NUMCBS],
+        )
+    }
+
+    /// Check an interaction as in [`approve_interaction`](`ServiceProvider::approve_interaction`),
+    /// where only some of the callbacks are owned by this service.
+    ///
+    /// When an interaction attaches callbacks for several services (see
+    /// [`ServiceRegistry`](`super::registry::ServiceRegistry`)), each service can only check the
+    /// tickets made from its own key. Tickets with `owned[i]` set are checked against `sk` and
+    /// the tickets this service has received, while the others are only checked against the
+    /// callback list and commitments.
+    fn approve_interaction_owned<
+        U: UserData<F>,
+        Snark: SNARK<F>,
+        PubArgs: Clone + ToConstraintField<F>,
+        Bul: PublicUserBul<F, U>,
+        H: FieldHash<F>,
+        const NUMCBS: usize,
+    >(
+        &self,
+        interaction_request: &ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>,
+        sk: Crypto::SigSK,
+        args: PubArgs,
+        bul: &Bul,
+        cb_list: Vec<Callback<F, U, CBArgs, CBArgsVar>>,
+        cur_time: Time<F>,
+        memb_data: Bul::MembershipPub,
+        is_memb_data_const: bool,
+        verif_key: &Snark::VerifyingKey,
+        owned: &[bool; NUMCBS],
     ) -> bool {
         let out = bul.verify_in::<PubArgs, Snark, NUMCBS>(
             interaction_request.new_object,
@@ -219,6 +283,10 @@ pub trait ServiceProvider<
                 return false;
             }
 
+            if !owned[i] {
+                continue;
+            }
+
             let rand = interaction_request.cb_tik_list[i].1.clone();
             let vpk = sk.rerand(rand).sk_to_pk();
             if vpk != cb.cb_entry.tik {
@@ -230,16 +298,18 @@ pub trait ServiceProvider<
             }
         }
 
-        let mut pub_inputs = vec![
+        let pub_inputs = interaction_public_inputs(
             interaction_request.new_object,
             interaction_request.old_nullifier,
-        ];
-        pub_inputs.extend::<Vec<F>>(args.to_field_elements().unwrap());
-        pub_inputs.extend::<Vec<F>>(interaction_request.cb_com_list.to_field_elements().unwrap());
-        if !is_memb_data_const {
-            pub_inputs.extend(memb_data.to_field_elements().unwrap());
-        }
-        Snark::verify(verif_key, &pub_inputs, &interaction_request.proof).unwrap_or(false)
+            &args,
+            &interaction_request.cb_com_list,
+            (!is_memb_data_const).then_some(&memb_data),
+            interaction_request.rate_limit_tag.as_ref(),
+        )
+        .unwrap();
+        span!("verify", callbacks = NUMCBS)
+            .in_scope(|| Snark::verify(verif_key, &pub_inputs, &interaction_request.proof))
+            .unwrap_or(false)
     }
 
     /// Approves an interaction, as well as stores it.
@@ -406,3 +476,150 @@ pub trait ServiceProvider<
             .map_err(BulError::AppendError)
     }
 }
+
+/// An error from calling a callback and posting it to a bulletin.
+#[derive(Debug)]
+pub enum CallError<S, B> {
+    /// The service failed to call the callback.
+    Service(S),
+    /// The callback bulletin rejected the called ticket.
+    Bulletin(BulError<B>),
+}
+
+/// A callback waiting in a [`CallbackQueue`].
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ScheduledCall<
+    F: PrimeField + Absorb,
+    CBArgs: Clone + CanonicalSerialize + CanonicalDeserialize,
+    Crypto: AECipherSigZK<F, CBArgs>,
+> {
+    /// The ticket to call.
+    pub ticket: CallbackCom<F, CBArgs, Crypto>,
+    /// The arguments to call the ticket with.
+    pub arguments: CBArgs,
+    /// The earliest time at which the call is posted.
+    pub at: Time<F>,
+    /// The number of failed attempts to post the call so far.
+    pub attempts: u32,
+}
+
+/// A record of a callback posted by a [`CallbackQueue`].
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CallReceipt<F: PrimeField + Absorb, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>> {
+    /// The called ticket.
+    pub tik: Crypto::SigPK,
+    /// The time passed to the tick which posted the call.
+    pub posted_at: Time<F>,
+    /// The number of attempts taken, including the successful one.
+    pub attempts: u32,
+}
+
+/// A queue of callbacks to be called at a later time.
+///
+/// Services often decide on a callback (a ban, a reputation change) before they want it applied,
+/// for example to batch moderation decisions or to avoid linking a call to the post which caused
+/// it. Calls are [`schedule`](`CallbackQueue::schedule`)d with a time, and each
+/// [`tick`](`CallbackQueue::tick`) posts every call which is due. Calls rejected by the bulletin
+/// are retried after `retry_delay`, up to `max_attempts` times, after which they are moved to the
+/// failed list.
+///
+/// The queue is serializable, so services may persist it between restarts.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CallbackQueue<
+    F: PrimeField + Absorb,
+    CBArgs: Clone + CanonicalSerialize + CanonicalDeserialize,
+    Crypto: AECipherSigZK<F, CBArgs>,
+> {
+    /// Calls waiting to be posted.
+    pub pending: Vec<ScheduledCall<F, CBArgs, Crypto>>,
+    /// Receipts for posted calls, in the order they were posted.
+    pub receipts: Vec<CallReceipt<F, CBArgs, Crypto>>,
+    /// Calls which failed `max_attempts` times.
+    pub failed: Vec<ScheduledCall<F, CBArgs, Crypto>>,
+    /// The time to wait before retrying a failed call.
+    pub retry_delay: Time<F>,
+    /// The number of attempts before a call is given up on.
+    pub max_attempts: u32,
+}
+
+impl<
+        F: PrimeField + Absorb,
+        CBArgs: Clone + CanonicalSerialize + CanonicalDeserialize,
+        Crypto: AECipherSigZK<F, CBArgs>,
+    > CallbackQueue<F, CBArgs, Crypto>
+{
+    /// Create an empty queue.
+    pub fn new(retry_delay: Time<F>, max_attempts: u32) -> Self {
+        Self {
+            pending: vec![],
+            receipts: vec![],
+            failed: vec![],
+            retry_delay,
+            max_attempts,
+        }
+    }
+
+    /// Schedule a call to `ticket` with `arguments`, to be posted at or after time `at`.
+    pub fn schedule(
+        &mut self,
+        ticket: CallbackCom<F, CBArgs, Crypto>,
+        arguments: CBArgs,
+        at: Time<F>,
+    ) {
+        self.pending.push(ScheduledCall {
+            ticket,
+            arguments,
+            at,
+            attempts: 0,
+        });
+    }
+
+    /// Post every call due at time `now` to the callback bulletin.
+    ///
+    /// Returns the receipts for the calls posted in this tick.
+    pub fn tick<
+        CBArgsVar: AllocVar<CBArgs, F>,
+        S: ServiceProvider<F, CBArgs, CBArgsVar, Crypto>,
+        CBul: CallbackBul<F, CBArgs, Crypto>,
+    >(
+        &mut self,
+        service: &S,
+        cbul: &mut CBul,
+        sk: &Crypto::SigSK,
+        now: Time<F>,
+    ) -> Vec<CallReceipt<F, CBArgs, Crypto>>
+    where
+        Crypto::SigSK: Clone,
+    {
+        let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|c| c.at <= now);
+        self.pending = waiting;
+
+        let mut posted = vec![];
+        for mut call in due {
+            call.attempts += 1;
+            match service.call_and_post(
+                call.ticket.clone(),
+                call.arguments.clone(),
+                sk.clone(),
+                cbul,
+                now,
+            ) {
+                Ok((tik, _, _)) => posted.push(CallReceipt {
+                    tik,
+                    posted_at: now,
+                    attempts: call.attempts,
+                }),
+                Err(_) if call.attempts >= self.max_attempts => self.failed.push(call),
+                Err(_) => {
+                    call.at = now + self.retry_delay;
+                    self.pending.push(call);
+                }
+            }
+        }
+
+        self.receipts.extend(posted.iter().cloned());
+        posted
+    }
+}
@@ -0,0 +1,207 @@
+use crate::generic::{
+    object::{ComVar, Time, TimeVar},
+    user::{UserData, UserVar},
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    fields::fp::FpVar,
+};
+use ark_relations::{
+    ns,
+    r1cs::{Namespace, Result as ArkResult, SynthesisError},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{CryptoRng, RngCore};
+use std::borrow::Borrow;
+
+/// A source of attested time.
+///
+/// Interactions take the current time as a bare field element, which is trusted by convention. A
+/// time provider instead hands out timestamps with an attestation (for example, a service
+/// signature on the epoch), which can be checked both natively and in-circuit. Proving
+/// [`attested_time_predicate`] with an interaction, and checking the attested time is the time of
+/// the interaction, binds the time used for expirations and rate limits to one attested by the
+/// provider, so a client can not pick an arbitrary time.
+///
+/// The provider is identified by its public [`Authority`](`TimeProvider::Authority`), such as a
+/// public key.
+pub trait TimeProvider<F: PrimeField> {
+    /// The public data identifying the time authority. For example, a public key.
+    type Authority: Clone + Default + ToConstraintField<F>;
+    /// The in-circuit representation of the authority.
+    type AuthorityVar: AllocVar<Self::Authority, F> + Clone;
+    /// An attestation of a time. For example, a signature.
+    type Attestation: Clone + Default + std::fmt::Debug + CanonicalSerialize + CanonicalDeserialize;
+    /// The in-circuit representation of the attestation.
+    type AttestationVar: AllocVar<Self::Attestation, F> + Clone;
+
+    /// Get the authority of the provider.
+    fn authority(&self) -> Self::Authority;
+
+    /// Get the current time along with an attestation.
+    fn now(&self, rng: &mut (impl CryptoRng + RngCore)) -> Timestamp<F, Self::Attestation>;
+
+    /// Verify an attestation on a time.
+    fn verify(authority: &Self::Authority, time: Time<F>, attestation: &Self::Attestation) -> bool;
+
+    /// Verify an attestation on a time in-circuit.
+    fn verify_in_zk(
+        authority: &Self::AuthorityVar,
+        time: &TimeVar<F>,
+        attestation: &Self::AttestationVar,
+    ) -> ArkResult<Boolean<F>>;
+}
+
+/// A time along with an attestation from a [`TimeProvider`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Timestamp<F: PrimeField, A: CanonicalSerialize + CanonicalDeserialize> {
+    /// The time.
+    pub time: Time<F>,
+    /// The attestation on the time.
+    pub attestation: A,
+}
+
+impl<F: PrimeField, A: Clone + CanonicalSerialize + CanonicalDeserialize> Timestamp<F, A> {
+    /// Check the timestamp against a provider authority.
+    pub fn verify<T: TimeProvider<F, Attestation = A>>(&self, authority: &T::Authority) -> bool {
+        T::verify(authority, self.time, &self.attestation)
+    }
+}
+
+/// A time provider which attests to nothing.
+///
+/// This reproduces passing the time as a bare field element: every time is accepted. It is
+/// useful as a stand-in when the verifier already knows the current time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UnattestedTime<F: PrimeField>(pub Time<F>);
+
+impl<F: PrimeField> TimeProvider<F> for UnattestedTime<F> {
+    type Authority = ();
+
+    type AuthorityVar = ();
+
+    type Attestation = ();
+
+    type AttestationVar = ();
+
+    fn authority(&self) {}
+
+    fn now(&self, _rng: &mut (impl CryptoRng + RngCore)) -> Timestamp<F, ()> {
+        Timestamp {
+            time: self.0,
+            attestation: (),
+        }
+    }
+
+    fn verify(_authority: &(), _time: Time<F>, _attestation: &()) -> bool {
+        true
+    }
+
+    fn verify_in_zk(
+        _authority: &(),
+        _time: &TimeVar<F>,
+        _attestation: &(),
+    ) -> ArkResult<Boolean<F>> {
+        Ok(Boolean::TRUE)
+    }
+}
+
+/// The public arguments to prove the time of an interaction was attested by a provider.
+///
+/// The private arguments are the [`TimeProvider::Attestation`].
+pub struct AttestedTimeArgs<F: PrimeField, T: TimeProvider<F>> {
+    /// The authority of the time provider.
+    pub authority: T::Authority,
+    /// The attested time.
+    pub time: Time<F>,
+}
+
+impl<F: PrimeField, T: TimeProvider<F>> Clone for AttestedTimeArgs<F, T> {
+    fn clone(&self) -> Self {
+        Self {
+            authority: self.authority.clone(),
+            time: self.time,
+        }
+    }
+}
+
+impl<F: PrimeField, T: TimeProvider<F>> Default for AttestedTimeArgs<F, T> {
+    fn default() -> Self {
+        Self {
+            authority: T::Authority::default(),
+            time: Time::<F>::default(),
+        }
+    }
+}
+
+impl<F: PrimeField, T: TimeProvider<F>> std::fmt::Debug for AttestedTimeArgs<F, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttestedTimeArgs")
+            .field("authority", &self.authority.to_field_elements())
+            .field("time", &self.time)
+            .finish()
+    }
+}
+
+impl<F: PrimeField, T: TimeProvider<F>> ToConstraintField<F> for AttestedTimeArgs<F, T> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        let mut out = self.authority.to_field_elements()?;
+        out.push(self.time);
+        Some(out)
+    }
+}
+
+/// The in-circuit representation of [`AttestedTimeArgs`].
+pub struct AttestedTimeArgsVar<F: PrimeField, T: TimeProvider<F>> {
+    /// The authority of the time provider.
+    pub authority: T::AuthorityVar,
+    /// The attested time.
+    pub time: TimeVar<F>,
+}
+
+impl<F: PrimeField, T: TimeProvider<F>> Clone for AttestedTimeArgsVar<F, T> {
+    fn clone(&self) -> Self {
+        Self {
+            authority: self.authority.clone(),
+            time: self.time.clone(),
+        }
+    }
+}
+
+impl<F: PrimeField, T: TimeProvider<F>> AllocVar<AttestedTimeArgs<F, T>, F>
+    for AttestedTimeArgsVar<F, T>
+{
+    fn new_variable<B: Borrow<AttestedTimeArgs<F, T>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<B, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let authority =
+                T::AuthorityVar::new_variable(ns!(cs, "authority"), || Ok(&rec.authority), mode)?;
+            let time = FpVar::new_variable(ns!(cs, "time"), || Ok(rec.time), mode)?;
+            Ok(AttestedTimeArgsVar { authority, time })
+        })
+    }
+}
+
+/// A singular predicate proving the public time was attested by the time provider.
+///
+/// The attestation is a private argument, so it is not revealed. Services should check the
+/// authority in the public arguments is the provider they trust, and that the time matches the
+/// `cur_time` of the interaction.
+pub fn attested_time_predicate<F: PrimeField + Absorb, U: UserData<F>, T: TimeProvider<F>>(
+    _user: &UserVar<F, U>,
+    _com: &ComVar<F>,
+    args: AttestedTimeArgsVar<F, T>,
+    attestation: T::AttestationVar,
+) -> ArkResult<Boolean<F>> {
+    T::verify_in_zk(&args.authority, &args.time, &attestation)
+}
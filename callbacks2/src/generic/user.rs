@@ -1,14 +1,17 @@
 use crate::{
     crypto::{enc::AECipherSigZK, hash::FieldHash},
     generic::{
+        anonymity::{AnonymityError, AnonymityGuard},
         bulletin::PublicUserBul,
         callbacks::{add_ticket_to_hc, create_cbs_from_interaction, CallbackCom},
         interaction::{
-            ExecMethodCircuit, Interaction, ProvePredInCircuit, ProvePredicateCircuit,
-            SingularPredicate,
+            ExecMethodCircuit, Interaction, ProvePredInCircuit, ProvePredicateCircuit, RateLimit,
+            RateLimitTag, RateLimitedInteraction, SingularPredicate,
         },
-        object::{Com, ComVar, Nul, Ser, SerVar, Time, ZKFields, ZKFieldsVar},
+        keystore::{CircuitHash, DigestedKey},
+        object::{Com, ComVar, Nul, PrfKey, PrfKeyVar, Ser, SerVar, Time, ZKFields, ZKFieldsVar},
     },
+    util::span,
 };
 use ark_crypto_primitives::sponge::Absorb;
 use ark_ff::PrimeField;
@@ -281,6 +284,22 @@ pub trait UserData<F: PrimeField + Absorb>: Clone + Eq + std::fmt::Debug {
 
     /// Convert the data of the user into a serialized vector of field elements in-circuit.
     fn serialize_in_zk(user_var: Self::UserDataVar) -> Result<Vec<SerVar<F>>, SynthesisError>;
+
+    /// The key rate-limit tags of the user are computed with, if the user has one.
+    ///
+    /// While a user has a key, every method and scan must leave it unchanged, so a user may not
+    /// pick a new key to escape a rate limit. The [`zk_object`] macro reads it from a field marked
+    /// `#[rate_limit_key]`.
+    fn rate_limit_key(&self) -> Option<PrfKey<F>> {
+        None
+    }
+
+    /// The rate-limit key of the user in-circuit, if the user has one.
+    fn rate_limit_key_in_zk(
+        _user_var: &Self::UserDataVar,
+    ) -> Result<Option<PrfKeyVar<F>>, SynthesisError> {
+        Ok(None)
+    }
 }
 
 /// Struct representing the whole user object.
@@ -329,8 +348,6 @@ where
         self.zk_fields.serialize_with_mode(&mut writer, compress)?;
         self.callbacks.serialize_with_mode(&mut writer, compress)?;
         self.scan_index.serialize_with_mode(&mut writer, compress)?;
-        (self.in_progress_cbs.serialized_size(compress))
-            .serialize_with_mode(&mut writer, compress)?;
         self.in_progress_cbs
             .serialize_with_mode(&mut writer, compress)?;
 
@@ -495,6 +512,12 @@ pub struct ExecutedMethod<
     pub cur_time: Time<F>,
     /// Proof of valid user object update.
     pub proof: Snark::Proof,
+    /// The rate-limit tag, if the interaction was rate limited. See
+    /// [`Interaction::with_rate_limit`].
+    pub rate_limit_tag: Option<RateLimitTag<F>>,
+    /// The digest of the circuit the proof was made for, if proven with a digested key. See
+    /// [`User::interact_digested`].
+    pub circuit_digest: Option<CircuitHash>,
 }
 
 /// Output data after a proof is made on the user object.
@@ -959,6 +982,219 @@ where
         pub_args: PubArgs,
         priv_args: PrivArgs,
         is_scan: bool,
+    ) -> Result<ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>, SynthesisError> {
+        self.interact_with_limit::<
+            H,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            Crypto,
+            Snark,
+            Bul,
+            NUMCBS,
+        >(
+            rng,
+            method,
+            rpks,
+            cur_time,
+            bul_data,
+            is_memb_data_const,
+            pk,
+            pub_args,
+            priv_args,
+            is_scan,
+            None,
+        )
+    }
+
+    /// Execute an interaction as in [`User::interact`], with a proving key tagged with the
+    /// digest of its circuit (see [`Interaction::generate_digested_keys`]).
+    ///
+    /// The digest is recorded in the [`ExecutedMethod`], so a bulletin checking the proof against
+    /// the key of a different circuit reports a mismatch rather than an invalid proof.
+    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::type_complexity)]
+    pub fn interact_digested<
+        H: FieldHash<F>,
+        PubArgs: Clone + std::fmt::Debug,
+        PubArgsVar: AllocVar<PubArgs, F> + Clone,
+        PrivArgs: Clone + std::fmt::Debug,
+        PrivArgsVar: AllocVar<PrivArgs, F> + Clone,
+        CBArgs: Clone + std::fmt::Debug,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        method: Interaction<
+            F,
+            U,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            NUMCBS,
+        >,
+        rpks: [Crypto::SigPK; NUMCBS],
+        cur_time: Time<F>,
+        bul_data: (Bul::MembershipPub, Bul::MembershipWitness),
+        is_memb_data_const: bool,
+        pk: &DigestedKey<Snark::ProvingKey>,
+        pub_args: PubArgs,
+        priv_args: PrivArgs,
+        is_scan: bool,
+    ) -> Result<ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>, SynthesisError> {
+        let mut out = self.interact::<
+            H,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            Crypto,
+            Snark,
+            Bul,
+            NUMCBS,
+        >(
+            rng,
+            method,
+            rpks,
+            cur_time,
+            bul_data,
+            is_memb_data_const,
+            &pk.key,
+            pub_args,
+            priv_args,
+            is_scan,
+        )?;
+        out.circuit_digest = Some(pk.digest);
+        Ok(out)
+    }
+
+    /// Execute a rate limited interaction.
+    ///
+    /// This behaves as [`User::interact`] with `is_scan = false`, but additionally reveals a
+    /// rate-limit tag `H(k, epoch, counter)` in the [`ExecutedMethod`], where `k` is the rate-limit
+    /// key of the user data (see [`UserData::rate_limit_key`]), which must have one. The `counter`
+    /// must be less than the maximum of the rate limit, and should be distinct for every
+    /// interaction within an epoch: reusing a counter reproduces the same tag, which the service
+    /// will reject.
+    ///
+    /// The keys must be generated through [`RateLimitedInteraction::generate_keys`].
+    pub fn interact_rate_limited<
+        H: FieldHash<F>,
+        PubArgs: Clone + std::fmt::Debug,
+        PubArgsVar: AllocVar<PubArgs, F> + Clone,
+        PrivArgs: Clone + std::fmt::Debug,
+        PrivArgsVar: AllocVar<PrivArgs, F> + Clone,
+        CBArgs: Clone + std::fmt::Debug,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        method: RateLimitedInteraction<
+            F,
+            U,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            NUMCBS,
+        >,
+        counter: u64,
+        rpks: [Crypto::SigPK; NUMCBS],
+        cur_time: Time<F>,
+        bul_data: (Bul::MembershipPub, Bul::MembershipWitness),
+        is_memb_data_const: bool,
+        pk: &Snark::ProvingKey,
+        pub_args: PubArgs,
+        priv_args: PrivArgs,
+    ) -> Result<ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>, SynthesisError> {
+        if counter >= method.rate_limit.max || self.data.rate_limit_key().is_none() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        self.interact_with_limit::<
+            H,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            Crypto,
+            Snark,
+            Bul,
+            NUMCBS,
+        >(
+            rng,
+            method.interaction,
+            rpks,
+            cur_time,
+            bul_data,
+            is_memb_data_const,
+            pk,
+            pub_args,
+            priv_args,
+            false,
+            Some((method.rate_limit, counter)),
+        )
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "interact", skip_all, fields(callbacks = NUMCBS, scan = is_scan))
+    )]
+    fn interact_with_limit<
+        H: FieldHash<F>,
+        PubArgs: Clone + std::fmt::Debug,
+        PubArgsVar: AllocVar<PubArgs, F> + Clone,
+        PrivArgs: Clone + std::fmt::Debug,
+        PrivArgsVar: AllocVar<PrivArgs, F> + Clone,
+        CBArgs: Clone + std::fmt::Debug,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        method: Interaction<
+            F,
+            U,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            NUMCBS,
+        >,
+        rpks: [Crypto::SigPK; NUMCBS],
+        cur_time: Time<F>,
+        bul_data: (Bul::MembershipPub, Bul::MembershipWitness),
+        is_memb_data_const: bool,
+        pk: &Snark::ProvingKey,
+        pub_args: PubArgs,
+        priv_args: PrivArgs,
+        is_scan: bool,
+        rate_limit: Option<(RateLimit<F>, u64)>,
     ) -> Result<ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>, SynthesisError> {
         // Steps:
         // a) update user/self [ old user ] --> method(user) [ new user ]
@@ -967,46 +1203,52 @@ where
         //      - a) the user was properly updated via the predicate
         //      - b) the zk statements (nul == old nul, proper cblist, etc)
 
-        // (A) update the user object
-        // Create the new zk_object from the method
-        let mut new_user = (method.meth.0)(self, pub_args.clone(), priv_args.clone());
-
-        // (B) update the new users zk fields properly
-
-        new_user.zk_fields.nul = rng.gen();
-        new_user.zk_fields.com_rand = rng.gen();
-
-        let cb_tik_list: [(CallbackCom<F, CBArgs, Crypto>, Crypto::Rand); NUMCBS] =
-            create_cbs_from_interaction(rng, method.clone(), rpks, cur_time);
-
-        let issued_callbacks: [CallbackCom<F, CBArgs, Crypto>; NUMCBS] = cb_tik_list
-            .iter()
-            .map(|(x, _)| x.clone())
-            .collect::<Vec<CallbackCom<F, CBArgs, Crypto>>>()
-            .try_into()
-            .unwrap();
-
-        let issued_cb_coms = cb_tik_list
-            .iter()
-            .map(|(x, _)| x.commit::<H>())
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
-
-        for item in issued_callbacks.iter().take(NUMCBS) {
-            let mut cb = Vec::new();
-            item.clone().serialize_compressed(&mut cb).unwrap();
-            new_user.callbacks.push(cb);
-
-            new_user.zk_fields.callback_hash = add_ticket_to_hc::<F, H, CBArgs, Crypto>(
-                new_user.zk_fields.callback_hash,
-                item.clone().cb_entry,
-            );
-        }
-
-        if !is_scan {
-            new_user.zk_fields.old_in_progress_callback_hash = new_user.zk_fields.callback_hash;
-        }
+        let (new_user, cb_tik_list, issued_callbacks, issued_cb_coms) = span!("witness generation")
+            .in_scope(|| {
+                // (A) update the user object
+                // Create the new zk_object from the method
+                let mut new_user = (method.meth.0)(self, pub_args.clone(), priv_args.clone());
+
+                // (B) update the new users zk fields properly
+
+                new_user.zk_fields.nul = rng.gen();
+                new_user.zk_fields.com_rand = rng.gen();
+
+                let cb_tik_list: [(CallbackCom<F, CBArgs, Crypto>, Crypto::Rand); NUMCBS] =
+                    create_cbs_from_interaction(rng, method.clone(), rpks, cur_time);
+
+                let issued_callbacks: [CallbackCom<F, CBArgs, Crypto>; NUMCBS] = cb_tik_list
+                    .iter()
+                    .map(|(x, _)| x.clone())
+                    .collect::<Vec<CallbackCom<F, CBArgs, Crypto>>>()
+                    .try_into()
+                    .unwrap();
+
+                let issued_cb_coms: [Com<F>; NUMCBS] = cb_tik_list
+                    .iter()
+                    .map(|(x, _)| x.commit::<H>())
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    .unwrap();
+
+                for item in issued_callbacks.iter().take(NUMCBS) {
+                    let mut cb = Vec::new();
+                    item.clone().serialize_compressed(&mut cb).unwrap();
+                    new_user.callbacks.push(cb);
+
+                    new_user.zk_fields.callback_hash = add_ticket_to_hc::<F, H, CBArgs, Crypto>(
+                        new_user.zk_fields.callback_hash,
+                        item.clone().cb_entry,
+                    );
+                }
+
+                if !is_scan {
+                    new_user.zk_fields.old_in_progress_callback_hash =
+                        new_user.zk_fields.callback_hash;
+                }
+
+                (new_user, cb_tik_list, issued_callbacks, issued_cb_coms)
+            });
 
         // (C) Generate proof of correctness
         // Extract the zk fields from the objects to do bookkeeping
@@ -1015,6 +1257,11 @@ where
 
         let out_nul = self.zk_fields.nul;
 
+        let rate_limit_tag = rate_limit.map(|(r, counter)| RateLimitTag {
+            epoch: r.epoch,
+            tag: r.tag::<H>(self.data.rate_limit_key().unwrap_or_default(), counter),
+        });
+
         let exec_method_circ: ExecMethodCircuit<
             F,
             H,
@@ -1041,19 +1288,24 @@ where
             pub_args,
             pub_bul_membership_data: bul_data.0,
             bul_memb_is_const: is_memb_data_const,
+            priv_rate_limit_counter: rate_limit.map(|(_, c)| c).unwrap_or_default(),
+            pub_rate_limit_tag: rate_limit_tag.map(|t| t.tag).unwrap_or_default(),
+            rate_limit: rate_limit.map(|(r, _)| r),
 
             associated_method: method,
             is_scan,
             _phantom_hash: core::marker::PhantomData,
         };
 
-        let new_cs = ConstraintSystem::<F>::new_ref();
-        exec_method_circ
-            .clone()
-            .generate_constraints(new_cs.clone())?;
-        new_cs.is_satisfied()?;
+        span!("constraint synthesis").in_scope(|| {
+            let new_cs = ConstraintSystem::<F>::new_ref();
+            exec_method_circ
+                .clone()
+                .generate_constraints(new_cs.clone())?;
+            new_cs.is_satisfied()
+        })?;
 
-        let proof = Snark::prove(pk, exec_method_circ, rng)?;
+        let proof = span!("prove").in_scope(|| Snark::prove(pk, exec_method_circ, rng))?;
 
         // (D) Update current object
         *self = new_user;
@@ -1065,6 +1317,8 @@ where
             cb_com_list: issued_cb_coms,
             cur_time,
             proof,
+            rate_limit_tag,
+            circuit_digest: None,
         })
     }
 
@@ -1201,6 +1455,9 @@ where
             pub_args,
             pub_bul_membership_data: bul_data.0,
             bul_memb_is_const: is_memb_data_const,
+            priv_rate_limit_counter: 0,
+            pub_rate_limit_tag: F::zero(),
+            rate_limit: None,
 
             associated_method: method,
             is_scan,
@@ -1331,6 +1588,9 @@ where
             pub_args,
             pub_bul_membership_data: bul_data.0,
             bul_memb_is_const: is_memb_data_const,
+            priv_rate_limit_counter: 0,
+            pub_rate_limit_tag: F::zero(),
+            rate_limit: None,
 
             associated_method: method,
             is_scan,
@@ -1447,6 +1707,89 @@ where
         )
     }
 
+    /// Execute a method and create callbacks as in [`User::exec_method_create_cb`], checking the
+    /// anonymity set of the bulletin first.
+    ///
+    /// If the bulletin reports fewer than `guard.k` users (or does not report its anonymity set),
+    /// a guard in [`Guardrail::Refuse`](`crate::generic::anonymity::Guardrail::Refuse`) mode
+    /// refuses to interact, and the user is left unchanged. In
+    /// [`Guardrail::Warn`](`crate::generic::anonymity::Guardrail::Warn`) mode, the interaction
+    /// proceeds and the failed check is returned alongside the executed method, so the client can
+    /// warn the user.
+    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::type_complexity)]
+    pub fn exec_method_create_cb_k_anonymous<
+        H: FieldHash<F>,
+        PubArgs: Clone + std::fmt::Debug,
+        PubArgsVar: AllocVar<PubArgs, F> + Clone,
+        PrivArgs: Clone + std::fmt::Debug,
+        PrivArgsVar: AllocVar<PrivArgs, F> + Clone,
+        CBArgs: Clone + std::fmt::Debug,
+        CBArgsVar: AllocVar<CBArgs, F> + Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        Snark: SNARK<F, Error = SynthesisError>,
+        Bul: PublicUserBul<F, U>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        method: Interaction<
+            F,
+            U,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            NUMCBS,
+        >,
+        rpks: [Crypto::SigPK; NUMCBS],
+        cur_time: Time<F>,
+        bul: &Bul,
+        is_memb_data_const: bool,
+        pk: &Snark::ProvingKey,
+        pub_args: PubArgs,
+        priv_args: PrivArgs,
+        guard: &AnonymityGuard,
+    ) -> Result<
+        (
+            ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>,
+            Option<AnonymityError>,
+        ),
+        SynthesisError,
+    > {
+        let check = guard.check(bul);
+        if !guard.permits(&check) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let out = self.exec_method_create_cb::<
+            H,
+            PubArgs,
+            PubArgsVar,
+            PrivArgs,
+            PrivArgsVar,
+            CBArgs,
+            CBArgsVar,
+            Crypto,
+            Snark,
+            Bul,
+            NUMCBS,
+        >(
+            rng,
+            method,
+            rpks,
+            cur_time,
+            bul,
+            is_memb_data_const,
+            pk,
+            pub_args,
+            priv_args,
+        )?;
+        Ok((out, check.err()))
+    }
+
     /// Get the constraint system for executing a method and creating callbacks.
     ///
     /// Useful for debugging.
@@ -1745,6 +2088,10 @@ where
     ///     <DummyStore as UserBul<Fr, Data>>::verify_interact_and_append::<PubScan, Groth, 0>(&mut DummyStore, scan_meth.new_object.clone(), scan_meth.old_nullifier.clone(), ps.clone(), scan_meth.cb_com_list.clone(), scan_meth.proof.clone(), None, &vks).unwrap();
     /// }
     /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "scan_callbacks", skip_all, fields(scans = NUMSCANS))
+    )]
     pub fn scan_callbacks<
         H: FieldHash<F>,
         CBArgs: Clone + std::fmt::Debug + PartialEq + Eq,
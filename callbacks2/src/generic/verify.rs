@@ -0,0 +1,130 @@
+use crate::{
+    crypto::enc::AECipherSigZK,
+    generic::{interaction::interaction_public_inputs, user::ExecutedMethod},
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_snark::SNARK;
+
+/// An error from verifying an executed method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The public arguments or membership data could not be converted to field elements.
+    InvalidInputs,
+    /// The proof does not verify against the key and public inputs.
+    InvalidProof,
+    /// The proof system failed while verifying, for example on a malformed key.
+    Snark(String),
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::InvalidInputs => {
+                write!(f, "public inputs can not be converted to field elements")
+            }
+            VerifyError::InvalidProof => write!(f, "the proof is invalid"),
+            VerifyError::Snark(e) => write!(f, "verification failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Verify the proof of an executed method, without a bulletin.
+///
+/// Bulletins verify interactions with
+/// [`UserBul::verify_interact_and_append`](`crate::generic::bulletin::UserBul::verify_interact_and_append`),
+/// which additionally checks the nullifier is fresh and stores the new object. Relying parties
+/// which only check proofs (for example, an auditor of the bulletin, or a service trusting another
+/// party to keep the bulletin) may instead call this function.
+///
+/// Note that this **only** checks the proof. It does not check that the old nullifier is unseen,
+/// that the membership data is current, or that the callback tickets are fresh.
+///
+/// # Arguments
+///- `verif_key`: The verifying key for the interaction.
+///- `exec`: The executed method produced by the user.
+///- `pub_args`: The public arguments of the method.
+///- `memb_data`: The membership data the proof was made against, or `None` if it was constant
+///  (and so encoded within the key).
+///
+/// # Example
+/// ```rust
+/// # use zk_callbacks::zk_object;
+/// # use zk_callbacks::generic::user::User;
+/// # use zk_callbacks::generic::verify::{verify_execution, VerifyError};
+/// # use rand::thread_rng;
+/// # use ark_bn254::{Bn254 as E, Fr};
+/// # use ark_r1cs_std::eq::EqGadget;
+/// # use zk_callbacks::generic::interaction::Interaction;
+/// # use zk_callbacks::generic::object::Time;
+/// # use ark_relations::r1cs::SynthesisError;
+/// # use zk_callbacks::generic::user::UserVar;
+/// # use ark_r1cs_std::fields::fp::FpVar;
+/// # use ark_groth16::Groth16;
+/// # use ark_r1cs_std::prelude::Boolean;
+/// # use zk_callbacks::impls::hash::Poseidon;
+/// # use zk_callbacks::impls::dummy::DummyStore;
+/// # use zk_callbacks::impls::centralized::crypto::NoSigOTP;
+/// # type Groth = Groth16<E>;
+/// #[zk_object(Fr)]
+/// #[derive(Default)]
+/// struct Data {
+///     karma: Fr,
+/// }
+///
+/// fn method<'a>(old_user: &'a User<Fr, Data>, _pub: (), _priv: ()) -> User<Fr, Data> {
+///     old_user.clone()
+/// }
+///
+/// fn predicate<'a>(old_user: &'a UserVar<Fr, Data>, new_user: &'a UserVar<Fr, Data>, _pub: (), _priv: ()) -> Result<Boolean<Fr>, SynthesisError> {
+///     old_user.data.karma.is_eq(&new_user.data.karma)
+/// }
+///
+/// let int = Interaction {
+///     meth: (method, predicate),
+///     callbacks: [],
+/// };
+///
+/// let mut rng = thread_rng();
+/// let (pk, vk) = int.generate_keys::<Poseidon<2>, Groth, NoSigOTP<Fr>, DummyStore>(&mut rng, Some(()), None, false);
+///
+/// let mut u = User::create(Data::default(), &mut rng);
+/// let mut exec = u.interact::<Poseidon<2>, (), (), (), (), Fr, FpVar<Fr>, NoSigOTP<Fr>, Groth, DummyStore, 0>(&mut rng, int, [], Time::from(0), ((), ()), true, &pk, (), (), false).unwrap();
+///
+/// assert_eq!(verify_execution::<_, _, _, _, _, (), 0>(&vk, &exec, &(), None), Ok(()));
+///
+/// exec.new_object = Fr::from(0);
+/// assert_eq!(verify_execution::<_, _, _, _, _, (), 0>(&vk, &exec, &(), None), Err(VerifyError::InvalidProof));
+/// ```
+pub fn verify_execution<
+    F: PrimeField + Absorb,
+    Snark: SNARK<F>,
+    PubArgs: ToConstraintField<F>,
+    CBArgs: Clone,
+    Crypto: AECipherSigZK<F, CBArgs>,
+    MembPub: ToConstraintField<F>,
+    const NUMCBS: usize,
+>(
+    verif_key: &Snark::VerifyingKey,
+    exec: &ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>,
+    pub_args: &PubArgs,
+    memb_data: Option<&MembPub>,
+) -> Result<(), VerifyError> {
+    let pub_inputs = interaction_public_inputs(
+        exec.new_object,
+        exec.old_nullifier,
+        pub_args,
+        &exec.cb_com_list,
+        memb_data,
+        exec.rate_limit_tag.as_ref(),
+    )
+    .ok_or(VerifyError::InvalidInputs)?;
+
+    match Snark::verify(verif_key, &pub_inputs, &exec.proof) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(VerifyError::InvalidProof),
+        Err(e) => Err(VerifyError::Snark(e.to_string())),
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    crypto::enc::AECipherSigZK,
+    generic::{
+        keystore::CircuitHash,
+        user::{ExecutedMethod, ProveResult},
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate,
+};
+use ark_snark::SNARK;
+use std::io::{Read, Write};
+
+/// The magic bytes starting every envelope.
+pub const WIRE_MAGIC: [u8; 4] = *b"ZKCB";
+
+/// The current envelope format version.
+///
+/// This is bumped whenever the serialization of a wrapped struct changes, so an old peer reports
+/// [`WireError::UnsupportedVersion`] rather than misreading the bytes.
+pub const WIRE_VERSION: u16 = 1;
+
+const FLAG_COMPRESSED: u8 = 1;
+const FLAG_DIGEST: u8 = 1 << 1;
+
+/// The header of an envelope.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WireHeader {
+    /// The format version the body was written with.
+    pub version: u16,
+    /// Whether curve points in the body are compressed.
+    pub compress: Compress,
+    /// The digest of the circuit a proof in the body was made for, if known.
+    pub circuit_digest: Option<CircuitHash>,
+}
+
+impl std::fmt::Debug for WireHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WireHeader")
+            .field("version", &self.version)
+            .field("compress", &(self.compress == Compress::Yes))
+            .field("circuit_digest", &self.circuit_digest)
+            .finish()
+    }
+}
+
+/// An error when reading an envelope.
+#[derive(Debug)]
+pub enum WireError {
+    /// The bytes do not start with [`WIRE_MAGIC`].
+    BadMagic,
+    /// The envelope was written with an unsupported format version.
+    UnsupportedVersion(u16),
+    /// The header has unknown flags set.
+    BadFlags(u8),
+    /// The body could not be serialized or deserialized.
+    Serialization(SerializationError),
+}
+
+impl std::fmt::Display for WireError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WireError::BadMagic => write!(f, "not a zk-callbacks envelope"),
+            WireError::UnsupportedVersion(v) => {
+                write!(
+                    f,
+                    "unsupported envelope version {} (expected {})",
+                    v, WIRE_VERSION
+                )
+            }
+            WireError::BadFlags(b) => write!(f, "unknown envelope flags {:#04x}", b),
+            WireError::Serialization(e) => write!(f, "envelope body is malformed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl From<SerializationError> for WireError {
+    fn from(value: SerializationError) -> Self {
+        Self::Serialization(value)
+    }
+}
+
+impl From<std::io::Error> for WireError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Serialization(SerializationError::IoError(value))
+    }
+}
+
+/// Write a value into an envelope.
+///
+/// The envelope consists of [`WIRE_MAGIC`], the little-endian [`WIRE_VERSION`], a flags byte,
+/// the circuit digest (if any), and then the canonical serialization of the value.
+///
+/// # Example
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use ark_serialize::Compress;
+/// # use zk_callbacks::generic::wire::{read_envelope, write_envelope, WireError};
+/// let mut bytes = vec![];
+/// write_envelope(&Fr::from(7), &mut bytes, Some([1; 32]), Compress::No).unwrap();
+///
+/// let (x, header) = read_envelope::<Fr>(&bytes[..]).unwrap();
+/// assert_eq!(x, Fr::from(7));
+/// assert_eq!(header.circuit_digest, Some([1; 32]));
+///
+/// // Raw serializations are rejected
+/// assert!(matches!(read_envelope::<Fr>(&bytes[39..]), Err(WireError::BadMagic)));
+/// ```
+pub fn write_envelope<T: CanonicalSerialize>(
+    value: &T,
+    mut writer: impl Write,
+    circuit_digest: Option<CircuitHash>,
+    compress: Compress,
+) -> Result<(), WireError> {
+    let mut flags = 0;
+    if compress == Compress::Yes {
+        flags |= FLAG_COMPRESSED;
+    }
+    if circuit_digest.is_some() {
+        flags |= FLAG_DIGEST;
+    }
+    writer.write_all(&WIRE_MAGIC)?;
+    writer.write_all(&WIRE_VERSION.to_le_bytes())?;
+    writer.write_all(&[flags])?;
+    if let Some(d) = circuit_digest {
+        writer.write_all(&d)?;
+    }
+    value.serialize_with_mode(writer, compress)?;
+    Ok(())
+}
+
+/// Read the header of an envelope, leaving `reader` at the start of the body.
+pub fn read_header(mut reader: impl Read) -> Result<WireHeader, WireError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != WIRE_MAGIC {
+        return Err(WireError::BadMagic);
+    }
+    let mut version = [0u8; 2];
+    reader.read_exact(&mut version)?;
+    let version = u16::from_le_bytes(version);
+    if version != WIRE_VERSION {
+        return Err(WireError::UnsupportedVersion(version));
+    }
+    let mut flags = [0u8; 1];
+    reader.read_exact(&mut flags)?;
+    let flags = flags[0];
+    if flags & !(FLAG_COMPRESSED | FLAG_DIGEST) != 0 {
+        return Err(WireError::BadFlags(flags));
+    }
+    let circuit_digest = if flags & FLAG_DIGEST != 0 {
+        let mut d = [0u8; 32];
+        reader.read_exact(&mut d)?;
+        Some(d)
+    } else {
+        None
+    };
+    Ok(WireHeader {
+        version,
+        compress: if flags & FLAG_COMPRESSED != 0 {
+            Compress::Yes
+        } else {
+            Compress::No
+        },
+        circuit_digest,
+    })
+}
+
+/// Read a value from an envelope written by [`write_envelope`].
+///
+/// The reader is left just past the envelope, so further values may follow it.
+pub fn read_envelope<T: CanonicalDeserialize>(
+    mut reader: impl Read,
+) -> Result<(T, WireHeader), WireError> {
+    let header = read_header(&mut reader)?;
+    let value = T::deserialize_with_mode(reader, header.compress, Validate::Yes)?;
+    Ok((value, header))
+}
+
+impl<
+        F: PrimeField + Absorb,
+        Snark: SNARK<F>,
+        CBArgs: Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        const NUMCBS: usize,
+    > ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>
+{
+    /// Write the executed method into an envelope (see [`write_envelope`]), tagged with its
+    /// [`circuit_digest`](`ExecutedMethod::circuit_digest`).
+    pub fn write_to(&self, writer: impl Write, compress: Compress) -> Result<(), WireError> {
+        write_envelope(self, writer, self.circuit_digest, compress)
+    }
+
+    /// Read an executed method from an envelope written by [`ExecutedMethod::write_to`].
+    pub fn read_from(reader: impl Read) -> Result<Self, WireError> {
+        read_envelope(reader).map(|(v, _)| v)
+    }
+
+    /// Serialize the executed method into an envelope.
+    pub fn to_bytes(&self, compress: Compress) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.write_to(&mut bytes, compress).unwrap();
+        bytes
+    }
+
+    /// Deserialize an executed method from an envelope.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WireError> {
+        Self::read_from(bytes)
+    }
+}
+
+impl<F: PrimeField + Absorb, S: SNARK<F>> ProveResult<F, S> {
+    /// Write the result into an envelope (see [`write_envelope`]).
+    pub fn write_to(&self, writer: impl Write, compress: Compress) -> Result<(), WireError> {
+        write_envelope(self, writer, None, compress)
+    }
+
+    /// Read a result from an envelope written by [`ProveResult::write_to`].
+    pub fn read_from(reader: impl Read) -> Result<Self, WireError> {
+        read_envelope(reader).map(|(v, _)| v)
+    }
+
+    /// Serialize the result into an envelope.
+    pub fn to_bytes(&self, compress: Compress) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.write_to(&mut bytes, compress).unwrap();
+        bytes
+    }
+
+    /// Deserialize a result from an envelope.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WireError> {
+        Self::read_from(bytes)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::crypto::{
-    enc::{AECipherSigZK, CPACipher},
+    enc::{AECipherSigZK, CPACipher, FieldArgs},
     rr::{RRSigner, RRVerifier},
 };
 #[cfg(feature = "folding")]
@@ -417,3 +417,235 @@ where
 
     type Rand = F;
 }
+
+/// A one time pad over callback arguments made of several field elements.
+///
+/// Each element of the arguments is padded with its own key element, so structured arguments
+/// (see [`FieldArgs`] and [`callback_args`](`crate::callback_args`)) may be encrypted without
+/// packing them into a single field element. As with [`NoSigOTP`], there are no signatures, so
+/// this is for the centralized setting.
+pub struct ArgsOTP<F: PrimeField, A> {
+    key: Vec<F>,
+    _args: PhantomData<fn() -> A>,
+}
+
+/// The type which implements AECipherSigZK for structured arguments, with an OTP and no
+/// signatures. This is [`NoSigOTP`] for arguments implementing [`FieldArgs`].
+pub type NoSigArgsOTP<F, A> = ArgsOTP<F, A>;
+
+impl<F: PrimeField, A> Clone for ArgsOTP<F, A> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField, A> std::fmt::Debug for ArgsOTP<F, A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ArgsOTP").field(&self.key).finish()
+    }
+}
+
+impl<F: PrimeField, A> PartialEq for ArgsOTP<F, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<F: PrimeField, A> Eq for ArgsOTP<F, A> {}
+
+impl<F: PrimeField, A: FieldArgs<F>> Default for ArgsOTP<F, A> {
+    fn default() -> Self {
+        Self {
+            key: vec![F::zero(); A::default().to_fields().len()],
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField, A> CanonicalSerialize for ArgsOTP<F, A> {
+    fn serialize_with_mode<W: std::io::Write>(
+        &self,
+        writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        self.key.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.key.serialized_size(compress)
+    }
+}
+
+impl<F: PrimeField, A> Valid for ArgsOTP<F, A> {
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        self.key.check()
+    }
+}
+
+impl<F: PrimeField, A> CanonicalDeserialize for ArgsOTP<F, A> {
+    fn deserialize_with_mode<R: std::io::Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        Ok(Self {
+            key: Vec::deserialize_with_mode(reader, compress, validate)?,
+            _args: PhantomData,
+        })
+    }
+}
+
+impl<F: PrimeField, A> ToConstraintField<F> for ArgsOTP<F, A> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(self.key.clone())
+    }
+}
+
+/// An [`ArgsOTP`] key in-circuit.
+#[derive(Clone)]
+pub struct ArgsOTPVar<F: PrimeField>(pub Vec<FpVar<F>>);
+
+impl<F: PrimeField> ToConstraintFieldGadget<F> for ArgsOTPVar<F> {
+    fn to_constraint_field(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(self.0.clone())
+    }
+}
+
+impl<F: PrimeField, A> AllocVar<ArgsOTP<F, A>, F> for ArgsOTPVar<F> {
+    fn new_variable<T: Borrow<ArgsOTP<F, A>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let key = rec
+                .key
+                .iter()
+                .map(|k| FpVar::new_variable(ns!(cs, "key"), || Ok(*k), mode))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(ArgsOTPVar(key))
+        })
+    }
+}
+
+impl<F: PrimeField, A: FieldArgs<F>> CPACipher<F> for ArgsOTP<F, A>
+where
+    Standard: Distribution<F>,
+{
+    type M = A;
+    type C = A;
+    type MV = A::Var;
+    type CV = A::Var;
+
+    type KeyVar = ArgsOTPVar<F>;
+
+    fn keygen(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        let len = A::default().to_fields().len();
+        Self {
+            key: (0..len).map(|_| rng.gen()).collect(),
+            _args: PhantomData,
+        }
+    }
+
+    fn encrypt(&self, message: Self::M) -> Self::C {
+        let fields: Vec<F> = message
+            .to_fields()
+            .iter()
+            .zip(&self.key)
+            .map(|(m, k)| *m + k)
+            .collect();
+        A::from_fields(&fields)
+    }
+
+    fn decrypt(&self, ciphertext: Self::C) -> Self::M {
+        let fields: Vec<F> = ciphertext
+            .to_fields()
+            .iter()
+            .zip(&self.key)
+            .map(|(c, k)| *c - k)
+            .collect();
+        A::from_fields(&fields)
+    }
+
+    fn decrypt_in_zk(key: Self::KeyVar, ciphertext: Self::CV) -> Result<Self::MV, SynthesisError> {
+        let fields: Vec<FpVar<F>> = A::to_fields_zk(&ciphertext)?
+            .iter()
+            .zip(&key.0)
+            .map(|(c, k)| c - k)
+            .collect();
+        A::from_fields_zk(&fields)
+    }
+}
+
+impl<F: PrimeField, A: FieldArgs<F>> AECipherSigZK<F, A> for ArgsOTP<F, A>
+where
+    Standard: Distribution<F>,
+{
+    type Sig = ();
+    type SigPK = FakeSigPubkey<F>;
+    type SigPKV = FakeSigPubkeyVar<F>;
+
+    type SigSK = FakeSigPrivkey<F>;
+
+    type AV = A::Var;
+
+    type Ct = A;
+
+    type EncKey = ArgsOTP<F, A>;
+
+    type EncKeyVar = ArgsOTPVar<F>;
+
+    type Rand = F;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_r1cs_std::{eq::EqGadget, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::thread_rng;
+
+    crate::callback_args! {
+        struct Moderation, ModerationVar {
+            penalty,
+            reason,
+            expiry,
+        }
+    }
+
+    // Tests that structured arguments decrypt to the same values in and out of circuit
+    #[test]
+    fn args_otp_decrypt() {
+        let mut rng = thread_rng();
+        let key = ArgsOTP::<Fr, Moderation<Fr>>::keygen(&mut rng);
+        let args = Moderation {
+            penalty: Fr::from(5u8),
+            reason: Fr::from(2u8),
+            expiry: Fr::from(100u8),
+        };
+
+        let ct = key.encrypt(args.clone());
+        assert_ne!(ct, args);
+        assert_eq!(key.decrypt(ct.clone()), args);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let key_var = ArgsOTPVar::new_witness(cs.clone(), || Ok(key.clone())).unwrap();
+        let ct_var = ModerationVar::new_witness(cs.clone(), || Ok(ct)).unwrap();
+        let args_var = ModerationVar::new_input(cs.clone(), || Ok(args.clone())).unwrap();
+
+        let out = ArgsOTP::<Fr, Moderation<Fr>>::decrypt_in_zk(key_var, ct_var).unwrap();
+        out.penalty.enforce_equal(&args_var.penalty).unwrap();
+        out.reason.enforce_equal(&args_var.reason).unwrap();
+        out.expiry.enforce_equal(&args_var.expiry).unwrap();
+        assert_eq!(out.expiry.value().unwrap(), args.expiry);
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::impls::centralized::ds::{
+    sig::{gr_schnorr::GrumpkinSchnorr, Signature},
+    sigrange::SigRangeStore,
+    sigstore::{CentralStore, NonmembStore},
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_grumpkin::Fq as BnFr;
+use rand::{
+    distributions::{Distribution, Standard},
+    CryptoRng, RngCore,
+};
+use std::collections::BTreeMap;
+
+/// The current roots of a group, for clients to check they hold the same view of the group as
+/// other members.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupRoots<F: PrimeField> {
+    /// The head of the transition log of the user bulletin. See
+    /// [`SigObjStore::log_head`](`super::sigstore::SigObjStore::log_head`).
+    pub user_root: F,
+    /// The current epoch of the callback bulletin.
+    pub epoch: F,
+    /// The root of the callback bulletin at the current epoch. See
+    /// [`CallbackStore::get_epoch_root`](`super::sigstore::CallbackStore::get_epoch_root`).
+    pub callback_root: F,
+}
+
+/// Many [`CentralStore`]s, one for each group, so a single service may host independent groups.
+///
+/// Groups are identified by a string (for example, the id of a group chat). Each group is created
+/// with fresh keys for both its user and callback bulletins, so an object which joined one group
+/// can not prove membership in another, and callbacks called in one group are never visible in
+/// another.
+///
+/// Note that proving keys generated with constant membership data (see
+/// [`Interaction::generate_keys`](`crate::generic::interaction::Interaction::generate_keys`)) are
+/// specific to a group, as they fix the public key of the user bulletin.
+///
+/// # Example
+/// ```rust
+/// # use zk_callbacks::generic::bulletin::JoinableBulletin;
+/// # use zk_callbacks::impls::centralized::ds::groups::GRSchnorrGroupedStore;
+/// # use zk_callbacks::impls::centralized::ds::sigstore::GRSchnorrObjStore;
+/// # use ark_grumpkin::Fq as Fr;
+/// # use rand::thread_rng;
+/// let mut rng = thread_rng();
+/// let mut store: GRSchnorrGroupedStore<Fr> = GRSchnorrGroupedStore::new();
+///
+/// let a = store.get_or_create("a", &mut rng);
+/// <GRSchnorrObjStore as JoinableBulletin<Fr, bool>>::join_bul(&mut a.obj_bul, Fr::from(1), ()).unwrap();
+/// store.get_or_create("b", &mut rng);
+///
+/// let (a, b) = (store.get("a").unwrap(), store.get("b").unwrap());
+/// assert!(a.obj_bul.get_signature_of(&Fr::from(1)).is_some());
+/// assert!(b.obj_bul.get_signature_of(&Fr::from(1)).is_none());
+/// assert_ne!(store.roots("a"), store.roots("b"));
+/// assert_eq!(store.groups().collect::<Vec<_>>(), vec!["a", "b"]);
+/// ```
+#[derive(Clone)]
+pub struct GroupedStore<
+    F: PrimeField + Absorb,
+    S: Signature<F>,
+    B: NonmembStore<F>,
+    A: Clone + ToConstraintField<F>,
+> where
+    Standard: Distribution<F>,
+{
+    groups: BTreeMap<String, CentralStore<F, S, B, A>>,
+}
+
+impl<
+        F: PrimeField + Absorb,
+        S: Signature<F>,
+        B: NonmembStore<F>,
+        A: Clone + ToConstraintField<F>,
+    > Default for GroupedStore<F, S, B, A>
+where
+    Standard: Distribution<F>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<
+        F: PrimeField + Absorb,
+        S: Signature<F>,
+        B: NonmembStore<F>,
+        A: Clone + ToConstraintField<F>,
+    > GroupedStore<F, S, B, A>
+where
+    Standard: Distribution<F>,
+{
+    /// Construct a new store with no groups.
+    pub fn new() -> Self {
+        Self {
+            groups: BTreeMap::new(),
+        }
+    }
+
+    /// Check whether a group exists.
+    pub fn contains(&self, group: &str) -> bool {
+        self.groups.contains_key(group)
+    }
+
+    /// Get the store of a group.
+    pub fn get(&self, group: &str) -> Option<&CentralStore<F, S, B, A>> {
+        self.groups.get(group)
+    }
+
+    /// Get the store of a group mutably.
+    pub fn get_mut(&mut self, group: &str) -> Option<&mut CentralStore<F, S, B, A>> {
+        self.groups.get_mut(group)
+    }
+
+    /// Get the store of a group, creating the group with fresh keys if it does not exist.
+    pub fn get_or_create(
+        &mut self,
+        group: &str,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> &mut CentralStore<F, S, B, A> {
+        self.groups
+            .entry(group.to_string())
+            .or_insert_with(|| CentralStore::new(rng))
+    }
+
+    /// Insert the store of a group, returning the previous store of the group if there was one.
+    ///
+    /// This may be used to restore a group from an existing store.
+    pub fn insert(
+        &mut self,
+        group: &str,
+        store: CentralStore<F, S, B, A>,
+    ) -> Option<CentralStore<F, S, B, A>> {
+        self.groups.insert(group.to_string(), store)
+    }
+
+    /// Remove a group, returning its store.
+    pub fn remove(&mut self, group: &str) -> Option<CentralStore<F, S, B, A>> {
+        self.groups.remove(group)
+    }
+
+    /// Iterate over the identifiers of all groups, in order.
+    pub fn groups(&self) -> impl Iterator<Item = &str> {
+        self.groups.keys().map(|g| g.as_str())
+    }
+
+    /// Get the number of groups.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Check whether there are no groups.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Get the current roots of a group.
+    pub fn roots(&self, group: &str) -> Option<GroupRoots<F>> {
+        let store = self.groups.get(group)?;
+        let epoch = store.callback_bul.get_epoch();
+        Some(GroupRoots {
+            user_root: store.obj_bul.log_head(),
+            epoch,
+            callback_root: store.callback_bul.get_epoch_root(epoch)?,
+        })
+    }
+}
+
+/// A grouped central storage system which uses Grumpkin BN254 Schnorr signatures.
+pub type GRSchnorrGroupedStore<A> =
+    GroupedStore<BnFr, GrumpkinSchnorr, SigRangeStore<BnFr, GrumpkinSchnorr>, A>;
@@ -0,0 +1,633 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    generic::{
+        bulletin::{
+            BatchedInteraction, BulError, BulletinDelta, JoinableBulletin, PublicUserBul, UserBul,
+        },
+        object::{Com, ComVar, Nul},
+        user::UserData,
+    },
+    impls::centralized::ds::{
+        sig::{gr_schnorr::GrumpkinSchnorr, Signature},
+        sigstore::{ObjEntry, SigObjStore},
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_grumpkin::Fq as BnFr;
+use ark_r1cs_std::prelude::Boolean;
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate,
+};
+use rand::distributions::{Distribution, Standard};
+
+/// A key and its value in a [`KVBackend`].
+pub type KVEntry = (Vec<u8>, Vec<u8>);
+
+/// A key-value storage backend.
+///
+/// Keys are ordered bytewise, so stores which key entries by big endian index can read their
+/// entries back in order.
+pub trait KVBackend {
+    /// The error returned by the backend.
+    type Error: std::fmt::Debug;
+
+    /// Get the value at some key.
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Write a batch of entries. Implementations should write the batch atomically if possible.
+    fn put_batch(&mut self, entries: Vec<KVEntry>) -> Result<(), Self::Error>;
+
+    /// Get all entries, ordered by key.
+    fn entries(&self) -> Result<Vec<KVEntry>, Self::Error>;
+
+    /// Make all writes durable.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// An in-memory [`KVBackend`]. Nothing survives a restart.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryKV {
+    /// The stored entries.
+    pub map: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl KVBackend for MemoryKV {
+    type Error = ();
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ()> {
+        Ok(self.map.get(key).cloned())
+    }
+
+    fn put_batch(&mut self, entries: Vec<KVEntry>) -> Result<(), ()> {
+        self.map.extend(entries);
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<KVEntry>, ()> {
+        Ok(self.map.clone().into_iter().collect())
+    }
+}
+
+/// A [`KVBackend`] backed by an append-only file.
+///
+/// Each write is appended to the file as length-prefixed key and value, and the file is synced on
+/// [`KVBackend::flush`]. On opening, the file is replayed into memory, with later writes to a key
+/// overriding earlier ones. A truncated record at the end of the file (from a crash during a write)
+/// is cut off, so later writes are appended after the last complete record.
+#[derive(Debug)]
+pub struct FileKV {
+    /// The path to the file.
+    pub path: PathBuf,
+    file: File,
+    map: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+fn read_record(buf: &[u8], pos: &mut usize) -> Option<Vec<u8>> {
+    let len_bytes: [u8; 8] = buf.get(*pos..*pos + 8)?.try_into().ok()?;
+    let len = usize::try_from(u64::from_le_bytes(len_bytes)).ok()?;
+    let rec = buf.get(*pos + 8..*pos + 8 + len)?.to_vec();
+    *pos += 8 + len;
+    Some(rec)
+}
+
+impl FileKV {
+    /// Open (or create) the file at some path, loading any existing entries.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+
+        let mut buf = vec![];
+        file.read_to_end(&mut buf)?;
+
+        let mut map = BTreeMap::new();
+        let mut pos = 0;
+        let mut good = 0;
+        while let (Some(k), Some(v)) = (read_record(&buf, &mut pos), read_record(&buf, &mut pos)) {
+            map.insert(k, v);
+            good = pos;
+        }
+
+        // Drop a torn record left by a crash, so it does not hide later writes
+        if good < buf.len() {
+            file.set_len(good as u64)?;
+            file.sync_data()?;
+        }
+
+        Ok(Self { path, file, map })
+    }
+}
+
+impl KVBackend for FileKV {
+    type Error = std::io::Error;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, std::io::Error> {
+        Ok(self.map.get(key).cloned())
+    }
+
+    fn put_batch(&mut self, entries: Vec<KVEntry>) -> Result<(), std::io::Error> {
+        let mut buf = vec![];
+        for (k, v) in &entries {
+            buf.extend_from_slice(&(k.len() as u64).to_le_bytes());
+            buf.extend_from_slice(k);
+            buf.extend_from_slice(&(v.len() as u64).to_le_bytes());
+            buf.extend_from_slice(v);
+        }
+        self.file.write_all(&buf)?;
+        self.map.extend(entries);
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<KVEntry>, std::io::Error> {
+        Ok(self.map.clone().into_iter().collect())
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.file.sync_data()
+    }
+}
+
+/// A [`KVBackend`] backed by a tree of a [`sled`] database.
+#[cfg(feature = "sled")]
+#[cfg(any(feature = "sled", doc))]
+#[doc(cfg(feature = "sled"))]
+#[derive(Clone, Debug)]
+pub struct SledKV {
+    /// The tree the entries are stored in.
+    pub tree: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+#[cfg(any(feature = "sled", doc))]
+#[doc(cfg(feature = "sled"))]
+impl SledKV {
+    /// Open a named tree of a database.
+    pub fn open(db: &sled::Db, name: &str) -> Result<Self, sled::Error> {
+        Ok(Self {
+            tree: db.open_tree(name)?,
+        })
+    }
+}
+
+#[cfg(feature = "sled")]
+#[cfg(any(feature = "sled", doc))]
+#[doc(cfg(feature = "sled"))]
+impl KVBackend for SledKV {
+    type Error = sled::Error;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, sled::Error> {
+        Ok(self.tree.get(key)?.map(|v| v.to_vec()))
+    }
+
+    fn put_batch(&mut self, entries: Vec<KVEntry>) -> Result<(), sled::Error> {
+        let mut batch = sled::Batch::default();
+        for (k, v) in entries {
+            batch.insert(k, v);
+        }
+        self.tree.apply_batch(batch)
+    }
+
+    fn entries(&self) -> Result<Vec<KVEntry>, sled::Error> {
+        self.tree
+            .iter()
+            .map(|r| r.map(|(k, v)| (k.to_vec(), v.to_vec())))
+            .collect()
+    }
+
+    fn flush(&mut self) -> Result<(), sled::Error> {
+        self.tree.flush().map(|_| ())
+    }
+}
+
+/// A [`KVBackend`] backed by a hash in a Redis server.
+///
+/// Batches are written in a single `MULTI`/`EXEC` transaction. Durability and replication depend
+/// on the configuration of the server.
+#[cfg(feature = "redis")]
+#[cfg(any(feature = "redis", doc))]
+#[doc(cfg(feature = "redis"))]
+pub struct RedisKV {
+    /// The connection to the server.
+    pub conn: std::sync::Mutex<redis::Connection>,
+    /// The name of the hash the entries are stored in.
+    pub name: String,
+}
+
+#[cfg(feature = "redis")]
+#[cfg(any(feature = "redis", doc))]
+#[doc(cfg(feature = "redis"))]
+impl RedisKV {
+    /// Connect to a server, storing entries in a named hash.
+    pub fn open(url: &str, name: &str) -> Result<Self, redis::RedisError> {
+        let conn = redis::Client::open(url)?.get_connection()?;
+        Ok(Self {
+            conn: std::sync::Mutex::new(conn),
+            name: name.to_string(),
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[cfg(any(feature = "redis", doc))]
+#[doc(cfg(feature = "redis"))]
+impl KVBackend for RedisKV {
+    type Error = redis::RedisError;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, redis::RedisError> {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("HGET")
+            .arg(&self.name)
+            .arg(key)
+            .query(&mut *conn)
+    }
+
+    fn put_batch(&mut self, entries: Vec<KVEntry>) -> Result<(), redis::RedisError> {
+        let mut conn = self.conn.lock().unwrap();
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (k, v) in entries {
+            pipe.cmd("HSET").arg(&self.name).arg(k).arg(v).ignore();
+        }
+        pipe.query(&mut *conn)
+    }
+
+    fn entries(&self) -> Result<Vec<KVEntry>, redis::RedisError> {
+        let mut conn = self.conn.lock().unwrap();
+        let map: BTreeMap<Vec<u8>, Vec<u8>> =
+            redis::cmd("HGETALL").arg(&self.name).query(&mut *conn)?;
+        Ok(map.into_iter().collect())
+    }
+}
+
+/// An error in a [`KVObjStore`].
+#[derive(Debug)]
+pub enum KVError<E> {
+    /// The underlying in-memory store failed (for example, signing failed).
+    Store,
+    /// The backend failed.
+    Backend(E),
+    /// An entry could not be serialized or deserialized.
+    Serialization(SerializationError),
+}
+
+/// A [`SigObjStore`] which writes through to a [`KVBackend`].
+///
+/// The signature logic is that of the in-memory store. Every entry is written to the backend, and
+/// the backend is flushed, before an append returns. Entries are keyed by their big endian index,
+/// so the store can be reopened with [`KVObjStore::open`].
+///
+/// The private key is not stored in the backend, and must be provided when opening the store.
+///
+/// Note that this implements [`PublicUserBul`], [`UserBul`], and [`JoinableBulletin`].
+#[derive(Clone)]
+pub struct KVObjStore<F: PrimeField + Absorb, S: Signature<F>, K: KVBackend> {
+    /// The in-memory store, which mirrors the backend.
+    pub store: SigObjStore<F, S>,
+    /// The backend.
+    pub backend: K,
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, K: KVBackend + std::fmt::Debug> std::fmt::Debug
+    for KVObjStore<F, S, K>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KVObjStore")
+            .field("coms", &self.store.coms)
+            .field("backend", &self.backend)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, K: KVBackend> KVObjStore<F, S, K> {
+    /// Open the store on a backend, loading any existing entries.
+    pub fn open(backend: K, privkey: S::Privkey) -> Result<Self, KVError<K::Error>> {
+        let entries = backend
+            .entries()
+            .map_err(KVError::Backend)?
+            .into_iter()
+            .map(|(_, v)| {
+                <ObjEntry<F, S>>::deserialize_with_mode(&*v, Compress::No, Validate::Yes)
+                    .map_err(KVError::Serialization)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            store: SigObjStore::from(privkey, entries),
+            backend,
+        })
+    }
+
+    fn entry(&self, i: usize) -> Result<Vec<u8>, KVError<K::Error>> {
+        let mut bytes = vec![];
+        (
+            self.store.coms[i],
+            self.store.old_nuls[i],
+            self.store.cb_com_lists[i].clone(),
+            self.store.sigs[i].clone(),
+        )
+            .serialize_with_mode(&mut bytes, Compress::No)
+            .map_err(KVError::Serialization)?;
+        Ok(bytes)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.store.coms.truncate(len);
+        self.store.old_nuls.truncate(len);
+        self.store.cb_com_lists.truncate(len);
+        self.store.sigs.truncate(len);
+    }
+
+    /// Write all entries from index `from` onwards in a single batch, and flush.
+    fn write_from(&mut self, from: usize) -> Result<(), KVError<K::Error>> {
+        let batch = (from..self.store.coms.len())
+            .map(|i| Ok(((i as u64).to_be_bytes().to_vec(), self.entry(i)?)))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.backend.put_batch(batch).map_err(KVError::Backend)?;
+        self.backend.flush().map_err(KVError::Backend)
+    }
+
+    /// Write all entries from index `from` onwards. If the write fails, the in-memory store is
+    /// rolled back to `from` entries.
+    fn persist_from(&mut self, from: usize) -> Result<(), KVError<K::Error>> {
+        let res = self.write_from(from);
+        if res.is_err() {
+            self.truncate(from);
+        }
+        res
+    }
+
+    /// Get the public key.
+    pub fn get_pubkey(&self) -> S::Pubkey {
+        self.store.get_pubkey()
+    }
+
+    /// Get the full database.
+    pub fn get_db(&self) -> Vec<ObjEntry<F, S>> {
+        self.store.get_db()
+    }
+
+    /// Rotate keys. Resigns all object commitments with the new key, and rewrites all entries.
+    pub fn rotate_key(&mut self, new_key: S::Privkey) -> Result<(), KVError<K::Error>> {
+        self.store.rotate_key(new_key).map_err(|_| KVError::Store)?;
+        self.write_from(0)
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>, K: KVBackend> PublicUserBul<F, U>
+    for KVObjStore<F, S, K>
+{
+    type MembershipWitness = S::Sig;
+
+    type MembershipWitnessVar = S::SigVar;
+
+    type MembershipPub = S::Pubkey;
+
+    type MembershipPubVar = S::PubkeyVar;
+
+    fn verify_in<PubArgs: ToConstraintField<F>, Snark: ark_snark::SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        args: PubArgs,
+        proof: Snark::Proof,
+        memb_data: Self::MembershipPub,
+        verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::verify_in::<PubArgs, Snark, NUMCBS>(
+            &self.store,
+            object,
+            old_nul,
+            cb_com_list,
+            args,
+            proof,
+            memb_data,
+            verif_key,
+        )
+    }
+
+    fn get_membership_data(&self, object: Com<F>) -> Option<(S::Pubkey, S::Sig)> {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::get_membership_data(&self.store, object)
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::enforce_membership_of(
+            data_var,
+            extra_witness,
+            extra_pub,
+        )
+    }
+
+    fn get_updates_since(&self, version: u64) -> Option<BulletinDelta<F, S::Pubkey, S::Sig>> {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::get_updates_since(&self.store, version)
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>, K: KVBackend> UserBul<F, U>
+    for KVObjStore<F, S, K>
+{
+    type Error = KVError<K::Error>;
+
+    fn has_never_received_nul(&self, nul: &Nul<F>) -> bool {
+        <SigObjStore<F, S> as UserBul<F, U>>::has_never_received_nul(&self.store, nul)
+    }
+
+    fn append_value<
+        PubArgs: ToConstraintField<F>,
+        Snark: ark_snark::SNARK<F>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        args: PubArgs,
+        proof: Snark::Proof,
+        memb_data: Option<Self::MembershipPub>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> Result<(), Self::Error> {
+        let len = self.store.coms.len();
+        <SigObjStore<F, S> as UserBul<F, U>>::append_value::<PubArgs, Snark, NUMCBS>(
+            &mut self.store,
+            object,
+            old_nul,
+            cb_com_list,
+            args,
+            proof,
+            memb_data,
+            verif_key,
+        )
+        .map_err(|_| KVError::Store)?;
+        self.persist_from(len)
+    }
+
+    fn verify_interact_and_append_batch<
+        PubArgs: ToConstraintField<F> + Clone,
+        Snark: ark_snark::SNARK<F>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        batch: Vec<BatchedInteraction<F, PubArgs, Snark, S::Pubkey, NUMCBS>>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> Vec<Result<(), BulError<KVError<K::Error>>>> {
+        let len = self.store.coms.len();
+        let out = <SigObjStore<F, S> as UserBul<F, U>>::verify_interact_and_append_batch::<
+            PubArgs,
+            Snark,
+            NUMCBS,
+        >(&mut self.store, batch, verif_key);
+
+        let persisted = self.persist_from(len).is_ok();
+
+        out.into_iter()
+            .map(|r| match r {
+                Ok(()) if persisted => Ok(()),
+                Ok(()) => Err(BulError::AppendError(KVError::Store)),
+                Err(BulError::VerifyError) => Err(BulError::VerifyError),
+                Err(BulError::CircuitMismatch) => Err(BulError::CircuitMismatch),
+                Err(BulError::AppendError(())) => Err(BulError::AppendError(KVError::Store)),
+            })
+            .collect()
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>, K: KVBackend> JoinableBulletin<F, U>
+    for KVObjStore<F, S, K>
+where
+    Standard: Distribution<F>,
+{
+    type PubData = ();
+
+    fn join_bul(&mut self, object: Com<F>, _pub_data: ()) -> Result<(), Self::Error> {
+        let len = self.store.coms.len();
+        <SigObjStore<F, S> as JoinableBulletin<F, U>>::join_bul(&mut self.store, object, ())
+            .map_err(|_| KVError::Store)?;
+        self.persist_from(len)
+    }
+}
+
+/// A user object store which uses Grumpkin BN254 Schnorr signatures, written through to a
+/// key-value backend.
+pub type GRSchnorrKVObjStore<K> = KVObjStore<BnFr, GrumpkinSchnorr, K>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ark_ff::UniformRand;
+    use rand::thread_rng;
+    use std::fs;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("zk-callbacks-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn record(k: &[u8], v: &[u8]) -> KVEntry {
+        (k.to_vec(), v.to_vec())
+    }
+
+    // Tests that entries survive reopening, with later writes to a key overriding earlier ones
+    #[test]
+    fn file_kv_reopen() {
+        let path = temp_path("kv-reopen");
+        let mut kv = FileKV::open(&path).unwrap();
+        kv.put_batch(vec![record(b"a", b"1"), record(b"b", b"2")])
+            .unwrap();
+        kv.put_batch(vec![record(b"a", b"3")]).unwrap();
+        kv.flush().unwrap();
+        drop(kv);
+
+        let kv = FileKV::open(&path).unwrap();
+        assert_eq!(
+            kv.entries().unwrap(),
+            vec![record(b"a", b"3"), record(b"b", b"2")]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    // Tests that a torn record from a crash is cut off, and does not hide writes made afterwards
+    #[test]
+    fn file_kv_crash_recovery() {
+        let path = temp_path("kv-crash");
+        let mut kv = FileKV::open(&path).unwrap();
+        kv.put_batch(vec![record(b"a", b"1")]).unwrap();
+        kv.flush().unwrap();
+        drop(kv);
+
+        // Simulate a crash halfway through writing a record
+        let good_len = fs::metadata(&path).unwrap().len();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&10u64.to_le_bytes()).unwrap();
+        file.write_all(b"torn").unwrap();
+        drop(file);
+
+        let mut kv = FileKV::open(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), good_len);
+        assert_eq!(kv.entries().unwrap(), vec![record(b"a", b"1")]);
+
+        kv.put_batch(vec![record(b"b", b"2")]).unwrap();
+        kv.flush().unwrap();
+        drop(kv);
+
+        let kv = FileKV::open(&path).unwrap();
+        assert_eq!(
+            kv.entries().unwrap(),
+            vec![record(b"a", b"1"), record(b"b", b"2")]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    // Tests that a store on a file backend reopens with the same entries and public key
+    #[test]
+    fn kv_obj_store_reopen() {
+        let mut rng = thread_rng();
+        let path = temp_path("kv-store");
+        let privkey = GrumpkinSchnorr::gen_key(&mut rng);
+
+        let mut store =
+            GRSchnorrKVObjStore::open(FileKV::open(&path).unwrap(), privkey.clone()).unwrap();
+        let coms: Vec<BnFr> = (0..3).map(|_| BnFr::rand(&mut rng)).collect();
+        for com in &coms {
+            <GRSchnorrKVObjStore<FileKV> as JoinableBulletin<BnFr, BnFr>>::join_bul(
+                &mut store,
+                *com,
+                (),
+            )
+            .unwrap();
+        }
+        let db = store.get_db();
+        drop(store);
+
+        let store = GRSchnorrKVObjStore::open(FileKV::open(&path).unwrap(), privkey).unwrap();
+        assert_eq!(store.store.coms, coms);
+        assert_eq!(store.get_db().len(), db.len());
+        for com in coms {
+            assert!(
+                <GRSchnorrKVObjStore<FileKV> as PublicUserBul<BnFr, BnFr>>::get_membership_data(
+                    &store, com
+                )
+                .is_some()
+            );
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
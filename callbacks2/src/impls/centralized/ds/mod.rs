@@ -1,9 +1,27 @@
 /// Signatures with in-circuit verification.
 pub mod sig;
 
+/// Pluggable key-value backends (in-memory, file, [`sled`], Redis) for signature stores.
+pub mod kv;
+
+/// Disk-backed signature stores.
+///
+/// These wrap the signature stores with a [`sled`] database, so the bulletins survive a restart.
+#[cfg(feature = "sled")]
+#[cfg(any(feature = "sled", doc))]
+#[doc(cfg(feature = "sled"))]
+pub mod persistent;
+
+/// Stores hosting many independent groups, each with its own bulletins and keys.
+pub mod groups;
+
 /// A range store which is signed for nonmembership proofs.
 pub mod sigrange;
 
+/// A signature store co-signed by a committee, with membership requiring a threshold of
+/// aggregated signatures.
+pub mod threshold;
+
 /// A signature store. One can verify membership through proof of knowledge of a signature from the
 /// service.
 pub mod sigstore;
@@ -0,0 +1,631 @@
+use crate::{
+    generic::{
+        bulletin::{
+            BatchedInteraction, BulError, BulletinDelta, CallbackBul, JoinableBulletin,
+            PublicCallbackBul, PublicUserBul, UserBul,
+        },
+        object::{Com, ComVar, Nul, Time, TimeVar},
+        user::UserData,
+    },
+    impls::centralized::{
+        crypto::{FakeSigPubkey, FakeSigPubkeyVar, NoSigOTP},
+        ds::{
+            sig::Signature,
+            sigstore::{CallbackStore, CalledEntry, NonmembStore, ObjEntry, SigObjStore},
+        },
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::Boolean};
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate,
+};
+use rand::{
+    distributions::{Distribution, Standard},
+    CryptoRng, RngCore,
+};
+
+/// An error in a persistent store.
+#[derive(Debug)]
+pub enum PersistError {
+    /// The underlying in-memory store failed (for example, signing failed).
+    Store,
+    /// Reading or writing to the database failed.
+    Db(sled::Error),
+    /// An entry could not be serialized or deserialized.
+    Serialization(SerializationError),
+}
+
+impl std::fmt::Display for PersistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PersistError::Store => write!(f, "store operation failed"),
+            PersistError::Db(e) => write!(f, "database error: {}", e),
+            PersistError::Serialization(e) => write!(f, "serialization failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PersistError {}
+
+fn encode<T: CanonicalSerialize>(value: &T) -> Result<Vec<u8>, PersistError> {
+    let mut bytes = vec![];
+    value
+        .serialize_with_mode(&mut bytes, Compress::No)
+        .map_err(PersistError::Serialization)?;
+    Ok(bytes)
+}
+
+fn load<T: CanonicalDeserialize>(tree: &sled::Tree) -> Result<Vec<T>, PersistError> {
+    tree.iter()
+        .values()
+        .map(|v| {
+            let v = v.map_err(PersistError::Db)?;
+            T::deserialize_with_mode(&*v, Compress::No, Validate::Yes)
+                .map_err(PersistError::Serialization)
+        })
+        .collect()
+}
+
+/// A [`SigObjStore`] backed by a [`sled`] database.
+///
+/// Every entry is written and flushed to disk before an append returns, so the store can be
+/// reopened with [`PersistentObjStore::open`] after a restart or crash. Entries are keyed by their
+/// index, so the database preserves the order of the bulletin.
+///
+/// The private key is not stored in the database, and must be provided when opening the store.
+///
+/// Note that this implements [`PublicUserBul`], [`UserBul`], and [`JoinableBulletin`].
+#[derive(Clone)]
+pub struct PersistentObjStore<F: PrimeField + Absorb, S: Signature<F>> {
+    /// The in-memory store, which mirrors the database.
+    pub store: SigObjStore<F, S>,
+    tree: sled::Tree,
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>> std::fmt::Debug for PersistentObjStore<F, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PersistentObjStore")
+            .field("coms", &self.store.coms)
+            .field("tree", &self.tree)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>> PersistentObjStore<F, S> {
+    /// Open the store in the `objects` tree of a database, loading any existing entries.
+    pub fn open(db: &sled::Db, privkey: S::Privkey) -> Result<Self, PersistError> {
+        let tree = db.open_tree("objects").map_err(PersistError::Db)?;
+        let entries = load::<ObjEntry<F, S>>(&tree)?;
+        Ok(Self {
+            store: SigObjStore::from(privkey, entries),
+            tree,
+        })
+    }
+
+    fn entry(&self, i: usize) -> Result<Vec<u8>, PersistError> {
+        encode(&(
+            self.store.coms[i],
+            self.store.old_nuls[i],
+            self.store.cb_com_lists[i].clone(),
+            self.store.sigs[i].clone(),
+        ))
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.store.coms.truncate(len);
+        self.store.old_nuls.truncate(len);
+        self.store.cb_com_lists.truncate(len);
+        self.store.sigs.truncate(len);
+    }
+
+    /// Write all entries from index `from` onwards in a single batch, and flush.
+    fn write_from(&self, from: usize) -> Result<(), PersistError> {
+        let mut batch = sled::Batch::default();
+        for i in from..self.store.coms.len() {
+            batch.insert((i as u64).to_be_bytes().to_vec(), self.entry(i)?);
+        }
+        self.tree.apply_batch(batch).map_err(PersistError::Db)?;
+        self.tree.flush().map_err(PersistError::Db)?;
+        Ok(())
+    }
+
+    /// Write all entries from index `from` onwards. If the write fails, the in-memory store is
+    /// rolled back to `from` entries.
+    fn persist_from(&mut self, from: usize) -> Result<(), PersistError> {
+        let res = self.write_from(from);
+        if res.is_err() {
+            self.truncate(from);
+        }
+        res
+    }
+
+    /// Get the public key.
+    pub fn get_pubkey(&self) -> S::Pubkey {
+        self.store.get_pubkey()
+    }
+
+    /// Get the full database.
+    pub fn get_db(&self) -> Vec<ObjEntry<F, S>> {
+        self.store.get_db()
+    }
+
+    /// Rotate keys. Resigns all object commitments with the new key, and rewrites all entries.
+    pub fn rotate_key(&mut self, new_key: S::Privkey) -> Result<(), PersistError> {
+        self.store
+            .rotate_key(new_key)
+            .map_err(|_| PersistError::Store)?;
+        self.write_from(0)
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> PublicUserBul<F, U>
+    for PersistentObjStore<F, S>
+{
+    type MembershipWitness = S::Sig;
+
+    type MembershipWitnessVar = S::SigVar;
+
+    type MembershipPub = S::Pubkey;
+
+    type MembershipPubVar = S::PubkeyVar;
+
+    fn verify_in<PubArgs: ToConstraintField<F>, Snark: ark_snark::SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        args: PubArgs,
+        proof: Snark::Proof,
+        memb_data: Self::MembershipPub,
+        verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::verify_in::<PubArgs, Snark, NUMCBS>(
+            &self.store,
+            object,
+            old_nul,
+            cb_com_list,
+            args,
+            proof,
+            memb_data,
+            verif_key,
+        )
+    }
+
+    fn get_membership_data(&self, object: Com<F>) -> Option<(S::Pubkey, S::Sig)> {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::get_membership_data(&self.store, object)
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::enforce_membership_of(
+            data_var,
+            extra_witness,
+            extra_pub,
+        )
+    }
+
+    fn get_updates_since(&self, version: u64) -> Option<BulletinDelta<F, S::Pubkey, S::Sig>> {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::get_updates_since(&self.store, version)
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> UserBul<F, U>
+    for PersistentObjStore<F, S>
+{
+    type Error = PersistError;
+
+    fn has_never_received_nul(&self, nul: &Nul<F>) -> bool {
+        <SigObjStore<F, S> as UserBul<F, U>>::has_never_received_nul(&self.store, nul)
+    }
+
+    fn append_value<
+        PubArgs: ToConstraintField<F>,
+        Snark: ark_snark::SNARK<F>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        args: PubArgs,
+        proof: Snark::Proof,
+        memb_data: Option<Self::MembershipPub>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> Result<(), Self::Error> {
+        let len = self.store.coms.len();
+        <SigObjStore<F, S> as UserBul<F, U>>::append_value::<PubArgs, Snark, NUMCBS>(
+            &mut self.store,
+            object,
+            old_nul,
+            cb_com_list,
+            args,
+            proof,
+            memb_data,
+            verif_key,
+        )
+        .map_err(|_| PersistError::Store)?;
+        self.persist_from(len)
+    }
+
+    fn verify_interact_and_append_batch<
+        PubArgs: ToConstraintField<F> + Clone,
+        Snark: ark_snark::SNARK<F>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        batch: Vec<BatchedInteraction<F, PubArgs, Snark, S::Pubkey, NUMCBS>>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> Vec<Result<(), BulError<PersistError>>> {
+        let len = self.store.coms.len();
+        let out = <SigObjStore<F, S> as UserBul<F, U>>::verify_interact_and_append_batch::<
+            PubArgs,
+            Snark,
+            NUMCBS,
+        >(&mut self.store, batch, verif_key);
+
+        let persisted = self.persist_from(len).is_ok();
+
+        out.into_iter()
+            .map(|r| match r {
+                Ok(()) if persisted => Ok(()),
+                Ok(()) => Err(BulError::AppendError(PersistError::Store)),
+                Err(BulError::VerifyError) => Err(BulError::VerifyError),
+                Err(BulError::CircuitMismatch) => Err(BulError::CircuitMismatch),
+                Err(BulError::AppendError(())) => Err(BulError::AppendError(PersistError::Store)),
+            })
+            .collect()
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> JoinableBulletin<F, U>
+    for PersistentObjStore<F, S>
+where
+    Standard: Distribution<F>,
+{
+    type PubData = ();
+
+    fn join_bul(&mut self, object: Com<F>, _pub_data: ()) -> Result<(), Self::Error> {
+        let len = self.store.coms.len();
+        <SigObjStore<F, S> as JoinableBulletin<F, U>>::join_bul(&mut self.store, object, ())
+            .map_err(|_| PersistError::Store)?;
+        self.persist_from(len)
+    }
+}
+
+/// A [`CallbackStore`] backed by a [`sled`] database.
+///
+/// Every called ticket is written and flushed to disk before an append returns, so the store can
+/// be reopened with [`PersistentCallbackStore::open`] after a restart or crash.
+///
+/// Only called tickets are stored. On opening, the nonmembership store is rebuilt from the called
+/// tickets, which starts a fresh epoch.
+///
+/// The private key is not stored in the database, and must be provided when opening the store.
+///
+/// Note that this implements [`PublicCallbackBul`] and [`CallbackBul`] for plain tickets.
+#[derive(Clone)]
+pub struct PersistentCallbackStore<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>>
+where
+    Standard: Distribution<F>,
+{
+    /// The in-memory store, which mirrors the database.
+    pub store: CallbackStore<F, S, B, F>,
+    tree: sled::Tree,
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>> std::fmt::Debug
+    for PersistentCallbackStore<F, S, B>
+where
+    Standard: Distribution<F>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PersistentCallbackStore")
+            .field("memb_called_cbs", &self.store.memb_called_cbs)
+            .field("tree", &self.tree)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>> PersistentCallbackStore<F, S, B>
+where
+    Standard: Distribution<F>,
+{
+    /// Open the store in the `callbacks` tree of a database, loading any existing tickets.
+    pub fn open(db: &sled::Db, privkey: S::Privkey) -> Result<Self, PersistError> {
+        let tree = db.open_tree("callbacks").map_err(PersistError::Db)?;
+        let entries = load::<CalledEntry<F, S, F>>(&tree)?;
+        Ok(Self {
+            store: CallbackStore::from_only_memb(privkey, entries),
+            tree,
+        })
+    }
+
+    fn entry(&self, i: usize) -> Result<Vec<u8>, PersistError> {
+        let (tik, args, time) = self.store.memb_called_cbs[i].clone();
+        encode(&(tik, args, time, self.store.memb_cbs_sigs[i].clone()))
+    }
+
+    /// Write all tickets from index `from` onwards in a single batch, and flush.
+    fn write_from(&self, from: usize) -> Result<(), PersistError> {
+        let mut batch = sled::Batch::default();
+        for i in from..self.store.memb_called_cbs.len() {
+            batch.insert((i as u64).to_be_bytes().to_vec(), self.entry(i)?);
+        }
+        self.tree.apply_batch(batch).map_err(PersistError::Db)?;
+        self.tree.flush().map_err(PersistError::Db)?;
+        Ok(())
+    }
+
+    /// Get the public key.
+    pub fn get_pubkey(&self) -> S::Pubkey {
+        self.store.get_pubkey()
+    }
+
+    /// Get the full database.
+    pub fn get_db(&self) -> Vec<CalledEntry<F, S, F>> {
+        self.store.get_db()
+    }
+
+    /// Get the current epoch.
+    pub fn get_epoch(&self) -> F {
+        self.store.get_epoch()
+    }
+
+    /// Update the epoch. See [`CallbackStore::update_epoch`].
+    pub fn update_epoch(&mut self, rng: &mut (impl CryptoRng + RngCore)) {
+        self.store.update_epoch(rng)
+    }
+
+    /// Rotate keys. Resigns all tickets with the new key, and rewrites all entries.
+    pub fn rotate_key(&mut self, new_key: S::Privkey) -> Result<(), PersistError> {
+        self.store
+            .rotate_key(new_key)
+            .map_err(|_| PersistError::Store)?;
+        self.write_from(0)
+    }
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>>
+    PublicCallbackBul<F, F, NoSigOTP<F>> for PersistentCallbackStore<F, S, B>
+where
+    Standard: Distribution<F>,
+{
+    type MembershipWitness = S::Sig;
+
+    type MembershipWitnessVar = S::SigVar;
+
+    type NonMembershipWitness = B::NonMembershipWitness;
+
+    type NonMembershipWitnessVar = B::NonMembershipWitnessVar;
+
+    type MembershipPub = S::Pubkey;
+
+    type MembershipPubVar = S::PubkeyVar;
+
+    type NonMembershipPub = B::NonMembershipPub;
+
+    type NonMembershipPubVar = B::NonMembershipPubVar;
+
+    fn verify_in(&self, tik: FakeSigPubkey<F>) -> Option<(F, Time<F>)> {
+        <CallbackStore<F, S, B, F> as PublicCallbackBul<F, F, NoSigOTP<F>>>::verify_in(
+            &self.store,
+            tik,
+        )
+    }
+
+    fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
+        <CallbackStore<F, S, B, F> as PublicCallbackBul<F, F, NoSigOTP<F>>>::verify_not_in(
+            &self.store,
+            tik,
+        )
+    }
+
+    fn get_membership_data(
+        &self,
+        tik: FakeSigPubkey<F>,
+    ) -> (
+        S::Pubkey,
+        S::Sig,
+        B::NonMembershipPub,
+        B::NonMembershipWitness,
+    ) {
+        <CallbackStore<F, S, B, F> as PublicCallbackBul<F, F, NoSigOTP<F>>>::get_membership_data(
+            &self.store,
+            tik,
+        )
+    }
+
+    fn enforce_membership_of(
+        tikvar: (FakeSigPubkeyVar<F>, FpVar<F>, TimeVar<F>),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <CallbackStore<F, S, B, F> as PublicCallbackBul<F, F, NoSigOTP<F>>>::enforce_membership_of(
+            tikvar,
+            extra_witness,
+            extra_pub,
+        )
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: FakeSigPubkeyVar<F>,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <CallbackStore<F, S, B, F> as PublicCallbackBul<F, F, NoSigOTP<F>>>::enforce_nonmembership_of(
+            tikvar,
+            extra_witness,
+            extra_pub,
+        )
+    }
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>> CallbackBul<F, F, NoSigOTP<F>>
+    for PersistentCallbackStore<F, S, B>
+where
+    Standard: Distribution<F>,
+{
+    type Error = PersistError;
+
+    fn has_never_received_tik(&self, tik: &FakeSigPubkey<F>) -> bool {
+        <CallbackStore<F, S, B, F> as CallbackBul<F, F, NoSigOTP<F>>>::has_never_received_tik(
+            &self.store,
+            tik,
+        )
+    }
+
+    fn append_value(
+        &mut self,
+        tik: FakeSigPubkey<F>,
+        enc_args: F,
+        signature: (),
+        time: Time<F>,
+    ) -> Result<(), Self::Error> {
+        let len = self.store.memb_called_cbs.len();
+        <CallbackStore<F, S, B, F> as CallbackBul<F, F, NoSigOTP<F>>>::append_value(
+            &mut self.store,
+            tik,
+            enc_args,
+            signature,
+            time,
+        )
+        .map_err(|_| PersistError::Store)?;
+
+        let res = self.write_from(len);
+        if res.is_err() {
+            self.store.memb_called_cbs.truncate(len);
+            self.store.memb_cbs_sigs.truncate(len);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::impls::centralized::ds::{
+        sig::gr_schnorr::GrumpkinSchnorr, sigrange::SigRangeStore,
+    };
+    use ark_bn254::Fr;
+    use ark_ff::UniformRand;
+    use rand::thread_rng;
+    use std::{
+        path::{Path, PathBuf},
+        thread::sleep,
+        time::Duration,
+    };
+
+    fn temp_db(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "zk-callbacks-persist-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    // Reopen the database at `path`. The flusher thread of a dropped handle may still hold the
+    // lock on the database for a moment, so this retries (for up to a second) while it is held
+    fn reopen(path: &Path) -> sled::Db {
+        for _ in 0..50 {
+            match sled::open(path) {
+                Err(sled::Error::Io(e)) if e.to_string().starts_with("could not acquire lock") => {
+                    sleep(Duration::from_millis(20))
+                }
+                res => return res.unwrap(),
+            }
+        }
+        panic!("the database at {} is still locked", path.display())
+    }
+
+    // Tests that joined objects and their signatures survive reopening the database
+    #[test]
+    fn persistent_obj_store_reopen() {
+        let mut rng = thread_rng();
+        let path = temp_db("objects");
+        let privkey = GrumpkinSchnorr::gen_key(&mut rng);
+        let coms: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+
+        let db = sled::open(&path).unwrap();
+        let mut store =
+            PersistentObjStore::<Fr, GrumpkinSchnorr>::open(&db, privkey.clone()).unwrap();
+        for c in &coms {
+            <PersistentObjStore<Fr, GrumpkinSchnorr> as JoinableBulletin<Fr, Fr>>::join_bul(
+                &mut store,
+                *c,
+                (),
+            )
+            .unwrap();
+        }
+        let before = store.get_db();
+        drop(store);
+        drop(db);
+
+        let db = reopen(&path);
+        let store = PersistentObjStore::<Fr, GrumpkinSchnorr>::open(&db, privkey).unwrap();
+        assert_eq!(store.get_db(), before);
+        for c in &coms {
+            assert!(
+                <PersistentObjStore<Fr, GrumpkinSchnorr> as PublicUserBul<Fr, Fr>>::get_membership_data(
+                    &store, *c
+                )
+                .is_some()
+            );
+        }
+        drop(store);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    // Tests that called tickets survive reopening, and that uncalled tickets stay provably uncalled
+    #[test]
+    fn persistent_callback_store_reopen() {
+        type Store =
+            PersistentCallbackStore<Fr, GrumpkinSchnorr, SigRangeStore<Fr, GrumpkinSchnorr>>;
+
+        let mut rng = thread_rng();
+        let path = temp_db("callbacks");
+        let privkey = GrumpkinSchnorr::gen_key(&mut rng);
+        // Tickets are below the largest value covered by the signed ranges
+        let called = FakeSigPubkey::new(Fr::from(1000u64));
+        let uncalled = FakeSigPubkey::new(Fr::from(7u64));
+
+        let db = sled::open(&path).unwrap();
+        let mut store = Store::open(&db, privkey.clone()).unwrap();
+        <Store as CallbackBul<Fr, Fr, NoSigOTP<Fr>>>::append_value(
+            &mut store,
+            called.clone(),
+            Fr::from(3u8),
+            (),
+            Fr::from(1u8),
+        )
+        .unwrap();
+        let before = store.get_db();
+        drop(store);
+        drop(db);
+
+        let db = reopen(&path);
+        let store = Store::open(&db, privkey).unwrap();
+        assert_eq!(store.get_db(), before);
+        assert_eq!(
+            <Store as PublicCallbackBul<Fr, Fr, NoSigOTP<Fr>>>::verify_in(&store, called.clone()),
+            Some((Fr::from(3u8), Fr::from(1u8)))
+        );
+        assert!(
+            !<Store as CallbackBul<Fr, Fr, NoSigOTP<Fr>>>::has_never_received_tik(&store, &called)
+        );
+        assert!(
+            <Store as PublicCallbackBul<Fr, Fr, NoSigOTP<Fr>>>::verify_not_in(&store, uncalled)
+        );
+        drop(store);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,466 @@
+//! Implements aggregatable BLS signatures over bls12_377, verified in-circuit over its base field.
+//!
+//! Signatures live in G1 and public keys in G2. Messages are hashed to G1 by try-and-increment
+//! with Poseidon, and the signature carries the counter and `y` coordinate found while hashing, so
+//! the hash can be checked in-circuit without a square root.
+//!
+//! Signatures from different keys on the same message can be summed into a single signature,
+//! which verifies under the sum of the public keys. To avoid rogue key attacks, keys should only
+//! be aggregated after checking a proof of possession, see [`BlsPrivkey::prove_possession`].
+
+use crate::{
+    crypto::hash::HasherZK,
+    impls::{
+        centralized::ds::sig::{Privkey, Pubkey, Signature},
+        hash::Poseidon,
+    },
+};
+use ark_bls12_377::{
+    constraints::{G1Var, G2Var, PairingVar as Bls377PairingVar},
+    g1::Config as G1Config,
+    Bls12_377, Fq as F, Fr, G1Affine, G1Projective, G2Projective,
+};
+use ark_ec::{
+    pairing::Pairing, short_weierstrass::SWCurveConfig, AffineRepr, CurveConfig, CurveGroup,
+    PrimeGroup,
+};
+use ark_ff::{
+    AdditiveGroup, BitIteratorLE, Field, PrimeField, ToConstraintField, UniformRand, Zero,
+};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    convert::ToConstraintFieldGadget,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    groups::CurveVar,
+    pairing::PairingVar as PairingGadget,
+    select::CondSelectGadget,
+    R1CSVar,
+};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSystemRef, Namespace, SynthesisError},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{CryptoRng, RngCore};
+use std::borrow::Borrow;
+
+type FV = FpVar<F>;
+
+type PV = Bls377PairingVar;
+
+const BLS_HASH_SEPARATOR: u8 = 0x06;
+
+const BLS_POP_SEPARATOR: u8 = 0x07;
+
+/// Hashes a message to G1 by try-and-increment.
+///
+/// Returns the point, along with the counter and `y` coordinate before clearing the cofactor.
+fn hash_to_g1(sep: u8, msg: &F) -> (G1Affine, F, F) {
+    let mut ctr = F::ZERO;
+    loop {
+        let x = <Poseidon<2>>::hash(&[F::from(sep), *msg, ctr]);
+        if let Some(y) = (x.square() * x + G1Config::COEFF_B).sqrt() {
+            // Pick the smaller root, so the hint is canonical
+            let y = if y.into_bigint() > (-y).into_bigint() {
+                -y
+            } else {
+                y
+            };
+            // Multiply by the full cofactor (not the effective cofactor used by
+            // `clear_cofactor`), to match the in-circuit hash
+            let p = G1Affine::new_unchecked(x, y).mul_by_cofactor();
+            if !p.is_zero() {
+                return (p, ctr, y);
+            }
+        }
+        ctr += F::ONE;
+    }
+}
+
+/// Hashes a message to G1 in-circuit, given the hint from [`hash_to_g1`].
+///
+/// Returns the point, and whether the hint lies on the curve.
+fn hash_to_g1_zk(
+    sep: u8,
+    msg: &FV,
+    ctr: &FV,
+    y: &FV,
+) -> Result<(G1Var, Boolean<F>), SynthesisError> {
+    let hash_input = [FpVar::Constant(F::from(sep)), msg.clone(), ctr.clone()];
+    let x = <Poseidon<2>>::hash_in_zk(&hash_input)?;
+    let on_curve = y
+        .square()?
+        .is_eq(&(x.square()? * &x + FpVar::Constant(G1Config::COEFF_B)))?;
+
+    let cofactor: Vec<Boolean<F>> = BitIteratorLE::new(<G1Config as CurveConfig>::COFACTOR)
+        .map(Boolean::constant)
+        .collect();
+    let p = G1Var::new(x, y.clone(), FV::one()).scalar_mul_le(cofactor.iter())?;
+
+    Ok((p, on_curve))
+}
+
+/// Checks e(sig, g2) = e(h, pubkey) in-circuit.
+fn pairing_check_zk(
+    cs: ConstraintSystemRef<F>,
+    pubkey: &G2Var,
+    sig: &G1Var,
+    h: &G1Var,
+) -> Result<Boolean<F>, SynthesisError> {
+    let g2 = G2Var::new_constant(ns!(cs, "G2 gen"), G2Projective::generator())?;
+    let p = [
+        <PV as PairingGadget<Bls12_377>>::prepare_g1(sig)?,
+        <PV as PairingGadget<Bls12_377>>::prepare_g1(&h.negate()?)?,
+    ];
+    let q = [
+        <PV as PairingGadget<Bls12_377>>::prepare_g2(&g2)?,
+        <PV as PairingGadget<Bls12_377>>::prepare_g2(pubkey)?,
+    ];
+    let gt = <PV as PairingGadget<Bls12_377>>::product_of_pairings(&p, &q)?;
+    gt.is_eq(&<PV as PairingGadget<Bls12_377>>::GTVar::one())
+}
+
+/// A private BLS signing key.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize, Default)]
+pub struct BlsPrivkey(Fr);
+
+/// A public BLS verification key.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BlsPubkey(G2Projective);
+
+/// A public BLS verification key in-circuit.
+#[derive(Clone)]
+pub struct BlsPubkeyVar(G2Var);
+
+/// A BLS signature.
+#[derive(Debug, Clone, Default, CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq)]
+pub struct BlsSignature {
+    /// The signature, sk H(msg)
+    sig: G1Affine,
+    /// Counter used when hashing the message
+    ctr: F,
+    /// `y` coordinate found when hashing the message
+    y: F,
+}
+
+/// A BLS signature in-circuit.
+#[derive(Clone)]
+pub struct BlsSignatureVar {
+    /// The signature, sk H(msg)
+    sig: G1Var,
+    /// Counter used when hashing the message
+    ctr: FV,
+    /// `y` coordinate found when hashing the message
+    y: FV,
+}
+
+impl AllocVar<BlsSignature, F> for BlsSignatureVar {
+    fn new_variable<T: Borrow<BlsSignature>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<BlsSignatureVar, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let res = f();
+        res.and_then(|sig| {
+            let sig = sig.borrow();
+
+            let sig_var = G1Var::new_variable(ns!(cs, "sig"), || Ok(sig.sig), mode)?;
+            let ctr_var = FV::new_variable(ns!(cs, "ctr"), || Ok(sig.ctr), mode)?;
+            let y_var = FV::new_variable(ns!(cs, "y"), || Ok(sig.y), mode)?;
+
+            Ok(BlsSignatureVar {
+                sig: sig_var,
+                ctr: ctr_var,
+                y: y_var,
+            })
+        })
+    }
+}
+
+impl BlsSignature {
+    /// Aggregate signatures on the same message into a single signature.
+    ///
+    /// Returns `None` if there are no signatures, or if they are not on the same message.
+    pub fn aggregate(sigs: &[BlsSignature]) -> Option<BlsSignature> {
+        let first = sigs.first()?;
+        if sigs.iter().any(|s| s.ctr != first.ctr || s.y != first.y) {
+            return None;
+        }
+        let sum = sigs
+            .iter()
+            .fold(G1Projective::default(), |acc, s| acc + s.sig);
+        Some(BlsSignature {
+            sig: sum.into_affine(),
+            ctr: first.ctr,
+            y: first.y,
+        })
+    }
+}
+
+impl<'a> From<&'a BlsPrivkey> for BlsPubkey {
+    fn from(privkey: &'a BlsPrivkey) -> BlsPubkey {
+        // g2^privkey is the pubkey
+        BlsPubkey(G2Projective::generator() * privkey.0)
+    }
+}
+
+impl BlsPubkey {
+    /// Aggregate public keys into a single key, which verifies aggregated signatures.
+    pub fn aggregate(pubkeys: &[BlsPubkey]) -> BlsPubkey {
+        BlsPubkey(
+            pubkeys
+                .iter()
+                .fold(G2Projective::default(), |acc, pk| acc + pk.0),
+        )
+    }
+
+    /// Verify a proof of possession of the private key for this public key.
+    pub fn verify_possession(&self, pop: &BlsSignature) -> bool {
+        self.verify_with(BLS_POP_SEPARATOR, &self.pop_msg(), pop)
+    }
+
+    fn pop_msg(&self) -> F {
+        <Poseidon<2>>::hash(&self.to_field_elements().unwrap())
+    }
+
+    fn verify_with(&self, sep: u8, msg: &F, sig: &BlsSignature) -> bool {
+        let (h, ctr, y) = hash_to_g1(sep, msg);
+        if sig.ctr != ctr || sig.y != y || self.0.is_zero() {
+            return false;
+        }
+        Bls12_377::pairing(sig.sig, G2Projective::generator()) == Bls12_377::pairing(h, self.0)
+    }
+}
+
+impl Pubkey<F> for BlsPubkey {
+    type PubkeyVar = BlsPubkeyVar;
+
+    type Sig = BlsSignature;
+
+    type SigVar = BlsSignatureVar;
+
+    fn verify(&self, signature: Self::Sig, msg: F) -> bool {
+        self.verify_with(BLS_HASH_SEPARATOR, &msg, &signature)
+    }
+
+    fn verify_zk(
+        pubkey: Self::PubkeyVar,
+        signature: Self::SigVar,
+        msg: FpVar<F>,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        BlsPubkeyVar::verify(&pubkey, &msg, &signature)
+    }
+}
+
+impl BlsPrivkey {
+    fn gen(rng: &mut (impl CryptoRng + RngCore)) -> BlsPrivkey {
+        BlsPrivkey(Fr::rand(rng))
+    }
+
+    fn sign_with(&self, sep: u8, msg: &F) -> BlsSignature {
+        let (h, ctr, y) = hash_to_g1(sep, msg);
+        BlsSignature {
+            sig: (h * self.0).into_affine(),
+            ctr,
+            y,
+        }
+    }
+
+    /// Prove possession of this private key, by signing the public key.
+    ///
+    /// This should be checked with [`BlsPubkey::verify_possession`] before aggregating the public
+    /// key with others.
+    pub fn prove_possession(&self) -> BlsSignature {
+        let pubkey = BlsPubkey::from(self);
+        self.sign_with(BLS_POP_SEPARATOR, &pubkey.pop_msg())
+    }
+}
+
+impl Privkey<F> for BlsPrivkey {
+    type CompressedPrivKey = BlsPrivkey;
+
+    type Sig = BlsSignature;
+
+    type Pubkey = BlsPubkey;
+
+    fn gen_ckey(rng: &mut (impl CryptoRng + RngCore)) -> Self::CompressedPrivKey {
+        Self::gen(rng)
+    }
+
+    fn into_key(c: Self::CompressedPrivKey) -> Self {
+        c
+    }
+
+    fn gen_key(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        Self::gen(rng)
+    }
+
+    fn get_pubkey(&self) -> Self::Pubkey {
+        Self::Pubkey::from(self)
+    }
+
+    fn sign(&self, _rng: &mut (impl CryptoRng + RngCore), msg: F) -> Option<Self::Sig> {
+        Some(self.sign_with(BLS_HASH_SEPARATOR, &msg))
+    }
+}
+
+impl ToConstraintField<F> for BlsPubkey {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        self.0.into_affine().to_field_elements()
+    }
+}
+
+impl ToConstraintFieldGadget<F> for BlsPubkeyVar {
+    fn to_constraint_field(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        self.0.to_constraint_field()
+    }
+}
+
+impl R1CSVar<F> for BlsPubkeyVar {
+    type Value = BlsPubkey;
+
+    fn cs(&self) -> ConstraintSystemRef<F> {
+        self.0.cs()
+    }
+
+    fn value(&self) -> Result<Self::Value, SynthesisError> {
+        Ok(BlsPubkey(self.0.value()?))
+    }
+}
+
+impl AllocVar<BlsPubkey, F> for BlsPubkeyVar {
+    fn new_variable<T: Borrow<BlsPubkey>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+
+        res.and_then(|pk| {
+            let pk = pk.borrow();
+            Ok(Self(G2Var::new_variable(
+                ns!(cs, "pubkey"),
+                || Ok(pk.0),
+                mode,
+            )?))
+        })
+    }
+}
+
+impl BlsPubkeyVar {
+    /// Aggregate the public keys of the signers in-circuit.
+    ///
+    /// Returns the sum of the keys for which the corresponding bit is set, along with the number
+    /// of signers.
+    pub fn aggregate(
+        pubkeys: &[BlsPubkeyVar],
+        signers: &[Boolean<F>],
+    ) -> Result<(BlsPubkeyVar, FpVar<F>), SynthesisError> {
+        let mut acc = G2Var::zero();
+        let mut count = FV::zero();
+        for (pk, signed) in pubkeys.iter().zip(signers) {
+            acc += G2Var::conditionally_select(signed, &pk.0, &G2Var::zero())?;
+            count += FV::from(signed.clone());
+        }
+        Ok((BlsPubkeyVar(acc), count))
+    }
+
+    /// Verifies the given (message, signature) pair under the given public key in zero-knowledge.
+    fn verify(&self, msg: &FV, sig: &BlsSignatureVar) -> Result<Boolean<F>, SynthesisError> {
+        let cs = self.0.cs().or(msg.cs()).or(sig.ctr.cs()).or(sig.y.cs());
+
+        let (h, on_curve) = hash_to_g1_zk(BLS_HASH_SEPARATOR, msg, &sig.ctr, &sig.y)?;
+        let nonzero = !self.0.is_zero()?;
+        let paired = pairing_check_zk(cs, &self.0, &sig.sig, &h)?;
+
+        Ok(on_curve & nonzero & paired)
+    }
+}
+
+impl CondSelectGadget<F> for BlsPubkeyVar {
+    fn conditionally_select(
+        cond: &Boolean<F>,
+        true_value: &Self,
+        false_value: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let selected = G2Var::conditionally_select(cond, &true_value.0, &false_value.0)?;
+        Ok(BlsPubkeyVar(selected))
+    }
+}
+
+/// Aggregatable BLS signatures on bls12_377. Implements [`Signature`].
+#[derive(Clone, Default, Debug)]
+pub struct Bls377Aggregate;
+
+impl Signature<F> for Bls377Aggregate {
+    type SigVar = BlsSignatureVar;
+
+    type Sig = BlsSignature;
+
+    type Pubkey = BlsPubkey;
+
+    type PubkeyVar = BlsPubkeyVar;
+
+    type CPrivkey = BlsPrivkey;
+
+    type Privkey = BlsPrivkey;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::thread_rng;
+
+    // Tests that aggregated signatures verify natively and in-circuit
+    #[test]
+    fn bls_aggregate_verify() -> Result<(), SynthesisError> {
+        let mut rng = thread_rng();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        let privkeys: Vec<BlsPrivkey> = (0..3).map(|_| BlsPrivkey::gen(&mut rng)).collect();
+        let pubkeys: Vec<BlsPubkey> = privkeys.iter().map(BlsPubkey::from).collect();
+        for (sk, pk) in privkeys.iter().zip(pubkeys.iter()) {
+            assert!(pk.verify_possession(&sk.prove_possession()));
+        }
+
+        let msg = F::rand(&mut rng);
+        let sigs: Vec<BlsSignature> = privkeys[..2]
+            .iter()
+            .map(|sk| sk.sign(&mut rng, msg).unwrap())
+            .collect();
+        let agg = BlsSignature::aggregate(&sigs).unwrap();
+
+        // Check verification natively
+        assert!(BlsPubkey::aggregate(&pubkeys[..2]).verify(agg.clone(), msg));
+        assert!(!BlsPubkey::aggregate(&pubkeys).verify(agg.clone(), msg));
+
+        // Check verification in ZK, aggregating the keys in-circuit
+        let msg_var = FV::new_input(cs.clone(), || Ok(msg))?;
+        let sig_var = BlsSignatureVar::new_witness(cs.clone(), || Ok(&agg))?;
+        let pk_vars = pubkeys
+            .iter()
+            .map(|pk| BlsPubkeyVar::new_input(cs.clone(), || Ok(pk)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let signers = [true, true, false]
+            .iter()
+            .map(|b| Boolean::new_witness(cs.clone(), || Ok(*b)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (agg_pk, count) = BlsPubkeyVar::aggregate(&pk_vars, &signers)?;
+        count.enforce_equal(&FV::Constant(F::from(2u8)))?;
+        agg_pk
+            .verify(&msg_var, &sig_var)?
+            .enforce_equal(&Boolean::TRUE)?;
+        assert!(cs.is_satisfied()?);
+        Ok(())
+    }
+}
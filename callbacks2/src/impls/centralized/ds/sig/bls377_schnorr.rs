@@ -197,9 +197,36 @@ impl BLS377SchnorrPrivkey {
         let k = EProjFr::rand(&mut rng);
         let com = g * k;
 
+        self.sign_with_nonce(k, com.into_affine(), msg)
+    }
+
+    /// Signs a batch of messages under `privkey`.
+    ///
+    /// All nonce commitments are normalized together, so this costs a single field inversion
+    /// rather than one per message.
+    fn sign_batch(&self, mut rng: impl Rng, msgs: &[BlsFr]) -> Vec<BLS377SchnorrSignature> {
+        let g = EProj::generator();
+        let ks: Vec<EProjFr> = msgs.iter().map(|_| EProjFr::rand(&mut rng)).collect();
+        let coms: Vec<EProj> = ks.iter().map(|k| g * k).collect();
+        let coms = EProj::normalize_batch(&coms);
+
+        ks.into_iter()
+            .zip(coms)
+            .zip(msgs)
+            .map(|((k, com), msg)| self.sign_with_nonce(k, com, msg))
+            .collect()
+    }
+
+    /// Signs the given message with the secret nonce `k` and its commitment `com`.
+    fn sign_with_nonce(
+        &self,
+        k: EProjFr,
+        com: <EProj as CurveGroup>::Affine,
+        msg: &BlsFr,
+    ) -> BLS377SchnorrSignature {
         // e is H(com || msg)
         let mut hash_input = vec![BlsFr::from(SCHNORR_HASH_SEPARATOR)];
-        hash_input.extend(com.into_affine().xy().map(|t| vec![t.0, t.1]).unwrap());
+        hash_input.extend(com.xy().map(|t| vec![t.0, t.1]).unwrap());
         hash_input.push(*msg);
         let digest = <Poseidon<2>>::hash(&hash_input);
 
@@ -398,6 +425,14 @@ impl Signature<F> for Bls377Schnorr {
     type CPrivkey = BLS377SchnorrPrivkey;
 
     type Privkey = BLS377SchnorrPrivkey;
+
+    fn sign_batch(
+        pk: &Self::Privkey,
+        rng: &mut (impl rand::CryptoRng + rand::RngCore),
+        msgs: &[F],
+    ) -> Option<Vec<Self::Sig>> {
+        Some(pk.sign_batch(rng, msgs))
+    }
 }
 
 // TODO: FoldSer
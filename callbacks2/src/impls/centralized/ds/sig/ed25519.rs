@@ -0,0 +1,456 @@
+//! Implements EdDSA signatures over the twisted Edwards curve embedded in bn254 (Baby Jubjub).
+//!
+//! Keys and nonces are derived as in Ed25519: a private key is a 32 byte seed, which is expanded
+//! with a hash into a secret scalar and a nonce prefix. Nonces are then deterministic, and the
+//! passed in randomness is unused. The challenge hash is Poseidon, so verification is cheap
+//! in-circuit.
+
+use crate::{
+    crypto::hash::HasherZK,
+    impls::{
+        centralized::ds::sig::{Privkey, Pubkey, Signature},
+        hash::Poseidon,
+    },
+};
+use ark_bn254::Fr as F;
+use ark_ec::{twisted_edwards::Affine, CurveGroup, PrimeGroup};
+use ark_ed_on_bn254::{
+    constraints::EdwardsVar as BabyJubjubVar, EdwardsConfig, EdwardsProjective as BabyJubjub,
+    Fr as BabyJubjubFr,
+};
+use ark_ff::{AdditiveGroup, BigInteger, Field, PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    convert::{ToBitsGadget, ToConstraintFieldGadget},
+    eq::EqGadget,
+    fields::fp::FpVar,
+    groups::CurveVar,
+    select::CondSelectGadget,
+    R1CSVar,
+};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSystemRef, Namespace, SynthesisError},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use blake2::{Blake2b512, Digest};
+use rand::{CryptoRng, RngCore};
+use std::borrow::Borrow;
+
+type FV = FpVar<F>;
+
+const EDDSA_HASH_SEPARATOR: u8 = 0x05;
+
+/// Converts an element of the Baby Jubjub scalar field into an element of the base field
+fn fr_to_fq(x: BabyJubjubFr) -> F {
+    let bits = x.into_bigint().to_bits_le();
+    F::from_bigint(<F as PrimeField>::BigInt::from_bits_le(&bits)).unwrap()
+}
+
+/// Computes the challenge H(R || A || msg), truncated to fit in the scalar field.
+fn challenge(r: &Affine<EdwardsConfig>, a: &Affine<EdwardsConfig>, msg: &F) -> BabyJubjubFr {
+    let hash_input = vec![F::from(EDDSA_HASH_SEPARATOR), r.x, r.y, a.x, a.y, *msg];
+    let digest = <Poseidon<2>>::hash(&hash_input);
+
+    // The digest is a base field element, so we only keep as many bits as are guaranteed to fit
+    // in a scalar
+    let digest_bits = digest.into_bigint().to_bits_le();
+    let r_bitlen = BabyJubjubFr::MODULUS_BIT_SIZE as usize;
+    BabyJubjubFr::from_bigint(<BabyJubjubFr as PrimeField>::BigInt::from_bits_le(
+        &digest_bits[..r_bitlen - 1],
+    ))
+    .expect("couldn't convert BaseField elem to ScalarField elem")
+}
+
+/// A private EdDSA signing key.
+///
+/// As in Ed25519, this is expanded from a 32 byte seed into a secret scalar and a nonce prefix.
+#[derive(Clone)]
+pub struct EdDSAPrivkey {
+    seed: [u8; 32],
+    scalar: BabyJubjubFr,
+    prefix: [u8; 32],
+}
+
+/// A public EdDSA verification key.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default, CanonicalSerialize, CanonicalDeserialize)]
+pub struct EdDSAPubkey(BabyJubjub);
+
+/// A public EdDSA verification key in-circuit.
+#[derive(Clone)]
+pub struct EdDSAPubkeyVar(BabyJubjubVar);
+
+impl Default for EdDSAPubkeyVar {
+    fn default() -> Self {
+        Self(BabyJubjubVar::new(
+            FpVar::Constant(F::ZERO),
+            FpVar::Constant(F::ONE),
+        ))
+    }
+}
+
+/// An EdDSA signature.
+#[derive(Debug, Clone, Default, CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq)]
+pub struct EdDSASignature {
+    /// Nonce commitment
+    r: Affine<EdwardsConfig>,
+    /// Response to challenge
+    s: BabyJubjubFr,
+}
+
+/// An EdDSA signature in-circuit.
+#[derive(Clone)]
+pub struct EdDSASignatureVar {
+    /// Nonce commitment
+    r: BabyJubjubVar,
+    /// Response to challenge, lifted into the base field
+    s: FV,
+}
+
+impl AllocVar<EdDSASignature, F> for EdDSASignatureVar {
+    fn new_variable<T: Borrow<EdDSASignature>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<EdDSASignatureVar, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let res = f();
+        res.and_then(|sig| {
+            let sig = sig.borrow();
+
+            // The scalar field of Baby Jubjub is smaller than bn254's scalar field, so lifting s
+            // is injective
+            let lifted_s = fr_to_fq(sig.s);
+
+            let r_var = <BabyJubjubVar as AllocVar<Affine<EdwardsConfig>, _>>::new_variable(
+                ns!(cs, "sig r var"),
+                || Ok(sig.r),
+                mode,
+            )?;
+            let s_var = FV::new_variable(ns!(cs, "sig s var"), || Ok(lifted_s), mode)?;
+
+            Ok(EdDSASignatureVar { r: r_var, s: s_var })
+        })
+    }
+}
+
+impl<'a> From<&'a EdDSAPrivkey> for EdDSAPubkey {
+    fn from(privkey: &'a EdDSAPrivkey) -> EdDSAPubkey {
+        // g^a is the pubkey
+        EdDSAPubkey(BabyJubjub::generator() * privkey.scalar)
+    }
+}
+
+impl EdDSAPubkey {
+    fn verify(&self, msg: &F, sig: &EdDSASignature) -> bool {
+        // Check g^s = R pubkey^k, where k = H(R || pubkey || msg)
+        let a = self.0.into_affine();
+        let k = challenge(&sig.r, &a, msg);
+        BabyJubjub::generator() * sig.s == sig.r + self.0 * k
+    }
+}
+
+impl Pubkey<F> for EdDSAPubkey {
+    type PubkeyVar = EdDSAPubkeyVar;
+
+    type Sig = EdDSASignature;
+
+    type SigVar = EdDSASignatureVar;
+
+    fn verify(&self, signature: Self::Sig, msg: F) -> bool {
+        EdDSAPubkey::verify(self, &msg, &signature)
+    }
+
+    fn verify_zk(
+        pubkey: Self::PubkeyVar,
+        signature: Self::SigVar,
+        msg: FpVar<F>,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        EdDSAPubkeyVar::verify(&pubkey, &msg, &signature)
+    }
+}
+
+impl EdDSAPrivkey {
+    /// Expand a 32 byte seed into a private key.
+    ///
+    /// This lets keys be imported from (or exported to) systems which store Ed25519-style seeds.
+    pub fn from_seed(seed: [u8; 32]) -> EdDSAPrivkey {
+        let h = Blake2b512::digest(seed);
+        let scalar = BabyJubjubFr::from_le_bytes_mod_order(&h[..32]);
+        let mut prefix = [0u8; 32];
+        prefix.copy_from_slice(&h[32..]);
+        EdDSAPrivkey {
+            seed,
+            scalar,
+            prefix,
+        }
+    }
+
+    /// Get the seed this key was expanded from.
+    pub fn seed(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    fn gen_seed(rng: &mut (impl CryptoRng + RngCore)) -> [u8; 32] {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        seed
+    }
+
+    /// Derives the deterministic nonce for a message, as H(prefix || msg).
+    fn nonce(&self, msg: &F) -> BabyJubjubFr {
+        let mut h = Blake2b512::new();
+        h.update(self.prefix);
+        h.update(msg.into_bigint().to_bytes_le());
+        BabyJubjubFr::from_le_bytes_mod_order(&h.finalize())
+    }
+
+    /// Signs the given message. Return value is `(R, s)`, where `R` is the nonce commitment and
+    /// `s = r + k a` for the challenge `k`.
+    fn sign(&self, msg: &F) -> EdDSASignature {
+        let r = self.nonce(msg);
+        let com = (BabyJubjub::generator() * r).into_affine();
+        self.sign_with_nonce(r, com, msg)
+    }
+
+    /// Signs a batch of messages.
+    ///
+    /// All nonce commitments are normalized together, so this costs a single field inversion
+    /// rather than one per message.
+    fn sign_batch(&self, msgs: &[F]) -> Vec<EdDSASignature> {
+        let g = BabyJubjub::generator();
+        let rs: Vec<BabyJubjubFr> = msgs.iter().map(|m| self.nonce(m)).collect();
+        let coms: Vec<BabyJubjub> = rs.iter().map(|r| g * r).collect();
+        let coms = BabyJubjub::normalize_batch(&coms);
+
+        rs.into_iter()
+            .zip(coms)
+            .zip(msgs)
+            .map(|((r, com), msg)| self.sign_with_nonce(r, com, msg))
+            .collect()
+    }
+
+    fn sign_with_nonce(
+        &self,
+        r: BabyJubjubFr,
+        com: Affine<EdwardsConfig>,
+        msg: &F,
+    ) -> EdDSASignature {
+        let a = (BabyJubjub::generator() * self.scalar).into_affine();
+        let k = challenge(&com, &a, msg);
+        EdDSASignature {
+            r: com,
+            s: r + k * self.scalar,
+        }
+    }
+}
+
+impl Privkey<F> for EdDSAPrivkey {
+    type CompressedPrivKey = [u8; 32];
+
+    type Sig = EdDSASignature;
+
+    type Pubkey = EdDSAPubkey;
+
+    fn gen_ckey(rng: &mut (impl CryptoRng + RngCore)) -> Self::CompressedPrivKey {
+        Self::gen_seed(rng)
+    }
+
+    fn into_key(c: Self::CompressedPrivKey) -> Self {
+        Self::from_seed(c)
+    }
+
+    fn gen_key(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        Self::from_seed(Self::gen_seed(rng))
+    }
+
+    fn get_pubkey(&self) -> Self::Pubkey {
+        Self::Pubkey::from(self)
+    }
+
+    fn sign(&self, _rng: &mut (impl CryptoRng + RngCore), msg: F) -> Option<Self::Sig> {
+        Some(EdDSAPrivkey::sign(self, &msg))
+    }
+}
+
+impl ToConstraintField<F> for EdDSAPubkey {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        let aff = self.0.into_affine();
+        Some(vec![aff.x, aff.y])
+    }
+}
+
+impl ToConstraintFieldGadget<F> for EdDSAPubkeyVar {
+    fn to_constraint_field(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        self.0.to_constraint_field()
+    }
+}
+
+impl R1CSVar<F> for EdDSAPubkeyVar {
+    type Value = EdDSAPubkey;
+
+    fn cs(&self) -> ConstraintSystemRef<F> {
+        self.0.cs()
+    }
+
+    fn value(&self) -> Result<Self::Value, SynthesisError> {
+        Ok(EdDSAPubkey(self.0.value()?))
+    }
+}
+
+impl AllocVar<EdDSAPubkey, F> for EdDSAPubkeyVar {
+    fn new_variable<T: Borrow<EdDSAPubkey>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+
+        res.and_then(|pk| {
+            let pk = pk.borrow();
+
+            if mode == AllocationMode::Constant {
+                return Ok(Self(BabyJubjubVar::new_constant(cs.clone(), pk.0)?));
+            }
+
+            let aff_pk = pk.0.into_affine();
+
+            let v = <BabyJubjubVar as AllocVar<Affine<EdwardsConfig>, _>>::new_variable(
+                ns!(cs, "entry"),
+                || Ok(&aff_pk),
+                mode,
+            )?;
+
+            Ok(Self(v))
+        })
+    }
+}
+
+impl EdDSAPubkeyVar {
+    /// Verifies the given (message, signature) pair under the given public key in zero-knowledge.
+    ///
+    /// The response `s` is expected to have been embedded from the Baby Jubjub scalar field into
+    /// the base field.
+    fn verify(&self, msg: &FV, sig: &EdDSASignatureVar) -> Result<Boolean<F>, SynthesisError> {
+        let cs = self.0.cs().or(msg.cs()).or(sig.r.cs()).or(sig.s.cs());
+
+        let gv = BabyJubjubVar::new_constant(ns!(cs, "Baby Jubjub gen"), BabyJubjub::generator())?;
+
+        // k is H(R || pubkey || msg), truncated to fit in the scalar field
+        let hash_input = vec![
+            FpVar::Constant(F::from(EDDSA_HASH_SEPARATOR)),
+            sig.r.x.clone(),
+            sig.r.y.clone(),
+            self.0.x.clone(),
+            self.0.y.clone(),
+            msg.clone(),
+        ];
+        let digest = <Poseidon<2>>::hash_in_zk(&hash_input)?;
+        let r_bitlen = BabyJubjubFr::MODULUS_BIT_SIZE as usize;
+        let k_bits = digest.to_bits_le()?;
+
+        // Check g^s = R pubkey^k
+        let g_s = gv.scalar_mul_le(sig.s.to_bits_le()?.iter())?;
+        let pubkey_k = self.0.scalar_mul_le(k_bits[..r_bitlen - 1].iter())?;
+        g_s.is_eq(&(sig.r.clone() + pubkey_k))
+    }
+}
+
+impl CondSelectGadget<F> for EdDSAPubkeyVar {
+    fn conditionally_select(
+        cond: &Boolean<F>,
+        true_value: &Self,
+        false_value: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let selected = BabyJubjubVar::conditionally_select(cond, &true_value.0, &false_value.0)?;
+        Ok(EdDSAPubkeyVar(selected))
+    }
+}
+
+/// EdDSA over Baby Jubjub and bn254. Implements [`Signature`].
+#[derive(Clone, Default, Debug)]
+pub struct BabyJubjubEdDSA;
+
+impl Signature<F> for BabyJubjubEdDSA {
+    type SigVar = EdDSASignatureVar;
+
+    type Sig = EdDSASignature;
+
+    type Pubkey = EdDSAPubkey;
+
+    type PubkeyVar = EdDSAPubkeyVar;
+
+    type CPrivkey = [u8; 32];
+
+    type Privkey = EdDSAPrivkey;
+
+    fn sign_batch(
+        pk: &Self::Privkey,
+        _rng: &mut (impl CryptoRng + RngCore),
+        msgs: &[F],
+    ) -> Option<Vec<Self::Sig>> {
+        Some(pk.sign_batch(msgs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ark_ff::UniformRand;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::thread_rng;
+
+    // Tests that the verification circuit is satisfied iff the signature is valid
+    #[test]
+    fn eddsa_verify() -> Result<(), SynthesisError> {
+        let mut rng = thread_rng();
+
+        for _ in 0..10 {
+            let cs = ConstraintSystem::<F>::new_ref();
+
+            // Make a random keypair and message
+            let privkey = EdDSAPrivkey::gen_key(&mut rng);
+            let pubkey: EdDSAPubkey = (&privkey).into();
+            let msg = F::rand(&mut rng);
+            let sig = privkey.sign(&msg);
+
+            // Check verification natively
+            assert!(pubkey.verify(&msg, &sig));
+            assert!(!pubkey.verify(&(msg + F::ONE), &sig));
+
+            // Witness all the values
+            let msg_var = FV::new_input(cs.clone(), || Ok(msg))?;
+            let sig_var = EdDSASignatureVar::new_witness(cs.clone(), || Ok(&sig))?;
+            let pubkey_var = EdDSAPubkeyVar::new_input(cs.clone(), || Ok(&pubkey))?;
+
+            // Check verification in ZK
+            let success = pubkey_var.verify(&msg_var, &sig_var)?;
+            success.enforce_equal(&Boolean::TRUE)?;
+            assert!(cs.is_satisfied()?);
+        }
+        Ok(())
+    }
+
+    // Tests that keys expanded from the same seed sign identically
+    #[test]
+    fn eddsa_seed_deterministic() {
+        let mut rng = thread_rng();
+
+        let seed = EdDSAPrivkey::gen_ckey(&mut rng);
+        let k1 = EdDSAPrivkey::from_seed(seed);
+        let k2 = EdDSAPrivkey::from_seed(k1.seed());
+        let msg = F::rand(&mut rng);
+
+        assert_eq!(EdDSAPubkey::from(&k1), EdDSAPubkey::from(&k2));
+        assert_eq!(k1.sign(&msg), k2.sign(&msg));
+
+        let sigs = k1.sign_batch(&[msg]);
+        assert_eq!(sigs[0], k2.sign(&msg));
+    }
+}
@@ -0,0 +1,353 @@
+//! Implements FROST threshold signing for Schnorr signatures over Jubjub.
+//!
+//! The private key is split among `N` parties with Shamir secret sharing, and any `T` of them can
+//! jointly produce a signature with the two-round FROST protocol. The resulting signatures are
+//! plain [`JJSchnorrSignature`]s, so they verify (natively and in-circuit) under the group public
+//! key exactly like signatures from [`JubjubSchnorr`](super::jj_schnorr::JubjubSchnorr).
+
+use crate::{
+    crypto::hash::HasherZK,
+    impls::{
+        centralized::ds::sig::{
+            jj_schnorr::{
+                challenge, JJSchnorrPubkey, JJSchnorrPubkeyVar, JJSchnorrSignature,
+                JJSchnorrSignatureVar, JubjubFr,
+            },
+            Privkey, Pubkey, Signature,
+        },
+        hash::Poseidon,
+    },
+};
+use ark_bls12_381::Fr as F;
+use ark_ec::{twisted_edwards::Affine, AffineRepr, CurveGroup, PrimeGroup};
+use ark_ed_on_bls12_381::{EdwardsProjective as Jubjub, JubjubConfig};
+use ark_ff::{AdditiveGroup, BigInteger, Field, PrimeField, UniformRand};
+use rand::{thread_rng, CryptoRng, RngCore};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+const FROST_BINDING_SEPARATOR: u8 = 0x08;
+
+/// The commitments of a party to its nonces, sent in the first round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrostCommitment {
+    /// The index of the party.
+    pub index: u64,
+    /// The commitment to the hiding nonce.
+    pub hiding: Affine<JubjubConfig>,
+    /// The commitment to the binding nonce.
+    pub binding: Affine<JubjubConfig>,
+}
+
+/// A party in the FROST protocol.
+///
+/// A party may be local (see [`FrostShare`]), or a handle to a remote signer.
+pub trait FrostParticipant {
+    /// The index of the party, which is the point its share was evaluated at.
+    fn index(&self) -> u64;
+
+    /// Round one: sample fresh nonces, and commit to them.
+    ///
+    /// Returns `None` if the party refuses to sign.
+    fn commit(&mut self) -> Option<FrostCommitment>;
+
+    /// Round two: respond with a signature share on a message, given the commitments of all
+    /// signing parties.
+    ///
+    /// The nonces from round one are consumed, so this may only be called once per commitment.
+    fn respond(&mut self, msg: F, commitments: &[FrostCommitment]) -> Option<JubjubFr>;
+}
+
+/// A handle to a party in the FROST protocol.
+pub type Participant = Arc<Mutex<dyn FrostParticipant + Send>>;
+
+/// Computes the Lagrange coefficient at `0` of a party, given the indices of all signing parties.
+fn lagrange(index: u64, indices: &[u64]) -> Option<JubjubFr> {
+    let i = JubjubFr::from(index);
+    let mut num = JubjubFr::ONE;
+    let mut den = JubjubFr::ONE;
+    for j in indices.iter().filter(|j| **j != index) {
+        let j = JubjubFr::from(*j);
+        num *= j;
+        den *= j - i;
+    }
+    Some(num * den.inverse()?)
+}
+
+/// Computes the binding factor of each party, and the group commitment.
+fn group_commitment(msg: &F, commitments: &[FrostCommitment]) -> (Vec<JubjubFr>, Jubjub) {
+    let mut base = vec![F::from(FROST_BINDING_SEPARATOR), *msg];
+    for c in commitments {
+        base.extend([
+            F::from(c.index),
+            c.hiding.x,
+            c.hiding.y,
+            c.binding.x,
+            c.binding.y,
+        ]);
+    }
+
+    let r_bitlen = JubjubFr::MODULUS_BIT_SIZE as usize;
+    let rhos: Vec<JubjubFr> = commitments
+        .iter()
+        .map(|c| {
+            let mut input = base.clone();
+            input.push(F::from(c.index));
+            let bits = <Poseidon<2>>::hash(&input).into_bigint().to_bits_le();
+            JubjubFr::from_bigint(<JubjubFr as PrimeField>::BigInt::from_bits_le(
+                &bits[..r_bitlen - 1],
+            ))
+            .expect("couldn't convert BaseField elem to ScalarField elem")
+        })
+        .collect();
+
+    let r = commitments
+        .iter()
+        .zip(rhos.iter())
+        .fold(Jubjub::default(), |acc, (c, rho)| {
+            acc + c.hiding + c.binding * *rho
+        });
+
+    (rhos, r)
+}
+
+/// A local party in the FROST protocol, holding a share of the private key.
+#[derive(Clone)]
+pub struct FrostShare {
+    index: u64,
+    secret: JubjubFr,
+    nonces: Option<(JubjubFr, JubjubFr, FrostCommitment)>,
+}
+
+impl FrostShare {
+    /// Construct a party from its index and share of the private key.
+    pub fn new(index: u64, secret: JubjubFr) -> Self {
+        Self {
+            index,
+            secret,
+            nonces: None,
+        }
+    }
+
+    /// Get the verification share of the party, which is the public key of its share.
+    pub fn verification_share(&self) -> Jubjub {
+        Jubjub::generator() * self.secret
+    }
+}
+
+impl FrostParticipant for FrostShare {
+    fn index(&self) -> u64 {
+        self.index
+    }
+
+    fn commit(&mut self) -> Option<FrostCommitment> {
+        let mut rng = thread_rng();
+        let g = Jubjub::generator();
+        let d = JubjubFr::rand(&mut rng);
+        let e = JubjubFr::rand(&mut rng);
+        let com = FrostCommitment {
+            index: self.index,
+            hiding: (g * d).into_affine(),
+            binding: (g * e).into_affine(),
+        };
+        self.nonces = Some((d, e, com));
+        Some(com)
+    }
+
+    fn respond(&mut self, msg: F, commitments: &[FrostCommitment]) -> Option<JubjubFr> {
+        let (d, e, com) = self.nonces.take()?;
+        let pos = commitments.iter().position(|c| *c == com)?;
+
+        let (rhos, r) = group_commitment(&msg, commitments);
+        let c = challenge(&r.into_affine(), &msg);
+        let indices: Vec<u64> = commitments.iter().map(|c| c.index).collect();
+        let lambda = lagrange(self.index, &indices)?;
+
+        // s_i is k_i - c lambda_i x_i, so the shares sum to k - c x
+        Some(d + e * rhos[pos] - c * lambda * self.secret)
+    }
+}
+
+/// A private key shared among `N` parties, of which `T` are needed to sign.
+///
+/// Signing runs the two-round FROST protocol with the first `T` parties which agree to commit.
+/// Each signature share is checked against the verification share of its party before
+/// aggregating, so a misbehaving party makes signing fail rather than produce an invalid
+/// signature.
+#[derive(Clone)]
+pub struct DistributedPrivkey<const T: usize, const N: usize> {
+    /// The group public key.
+    pub group_key: JJSchnorrPubkey,
+
+    /// The verification shares of the parties, keyed by index.
+    pub verification_shares: BTreeMap<u64, Jubjub>,
+
+    /// The parties holding the shares.
+    pub participants: Vec<Participant>,
+}
+
+impl<const T: usize, const N: usize> DistributedPrivkey<T, N> {
+    /// Split a fresh private key into `N` local shares with a trusted dealer.
+    pub fn deal(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        assert!(0 < T && T <= N);
+
+        // f(0) is the private key, and f(i) is the share of party i
+        let coeffs: Vec<JubjubFr> = (0..T).map(|_| JubjubFr::rand(rng)).collect();
+        let shares: Vec<FrostShare> = (1..=N as u64)
+            .map(|i| {
+                let x = JubjubFr::from(i);
+                let secret = coeffs
+                    .iter()
+                    .rev()
+                    .fold(JubjubFr::ZERO, |acc, a| acc * x + a);
+                FrostShare::new(i, secret)
+            })
+            .collect();
+
+        Self::from_participants(
+            JJSchnorrPubkey(Jubjub::generator() * coeffs[0]),
+            shares
+                .iter()
+                .map(|s| (s.index, s.verification_share()))
+                .collect(),
+            shares
+                .into_iter()
+                .map(|s| Arc::new(Mutex::new(s)) as Participant)
+                .collect(),
+        )
+    }
+
+    /// Construct a distributed key from the group key, the verification shares, and handles to
+    /// the parties.
+    pub fn from_participants(
+        group_key: JJSchnorrPubkey,
+        verification_shares: BTreeMap<u64, Jubjub>,
+        participants: Vec<Participant>,
+    ) -> Self {
+        Self {
+            group_key,
+            verification_shares,
+            participants,
+        }
+    }
+
+    fn sign_frost(&self, msg: F) -> Option<JJSchnorrSignature> {
+        // Round one
+        let mut signers = vec![];
+        let mut commitments = vec![];
+        for p in &self.participants {
+            if signers.len() == T {
+                break;
+            }
+            if let Some(com) = p.lock().ok()?.commit() {
+                signers.push(p);
+                commitments.push(com);
+            }
+        }
+        if signers.len() < T {
+            return None;
+        }
+
+        // Round two
+        let (rhos, r) = group_commitment(&msg, &commitments);
+        let r = r.into_affine();
+        let c = challenge(&r, &msg);
+        let indices: Vec<u64> = commitments.iter().map(|c| c.index).collect();
+
+        let mut s = JubjubFr::ZERO;
+        for ((p, com), rho) in signers.iter().zip(commitments.iter()).zip(rhos) {
+            let s_i = p.lock().ok()?.respond(msg, &commitments)?;
+
+            // Check g^s_i Y_i^(c lambda_i) = D_i E_i^rho_i
+            let y_i = self.verification_shares.get(&com.index)?;
+            let lambda = lagrange(com.index, &indices)?;
+            if Jubjub::generator() * s_i + *y_i * (c * lambda)
+                != com.hiding.into_group() + com.binding * rho
+            {
+                return None;
+            }
+            s += s_i;
+        }
+
+        Some(JJSchnorrSignature { e: c, s })
+    }
+}
+
+impl<const T: usize, const N: usize> Privkey<F> for DistributedPrivkey<T, N> {
+    type CompressedPrivKey = DistributedPrivkey<T, N>;
+
+    type Sig = JJSchnorrSignature;
+
+    type Pubkey = JJSchnorrPubkey;
+
+    fn gen_ckey(rng: &mut (impl CryptoRng + RngCore)) -> Self::CompressedPrivKey {
+        Self::deal(rng)
+    }
+
+    fn into_key(c: Self::CompressedPrivKey) -> Self {
+        c
+    }
+
+    fn gen_key(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        Self::deal(rng)
+    }
+
+    fn get_pubkey(&self) -> Self::Pubkey {
+        self.group_key
+    }
+
+    fn sign(&self, _rng: &mut (impl CryptoRng + RngCore), msg: F) -> Option<Self::Sig> {
+        let sig = self.sign_frost(msg)?;
+        Pubkey::verify(&self.group_key, sig.clone(), msg).then_some(sig)
+    }
+}
+
+/// Schnorr signatures over Jubjub, with the private key shared `T`-of-`N` with FROST.
+/// Implements [`Signature`].
+#[derive(Clone, Default, Debug)]
+pub struct FrostJubjubSchnorr<const T: usize, const N: usize>;
+
+impl<const T: usize, const N: usize> Signature<F> for FrostJubjubSchnorr<T, N> {
+    type SigVar = JJSchnorrSignatureVar;
+
+    type Sig = JJSchnorrSignature;
+
+    type Pubkey = JJSchnorrPubkey;
+
+    type PubkeyVar = JJSchnorrPubkeyVar;
+
+    type CPrivkey = DistributedPrivkey<T, N>;
+
+    type Privkey = DistributedPrivkey<T, N>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Tests that a threshold of parties can sign, and that signing fails without one
+    #[test]
+    fn frost_sign() {
+        let mut rng = thread_rng();
+
+        let key = DistributedPrivkey::<2, 3>::deal(&mut rng);
+        let pubkey = key.get_pubkey();
+
+        for _ in 0..5 {
+            let msg = F::rand(&mut rng);
+            let sig = FrostJubjubSchnorr::<2, 3>::sign(&key, &mut rng, msg).unwrap();
+            assert!(FrostJubjubSchnorr::<2, 3>::verify(pubkey, sig, msg));
+        }
+
+        // Only one party left
+        let lone = DistributedPrivkey::<2, 3>::from_participants(
+            key.group_key,
+            key.verification_shares.clone(),
+            key.participants[..1].to_vec(),
+        );
+        let msg = F::rand(&mut rng);
+        assert!(lone.sign(&mut rng, msg).is_none());
+    }
+}
@@ -190,10 +190,37 @@ impl GRSchnorrPrivkey {
         let k = F::rand(&mut rng);
         let com = g * k;
 
+        self.sign_with_nonce(k, com.into_affine(), msg)
+    }
+
+    /// Signs a batch of messages under `privkey`.
+    ///
+    /// All nonce commitments are normalized together, so this costs a single field inversion
+    /// rather than one per message.
+    fn sign_batch(&self, mut rng: impl Rng, msgs: &[Fq]) -> Vec<GRSchnorrSignature> {
+        let g = G::generator();
+        let ks: Vec<F> = msgs.iter().map(|_| F::rand(&mut rng)).collect();
+        let coms: Vec<G> = ks.iter().map(|k| g * k).collect();
+        let coms = G::normalize_batch(&coms);
+
+        ks.into_iter()
+            .zip(coms)
+            .zip(msgs)
+            .map(|((k, com), msg)| self.sign_with_nonce(k, com, msg))
+            .collect()
+    }
+
+    /// Signs the given message with the secret nonce `k` and its commitment `com`.
+    fn sign_with_nonce(
+        &self,
+        k: F,
+        com: <G as CurveGroup>::Affine,
+        msg: &Fq,
+    ) -> GRSchnorrSignature {
         // e is H(com || msg)
 
         let mut hash_input = vec![Fq::from(SCHNORR_HASH_SEPARATOR)];
-        hash_input.extend(com.into_affine().xy().map(|t| vec![t.0, t.1]).unwrap());
+        hash_input.extend(com.xy().map(|t| vec![t.0, t.1]).unwrap());
         hash_input.push(*msg);
         let digest: Fq = <Poseidon<2>>::hash(&hash_input);
 
@@ -383,6 +410,14 @@ impl Signature<Fq> for GrumpkinSchnorr {
     type CPrivkey = GRSchnorrPrivkey;
 
     type Privkey = GRSchnorrPrivkey;
+
+    fn sign_batch(
+        pk: &Self::Privkey,
+        rng: &mut (impl rand::CryptoRng + rand::RngCore),
+        msgs: &[Fq],
+    ) -> Option<Vec<Self::Sig>> {
+        Some(pk.sign_batch(rng, msgs))
+    }
 }
 
 // TODO: FoldSer
@@ -48,15 +48,40 @@ where
     Fq::from_bigint(Fq::BigInt::from_bits_le(&bits)).unwrap()
 }
 
-type JubjubFr = <Jubjub as PrimeGroup>::ScalarField;
+/// Computes the challenge H(com || msg), truncated to fit in the scalar field.
+pub(crate) fn challenge(com: &Affine<JubjubConfig>, msg: &BlsFr) -> JubjubFr {
+    let mut hash_input = vec![BlsFr::from(SCHNORR_HASH_SEPARATOR)];
+    hash_input.extend(com.xy().map(|t| vec![t.0, t.1]).unwrap());
+    hash_input.push(*msg);
+    let digest = <Poseidon<2>>::hash(&hash_input);
+
+    // The hash function outputs a Jubjub base field element, which we can't use as a Jubjub
+    // scalar. So we convert it to bytes and truncate it to as many bits as a ScalarField
+    // element can hold
+    let digest_bits = digest.into_bigint().to_bits_le();
+
+    // We only want the first floor(log2(p)) bits of e, where r is the prime order of the
+    // scalar field. We do this by finding how many bits are needed to represent r,
+    // and truncating e to that many bits
+    let r_bitlen = JubjubFr::MODULUS_BIT_SIZE as usize;
+    let truncated_bits = &digest_bits[..r_bitlen - 1];
+
+    // The truncated bits now represent an integer that's less than r. This cannot fail.
+    JubjubFr::from_bigint(<JubjubFr as PrimeField>::BigInt::from_bits_le(
+        truncated_bits,
+    ))
+    .expect("couldn't convert BaseField elem to ScalarField elem")
+}
+
+pub(crate) type JubjubFr = <Jubjub as PrimeGroup>::ScalarField;
 
 /// A private Jubjub BLS Schnorr signing key.
 #[derive(Clone, CanonicalSerialize, CanonicalDeserialize, Default)]
-pub struct JJSchnorrPrivkey(JubjubFr);
+pub struct JJSchnorrPrivkey(pub(crate) JubjubFr);
 
 /// A public Jubjub BLS Schnorr verification key.
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Default, CanonicalSerialize, CanonicalDeserialize)]
-pub struct JJSchnorrPubkey(Jubjub);
+pub struct JJSchnorrPubkey(pub(crate) Jubjub);
 
 /// A public Jubjub BLS Schnorr verification key in-circuit.
 #[derive(Clone)]
@@ -75,9 +100,9 @@ impl Default for JJSchnorrPubkeyVar {
 #[derive(Debug, Clone, Default, CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq)]
 pub struct JJSchnorrSignature {
     /// Challenge
-    e: JubjubFr,
+    pub(crate) e: JubjubFr,
     /// Response to challenge
-    s: JubjubFr,
+    pub(crate) s: JubjubFr,
 }
 
 /// A Jubjub BLS Schnorr signature in-circuit.
@@ -134,29 +159,7 @@ impl JJSchnorrPubkey {
         let com = g * sig.s + self.0 * sig.e;
 
         // e is H(com || msg)
-        let mut hash_input = vec![BlsFr::from(SCHNORR_HASH_SEPARATOR)];
-        hash_input.extend(com.into_affine().xy().map(|t| vec![t.0, t.1]).unwrap());
-        hash_input.push(*msg);
-        let digest = <Poseidon<2>>::hash(&hash_input);
-
-        // The hash function outputs a Jubjub base field element, which we can't use as a Jubjub
-        // scalar. So we convert it to bytes and truncate it to as many bits as a ScalarField
-        // element can hold
-        let e = {
-            let digest_bits = digest.into_bigint().to_bits_le();
-
-            // We only want the first floor(log2(p)) bits of e, where r is the prime order of the
-            // scalar field. We do this by finding how many bits are needed to represent r,
-            // and truncating e to that many bits
-            let r_bitlen = JubjubFr::MODULUS_BIT_SIZE as usize;
-            let truncated_bits = &digest_bits[..r_bitlen - 1];
-
-            // The truncated bits now represent an integer that's less than r. This cannot fail.
-            JubjubFr::from_bigint(<JubjubFr as PrimeField>::BigInt::from_bits_le(
-                &truncated_bits,
-            ))
-            .unwrap()
-        };
+        let e = challenge(&com.into_affine(), msg);
 
         e == sig.e
     }
@@ -197,30 +200,35 @@ impl JJSchnorrPrivkey {
         let k = JubjubFr::rand(&mut rng);
         let com = g * k;
 
+        self.sign_with_nonce(k, com.into_affine(), msg)
+    }
+
+    /// Signs a batch of messages under `privkey`.
+    ///
+    /// All nonce commitments are normalized together, so this costs a single field inversion
+    /// rather than one per message.
+    fn sign_batch(&self, mut rng: impl Rng, msgs: &[BlsFr]) -> Vec<JJSchnorrSignature> {
+        let g = Jubjub::generator();
+        let ks: Vec<JubjubFr> = msgs.iter().map(|_| JubjubFr::rand(&mut rng)).collect();
+        let coms: Vec<Jubjub> = ks.iter().map(|k| g * k).collect();
+        let coms = Jubjub::normalize_batch(&coms);
+
+        ks.into_iter()
+            .zip(coms)
+            .zip(msgs)
+            .map(|((k, com), msg)| self.sign_with_nonce(k, com, msg))
+            .collect()
+    }
+
+    /// Signs the given message with the secret nonce `k` and its commitment `com`.
+    fn sign_with_nonce(
+        &self,
+        k: JubjubFr,
+        com: <Jubjub as CurveGroup>::Affine,
+        msg: &BlsFr,
+    ) -> JJSchnorrSignature {
         // e is H(com || msg)
-        let mut hash_input = vec![BlsFr::from(SCHNORR_HASH_SEPARATOR)];
-        hash_input.extend(com.into_affine().xy().map(|t| vec![t.0, t.1]).unwrap());
-        hash_input.push(*msg);
-        let digest = <Poseidon<2>>::hash(&hash_input);
-
-        // The hash function outputs a Jubjub base field element, which we can't use as a Jubjub
-        // scalar. So we convert it to bytes and truncate it to as many bits as a ScalarField
-        // element can hold
-        let e = {
-            let digest_bits = digest.into_bigint().to_bits_le();
-
-            // We only want the first floor(log2(p)) bits of e, where r is the prime order of the
-            // scalar field. We do this by finding how many bits are needed to represent r,
-            // and truncating e to that many bits
-            let r_bitlen = JubjubFr::MODULUS_BIT_SIZE as usize;
-            let truncated_bits = &digest_bits[..r_bitlen - 1];
-
-            // The truncated bits now represent an integer that's less than r. This cannot fail.
-            JubjubFr::from_bigint(<JubjubFr as PrimeField>::BigInt::from_bits_le(
-                &truncated_bits,
-            ))
-            .expect("couldn't convert BaseField elem to ScalarField elem")
-        };
+        let e = challenge(&com, msg);
 
         // s is k - e * privkey
         let s = k - (e * self.0);
@@ -398,6 +406,14 @@ impl Signature<F> for JubjubSchnorr {
     type CPrivkey = JJSchnorrPrivkey;
 
     type Privkey = JJSchnorrPrivkey;
+
+    fn sign_batch(
+        pk: &Self::Privkey,
+        rng: &mut (impl rand::CryptoRng + rand::RngCore),
+        msgs: &[F],
+    ) -> Option<Vec<Self::Sig>> {
+        Some(pk.sign_batch(rng, msgs))
+    }
 }
 
 // TODO: FoldSer
@@ -457,4 +473,21 @@ mod test {
         }
         Ok(())
     }
+
+    // Tests that batch signing produces signatures which verify natively
+    #[test]
+    fn schnorr_sign_batch() {
+        let mut rng = thread_rng();
+
+        let privkey = JJSchnorrPrivkey::gen(&mut rng);
+        let pubkey: JJSchnorrPubkey = (&privkey).into();
+        let msgs: Vec<BlsFr> = (0..20).map(|_| BlsFr::rand(&mut rng)).collect();
+
+        let sigs = privkey.sign_batch(&mut rng, &msgs);
+
+        assert_eq!(sigs.len(), msgs.len());
+        for (msg, sig) in msgs.iter().zip(sigs.iter()) {
+            assert!(pubkey.verify(msg, sig));
+        }
+    }
 }
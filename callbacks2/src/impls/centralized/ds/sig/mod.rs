@@ -99,6 +99,18 @@ pub trait Signature<F: PrimeField>: Clone {
         pk.sign(rng, msg)
     }
 
+    /// Sign a batch of messages with a private key.
+    ///
+    /// By default, this signs each message individually. Signature schemes which can amortize
+    /// work across messages should override this. Returns `None` if any signature fails.
+    fn sign_batch(
+        pk: &Self::Privkey,
+        rng: &mut (impl CryptoRng + RngCore),
+        msgs: &[F],
+    ) -> Option<Vec<Self::Sig>> {
+        msgs.iter().map(|m| Self::sign(pk, rng, *m)).collect()
+    }
+
     /// Verify a message with a public verification key.
     fn verify(vk: Self::Pubkey, signature: Self::Sig, msg: F) -> bool {
         vk.verify(signature, msg)
@@ -125,3 +137,12 @@ pub mod bls377_schnorr;
 
 /// Schnorr signatures over Grumpkin and bn254 for in-circuit verification.
 pub mod gr_schnorr;
+
+/// EdDSA signatures (Ed25519-style key derivation) over Baby Jubjub and bn254 for in-circuit verification.
+pub mod ed25519;
+
+/// Aggregatable BLS signatures over bls12_377 for in-circuit verification.
+pub mod bls;
+
+/// Threshold Schnorr signatures over Jubjub, signed with FROST.
+pub mod frost;
@@ -24,6 +24,26 @@ use rand::{distributions::Standard, prelude::Distribution, thread_rng};
 
 use crate::impls::centralized::ds::sigstore::NonmembStore;
 
+/// An error when advancing a [`SigRangeStore`] to a later epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdvanceError {
+    /// The epoch is not larger than the current epoch.
+    StaleEpoch,
+    /// Signing the new ranges failed.
+    Signing,
+}
+
+impl std::fmt::Display for AdvanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdvanceError::StaleEpoch => write!(f, "epoch is not larger than the current epoch"),
+            AdvanceError::Signing => write!(f, "signing failed"),
+        }
+    }
+}
+
+impl std::error::Error for AdvanceError {}
+
 /// A signed range and time.
 #[derive(Clone, Default, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SignedRange<F: PrimeField, S: Signature<F>> {
@@ -136,6 +156,35 @@ where
     }
 }
 
+/// The exclusive upper bound of all signed ranges, `((p - 1) / 2) - 1`.
+pub fn range_max<F: PrimeField>() -> F {
+    F::from_bigint(F::MODULUS_MINUS_ONE_DIV_TWO).unwrap() - F::ONE
+}
+
+/// Compute the ranges covering exactly the tickets in `[0, range_max)` which are not in `tiks`.
+///
+/// Tickets may be given in any order and with duplicates. Ranges around each ticket are split,
+/// ranges between consecutive tickets are merged, and empty ranges are dropped.
+pub fn complement_ranges<F: PrimeField>(tiks: &[F]) -> Vec<(F, F)> {
+    let max = range_max::<F>();
+    let mut v: Vec<F> = tiks.iter().copied().filter(|t| *t < max).collect();
+    v.sort();
+    v.dedup();
+
+    let mut ranges = vec![];
+    let mut bot = F::ZERO;
+    for top in v {
+        if bot < top {
+            ranges.push((bot, top));
+        }
+        bot = top + F::ONE;
+    }
+    if bot < max {
+        ranges.push((bot, max));
+    }
+    ranges
+}
+
 /// This is a nonmembership store which uses signed ranges.
 ///
 /// To prove nonmembership, tickets are ordered from `0` to `((p - 1) / 2) - 1`.
@@ -143,7 +192,9 @@ where
 /// range.
 ///
 /// On an epoch update, the new ranges are created and are resigned (along with the epoch).
-/// Therefore, all signed ranges also contain an epoch.
+/// Therefore, all signed ranges also contain an epoch. Epochs need not be consecutive: any
+/// increasing sequence of field elements (such as 64-bit timestamps) may be used with
+/// [`SigRangeStore::advance_to`].
 #[derive(Clone, Default, Debug)]
 pub struct SigRangeStore<F: PrimeField + Absorb, S: Signature<F>>
 where
@@ -158,6 +209,9 @@ where
 
     /// The current epoch on this range store.
     pub epoch: F,
+
+    /// The past epochs along with their lists of nonmembership ranges, in order.
+    pub history: Vec<(F, Vec<SignedRange<F, S>>)>,
 }
 
 impl<F: PrimeField + Absorb, S: Signature<F>> SigRangeStore<F, S>
@@ -172,6 +226,7 @@ where
             pubkey,
             ncalled_cbs: db,
             epoch,
+            history: vec![],
         }
     }
 
@@ -186,6 +241,8 @@ where
     }
 
     /// Rotate the key. Resigns all ranges with a new key.
+    ///
+    /// Ranges from past epochs are signed under the old key, so they are dropped.
     pub fn rotate_key(&mut self, new_key: S::Privkey) -> Result<(), ()> {
         self.pubkey = S::get_pubkey(&new_key);
         self.privkey = new_key;
@@ -214,9 +271,53 @@ where
         }
 
         self.ncalled_cbs = sv;
+        self.history.clear();
+
+        Ok(())
+    }
+
+    /// Step to a later epoch, resigning the ranges not covered by the current tickets.
+    ///
+    /// Unlike [`NonmembStore::update_epoch`], which steps the epoch by one, this accepts any
+    /// epoch larger than the current one. Returns an error (and does nothing) if the epoch is not
+    /// larger, or signing fails.
+    pub fn advance_to(
+        &mut self,
+        rng: &mut (impl rand::CryptoRng + rand::RngCore),
+        current_store: &[FakeSigPubkey<F>],
+        epoch: F,
+    ) -> Result<(), AdvanceError> {
+        if epoch <= self.epoch {
+            return Err(AdvanceError::StaleEpoch);
+        }
+
+        let tiks: Vec<F> = current_store.iter().map(|t| t.to()).collect();
+        let ranges = complement_ranges(&tiks);
+
+        let msgs: Vec<F> = ranges
+            .iter()
+            .map(|r| <Poseidon<2>>::hash(&[r.0, r.1, epoch]))
+            .collect();
+        let sigs = S::sign_batch(&self.privkey, rng, &msgs).ok_or(AdvanceError::Signing)?;
+
+        self.history
+            .push((self.epoch, std::mem::take(&mut self.ncalled_cbs)));
+        self.epoch = epoch;
+        self.ncalled_cbs = ranges
+            .into_iter()
+            .zip(sigs)
+            .map(|(range, sig)| SignedRange { range, epoch, sig })
+            .collect();
 
         Ok(())
     }
+
+    /// Drop the ranges of all past epochs before some epoch.
+    ///
+    /// Nonmembership witnesses for these epochs can no longer be fetched.
+    pub fn prune_history(&mut self, before: F) {
+        self.history.retain(|(e, _)| *e >= before);
+    }
 }
 
 impl<F: PrimeField + Absorb, S: Signature<F>> NonmembStore<F> for SigRangeStore<F, S>
@@ -234,10 +335,7 @@ where
 
     fn new(rng: &mut (impl rand::CryptoRng + rand::RngCore)) -> Self {
         let sk = S::gen_key(rng);
-        let init_range = (
-            F::ZERO,
-            F::from_bigint(F::MODULUS_MINUS_ONE_DIV_TWO).unwrap() - F::ONE,
-        );
+        let init_range = (F::ZERO, range_max::<F>());
         let sig = S::sign(
             &sk,
             rng,
@@ -254,6 +352,7 @@ where
             pubkey: S::get_pubkey(&sk),
             ncalled_cbs: vec![first_range],
             epoch: F::ZERO,
+            history: vec![],
         }
     }
 
@@ -266,73 +365,8 @@ where
         rng: &mut (impl rand::CryptoRng + rand::RngCore),
         current_store: Vec<FakeSigPubkey<F>>,
     ) {
-        self.epoch += F::ONE;
-
-        let mut v = vec![];
-
-        for i in &current_store {
-            v.push(i.to());
-        }
-
-        v.sort();
-
-        let mut updated_ranges = vec![];
-
-        let mut bot = F::ZERO;
-
-        for top in v {
-            if bot != top {
-                updated_ranges.push((bot, top));
-            }
-            bot = top + F::ONE;
-        }
-
-        // There's a small problem here: if we get a ticket at (top - 1), then there is exactly 1
-        // left over at the top, in which case the range becomes [max, max). But then if someone
-        // gets the max ticket, this check is not satisfied.
-        if bot != F::ZERO {
-            updated_ranges.push((
-                bot,
-                F::from_bigint(F::MODULUS_MINUS_ONE_DIV_TWO).unwrap() - F::ONE,
-            ));
-        }
-
-        let mut sv = vec![];
-
-        for range in updated_ranges {
-            let sig = S::sign(
-                &self.privkey,
-                rng,
-                <Poseidon<2>>::hash(&[range.0, range.1, self.epoch]),
-            )
-            .unwrap();
-            sv.push(SignedRange {
-                range,
-                epoch: self.epoch,
-                sig,
-            });
-        }
-
-        if sv.is_empty() {
-            let init_range = (
-                F::ZERO,
-                F::from_bigint(F::MODULUS_MINUS_ONE_DIV_TWO).unwrap() - F::ONE,
-            );
-            let sig = S::sign(
-                &self.privkey,
-                rng,
-                <Poseidon<2>>::hash(&[init_range.0, init_range.1, self.epoch]),
-            )
-            .unwrap();
-            let first_range = SignedRange {
-                range: init_range,
-                epoch: self.epoch,
-                sig,
-            };
-            sv.push(first_range);
-        }
-
-        self.ncalled_cbs = sv;
+        let epoch = self.epoch + F::ONE;
+        self.advance_to(rng, &current_store, epoch).unwrap();
     }
 
     fn get_nmemb(
@@ -347,6 +381,21 @@ where
         None
     }
 
+    fn get_nmemb_at(
+        &self,
+        tik: &FakeSigPubkey<F>,
+        epoch: F,
+    ) -> Option<(Self::NonMembershipPub, Self::NonMembershipWitness)> {
+        if epoch == self.epoch {
+            return self.get_nmemb(tik);
+        }
+        let (_, ranges) = self.history.iter().find(|(e, _)| *e == epoch)?;
+        ranges
+            .iter()
+            .find(|sr| sr.is_in_range(tik.to()))
+            .map(|sr| (self.get_pubkey(), sr.clone()))
+    }
+
     fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
         for sr in &self.ncalled_cbs {
             if sr.range.0 <= tik.to() && tik.to() < sr.range.1 {
@@ -387,3 +436,126 @@ where
         self.get_pubkey()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::impls::centralized::ds::sig::gr_schnorr::GrumpkinSchnorr;
+    use ark_bn254::Fr;
+
+    type Store = SigRangeStore<Fr, GrumpkinSchnorr>;
+
+    fn tiks(v: &[u64]) -> Vec<FakeSigPubkey<Fr>> {
+        v.iter().map(|t| FakeSigPubkey::new(Fr::from(*t))).collect()
+    }
+
+    fn ranges(v: &[(u64, u64)]) -> Vec<(Fr, Fr)> {
+        v.iter()
+            .map(|(a, b)| (Fr::from(*a), Fr::from(*b)))
+            .collect()
+    }
+
+    // Tests the complement of empty, adjacent, and repeated tickets
+    #[test]
+    fn complement_ranges_edges() {
+        let max = range_max::<Fr>();
+
+        assert_eq!(complement_ranges::<Fr>(&[]), vec![(Fr::from(0), max)]);
+        assert_eq!(complement_ranges(&[Fr::from(0)]), vec![(Fr::from(1), max)]);
+
+        // Adjacent tickets leave no empty range between them
+        let out = complement_ranges(&[Fr::from(3), Fr::from(4)]);
+        assert_eq!(out[0], (Fr::from(0), Fr::from(3)));
+        assert_eq!(out[1], (Fr::from(5), max));
+
+        // Unordered and repeated tickets give the same ranges as sorted unique tickets
+        let out = complement_ranges(&[Fr::from(7), Fr::from(3), Fr::from(7)]);
+        assert_eq!(&out[..2], &ranges(&[(0, 3), (4, 7)])[..]);
+        assert_eq!(out[2], (Fr::from(8), max));
+
+        // Tickets at or past the bound are ignored, and the last range ends before the bound
+        let out = complement_ranges(&[max - Fr::from(1), max, -Fr::from(1)]);
+        assert_eq!(out, vec![(Fr::from(0), max - Fr::from(1))]);
+    }
+
+    // Tests that the complement is sorted, disjoint, and excludes exactly the tickets
+    #[test]
+    fn complement_ranges_disjoint() {
+        let v: Vec<Fr> = [9, 1, 2, 20, 5, 6, 7, 2].map(Fr::from).to_vec();
+        let out = complement_ranges(&v);
+        for w in out.windows(2) {
+            assert!(w[0].0 < w[0].1 && w[0].1 < w[1].0);
+        }
+        for t in 0..25u64 {
+            let t = Fr::from(t);
+            let covered = out.iter().filter(|(a, b)| *a <= t && t < *b).count();
+            assert_eq!(covered, usize::from(!v.contains(&t)));
+        }
+    }
+
+    // Tests that the store only advances to later epochs, and signs the new ranges
+    #[test]
+    fn advance_to_later_epoch() {
+        let mut rng = thread_rng();
+        let mut store = Store::new(&mut rng);
+        let now = Fr::from(1_700_000_000u64);
+
+        store.advance_to(&mut rng, &tiks(&[4, 5]), now).unwrap();
+        assert_eq!(store.get_epoch(), now);
+        assert_eq!(store.ncalled_cbs.len(), 2);
+        for sr in &store.ncalled_cbs {
+            assert_eq!(sr.epoch, now);
+            let msg = <Poseidon<2>>::hash(&[sr.range.0, sr.range.1, sr.epoch]);
+            assert!(GrumpkinSchnorr::verify(
+                store.get_pubkey(),
+                sr.sig.clone(),
+                msg
+            ));
+        }
+
+        assert_eq!(
+            store.advance_to(&mut rng, &tiks(&[]), now),
+            Err(AdvanceError::StaleEpoch)
+        );
+        assert_eq!(
+            store.advance_to(&mut rng, &tiks(&[]), now - Fr::from(1)),
+            Err(AdvanceError::StaleEpoch)
+        );
+        assert_eq!(store.get_epoch(), now);
+        assert_eq!(store.history.len(), 1);
+    }
+
+    // Tests that pruning drops exactly the epochs before the bound, even without ranges
+    #[test]
+    fn prune_history_across_epochs() {
+        let mut rng = thread_rng();
+        let sk = GrumpkinSchnorr::gen_key(&mut rng);
+        let mut store = Store::from(sk, vec![], Fr::from(5));
+
+        let tik = FakeSigPubkey::new(Fr::from(10));
+        for (epoch, called) in [(10, vec![]), (20, vec![3]), (30, vec![3, 10])] {
+            store
+                .advance_to(&mut rng, &tiks(&called), Fr::from(epoch))
+                .unwrap();
+        }
+        let epochs: Vec<Fr> = store.history.iter().map(|(e, _)| *e).collect();
+        assert_eq!(epochs, [5, 10, 20].map(Fr::from));
+
+        // The ticket is uncalled at every epoch but the current one
+        assert!(store.get_nmemb_at(&tik, Fr::from(10)).is_some());
+        assert!(store.get_nmemb_at(&tik, Fr::from(20)).is_some());
+        assert!(store.get_nmemb_at(&tik, Fr::from(30)).is_none());
+        assert!(store.get_nmemb_at(&tik, Fr::from(15)).is_none());
+
+        store.prune_history(Fr::from(10));
+        assert_eq!(store.history.len(), 2);
+        store.prune_history(Fr::from(20));
+        assert!(store.get_nmemb_at(&tik, Fr::from(10)).is_none());
+        assert_eq!(
+            store.get_nmemb_at(&tik, Fr::from(20)).unwrap().1.epoch,
+            Fr::from(20)
+        );
+        store.prune_history(Fr::from(30));
+        assert!(store.history.is_empty());
+    }
+}
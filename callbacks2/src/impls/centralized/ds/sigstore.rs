@@ -1,7 +1,10 @@
 use crate::{
     crypto::hash::HasherZK,
     generic::{
-        bulletin::{CallbackBul, JoinableBulletin, PublicCallbackBul, PublicUserBul, UserBul},
+        bulletin::{
+            BatchedInteraction, BulError, BulletinDelta, CallbackBul, EpochCallbackBul,
+            JoinableBulletin, PublicCallbackBul, PublicEpochCallbackBul, PublicUserBul, UserBul,
+        },
         callbacks::CallbackCom,
         object::{Com, Nul, Time, TimeVar},
         service::ServiceProvider,
@@ -12,8 +15,9 @@ use crate::{
             crypto::{FakeSigPubkey, FakeSigPubkeyVar, NoEnc, NoSigOTP},
             ds::{
                 sig::{
-                    bls377_schnorr::Bls377Schnorr, gr_schnorr::GrumpkinSchnorr,
-                    jj_schnorr::JubjubSchnorr, uov::BleedingUOV, Signature,
+                    bls377_schnorr::Bls377Schnorr, frost::FrostJubjubSchnorr,
+                    gr_schnorr::GrumpkinSchnorr, jj_schnorr::JubjubSchnorr, uov::BleedingUOV,
+                    Signature,
                 },
                 sigrange::SigRangeStore,
             },
@@ -36,6 +40,14 @@ use rand::{
     thread_rng, CryptoRng, Rng, RngCore,
 };
 
+/// An entry of a [`SigObjStore`]: an object commitment, the old nullifier of the object, the
+/// callback commitments given with it, and the signature on it.
+pub type ObjEntry<F, S> = (Com<F>, Nul<F>, Vec<Com<F>>, <S as Signature<F>>::Sig);
+
+/// An entry of a [`CallbackStore`]: a called ticket, the arguments it was called with, the time it
+/// was called, and the signature on it.
+pub type CalledEntry<F, S, Args> = (FakeSigPubkey<F>, Args, Time<F>, <S as Signature<F>>::Sig);
+
 /// This is a centralized object storage system, with proofs of membership.
 ///
 /// To add an object, object commitments are signed with a private key associated to the server.
@@ -62,6 +74,10 @@ pub struct SigObjStore<F: PrimeField + Absorb, S: Signature<F>> {
 
     /// The signatures on each object.
     pub sigs: Vec<S::Sig>,
+
+    /// The number of users which joined the bulletin, or `None` if unknown (for example, when
+    /// restored from a database).
+    pub num_joined: Option<u64>,
 }
 
 impl<F: PrimeField + Absorb, S: Signature<F>> SigObjStore<F, S> {
@@ -77,10 +93,14 @@ impl<F: PrimeField + Absorb, S: Signature<F>> SigObjStore<F, S> {
             old_nuls: vec![],
             cb_com_lists: vec![],
             sigs: vec![],
+            num_joined: Some(0),
         }
     }
 
     /// Given an already existing database, initialize the store from this database.
+    ///
+    /// Since the database does not distinguish joins from interactions, the number of joined users
+    /// is unknown.
     pub fn from(privkey: S::Privkey, db: Vec<(Com<F>, Nul<F>, Vec<Com<F>>, S::Sig)>) -> Self {
         let pubkey = S::get_pubkey(&privkey);
         let coms = db.iter().map(|(c, _, _, _)| c.clone()).collect();
@@ -94,6 +114,7 @@ impl<F: PrimeField + Absorb, S: Signature<F>> SigObjStore<F, S> {
             old_nuls,
             cb_com_lists,
             sigs,
+            num_joined: None,
         }
     }
 
@@ -147,6 +168,150 @@ impl<F: PrimeField + Absorb, S: Signature<F>> SigObjStore<F, S> {
         }
         None
     }
+
+    /// Get the head of the transition log of the store. See [`Checkpoint`].
+    pub fn log_head(&self) -> F {
+        log_head(
+            F::ZERO,
+            (0..self.coms.len())
+                .map(|i| (&self.coms[i], &self.old_nuls[i], &self.cb_com_lists[i][..])),
+        )
+    }
+
+    /// Sign a checkpoint of the store at the current version.
+    ///
+    /// The checkpoint is signed with `log_key`, which **must not** be the key of the store: the
+    /// store signs any commitment a user joins with, so a user could otherwise obtain a signature
+    /// on an arbitrary checkpoint. Clients verify checkpoints under the public key of `log_key`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use zk_callbacks::generic::bulletin::{JoinableBulletin, PublicUserBul};
+    /// # use zk_callbacks::impls::centralized::ds::sig::{gr_schnorr::GrumpkinSchnorr, Signature};
+    /// # use zk_callbacks::impls::centralized::ds::sigstore::{verify_transition, GRSchnorrObjStore};
+    /// # use ark_grumpkin::Fq as Fr;
+    /// # use rand::thread_rng;
+    /// let mut rng = thread_rng();
+    /// let mut store = GRSchnorrObjStore::new(&mut rng);
+    /// let log_key = GrumpkinSchnorr::gen_key(&mut rng);
+    /// let log_pk = GrumpkinSchnorr::get_pubkey(&log_key);
+    ///
+    /// <GRSchnorrObjStore as JoinableBulletin<Fr, bool>>::join_bul(&mut store, Fr::from(1), ()).unwrap();
+    /// let old = store.checkpoint(&log_key, &mut rng).unwrap();
+    ///
+    /// <GRSchnorrObjStore as JoinableBulletin<Fr, bool>>::join_bul(&mut store, Fr::from(2), ()).unwrap();
+    /// let new = store.checkpoint(&log_key, &mut rng).unwrap();
+    ///
+    /// let delta = <GRSchnorrObjStore as PublicUserBul<Fr, bool>>::get_updates_since(&store, old.version).unwrap();
+    /// assert!(verify_transition::<Fr, GrumpkinSchnorr>(&log_pk, &old, &new, &delta).is_ok());
+    ///
+    /// // Swapping out the first commitment is detected.
+    /// store.coms[0] = Fr::from(3);
+    /// let forged = store.checkpoint(&log_key, &mut rng).unwrap();
+    /// assert!(verify_transition::<Fr, GrumpkinSchnorr>(&log_pk, &old, &forged, &delta).is_err());
+    /// ```
+    pub fn checkpoint(
+        &self,
+        log_key: &S::Privkey,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Option<Checkpoint<F, S::Sig>> {
+        let version = self.coms.len() as u64;
+        let head = self.log_head();
+        let sig = S::sign(
+            log_key,
+            rng,
+            Checkpoint::<F, S::Sig>::message(version, head),
+        )?;
+        Some(Checkpoint { version, head, sig })
+    }
+}
+
+/// A signed checkpoint of the transition log of a user bulletin.
+///
+/// The log is a hash chain over every entry of the bulletin (the object, old nullifier, and
+/// callback commitments), so the head at some version commits to the entire bulletin up to that
+/// version. A client holding a checkpoint for version `N` may check a later checkpoint with the
+/// entries between them (a [`BulletinDelta`]) using [`verify_transition`]. If the bulletin dropped
+/// or swapped any entry, the heads no longer match, and the two signed checkpoints are evidence of
+/// the misbehavior.
+///
+/// Note that a maintainer may still sign different checkpoints for the same version to different
+/// clients. Clients should therefore compare (gossip) the checkpoints they receive.
+#[derive(Clone, Debug, Default, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Checkpoint<F: PrimeField, Sig: CanonicalSerialize + CanonicalDeserialize> {
+    /// The number of entries in the bulletin.
+    pub version: u64,
+    /// The head of the transition log after `version` entries.
+    pub head: F,
+    /// The signature on the version and head.
+    pub sig: Sig,
+}
+
+impl<F: PrimeField + Absorb, Sig: Clone + CanonicalSerialize + CanonicalDeserialize>
+    Checkpoint<F, Sig>
+{
+    fn message(version: u64, head: F) -> F {
+        <Poseidon<2>>::hash(&[F::from(version), head])
+    }
+
+    /// Verify the signature on the checkpoint.
+    pub fn verify<S: Signature<F, Sig = Sig>>(&self, log_pubkey: &S::Pubkey) -> bool {
+        S::verify(
+            log_pubkey.clone(),
+            self.sig.clone(),
+            Self::message(self.version, self.head),
+        )
+    }
+}
+
+/// Extend the head of a transition log with some entries. See [`Checkpoint`].
+pub fn log_head<'a, F: PrimeField + Absorb>(
+    head: F,
+    entries: impl IntoIterator<Item = (&'a Com<F>, &'a Nul<F>, &'a [Com<F>])>,
+) -> F {
+    entries.into_iter().fold(head, |head, (c, n, l)| {
+        let mut v = vec![head, *c, *n];
+        v.extend_from_slice(l);
+        <Poseidon<2>>::hash(&v)
+    })
+}
+
+/// Check that a checkpoint is an append-only extension of an older checkpoint.
+///
+/// The `delta` holds the entries between the two checkpoints, as returned by
+/// [`PublicUserBul::get_updates_since`] for the older version.
+pub fn verify_transition<F: PrimeField + Absorb, S: Signature<F>>(
+    log_pubkey: &S::Pubkey,
+    old: &Checkpoint<F, S::Sig>,
+    new: &Checkpoint<F, S::Sig>,
+    delta: &BulletinDelta<F, S::Pubkey, S::Sig>,
+) -> Result<(), DeltaError> {
+    if !old.verify::<S>(log_pubkey) || !new.verify::<S>(log_pubkey) {
+        return Err(DeltaError::InvalidCheckpoint);
+    }
+    if delta.from_version != old.version {
+        return Err(DeltaError::VersionMismatch {
+            expected: old.version,
+            got: delta.from_version,
+        });
+    }
+    if new.version < old.version {
+        return Err(DeltaError::NotAppendOnly);
+    }
+    if delta.to_version != new.version || delta.entries.len() as u64 != new.version - old.version {
+        return Err(DeltaError::VersionMismatch {
+            expected: new.version,
+            got: delta.to_version,
+        });
+    }
+    let head = log_head(
+        old.head,
+        delta.entries.iter().map(|(c, n, l, _)| (c, n, &l[..])),
+    );
+    if head != new.head {
+        return Err(DeltaError::NotAppendOnly);
+    }
+    Ok(())
 }
 
 impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> PublicUserBul<F, U>
@@ -193,6 +358,250 @@ impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> PublicUserBul<F, U
     ) -> Result<Boolean<F>, SynthesisError> {
         S::verify_zk(extra_pub, extra_witness, data_var)
     }
+
+    fn get_updates_since(&self, version: u64) -> Option<BulletinDelta<F, S::Pubkey, S::Sig>> {
+        let from = usize::try_from(version).ok()?;
+        if from > self.coms.len() {
+            return None;
+        }
+        let entries = (from..self.coms.len())
+            .map(|x| {
+                (
+                    self.coms[x],
+                    self.old_nuls[x],
+                    self.cb_com_lists[x].clone(),
+                    self.sigs[x].clone(),
+                )
+            })
+            .collect();
+        Some(BulletinDelta {
+            from_version: version,
+            to_version: self.coms.len() as u64,
+            memb_pub: self.get_pubkey(),
+            entries,
+        })
+    }
+
+    fn anonymity_set_size(&self) -> Option<u64> {
+        self.num_joined
+    }
+}
+
+/// An error when applying a [`BulletinDelta`] to a mirror.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeltaError {
+    /// The delta does not apply on top of the current version of the mirror.
+    VersionMismatch {
+        /// The current version of the mirror.
+        expected: u64,
+        /// The version the delta applies on top of.
+        got: u64,
+    },
+    /// The public key changed, so the mirror must be resynchronized from version `0`.
+    KeyChanged,
+    /// The signature of the entry at this version does not verify.
+    InvalidSignature(u64),
+    /// The signature on a [`Checkpoint`] does not verify.
+    InvalidCheckpoint,
+    /// The entries do not extend the previous state to the checkpoint, so an entry was dropped or
+    /// changed.
+    NotAppendOnly,
+}
+
+/// The calls made on a [`CallbackStore`] between two versions.
+///
+/// See [`CallbackStore::get_calls_since`].
+#[derive(Clone, Debug, Default, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CallbackDelta<
+    F: PrimeField,
+    Args: CanonicalSerialize + CanonicalDeserialize,
+    Pubkey: CanonicalSerialize + CanonicalDeserialize,
+    Sig: CanonicalSerialize + CanonicalDeserialize,
+> {
+    /// The version the delta applies on top of.
+    pub from_version: u64,
+    /// The version of the bulletin after applying the delta.
+    pub to_version: u64,
+    /// The current membership public key of the bulletin.
+    pub pubkey: Pubkey,
+    /// The new calls, as (ticket, arguments, time, signature).
+    pub entries: Vec<(FakeSigPubkey<F>, Args, Time<F>, Sig)>,
+}
+
+/// A client-side mirror of a [`SigObjStore`].
+///
+/// The mirror holds a copy of the public key and the database, and is kept up to date by applying
+/// the deltas from [`PublicUserBul::get_updates_since`]. All signatures are checked when applying
+/// a delta, so a mirror only ever holds valid membership data.
+///
+/// Note that this implements [`PublicUserBul`], so it may be used in place of the store when
+/// generating proofs.
+#[derive(Clone, Default, Debug)]
+pub struct SigObjMirror<F: PrimeField + Absorb, S: Signature<F>> {
+    /// The public key of the mirrored store.
+    pub pubkey: S::Pubkey,
+
+    /// The mirrored entries, as (object, old nullifier, callback commitments, signature).
+    pub db: Vec<(Com<F>, Nul<F>, Vec<Com<F>>, S::Sig)>,
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>> SigObjMirror<F, S> {
+    /// Construct a new empty mirror.
+    pub fn new() -> Self {
+        Self {
+            pubkey: S::Pubkey::default(),
+            db: vec![],
+        }
+    }
+
+    /// Get the current version of the mirror. This should be passed to
+    /// [`PublicUserBul::get_updates_since`] to fetch the next delta.
+    pub fn get_version(&self) -> u64 {
+        self.db.len() as u64
+    }
+
+    /// Apply a delta to the mirror.
+    ///
+    /// If the public key in the delta differs from the mirror, the delta must start at version
+    /// `0`, in which case the mirror is replaced. If any signature in the delta does not verify,
+    /// the mirror is left unchanged.
+    pub fn apply(&mut self, delta: BulletinDelta<F, S::Pubkey, S::Sig>) -> Result<(), DeltaError> {
+        let key_changed = delta.memb_pub.to_field_elements() != self.pubkey.to_field_elements();
+
+        if key_changed && delta.from_version != 0 {
+            return Err(DeltaError::KeyChanged);
+        }
+
+        let base = if key_changed { 0 } else { self.get_version() };
+
+        if delta.from_version != base {
+            return Err(DeltaError::VersionMismatch {
+                expected: base,
+                got: delta.from_version,
+            });
+        }
+
+        for (i, (c, _, _, s)) in delta.entries.iter().enumerate() {
+            if !S::verify(delta.memb_pub.clone(), s.clone(), *c) {
+                return Err(DeltaError::InvalidSignature(base + i as u64));
+            }
+        }
+
+        if key_changed {
+            self.db.clear();
+        }
+        self.pubkey = delta.memb_pub;
+        self.db.extend(delta.entries);
+
+        Ok(())
+    }
+
+    /// Get the head of the transition log of the mirror. See [`Checkpoint`].
+    pub fn log_head(&self) -> F {
+        log_head(F::ZERO, self.db.iter().map(|(c, n, l, _)| (c, n, &l[..])))
+    }
+
+    /// Apply a delta to the mirror, checking it against a signed checkpoint.
+    ///
+    /// This is [`SigObjMirror::apply`], but additionally checks that the mirror with the delta
+    /// applied matches the checkpoint. Since the mirror already holds the earlier entries, a
+    /// bulletin which dropped or swapped an entry is caught, even across key rotations (where
+    /// the delta restarts at version `0`, and must then begin with the entries already held).
+    pub fn apply_checked(
+        &mut self,
+        delta: BulletinDelta<F, S::Pubkey, S::Sig>,
+        checkpoint: &Checkpoint<F, S::Sig>,
+        log_pubkey: &S::Pubkey,
+    ) -> Result<(), DeltaError> {
+        if !checkpoint.verify::<S>(log_pubkey) {
+            return Err(DeltaError::InvalidCheckpoint);
+        }
+        if checkpoint.version != delta.to_version {
+            return Err(DeltaError::VersionMismatch {
+                expected: delta.to_version,
+                got: checkpoint.version,
+            });
+        }
+
+        let head = if delta.from_version == 0 {
+            let held = self.db.len().min(delta.entries.len());
+            let prefix = log_head(
+                F::ZERO,
+                delta.entries[..held]
+                    .iter()
+                    .map(|(c, n, l, _)| (c, n, &l[..])),
+            );
+            if held < self.db.len() || prefix != self.log_head() {
+                return Err(DeltaError::NotAppendOnly);
+            }
+            log_head(
+                prefix,
+                delta.entries[held..]
+                    .iter()
+                    .map(|(c, n, l, _)| (c, n, &l[..])),
+            )
+        } else {
+            log_head(
+                self.log_head(),
+                delta.entries.iter().map(|(c, n, l, _)| (c, n, &l[..])),
+            )
+        };
+        if head != checkpoint.head {
+            return Err(DeltaError::NotAppendOnly);
+        }
+
+        if delta.from_version == 0 {
+            // The delta repeats the entries already held, so apply it to an empty mirror.
+            let mut fresh = Self::new();
+            fresh.pubkey = self.pubkey.clone();
+            fresh.apply(delta)?;
+            *self = fresh;
+            return Ok(());
+        }
+        self.apply(delta)
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> PublicUserBul<F, U>
+    for SigObjMirror<F, S>
+{
+    type MembershipWitness = S::Sig;
+
+    type MembershipWitnessVar = S::SigVar;
+
+    type MembershipPub = S::Pubkey;
+
+    type MembershipPubVar = S::PubkeyVar;
+
+    fn verify_in<PubArgs, Snark: ark_snark::SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        _args: PubArgs,
+        _proof: Snark::Proof,
+        _memb_data: Self::MembershipPub,
+        _verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        self.db
+            .iter()
+            .any(|(c, n, l, _)| *c == object && *n == old_nul && *l == cb_com_list.to_vec())
+    }
+
+    fn get_membership_data(&self, object: Com<F>) -> Option<(S::Pubkey, S::Sig)> {
+        self.db
+            .iter()
+            .find(|(c, _, _, _)| *c == object)
+            .map(|(_, _, _, s)| (self.pubkey.clone(), s.clone()))
+    }
+
+    fn enforce_membership_of(
+        data_var: crate::generic::object::ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        S::verify_zk(extra_pub, extra_witness, data_var)
+    }
 }
 
 impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> UserBul<F, U> for SigObjStore<F, S> {
@@ -230,6 +639,62 @@ impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> UserBul<F, U> for
             None => Err(()),
         }
     }
+
+    fn verify_interact_and_append_batch<
+        PubArgs: ToConstraintField<F> + Clone,
+        Snark: ark_snark::SNARK<F>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        batch: Vec<BatchedInteraction<F, PubArgs, Snark, S::Pubkey, NUMCBS>>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> Vec<Result<(), BulError<()>>> {
+        let mut out = Vec::with_capacity(batch.len());
+        let mut accepted = vec![];
+        let mut seen_nuls = vec![];
+
+        for (object, old_nul, args, cb_com_list, proof, memb_data) in batch {
+            if seen_nuls.contains(&old_nul)
+                || !<Self as UserBul<F, U>>::verify_interaction::<PubArgs, Snark, NUMCBS>(
+                    self,
+                    object,
+                    old_nul,
+                    args,
+                    cb_com_list,
+                    proof,
+                    memb_data,
+                    verif_key,
+                )
+            {
+                out.push(Err(BulError::VerifyError));
+                continue;
+            }
+
+            seen_nuls.push(old_nul);
+            accepted.push((out.len(), object, old_nul, cb_com_list));
+            out.push(Ok(()));
+        }
+
+        let msgs: Vec<Com<F>> = accepted.iter().map(|(_, c, _, _)| *c).collect();
+
+        match S::sign_batch(&self.privkey, &mut thread_rng(), &msgs) {
+            Some(sigs) => {
+                for ((_, object, old_nul, cb_com_list), sig) in accepted.into_iter().zip(sigs) {
+                    self.coms.push(object);
+                    self.old_nuls.push(old_nul);
+                    self.cb_com_lists.push(cb_com_list.into());
+                    self.sigs.push(sig);
+                }
+            }
+            None => {
+                for (i, _, _, _) in accepted {
+                    out[i] = Err(BulError::AppendError(()));
+                }
+            }
+        }
+
+        out
+    }
 }
 
 impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> JoinableBulletin<F, U>
@@ -252,6 +717,7 @@ where
                 self.old_nuls.push(rng.gen());
                 self.cb_com_lists.push(vec![]);
                 self.sigs.push(x);
+                self.num_joined = self.num_joined.map(|n| n + 1);
                 Ok(())
             }
             None => Err(()),
@@ -317,6 +783,22 @@ where
     /// Get the nonmembership public data.
     fn get_nmemb_pub(&self) -> Self::NonMembershipPub;
 
+    /// Get nonmembership data for a specific ticket at some epoch. If the ticket was a member at
+    /// the epoch, or the epoch is not kept, this should return None.
+    ///
+    /// By default, only the current epoch is kept.
+    fn get_nmemb_at(
+        &self,
+        tik: &FakeSigPubkey<F>,
+        epoch: F,
+    ) -> Option<(Self::NonMembershipPub, Self::NonMembershipWitness)> {
+        if epoch == self.get_epoch() {
+            self.get_nmemb(tik)
+        } else {
+            None
+        }
+    }
+
     /// Return true if the ticket is a non-member, and false if the ticket is a member.
     fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool;
 
@@ -354,6 +836,11 @@ where
     pub memb_cbs_sigs: Vec<S::Sig>,
     /// A nonmembership bulletin for proofs of nonmembership on called tickets.
     pub nmemb_bul: B,
+    /// The epochs of the bulletin, as (epoch, number of called tickets, root).
+    pub epochs: Vec<(F, usize, F)>,
+    /// Tickets pruned from the bulletin, in the order they were called. See
+    /// [`CallbackStore::prune_before`].
+    pub tombstones: Vec<FakeSigPubkey<F>>,
 }
 
 impl<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>, Args> CallbackStore<F, S, B, Args>
@@ -366,12 +853,16 @@ where
     /// Generates a random public key / private key pair.
     pub fn new(rng: &mut (impl CryptoRng + RngCore)) -> Self {
         let sk = S::gen_key(rng);
+        let nmemb_bul = B::new(rng);
+        let epoch = nmemb_bul.get_epoch();
         Self {
             privkey: sk.clone(),
             pubkey: S::get_pubkey(&sk),
             memb_called_cbs: vec![],
             memb_cbs_sigs: vec![],
-            nmemb_bul: B::new(rng),
+            nmemb_bul,
+            epochs: vec![(epoch, 0, Self::epoch_root(F::ZERO, epoch, &[]))],
+            tombstones: vec![],
         }
     }
 
@@ -384,13 +875,21 @@ where
     ) -> Self {
         let pubkey = S::get_pubkey(&privkey);
         let memb_cbs_sigs = db.iter().map(|(_, _, _, s)| s.clone()).collect();
-        let memb_called_cbs = db.into_iter().map(|(t, a, e, _)| (t, a, e)).collect();
+        let memb_called_cbs: Vec<_> = db.into_iter().map(|(t, a, e, _)| (t, a, e)).collect();
+        let epoch = nmemb_bul.get_epoch();
+        let epochs = vec![(
+            epoch,
+            memb_called_cbs.len(),
+            Self::epoch_root(F::ZERO, epoch, &memb_called_cbs),
+        )];
         Self {
             privkey,
             pubkey,
             memb_called_cbs,
             memb_cbs_sigs,
             nmemb_bul,
+            epochs,
+            tombstones: vec![],
         }
     }
 
@@ -491,8 +990,206 @@ where
     pub fn update_epoch(&mut self, rng: &mut (impl CryptoRng + RngCore)) {
         self.nmemb_bul.update_epoch(
             rng,
-            (self.memb_called_cbs).iter().map(|x| x.0.clone()).collect(),
+            self.tombstones
+                .iter()
+                .cloned()
+                .chain(self.memb_called_cbs.iter().map(|x| x.0.clone()))
+                .collect(),
         );
+
+        let (prev_root, prev_len) = self
+            .epochs
+            .last()
+            .map(|(_, n, r)| (*r, *n))
+            .unwrap_or((F::ZERO, 0));
+        let epoch = self.nmemb_bul.get_epoch();
+        let pruned = self.tombstones.len();
+        let root = Self::epoch_root(prev_root, epoch, &self.memb_called_cbs[prev_len - pruned..]);
+        self.epochs
+            .push((epoch, pruned + self.memb_called_cbs.len(), root));
+    }
+
+    /// Prune the tickets called before the start of an epoch.
+    ///
+    /// The arguments, times, and signatures of the pruned tickets are removed from the bulletin
+    /// and returned, so they may be archived elsewhere. The tickets themselves are kept as
+    /// tombstones: a pruned ticket can never be called again, and stays within the
+    /// nonmembership bulletin, so it can not be scanned as uncalled. The epoch roots (which hash
+    /// the tickets called within each epoch) are kept as well.
+    ///
+    /// A user may still scan a pruned ticket with its archived entry, as the signature verifies
+    /// under the same public key. See [`CallbackStore::verify_archived`]. Note that archived
+    /// entries are not resigned by [`CallbackStore::rotate_key`].
+    ///
+    /// Returns `None` if the bulletin does not keep the epoch.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use zk_callbacks::generic::bulletin::{CallbackBul, PublicCallbackBul};
+    /// # use zk_callbacks::generic::object::Time;
+    /// # use zk_callbacks::impls::centralized::crypto::{FakeSigPubkey, NoSigOTP};
+    /// # use zk_callbacks::impls::centralized::ds::sigstore::GRSchnorrCallbackStore;
+    /// # use ark_grumpkin::Fq as Fr;
+    /// # use rand::thread_rng;
+    /// # type CBul = GRSchnorrCallbackStore<Fr>;
+    /// let mut rng = thread_rng();
+    /// let mut store = CBul::new(&mut rng);
+    /// let tiks: Vec<_> = (1..4).map(|i| FakeSigPubkey::new(Fr::from(i))).collect();
+    ///
+    /// for tik in &tiks[..2] {
+    ///     <CBul as CallbackBul<Fr, Fr, NoSigOTP<Fr>>>::append_value(&mut store, tik.clone(), Fr::from(5), (), Time::from(0)).unwrap();
+    /// }
+    /// store.update_epoch(&mut rng);
+    /// <CBul as CallbackBul<Fr, Fr, NoSigOTP<Fr>>>::append_value(&mut store, tiks[2].clone(), Fr::from(5), (), Time::from(1)).unwrap();
+    /// store.update_epoch(&mut rng);
+    ///
+    /// let archive = store.prune_before(store.get_epoch()).unwrap();
+    /// assert_eq!(archive.len(), 3);
+    /// assert!(store.memb_called_cbs.is_empty());
+    ///
+    /// // Pruned tickets may not be called again, or shown to be uncalled (even in later epochs).
+    /// store.update_epoch(&mut rng);
+    /// assert!(!<CBul as CallbackBul<Fr, Fr, NoSigOTP<Fr>>>::has_never_received_tik(&store, &tiks[0]));
+    /// assert!(!<CBul as PublicCallbackBul<Fr, Fr, NoSigOTP<Fr>>>::verify_not_in(&store, tiks[0].clone()));
+    ///
+    /// // The archive still verifies.
+    /// assert!(archive.iter().all(|entry| store.verify_archived(entry)));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn prune_before(
+        &mut self,
+        epoch: F,
+    ) -> Option<Vec<(FakeSigPubkey<F>, Args, Time<F>, S::Sig)>> {
+        let n = self.get_epoch_len(epoch)? - self.tombstones.len();
+        let sigs = self.memb_cbs_sigs.drain(..n);
+        let entries: Vec<_> = self
+            .memb_called_cbs
+            .drain(..n)
+            .zip(sigs)
+            .map(|((t, a, time), s)| (t, a, time, s))
+            .collect();
+        self.tombstones
+            .extend(entries.iter().map(|(t, _, _, _)| t.clone()));
+        Some(entries)
+    }
+
+    /// Check an archived entry returned by [`CallbackStore::prune_before`].
+    ///
+    /// Returns `true` if the ticket was pruned from this bulletin and the signature on the entry
+    /// verifies under the current public key.
+    pub fn verify_archived(&self, entry: &(FakeSigPubkey<F>, Args, Time<F>, S::Sig)) -> bool {
+        let (tik, args, time, sig) = entry;
+        if !self.tombstones.contains(tik) {
+            return false;
+        }
+        let Some(args) = args.to_field_elements() else {
+            return false;
+        };
+        let mut v = vec![tik.to()];
+        v.extend_from_slice(&args);
+        v.push(*time);
+        S::verify(self.get_pubkey(), sig.clone(), <Poseidon<2>>::hash(&v))
+    }
+
+    /// Get the number of tickets ever called, including pruned tickets.
+    pub fn get_version(&self) -> u64 {
+        (self.tombstones.len() + self.memb_called_cbs.len()) as u64
+    }
+
+    /// Get all calls since some version, so mirrors can follow the bulletin. See
+    /// [`BulletinMirror`](`crate::impls::centralized::mirror::BulletinMirror`).
+    ///
+    /// The version is the number of calls the mirror has already seen. This returns `None` if the
+    /// version is ahead of the bulletin, or if the calls after the version were pruned.
+    pub fn get_calls_since(&self, version: u64) -> Option<CallbackDelta<F, Args, S::Pubkey, S::Sig>>
+    where
+        Args: CanonicalSerialize + CanonicalDeserialize,
+        S::Pubkey: CanonicalSerialize + CanonicalDeserialize,
+    {
+        let from = usize::try_from(version)
+            .ok()?
+            .checked_sub(self.tombstones.len())?;
+        if from > self.memb_called_cbs.len() {
+            return None;
+        }
+        let entries = (from..self.memb_called_cbs.len())
+            .map(|i| {
+                let (t, a, time) = self.memb_called_cbs[i].clone();
+                (t, a, time, self.memb_cbs_sigs[i].clone())
+            })
+            .collect();
+        Some(CallbackDelta {
+            from_version: version,
+            to_version: self.get_version(),
+            pubkey: self.get_pubkey(),
+            entries,
+        })
+    }
+
+    /// Hash the tickets called since the previous epoch into the previous root.
+    fn epoch_root(prev_root: F, epoch: F, new_cbs: &[(FakeSigPubkey<F>, Args, Time<F>)]) -> F {
+        let mut v = vec![prev_root, epoch];
+        v.extend(new_cbs.iter().map(|(t, _, _)| t.to()));
+        <Poseidon<2>>::hash(&v)
+    }
+
+    /// Get the root of the bulletin at an epoch.
+    ///
+    /// The root of an epoch is a hash chain over all tickets called before the start of the epoch.
+    pub fn get_epoch_root(&self, epoch: F) -> Option<F> {
+        self.epochs
+            .iter()
+            .find(|(e, _, _)| *e == epoch)
+            .map(|(_, _, r)| *r)
+    }
+
+    /// Get the number of tickets called before the start of an epoch.
+    fn get_epoch_len(&self, epoch: F) -> Option<usize> {
+        self.epochs
+            .iter()
+            .find(|(e, _, _)| *e == epoch)
+            .map(|(_, n, _)| *n)
+    }
+
+    /// Check if a ticket was called before the start of an epoch.
+    pub fn verify_in_at(&self, tik: &FakeSigPubkey<F>, epoch: F) -> Option<(Args, Time<F>)> {
+        let n = self
+            .get_epoch_len(epoch)?
+            .saturating_sub(self.tombstones.len());
+        self.memb_called_cbs[..n]
+            .iter()
+            .find(|(t, _, _)| t == tik)
+            .map(|(_, a, time)| (a.clone(), *time))
+    }
+
+    /// Get the membership and nonmembership data for a ticket at the start of an epoch.
+    #[allow(clippy::type_complexity)]
+    pub fn get_membership_data_at(
+        &self,
+        tik: &FakeSigPubkey<F>,
+        epoch: F,
+    ) -> Option<(
+        S::Pubkey,
+        S::Sig,
+        B::NonMembershipPub,
+        B::NonMembershipWitness,
+    )> {
+        let n = self
+            .get_epoch_len(epoch)?
+            .saturating_sub(self.tombstones.len());
+        for (i, (t, _, _)) in self.memb_called_cbs[..n].iter().enumerate() {
+            if t == tik {
+                return Some((
+                    self.get_pubkey(),
+                    self.memb_cbs_sigs[i].clone(),
+                    self.nmemb_bul.get_nmemb_pub(),
+                    B::NonMembershipWitness::default(),
+                ));
+            }
+        }
+        self.nmemb_bul
+            .get_nmemb_at(tik, epoch)
+            .map(|(p, w)| (self.get_pubkey(), S::Sig::default(), p, w))
     }
 }
 
@@ -655,6 +1352,112 @@ where
     }
 }
 
+impl<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>>
+    PublicEpochCallbackBul<F, F, NoSigOTP<F>> for CallbackStore<F, S, B, F>
+where
+    Standard: Distribution<F>,
+{
+    type EpochRoot = F;
+
+    fn get_epoch(&self) -> F {
+        self.get_epoch()
+    }
+
+    fn get_epoch_root(&self, epoch: F) -> Option<F> {
+        self.get_epoch_root(epoch)
+    }
+
+    fn verify_in_at(&self, tik: FakeSigPubkey<F>, epoch: F) -> Option<(F, Time<F>)> {
+        self.verify_in_at(&tik, epoch)
+    }
+
+    fn verify_not_in_at(&self, tik: FakeSigPubkey<F>, epoch: F) -> bool {
+        self.nmemb_bul.get_nmemb_at(&tik, epoch).is_some()
+    }
+
+    fn get_membership_data_at(
+        &self,
+        tik: FakeSigPubkey<F>,
+        epoch: F,
+    ) -> Option<(
+        S::Pubkey,
+        S::Sig,
+        B::NonMembershipPub,
+        B::NonMembershipWitness,
+    )> {
+        self.get_membership_data_at(&tik, epoch)
+    }
+}
+
+impl<
+        F: PrimeField + Absorb,
+        S: Signature<F>,
+        B: NonmembStore<F>,
+        A: Clone + Default + ToConstraintField<F>,
+        AVar: Clone + AllocVar<A, F> + ToConstraintFieldGadget<F>,
+    > PublicEpochCallbackBul<F, A, NoEnc<F, A, AVar>> for CallbackStore<F, S, B, A>
+where
+    Standard: Distribution<F>,
+{
+    type EpochRoot = F;
+
+    fn get_epoch(&self) -> F {
+        self.get_epoch()
+    }
+
+    fn get_epoch_root(&self, epoch: F) -> Option<F> {
+        self.get_epoch_root(epoch)
+    }
+
+    fn verify_in_at(&self, tik: FakeSigPubkey<F>, epoch: F) -> Option<(A, Time<F>)> {
+        self.verify_in_at(&tik, epoch)
+    }
+
+    fn verify_not_in_at(&self, tik: FakeSigPubkey<F>, epoch: F) -> bool {
+        self.nmemb_bul.get_nmemb_at(&tik, epoch).is_some()
+    }
+
+    fn get_membership_data_at(
+        &self,
+        tik: FakeSigPubkey<F>,
+        epoch: F,
+    ) -> Option<(
+        S::Pubkey,
+        S::Sig,
+        B::NonMembershipPub,
+        B::NonMembershipWitness,
+    )> {
+        self.get_membership_data_at(&tik, epoch)
+    }
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>>
+    EpochCallbackBul<F, F, NoSigOTP<F>> for CallbackStore<F, S, B, F>
+where
+    Standard: Distribution<F>,
+{
+    fn advance_epoch(&mut self, rng: &mut (impl CryptoRng + RngCore)) -> Result<(), ()> {
+        self.update_epoch(rng);
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField + Absorb,
+        S: Signature<F>,
+        B: NonmembStore<F>,
+        A: Clone + Default + ToConstraintField<F>,
+        AVar: Clone + AllocVar<A, F> + ToConstraintFieldGadget<F>,
+    > EpochCallbackBul<F, A, NoEnc<F, A, AVar>> for CallbackStore<F, S, B, A>
+where
+    Standard: Distribution<F>,
+{
+    fn advance_epoch(&mut self, rng: &mut (impl CryptoRng + RngCore)) -> Result<(), ()> {
+        self.update_epoch(rng);
+        Ok(())
+    }
+}
+
 impl<F: PrimeField + Absorb, S: Signature<F>, B: NonmembStore<F>> CallbackBul<F, F, NoSigOTP<F>>
     for CallbackStore<F, S, B, F>
 where
@@ -668,7 +1471,11 @@ where
                 return false;
             }
         }
-        true
+        !self.tombstones.contains(tik)
+    }
+
+    fn prune_before(&mut self, epoch: F) -> Result<usize, Self::Error> {
+        self.prune_before(epoch).map(|v| v.len()).ok_or(())
     }
 
     fn append_value(
@@ -711,7 +1518,11 @@ where
                 return false;
             }
         }
-        true
+        !self.tombstones.contains(tik)
+    }
+
+    fn prune_before(&mut self, epoch: F) -> Result<usize, Self::Error> {
+        self.prune_before(epoch).map(|v| v.len()).ok_or(())
     }
 
     fn append_value(
@@ -972,6 +1783,11 @@ pub type UOVStore<F, A> = CentralStore<F, BleedingUOV<F>, SigRangeStore<F, Bleed
 /// A user object store which uses Jubjub BLS Schnorr signatures.
 pub type JJSchnorrObjStore = SigObjStore<BlsFr, JubjubSchnorr>;
 
+/// A user object store which uses Jubjub BLS Schnorr signatures, with the private key shared
+/// `T`-of-`N` among parties which sign with FROST.
+pub type FrostObjStore<const T: usize, const N: usize> =
+    SigObjStore<BlsFr, FrostJubjubSchnorr<T, N>>;
+
 /// A user object store which uses  BLS377 Schnorr signatures.
 pub type BLS377SchnorrObjStore = SigObjStore<Bls377Fr, Bls377Schnorr>;
 
@@ -997,3 +1813,260 @@ pub type GRSchnorrCallbackStore<A> =
 /// A central storage system which uses Grumpkin BN254 Schnorr signatures.
 pub type GRSchnorrStore<A> =
     CentralStore<BnFr, GrumpkinSchnorr, SigRangeStore<BnFr, GrumpkinSchnorr>, A>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generic::{
+        bulletin::PinnedCallbackBul,
+        interaction::Interaction,
+        user::{User, UserVar},
+    };
+    use ark_bn254::{Bn254, Fr};
+    use ark_groth16::Groth16;
+    use ark_r1cs_std::eq::EqGadget;
+    use ark_snark::SNARK;
+
+    type H = Poseidon<2>;
+    type Groth = Groth16<Bn254>;
+    type Int = Interaction<Fr, Fr, (), (), (), (), Fr, FpVar<Fr>, 0>;
+
+    fn method(old_user: &User<Fr, Fr>, _pub: (), _priv: ()) -> User<Fr, Fr> {
+        let mut u = old_user.clone();
+        u.data += Fr::from(1);
+        u
+    }
+
+    fn predicate(
+        old_user: &UserVar<Fr, Fr>,
+        new_user: &UserVar<Fr, Fr>,
+        _pub: (),
+        _priv: (),
+    ) -> Result<Boolean<Fr>, SynthesisError> {
+        new_user.data.is_eq(&(old_user.data.clone() + Fr::from(1)))
+    }
+
+    fn interaction() -> Int {
+        Interaction {
+            meth: (method, predicate),
+            callbacks: [],
+        }
+    }
+
+    // Joins a new user to the store and executes the interaction against the store
+    fn interact(
+        store: &mut GRSchnorrObjStore,
+        pk: &<Groth as SNARK<Fr>>::ProvingKey,
+    ) -> BatchedInteraction<Fr, (), Groth, <GrumpkinSchnorr as Signature<Fr>>::Pubkey, 0> {
+        let mut rng = thread_rng();
+        let mut u = User::create(Fr::from(0), &mut rng);
+        <GRSchnorrObjStore as JoinableBulletin<Fr, Fr>>::join_bul(store, u.commit::<H>(), ())
+            .unwrap();
+        let exec = u
+            .exec_method_create_cb::<H, (), (), (), (), Fr, FpVar<Fr>, NoSigOTP<Fr>, Groth, GRSchnorrObjStore, 0>(
+                &mut rng,
+                interaction(),
+                [],
+                Time::from(0),
+                store,
+                true,
+                pk,
+                (),
+                (),
+            )
+            .unwrap();
+        (
+            exec.new_object,
+            exec.old_nullifier,
+            (),
+            exec.cb_com_list,
+            exec.proof,
+            None,
+        )
+    }
+
+    // Tests that a batch appends valid interactions and rejects replayed nullifiers and bad proofs
+    #[test]
+    fn verify_interact_and_append_batch() {
+        let mut rng = thread_rng();
+        let mut store = GRSchnorrObjStore::new(&mut rng);
+        let (pk, vk) = interaction().generate_keys::<H, Groth, NoSigOTP<Fr>, GRSchnorrObjStore>(
+            &mut rng,
+            Some(store.get_pubkey()),
+            None,
+            false,
+        );
+
+        let first = interact(&mut store, &pk);
+        let second = interact(&mut store, &pk);
+        let replayed = first.clone();
+        let mut forged = interact(&mut store, &pk);
+        forged.0 = Fr::from(2);
+
+        let out = <GRSchnorrObjStore as UserBul<Fr, Fr>>::verify_interact_and_append_batch::<
+            (),
+            Groth,
+            0,
+        >(
+            &mut store,
+            vec![first.clone(), second.clone(), replayed, forged],
+            &vk,
+        );
+
+        assert!(out[0].is_ok());
+        assert!(out[1].is_ok());
+        assert!(matches!(out[2], Err(BulError::VerifyError)));
+        assert!(matches!(out[3], Err(BulError::VerifyError)));
+
+        let pubkey = store.get_pubkey();
+        for obj in [first.0, second.0] {
+            let sig = store.get_signature_of(&obj).unwrap();
+            assert!(GrumpkinSchnorr::verify(pubkey, sig, obj));
+        }
+        assert!(store.get_signature_of(&Fr::from(2)).is_none());
+        assert_eq!(store.coms.iter().filter(|c| **c == first.0).count(), 1);
+
+        // A nullifier accepted in an earlier batch is rejected in a later one
+        let out = <GRSchnorrObjStore as UserBul<Fr, Fr>>::verify_interact_and_append_batch::<
+            (),
+            Groth,
+            0,
+        >(&mut store, vec![second], &vk);
+        assert!(matches!(out[0], Err(BulError::VerifyError)));
+    }
+
+    // Tests that a bulletin pinned to an epoch ignores tickets called after the epoch started
+    #[test]
+    fn pinned_epoch_view() {
+        type CBul = GRSchnorrCallbackStore<Fr>;
+        type Cr = NoSigOTP<Fr>;
+
+        let mut rng = thread_rng();
+        let mut store = CBul::new(&mut rng);
+        let tik1 = FakeSigPubkey::new(Fr::from(10));
+        let tik2 = FakeSigPubkey::new(Fr::from(20));
+
+        let e0 = store.get_epoch();
+        <CBul as CallbackBul<Fr, Fr, Cr>>::append_value(
+            &mut store,
+            tik1.clone(),
+            Fr::from(5),
+            (),
+            Time::from(0),
+        )
+        .unwrap();
+        <CBul as EpochCallbackBul<Fr, Fr, Cr>>::advance_epoch(&mut store, &mut rng).unwrap();
+        let e1 = store.get_epoch();
+        <CBul as CallbackBul<Fr, Fr, Cr>>::append_value(
+            &mut store,
+            tik2.clone(),
+            Fr::from(6),
+            (),
+            Time::from(1),
+        )
+        .unwrap();
+        <CBul as EpochCallbackBul<Fr, Fr, Cr>>::advance_epoch(&mut store, &mut rng).unwrap();
+        let e2 = store.get_epoch();
+
+        let roots = [e0, e1, e2].map(|e| store.get_epoch_root(e).unwrap());
+        assert_ne!(roots[0], roots[1]);
+        assert_ne!(roots[1], roots[2]);
+        assert!(store.get_epoch_root(e2 + Fr::from(1)).is_none());
+
+        let pinned = PinnedCallbackBul::<_, Fr, Cr, _>::new(&store, e1);
+        assert_eq!(
+            pinned.verify_in(tik1.clone()),
+            Some((Fr::from(5), Time::from(0)))
+        );
+        assert!(pinned.verify_in(tik2.clone()).is_none());
+        assert!(!pinned.verify_not_in(tik1.clone()));
+        assert!(pinned.verify_not_in(tik2.clone()));
+
+        // The membership data at the pinned epoch verifies for the ticket called before it
+        let (pubkey, sig, _, _) = pinned.get_membership_data(tik1.clone());
+        let msg = <Poseidon<2>>::hash(&[tik1.to(), Fr::from(5), Time::from(0)]);
+        assert!(GrumpkinSchnorr::verify(pubkey, sig, msg));
+
+        // At the latest epoch, both tickets are members
+        let live = PinnedCallbackBul::<_, Fr, Cr, _>::new(&store, e2);
+        assert!(live.verify_in(tik2.clone()).is_some());
+        assert!(!live.verify_not_in(tik2));
+    }
+
+    // Tests that a mirror follows the store through incremental deltas and key rotations
+    #[test]
+    fn mirror_follows_deltas() {
+        type Mirror = SigObjMirror<Fr, GrumpkinSchnorr>;
+        let mut rng = thread_rng();
+        let mut store = GRSchnorrObjStore::new(&mut rng);
+        let mut mirror = Mirror::new();
+        let updates = |store: &GRSchnorrObjStore, v| {
+            <GRSchnorrObjStore as PublicUserBul<Fr, Fr>>::get_updates_since(store, v).unwrap()
+        };
+        let join = |store: &mut GRSchnorrObjStore, c: u64| {
+            <GRSchnorrObjStore as JoinableBulletin<Fr, Fr>>::join_bul(store, Fr::from(c), ())
+                .unwrap()
+        };
+
+        join(&mut store, 1);
+        join(&mut store, 2);
+        mirror.apply(updates(&store, 0)).unwrap();
+        assert_eq!(mirror.get_version(), 2);
+
+        join(&mut store, 3);
+        let delta = updates(&store, mirror.get_version());
+        assert_eq!(delta.entries.len(), 1);
+
+        // The delta survives serialization
+        let mut bytes = vec![];
+        delta.serialize_compressed(&mut bytes).unwrap();
+        let delta = BulletinDelta::deserialize_compressed(&*bytes).unwrap();
+        mirror.apply(delta).unwrap();
+        assert_eq!(mirror.db, store.get_db());
+
+        // A delta which skips or repeats entries does not apply
+        assert_eq!(
+            mirror.apply(updates(&store, 1)),
+            Err(DeltaError::VersionMismatch {
+                expected: 3,
+                got: 1
+            })
+        );
+        assert!(
+            <GRSchnorrObjStore as PublicUserBul<Fr, Fr>>::get_updates_since(&store, 4).is_none()
+        );
+
+        // After a key rotation, the mirror must resync from the start
+        store
+            .rotate_key(GrumpkinSchnorr::gen_key(&mut rng))
+            .unwrap();
+        join(&mut store, 4);
+        assert_eq!(
+            mirror.apply(updates(&store, 3)),
+            Err(DeltaError::KeyChanged)
+        );
+        mirror.apply(updates(&store, 0)).unwrap();
+        assert_eq!(mirror.db, store.get_db());
+        assert_eq!(mirror.pubkey, store.get_pubkey());
+    }
+
+    // Tests that a delta with a forged signature leaves the mirror unchanged
+    #[test]
+    fn mirror_rejects_bad_signature() {
+        type Mirror = SigObjMirror<Fr, GrumpkinSchnorr>;
+        let mut rng = thread_rng();
+        let mut store = GRSchnorrObjStore::new(&mut rng);
+        for c in 1..4 {
+            <GRSchnorrObjStore as JoinableBulletin<Fr, Fr>>::join_bul(&mut store, Fr::from(c), ())
+                .unwrap();
+        }
+
+        let mut delta =
+            <GRSchnorrObjStore as PublicUserBul<Fr, Fr>>::get_updates_since(&store, 0).unwrap();
+        delta.entries[1].0 = Fr::from(5);
+
+        let mut mirror = Mirror::new();
+        assert_eq!(mirror.apply(delta), Err(DeltaError::InvalidSignature(1)));
+        assert_eq!(mirror.get_version(), 0);
+    }
+}
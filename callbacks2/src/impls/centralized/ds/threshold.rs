@@ -0,0 +1,450 @@
+use std::cmp::Ordering;
+
+use crate::{
+    generic::{
+        bulletin::{JoinableBulletin, PublicUserBul, UserBul},
+        object::{Com, ComVar, Nul},
+        user::UserData,
+    },
+    impls::centralized::ds::sig::{
+        bls::{
+            Bls377Aggregate, BlsPrivkey, BlsPubkey, BlsPubkeyVar, BlsSignature, BlsSignatureVar,
+        },
+        Privkey, Pubkey, Signature,
+    },
+};
+use ark_bls12_377::Fq as F;
+use ark_ff::ToConstraintField;
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    fields::fp::FpVar,
+    prelude::Boolean,
+};
+use ark_relations::{
+    ns,
+    r1cs::{Namespace, SynthesisError},
+};
+use rand::{thread_rng, Rng};
+use std::borrow::Borrow;
+
+/// A committee of `N` bulletin maintainers, of which `threshold` must sign an object for it to be
+/// a member.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Committee<const N: usize> {
+    /// The public keys of the maintainers.
+    pub pubkeys: Vec<BlsPubkey>,
+    /// The number of signatures needed for membership.
+    pub threshold: u64,
+}
+
+impl<const N: usize> Default for Committee<N> {
+    fn default() -> Self {
+        Self {
+            pubkeys: vec![BlsPubkey::default(); N],
+            threshold: 0,
+        }
+    }
+}
+
+impl<const N: usize> Committee<N> {
+    /// Construct a committee from the public keys of the maintainers, along with their proofs of
+    /// possession.
+    ///
+    /// Returns `None` if there are not exactly `N` keys, if any proof of possession does not
+    /// verify, or if the threshold is not between `1` and `N`.
+    pub fn new(keys: Vec<(BlsPubkey, BlsSignature)>, threshold: u64) -> Option<Self> {
+        if keys.len() != N || threshold == 0 || threshold > N as u64 {
+            return None;
+        }
+        if !keys.iter().all(|(pk, pop)| pk.verify_possession(pop)) {
+            return None;
+        }
+        Some(Self {
+            pubkeys: keys.into_iter().map(|(pk, _)| pk).collect(),
+            threshold,
+        })
+    }
+}
+
+impl<const N: usize> ToConstraintField<F> for Committee<N> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        let mut v = vec![];
+        for pk in &self.pubkeys {
+            v.extend(pk.to_field_elements()?);
+        }
+        v.push(F::from(self.threshold));
+        Some(v)
+    }
+}
+
+/// A committee in-circuit.
+#[derive(Clone)]
+pub struct CommitteeVar<const N: usize> {
+    /// The public keys of the maintainers.
+    pub pubkeys: Vec<BlsPubkeyVar>,
+    /// The number of signatures needed for membership.
+    pub threshold: FpVar<F>,
+}
+
+impl<const N: usize> AllocVar<Committee<N>, F> for CommitteeVar<N> {
+    fn new_variable<T: Borrow<Committee<N>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let pubkeys = rec
+                .pubkeys
+                .iter()
+                .map(|pk| BlsPubkeyVar::new_variable(ns!(cs, "pubkey"), || Ok(pk), mode))
+                .collect::<Result<Vec<_>, _>>()?;
+            let threshold =
+                FpVar::new_variable(ns!(cs, "threshold"), || Ok(F::from(rec.threshold)), mode)?;
+            Ok(Self { pubkeys, threshold })
+        })
+    }
+}
+
+/// A proof that at least a threshold of the committee signed an object commitment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThresholdWitness<const N: usize> {
+    /// Which maintainers signed.
+    pub signers: Vec<bool>,
+    /// The aggregate signature of the signers.
+    pub sig: BlsSignature,
+}
+
+impl<const N: usize> Default for ThresholdWitness<N> {
+    fn default() -> Self {
+        Self {
+            signers: vec![false; N],
+            sig: BlsSignature::default(),
+        }
+    }
+}
+
+/// A threshold witness in-circuit.
+#[derive(Clone)]
+pub struct ThresholdWitnessVar<const N: usize> {
+    /// Which maintainers signed.
+    pub signers: Vec<Boolean<F>>,
+    /// The aggregate signature of the signers.
+    pub sig: BlsSignatureVar,
+}
+
+impl<const N: usize> AllocVar<ThresholdWitness<N>, F> for ThresholdWitnessVar<N> {
+    fn new_variable<T: Borrow<ThresholdWitness<N>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let signers = rec
+                .signers
+                .iter()
+                .map(|b| Boolean::new_variable(ns!(cs, "signer"), || Ok(*b), mode))
+                .collect::<Result<Vec<_>, _>>()?;
+            let sig = BlsSignatureVar::new_variable(ns!(cs, "sig"), || Ok(&rec.sig), mode)?;
+            Ok(Self { signers, sig })
+        })
+    }
+}
+
+/// An error when adding a signature share to a [`ThresholdSigObjStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShareError {
+    /// There is no maintainer with this index in the committee.
+    UnknownMaintainer,
+    /// The share does not verify under the key of the maintainer.
+    InvalidShare,
+    /// The object is not in the bulletin.
+    UnknownObject,
+}
+
+impl std::fmt::Display for ShareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShareError::UnknownMaintainer => write!(f, "unknown maintainer"),
+            ShareError::InvalidShare => write!(f, "share does not verify"),
+            ShareError::UnknownObject => write!(f, "object not in the bulletin"),
+        }
+    }
+}
+
+impl std::error::Error for ShareError {}
+
+/// A centralized object storage system, co-signed by a committee of maintainers.
+///
+/// Each object commitment is signed separately by the maintainers, with [`Bls377Aggregate`]
+/// signatures. An object is a member once at least `threshold` of the `N` maintainers have signed
+/// it. The store signs with the keys of the maintainers it operates on append, while signature
+/// shares from other maintainers are added with [`ThresholdSigObjStore::add_share`].
+///
+/// To prove membership, users prove knowledge of an aggregate signature on their object
+/// commitment, along with which maintainers signed. The public keys of the signers are aggregated
+/// in-circuit, and the number of signers is checked against the threshold.
+///
+/// Note that this implements [`PublicUserBul`] and [`UserBul`].
+#[derive(Clone, Default)]
+pub struct ThresholdSigObjStore<const N: usize> {
+    privkeys: Vec<(usize, BlsPrivkey)>,
+
+    /// The committee of maintainers.
+    pub committee: Committee<N>,
+
+    /// The object commitments.
+    pub coms: Vec<Com<F>>,
+
+    /// The old nullifiers for each object.
+    pub old_nuls: Vec<Nul<F>>,
+
+    /// The callback commitments given by the users.
+    pub cb_com_lists: Vec<Vec<Com<F>>>,
+
+    /// The signature shares on each object, indexed by maintainer.
+    pub shares: Vec<Vec<Option<BlsSignature>>>,
+}
+
+impl<const N: usize> ThresholdSigObjStore<N> {
+    /// Construct a new store for a committee, operating the given maintainers.
+    ///
+    /// Each private key is given along with the index of its maintainer in the committee. Returns
+    /// `None` if a key does not match the committee.
+    pub fn new(committee: Committee<N>, privkeys: Vec<(usize, BlsPrivkey)>) -> Option<Self> {
+        for (i, sk) in &privkeys {
+            if committee.pubkeys.get(*i) != Some(&sk.get_pubkey()) {
+                return None;
+            }
+        }
+        Some(Self {
+            privkeys,
+            committee,
+            coms: vec![],
+            old_nuls: vec![],
+            cb_com_lists: vec![],
+            shares: vec![],
+        })
+    }
+
+    /// Get the committee.
+    pub fn get_committee(&self) -> Committee<N> {
+        self.committee.clone()
+    }
+
+    fn push(&mut self, object: Com<F>, old_nul: Nul<F>, cb_com_list: Vec<Com<F>>) {
+        let mut rng = thread_rng();
+        let mut shares = vec![None; N];
+        for (i, sk) in &self.privkeys {
+            shares[*i] = Bls377Aggregate::sign(sk, &mut rng, object);
+        }
+        self.coms.push(object);
+        self.old_nuls.push(old_nul);
+        self.cb_com_lists.push(cb_com_list);
+        self.shares.push(shares);
+    }
+
+    /// Add a signature share from a maintainer on an object.
+    ///
+    /// Returns an error if the object is not in the bulletin, or if the share does not verify
+    /// under the key of the maintainer.
+    pub fn add_share(
+        &mut self,
+        obj: &Com<F>,
+        index: usize,
+        sig: BlsSignature,
+    ) -> Result<(), ShareError> {
+        let pk = self
+            .committee
+            .pubkeys
+            .get(index)
+            .ok_or(ShareError::UnknownMaintainer)?;
+        if !pk.verify(sig.clone(), *obj) {
+            return Err(ShareError::InvalidShare);
+        }
+        let pos = self
+            .coms
+            .iter()
+            .position(|c| c == obj)
+            .ok_or(ShareError::UnknownObject)?;
+        self.shares[pos][index] = Some(sig);
+        Ok(())
+    }
+
+    /// Returns if an object has been signed by at least the threshold of the committee.
+    pub fn is_certified(&self, obj: &Com<F>) -> bool {
+        self.get_certificate(obj).is_some()
+    }
+
+    /// Get the aggregate signature on an object. Returns None if the object is not contained in
+    /// the bulletin, or if too few maintainers have signed it.
+    pub fn get_certificate(&self, obj: &Com<F>) -> Option<ThresholdWitness<N>> {
+        let pos = self.coms.iter().position(|c| c == obj)?;
+        let shares = &self.shares[pos];
+        let sigs: Vec<BlsSignature> = shares.iter().flatten().cloned().collect();
+        if (sigs.len() as u64) < self.committee.threshold {
+            return None;
+        }
+        Some(ThresholdWitness {
+            signers: shares.iter().map(Option::is_some).collect(),
+            sig: BlsSignature::aggregate(&sigs)?,
+        })
+    }
+
+    /// Enforce in-circuit that a threshold witness certifies an object commitment under a
+    /// committee.
+    pub fn enforce_certificate(
+        data_var: ComVar<F>,
+        witness: ThresholdWitnessVar<N>,
+        committee: CommitteeVar<N>,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        let (agg_pk, count) = BlsPubkeyVar::aggregate(&committee.pubkeys, &witness.signers)?;
+        let enough = count.is_cmp_unchecked(&committee.threshold, Ordering::Greater, true)?;
+        let valid = BlsPubkey::verify_zk(agg_pk, witness.sig, data_var)?;
+        Ok(enough & valid)
+    }
+}
+
+impl<U: UserData<F>, const N: usize> PublicUserBul<F, U> for ThresholdSigObjStore<N> {
+    type MembershipWitness = ThresholdWitness<N>;
+
+    type MembershipWitnessVar = ThresholdWitnessVar<N>;
+
+    type MembershipPub = Committee<N>;
+
+    type MembershipPubVar = CommitteeVar<N>;
+
+    fn verify_in<PubArgs, Snark: ark_snark::SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        _args: PubArgs,
+        _proof: Snark::Proof,
+        _memb_data: Self::MembershipPub,
+        _verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        for (i, c) in self.coms.iter().enumerate() {
+            if c == &object
+                && self.old_nuls[i] == old_nul
+                && self.cb_com_lists[i] == cb_com_list.to_vec()
+            {
+                return self.is_certified(c);
+            }
+        }
+        false
+    }
+
+    fn get_membership_data(&self, object: Com<F>) -> Option<(Committee<N>, ThresholdWitness<N>)> {
+        self.get_certificate(&object)
+            .map(|w| (self.get_committee(), w))
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        Self::enforce_certificate(data_var, extra_witness, extra_pub)
+    }
+}
+
+impl<U: UserData<F>, const N: usize> UserBul<F, U> for ThresholdSigObjStore<N> {
+    type Error = ();
+
+    fn has_never_received_nul(&self, nul: &Nul<F>) -> bool {
+        !self.old_nuls.contains(nul)
+    }
+
+    fn append_value<PubArgs, Snark: ark_snark::SNARK<F>, const NUMCBS: usize>(
+        &mut self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        _args: PubArgs,
+        _proof: Snark::Proof,
+        _memb_data: Option<Self::MembershipPub>,
+        _verif_key: &Snark::VerifyingKey,
+    ) -> Result<(), Self::Error> {
+        self.push(object, old_nul, cb_com_list.into());
+        Ok(())
+    }
+}
+
+impl<U: UserData<F>, const N: usize> JoinableBulletin<F, U> for ThresholdSigObjStore<N> {
+    type PubData = ();
+
+    fn join_bul(&mut self, object: Com<F>, _pub_data: ()) -> Result<(), Self::Error> {
+        let old_nul = thread_rng().gen();
+        self.push(object, old_nul, vec![]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ark_r1cs_std::eq::EqGadget;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    // Tests that an object is only certified once the threshold is met, and that the certificate
+    // verifies in-circuit
+    #[test]
+    fn threshold_membership() -> Result<(), SynthesisError> {
+        let mut rng = thread_rng();
+
+        let privkeys: Vec<BlsPrivkey> =
+            (0..3).map(|_| Bls377Aggregate::gen_key(&mut rng)).collect();
+        let committee = Committee::<3>::new(
+            privkeys
+                .iter()
+                .map(|sk| (sk.get_pubkey(), sk.prove_possession()))
+                .collect(),
+            2,
+        )
+        .unwrap();
+
+        let mut store =
+            ThresholdSigObjStore::<3>::new(committee.clone(), vec![(0, privkeys[0].clone())])
+                .unwrap();
+        let obj: F = rng.gen();
+        store.push(obj, rng.gen(), vec![]);
+        assert!(!store.is_certified(&obj));
+
+        let share = Bls377Aggregate::sign(&privkeys[2], &mut rng, obj).unwrap();
+        assert_eq!(
+            store.add_share(&obj, 1, share.clone()),
+            Err(ShareError::InvalidShare)
+        );
+        assert_eq!(
+            store.add_share(&obj, 3, share.clone()),
+            Err(ShareError::UnknownMaintainer)
+        );
+        let other: F = rng.gen();
+        let other_share = Bls377Aggregate::sign(&privkeys[2], &mut rng, other).unwrap();
+        assert_eq!(
+            store.add_share(&other, 2, other_share),
+            Err(ShareError::UnknownObject)
+        );
+        store.add_share(&obj, 2, share).unwrap();
+        let cert = store.get_certificate(&obj).unwrap();
+        assert_eq!(cert.signers, vec![true, false, true]);
+
+        let cs = ConstraintSystem::<F>::new_ref();
+        let obj_var = FpVar::new_input(cs.clone(), || Ok(obj))?;
+        let cert_var = ThresholdWitnessVar::new_witness(cs.clone(), || Ok(&cert))?;
+        let committee_var = CommitteeVar::new_input(cs.clone(), || Ok(&committee))?;
+        let out = ThresholdSigObjStore::<3>::enforce_certificate(obj_var, cert_var, committee_var)?;
+        out.enforce_equal(&Boolean::TRUE)?;
+        assert!(cs.is_satisfied()?);
+        Ok(())
+    }
+}
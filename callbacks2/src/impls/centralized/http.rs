@@ -0,0 +1,643 @@
+use std::marker::PhantomData;
+
+use crate::{
+    generic::{
+        bulletin::{PublicCallbackBul, PublicUserBul},
+        object::{Com, ComVar, Nul, Time, TimeVar},
+        user::UserData,
+    },
+    impls::centralized::{
+        crypto::{FakeSigPubkey, FakeSigPubkeyVar, NoEnc, NoSigOTP},
+        ds::{
+            sig::Signature,
+            sigrange::{SigRangeStore, SignedRange, SignedRangeVar},
+            sigstore::{CallbackStore, CalledEntry, ObjEntry, SigObjStore},
+        },
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::AllocVar, convert::ToConstraintFieldGadget, fields::fp::FpVar, prelude::Boolean,
+};
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalDeserialize, Compress, SerializationError, Validate};
+use rand::distributions::{Distribution, Standard};
+use reqwest::{blocking::Client, StatusCode, Url};
+
+/// An error when fetching data from a remote bulletin.
+#[derive(Debug)]
+pub enum HttpBulError {
+    /// The endpoint could not be joined onto the base url.
+    Url(String),
+    /// The request could not be sent, or the response could not be read.
+    Request(reqwest::Error),
+    /// The server responded with a non-success status.
+    Status(StatusCode),
+    /// A request or response body could not be serialized or deserialized.
+    Serialization(SerializationError),
+}
+
+impl std::fmt::Display for HttpBulError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpBulError::Url(e) => write!(f, "invalid endpoint: {}", e),
+            HttpBulError::Request(e) => write!(f, "request failed: {}", e),
+            HttpBulError::Status(s) => write!(f, "server responded with status {}", s),
+            HttpBulError::Serialization(e) => write!(f, "serialization failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HttpBulError {}
+
+fn get_with<T: CanonicalDeserialize>(
+    client: &Client,
+    api: &Url,
+    endpoint: &str,
+) -> Result<T, HttpBulError> {
+    let url = api
+        .join(endpoint)
+        .map_err(|e| HttpBulError::Url(e.to_string()))?;
+
+    let res = client.get(url).send().map_err(HttpBulError::Request)?;
+
+    if !res.status().is_success() {
+        return Err(HttpBulError::Status(res.status()));
+    }
+
+    let bytes = res.bytes().map_err(HttpBulError::Request)?;
+
+    T::deserialize_with_mode(&*bytes, Compress::No, Validate::Yes)
+        .map_err(HttpBulError::Serialization)
+}
+
+/// The membership public key and signature, and the nonmembership public key and signed range,
+/// for a ticket.
+type TicketMembership<F, S> = (
+    <S as Signature<F>>::Pubkey,
+    <S as Signature<F>>::Sig,
+    <S as Signature<F>>::Pubkey,
+    SignedRange<F, S>,
+);
+
+/// A handle to a remote [`SigObjStore`], accessed over HTTP.
+///
+/// This talks to a server exposing the object bulletin at the following endpoints, relative to
+/// the base url:
+///
+/// * `GET api/user/pubkey`, returning the public key of the store.
+/// * `GET api/user/bulletin`, returning the output of [`SigObjStore::get_db`].
+/// * `POST api/user/join`, taking a commitment to a new user.
+///
+/// All payloads are serialized uncompressed with [`CanonicalSerialize`](ark_serialize::CanonicalSerialize).
+///
+/// Note that this implements [`PublicUserBul`], so it may be used in place of a local
+/// [`SigObjStore`] when generating proofs. Any proving or verifying keys generated with a
+/// [`SigObjStore`] are compatible with this handle.
+#[derive(Clone, Debug)]
+pub struct HttpObjStore<F: PrimeField + Absorb, S: Signature<F>> {
+    /// The client used to make requests.
+    pub client: Client,
+    /// The base url of the server.
+    pub api: Url,
+    _phantom: PhantomData<(F, S)>,
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>> HttpObjStore<F, S>
+where
+    S::Pubkey: CanonicalDeserialize,
+{
+    /// Construct a new handle to the object bulletin at `api`.
+    pub fn new(api: Url) -> Self {
+        Self::with_client(Client::new(), api)
+    }
+
+    /// Construct a new handle to the object bulletin at `api`, using an existing client.
+    pub fn with_client(client: Client, api: Url) -> Self {
+        Self {
+            client,
+            api,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Fetch the public key of the remote store.
+    pub fn fetch_pubkey(&self) -> Result<S::Pubkey, HttpBulError> {
+        get_with(&self.client, &self.api, "api/user/pubkey")
+    }
+
+    /// Fetch the full database of the remote store.
+    pub fn fetch_db(&self) -> Result<Vec<ObjEntry<F, S>>, HttpBulError> {
+        get_with(&self.client, &self.api, "api/user/bulletin")
+    }
+
+    /// Join the remote bulletin with a commitment to a new user.
+    pub fn join(&self, object: Com<F>) -> Result<(), HttpBulError> {
+        let url = self
+            .api
+            .join("api/user/join")
+            .map_err(|e| HttpBulError::Url(e.to_string()))?;
+
+        let mut bytes = vec![];
+        object
+            .serialize_with_mode(&mut bytes, Compress::No)
+            .map_err(HttpBulError::Serialization)?;
+
+        let res = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/octet-stream")
+            .body(bytes)
+            .send()
+            .map_err(HttpBulError::Request)?;
+
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(HttpBulError::Status(res.status()))
+        }
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>> PublicUserBul<F, U>
+    for HttpObjStore<F, S>
+where
+    S::Pubkey: CanonicalDeserialize,
+{
+    type MembershipWitness = S::Sig;
+
+    type MembershipWitnessVar = S::SigVar;
+
+    type MembershipPub = S::Pubkey;
+
+    type MembershipPubVar = S::PubkeyVar;
+
+    fn verify_in<PubArgs, Snark: ark_snark::SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        _args: PubArgs,
+        _proof: Snark::Proof,
+        _memb_data: Self::MembershipPub,
+        _verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        match self.fetch_db() {
+            Ok(db) => db
+                .iter()
+                .any(|(c, n, l, _)| *c == object && *n == old_nul && *l == cb_com_list.to_vec()),
+            Err(_) => false,
+        }
+    }
+
+    fn get_membership_data(&self, object: Com<F>) -> Option<(S::Pubkey, S::Sig)> {
+        let db = self.fetch_db().ok()?;
+        let sig = db
+            .into_iter()
+            .find(|(c, _, _, _)| *c == object)
+            .map(|(_, _, _, s)| s)?;
+        Some((self.fetch_pubkey().ok()?, sig))
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <SigObjStore<F, S> as PublicUserBul<F, U>>::enforce_membership_of(
+            data_var,
+            extra_witness,
+            extra_pub,
+        )
+    }
+}
+
+/// A handle to a remote [`CallbackStore`] with a [`SigRangeStore`] for nonmembership, accessed
+/// over HTTP.
+///
+/// This talks to a server exposing the callback bulletin at the following endpoints, relative to
+/// the base url:
+///
+/// * `GET api/callbacks/membership_pubkey`, returning the membership public key.
+/// * `GET api/callbacks/nonmembership_pubkey`, returning the nonmembership public key.
+/// * `GET api/callbacks/bulletin`, returning the output of [`CallbackStore::get_db`].
+/// * `GET api/callbacks/nmemb_bulletin`, returning the output of [`SigRangeStore::get_db`].
+///
+/// All payloads are serialized uncompressed with [`CanonicalSerialize`](ark_serialize::CanonicalSerialize).
+///
+/// Note that this implements [`PublicCallbackBul`], so it may be used in place of a local
+/// [`CallbackStore`] when scanning. Any proving or verifying keys generated with a
+/// [`CallbackStore`] are compatible with this handle.
+#[derive(Clone, Debug)]
+pub struct HttpCallbackStore<F: PrimeField + Absorb, S: Signature<F>, Args> {
+    /// The client used to make requests.
+    pub client: Client,
+    /// The base url of the server.
+    pub api: Url,
+    _phantom: PhantomData<(F, S, Args)>,
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F> + Default, Args: Clone + CanonicalDeserialize>
+    HttpCallbackStore<F, S, Args>
+where
+    S::Pubkey: CanonicalDeserialize,
+{
+    /// Construct a new handle to the callback bulletin at `api`.
+    pub fn new(api: Url) -> Self {
+        Self::with_client(Client::new(), api)
+    }
+
+    /// Construct a new handle to the callback bulletin at `api`, using an existing client.
+    pub fn with_client(client: Client, api: Url) -> Self {
+        Self {
+            client,
+            api,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Fetch the membership public key of the remote store.
+    pub fn fetch_pubkey(&self) -> Result<S::Pubkey, HttpBulError> {
+        get_with(&self.client, &self.api, "api/callbacks/membership_pubkey")
+    }
+
+    /// Fetch the nonmembership public key of the remote store.
+    pub fn fetch_nmemb_pubkey(&self) -> Result<S::Pubkey, HttpBulError> {
+        get_with(
+            &self.client,
+            &self.api,
+            "api/callbacks/nonmembership_pubkey",
+        )
+    }
+
+    /// Fetch the called tickets of the remote store.
+    pub fn fetch_db(&self) -> Result<Vec<CalledEntry<F, S, Args>>, HttpBulError> {
+        get_with(&self.client, &self.api, "api/callbacks/bulletin")
+    }
+
+    /// Fetch the signed ranges of the remote nonmembership store.
+    pub fn fetch_nmemb_db(&self) -> Result<Vec<SignedRange<F, S>>, HttpBulError> {
+        get_with(&self.client, &self.api, "api/callbacks/nmemb_bulletin")
+    }
+
+    fn called(&self, tik: &FakeSigPubkey<F>) -> Option<(Args, Time<F>)> {
+        let db = self.fetch_db().ok()?;
+        db.into_iter()
+            .find(|(t, _, _, _)| t == tik)
+            .map(|(_, a, time, _)| (a, time))
+    }
+
+    fn not_called(&self, tik: &FakeSigPubkey<F>) -> bool {
+        match self.fetch_nmemb_db() {
+            Ok(db) => db.iter().any(|r| r.is_in_range(tik.to())),
+            Err(_) => false,
+        }
+    }
+
+    /// Fetch the membership and nonmembership data for a ticket.
+    ///
+    /// If the ticket has been called, this returns the signature on the ticket, along with a
+    /// default nonmembership witness. Otherwise, this returns the signed range containing the
+    /// ticket, along with a default signature.
+    ///
+    /// If the ticket is in neither store, this returns [`None`].
+    pub fn fetch_membership_data(
+        &self,
+        tik: &FakeSigPubkey<F>,
+    ) -> Result<Option<TicketMembership<F, S>>, HttpBulError> {
+        let mkey = self.fetch_pubkey()?;
+        let nkey = self.fetch_nmemb_pubkey()?;
+
+        if let Some((_, _, _, s)) = self.fetch_db()?.into_iter().find(|(t, _, _, _)| t == tik) {
+            return Ok(Some((mkey, s, nkey, SignedRange::default())));
+        }
+
+        Ok(self
+            .fetch_nmemb_db()?
+            .into_iter()
+            .find(|r| r.is_in_range(tik.to()))
+            .map(|r| (mkey, S::Sig::default(), nkey, r)))
+    }
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F> + Default> PublicCallbackBul<F, F, NoSigOTP<F>>
+    for HttpCallbackStore<F, S, F>
+where
+    Standard: Distribution<F>,
+    S::Pubkey: CanonicalDeserialize,
+{
+    type MembershipWitness = S::Sig;
+
+    type MembershipWitnessVar = S::SigVar;
+
+    type NonMembershipWitness = SignedRange<F, S>;
+
+    type NonMembershipWitnessVar = SignedRangeVar<F, S>;
+
+    type MembershipPub = S::Pubkey;
+
+    type MembershipPubVar = S::PubkeyVar;
+
+    type NonMembershipPub = S::Pubkey;
+
+    type NonMembershipPubVar = S::PubkeyVar;
+
+    fn verify_in(&self, tik: FakeSigPubkey<F>) -> Option<(F, Time<F>)> {
+        self.called(&tik)
+    }
+
+    fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
+        self.not_called(&tik)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the remote bulletin cannot be reached, or if the ticket is in neither store.
+    fn get_membership_data(
+        &self,
+        tik: FakeSigPubkey<F>,
+    ) -> (S::Pubkey, S::Sig, S::Pubkey, SignedRange<F, S>) {
+        self.fetch_membership_data(&tik)
+            .expect("Could not fetch membership data.")
+            .expect("Ticket is in neither the membership nor the nonmembership store.")
+    }
+
+    fn enforce_membership_of(
+        tikvar: (FakeSigPubkeyVar<F>, FpVar<F>, TimeVar<F>),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <CallbackStore<F, S, SigRangeStore<F, S>, F> as PublicCallbackBul<
+            F,
+            F,
+            NoSigOTP<F>,
+        >>::enforce_membership_of(tikvar, extra_witness, extra_pub)
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: FakeSigPubkeyVar<F>,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <CallbackStore<F, S, SigRangeStore<F, S>, F> as PublicCallbackBul<
+            F,
+            F,
+            NoSigOTP<F>,
+        >>::enforce_nonmembership_of(tikvar, extra_witness, extra_pub)
+    }
+}
+
+impl<
+        F: PrimeField + Absorb,
+        S: Signature<F> + Default,
+        A: Clone + Default + ToConstraintField<F> + CanonicalDeserialize,
+        AVar: Clone + AllocVar<A, F> + ToConstraintFieldGadget<F>,
+    > PublicCallbackBul<F, A, NoEnc<F, A, AVar>> for HttpCallbackStore<F, S, A>
+where
+    Standard: Distribution<F>,
+    S::Pubkey: CanonicalDeserialize,
+{
+    type MembershipWitness = S::Sig;
+
+    type MembershipWitnessVar = S::SigVar;
+
+    type NonMembershipWitness = SignedRange<F, S>;
+
+    type NonMembershipWitnessVar = SignedRangeVar<F, S>;
+
+    type MembershipPub = S::Pubkey;
+
+    type MembershipPubVar = S::PubkeyVar;
+
+    type NonMembershipPub = S::Pubkey;
+
+    type NonMembershipPubVar = S::PubkeyVar;
+
+    fn verify_in(&self, tik: FakeSigPubkey<F>) -> Option<(A, Time<F>)> {
+        self.called(&tik)
+    }
+
+    fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
+        self.not_called(&tik)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the remote bulletin cannot be reached, or if the ticket is in neither store.
+    fn get_membership_data(
+        &self,
+        tik: FakeSigPubkey<F>,
+    ) -> (S::Pubkey, S::Sig, S::Pubkey, SignedRange<F, S>) {
+        self.fetch_membership_data(&tik)
+            .expect("Could not fetch membership data.")
+            .expect("Ticket is in neither the membership nor the nonmembership store.")
+    }
+
+    fn enforce_membership_of(
+        tikvar: (FakeSigPubkeyVar<F>, AVar, TimeVar<F>),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <CallbackStore<F, S, SigRangeStore<F, S>, A> as PublicCallbackBul<
+            F,
+            A,
+            NoEnc<F, A, AVar>,
+        >>::enforce_membership_of(tikvar, extra_witness, extra_pub)
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: FakeSigPubkeyVar<F>,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <CallbackStore<F, S, SigRangeStore<F, S>, A> as PublicCallbackBul<
+            F,
+            A,
+            NoEnc<F, A, AVar>,
+        >>::enforce_nonmembership_of(tikvar, extra_witness, extra_pub)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        generic::bulletin::{CallbackBul, JoinableBulletin},
+        impls::centralized::ds::{
+            sig::jj_schnorr::JubjubSchnorr,
+            sigstore::{CallbackStore, SigObjStore},
+        },
+    };
+    use ark_bls12_381::Fr;
+    use ark_ff::UniformRand;
+    use ark_serialize::CanonicalSerialize;
+    use rand::thread_rng;
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    fn ser(obj: &impl CanonicalSerialize) -> Vec<u8> {
+        let mut bytes = vec![];
+        obj.serialize_with_mode(&mut bytes, Compress::No).unwrap();
+        bytes
+    }
+
+    // Serves fixed bodies on GET, and accepts any POST, on a local port
+    fn serve(routes: HashMap<&'static str, Vec<u8>>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let path = parts.next().unwrap_or_default().to_string();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header == "\r\n" || header.is_empty() {
+                        break;
+                    }
+                }
+                let (status, body) = match routes.get(path.trim_start_matches('/')) {
+                    Some(body) if method == "GET" => ("200 OK", body.clone()),
+                    _ if method == "POST" => ("200 OK", vec![]),
+                    _ => ("404 Not Found", vec![]),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        Url::parse(&format!("http://{}/", addr)).unwrap()
+    }
+
+    // Tests that a remote object store returns the same data as the local store
+    #[test]
+    fn http_obj_store_membership() {
+        let mut rng = thread_rng();
+        let mut store = SigObjStore::<Fr, JubjubSchnorr>::new(&mut rng);
+        let joined = Fr::rand(&mut rng);
+        <SigObjStore<Fr, JubjubSchnorr> as JoinableBulletin<Fr, Fr>>::join_bul(
+            &mut store,
+            joined,
+            (),
+        )
+        .unwrap();
+
+        let api = serve(HashMap::from([
+            ("api/user/pubkey", ser(&store.get_pubkey())),
+            ("api/user/bulletin", ser(&store.get_db())),
+        ]));
+        let remote = HttpObjStore::<Fr, JubjubSchnorr>::new(api);
+
+        assert_eq!(remote.fetch_pubkey().unwrap(), store.get_pubkey());
+        assert_eq!(remote.fetch_db().unwrap(), store.get_db());
+        remote.join(Fr::rand(&mut rng)).unwrap();
+
+        let (pk, sig) =
+            <HttpObjStore<Fr, JubjubSchnorr> as PublicUserBul<Fr, Fr>>::get_membership_data(
+                &remote, joined,
+            )
+            .unwrap();
+        assert_eq!(pk, store.get_pubkey());
+        assert_eq!(Some(sig), store.get_signature_of(&joined));
+
+        assert!(
+            <HttpObjStore<Fr, JubjubSchnorr> as PublicUserBul<Fr, Fr>>::get_membership_data(
+                &remote,
+                Fr::rand(&mut rng),
+            )
+            .is_none()
+        );
+    }
+
+    // Tests that a missing endpoint surfaces as a status error
+    #[test]
+    fn http_missing_endpoint() {
+        let remote = HttpObjStore::<Fr, JubjubSchnorr>::new(serve(HashMap::new()));
+        assert!(matches!(
+            remote.fetch_db(),
+            Err(HttpBulError::Status(StatusCode::NOT_FOUND))
+        ));
+        assert!(
+            <HttpObjStore<Fr, JubjubSchnorr> as PublicUserBul<Fr, Fr>>::get_membership_data(
+                &remote,
+                Fr::from(1u8),
+            )
+            .is_none()
+        );
+    }
+
+    // Tests that a remote callback store gives membership data for called tickets, and
+    // nonmembership data for tickets which were never called
+    #[test]
+    fn http_callback_store_membership() {
+        let mut rng = thread_rng();
+        let mut store =
+            CallbackStore::<Fr, JubjubSchnorr, SigRangeStore<Fr, JubjubSchnorr>, Fr>::new(&mut rng);
+        // Tickets are below the largest value covered by the signed ranges
+        let called = FakeSigPubkey::new(Fr::from(1000u64));
+        let uncalled = FakeSigPubkey::new(Fr::from(7u64));
+        let args = Fr::from(7u8);
+        <CallbackStore<Fr, JubjubSchnorr, SigRangeStore<Fr, JubjubSchnorr>, Fr> as CallbackBul<
+            Fr,
+            Fr,
+            NoSigOTP<Fr>,
+        >>::append_value(&mut store, called.clone(), args, (), Fr::from(1u8))
+        .unwrap();
+        store.update_epoch(&mut rng);
+
+        let api = serve(HashMap::from([
+            ("api/callbacks/membership_pubkey", ser(&store.get_pubkey())),
+            (
+                "api/callbacks/nonmembership_pubkey",
+                ser(&store.nmemb_bul.get_pubkey()),
+            ),
+            ("api/callbacks/bulletin", ser(&store.get_db())),
+            (
+                "api/callbacks/nmemb_bulletin",
+                ser(&store.nmemb_bul.get_db()),
+            ),
+        ]));
+        let remote = HttpCallbackStore::<Fr, JubjubSchnorr, Fr>::new(api);
+        type Bul = HttpCallbackStore<Fr, JubjubSchnorr, Fr>;
+
+        assert_eq!(
+            <Bul as PublicCallbackBul<Fr, Fr, NoSigOTP<Fr>>>::verify_in(&remote, called.clone()),
+            Some((args, Fr::from(1u8)))
+        );
+        assert!(
+            !<Bul as PublicCallbackBul<Fr, Fr, NoSigOTP<Fr>>>::verify_not_in(
+                &remote,
+                called.clone()
+            )
+        );
+        assert!(
+            <Bul as PublicCallbackBul<Fr, Fr, NoSigOTP<Fr>>>::verify_not_in(
+                &remote,
+                uncalled.clone()
+            )
+        );
+
+        let (mkey, sig, nkey, _) = remote.fetch_membership_data(&called).unwrap().unwrap();
+        assert_eq!(mkey, store.get_pubkey());
+        assert_eq!(nkey, store.nmemb_bul.get_pubkey());
+        assert_eq!(Some(sig), store.get_memb_witness(&called));
+
+        let (_, _, _, range) = remote.fetch_membership_data(&uncalled).unwrap().unwrap();
+        assert!(range.is_in_range(uncalled.to()));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    generic::{bulletin::JoinPolicy, object::Com},
+    impls::centralized::ds::sig::{Privkey, Pubkey, Signature},
+};
+use ark_ff::PrimeField;
+use rand::{CryptoRng, RngCore};
+use std::{
+    collections::{HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// The reason a [`JoinPolicy`] rejected a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    /// The invite was not signed by any accepted inviter.
+    BadInvite,
+    /// The token was not signed by the issuer.
+    BadToken,
+    /// The token was already used to join.
+    TokenSpent,
+    /// Too many users joined recently.
+    RateLimited,
+}
+
+impl std::fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyError::BadInvite => write!(f, "invite signature is invalid"),
+            PolicyError::BadToken => write!(f, "token signature is invalid"),
+            PolicyError::TokenSpent => write!(f, "token was already used"),
+            PolicyError::RateLimited => write!(f, "too many joins, try again later"),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+/// Admit users holding an invite signature.
+///
+/// An invite is a signature on the commitment of the new user, by one of the accepted inviters
+/// (for example, existing members or moderators). Since the invite signs the commitment, it can
+/// not be reused for a different user object.
+///
+/// # Example
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use rand::thread_rng;
+/// # use zk_callbacks::generic::bulletin::JoinPolicy;
+/// # use zk_callbacks::impls::centralized::ds::sig::Signature;
+/// # use zk_callbacks::impls::centralized::ds::sig::gr_schnorr::GrumpkinSchnorr;
+/// # use zk_callbacks::impls::centralized::join::{InvitePolicy, PolicyError};
+/// let mut rng = thread_rng();
+/// let inviter = GrumpkinSchnorr::gen_key(&mut rng);
+/// let mut policy = InvitePolicy::<Fr, GrumpkinSchnorr>::new(vec![GrumpkinSchnorr::get_pubkey(&inviter)]);
+///
+/// let com = Fr::from(1234);
+/// let invite = InvitePolicy::<Fr, GrumpkinSchnorr>::invite(&inviter, &mut rng, com);
+/// assert!(policy.admit(&com, &invite).is_ok());
+/// assert_eq!(policy.admit(&Fr::from(1), &invite), Err(PolicyError::BadInvite));
+/// ```
+pub struct InvitePolicy<F: PrimeField, S: Signature<F>> {
+    /// The public keys of the accepted inviters.
+    pub inviters: Vec<S::Pubkey>,
+}
+
+impl<F: PrimeField, S: Signature<F>> InvitePolicy<F, S> {
+    /// Create a policy accepting invites from the given inviters.
+    pub fn new(inviters: Vec<S::Pubkey>) -> Self {
+        Self { inviters }
+    }
+
+    /// Sign an invite for a user commitment.
+    pub fn invite(
+        inviter: &S::Privkey,
+        rng: &mut (impl CryptoRng + RngCore),
+        com: Com<F>,
+    ) -> S::Sig {
+        inviter.sign(rng, com).expect("signing an invite failed")
+    }
+}
+
+impl<F: PrimeField, S: Signature<F>> JoinPolicy<F> for InvitePolicy<F, S> {
+    type Credential = S::Sig;
+
+    type Error = PolicyError;
+
+    fn admit(&mut self, object: &Com<F>, credential: &S::Sig) -> Result<(), PolicyError> {
+        if self
+            .inviters
+            .iter()
+            .any(|pk| pk.verify(credential.clone(), *object))
+        {
+            Ok(())
+        } else {
+            Err(PolicyError::BadInvite)
+        }
+    }
+}
+
+/// Admit users holding a unique, issuer-signed token.
+///
+/// An identity provider (for example, one checking a phone number or government credential) signs
+/// a random token for each person, and each token may be used to join once. If the provider signs
+/// tokens blindly, the provider can not link a token to the person it was issued to; this policy
+/// only checks the signature and that the token is unspent.
+pub struct TokenPolicy<F: PrimeField, S: Signature<F>> {
+    /// The public key of the token issuer.
+    pub issuer: S::Pubkey,
+    /// The tokens which were used to join.
+    pub spent: HashSet<F>,
+}
+
+impl<F: PrimeField, S: Signature<F>> TokenPolicy<F, S> {
+    /// Create a policy accepting tokens signed by the issuer.
+    pub fn new(issuer: S::Pubkey) -> Self {
+        Self {
+            issuer,
+            spent: HashSet::new(),
+        }
+    }
+}
+
+impl<F: PrimeField, S: Signature<F>> JoinPolicy<F> for TokenPolicy<F, S> {
+    type Credential = (F, S::Sig);
+
+    type Error = PolicyError;
+
+    fn admit(&mut self, _object: &Com<F>, credential: &(F, S::Sig)) -> Result<(), PolicyError> {
+        let (token, sig) = credential;
+        if !self.issuer.verify(sig.clone(), *token) {
+            return Err(PolicyError::BadToken);
+        }
+        if !self.spent.insert(*token) {
+            return Err(PolicyError::TokenSpent);
+        }
+        Ok(())
+    }
+}
+
+/// Limit the number of users joining within a window of time, on top of another policy.
+///
+/// This bounds how quickly anyone can grow the bulletin, even with valid credentials. Rejected
+/// joins do not count towards the limit.
+///
+/// # Example
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use std::time::Duration;
+/// # use zk_callbacks::generic::bulletin::JoinPolicy;
+/// # use zk_callbacks::impls::centralized::join::{PolicyError, RateLimitedPolicy};
+/// let mut policy = RateLimitedPolicy::new((), 2, Duration::from_secs(60));
+/// let com = Fr::from(1);
+/// assert!(policy.admit(&com, &()).is_ok());
+/// assert!(policy.admit(&com, &()).is_ok());
+/// assert_eq!(policy.admit(&com, &()), Err(PolicyError::RateLimited));
+/// ```
+pub struct RateLimitedPolicy<P> {
+    /// The inner policy.
+    pub inner: P,
+    /// The maximum number of joins within a window.
+    pub max_joins: usize,
+    /// The length of the window.
+    pub window: Duration,
+    joined: VecDeque<Instant>,
+}
+
+impl<P> RateLimitedPolicy<P> {
+    /// Limit an inner policy to `max_joins` within every `window`.
+    pub fn new(inner: P, max_joins: usize, window: Duration) -> Self {
+        Self {
+            inner,
+            max_joins,
+            window,
+            joined: VecDeque::new(),
+        }
+    }
+}
+
+impl<F: PrimeField, P: JoinPolicy<F>> JoinPolicy<F> for RateLimitedPolicy<P>
+where
+    PolicyError: From<P::Error>,
+{
+    type Credential = P::Credential;
+
+    type Error = PolicyError;
+
+    fn admit(&mut self, object: &Com<F>, credential: &P::Credential) -> Result<(), PolicyError> {
+        let now = Instant::now();
+        while let Some(t) = self.joined.front() {
+            if now.duration_since(*t) >= self.window {
+                self.joined.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.joined.len() >= self.max_joins {
+            return Err(PolicyError::RateLimited);
+        }
+        self.inner.admit(object, credential)?;
+        self.joined.push_back(now);
+        Ok(())
+    }
+}
+
+impl From<std::convert::Infallible> for PolicyError {
+    fn from(e: std::convert::Infallible) -> Self {
+        match e {}
+    }
+}
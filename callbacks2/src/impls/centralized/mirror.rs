@@ -0,0 +1,315 @@
+use crate::{
+    crypto::{enc::AECipherSigZK, hash::HasherZK},
+    generic::{
+        bulletin::{BulletinDelta, PublicUserBul},
+        object::{Com, ComVar, Nul, Time},
+        user::{ExecutedMethod, UserData},
+        verify::{verify_execution, VerifyError},
+    },
+    impls::{
+        centralized::{
+            crypto::FakeSigPubkey,
+            ds::{
+                sig::Signature,
+                sigstore::{CallbackDelta, CalledEntry, Checkpoint, DeltaError, SigObjMirror},
+            },
+        },
+        hash::Poseidon,
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::prelude::Boolean;
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_snark::SNARK;
+
+/// An error when auditing an interaction with a [`BulletinMirror`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditError {
+    /// The mirror does not hold the new object of the interaction (along with the same old
+    /// nullifier and callback commitments).
+    NotInBulletin,
+    /// The proof of the interaction does not verify.
+    Proof(VerifyError),
+}
+
+impl std::fmt::Display for AuditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditError::NotInBulletin => write!(f, "the interaction is not on the bulletin"),
+            AuditError::Proof(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+/// A read-only mirror of a centralized service, following its user bulletin (a
+/// [`SigObjStore`](`super::ds::sigstore::SigObjStore`)) and callback bulletin (a
+/// [`CallbackStore`](`super::ds::sigstore::CallbackStore`)).
+///
+/// The mirror is kept up to date with the deltas from
+/// [`PublicUserBul::get_updates_since`] and
+/// [`CallbackStore::get_calls_since`](`super::ds::sigstore::CallbackStore::get_calls_since`).
+/// Every signature is checked as the deltas are applied, and (if a log key is set) the user
+/// bulletin is checked to only ever grow against signed [`Checkpoint`]s. Interactions the service
+/// publishes may additionally be audited with [`BulletinMirror::audit_interaction`].
+///
+/// The mirror serves user membership data through [`PublicUserBul`], and call membership data
+/// through [`BulletinMirror::get_call_witness`], so third parties may both audit a server and
+/// take load off of it.
+///
+/// # Example
+/// ```rust
+/// # use zk_callbacks::generic::bulletin::{CallbackBul, JoinableBulletin, PublicUserBul};
+/// # use zk_callbacks::generic::object::Time;
+/// # use zk_callbacks::impls::centralized::crypto::{FakeSigPubkey, NoSigOTP};
+/// # use zk_callbacks::impls::centralized::ds::sig::gr_schnorr::GrumpkinSchnorr;
+/// # use zk_callbacks::impls::centralized::ds::sigstore::{GRSchnorrCallbackStore, GRSchnorrObjStore};
+/// # use zk_callbacks::impls::centralized::mirror::BulletinMirror;
+/// # use ark_grumpkin::Fq as Fr;
+/// # use rand::thread_rng;
+/// # type CBul = GRSchnorrCallbackStore<Fr>;
+/// let mut rng = thread_rng();
+/// let mut obj_bul = GRSchnorrObjStore::new(&mut rng);
+/// let mut cb_bul = CBul::new(&mut rng);
+/// let mut mirror: BulletinMirror<Fr, GrumpkinSchnorr, Fr> = BulletinMirror::new();
+///
+/// <GRSchnorrObjStore as JoinableBulletin<Fr, bool>>::join_bul(&mut obj_bul, Fr::from(1), ()).unwrap();
+/// let tik = FakeSigPubkey::new(Fr::from(7));
+/// <CBul as CallbackBul<Fr, Fr, NoSigOTP<Fr>>>::append_value(&mut cb_bul, tik.clone(), Fr::from(5), (), Time::from(0)).unwrap();
+///
+/// let delta = <GRSchnorrObjStore as PublicUserBul<Fr, bool>>::get_updates_since(&obj_bul, mirror.users.get_version()).unwrap();
+/// mirror.sync_users(delta, None).unwrap();
+/// mirror.sync_calls(cb_bul.get_calls_since(mirror.get_calls_version()).unwrap()).unwrap();
+///
+/// assert!(<BulletinMirror<Fr, GrumpkinSchnorr, Fr> as PublicUserBul<Fr, bool>>::get_membership_data(&mirror, Fr::from(1)).is_some());
+/// assert_eq!(mirror.verify_call_in(&tik), Some((Fr::from(5), Time::from(0))));
+/// ```
+#[derive(Clone, Debug)]
+pub struct BulletinMirror<F: PrimeField + Absorb, S: Signature<F>, Args> {
+    /// The mirrored user bulletin.
+    pub users: SigObjMirror<F, S>,
+    /// The public key checkpoints of the user bulletin are signed under. If set, every user delta
+    /// must come with a checkpoint.
+    pub log_pubkey: Option<S::Pubkey>,
+    /// The membership public key of the callback bulletin.
+    pub cb_pubkey: S::Pubkey,
+    /// The mirrored calls, as (ticket, arguments, time, signature).
+    pub calls: Vec<CalledEntry<F, S, Args>>,
+    /// The number of interactions audited with [`BulletinMirror::audit_interaction`].
+    pub audited: u64,
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, Args: Clone + ToConstraintField<F>> Default
+    for BulletinMirror<F, S, Args>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField + Absorb, S: Signature<F>, Args: Clone + ToConstraintField<F>>
+    BulletinMirror<F, S, Args>
+{
+    /// Construct a new empty mirror.
+    pub fn new() -> Self {
+        Self {
+            users: SigObjMirror::new(),
+            log_pubkey: None,
+            cb_pubkey: S::Pubkey::default(),
+            calls: vec![],
+            audited: 0,
+        }
+    }
+
+    /// Construct a new empty mirror, which checks the user bulletin against checkpoints signed
+    /// under `log_pubkey`.
+    pub fn with_log_pubkey(log_pubkey: S::Pubkey) -> Self {
+        Self {
+            log_pubkey: Some(log_pubkey),
+            ..Self::new()
+        }
+    }
+
+    /// Apply a delta of the user bulletin.
+    ///
+    /// If the mirror has a log key, the delta must come with a checkpoint for its version (see
+    /// [`SigObjMirror::apply_checked`]). Otherwise, the checkpoint is ignored.
+    pub fn sync_users(
+        &mut self,
+        delta: BulletinDelta<F, S::Pubkey, S::Sig>,
+        checkpoint: Option<&Checkpoint<F, S::Sig>>,
+    ) -> Result<(), DeltaError> {
+        match &self.log_pubkey {
+            Some(pk) => {
+                let checkpoint = checkpoint.ok_or(DeltaError::InvalidCheckpoint)?;
+                self.users.apply_checked(delta, checkpoint, pk)
+            }
+            None => self.users.apply(delta),
+        }
+    }
+
+    /// Get the number of calls mirrored. This should be passed to
+    /// [`CallbackStore::get_calls_since`](`super::ds::sigstore::CallbackStore::get_calls_since`)
+    /// to fetch the next delta.
+    pub fn get_calls_version(&self) -> u64 {
+        self.calls.len() as u64
+    }
+
+    /// Apply a delta of the callback bulletin.
+    ///
+    /// As with [`SigObjMirror::apply`], a delta under a new key must start at version `0`, and the
+    /// mirror is left unchanged if any signature does not verify.
+    pub fn sync_calls(
+        &mut self,
+        delta: CallbackDelta<F, Args, S::Pubkey, S::Sig>,
+    ) -> Result<(), DeltaError>
+    where
+        Args: CanonicalSerialize + CanonicalDeserialize,
+        S::Pubkey: CanonicalSerialize + CanonicalDeserialize,
+    {
+        let key_changed = delta.pubkey.to_field_elements() != self.cb_pubkey.to_field_elements();
+
+        if key_changed && delta.from_version != 0 {
+            return Err(DeltaError::KeyChanged);
+        }
+
+        let base = if key_changed {
+            0
+        } else {
+            self.get_calls_version()
+        };
+
+        if delta.from_version != base {
+            return Err(DeltaError::VersionMismatch {
+                expected: base,
+                got: delta.from_version,
+            });
+        }
+
+        for (i, (tik, args, time, sig)) in delta.entries.iter().enumerate() {
+            let valid = args.to_field_elements().is_some_and(|args| {
+                let mut v = vec![tik.to()];
+                v.extend_from_slice(&args);
+                v.push(*time);
+                S::verify(delta.pubkey.clone(), sig.clone(), <Poseidon<2>>::hash(&v))
+            });
+            if !valid {
+                return Err(DeltaError::InvalidSignature(base + i as u64));
+            }
+        }
+
+        if key_changed {
+            self.calls.clear();
+        }
+        self.cb_pubkey = delta.pubkey;
+        self.calls.extend(delta.entries);
+
+        Ok(())
+    }
+
+    /// Check whether a ticket was called, returning the arguments and time of the call.
+    pub fn verify_call_in(&self, tik: &FakeSigPubkey<F>) -> Option<(Args, Time<F>)> {
+        self.calls
+            .iter()
+            .find(|(t, _, _, _)| t == tik)
+            .map(|(_, a, time, _)| (a.clone(), *time))
+    }
+
+    /// Get the membership data for a called ticket, as (public key, signature).
+    pub fn get_call_witness(&self, tik: &FakeSigPubkey<F>) -> Option<(S::Pubkey, S::Sig)> {
+        self.calls
+            .iter()
+            .find(|(t, _, _, _)| t == tik)
+            .map(|(_, _, _, s)| (self.cb_pubkey.clone(), s.clone()))
+    }
+
+    /// Audit an interaction published by the service.
+    ///
+    /// This checks that the mirror holds the new object of the interaction (along with its old
+    /// nullifier and callback commitments), and that the proof verifies. Services may publish
+    /// the executed methods and public arguments they accept, so auditors can check the service
+    /// only appended objects with valid proofs.
+    ///
+    /// The membership data should be `None` if it was constant when generating the keys.
+    pub fn audit_interaction<
+        Snark: SNARK<F>,
+        PubArgs: ToConstraintField<F>,
+        CBArgs: Clone,
+        Crypto: AECipherSigZK<F, CBArgs>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        verif_key: &Snark::VerifyingKey,
+        exec: &ExecutedMethod<F, Snark, CBArgs, Crypto, NUMCBS>,
+        pub_args: &PubArgs,
+        memb_data: Option<&S::Pubkey>,
+    ) -> Result<(), AuditError> {
+        let held = self.users.db.iter().any(|(c, n, l, _)| {
+            *c == exec.new_object && *n == exec.old_nullifier && l[..] == exec.cb_com_list[..]
+        });
+        if !held {
+            return Err(AuditError::NotInBulletin);
+        }
+
+        verify_execution::<F, Snark, PubArgs, CBArgs, Crypto, S::Pubkey, NUMCBS>(
+            verif_key, exec, pub_args, memb_data,
+        )
+        .map_err(AuditError::Proof)?;
+
+        self.audited += 1;
+        Ok(())
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, S: Signature<F>, Args> PublicUserBul<F, U>
+    for BulletinMirror<F, S, Args>
+{
+    type MembershipWitness = S::Sig;
+
+    type MembershipWitnessVar = S::SigVar;
+
+    type MembershipPub = S::Pubkey;
+
+    type MembershipPubVar = S::PubkeyVar;
+
+    fn verify_in<PubArgs: ToConstraintField<F>, Snark: SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        args: PubArgs,
+        proof: Snark::Proof,
+        memb_data: Self::MembershipPub,
+        verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        <SigObjMirror<F, S> as PublicUserBul<F, U>>::verify_in::<PubArgs, Snark, NUMCBS>(
+            &self.users,
+            object,
+            old_nul,
+            cb_com_list,
+            args,
+            proof,
+            memb_data,
+            verif_key,
+        )
+    }
+
+    fn get_membership_data(&self, object: Com<F>) -> Option<(S::Pubkey, S::Sig)> {
+        <SigObjMirror<F, S> as PublicUserBul<F, U>>::get_membership_data(&self.users, object)
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <SigObjMirror<F, S> as PublicUserBul<F, U>>::enforce_membership_of(
+            data_var,
+            extra_witness,
+            extra_pub,
+        )
+    }
+}
@@ -6,3 +6,37 @@ pub mod crypto;
 
 /// Data structures in the centralized setting.
 pub mod ds;
+
+/// Policies controlling which users may join a bulletin.
+///
+/// These implement [`JoinPolicy`](`crate::generic::bulletin::JoinPolicy`) with invites signed by
+/// existing members, one-time tokens signed by an identity provider, and rate limits.
+pub mod join;
+
+/// Read-only mirrors of centralized bulletins.
+///
+/// A mirror follows a service's user and callback bulletins over the delta-sync protocol, checks
+/// every signature (and optionally every interaction proof), and serves membership data, so third
+/// parties can audit the service.
+pub mod mirror;
+
+/// Signed receipts for called callbacks.
+///
+/// A service signs each call it makes, so users can check which arguments were applied to their
+/// tickets and hold the service to them.
+pub mod receipt;
+/// Signed timestamps issued by a service.
+///
+/// This implements [`TimeProvider`](`crate::generic::time::TimeProvider`) with a service key
+/// signing the current epoch, which users verify in-circuit.
+pub mod time;
+
+/// Handles to remote centralized bulletins over HTTP.
+///
+/// These implement the public bulletin traits by fetching membership data from a server exposing
+/// a [`SigObjStore`](`ds::sigstore::SigObjStore`) and a
+/// [`CallbackStore`](`ds::sigstore::CallbackStore`) over a REST API.
+#[cfg(feature = "http")]
+#[cfg(any(feature = "http", doc))]
+#[doc(cfg(feature = "http"))]
+pub mod http;
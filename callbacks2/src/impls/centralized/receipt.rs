@@ -0,0 +1,149 @@
+use crate::{
+    crypto::{
+        enc::{AECipherSigZK, CPACipher},
+        hash::FieldHash,
+    },
+    generic::{bulletin::PublicCallbackBul, callbacks::CallbackCom, object::Time},
+    impls::centralized::ds::sig::Signature,
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{CryptoRng, RngCore};
+
+/// An error when checking a receipt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptError {
+    /// The signature on the receipt does not verify under the service key.
+    BadSignature,
+    /// The receipt is for a different ticket.
+    WrongTicket,
+    /// The callback bulletin does not hold the call in the receipt.
+    NotPosted,
+}
+
+impl std::fmt::Display for ReceiptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReceiptError::BadSignature => write!(f, "receipt signature does not verify"),
+            ReceiptError::WrongTicket => write!(f, "receipt is for a different ticket"),
+            ReceiptError::NotPosted => write!(f, "call in the receipt is not on the bulletin"),
+        }
+    }
+}
+
+impl std::error::Error for ReceiptError {}
+
+/// A signed statement by a service that it called a ticket with some arguments at some time.
+///
+/// When a service calls a callback, it may hand the user (or post alongside the call) a receipt
+/// signed under a long-term key. The user checks the receipt with
+/// [`verify_for`](`Receipt::verify_for`), which also decrypts the arguments the service applied.
+/// If the service later claims a different argument was applied (for example, a larger penalty),
+/// the user can present the receipt, as the signature binds the ticket, the encrypted arguments,
+/// and the time.
+///
+/// The [`digest`](`Receipt::digest`) of a receipt is a single field element, so it may be added to
+/// the public arguments of an interaction to tie a proof to a specific call.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Receipt<
+    F: PrimeField + Absorb,
+    CBArgs: Clone,
+    Crypto: AECipherSigZK<F, CBArgs>,
+    S: Signature<F>,
+> where
+    Crypto::Ct: CanonicalSerialize + CanonicalDeserialize,
+{
+    /// The called ticket.
+    pub tik: Crypto::SigPK,
+    /// The encrypted arguments posted with the ticket.
+    pub enc_args: Crypto::Ct,
+    /// The time the ticket was called.
+    pub time: Time<F>,
+    /// The service signature on the digest of the above.
+    pub signature: S::Sig,
+}
+
+impl<F: PrimeField + Absorb, CBArgs: Clone, Crypto: AECipherSigZK<F, CBArgs>, S: Signature<F>>
+    Receipt<F, CBArgs, Crypto, S>
+where
+    Crypto::Ct: CanonicalSerialize + CanonicalDeserialize + ToConstraintField<F>,
+{
+    /// The hash of a ticket, encrypted arguments, and time, which is signed in a receipt.
+    pub fn message<H: FieldHash<F>>(
+        tik: &Crypto::SigPK,
+        enc_args: &Crypto::Ct,
+        time: Time<F>,
+    ) -> F {
+        let mut data = tik.to_field_elements().unwrap();
+        data.extend(enc_args.to_field_elements().unwrap());
+        data.push(time);
+        H::hash(&data)
+    }
+
+    /// Issue a receipt for a call, signed by the service key `sk`.
+    ///
+    /// Returns `None` if signing fails.
+    pub fn issue<H: FieldHash<F>>(
+        sk: &S::Privkey,
+        rng: &mut (impl CryptoRng + RngCore),
+        tik: Crypto::SigPK,
+        enc_args: Crypto::Ct,
+        time: Time<F>,
+    ) -> Option<Self> {
+        let msg = Self::message::<H>(&tik, &enc_args, time);
+        let signature = S::sign(sk, rng, msg)?;
+        Some(Self {
+            tik,
+            enc_args,
+            time,
+            signature,
+        })
+    }
+
+    /// The digest of the receipt, binding the call and the signature.
+    pub fn digest<H: FieldHash<F>>(&self) -> F
+    where
+        S::Sig: ToConstraintField<F>,
+    {
+        let mut data = vec![Self::message::<H>(&self.tik, &self.enc_args, self.time)];
+        data.extend(self.signature.to_field_elements().unwrap());
+        H::hash(&data)
+    }
+
+    /// Check the signature on the receipt under the service key `pk`.
+    pub fn verify<H: FieldHash<F>>(&self, pk: &S::Pubkey) -> bool {
+        S::verify(
+            pk.clone(),
+            self.signature.clone(),
+            Self::message::<H>(&self.tik, &self.enc_args, self.time),
+        )
+    }
+
+    /// Check the receipt as a user holding `ticket`, and decrypt the arguments the service
+    /// applied.
+    ///
+    /// This checks the signature, that the receipt is for the user's ticket, and that the callback
+    /// bulletin holds the same call.
+    pub fn verify_for<H: FieldHash<F>, CBul: PublicCallbackBul<F, CBArgs, Crypto>>(
+        &self,
+        pk: &S::Pubkey,
+        ticket: &CallbackCom<F, CBArgs, Crypto>,
+        cbul: &CBul,
+    ) -> Result<CBArgs, ReceiptError>
+    where
+        Crypto::Ct: PartialEq,
+    {
+        if !self.verify::<H>(pk) {
+            return Err(ReceiptError::BadSignature);
+        }
+        if ticket.get_ticket() != self.tik {
+            return Err(ReceiptError::WrongTicket);
+        }
+        match cbul.verify_in(self.tik.clone()) {
+            Some((enc_args, time)) if enc_args == self.enc_args && time == self.time => {}
+            _ => return Err(ReceiptError::NotPosted),
+        }
+        Ok(ticket.cb_entry.enc_key.decrypt(self.enc_args.clone()))
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    generic::{
+        object::{Time, TimeVar},
+        time::{TimeProvider, Timestamp},
+    },
+    impls::centralized::ds::sig::Signature,
+};
+use ark_ff::PrimeField;
+use ark_r1cs_std::boolean::Boolean;
+use ark_relations::r1cs::Result as ArkResult;
+use rand::{CryptoRng, RngCore};
+
+/// A time provider where a service signs the current epoch.
+///
+/// The service keeps the current epoch, and advances it (for example, once a day, or whenever the
+/// callback bulletin moves to a new epoch). Users fetch a signed timestamp with
+/// [`now`](`TimeProvider::now`), and prove in-circuit that the time of their interaction is
+/// signed by the service.
+///
+/// # Example
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+/// # use ark_relations::r1cs::ConstraintSystem;
+/// # use rand::thread_rng;
+/// # use zk_callbacks::generic::time::TimeProvider;
+/// # use zk_callbacks::impls::centralized::ds::sig::{Signature, gr_schnorr::GrumpkinSchnorr};
+/// # use zk_callbacks::impls::centralized::time::SignedClock;
+/// type Clock = SignedClock<Fr, GrumpkinSchnorr>;
+///
+/// let mut rng = thread_rng();
+/// let mut clock = Clock::new(&mut rng, Fr::from(10));
+/// clock.advance();
+///
+/// let stamp = clock.now(&mut rng);
+/// assert_eq!(stamp.time, Fr::from(11));
+/// assert!(Clock::verify(&clock.authority(), stamp.time, &stamp.attestation));
+/// assert!(!Clock::verify(&clock.authority(), Fr::from(12), &stamp.attestation));
+///
+/// let cs = ConstraintSystem::<Fr>::new_ref();
+/// let pk = <GrumpkinSchnorr as Signature<Fr>>::PubkeyVar::new_input(cs.clone(), || Ok(clock.authority())).unwrap();
+/// let time = FpVar::new_input(cs.clone(), || Ok(stamp.time)).unwrap();
+/// let sig = <GrumpkinSchnorr as Signature<Fr>>::SigVar::new_witness(cs.clone(), || Ok(stamp.attestation)).unwrap();
+/// assert!(Clock::verify_in_zk(&pk, &time, &sig).unwrap().value().unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SignedClock<F: PrimeField, S: Signature<F>> {
+    privkey: S::Privkey,
+
+    /// The public key to verify timestamps.
+    pub pubkey: S::Pubkey,
+
+    /// The current epoch.
+    pub epoch: Time<F>,
+}
+
+impl<F: PrimeField, S: Signature<F>> SignedClock<F, S> {
+    /// Create a new clock starting at `epoch`, with a fresh signing key.
+    pub fn new(rng: &mut (impl CryptoRng + RngCore), epoch: Time<F>) -> Self {
+        Self::from_key(S::gen_key(rng), epoch)
+    }
+
+    /// Create a new clock starting at `epoch`, with an existing signing key.
+    pub fn from_key(privkey: S::Privkey, epoch: Time<F>) -> Self {
+        Self {
+            pubkey: S::get_pubkey(&privkey),
+            privkey,
+            epoch,
+        }
+    }
+
+    /// Move to the next epoch.
+    pub fn advance(&mut self) {
+        self.epoch += F::one();
+    }
+
+    /// Set the current epoch.
+    pub fn set_epoch(&mut self, epoch: Time<F>) {
+        self.epoch = epoch;
+    }
+}
+
+impl<F: PrimeField, S: Signature<F>> TimeProvider<F> for SignedClock<F, S> {
+    type Authority = S::Pubkey;
+
+    type AuthorityVar = S::PubkeyVar;
+
+    type Attestation = S::Sig;
+
+    type AttestationVar = S::SigVar;
+
+    fn authority(&self) -> S::Pubkey {
+        self.pubkey.clone()
+    }
+
+    fn now(&self, rng: &mut (impl CryptoRng + RngCore)) -> Timestamp<F, S::Sig> {
+        Timestamp {
+            time: self.epoch,
+            attestation: S::sign(&self.privkey, rng, self.epoch)
+                .expect("signing a timestamp failed"),
+        }
+    }
+
+    fn verify(authority: &S::Pubkey, time: Time<F>, attestation: &S::Sig) -> bool {
+        S::verify(authority.clone(), attestation.clone(), time)
+    }
+
+    fn verify_in_zk(
+        authority: &S::PubkeyVar,
+        time: &TimeVar<F>,
+        attestation: &S::SigVar,
+    ) -> ArkResult<Boolean<F>> {
+        S::verify_zk(authority.clone(), attestation.clone(), time.clone())
+    }
+}
@@ -0,0 +1,323 @@
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, PrimeGroup};
+use ark_ff::{BigInteger, Field, PrimeField, ToConstraintField, UniformRand, Zero};
+use ark_r1cs_std::{
+    alloc::AllocVar, convert::ToConstraintFieldGadget, fields::fp::FpVar, uint8::UInt8,
+};
+use ark_relations::{ns, r1cs::SynthesisError};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use blake2::{Blake2s256 as Blake, Digest};
+use rand::{distributions::Standard, prelude::Distribution, thread_rng, CryptoRng, RngCore};
+use std::marker::PhantomData;
+
+use crate::{
+    crypto::{
+        audit::AuditableCipher,
+        enc::AECipherSigZK,
+        rr::{RRSigner, RRVerifier},
+    },
+    impls::decentralized::crypto::{Ciphertext, StreamKey, StreamKeyVar},
+};
+
+const BBS_GENERATOR_DOMAIN: &[u8] = b"zk-callbacks bbs+ generator";
+
+/// Derives the `i`th message generator in G1, with unknown discrete logarithm.
+fn generator<E: Pairing>(i: u64) -> E::G1Affine {
+    let mut ctr: u64 = 0;
+    loop {
+        let mut h = Blake::new();
+        h.update(BBS_GENERATOR_DOMAIN);
+        h.update(i.to_le_bytes());
+        h.update(ctr.to_le_bytes());
+        if let Some(p) = E::G1Affine::from_random_bytes(&h.finalize()) {
+            let p = p.clear_cofactor();
+            if !p.is_zero() {
+                return p;
+            }
+        }
+        ctr += 1;
+    }
+}
+
+/// Computes g1 h_0^s prod h_i^m_i, the point signed by a BBS+ signature.
+fn commit_attributes<E: Pairing>(s: E::ScalarField, attrs: &[E::ScalarField]) -> E::G1 {
+    let mut b = E::G1::generator() + generator::<E>(0) * s;
+    for (i, m) in attrs.iter().enumerate() {
+        b += generator::<E>(i as u64 + 1) * m;
+    }
+    b
+}
+
+/// Converts field elements into scalars of the pairing curve, to be signed as attributes.
+fn to_attributes<F: PrimeField, E: Pairing>(msg: &[F]) -> Vec<E::ScalarField> {
+    msg.iter()
+        .map(|m| E::ScalarField::from_le_bytes_mod_order(&m.into_bigint().to_bytes_le()))
+        .collect()
+}
+
+/// A BBS+ signing key. Implements [`RRSigner`].
+pub struct BbsPrivkey<E: Pairing> {
+    sk: E::ScalarField,
+}
+
+/// A BBS+ verification key. Implements [`RRVerifier`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BbsPubkey<E: Pairing> {
+    key: E::G2Affine,
+}
+
+impl<E: Pairing> Default for BbsPubkey<E> {
+    fn default() -> Self {
+        Self {
+            key: E::G2Affine::default(),
+        }
+    }
+}
+
+impl<E: Pairing> CanonicalSerialize for BbsPubkey<E> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.key.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.key.serialized_size(compress)
+    }
+}
+
+impl<E: Pairing> Valid for BbsPubkey<E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.key.check()
+    }
+}
+
+impl<E: Pairing> CanonicalDeserialize for BbsPubkey<E> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            key: E::G2Affine::deserialize_with_mode(reader, compress, validate)?,
+        })
+    }
+}
+
+/// A BBS+ signature on a list of attributes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BbsSig<E: Pairing> {
+    a: E::G1Affine,
+    e: E::ScalarField,
+    s: E::ScalarField,
+}
+
+impl<E: Pairing> BbsPrivkey<E> {
+    /// Sign a list of attributes.
+    pub fn sign_attributes(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        attrs: &[E::ScalarField],
+    ) -> BbsSig<E> {
+        loop {
+            let e = E::ScalarField::rand(rng);
+            let s = E::ScalarField::rand(rng);
+            // A is (g1 h_0^s prod h_i^m_i)^(1 / (x + e))
+            if let Some(inv) = (self.sk + e).inverse() {
+                let a = (commit_attributes::<E>(s, attrs) * inv).into_affine();
+                return BbsSig { a, e, s };
+            }
+        }
+    }
+}
+
+impl<E: Pairing> BbsPubkey<E> {
+    /// Verify a signature on a list of attributes.
+    pub fn verify_attributes(&self, attrs: &[E::ScalarField], sig: &BbsSig<E>) -> bool {
+        if sig.a.is_zero() || self.key.is_zero() {
+            return false;
+        }
+        // Check e(A, w g2^e) = e(g1 h_0^s prod h_i^m_i, g2)
+        let lhs = E::pairing(sig.a, self.key.into_group() + E::G2::generator() * sig.e);
+        let rhs = E::pairing(commit_attributes::<E>(sig.s, attrs), E::G2::generator());
+        lhs == rhs
+    }
+}
+
+impl<F: PrimeField, E: Pairing> ToConstraintField<F> for BbsPubkey<E> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        let mut bytes = vec![];
+        self.key.serialize_compressed(&mut bytes).unwrap();
+        Some(bytes.into_iter().map(|x| F::from(x)).collect::<Vec<_>>())
+    }
+}
+
+/// The BBS+ public key in-circuit.
+#[derive(Clone)]
+pub struct BbsPubkeyVar<F: PrimeField> {
+    key_ser: Vec<UInt8<F>>,
+}
+
+impl<F: PrimeField, E: Pairing> AllocVar<BbsPubkey<E>, F> for BbsPubkeyVar<F> {
+    fn new_variable<T: std::borrow::Borrow<BbsPubkey<E>>>(
+        cs: impl Into<ark_relations::r1cs::Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: ark_r1cs_std::prelude::AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let mut serkey = Vec::new();
+            rec.key.serialize_compressed(&mut serkey).unwrap();
+            let key_ser = <Vec<UInt8<F>>>::new_variable(ns!(cs, "ser_key"), || Ok(serkey), mode)?;
+            Ok(Self { key_ser })
+        })
+    }
+}
+
+impl<F: PrimeField> ToConstraintFieldGadget<F> for BbsPubkeyVar<F> {
+    fn to_constraint_field(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        self.key_ser.to_constraint_field()
+    }
+}
+
+impl<E: Pairing, F: PrimeField, const N: usize>
+    RRVerifier<BbsSig<E>, Ciphertext<F, N>, E::ScalarField> for BbsPubkey<E>
+{
+    fn verify(&self, message: Ciphertext<F, N>, signature: BbsSig<E>) -> bool {
+        self.verify_attributes(&to_attributes::<F, E>(&message.0), &signature)
+    }
+
+    fn rerand(&self, rng: &mut (impl CryptoRng + RngCore)) -> (E::ScalarField, Self) {
+        let f = E::ScalarField::rand(rng);
+        (
+            f,
+            Self {
+                key: (self.key * f).into(),
+            },
+        )
+    }
+}
+
+impl<E: Pairing, F: PrimeField, const N: usize>
+    RRSigner<BbsSig<E>, Ciphertext<F, N>, E::ScalarField, BbsPubkey<E>> for BbsPrivkey<E>
+{
+    type Vk = BbsPubkey<E>;
+
+    fn sign_message(&self, message: &Ciphertext<F, N>) -> BbsSig<E> {
+        self.sign_attributes(&mut thread_rng(), &to_attributes::<F, E>(&message.0))
+    }
+
+    fn sk_to_pk(&self) -> BbsPubkey<E> {
+        BbsPubkey {
+            key: (E::G2::generator() * self.sk).into(),
+        }
+    }
+
+    fn gen(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        let mut sk = E::ScalarField::rand(rng);
+        while sk.is_zero() {
+            sk = E::ScalarField::rand(rng);
+        }
+        Self { sk }
+    }
+
+    fn rerand(&self, randomness: E::ScalarField) -> Self {
+        Self {
+            sk: randomness * self.sk,
+        }
+    }
+}
+
+/// This type implements AECipherSigZK. This uses a Poseidon based stream cipher for encryption of
+/// arguments, and signs each element of the ciphertext as a separate attribute with a BBS+
+/// signature over the pairing `E`.
+///
+/// As with [`StreamSchnorr`](`crate::impls::decentralized::crypto::StreamSchnorr`), the
+/// verification keys are rerandomized per ticket, so signed arguments posted when calling cannot
+/// be linked to the key used at issuance.
+#[derive(Clone, Debug)]
+pub struct StreamBbs<F: PrimeField + Absorb, E: Pairing, const N: usize>
+where
+    [(); N + 1]:,
+{
+    phantom: PhantomData<[F; N]>,
+    phantom_e: PhantomData<E>,
+}
+
+impl<F: PrimeField + Absorb, E: Pairing, const N: usize> AECipherSigZK<F, [F; N]>
+    for StreamBbs<F, E, N>
+where
+    [(); N + 1]:,
+    Standard: Distribution<F>,
+    F: Default,
+{
+    type Ct = Ciphertext<F, { N + 1 }>;
+
+    type AV = [FpVar<F>; N];
+
+    type EncKey = StreamKey<F, N>;
+
+    type EncKeyVar = StreamKeyVar<F, N>;
+
+    type Sig = BbsSig<E>;
+
+    type Rand = E::ScalarField;
+
+    type SigPK = BbsPubkey<E>;
+
+    type SigPKV = BbsPubkeyVar<F>;
+
+    type SigSK = BbsPrivkey<E>;
+}
+
+impl<F: PrimeField + Absorb, E: Pairing, const N: usize> AuditableCipher<F, [F; N]>
+    for StreamBbs<F, E, N>
+where
+    [(); N + 1]:,
+    Standard: Distribution<F>,
+    F: Default,
+{
+    fn args_to_fields(args: &[F; N]) -> Vec<F> {
+        args.to_vec()
+    }
+
+    fn args_to_fields_zk(args: &[FpVar<F>; N]) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(args.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ark_bls12_381::{Bls12_381, Fr};
+
+    // Tests that signatures verify under rerandomized keys, and only on the signed attributes
+    #[test]
+    fn bbs_rerand_verify() {
+        let mut rng = thread_rng();
+
+        let sk = <BbsPrivkey<Bls12_381> as RRSigner<_, Ciphertext<Fr, 3>, _, _>>::gen(&mut rng);
+        let pk = <BbsPrivkey<Bls12_381> as RRSigner<_, Ciphertext<Fr, 3>, _, _>>::sk_to_pk(&sk);
+        let (r, pk2) =
+            <BbsPubkey<Bls12_381> as RRVerifier<_, Ciphertext<Fr, 3>, _>>::rerand(&pk, &mut rng);
+        let sk2 = <BbsPrivkey<Bls12_381> as RRSigner<_, Ciphertext<Fr, 3>, _, _>>::rerand(&sk, r);
+
+        let msg = Ciphertext([Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng)]);
+        let sig = sk2.sign_message(&msg);
+        assert!(pk2.verify(msg.clone(), sig.clone()));
+        assert!(!pk.verify(msg.clone(), sig.clone()));
+
+        let mut other = msg.clone();
+        other.0[1] += Fr::from(1u8);
+        assert!(!pk2.verify(other, sig));
+    }
+}
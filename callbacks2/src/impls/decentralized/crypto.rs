@@ -6,7 +6,8 @@ use ark_crypto_primitives::sponge::{
 use ark_ec::CurveGroup;
 use ark_ff::{Field, PrimeField, ToConstraintField};
 use ark_r1cs_std::{
-    alloc::AllocVar, convert::ToConstraintFieldGadget, fields::fp::FpVar, uint8::UInt8, R1CSVar,
+    alloc::AllocVar, convert::ToConstraintFieldGadget, eq::EqGadget, fields::fp::FpVar,
+    uint8::UInt8, R1CSVar,
 };
 use ark_relations::{ns, r1cs::SynthesisError};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
@@ -16,6 +17,7 @@ use std::marker::PhantomData;
 
 use crate::{
     crypto::{
+        audit::AuditableCipher,
         enc::{AECipherSigZK, CPACipher},
         rr::{RRSigner, RRVerifier},
     },
@@ -95,6 +97,12 @@ impl<F: PrimeField, const K: usize> Default for Ciphertext<F, K> {
     }
 }
 
+impl<F: PrimeField, const K: usize> ToConstraintField<F> for Ciphertext<F, K> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(self.0.to_vec())
+    }
+}
+
 /// The encrypted arguments in-circuit.
 #[derive(Clone)]
 pub struct CiphertextVar<F: PrimeField, const K: usize>(pub [FpVar<F>; K]);
@@ -155,7 +163,7 @@ where
         sponge.absorb(&ciphertext.0[N]);
         let keystream: Vec<F> = sponge.squeeze_field_elements(N);
         let msg = (0..N)
-            .map(|x| ciphertext.0[x] + keystream[x])
+            .map(|x| ciphertext.0[x] - keystream[x])
             .collect::<Vec<_>>();
         msg.try_into().unwrap()
     }
@@ -167,7 +175,7 @@ where
         sponge.absorb(&ciphertext.0[N])?;
         let keystream: Vec<FpVar<F>> = sponge.squeeze_field_elements(N)?;
         let msg = (0..N)
-            .map(|x| ciphertext.0[x].clone() + keystream[x].clone())
+            .map(|x| ciphertext.0[x].clone() - keystream[x].clone())
             .collect::<Vec<_>>();
         Ok(msg.try_into().unwrap())
     }
@@ -235,12 +243,9 @@ pub struct SchnorrSig<E: CurveGroup> {
     response: E::ScalarField,
 }
 
-impl<E: CurveGroup, F: PrimeField, const N: usize>
-    RRVerifier<SchnorrSig<E>, Ciphertext<F, N>, E::ScalarField> for SchnorrPubkey<E>
-where
-    Standard: Distribution<E::ScalarField>,
-{
-    fn verify(&self, message: Ciphertext<F, N>, signature: SchnorrSig<E>) -> bool {
+impl<E: CurveGroup> SchnorrPubkey<E> {
+    // Verifies a signature on a serialized message.
+    pub(crate) fn verify_bytes(&self, msg: &[u8], signature: &SchnorrSig<E>) -> bool {
         let mut claimed_com = self.key * signature.challenge;
         let full = E::generator() * signature.response;
         claimed_com += full;
@@ -248,7 +253,7 @@ where
 
         let mut v = vec![];
         claimed.serialize_compressed(&mut v).unwrap();
-        message.0.serialize_compressed(&mut v).unwrap();
+        v.extend_from_slice(msg);
         let chall = if let Some(claimed_e) = E::ScalarField::from_random_bytes(&Blake::digest(&v)) {
             claimed_e
         } else {
@@ -256,36 +261,21 @@ where
         };
         chall == signature.challenge
     }
-
-    fn rerand(&self, rng: &mut (impl CryptoRng + RngCore)) -> (E::ScalarField, Self) {
-        let f = rng.gen();
-        (
-            f,
-            Self {
-                key: (self.key * f).into(),
-            },
-        )
-    }
 }
 
-impl<E: CurveGroup, F: PrimeField, const N: usize>
-    RRSigner<SchnorrSig<E>, Ciphertext<F, N>, E::ScalarField, SchnorrPubkey<E>>
-    for SchnorrPrivkey<E>
+impl<E: CurveGroup> SchnorrPrivkey<E>
 where
     Standard: Distribution<E::ScalarField>,
-    E::ScalarField: Absorb,
-    F: CanonicalSerialize,
 {
-    type Vk = SchnorrPubkey<E>;
-
-    fn sign_message(&self, message: &Ciphertext<F, N>) -> SchnorrSig<E> {
+    // Signs a serialized message.
+    pub(crate) fn sign_bytes(&self, msg: &[u8]) -> SchnorrSig<E> {
         let (rand, chall) = loop {
             let mut rng = thread_rng();
             let randomness = rng.gen();
             let com = E::generator() * randomness;
             let mut v = vec![];
             com.serialize_compressed(&mut v).unwrap();
-            message.0.serialize_compressed(&mut v).unwrap();
+            v.extend_from_slice(msg);
             if let Some(challenge) = E::ScalarField::from_random_bytes(&Blake::digest(&v)) {
                 break (randomness, challenge);
             }
@@ -297,23 +287,90 @@ where
         }
     }
 
-    fn sk_to_pk(&self) -> SchnorrPubkey<E> {
+    // Gets the public key.
+    pub(crate) fn pubkey(&self) -> SchnorrPubkey<E> {
         SchnorrPubkey {
             key: (E::generator() * self.sk).into(),
         }
     }
 
-    fn gen(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+    // Samples a signing key.
+    pub(crate) fn sample(rng: &mut (impl CryptoRng + RngCore)) -> Self {
         Self { sk: rng.gen() }
     }
 
-    fn rerand(&self, randomness: E::ScalarField) -> Self {
+    // Rerandomizes the signing key.
+    pub(crate) fn rerandomize(&self, randomness: E::ScalarField) -> Self {
         Self {
             sk: randomness * self.sk,
         }
     }
 }
 
+impl<E: CurveGroup> SchnorrPubkey<E>
+where
+    Standard: Distribution<E::ScalarField>,
+{
+    // Rerandomizes the verification key.
+    pub(crate) fn rerandomize(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> (E::ScalarField, Self) {
+        let f = rng.gen();
+        (
+            f,
+            Self {
+                key: (self.key * f).into(),
+            },
+        )
+    }
+}
+
+impl<E: CurveGroup, F: PrimeField, const N: usize>
+    RRVerifier<SchnorrSig<E>, Ciphertext<F, N>, E::ScalarField> for SchnorrPubkey<E>
+where
+    Standard: Distribution<E::ScalarField>,
+{
+    fn verify(&self, message: Ciphertext<F, N>, signature: SchnorrSig<E>) -> bool {
+        let mut msg = vec![];
+        message.0.serialize_compressed(&mut msg).unwrap();
+        self.verify_bytes(&msg, &signature)
+    }
+
+    fn rerand(&self, rng: &mut (impl CryptoRng + RngCore)) -> (E::ScalarField, Self) {
+        self.rerandomize(rng)
+    }
+}
+
+impl<E: CurveGroup, F: PrimeField, const N: usize>
+    RRSigner<SchnorrSig<E>, Ciphertext<F, N>, E::ScalarField, SchnorrPubkey<E>>
+    for SchnorrPrivkey<E>
+where
+    Standard: Distribution<E::ScalarField>,
+    E::ScalarField: Absorb,
+    F: CanonicalSerialize,
+{
+    type Vk = SchnorrPubkey<E>;
+
+    fn sign_message(&self, message: &Ciphertext<F, N>) -> SchnorrSig<E> {
+        let mut msg = vec![];
+        message.0.serialize_compressed(&mut msg).unwrap();
+        self.sign_bytes(&msg)
+    }
+
+    fn sk_to_pk(&self) -> SchnorrPubkey<E> {
+        self.pubkey()
+    }
+
+    fn gen(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        Self::sample(rng)
+    }
+
+    fn rerand(&self, randomness: E::ScalarField) -> Self {
+        self.rerandomize(randomness)
+    }
+}
+
 /// This type implements AECipherSigZK. This uses a Poseidon based stream cipher for encryption of
 /// arguments, and signs arguments with a Schnorr signature (which is rerandomizable!).
 ///
@@ -356,3 +413,301 @@ where
 
     type SigSK = SchnorrPrivkey<E>;
 }
+
+impl<F: PrimeField + Absorb, E: CurveGroup, const N: usize> AuditableCipher<F, [F; N]>
+    for StreamSchnorr<F, E, N>
+where
+    [(); N + 1]:,
+    Standard: Distribution<F>,
+    Standard: Distribution<E::ScalarField>,
+    E::ScalarField: Absorb,
+    F: Default,
+{
+    fn args_to_fields(args: &[F; N]) -> Vec<F> {
+        args.to_vec()
+    }
+
+    fn args_to_fields_zk(args: &[FpVar<F>; N]) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(args.to_vec())
+    }
+}
+
+/// Encryption key (IND-CPA) for a Poseidon-based stream cipher, which is bound to a service.
+///
+/// Along with the arguments, each ciphertext encrypts the identity of the service the key is bound
+/// to, and decryption in-circuit checks it. So while the user can check which service called a
+/// ticket, observers of the callback bulletin only see uniformly random ciphertexts.
+#[derive(
+    Clone, Debug, PartialEq, Eq, Default, CanonicalSerialize, CanonicalDeserialize, PartialOrd, Ord,
+)]
+pub struct ServiceBoundKey<F: CanonicalSerialize + CanonicalDeserialize, const N: usize> {
+    key: F,
+    service: F,
+    phantom_max_size: PhantomData<[(); N]>,
+}
+
+impl<F: PrimeField, const N: usize> ServiceBoundKey<F, N> {
+    /// Construct a new encryption key from a field element, bound to a service identity.
+    pub fn new(key: F, service: F) -> Self {
+        Self {
+            key,
+            service,
+            phantom_max_size: PhantomData,
+        }
+    }
+
+    /// Bind the key to a service identity.
+    ///
+    /// Keys from [`CPACipher::keygen`] are bound to the zero identity.
+    pub fn bind(self, service: F) -> Self {
+        Self { service, ..self }
+    }
+
+    /// Get the identity of the service the key is bound to.
+    pub fn service(&self) -> F {
+        self.service
+    }
+
+    /// Decrypt the service identity from a ciphertext.
+    pub fn decrypt_service<const K: usize>(&self, ciphertext: &Ciphertext<F, K>) -> F
+    where
+        F: Absorb,
+    {
+        let keystream = stream_keystream(&self.key, &ciphertext.0[K - 1], N + 1);
+        ciphertext.0[N] - keystream[N]
+    }
+}
+
+impl<F: PrimeField, const N: usize> ToConstraintField<F> for ServiceBoundKey<F, N> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(vec![self.key, self.service])
+    }
+}
+
+/// Service bound encryption key in-circuit.
+#[derive(Clone)]
+pub struct ServiceBoundKeyVar<F: PrimeField, const N: usize> {
+    key: FpVar<F>,
+    service: FpVar<F>,
+    phantom_max_size: PhantomData<[(); N]>,
+}
+
+impl<F: PrimeField, const N: usize> AllocVar<ServiceBoundKey<F, N>, F>
+    for ServiceBoundKeyVar<F, N>
+{
+    fn new_variable<T: std::borrow::Borrow<ServiceBoundKey<F, N>>>(
+        cs: impl Into<ark_relations::r1cs::Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: ark_r1cs_std::prelude::AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let key = FpVar::new_variable(ns!(cs, "key"), || Ok(rec.key), mode)?;
+            let service = FpVar::new_variable(ns!(cs, "service"), || Ok(rec.service), mode)?;
+            Ok(Self {
+                key,
+                service,
+                phantom_max_size: PhantomData,
+            })
+        })
+    }
+}
+
+impl<F: PrimeField, const N: usize> ToConstraintFieldGadget<F> for ServiceBoundKeyVar<F, N> {
+    fn to_constraint_field(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(vec![self.key.clone(), self.service.clone()])
+    }
+}
+
+// Derives a keystream of length `len` from a key and nonce.
+fn stream_keystream<F: PrimeField + Absorb>(key: &F, nonce: &F, len: usize) -> Vec<F> {
+    let mut sponge: PoseidonSponge<F> = PoseidonSponge::new(&gen_poseidon_params(2, false));
+    sponge.absorb(key);
+    sponge.absorb(nonce);
+    sponge.squeeze_field_elements(len)
+}
+
+impl<F: PrimeField + Absorb, const N: usize> CPACipher<F> for ServiceBoundKey<F, N>
+where
+    Standard: Distribution<F>,
+    [(); N + 2]:,
+{
+    type M = [F; N];
+    type C = Ciphertext<F, { N + 2 }>;
+    type MV = [FpVar<F>; N];
+    type CV = CiphertextVar<F, { N + 2 }>;
+
+    type KeyVar = ServiceBoundKeyVar<F, N>;
+
+    fn keygen(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        Self::new(rng.gen(), F::ZERO)
+    }
+
+    fn encrypt(&self, message: Self::M) -> Self::C {
+        let mut rng = thread_rng();
+        let nonce: F = rng.gen();
+        let keystream = stream_keystream(&self.key, &nonce, N + 1);
+        let mut ct = (0..N)
+            .map(|x| message[x] + keystream[x])
+            .collect::<Vec<_>>();
+        ct.push(self.service + keystream[N]);
+        ct.push(nonce);
+        Ciphertext(ct.try_into().unwrap())
+    }
+
+    fn decrypt(&self, ciphertext: Self::C) -> Self::M {
+        let keystream = stream_keystream(&self.key, &ciphertext.0[N + 1], N);
+        let msg = (0..N)
+            .map(|x| ciphertext.0[x] - keystream[x])
+            .collect::<Vec<_>>();
+        msg.try_into().unwrap()
+    }
+
+    fn decrypt_in_zk(key: Self::KeyVar, ciphertext: Self::CV) -> Result<Self::MV, SynthesisError> {
+        let mut sponge =
+            PoseidonSpongeVar::new(ciphertext.0[N + 1].cs(), &gen_poseidon_params(2, false));
+        sponge.absorb(&key.key)?;
+        sponge.absorb(&ciphertext.0[N + 1])?;
+        let keystream: Vec<FpVar<F>> = sponge.squeeze_field_elements(N + 1)?;
+
+        // The ciphertext must be from the service the key is bound to
+        (ciphertext.0[N].clone() - keystream[N].clone()).enforce_equal(&key.service)?;
+
+        let msg = (0..N)
+            .map(|x| ciphertext.0[x].clone() - keystream[x].clone())
+            .collect::<Vec<_>>();
+        Ok(msg.try_into().unwrap())
+    }
+}
+
+/// This type implements AECipherSigZK. This is as
+/// [`StreamSchnorr`], but the encryption keys are bound to a service identity, which is encrypted
+/// along with the arguments.
+///
+/// With rerandomized Schnorr keys as tickets, a called ticket on the callback bulletin reveals
+/// nothing about which service issued or called it, as long as all services use the same number of
+/// arguments `N`. Users still check in-circuit that the ticket was called by the bound service.
+#[derive(Clone, Debug)]
+pub struct KeyPrivateSchnorr<F: PrimeField + Absorb, E: CurveGroup, const N: usize>
+where
+    [(); N + 2]:,
+{
+    phantom: PhantomData<[F; N]>,
+    phantom_e: PhantomData<E>,
+}
+
+impl<F: PrimeField + Absorb, E: CurveGroup, const N: usize> AECipherSigZK<F, [F; N]>
+    for KeyPrivateSchnorr<F, E, N>
+where
+    [(); N + 2]:,
+    Standard: Distribution<F>,
+    Standard: Distribution<E::ScalarField>,
+    E::ScalarField: Absorb,
+    F: Default,
+{
+    type Ct = Ciphertext<F, { N + 2 }>;
+
+    type AV = [FpVar<F>; N];
+
+    type EncKey = ServiceBoundKey<F, N>;
+
+    type EncKeyVar = ServiceBoundKeyVar<F, N>;
+
+    type Sig = SchnorrSig<E>;
+
+    type Rand = E::ScalarField;
+
+    type SigPK = SchnorrPubkey<E>;
+
+    type SigPKV = SchnorrPubkeyVar<F>;
+
+    type SigSK = SchnorrPrivkey<E>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ec::PrimeGroup;
+    use ark_grumpkin::Projective as Grumpkin;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    type Kp = KeyPrivateSchnorr<Fr, Grumpkin, 2>;
+
+    type Rand = <Grumpkin as PrimeGroup>::ScalarField;
+
+    // Generates a public key, along with a rerandomized key pair
+    fn rerand_keys<
+        S: RRSigner<SchnorrSig<Grumpkin>, Ciphertext<Fr, 4>, Rand, SchnorrPubkey<Grumpkin>>,
+    >(
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> (SchnorrPubkey<Grumpkin>, S, SchnorrPubkey<Grumpkin>) {
+        let sk = S::gen(rng);
+        let pk = sk.sk_to_pk();
+        let (rand, rpk) =
+            <SchnorrPubkey<Grumpkin> as RRVerifier<_, Ciphertext<Fr, 4>, _>>::rerand(&pk, rng);
+        let rsk = sk.rerand(rand);
+        (pk, rsk, rpk)
+    }
+
+    fn decrypts_in_zk<K: CPACipher<Fr, MV = [FpVar<Fr>; 2]>>(
+        key: &K,
+        ct: &K::C,
+    ) -> Option<[Fr; 2]> {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let key_var = K::KeyVar::new_witness(ns!(cs, "key"), || Ok(key)).unwrap();
+        let ct_var = K::CV::new_input(ns!(cs, "ct"), || Ok(ct)).unwrap();
+        let msg = K::decrypt_in_zk(key_var, ct_var).unwrap();
+        cs.is_satisfied()
+            .unwrap()
+            .then(|| [msg[0].value().unwrap(), msg[1].value().unwrap()])
+    }
+
+    // Tests the stream cipher decrypts its own ciphertexts, natively and in-circuit
+    #[test]
+    fn stream_roundtrip() {
+        let key = StreamKey::<Fr, 2>::keygen(&mut thread_rng());
+        let msg = [Fr::from(5), -Fr::from(3)];
+        let ct = key.encrypt(msg);
+        assert_eq!(key.decrypt(ct.clone()), msg);
+        assert_eq!(decrypts_in_zk(&key, &ct), Some(msg));
+    }
+
+    // Tests a service bound key decrypts the arguments and the service, and a key bound to another
+    // service cannot decrypt in-circuit
+    #[test]
+    fn service_bound_roundtrip() {
+        let key = ServiceBoundKey::<Fr, 2>::keygen(&mut thread_rng()).bind(Fr::from(7));
+        assert_eq!(key.service(), Fr::from(7));
+
+        let msg = [Fr::from(5), -Fr::from(3)];
+        let ct = key.encrypt(msg);
+        assert_eq!(key.decrypt(ct.clone()), msg);
+        assert_eq!(key.decrypt_service(&ct), Fr::from(7));
+        assert_eq!(decrypts_in_zk(&key, &ct), Some(msg));
+
+        let other = key.clone().bind(Fr::from(8));
+        assert_eq!(decrypts_in_zk(&other, &ct), None);
+    }
+
+    // Tests ciphertexts under the same key are unlinkable, and signatures verify under a
+    // rerandomized key
+    #[test]
+    fn key_private_sign() {
+        let mut rng = thread_rng();
+        let key = ServiceBoundKey::<Fr, 2>::keygen(&mut rng).bind(Fr::from(7));
+        let msg = [Fr::from(1), Fr::from(2)];
+        let (a, b) = (key.encrypt(msg), key.encrypt(msg));
+        assert!(a.0.iter().zip(b.0.iter()).all(|(x, y)| x != y));
+
+        let (pk, rsk, rpk) = rerand_keys::<SchnorrPrivkey<Grumpkin>>(&mut rng);
+        let (ct, sig) = Kp::encrypt_and_sign(msg, key.clone(), rsk);
+        assert!(rpk.verify(ct.clone(), sig.clone()));
+        assert!(!pk.verify(ct.clone(), sig.clone()));
+        assert!(!rpk.verify(a, sig));
+        assert_eq!(key.decrypt_service(&ct), Fr::from(7));
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+
+use crate::{
+    generic::{
+        bulletin::{CallbackBul, PublicCallbackBul},
+        object::{Time, TimeVar},
+    },
+    impls::{
+        centralized::crypto::{FakeSigPubkey, FakeSigPubkeyVar, NoSigOTP},
+        decentralized::ds::treestore::{
+            IndexedCallbackStore, IndexedMembWitness, IndexedMembWitnessVar, IndexedNonMembWitness,
+            IndexedNonMembWitnessVar,
+        },
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::Boolean};
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate,
+};
+use blake2::{Blake2s256 as Blake, Digest};
+use rand::distributions::{Distribution, Standard};
+
+/// A content-addressed blob storage layer, such as a data-availability layer or IPFS.
+pub trait BlobStore {
+    /// The content identifier of a blob.
+    type Id: Clone + Eq + std::fmt::Debug;
+
+    /// The error when storing or fetching blobs.
+    type Error: std::fmt::Debug;
+
+    /// Store a blob, returning its content identifier.
+    fn put(&mut self, blob: &[u8]) -> Result<Self::Id, Self::Error>;
+
+    /// Fetch a blob by its content identifier.
+    fn get(&self, id: &Self::Id) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// An in-memory [`BlobStore`], addressed by the Blake2s hash of each blob.
+///
+/// This is mostly useful for testing, or as a local cache in front of a remote layer.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBlobStore {
+    /// The stored blobs.
+    pub blobs: HashMap<[u8; 32], Vec<u8>>,
+}
+
+impl BlobStore for MemoryBlobStore {
+    type Id = [u8; 32];
+
+    type Error = ();
+
+    fn put(&mut self, blob: &[u8]) -> Result<[u8; 32], ()> {
+        let id: [u8; 32] = Blake::digest(blob).into();
+        self.blobs.insert(id, blob.to_vec());
+        Ok(id)
+    }
+
+    fn get(&self, id: &[u8; 32]) -> Result<Vec<u8>, ()> {
+        self.blobs.get(id).cloned().ok_or(())
+    }
+}
+
+/// A [`BlobStore`] backed by an IPFS node, through the Kubo RPC API.
+///
+/// Blobs are added with `/api/v0/add` and fetched with `/api/v0/cat`, and identified by their
+/// CID.
+#[cfg(feature = "ipfs")]
+#[cfg(any(feature = "ipfs", doc))]
+#[doc(cfg(feature = "ipfs"))]
+#[derive(Clone, Debug)]
+pub struct IpfsBlobStore {
+    /// The HTTP client used to talk to the node.
+    pub client: reqwest::blocking::Client,
+    /// The base url of the RPC API of the node, such as `http://127.0.0.1:5001`.
+    pub api: String,
+}
+
+#[cfg(feature = "ipfs")]
+#[cfg(any(feature = "ipfs", doc))]
+#[doc(cfg(feature = "ipfs"))]
+impl IpfsBlobStore {
+    /// Construct a new store talking to a node.
+    pub fn new(api: String) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            api,
+        }
+    }
+}
+
+/// An error when talking to an IPFS node.
+#[cfg(feature = "ipfs")]
+#[cfg(any(feature = "ipfs", doc))]
+#[doc(cfg(feature = "ipfs"))]
+#[derive(Debug)]
+pub enum IpfsError {
+    /// The request could not be sent, or the response could not be read.
+    Request(reqwest::Error),
+    /// The node responded with a non-success status.
+    Status(reqwest::StatusCode),
+    /// The response was not in the expected format.
+    Malformed,
+}
+
+#[cfg(feature = "ipfs")]
+#[cfg(any(feature = "ipfs", doc))]
+#[doc(cfg(feature = "ipfs"))]
+impl BlobStore for IpfsBlobStore {
+    type Id = String;
+
+    type Error = IpfsError;
+
+    fn put(&mut self, blob: &[u8]) -> Result<String, IpfsError> {
+        let part = reqwest::blocking::multipart::Part::bytes(blob.to_vec());
+        let form = reqwest::blocking::multipart::Form::new().part("file", part);
+        let res = self
+            .client
+            .post(format!("{}/api/v0/add?pin=true", self.api))
+            .multipart(form)
+            .send()
+            .map_err(IpfsError::Request)?;
+        if !res.status().is_success() {
+            return Err(IpfsError::Status(res.status()));
+        }
+        let bytes = res.bytes().map_err(IpfsError::Request)?;
+        let v: serde_json::Value =
+            serde_json::from_slice(&bytes).map_err(|_| IpfsError::Malformed)?;
+        v.get("Hash")
+            .and_then(serde_json::Value::as_str)
+            .map(String::from)
+            .ok_or(IpfsError::Malformed)
+    }
+
+    fn get(&self, id: &String) -> Result<Vec<u8>, IpfsError> {
+        let res = self
+            .client
+            .post(format!("{}/api/v0/cat?arg={}", self.api, id))
+            .send()
+            .map_err(IpfsError::Request)?;
+        if !res.status().is_success() {
+            return Err(IpfsError::Status(res.status()));
+        }
+        Ok(res.bytes().map_err(IpfsError::Request)?.to_vec())
+    }
+}
+
+/// An error in a [`DaCallbackStore`].
+#[derive(Debug)]
+pub enum DaError<E> {
+    /// The blob layer failed.
+    Blob(E),
+    /// A fetched blob does not match the digest stored locally.
+    Corrupted(usize),
+    /// A blob could not be serialized or deserialized.
+    Serialization(SerializationError),
+    /// The ticket could not be inserted into the tree.
+    Store,
+}
+
+/// A callback bulletin which keeps called tickets and their arguments on a blob layer.
+///
+/// Each call is posted to a [`BlobStore`] as a blob containing the ticket, (encrypted) arguments
+/// and time. Locally, only the content identifier and a digest of each blob are kept, so storage
+/// does not grow with the size of the arguments.
+///
+/// Membership data is reconstructed by fetching all blobs and rebuilding an
+/// [`IndexedCallbackStore`]. Optionally, the rebuilt store can be kept as a cache with
+/// [`DaCallbackStore::warm`], in which case it is kept up to date on each call.
+///
+/// # Panics
+///
+/// Without a cache, every bulletin query fetches all blobs, and panics if the blob layer fails.
+#[derive(Clone, Debug)]
+pub struct DaCallbackStore<F: PrimeField + Absorb, B: BlobStore, const D: usize> {
+    /// The blob layer.
+    pub blobs: B,
+    /// The content identifiers and digests of the calls, in order.
+    pub ids: Vec<(B::Id, [u8; 32])>,
+    /// A cache of the rebuilt store.
+    pub cache: Option<IndexedCallbackStore<F, F, D>>,
+}
+
+impl<F: PrimeField + Absorb, B: BlobStore, const D: usize> DaCallbackStore<F, B, D> {
+    /// Construct a new empty store on a blob layer.
+    pub fn new(blobs: B) -> Self {
+        Self {
+            blobs,
+            ids: vec![],
+            cache: None,
+        }
+    }
+
+    /// Fetch all calls from the blob layer and rebuild the store.
+    pub fn rebuild(&self) -> Result<IndexedCallbackStore<F, F, D>, DaError<B::Error>> {
+        let mut store = IndexedCallbackStore::new();
+        for (i, (id, digest)) in self.ids.iter().enumerate() {
+            let blob = self.blobs.get(id).map_err(DaError::Blob)?;
+            if <[u8; 32]>::from(Blake::digest(&blob)) != *digest {
+                return Err(DaError::Corrupted(i));
+            }
+            let (tik, args, time) =
+                <(F, F, F)>::deserialize_with_mode(&*blob, Compress::No, Validate::Yes)
+                    .map_err(DaError::Serialization)?;
+            store
+                .insert(FakeSigPubkey::new(tik), args, time)
+                .map_err(|_| DaError::Store)?;
+        }
+        Ok(store)
+    }
+
+    /// Rebuild the store and keep it as a cache.
+    pub fn warm(&mut self) -> Result<(), DaError<B::Error>> {
+        self.cache = Some(self.rebuild()?);
+        Ok(())
+    }
+
+    /// Drop the cached store.
+    pub fn cool(&mut self) {
+        self.cache = None;
+    }
+
+    fn with_store<T>(&self, f: impl FnOnce(&IndexedCallbackStore<F, F, D>) -> T) -> T {
+        match &self.cache {
+            Some(s) => f(s),
+            None => f(&self.rebuild().expect("could not rebuild store from blobs")),
+        }
+    }
+
+    /// Post a call to the blob layer.
+    pub fn post(
+        &mut self,
+        tik: FakeSigPubkey<F>,
+        args: F,
+        time: Time<F>,
+    ) -> Result<(), DaError<B::Error>> {
+        let mut blob = vec![];
+        (tik.to(), args, time)
+            .serialize_with_mode(&mut blob, Compress::No)
+            .map_err(DaError::Serialization)?;
+        let id = self.blobs.put(&blob).map_err(DaError::Blob)?;
+        if let Some(s) = &mut self.cache {
+            s.insert(tik, args, time).map_err(|_| DaError::Store)?;
+        }
+        self.ids.push((id, Blake::digest(&blob).into()));
+        Ok(())
+    }
+}
+
+impl<F: PrimeField + Absorb, B: BlobStore, const D: usize> PublicCallbackBul<F, F, NoSigOTP<F>>
+    for DaCallbackStore<F, B, D>
+where
+    Standard: Distribution<F>,
+{
+    type MembershipWitness = IndexedMembWitness<F, D>;
+
+    type MembershipWitnessVar = IndexedMembWitnessVar<F, D>;
+
+    type NonMembershipWitness = IndexedNonMembWitness<F, D>;
+
+    type NonMembershipWitnessVar = IndexedNonMembWitnessVar<F, D>;
+
+    type MembershipPub = F;
+
+    type MembershipPubVar = FpVar<F>;
+
+    type NonMembershipPub = F;
+
+    type NonMembershipPubVar = FpVar<F>;
+
+    fn verify_in(&self, tik: FakeSigPubkey<F>) -> Option<(F, Time<F>)> {
+        self.with_store(|s| s.get_called(&tik))
+    }
+
+    fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
+        self.with_store(|s| s.get_nmemb_witness(&tik).is_some())
+    }
+
+    fn get_membership_data(
+        &self,
+        tik: FakeSigPubkey<F>,
+    ) -> (F, IndexedMembWitness<F, D>, F, IndexedNonMembWitness<F, D>) {
+        self.with_store(|s| {
+            <IndexedCallbackStore<F, F, D> as PublicCallbackBul<F, F, NoSigOTP<F>>>::get_membership_data(s, tik)
+        })
+    }
+
+    fn enforce_membership_of(
+        tikvar: (FakeSigPubkeyVar<F>, FpVar<F>, TimeVar<F>),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <IndexedCallbackStore<F, F, D> as PublicCallbackBul<F, F, NoSigOTP<F>>>::enforce_membership_of(
+            tikvar,
+            extra_witness,
+            extra_pub,
+        )
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: FakeSigPubkeyVar<F>,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        IndexedCallbackStore::<F, F, D>::enforce_nonmembership(tikvar, extra_witness, extra_pub)
+    }
+}
+
+impl<F: PrimeField + Absorb, B: BlobStore, const D: usize> CallbackBul<F, F, NoSigOTP<F>>
+    for DaCallbackStore<F, B, D>
+where
+    Standard: Distribution<F>,
+{
+    type Error = DaError<B::Error>;
+
+    fn has_never_received_tik(&self, tik: &FakeSigPubkey<F>) -> bool {
+        self.with_store(|s| s.get_called(tik).is_none())
+    }
+
+    fn append_value(
+        &mut self,
+        tik: FakeSigPubkey<F>,
+        enc_args: F,
+        _signature: (),
+        time: Time<F>,
+    ) -> Result<(), Self::Error> {
+        self.post(tik, enc_args, time)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bn254::Fr;
+
+    type Store<B> = DaCallbackStore<Fr, B, 4>;
+
+    fn calls() -> [(FakeSigPubkey<Fr>, Fr, Time<Fr>); 3] {
+        [(30, 1), (10, 2), (20, 3)].map(|(t, a)| {
+            (
+                FakeSigPubkey::new(Fr::from(t)),
+                Fr::from(a),
+                Fr::from(a + 100),
+            )
+        })
+    }
+
+    // Tests that queries agree with a local indexed store, with and without the cache
+    #[test]
+    fn da_rebuild() {
+        let mut store = Store::new(MemoryBlobStore::default());
+        let mut expected = IndexedCallbackStore::<Fr, Fr, 4>::new();
+        for (tik, args, time) in calls() {
+            <Store<MemoryBlobStore> as CallbackBul<Fr, Fr, NoSigOTP<Fr>>>::append_value(
+                &mut store,
+                tik.clone(),
+                args,
+                (),
+                time,
+            )
+            .unwrap();
+            expected.insert(tik, args, time).unwrap();
+        }
+        assert_eq!(store.blobs.blobs.len(), 3);
+
+        let rebuilt = store.rebuild().unwrap();
+        assert_eq!(rebuilt.get_root(), expected.get_root());
+
+        let cold = store.get_membership_data(FakeSigPubkey::new(Fr::from(10)));
+        store.warm().unwrap();
+        let warm = store.get_membership_data(FakeSigPubkey::new(Fr::from(10)));
+        assert_eq!(cold.0, warm.0);
+        assert_eq!(cold.1.path, warm.1.path);
+
+        // Calls made while warm keep the cache up to date
+        let tik = FakeSigPubkey::new(Fr::from(15));
+        store.post(tik.clone(), Fr::from(4), Fr::from(0)).unwrap();
+        expected
+            .insert(tik.clone(), Fr::from(4), Fr::from(0))
+            .unwrap();
+        assert_eq!(
+            store.cache.as_ref().unwrap().get_root(),
+            expected.get_root()
+        );
+        store.cool();
+        assert_eq!(
+            store.verify_in(tik.clone()),
+            Some((Fr::from(4), Fr::from(0)))
+        );
+        assert!(!store.verify_not_in(tik));
+        assert!(store.verify_not_in(FakeSigPubkey::new(Fr::from(16))));
+    }
+
+    // Tests that a blob changed on the blob layer is detected when rebuilding
+    #[test]
+    fn da_corrupted_blob() {
+        let mut store = Store::new(MemoryBlobStore::default());
+        for (tik, args, time) in calls() {
+            store.post(tik, args, time).unwrap();
+        }
+
+        let (id, _) = store.ids[1];
+        store.blobs.blobs.get_mut(&id).unwrap()[0] ^= 1;
+        assert!(matches!(store.rebuild(), Err(DaError::Corrupted(1))));
+
+        store.blobs.blobs.remove(&id);
+        assert!(matches!(store.rebuild(), Err(DaError::Blob(()))));
+    }
+
+    // Tests the IPFS store against a local node which keeps added files in memory
+    #[cfg(feature = "ipfs")]
+    #[test]
+    fn da_ipfs_roundtrip() {
+        use std::{
+            io::{BufRead, BufReader, Read, Write},
+            net::TcpListener,
+            thread,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_string();
+                let mut len = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header == "\r\n" || header.is_empty() {
+                        break;
+                    }
+                    if let Some(v) = header.to_lowercase().strip_prefix("content-length:") {
+                        len = v.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+
+                let (status, out) = if path.starts_with("/api/v0/add") {
+                    // The file is the only part of the form
+                    let start = body.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+                    let end = body.windows(4).rposition(|w| w == b"\r\n--").unwrap();
+                    let cid = format!("cid{}", files.len());
+                    files.insert(cid.clone(), body[start..end].to_vec());
+                    ("200 OK", format!("{{\"Hash\":\"{}\"}}", cid).into_bytes())
+                } else {
+                    let cid = path.rsplit("arg=").next().unwrap_or_default();
+                    match files.get(cid) {
+                        Some(f) => ("200 OK", f.clone()),
+                        None => ("500 Internal Server Error", vec![]),
+                    }
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    out.len()
+                )
+                .unwrap();
+                stream.write_all(&out).unwrap();
+            }
+        });
+
+        let mut store = Store::new(IpfsBlobStore::new(format!("http://{}", addr)));
+        for (tik, args, time) in calls() {
+            store.post(tik, args, time).unwrap();
+        }
+        assert_eq!(store.ids[2].0, "cid2");
+        assert_eq!(
+            store.verify_in(FakeSigPubkey::new(Fr::from(20))),
+            Some((Fr::from(3), Fr::from(103)))
+        );
+
+        assert!(matches!(
+            store.blobs.get(&"missing".to_string()),
+            Err(IpfsError::Status(_))
+        ));
+    }
+}
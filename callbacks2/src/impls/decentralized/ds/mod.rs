@@ -1,6 +1,9 @@
 /// A Merkle tree based storage system. Membership verification is given through Merkle path
-/// proofs.
+/// proofs, and nonmembership of callback tickets through sorted (indexed) leaves.
 pub mod treestore;
 
 /// Merkle tree proofs.
 pub mod tree;
+
+/// Tracking of Merkle witnesses across bulletin updates.
+pub mod witness;
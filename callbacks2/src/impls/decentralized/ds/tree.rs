@@ -1 +1,273 @@
+use crate::crypto::hash::FieldHash;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, prelude::Boolean, select::CondSelectGadget,
+};
+use ark_relations::{ns, r1cs::SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::{collections::HashMap, marker::PhantomData};
 
+/// A sparse Merkle tree of fixed depth `D`.
+///
+/// The tree has `2^D` leaves, all of which are initially zero. Only the nodes which differ from
+/// the empty tree are stored. Nodes are hashed as `H(left, right)`.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<F: PrimeField, H: FieldHash<F>, const D: usize> {
+    /// The roots of the empty subtrees at each level, with level 0 being the leaves.
+    zeros: Vec<F>,
+    /// The nonempty nodes at each level, with level 0 being the leaves.
+    nodes: Vec<HashMap<u64, F>>,
+    _phantom: PhantomData<H>,
+}
+
+impl<F: PrimeField, H: FieldHash<F>, const D: usize> Default for MerkleTree<F, H, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField, H: FieldHash<F>, const D: usize> MerkleTree<F, H, D> {
+    /// Construct a new empty tree.
+    pub fn new() -> Self {
+        assert!(D < 64, "tree depth must be less than 64");
+        let mut zeros = vec![F::ZERO];
+        for i in 0..D {
+            zeros.push(H::hash(&[zeros[i], zeros[i]]));
+        }
+        Self {
+            zeros,
+            nodes: vec![HashMap::new(); D + 1],
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The number of leaves in the tree.
+    pub fn capacity() -> u64 {
+        1 << D
+    }
+
+    fn node(&self, level: usize, index: u64) -> F {
+        self.nodes[level]
+            .get(&index)
+            .copied()
+            .unwrap_or(self.zeros[level])
+    }
+
+    /// Get the root of the tree.
+    pub fn root(&self) -> F {
+        self.node(D, 0)
+    }
+
+    /// Get the leaf at some index.
+    pub fn get(&self, index: u64) -> F {
+        self.node(0, index)
+    }
+
+    /// Set the leaf at some index, updating the path to the root.
+    ///
+    /// Returns `None` if the index is out of range.
+    pub fn set(&mut self, index: u64, leaf: F) -> Option<()> {
+        if index >= Self::capacity() {
+            return None;
+        }
+        let mut ind = index;
+        let mut cur = leaf;
+        self.nodes[0].insert(ind, cur);
+        for level in 0..D {
+            let sib = self.node(level, ind ^ 1);
+            cur = if ind & 1 == 0 {
+                H::hash(&[cur, sib])
+            } else {
+                H::hash(&[sib, cur])
+            };
+            ind >>= 1;
+            self.nodes[level + 1].insert(ind, cur);
+        }
+        Some(())
+    }
+
+    /// Get the authentication path for the leaf at some index.
+    ///
+    /// Returns `None` if the index is out of range.
+    pub fn path(&self, index: u64) -> Option<MerklePath<F, D>> {
+        if index >= Self::capacity() {
+            return None;
+        }
+        let mut siblings = [F::ZERO; D];
+        let mut ind = index;
+        for (level, sib) in siblings.iter_mut().enumerate() {
+            *sib = self.node(level, ind ^ 1);
+            ind >>= 1;
+        }
+        Some(MerklePath { index, siblings })
+    }
+}
+
+/// An authentication path for a leaf in a [`MerkleTree`].
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerklePath<F: PrimeField, const D: usize> {
+    /// The index of the leaf.
+    pub index: u64,
+    /// The siblings along the path, from the leaf level up.
+    pub siblings: [F; D],
+}
+
+impl<F: PrimeField, const D: usize> Default for MerklePath<F, D> {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            siblings: [F::ZERO; D],
+        }
+    }
+}
+
+impl<F: PrimeField, const D: usize> MerklePath<F, D> {
+    /// Compute the root of the tree given a leaf at the index of the path.
+    pub fn compute_root<H: FieldHash<F>>(&self, leaf: F) -> F {
+        let mut cur = leaf;
+        for (level, sib) in self.siblings.iter().enumerate() {
+            cur = if (self.index >> level) & 1 == 0 {
+                H::hash(&[cur, *sib])
+            } else {
+                H::hash(&[*sib, cur])
+            };
+        }
+        cur
+    }
+
+    /// Verify that a leaf is in a tree with some root.
+    pub fn verify<H: FieldHash<F>>(&self, leaf: F, root: F) -> bool {
+        self.compute_root::<H>(leaf) == root
+    }
+}
+
+/// An authentication path in-circuit.
+#[derive(Clone)]
+pub struct MerklePathVar<F: PrimeField, const D: usize> {
+    /// The bits of the index of the leaf, from the leaf level up.
+    pub index_bits: Vec<Boolean<F>>,
+    /// The siblings along the path, from the leaf level up.
+    pub siblings: Vec<FpVar<F>>,
+}
+
+impl<F: PrimeField, const D: usize> AllocVar<MerklePath<F, D>, F> for MerklePathVar<F, D> {
+    fn new_variable<T: std::borrow::Borrow<MerklePath<F, D>>>(
+        cs: impl Into<ark_relations::r1cs::Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: ark_r1cs_std::prelude::AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let mut index_bits = vec![];
+            let mut siblings = vec![];
+            for level in 0..D {
+                index_bits.push(Boolean::new_variable(
+                    ns!(cs, "index_bit"),
+                    || Ok((rec.index >> level) & 1 == 1),
+                    mode,
+                )?);
+                siblings.push(FpVar::new_variable(
+                    ns!(cs, "sibling"),
+                    || Ok(rec.siblings[level]),
+                    mode,
+                )?);
+            }
+            Ok(MerklePathVar {
+                index_bits,
+                siblings,
+            })
+        })
+    }
+}
+
+impl<F: PrimeField, const D: usize> MerklePathVar<F, D> {
+    /// Compute the root of the tree in-circuit given a leaf at the index of the path.
+    pub fn compute_root<H: FieldHash<F>>(
+        &self,
+        leaf: FpVar<F>,
+    ) -> Result<FpVar<F>, SynthesisError> {
+        let mut cur = leaf;
+        for (bit, sib) in self.index_bits.iter().zip(self.siblings.iter()) {
+            let left = FpVar::conditionally_select(bit, sib, &cur)?;
+            let right = FpVar::conditionally_select(bit, &cur, sib)?;
+            cur = H::hash_in_zk(&[left, right])?;
+        }
+        Ok(cur)
+    }
+
+    /// Check that a leaf is in a tree with some root in-circuit.
+    pub fn verify<H: FieldHash<F>>(
+        &self,
+        leaf: FpVar<F>,
+        root: &FpVar<F>,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        self.compute_root::<H>(leaf)?.is_eq(root)
+    }
+}
+
+/// A leaf which can be stored in a [`MerkleTree`].
+pub trait MerkleLeaf<F: PrimeField>: Clone {
+    /// The value of the leaf in the tree.
+    fn leaf_hash(&self) -> F;
+}
+
+impl<F: PrimeField> MerkleLeaf<F> for F {
+    fn leaf_hash(&self) -> F {
+        *self
+    }
+}
+
+/// A write to a leaf of a [`MerkleTree`], along with the path of the leaf right after the write.
+///
+/// Applying a sequence of updates in order to a path keeps the path up to date, see
+/// [`MerklePath::apply_update`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeafUpdate<F: PrimeField, L, const D: usize> {
+    /// The new leaf.
+    pub leaf: L,
+    /// The path of the leaf after the write.
+    pub path: MerklePath<F, D>,
+}
+
+impl<F: PrimeField, L: MerkleLeaf<F>, const D: usize> LeafUpdate<F, L, D> {
+    /// Compute the root of the tree right after the write.
+    pub fn root<H: FieldHash<F>>(&self) -> F {
+        self.path.compute_root::<H>(self.leaf.leaf_hash())
+    }
+}
+
+impl<F: PrimeField, const D: usize> MerklePath<F, D> {
+    /// Update the path after a write to a different leaf.
+    ///
+    /// Only the sibling at the level where the two paths meet changes, and it is recomputed from
+    /// the path of the written leaf. Writes to the leaf of this path itself should replace the path
+    /// instead. Returns `false` (and does nothing) in that case, or if the written index is out of
+    /// range.
+    pub fn apply_update<H: FieldHash<F>, L: MerkleLeaf<F>>(
+        &mut self,
+        update: &LeafUpdate<F, L, D>,
+    ) -> bool {
+        let diff = self.index ^ update.path.index;
+        if diff == 0 {
+            return false;
+        }
+        let level = (63 - diff.leading_zeros()) as usize;
+        if level >= D {
+            return false;
+        }
+
+        let mut cur = update.leaf.leaf_hash();
+        for (l, sib) in update.path.siblings[..level].iter().enumerate() {
+            cur = if (update.path.index >> l) & 1 == 0 {
+                H::hash(&[cur, *sib])
+            } else {
+                H::hash(&[*sib, cur])
+            };
+        }
+        self.siblings[level] = cur;
+        true
+    }
+}
@@ -1 +1,952 @@
+use std::{cmp::Ordering, collections::BTreeMap};
 
+use crate::{
+    crypto::hash::HasherZK,
+    generic::{
+        bulletin::{CallbackBul, JoinableBulletin, PublicCallbackBul, PublicUserBul, UserBul},
+        object::{Com, ComVar, Nul, Time, TimeVar},
+        user::UserData,
+    },
+    impls::{
+        centralized::crypto::{FakeSigPubkey, FakeSigPubkeyVar, NoEnc, NoSigOTP},
+        decentralized::ds::{
+            tree::{LeafUpdate, MerkleLeaf, MerklePath, MerklePathVar, MerkleTree},
+            witness::MerkleBulletin,
+        },
+        hash::Poseidon,
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    convert::ToConstraintFieldGadget,
+    fields::{fp::FpVar, FieldVar},
+    prelude::Boolean,
+};
+use ark_relations::{ns, r1cs::SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_snark::SNARK;
+use rand::{
+    distributions::{Distribution, Standard},
+    thread_rng, Rng,
+};
+
+/// An error when writing to a Merkle tree based store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeStoreError {
+    /// The tree is full.
+    Full,
+    /// The ticket may never be inserted, as it is zero or at least `(p - 1) / 2`.
+    InvalidTicket,
+    /// The ticket was already called.
+    AlreadyCalled,
+}
+
+impl std::fmt::Display for TreeStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TreeStoreError::Full => write!(f, "the tree is full"),
+            TreeStoreError::InvalidTicket => write!(f, "ticket out of range"),
+            TreeStoreError::AlreadyCalled => write!(f, "ticket already called"),
+        }
+    }
+}
+
+impl std::error::Error for TreeStoreError {}
+
+/// A Merkle tree based object store.
+///
+/// Each object commitment is a leaf of a [`MerkleTree`] of depth `D`. Membership is proven with a
+/// Merkle path to the root, so the membership public data is the root of the tree.
+///
+/// As the root changes with each append, the store keeps every root it has had, and only accepts
+/// interactions which prove membership against one of these roots.
+#[derive(Clone, Debug)]
+pub struct MerkleObjStore<F: PrimeField + Absorb, const D: usize> {
+    /// The Merkle tree of object commitments.
+    pub tree: MerkleTree<F, Poseidon<2>, D>,
+    /// The object commitments, in the order of the leaves.
+    pub coms: Vec<Com<F>>,
+    /// The nullifiers associated to each object.
+    pub old_nuls: Vec<Nul<F>>,
+    /// The callback commitments associated to each object.
+    pub cb_com_lists: Vec<Vec<Com<F>>>,
+    /// All roots the tree has had, including the current one.
+    pub roots: Vec<F>,
+    /// The log of writes to the tree, for keeping witnesses up to date.
+    pub log: Vec<LeafUpdate<F, Com<F>, D>>,
+}
+
+impl<F: PrimeField + Absorb, const D: usize> Default for MerkleObjStore<F, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField + Absorb, const D: usize> MerkleObjStore<F, D> {
+    /// Construct a new empty store.
+    pub fn new() -> Self {
+        let tree = MerkleTree::new();
+        let roots = vec![tree.root()];
+        Self {
+            tree,
+            coms: vec![],
+            old_nuls: vec![],
+            cb_com_lists: vec![],
+            roots,
+            log: vec![],
+        }
+    }
+
+    /// Get the current root of the tree.
+    pub fn get_root(&self) -> F {
+        self.tree.root()
+    }
+
+    /// Returns whether the root is a current or past root of the tree.
+    pub fn is_known_root(&self, root: &F) -> bool {
+        self.roots.contains(root)
+    }
+
+    /// Append an object to the store, without verifying any interaction.
+    ///
+    /// Fails if the tree is full.
+    pub fn push(
+        &mut self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: Vec<Com<F>>,
+    ) -> Result<(), TreeStoreError> {
+        let ind = self.coms.len() as u64;
+        self.tree.set(ind, object).ok_or(TreeStoreError::Full)?;
+        self.log.push(LeafUpdate {
+            leaf: object,
+            path: self.tree.path(ind).ok_or(TreeStoreError::Full)?,
+        });
+        self.coms.push(object);
+        self.old_nuls.push(old_nul);
+        self.cb_com_lists.push(cb_com_list);
+        self.roots.push(self.tree.root());
+        Ok(())
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, const D: usize> PublicUserBul<F, U>
+    for MerkleObjStore<F, D>
+{
+    type MembershipWitness = MerklePath<F, D>;
+
+    type MembershipWitnessVar = MerklePathVar<F, D>;
+
+    type MembershipPub = F;
+
+    type MembershipPubVar = FpVar<F>;
+
+    fn verify_in<PubArgs, Snark: SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        _args: PubArgs,
+        _proof: Snark::Proof,
+        _memb_data: Self::MembershipPub,
+        _verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        for (i, c) in self.coms.iter().enumerate() {
+            if c == &object
+                && self.old_nuls[i] == old_nul
+                && self.cb_com_lists[i] == cb_com_list.to_vec()
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn get_membership_data(&self, object: Com<F>) -> Option<(F, MerklePath<F, D>)> {
+        let ind = self.coms.iter().position(|c| c == &object)?;
+        Some((self.get_root(), self.tree.path(ind as u64)?))
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        extra_witness.verify::<Poseidon<2>>(data_var, &extra_pub)
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, const D: usize> UserBul<F, U>
+    for MerkleObjStore<F, D>
+{
+    type Error = TreeStoreError;
+
+    fn has_never_received_nul(&self, nul: &Nul<F>) -> bool {
+        !self.old_nuls.contains(nul)
+    }
+
+    fn verify_interaction<PubArgs: ToConstraintField<F>, Snark: SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        args: PubArgs,
+        cb_com_list: [Com<F>; NUMCBS],
+        proof: Snark::Proof,
+        memb_data: Option<F>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        if !<Self as UserBul<F, U>>::has_never_received_nul(self, &old_nul) {
+            return false;
+        }
+
+        let mut pub_inputs = vec![object, old_nul];
+        pub_inputs.extend::<Vec<F>>(args.to_field_elements().unwrap());
+        pub_inputs.extend::<Vec<F>>(cb_com_list.to_field_elements().unwrap());
+        if let Some(root) = memb_data {
+            // A proof against an unknown root proves nothing about the store.
+            if !self.is_known_root(&root) {
+                return false;
+            }
+            pub_inputs.push(root);
+        }
+
+        let out = Snark::verify(verif_key, &pub_inputs, &proof);
+
+        out.unwrap_or(false)
+    }
+
+    fn append_value<PubArgs, Snark: SNARK<F>, const NUMCBS: usize>(
+        &mut self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        _args: PubArgs,
+        _proof: Snark::Proof,
+        _memb_data: Option<F>,
+        _verif_key: &Snark::VerifyingKey,
+    ) -> Result<(), Self::Error> {
+        self.push(object, old_nul, cb_com_list.into())
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, const D: usize> JoinableBulletin<F, U>
+    for MerkleObjStore<F, D>
+where
+    Standard: Distribution<F>,
+{
+    type PubData = ();
+
+    fn join_bul(&mut self, object: Com<F>, _pub_data: ()) -> Result<(), Self::Error> {
+        let mut rng = thread_rng();
+        self.push(object, rng.gen(), vec![])
+    }
+}
+
+/// A leaf of an indexed Merkle tree.
+///
+/// The leaves form a linked list sorted by key: `next` is the smallest key in the tree which is
+/// larger than `key`, or zero if there is none.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IndexedLeaf<F: PrimeField> {
+    /// The key of the leaf (the ticket).
+    pub key: F,
+    /// The next key in sorted order, or zero if this is the largest key.
+    pub next: F,
+    /// The data associated to the key.
+    pub data: F,
+}
+
+impl<F: PrimeField + Absorb> IndexedLeaf<F> {
+    /// Hash the leaf.
+    pub fn hash(&self) -> F {
+        <Poseidon<2>>::hash(&[self.key, self.next, self.data])
+    }
+}
+
+impl<F: PrimeField + Absorb> MerkleLeaf<F> for IndexedLeaf<F> {
+    fn leaf_hash(&self) -> F {
+        self.hash()
+    }
+}
+
+/// A leaf of an indexed Merkle tree in-circuit.
+#[derive(Clone)]
+pub struct IndexedLeafVar<F: PrimeField> {
+    /// The key of the leaf.
+    pub key: FpVar<F>,
+    /// The next key in sorted order.
+    pub next: FpVar<F>,
+    /// The data associated to the key.
+    pub data: FpVar<F>,
+}
+
+impl<F: PrimeField + Absorb> IndexedLeafVar<F> {
+    /// Hash the leaf in-circuit.
+    pub fn hash(&self) -> Result<FpVar<F>, SynthesisError> {
+        <Poseidon<2>>::hash_in_zk(&[self.key.clone(), self.next.clone(), self.data.clone()])
+    }
+}
+
+impl<F: PrimeField> AllocVar<IndexedLeaf<F>, F> for IndexedLeafVar<F> {
+    fn new_variable<T: std::borrow::Borrow<IndexedLeaf<F>>>(
+        cs: impl Into<ark_relations::r1cs::Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: ark_r1cs_std::prelude::AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let key = FpVar::new_variable(ns!(cs, "key"), || Ok(rec.key), mode)?;
+            let next = FpVar::new_variable(ns!(cs, "next"), || Ok(rec.next), mode)?;
+            let data = FpVar::new_variable(ns!(cs, "data"), || Ok(rec.data), mode)?;
+            Ok(IndexedLeafVar { key, next, data })
+        })
+    }
+}
+
+/// A membership witness for an [`IndexedCallbackStore`].
+#[derive(Clone, Default, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IndexedMembWitness<F: PrimeField, const D: usize> {
+    /// The path to the leaf of the ticket.
+    pub path: MerklePath<F, D>,
+    /// The next key stored in the leaf of the ticket.
+    pub next: F,
+}
+
+/// A membership witness for an [`IndexedCallbackStore`] in-circuit.
+#[derive(Clone)]
+pub struct IndexedMembWitnessVar<F: PrimeField, const D: usize> {
+    /// The path to the leaf of the ticket.
+    pub path: MerklePathVar<F, D>,
+    /// The next key stored in the leaf of the ticket.
+    pub next: FpVar<F>,
+}
+
+impl<F: PrimeField, const D: usize> AllocVar<IndexedMembWitness<F, D>, F>
+    for IndexedMembWitnessVar<F, D>
+{
+    fn new_variable<T: std::borrow::Borrow<IndexedMembWitness<F, D>>>(
+        cs: impl Into<ark_relations::r1cs::Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: ark_r1cs_std::prelude::AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let path = MerklePathVar::new_variable(ns!(cs, "path"), || Ok(rec.path.clone()), mode)?;
+            let next = FpVar::new_variable(ns!(cs, "next"), || Ok(rec.next), mode)?;
+            Ok(IndexedMembWitnessVar { path, next })
+        })
+    }
+}
+
+/// A nonmembership witness for an [`IndexedCallbackStore`].
+///
+/// This is the low leaf of the ticket: the leaf with the largest key smaller than the ticket.
+#[derive(Clone, Default, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IndexedNonMembWitness<F: PrimeField, const D: usize> {
+    /// The path to the low leaf.
+    pub path: MerklePath<F, D>,
+    /// The low leaf.
+    pub low: IndexedLeaf<F>,
+}
+
+/// A nonmembership witness for an [`IndexedCallbackStore`] in-circuit.
+#[derive(Clone)]
+pub struct IndexedNonMembWitnessVar<F: PrimeField, const D: usize> {
+    /// The path to the low leaf.
+    pub path: MerklePathVar<F, D>,
+    /// The low leaf.
+    pub low: IndexedLeafVar<F>,
+}
+
+impl<F: PrimeField, const D: usize> AllocVar<IndexedNonMembWitness<F, D>, F>
+    for IndexedNonMembWitnessVar<F, D>
+{
+    fn new_variable<T: std::borrow::Borrow<IndexedNonMembWitness<F, D>>>(
+        cs: impl Into<ark_relations::r1cs::Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: ark_r1cs_std::prelude::AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let res = f();
+        res.and_then(|rec| {
+            let rec = rec.borrow();
+            let path = MerklePathVar::new_variable(ns!(cs, "path"), || Ok(rec.path.clone()), mode)?;
+            let low = IndexedLeafVar::new_variable(ns!(cs, "low"), || Ok(rec.low), mode)?;
+            Ok(IndexedNonMembWitnessVar { path, low })
+        })
+    }
+}
+
+/// A callback storage system based on an indexed Merkle tree.
+///
+/// Called tickets are stored as leaves of a [`MerkleTree`] of depth `D`, which form a linked list
+/// sorted by ticket (see [`IndexedLeaf`]). The first leaf is a sentinel with key zero.
+///
+/// To prove membership of a ticket, one proves a path to the leaf keyed by the ticket, whose data
+/// is the hash of the arguments and time of the call.
+///
+/// To prove nonmembership of a ticket, one proves a path to the low leaf of the ticket, whose key
+/// is smaller than the ticket and whose next key is larger than the ticket (or zero). Unlike
+/// [`SigRangeStore`](`crate::impls::centralized::ds::sigrange::SigRangeStore`), this requires no
+/// signatures, and so no trusted party.
+///
+/// In both cases, the public data is the root of the tree. As with the signed ranges, tickets are
+/// compared as integers, and so must be less than `(p - 1) / 2`.
+#[derive(Clone, Debug)]
+pub struct IndexedCallbackStore<F: PrimeField + Absorb, Args: Clone, const D: usize> {
+    /// The Merkle tree of leaves.
+    pub tree: MerkleTree<F, Poseidon<2>, D>,
+    /// The leaves of the tree, in order of insertion.
+    pub leaves: Vec<IndexedLeaf<F>>,
+    /// The called tickets, in order of insertion (offset by one from the leaves).
+    pub called: Vec<(FakeSigPubkey<F>, Args, Time<F>)>,
+    /// The log of writes to the tree, for keeping witnesses up to date.
+    pub log: Vec<LeafUpdate<F, IndexedLeaf<F>, D>>,
+    /// A map from keys to leaf indices.
+    keys: BTreeMap<F, usize>,
+}
+
+impl<F: PrimeField + Absorb, Args: Clone + ToConstraintField<F>, const D: usize> Default
+    for IndexedCallbackStore<F, Args, D>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField + Absorb, Args: Clone + ToConstraintField<F>, const D: usize>
+    IndexedCallbackStore<F, Args, D>
+{
+    /// Construct a new store, containing only the sentinel leaf.
+    pub fn new() -> Self {
+        let mut store = Self {
+            tree: MerkleTree::new(),
+            leaves: vec![IndexedLeaf::default()],
+            called: vec![],
+            log: vec![],
+            keys: BTreeMap::new(),
+        };
+        store.write(0);
+        store
+    }
+
+    fn write(&mut self, ind: usize) -> Option<()> {
+        let leaf = self.leaves[ind];
+        self.tree.set(ind as u64, leaf.hash())?;
+        self.log.push(LeafUpdate {
+            leaf,
+            path: self.tree.path(ind as u64)?,
+        });
+        Some(())
+    }
+
+    /// Get the current root of the tree.
+    pub fn get_root(&self) -> F {
+        self.tree.root()
+    }
+
+    /// Compute the data of a leaf for a called ticket.
+    pub fn leaf_data(args: &Args, time: Time<F>) -> F {
+        let mut v = args.to_field_elements().unwrap();
+        v.push(time);
+        <Poseidon<2>>::hash(&v)
+    }
+
+    /// Compute the data of a leaf for a called ticket in-circuit.
+    pub fn leaf_data_zk(
+        args: &impl ToConstraintFieldGadget<F>,
+        time: TimeVar<F>,
+    ) -> Result<FpVar<F>, SynthesisError> {
+        let mut v = args.to_constraint_field()?;
+        v.push(time);
+        <Poseidon<2>>::hash_in_zk(&v)
+    }
+
+    fn low_leaf(&self, key: &F) -> usize {
+        self.keys
+            .range(..key)
+            .next_back()
+            .map(|(_, i)| *i)
+            .unwrap_or(0)
+    }
+
+    /// Insert a called ticket into the tree.
+    ///
+    /// Fails if the ticket is zero or at least `(p - 1) / 2`, was already called, or the tree is
+    /// full.
+    pub fn insert(
+        &mut self,
+        tik: FakeSigPubkey<F>,
+        args: Args,
+        time: Time<F>,
+    ) -> Result<(), TreeStoreError> {
+        let key = tik.to();
+        let ind = self.leaves.len();
+        if key == F::ZERO || key.into_bigint() >= F::MODULUS_MINUS_ONE_DIV_TWO {
+            return Err(TreeStoreError::InvalidTicket);
+        }
+        if self.keys.contains_key(&key) {
+            return Err(TreeStoreError::AlreadyCalled);
+        }
+        if ind as u64 >= MerkleTree::<F, Poseidon<2>, D>::capacity() {
+            return Err(TreeStoreError::Full);
+        }
+
+        let low = self.low_leaf(&key);
+        let leaf = IndexedLeaf {
+            key,
+            next: self.leaves[low].next,
+            data: Self::leaf_data(&args, time),
+        };
+        self.leaves[low].next = key;
+
+        self.leaves.push(leaf);
+        self.write(low).ok_or(TreeStoreError::Full)?;
+        self.write(ind).ok_or(TreeStoreError::Full)?;
+        self.keys.insert(key, ind);
+        self.called.push((tik, args, time));
+        Ok(())
+    }
+
+    /// Get the arguments and time of a called ticket.
+    pub fn get_called(&self, tik: &FakeSigPubkey<F>) -> Option<(Args, Time<F>)> {
+        let ind = self.keys.get(&tik.to())?;
+        let (_, args, time) = &self.called[ind - 1];
+        Some((args.clone(), *time))
+    }
+
+    /// Get the membership witness of a ticket, if it was called.
+    pub fn get_memb_witness(&self, tik: &FakeSigPubkey<F>) -> Option<IndexedMembWitness<F, D>> {
+        let ind = *self.keys.get(&tik.to())?;
+        Some(IndexedMembWitness {
+            path: self.tree.path(ind as u64)?,
+            next: self.leaves[ind].next,
+        })
+    }
+
+    /// Get the nonmembership witness of a ticket, if it was not called.
+    ///
+    /// Returns `None` for tickets which may never be inserted (see
+    /// [`IndexedCallbackStore::insert`]), as nonmembership can not be proven for them.
+    pub fn get_nmemb_witness(&self, tik: &FakeSigPubkey<F>) -> Option<IndexedNonMembWitness<F, D>> {
+        let key = tik.to();
+        if key == F::ZERO
+            || key.into_bigint() >= F::MODULUS_MINUS_ONE_DIV_TWO
+            || self.keys.contains_key(&key)
+        {
+            return None;
+        }
+        let low = self.low_leaf(&key);
+        Some(IndexedNonMembWitness {
+            path: self.tree.path(low as u64)?,
+            low: self.leaves[low],
+        })
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn membership_data(
+        &self,
+        tik: &FakeSigPubkey<F>,
+    ) -> (F, IndexedMembWitness<F, D>, F, IndexedNonMembWitness<F, D>) {
+        let root = self.get_root();
+        match self.get_memb_witness(tik) {
+            Some(w) => (root, w, root, IndexedNonMembWitness::default()),
+            None => (
+                root,
+                IndexedMembWitness::default(),
+                root,
+                self.get_nmemb_witness(tik).unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// Check that a ticket with some leaf data is in the tree in-circuit.
+    pub fn enforce_membership(
+        tik: FakeSigPubkeyVar<F>,
+        data: FpVar<F>,
+        witness: IndexedMembWitnessVar<F, D>,
+        root: FpVar<F>,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        let leaf = IndexedLeafVar {
+            key: tik.0,
+            next: witness.next,
+            data,
+        };
+        witness.path.verify::<Poseidon<2>>(leaf.hash()?, &root)
+    }
+
+    /// Check that a ticket is not in the tree in-circuit.
+    pub fn enforce_nonmembership(
+        tik: FakeSigPubkeyVar<F>,
+        witness: IndexedNonMembWitnessVar<F, D>,
+        root: FpVar<F>,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        let low = witness.low;
+
+        // The ticket is not authenticated by the tree, so it must be range checked.
+        let c0 = tik.0.is_cmp(&low.key, Ordering::Greater, false)?;
+        let c1 = tik.0.is_cmp(&low.next, Ordering::Less, false)?;
+        let last = low.next.is_zero()?;
+
+        let range_correct = c0 & (c1 | last);
+
+        let c2 = witness.path.verify::<Poseidon<2>>(low.hash()?, &root)?;
+
+        Ok(range_correct & c2)
+    }
+}
+
+impl<F: PrimeField + Absorb, const D: usize> PublicCallbackBul<F, F, NoSigOTP<F>>
+    for IndexedCallbackStore<F, F, D>
+where
+    Standard: Distribution<F>,
+{
+    type MembershipWitness = IndexedMembWitness<F, D>;
+
+    type MembershipWitnessVar = IndexedMembWitnessVar<F, D>;
+
+    type NonMembershipWitness = IndexedNonMembWitness<F, D>;
+
+    type NonMembershipWitnessVar = IndexedNonMembWitnessVar<F, D>;
+
+    type MembershipPub = F;
+
+    type MembershipPubVar = FpVar<F>;
+
+    type NonMembershipPub = F;
+
+    type NonMembershipPubVar = FpVar<F>;
+
+    fn verify_in(&self, tik: FakeSigPubkey<F>) -> Option<(F, Time<F>)> {
+        self.get_called(&tik)
+    }
+
+    fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
+        self.get_nmemb_witness(&tik).is_some()
+    }
+
+    fn get_membership_data(
+        &self,
+        tik: FakeSigPubkey<F>,
+    ) -> (F, IndexedMembWitness<F, D>, F, IndexedNonMembWitness<F, D>) {
+        self.membership_data(&tik)
+    }
+
+    fn enforce_membership_of(
+        tikvar: (FakeSigPubkeyVar<F>, FpVar<F>, TimeVar<F>),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        let data = Self::leaf_data_zk(&tikvar.1, tikvar.2)?;
+        Self::enforce_membership(tikvar.0, data, extra_witness, extra_pub)
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: FakeSigPubkeyVar<F>,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        Self::enforce_nonmembership(tikvar, extra_witness, extra_pub)
+    }
+}
+
+impl<
+        F: PrimeField + Absorb,
+        A: Clone + Default + ToConstraintField<F>,
+        AVar: Clone + AllocVar<A, F> + ToConstraintFieldGadget<F>,
+        const D: usize,
+    > PublicCallbackBul<F, A, NoEnc<F, A, AVar>> for IndexedCallbackStore<F, A, D>
+where
+    Standard: Distribution<F>,
+{
+    type MembershipWitness = IndexedMembWitness<F, D>;
+
+    type MembershipWitnessVar = IndexedMembWitnessVar<F, D>;
+
+    type NonMembershipWitness = IndexedNonMembWitness<F, D>;
+
+    type NonMembershipWitnessVar = IndexedNonMembWitnessVar<F, D>;
+
+    type MembershipPub = F;
+
+    type MembershipPubVar = FpVar<F>;
+
+    type NonMembershipPub = F;
+
+    type NonMembershipPubVar = FpVar<F>;
+
+    fn verify_in(&self, tik: FakeSigPubkey<F>) -> Option<(A, Time<F>)> {
+        self.get_called(&tik)
+    }
+
+    fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
+        self.get_nmemb_witness(&tik).is_some()
+    }
+
+    fn get_membership_data(
+        &self,
+        tik: FakeSigPubkey<F>,
+    ) -> (F, IndexedMembWitness<F, D>, F, IndexedNonMembWitness<F, D>) {
+        self.membership_data(&tik)
+    }
+
+    fn enforce_membership_of(
+        tikvar: (FakeSigPubkeyVar<F>, AVar, TimeVar<F>),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        let data = Self::leaf_data_zk(&tikvar.1, tikvar.2)?;
+        Self::enforce_membership(tikvar.0, data, extra_witness, extra_pub)
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: FakeSigPubkeyVar<F>,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        Self::enforce_nonmembership(tikvar, extra_witness, extra_pub)
+    }
+}
+
+impl<F: PrimeField + Absorb, const D: usize> CallbackBul<F, F, NoSigOTP<F>>
+    for IndexedCallbackStore<F, F, D>
+where
+    Standard: Distribution<F>,
+{
+    type Error = TreeStoreError;
+
+    fn has_never_received_tik(&self, tik: &FakeSigPubkey<F>) -> bool {
+        !self.keys.contains_key(&tik.to())
+    }
+
+    fn append_value(
+        &mut self,
+        tik: FakeSigPubkey<F>,
+        enc_args: F,
+        _signature: (),
+        time: Time<F>,
+    ) -> Result<(), Self::Error> {
+        self.insert(tik, enc_args, time)
+    }
+}
+
+impl<
+        F: PrimeField + Absorb,
+        A: Clone + Default + ToConstraintField<F>,
+        AVar: Clone + AllocVar<A, F> + ToConstraintFieldGadget<F>,
+        const D: usize,
+    > CallbackBul<F, A, NoEnc<F, A, AVar>> for IndexedCallbackStore<F, A, D>
+where
+    Standard: Distribution<F>,
+{
+    type Error = TreeStoreError;
+
+    fn has_never_received_tik(&self, tik: &FakeSigPubkey<F>) -> bool {
+        !self.keys.contains_key(&tik.to())
+    }
+
+    fn append_value(
+        &mut self,
+        tik: FakeSigPubkey<F>,
+        enc_args: A,
+        _signature: (),
+        time: Time<F>,
+    ) -> Result<(), Self::Error> {
+        self.insert(tik, enc_args, time)
+    }
+}
+
+impl<F: PrimeField + Absorb, const D: usize> MerkleBulletin<F, D> for MerkleObjStore<F, D> {
+    type Leaf = Com<F>;
+
+    fn get_root(&self) -> F {
+        self.get_root()
+    }
+
+    fn get_version(&self) -> u64 {
+        self.log.len() as u64
+    }
+
+    fn get_updates_since(&self, version: u64) -> Option<Vec<LeafUpdate<F, Com<F>, D>>> {
+        let from = usize::try_from(version).ok()?;
+        self.log.get(from..).map(|x| x.to_vec())
+    }
+}
+
+impl<F: PrimeField + Absorb, Args: Clone + ToConstraintField<F>, const D: usize>
+    MerkleBulletin<F, D> for IndexedCallbackStore<F, Args, D>
+{
+    type Leaf = IndexedLeaf<F>;
+
+    fn get_root(&self) -> F {
+        self.get_root()
+    }
+
+    fn get_version(&self) -> u64 {
+        self.log.len() as u64
+    }
+
+    fn get_updates_since(&self, version: u64) -> Option<Vec<LeafUpdate<F, IndexedLeaf<F>, D>>> {
+        let from = usize::try_from(version).ok()?;
+        self.log.get(from..).map(|x| x.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_r1cs_std::{alloc::AllocationMode, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    type Store = IndexedCallbackStore<Fr, Fr, 4>;
+
+    fn store() -> Store {
+        let mut store = Store::new();
+        for (i, key) in [20, 5, 10].into_iter().enumerate() {
+            store
+                .insert(
+                    FakeSigPubkey::new(Fr::from(key)),
+                    Fr::from(100 + key),
+                    Time::from(i as u64),
+                )
+                .unwrap();
+        }
+        store
+    }
+
+    // Checks membership of a ticket in-circuit, returning the output and whether the circuit is
+    // satisfied
+    fn check_memb(tik: Fr, data: Fr, witness: IndexedMembWitness<Fr, 4>, root: Fr) -> (bool, bool) {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let tik =
+            FakeSigPubkeyVar::new_witness(cs.clone(), || Ok(FakeSigPubkey::new(tik))).unwrap();
+        let data = FpVar::new_witness(cs.clone(), || Ok(data)).unwrap();
+        let witness = IndexedMembWitnessVar::new_variable(
+            cs.clone(),
+            || Ok(witness),
+            AllocationMode::Witness,
+        )
+        .unwrap();
+        let root = FpVar::new_input(cs.clone(), || Ok(root)).unwrap();
+        let out = Store::enforce_membership(tik, data, witness, root).unwrap();
+        (out.value().unwrap(), cs.is_satisfied().unwrap())
+    }
+
+    // Checks nonmembership of a ticket in-circuit, returning the output and whether the circuit
+    // is satisfied
+    fn check_nmemb(tik: Fr, witness: IndexedNonMembWitness<Fr, 4>, root: Fr) -> (bool, bool) {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let tik =
+            FakeSigPubkeyVar::new_witness(cs.clone(), || Ok(FakeSigPubkey::new(tik))).unwrap();
+        let witness = IndexedNonMembWitnessVar::new_variable(
+            cs.clone(),
+            || Ok(witness),
+            AllocationMode::Witness,
+        )
+        .unwrap();
+        let root = FpVar::new_input(cs.clone(), || Ok(root)).unwrap();
+        let out = Store::enforce_nonmembership(tik, witness, root).unwrap();
+        (out.value().unwrap(), cs.is_satisfied().unwrap())
+    }
+
+    // Tests that called tickets prove membership, and only with their own data
+    #[test]
+    fn indexed_membership() {
+        let store = store();
+        let root = store.get_root();
+
+        for key in [5, 10, 20] {
+            let tik = FakeSigPubkey::new(Fr::from(key));
+            let (args, time) = store.get_called(&tik).unwrap();
+            let data = Store::leaf_data(&args, time);
+            let witness = store.get_memb_witness(&tik).unwrap();
+            assert_eq!(
+                check_memb(Fr::from(key), data, witness.clone(), root),
+                (true, true)
+            );
+            assert!(!check_memb(Fr::from(key), data + Fr::from(1), witness, root).0);
+            assert!(store.get_nmemb_witness(&tik).is_none());
+        }
+        assert!(store
+            .get_memb_witness(&FakeSigPubkey::new(Fr::from(7)))
+            .is_none());
+    }
+
+    // Tests that uncalled tickets prove nonmembership, including past the largest key
+    #[test]
+    fn indexed_nonmembership() {
+        let store = store();
+        let root = store.get_root();
+
+        for (key, low) in [(1, 0), (7, 5), (15, 10), (25, 20)] {
+            let witness = store
+                .get_nmemb_witness(&FakeSigPubkey::new(Fr::from(key)))
+                .unwrap();
+            assert_eq!(witness.low.key, Fr::from(low));
+            assert_eq!(check_nmemb(Fr::from(key), witness, root), (true, true));
+        }
+    }
+
+    // Tests that nonmembership can not be proven with a forged or wrong low leaf
+    #[test]
+    fn indexed_forged_low_leaf() {
+        let store = store();
+        let root = store.get_root();
+        let tik = FakeSigPubkey::new(Fr::from(10));
+
+        // A low leaf skipping over the called ticket is not in the tree
+        let mut forged = store
+            .get_nmemb_witness(&FakeSigPubkey::new(Fr::from(7)))
+            .unwrap();
+        forged.low.next = Fr::from(20);
+        assert!(!check_nmemb(Fr::from(10), forged, root).0);
+
+        // The low leaf of a smaller ticket does not cover the called ticket
+        let below = store
+            .get_nmemb_witness(&FakeSigPubkey::new(Fr::from(7)))
+            .unwrap();
+        assert!(!check_nmemb(Fr::from(10), below, root).0);
+
+        // The leaf of the ticket itself does not prove nonmembership
+        let own = IndexedNonMembWitness {
+            path: store.get_memb_witness(&tik).unwrap().path,
+            low: store.leaves[*store.keys.get(&tik.to()).unwrap()],
+        };
+        assert!(!check_nmemb(Fr::from(10), own, root).0);
+    }
+
+    // Tests that tickets past `(p - 1) / 2` can not be inserted or shown to be uncalled, as they
+    // would otherwise compare above the largest key, and that called tickets can not be inserted
+    // again
+    #[test]
+    fn indexed_out_of_range_ticket() {
+        let mut store = store();
+        let root = store.get_root();
+        let big = -Fr::from(1);
+
+        assert_eq!(
+            store.insert(FakeSigPubkey::new(big), Fr::from(0), Time::from(0)),
+            Err(TreeStoreError::InvalidTicket)
+        );
+        assert_eq!(
+            store.insert(FakeSigPubkey::new(Fr::from(10)), Fr::from(0), Time::from(0)),
+            Err(TreeStoreError::AlreadyCalled)
+        );
+        assert!(store.get_nmemb_witness(&FakeSigPubkey::new(big)).is_none());
+
+        let last = store
+            .get_nmemb_witness(&FakeSigPubkey::new(Fr::from(25)))
+            .unwrap();
+        assert!(!check_nmemb(big, last, root).1);
+    }
+}
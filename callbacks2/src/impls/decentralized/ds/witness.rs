@@ -0,0 +1,302 @@
+use crate::{
+    crypto::hash::FieldHash,
+    impls::decentralized::ds::{
+        tree::{LeafUpdate, MerkleLeaf, MerklePath},
+        treestore::{IndexedLeaf, IndexedMembWitness, IndexedNonMembWitness},
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// A bulletin backed by a [`MerkleTree`](`crate::impls::decentralized::ds::tree::MerkleTree`)
+/// which publishes its writes.
+///
+/// The version of the bulletin is the number of writes to the tree. Each write is published as a
+/// [`LeafUpdate`], which lets a [`WitnessTracker`] keep witnesses up to date without refetching
+/// them.
+pub trait MerkleBulletin<F: PrimeField, const D: usize> {
+    /// The leaves stored in the tree.
+    type Leaf: MerkleLeaf<F>;
+
+    /// Get the current root of the tree.
+    fn get_root(&self) -> F;
+
+    /// Get the current version of the bulletin.
+    fn get_version(&self) -> u64;
+
+    /// Get all writes since some version, in order.
+    ///
+    /// Returns `None` if the version is ahead of the bulletin.
+    fn get_updates_since(&self, version: u64) -> Option<Vec<LeafUpdate<F, Self::Leaf, D>>>;
+}
+
+/// An asynchronous bulletin backed by a Merkle tree, see [`MerkleBulletin`].
+#[cfg(feature = "asynchr")]
+#[cfg(any(feature = "asynchr", doc))]
+#[doc(cfg(feature = "asynchr"))]
+#[allow(async_fn_in_trait)]
+pub trait AsyncMerkleBulletin<F: PrimeField, const D: usize> {
+    /// The leaves stored in the tree.
+    type Leaf: MerkleLeaf<F>;
+
+    /// Get the current root of the tree.
+    async fn get_root(&self) -> F;
+
+    /// Get the current version of the bulletin.
+    async fn get_version(&self) -> u64;
+
+    /// Get all writes since some version, in order.
+    ///
+    /// Returns `None` if the version is ahead of the bulletin.
+    async fn get_updates_since(&self, version: u64) -> Option<Vec<LeafUpdate<F, Self::Leaf, D>>>;
+}
+
+/// An error when refreshing a [`WitnessTracker`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackerError {
+    /// The bulletin is behind the tracker, or has dropped the needed updates.
+    MissingUpdates,
+    /// The root after applying the updates does not match the root of the bulletin.
+    RootMismatch,
+}
+
+impl std::fmt::Display for TrackerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackerError::MissingUpdates => write!(f, "bulletin is missing updates"),
+            TrackerError::RootMismatch => write!(f, "root mismatch after applying updates"),
+        }
+    }
+}
+
+impl std::error::Error for TrackerError {}
+
+/// Keeps Merkle witnesses up to date with a [`MerkleBulletin`].
+///
+/// When the root of a Merkle bulletin changes, every stored path becomes stale. Instead of
+/// refetching membership data before each proof, a tracker stores the witnesses of some leaves
+/// and incrementally updates them from the writes published by the bulletin.
+///
+/// A witness should only be tracked if it was fetched at the version of the tracker. The tracker
+/// can then be refreshed with [`WitnessTracker::refresh`] (or
+/// [`WitnessTracker::refresh_async`]) before each proof.
+#[derive(Clone, Debug)]
+pub struct WitnessTracker<F: PrimeField, H: FieldHash<F>, L: MerkleLeaf<F>, const D: usize> {
+    /// The version of the bulletin the witnesses are valid at.
+    pub version: u64,
+    /// The root of the tree the witnesses are valid at.
+    pub root: F,
+    /// The tracked leaves and their paths, keyed by index.
+    pub witnesses: BTreeMap<u64, (L, MerklePath<F, D>)>,
+    _phantom: PhantomData<H>,
+}
+
+impl<F: PrimeField, H: FieldHash<F>, L: MerkleLeaf<F>, const D: usize> WitnessTracker<F, H, L, D> {
+    /// Construct a new tracker with no witnesses, at some version and root.
+    pub fn new(version: u64, root: F) -> Self {
+        Self {
+            version,
+            root,
+            witnesses: BTreeMap::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Construct a new tracker with no witnesses, at the current version of a bulletin.
+    pub fn from_bulletin<B: MerkleBulletin<F, D, Leaf = L>>(bul: &B) -> Self {
+        Self::new(bul.get_version(), bul.get_root())
+    }
+
+    /// Track a leaf along with its path.
+    ///
+    /// Returns `false` (and does nothing) if the path does not verify under the tracked root.
+    pub fn track(&mut self, leaf: L, path: MerklePath<F, D>) -> bool {
+        if !path.verify::<H>(leaf.leaf_hash(), self.root) {
+            return false;
+        }
+        self.witnesses.insert(path.index, (leaf, path));
+        true
+    }
+
+    /// Stop tracking the leaf at some index.
+    pub fn untrack(&mut self, index: u64) -> Option<(L, MerklePath<F, D>)> {
+        self.witnesses.remove(&index)
+    }
+
+    /// Get the tracked leaf and path at some index.
+    pub fn get(&self, index: u64) -> Option<&(L, MerklePath<F, D>)> {
+        self.witnesses.get(&index)
+    }
+
+    /// Apply a sequence of writes, in order, to all tracked witnesses.
+    pub fn apply_updates(&mut self, updates: &[LeafUpdate<F, L, D>]) {
+        for update in updates {
+            for (index, (leaf, path)) in self.witnesses.iter_mut() {
+                if *index == update.path.index {
+                    *leaf = update.leaf.clone();
+                    *path = update.path.clone();
+                } else {
+                    path.apply_update::<H, L>(update);
+                }
+            }
+            self.root = update.root::<H>();
+            self.version += 1;
+        }
+    }
+
+    fn check_root(&self, root: F) -> Result<(), TrackerError> {
+        if self.root != root {
+            return Err(TrackerError::RootMismatch);
+        }
+        Ok(())
+    }
+
+    /// Fetch the writes since the version of the tracker from a bulletin, and apply them.
+    pub fn refresh<B: MerkleBulletin<F, D, Leaf = L>>(
+        &mut self,
+        bul: &B,
+    ) -> Result<(), TrackerError> {
+        let updates = bul
+            .get_updates_since(self.version)
+            .ok_or(TrackerError::MissingUpdates)?;
+        self.apply_updates(&updates);
+        self.check_root(bul.get_root())
+    }
+
+    /// Fetch the writes since the version of the tracker from an asynchronous bulletin, and apply
+    /// them.
+    #[cfg(feature = "asynchr")]
+    #[cfg(any(feature = "asynchr", doc))]
+    #[doc(cfg(feature = "asynchr"))]
+    pub async fn refresh_async<B: AsyncMerkleBulletin<F, D, Leaf = L>>(
+        &mut self,
+        bul: &B,
+    ) -> Result<(), TrackerError> {
+        let updates = bul
+            .get_updates_since(self.version)
+            .await
+            .ok_or(TrackerError::MissingUpdates)?;
+        self.apply_updates(&updates);
+        self.check_root(bul.get_root().await)
+    }
+}
+
+impl<F: PrimeField + Absorb, H: FieldHash<F>, const D: usize>
+    WitnessTracker<F, H, IndexedLeaf<F>, D>
+{
+    /// Get the membership witness of a called ticket from the tracked leaves.
+    pub fn get_memb_witness(&self, tik: F) -> Option<IndexedMembWitness<F, D>> {
+        self.witnesses
+            .values()
+            .find(|(leaf, _)| leaf.key == tik)
+            .map(|(leaf, path)| IndexedMembWitness {
+                path: path.clone(),
+                next: leaf.next,
+            })
+    }
+
+    /// Get the nonmembership witness of a ticket from the tracked leaves.
+    ///
+    /// If a ticket was inserted between the tracked low leaf and the ticket, the tracked leaf is
+    /// no longer the low leaf, and this returns `None`. A fresh witness must then be fetched.
+    pub fn get_nmemb_witness(&self, tik: F) -> Option<IndexedNonMembWitness<F, D>> {
+        self.witnesses
+            .values()
+            .find(|(leaf, _)| leaf.key < tik && (tik < leaf.next || leaf.next == F::ZERO))
+            .map(|(leaf, path)| IndexedNonMembWitness {
+                path: path.clone(),
+                low: *leaf,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::impls::{
+        centralized::crypto::FakeSigPubkey, decentralized::ds::treestore::IndexedCallbackStore,
+        hash::Poseidon,
+    };
+    use ark_bn254::Fr;
+
+    type Store = IndexedCallbackStore<Fr, Fr, 4>;
+    type Tracker = WitnessTracker<Fr, Poseidon<2>, IndexedLeaf<Fr>, 4>;
+
+    fn insert(store: &mut Store, key: u64) {
+        store
+            .insert(
+                FakeSigPubkey::new(Fr::from(key)),
+                Fr::from(key),
+                Fr::from(0),
+            )
+            .unwrap();
+    }
+
+    // Tests that tracked membership and nonmembership witnesses match fresh witnesses after
+    // refreshing
+    #[test]
+    fn tracker_refresh() {
+        let mut store = Store::new();
+        insert(&mut store, 10);
+        insert(&mut store, 30);
+
+        let mut tracker = Tracker::from_bulletin(&store);
+        let memb = store
+            .get_memb_witness(&FakeSigPubkey::new(Fr::from(10)))
+            .unwrap();
+        assert!(tracker.track(store.leaves[memb.path.index as usize], memb.path));
+        let nmemb = store
+            .get_nmemb_witness(&FakeSigPubkey::new(Fr::from(40)))
+            .unwrap();
+        assert!(tracker.track(nmemb.low, nmemb.path));
+
+        insert(&mut store, 20);
+        insert(&mut store, 5);
+        tracker.refresh(&store).unwrap();
+        assert_eq!(tracker.version, store.get_version());
+        assert_eq!(tracker.root, store.get_root());
+
+        let fresh = store
+            .get_memb_witness(&FakeSigPubkey::new(Fr::from(10)))
+            .unwrap();
+        let tracked = tracker.get_memb_witness(Fr::from(10)).unwrap();
+        assert_eq!(tracked.path, fresh.path);
+        assert_eq!(tracked.next, Fr::from(20));
+
+        // The leaf of 10 is still the low leaf of 15, and the leaf of 30 of 40
+        for key in [15, 40] {
+            let fresh = store
+                .get_nmemb_witness(&FakeSigPubkey::new(Fr::from(key)))
+                .unwrap();
+            let tracked = tracker.get_nmemb_witness(Fr::from(key)).unwrap();
+            assert_eq!(tracked.low, fresh.low);
+            assert_eq!(tracked.path, fresh.path);
+        }
+
+        // 20 was inserted after 10, so the tracked leaf no longer covers 25
+        assert!(tracker.get_nmemb_witness(Fr::from(25)).is_none());
+    }
+
+    // Tests that the tracker rejects invalid paths, and bulletins which are behind or disagree
+    #[test]
+    fn tracker_errors() {
+        let mut store = Store::new();
+        insert(&mut store, 10);
+
+        let mut tracker = Tracker::from_bulletin(&store);
+        let memb = store
+            .get_memb_witness(&FakeSigPubkey::new(Fr::from(10)))
+            .unwrap();
+        let mut leaf = store.leaves[memb.path.index as usize];
+        leaf.data += Fr::from(1);
+        assert!(!tracker.track(leaf, memb.path));
+        assert!(tracker.witnesses.is_empty());
+
+        let mut ahead = Tracker::new(store.get_version() + 1, store.get_root());
+        assert_eq!(ahead.refresh(&store), Err(TrackerError::MissingUpdates));
+
+        let mut wrong = Tracker::new(store.get_version(), Fr::from(0));
+        assert_eq!(wrong.refresh(&store), Err(TrackerError::RootMismatch));
+    }
+}
@@ -0,0 +1,430 @@
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, PrimeField, ToConstraintField, UniformRand};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    convert::{ToBitsGadget, ToConstraintFieldGadget},
+    eq::EqGadget,
+    fields::fp::FpVar,
+    groups::CurveVar,
+    R1CSVar,
+};
+use ark_relations::{
+    ns,
+    r1cs::{Namespace, SynthesisError},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{distributions::Standard, prelude::Distribution, thread_rng, CryptoRng, RngCore};
+use std::{borrow::Borrow, collections::HashMap, marker::PhantomData};
+
+use crate::{
+    crypto::{
+        enc::{AECipherSigZK, CPACipher},
+        rr::{RRSigner, RRVerifier},
+    },
+    impls::decentralized::crypto::{SchnorrPrivkey, SchnorrPubkey, SchnorrPubkeyVar, SchnorrSig},
+};
+
+/// The number of bits in a plaintext. Messages must be in `[0, 2^MSG_BITS)`, so they can be
+/// recovered with a discrete logarithm.
+pub const MSG_BITS: usize = 32;
+
+/// A twisted Edwards curve embedded in the field `F`, along with its in-circuit representation.
+pub trait EmbeddedCurve<F: PrimeField>: CurveGroup<BaseField = F> {
+    /// The curve in-circuit.
+    type Var: CurveVar<Self, F>;
+}
+
+impl EmbeddedCurve<ark_bls12_381::Fr> for ark_ed_on_bls12_381::EdwardsProjective {
+    type Var = ark_ed_on_bls12_381::constraints::EdwardsVar;
+}
+
+impl EmbeddedCurve<ark_bn254::Fr> for ark_ed_on_bn254::EdwardsProjective {
+    type Var = ark_ed_on_bn254::constraints::EdwardsVar;
+}
+
+impl EmbeddedCurve<ark_bls12_377::Fr> for ark_ed_on_bls12_377::EdwardsProjective {
+    type Var = ark_ed_on_bls12_377::constraints::EdwardsVar;
+}
+
+// Converts a message into a scalar of the curve.
+fn to_scalar<F: PrimeField, C: CurveGroup>(m: &F) -> C::ScalarField {
+    C::ScalarField::from_le_bytes_mod_order(&m.into_bigint().to_bytes_le())
+}
+
+// Finds `m` in `[0, 2^MSG_BITS)` with `m G = p`, with baby-step giant-step.
+fn discrete_log<C: CurveGroup>(p: C) -> Option<u64> {
+    let steps = 1u64 << (MSG_BITS / 2);
+    let g = C::generator();
+
+    let mut baby = HashMap::new();
+    let mut acc = C::zero();
+    for j in 0..steps {
+        baby.insert(acc.into_affine(), j);
+        acc += g;
+    }
+
+    // acc is now steps G
+    let mut giant = p;
+    for i in 0..steps {
+        if let Some(j) = baby.get(&giant.into_affine()) {
+            return Some(i * steps + j);
+        }
+        giant -= acc;
+    }
+    None
+}
+
+/// An ElGamal public key, which may encrypt to the holder of an [`ElGamalKey`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ElGamalPubkey<C: CurveGroup> {
+    key: C::Affine,
+}
+
+impl<C: CurveGroup> ElGamalPubkey<C> {
+    /// Encrypt each message in `[0, 2^MSG_BITS)` "in the exponent", with fresh randomness.
+    pub fn encrypt<F: PrimeField, const N: usize>(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        message: &[F; N],
+    ) -> ElGamalCiphertext<C, N> {
+        let g = C::generator();
+        ElGamalCiphertext(
+            message
+                .iter()
+                .map(|m| {
+                    let r = C::ScalarField::rand(rng);
+                    (
+                        (g * r).into_affine(),
+                        (g * to_scalar::<F, C>(m) + self.key * r).into_affine(),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+/// An exponential ElGamal key over an embedded curve. Implements [`CPACipher`].
+///
+/// Each argument is encrypted separately as `(r G, m G + r H)`, where `H` is the public key, so
+/// decryption recovers `m G` and then `m` with a discrete logarithm. Arguments must be in
+/// `[0, 2^MSG_BITS)`.
+///
+/// Since the curve is embedded in `F`, decryption in-circuit is native: the circuit checks
+/// `m G = C_2 - sk C_1` with two scalar multiplications per argument, rather than
+/// re-deriving a keystream.
+///
+/// Encryption only uses the public key (see [`ElGamalKey::pubkey`]), so anyone holding it may
+/// encrypt arguments to the user. Note that within a callback ticket, the whole key is shared with
+/// the service as with any other [`CPACipher`].
+#[derive(Clone, Debug, PartialEq, Eq, Default, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ElGamalKey<C: CurveGroup, const N: usize> {
+    sk: C::ScalarField,
+    pk: ElGamalPubkey<C>,
+}
+
+impl<C: CurveGroup, const N: usize> ElGamalKey<C, N> {
+    /// Get the public key, which encrypts to this key.
+    pub fn pubkey(&self) -> ElGamalPubkey<C> {
+        self.pk
+    }
+}
+
+impl<F: PrimeField, C: CurveGroup, const N: usize> ToConstraintField<F> for ElGamalKey<C, N> {
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        Some(vec![F::from_le_bytes_mod_order(
+            &self.sk.into_bigint().to_bytes_le(),
+        )])
+    }
+}
+
+/// An ElGamal key in-circuit.
+#[derive(Clone)]
+pub struct ElGamalKeyVar<F: PrimeField, C: EmbeddedCurve<F>, const N: usize> {
+    sk: FpVar<F>,
+    _curve: PhantomData<C>,
+}
+
+impl<F: PrimeField, C: EmbeddedCurve<F>, const N: usize> AllocVar<ElGamalKey<C, N>, F>
+    for ElGamalKeyVar<F, C, N>
+{
+    fn new_variable<T: Borrow<ElGamalKey<C, N>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let sk = FpVar::new_variable(
+            ns!(cs, "sk"),
+            || {
+                f().map(|k| {
+                    <ElGamalKey<C, N> as ToConstraintField<F>>::to_field_elements(k.borrow())
+                        .unwrap()[0]
+                })
+            },
+            mode,
+        )?;
+        Ok(Self {
+            sk,
+            _curve: PhantomData,
+        })
+    }
+}
+
+impl<F: PrimeField, C: EmbeddedCurve<F>, const N: usize> ToConstraintFieldGadget<F>
+    for ElGamalKeyVar<F, C, N>
+{
+    fn to_constraint_field(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(vec![self.sk.clone()])
+    }
+}
+
+/// An exponential ElGamal ciphertext, as a pair of points per argument.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ElGamalCiphertext<C: CurveGroup, const N: usize>(pub Vec<(C::Affine, C::Affine)>);
+
+impl<C: CurveGroup, const N: usize> Default for ElGamalCiphertext<C, N> {
+    fn default() -> Self {
+        Self(vec![(C::Affine::zero(), C::Affine::zero()); N])
+    }
+}
+
+impl<F: PrimeField, C: CurveGroup, const N: usize> ToConstraintField<F> for ElGamalCiphertext<C, N>
+where
+    C::Affine: ToConstraintField<F>,
+{
+    fn to_field_elements(&self) -> Option<Vec<F>> {
+        let mut elems = vec![];
+        for (c1, c2) in &self.0 {
+            elems.extend(c1.to_field_elements()?);
+            elems.extend(c2.to_field_elements()?);
+        }
+        Some(elems)
+    }
+}
+
+/// An ElGamal ciphertext in-circuit.
+#[derive(Clone)]
+pub struct ElGamalCiphertextVar<F: PrimeField, C: EmbeddedCurve<F>, const N: usize>(
+    pub Vec<(C::Var, C::Var)>,
+);
+
+impl<F: PrimeField, C: EmbeddedCurve<F>, const N: usize> AllocVar<ElGamalCiphertext<C, N>, F>
+    for ElGamalCiphertextVar<F, C, N>
+{
+    fn new_variable<T: Borrow<ElGamalCiphertext<C, N>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let ct = f().map(|ct| ct.borrow().clone());
+        let mut vars = vec![];
+        for i in 0..N {
+            let point = |j: usize| {
+                ct.as_ref()
+                    .map(|ct| {
+                        let pair = ct.0[i];
+                        if j == 0 {
+                            pair.0
+                        } else {
+                            pair.1
+                        }
+                    })
+                    .map_err(|_| SynthesisError::AssignmentMissing)
+            };
+            let c1 =
+                <C::Var as AllocVar<C::Affine, F>>::new_variable(ns!(cs, "c1"), || point(0), mode)?;
+            let c2 =
+                <C::Var as AllocVar<C::Affine, F>>::new_variable(ns!(cs, "c2"), || point(1), mode)?;
+            vars.push((c1, c2));
+        }
+        Ok(Self(vars))
+    }
+}
+
+impl<F: PrimeField, C: EmbeddedCurve<F>, const N: usize> CPACipher<F> for ElGamalKey<C, N> {
+    type M = [F; N];
+    type C = ElGamalCiphertext<C, N>;
+    type MV = [FpVar<F>; N];
+    type CV = ElGamalCiphertextVar<F, C, N>;
+
+    type KeyVar = ElGamalKeyVar<F, C, N>;
+
+    fn keygen(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        // The key is read as a field element in-circuit, so it must fit in F
+        let sk = C::ScalarField::rand(rng);
+        Self {
+            sk,
+            pk: ElGamalPubkey {
+                key: (C::generator() * sk).into_affine(),
+            },
+        }
+    }
+
+    fn encrypt(&self, message: Self::M) -> Self::C {
+        self.pk.encrypt(&mut thread_rng(), &message)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if some argument is not in `[0, 2^MSG_BITS)`.
+    fn decrypt(&self, ciphertext: Self::C) -> Self::M {
+        let msg = ciphertext
+            .0
+            .iter()
+            .map(|(c1, c2)| {
+                let m =
+                    discrete_log(c2.into_group() - *c1 * self.sk).expect("argument out of range");
+                F::from(m)
+            })
+            .collect::<Vec<_>>();
+        msg.try_into().unwrap()
+    }
+
+    fn decrypt_in_zk(key: Self::KeyVar, ciphertext: Self::CV) -> Result<Self::MV, SynthesisError> {
+        let sk_bits = key.sk.to_bits_le()?;
+        let sk = key.sk.value().ok().map(|sk| to_scalar::<F, C>(&sk));
+        let g = C::Var::constant(C::generator());
+
+        let mut msg = vec![];
+        for (c1, c2) in ciphertext.0 {
+            let point = c2.clone() - c1.scalar_mul_le(sk_bits.iter())?;
+
+            // Witness the discrete logarithm, and check it
+            let m = match (sk, c1.value(), c2.value()) {
+                (Some(sk), Ok(c1), Ok(c2)) => discrete_log(c2 - c1 * sk),
+                _ => None,
+            };
+            let m_bits = (0..MSG_BITS)
+                .map(|j| {
+                    Boolean::new_witness(ns!(key.sk.cs(), "m_bit"), || {
+                        m.map(|m| (m >> j) & 1 == 1)
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            g.scalar_mul_le(m_bits.iter())?.enforce_equal(&point)?;
+
+            msg.push(Boolean::le_bits_to_fp(&m_bits)?);
+        }
+        Ok(msg.try_into().unwrap())
+    }
+}
+
+impl<E: CurveGroup, C: CurveGroup, const N: usize>
+    RRVerifier<SchnorrSig<E>, ElGamalCiphertext<C, N>, E::ScalarField> for SchnorrPubkey<E>
+where
+    Standard: Distribution<E::ScalarField>,
+{
+    fn verify(&self, message: ElGamalCiphertext<C, N>, signature: SchnorrSig<E>) -> bool {
+        let mut msg = vec![];
+        message.serialize_compressed(&mut msg).unwrap();
+        self.verify_bytes(&msg, &signature)
+    }
+
+    fn rerand(&self, rng: &mut (impl CryptoRng + RngCore)) -> (E::ScalarField, Self) {
+        self.rerandomize(rng)
+    }
+}
+
+impl<E: CurveGroup, C: CurveGroup, const N: usize>
+    RRSigner<SchnorrSig<E>, ElGamalCiphertext<C, N>, E::ScalarField, SchnorrPubkey<E>>
+    for SchnorrPrivkey<E>
+where
+    Standard: Distribution<E::ScalarField>,
+{
+    type Vk = SchnorrPubkey<E>;
+
+    fn sign_message(&self, message: &ElGamalCiphertext<C, N>) -> SchnorrSig<E> {
+        let mut msg = vec![];
+        message.serialize_compressed(&mut msg).unwrap();
+        self.sign_bytes(&msg)
+    }
+
+    fn sk_to_pk(&self) -> SchnorrPubkey<E> {
+        self.pubkey()
+    }
+
+    fn gen(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        Self::sample(rng)
+    }
+
+    fn rerand(&self, randomness: E::ScalarField) -> Self {
+        self.rerandomize(randomness)
+    }
+}
+
+/// This type implements AECipherSigZK. This uses exponential ElGamal over the embedded curve `C`
+/// for encryption of arguments, and signs the ciphertext with a rerandomizable Schnorr signature
+/// over `E`.
+///
+/// This may be used in place of
+/// [`StreamSchnorr`](`crate::impls::decentralized::crypto::StreamSchnorr`) when arguments are
+/// small nonnegative integers, and should be encrypted under a public key.
+#[derive(Clone, Debug)]
+pub struct ElGamalSchnorr<
+    F: PrimeField + Absorb,
+    C: EmbeddedCurve<F>,
+    E: CurveGroup,
+    const N: usize,
+> {
+    phantom: PhantomData<[F; N]>,
+    phantom_c: PhantomData<C>,
+    phantom_e: PhantomData<E>,
+}
+
+impl<F: PrimeField + Absorb, C: EmbeddedCurve<F>, E: CurveGroup, const N: usize>
+    AECipherSigZK<F, [F; N]> for ElGamalSchnorr<F, C, E, N>
+where
+    Standard: Distribution<E::ScalarField>,
+{
+    type Ct = ElGamalCiphertext<C, N>;
+
+    type AV = [FpVar<F>; N];
+
+    type EncKey = ElGamalKey<C, N>;
+
+    type EncKeyVar = ElGamalKeyVar<F, C, N>;
+
+    type Sig = SchnorrSig<E>;
+
+    type Rand = E::ScalarField;
+
+    type SigPK = SchnorrPubkey<E>;
+
+    type SigPKV = SchnorrPubkeyVar<F>;
+
+    type SigSK = SchnorrPrivkey<E>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ark_bls12_381::Fr;
+    use ark_ed_on_bls12_381::EdwardsProjective as Jubjub;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    // Tests that decryption natively and in-circuit agree
+    #[test]
+    fn elgamal_decrypt() {
+        let mut rng = thread_rng();
+        let key = <ElGamalKey<Jubjub, 2> as CPACipher<Fr>>::keygen(&mut rng);
+        let msg = [Fr::from(7u8), Fr::from(123456u32)];
+        let ct = key.encrypt(msg);
+        assert_eq!(key.decrypt(ct.clone()), msg);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let key_var =
+            ElGamalKeyVar::<Fr, Jubjub, 2>::new_witness(ns!(cs, "key"), || Ok(key)).unwrap();
+        let ct_var =
+            ElGamalCiphertextVar::<Fr, Jubjub, 2>::new_input(ns!(cs, "ct"), || Ok(ct)).unwrap();
+        let dec = <ElGamalKey<Jubjub, 2> as CPACipher<Fr>>::decrypt_in_zk(key_var, ct_var).unwrap();
+        assert_eq!(dec.value().unwrap(), msg);
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,655 @@
+use crate::{
+    generic::{
+        bulletin::{PublicCallbackBul, PublicUserBul},
+        object::{Com, ComVar, Nul, Time, TimeVar},
+        user::UserData,
+    },
+    impls::{
+        centralized::crypto::{FakeSigPubkey, FakeSigPubkeyVar, NoSigOTP},
+        decentralized::ds::{
+            tree::{MerklePath, MerklePathVar},
+            treestore::{
+                IndexedCallbackStore, IndexedMembWitness, IndexedMembWitnessVar,
+                IndexedNonMembWitness, IndexedNonMembWitnessVar, MerkleObjStore,
+            },
+        },
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::Boolean};
+use ark_relations::r1cs::SynthesisError;
+use rand::distributions::{Distribution, Standard};
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+
+/// An error when talking to an EVM bulletin contract.
+#[derive(Debug)]
+pub enum EvmError {
+    /// The request could not be sent, or the response could not be read.
+    Request(reqwest::Error),
+    /// The node responded with a JSON-RPC error.
+    Rpc(String),
+    /// The response was not in the expected format.
+    Malformed,
+    /// The leaves read from the contract logs produce a different root than the contract.
+    RootMismatch,
+    /// The local mirror could not store a leaf (for example, the tree is full).
+    Store,
+}
+
+impl std::fmt::Display for EvmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvmError::Request(e) => write!(f, "request failed: {}", e),
+            EvmError::Rpc(e) => write!(f, "rpc error: {}", e),
+            EvmError::Malformed => write!(f, "malformed response"),
+            EvmError::RootMismatch => write!(f, "local root does not match the contract"),
+            EvmError::Store => write!(f, "could not store leaf"),
+        }
+    }
+}
+
+impl std::error::Error for EvmError {}
+
+/// Compute the 4 byte selector of a function, or the topic of an event, from its signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let h = Keccak256::digest(signature.as_bytes());
+    [h[0], h[1], h[2], h[3]]
+}
+
+fn topic(signature: &str) -> String {
+    to_hex(&Keccak256::digest(signature.as_bytes()))
+}
+
+/// Encode a field element as a big endian `uint256` word.
+pub fn encode_word<F: PrimeField>(f: F) -> [u8; 32] {
+    let bytes = f.into_bigint().to_bytes_be();
+    let mut out = [0u8; 32];
+    out[32 - bytes.len()..].copy_from_slice(&bytes);
+    out
+}
+
+/// Decode a big endian `uint256` word into a field element, reducing modulo the field.
+pub fn decode_word<F: PrimeField>(word: &[u8]) -> F {
+    F::from_be_bytes_mod_order(word)
+}
+
+fn encode_usize(x: usize) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out[24..].copy_from_slice(&(x as u64).to_be_bytes());
+    out
+}
+
+fn decode_usize(word: &[u8]) -> Option<usize> {
+    if word.len() != 32 || word[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+    usize::try_from(u64::from_be_bytes(word[24..].try_into().ok()?)).ok()
+}
+
+fn word(data: &[u8], i: usize) -> Option<&[u8]> {
+    data.get(32 * i..32 * (i + 1))
+}
+
+/// ABI encode a call with some static words, followed by a single dynamic `uint256[]`.
+fn encode_call<F: PrimeField>(
+    signature: &str,
+    head: &[F],
+    array: &[F],
+    array_pos: usize,
+) -> Vec<u8> {
+    let mut out = selector(signature).to_vec();
+    let nhead = head.len() + 1;
+    let mut h = head.iter();
+    for i in 0..nhead {
+        if i == array_pos {
+            out.extend_from_slice(&encode_usize(32 * nhead));
+        } else {
+            out.extend_from_slice(&encode_word(*h.next().unwrap()));
+        }
+    }
+    out.extend_from_slice(&encode_usize(array.len()));
+    for x in array {
+        out.extend_from_slice(&encode_word(*x));
+    }
+    out
+}
+
+/// ABI decode some static words, followed by a single dynamic `uint256[]` at some position.
+#[allow(clippy::type_complexity)]
+fn decode_event<F: PrimeField>(
+    data: &[u8],
+    nhead: usize,
+    array_pos: usize,
+) -> Option<(Vec<F>, Vec<F>)> {
+    let mut head = vec![];
+    let mut array = vec![];
+    for i in 0..nhead {
+        let w = word(data, i)?;
+        if i == array_pos {
+            let offset = decode_usize(w)?;
+            let len = decode_usize(data.get(offset..offset + 32)?)?;
+            for j in 0..len {
+                array.push(decode_word(word(&data[offset + 32..], j)?));
+            }
+        } else {
+            head.push(decode_word(w));
+        }
+    }
+    Some((head, array))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::from("0x");
+    for b in bytes {
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    let s = s.strip_prefix("0x")?;
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// A JSON-RPC connection to a bulletin contract on an EVM chain.
+///
+/// The contract is expected to expose the following interface, where all field elements are
+/// encoded as big endian `uint256`s:
+///
+/// ```solidity
+/// function objectRoot() external view returns (uint256);
+/// function callbackRoot() external view returns (uint256);
+/// function appendObject(uint256 com, uint256 nul, uint256[] cbComs) external;
+/// function appendCallback(uint256 tik, uint256[] args, uint256 time) external;
+///
+/// event ObjectAppended(uint256 com, uint256 nul, uint256[] cbComs);
+/// event CallbackAppended(uint256 tik, uint256[] args, uint256 time);
+/// ```
+///
+/// The contract maintains the Merkle roots of a [`MerkleObjStore`] and an
+/// [`IndexedCallbackStore`] of the same depth, using Poseidon.
+#[derive(Clone, Debug)]
+pub struct EvmRpc {
+    /// The HTTP client used to talk to the node.
+    pub client: Client,
+    /// The url of the JSON-RPC endpoint of the node.
+    pub rpc: String,
+    /// The address of the bulletin contract.
+    pub contract: [u8; 20],
+}
+
+impl EvmRpc {
+    /// Construct a new connection to a contract through a node.
+    pub fn new(rpc: String, contract: [u8; 20]) -> Self {
+        Self {
+            client: Client::new(),
+            rpc,
+            contract,
+        }
+    }
+
+    fn request(&self, method: &str, params: Value) -> Result<Value, EvmError> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let res = self
+            .client
+            .post(&self.rpc)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .map_err(EvmError::Request)?;
+        let bytes = res.bytes().map_err(EvmError::Request)?;
+        let mut v: Value = serde_json::from_slice(&bytes).map_err(|_| EvmError::Malformed)?;
+        if let Some(e) = v.get("error") {
+            return Err(EvmError::Rpc(e.to_string()));
+        }
+        v.get_mut("result")
+            .map(Value::take)
+            .ok_or(EvmError::Malformed)
+    }
+
+    /// Call a view function of the contract, returning the raw output.
+    pub fn call(&self, data: &[u8]) -> Result<Vec<u8>, EvmError> {
+        let res = self.request(
+            "eth_call",
+            json!([{ "to": to_hex(&self.contract), "data": to_hex(data) }, "latest"]),
+        )?;
+        res.as_str().and_then(from_hex).ok_or(EvmError::Malformed)
+    }
+
+    /// Call a view function of the contract which takes no arguments and returns a `uint256`.
+    pub fn call_word<F: PrimeField>(&self, signature: &str) -> Result<F, EvmError> {
+        let out = self.call(&selector(signature))?;
+        word(&out, 0).map(decode_word).ok_or(EvmError::Malformed)
+    }
+
+    /// Get the latest block number.
+    pub fn block_number(&self) -> Result<u64, EvmError> {
+        let res = self.request("eth_blockNumber", json!([]))?;
+        res.as_str()
+            .and_then(|s| u64::from_str_radix(s.strip_prefix("0x")?, 16).ok())
+            .ok_or(EvmError::Malformed)
+    }
+
+    /// Get the data of all logs of an event emitted by the contract within some block range, in
+    /// order.
+    pub fn logs(&self, event: &str, from: u64, to: u64) -> Result<Vec<Vec<u8>>, EvmError> {
+        let res = self.request(
+            "eth_getLogs",
+            json!([{
+                "address": to_hex(&self.contract),
+                "fromBlock": format!("0x{:x}", from),
+                "toBlock": format!("0x{:x}", to),
+                "topics": [topic(event)],
+            }]),
+        )?;
+        res.as_array()
+            .ok_or(EvmError::Malformed)?
+            .iter()
+            .map(|l| {
+                l.get("data")
+                    .and_then(Value::as_str)
+                    .and_then(from_hex)
+                    .ok_or(EvmError::Malformed)
+            })
+            .collect()
+    }
+}
+
+const OBJECT_ROOT: &str = "objectRoot()";
+const CALLBACK_ROOT: &str = "callbackRoot()";
+const APPEND_OBJECT: &str = "appendObject(uint256,uint256,uint256[])";
+const APPEND_CALLBACK: &str = "appendCallback(uint256,uint256[],uint256)";
+const OBJECT_APPENDED: &str = "ObjectAppended(uint256,uint256,uint256[])";
+const CALLBACK_APPENDED: &str = "CallbackAppended(uint256,uint256[],uint256)";
+
+/// An object bulletin stored in an EVM contract (see [`EvmRpc`]).
+///
+/// The contract only stores the root. The leaves are read from the `ObjectAppended` logs into a
+/// local [`MerkleObjStore`] with [`EvmObjBul::sync`], which is then used to answer membership
+/// queries. Appends are submitted as transactions by the user, with calldata from
+/// [`EvmObjBul::append_calldata`].
+#[derive(Clone, Debug)]
+pub struct EvmObjBul<F: PrimeField + Absorb, const D: usize> {
+    /// The connection to the contract.
+    pub rpc: EvmRpc,
+    /// The local mirror of the tree.
+    pub store: MerkleObjStore<F, D>,
+    /// The next block to read logs from.
+    pub next_block: u64,
+}
+
+impl<F: PrimeField + Absorb, const D: usize> EvmObjBul<F, D> {
+    /// Construct a new bulletin, reading logs from the block in which the contract was deployed.
+    pub fn new(rpc: EvmRpc, deploy_block: u64) -> Self {
+        Self {
+            rpc,
+            store: MerkleObjStore::new(),
+            next_block: deploy_block,
+        }
+    }
+
+    /// Fetch the current root from the contract.
+    pub fn fetch_root(&self) -> Result<F, EvmError> {
+        self.rpc.call_word(OBJECT_ROOT)
+    }
+
+    /// Read all new objects from the contract logs into the local mirror, and check the mirror
+    /// against the root of the contract.
+    pub fn sync(&mut self) -> Result<(), EvmError> {
+        let to = self.rpc.block_number()?;
+        if to >= self.next_block {
+            for data in self.rpc.logs(OBJECT_APPENDED, self.next_block, to)? {
+                let (head, cbs) = decode_event::<F>(&data, 3, 2).ok_or(EvmError::Malformed)?;
+                self.store
+                    .push(head[0], head[1], cbs)
+                    .map_err(|_| EvmError::Store)?;
+            }
+            self.next_block = to + 1;
+        }
+        if self.store.get_root() != self.fetch_root()? {
+            return Err(EvmError::RootMismatch);
+        }
+        Ok(())
+    }
+
+    /// Format the calldata for appending an object to the contract.
+    pub fn append_calldata(object: Com<F>, old_nul: Nul<F>, cb_com_list: &[Com<F>]) -> Vec<u8> {
+        encode_call(APPEND_OBJECT, &[object, old_nul], cb_com_list, 2)
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, const D: usize> PublicUserBul<F, U>
+    for EvmObjBul<F, D>
+{
+    type MembershipWitness = MerklePath<F, D>;
+
+    type MembershipWitnessVar = MerklePathVar<F, D>;
+
+    type MembershipPub = F;
+
+    type MembershipPubVar = FpVar<F>;
+
+    fn verify_in<PubArgs: ToConstraintField<F>, Snark: ark_snark::SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        args: PubArgs,
+        proof: Snark::Proof,
+        memb_data: Self::MembershipPub,
+        verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        <MerkleObjStore<F, D> as PublicUserBul<F, U>>::verify_in::<PubArgs, Snark, NUMCBS>(
+            &self.store,
+            object,
+            old_nul,
+            cb_com_list,
+            args,
+            proof,
+            memb_data,
+            verif_key,
+        )
+    }
+
+    fn get_membership_data(&self, object: Com<F>) -> Option<(F, MerklePath<F, D>)> {
+        <MerkleObjStore<F, D> as PublicUserBul<F, U>>::get_membership_data(&self.store, object)
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <MerkleObjStore<F, D> as PublicUserBul<F, U>>::enforce_membership_of(
+            data_var,
+            extra_witness,
+            extra_pub,
+        )
+    }
+}
+
+/// A callback bulletin stored in an EVM contract (see [`EvmRpc`]).
+///
+/// The contract only stores the root. The called tickets are read from the `CallbackAppended` logs
+/// into a local [`IndexedCallbackStore`] with [`EvmCallbackBul::sync`], which is then used to
+/// answer membership and nonmembership queries. Calls are submitted as transactions, with
+/// calldata from [`EvmCallbackBul::append_calldata`].
+#[derive(Clone, Debug)]
+pub struct EvmCallbackBul<F: PrimeField + Absorb, const D: usize> {
+    /// The connection to the contract.
+    pub rpc: EvmRpc,
+    /// The local mirror of the tree.
+    pub store: IndexedCallbackStore<F, F, D>,
+    /// The next block to read logs from.
+    pub next_block: u64,
+}
+
+impl<F: PrimeField + Absorb, const D: usize> EvmCallbackBul<F, D> {
+    /// Construct a new bulletin, reading logs from the block in which the contract was deployed.
+    pub fn new(rpc: EvmRpc, deploy_block: u64) -> Self {
+        Self {
+            rpc,
+            store: IndexedCallbackStore::new(),
+            next_block: deploy_block,
+        }
+    }
+
+    /// Fetch the current root from the contract.
+    pub fn fetch_root(&self) -> Result<F, EvmError> {
+        self.rpc.call_word(CALLBACK_ROOT)
+    }
+
+    /// Read all new called tickets from the contract logs into the local mirror, and check the
+    /// mirror against the root of the contract.
+    pub fn sync(&mut self) -> Result<(), EvmError> {
+        let to = self.rpc.block_number()?;
+        if to >= self.next_block {
+            for data in self.rpc.logs(CALLBACK_APPENDED, self.next_block, to)? {
+                let (head, args) = decode_event::<F>(&data, 3, 1).ok_or(EvmError::Malformed)?;
+                let arg = *args.first().ok_or(EvmError::Malformed)?;
+                self.store
+                    .insert(FakeSigPubkey::new(head[0]), arg, head[1])
+                    .map_err(|_| EvmError::Store)?;
+            }
+            self.next_block = to + 1;
+        }
+        if self.store.get_root() != self.fetch_root()? {
+            return Err(EvmError::RootMismatch);
+        }
+        Ok(())
+    }
+
+    /// Format the calldata for calling a ticket on the contract.
+    pub fn append_calldata(tik: FakeSigPubkey<F>, args: F, time: Time<F>) -> Vec<u8> {
+        encode_call(APPEND_CALLBACK, &[tik.to(), time], &[args], 1)
+    }
+}
+
+impl<F: PrimeField + Absorb, const D: usize> PublicCallbackBul<F, F, NoSigOTP<F>>
+    for EvmCallbackBul<F, D>
+where
+    Standard: Distribution<F>,
+{
+    type MembershipWitness = IndexedMembWitness<F, D>;
+
+    type MembershipWitnessVar = IndexedMembWitnessVar<F, D>;
+
+    type NonMembershipWitness = IndexedNonMembWitness<F, D>;
+
+    type NonMembershipWitnessVar = IndexedNonMembWitnessVar<F, D>;
+
+    type MembershipPub = F;
+
+    type MembershipPubVar = FpVar<F>;
+
+    type NonMembershipPub = F;
+
+    type NonMembershipPubVar = FpVar<F>;
+
+    fn verify_in(&self, tik: FakeSigPubkey<F>) -> Option<(F, Time<F>)> {
+        self.store.get_called(&tik)
+    }
+
+    fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
+        self.store.get_nmemb_witness(&tik).is_some()
+    }
+
+    fn get_membership_data(
+        &self,
+        tik: FakeSigPubkey<F>,
+    ) -> (F, IndexedMembWitness<F, D>, F, IndexedNonMembWitness<F, D>) {
+        <IndexedCallbackStore<F, F, D> as PublicCallbackBul<F, F, NoSigOTP<F>>>::get_membership_data(
+            &self.store,
+            tik,
+        )
+    }
+
+    fn enforce_membership_of(
+        tikvar: (FakeSigPubkeyVar<F>, FpVar<F>, TimeVar<F>),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <IndexedCallbackStore<F, F, D> as PublicCallbackBul<F, F, NoSigOTP<F>>>::enforce_membership_of(
+            tikvar,
+            extra_witness,
+            extra_pub,
+        )
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: FakeSigPubkeyVar<F>,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        IndexedCallbackStore::<F, F, D>::enforce_nonmembership(tikvar, extra_witness, extra_pub)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::impls::hash::Poseidon;
+    use ark_bn254::Fr;
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    // Answers JSON-RPC requests with a fixed result per method, on a local port
+    fn serve(results: HashMap<&'static str, Value>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut len = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header == "\r\n" || header.is_empty() {
+                        break;
+                    }
+                    if let Some(v) = header.to_lowercase().strip_prefix("content-length:") {
+                        len = v.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+                let req: Value = serde_json::from_slice(&body).unwrap();
+                let method = req["method"].as_str().unwrap_or_default();
+                let res = match results.get(method) {
+                    Some(r) => json!({ "jsonrpc": "2.0", "id": 1, "result": r }),
+                    None => json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32601 } }),
+                }
+                .to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    res.len(),
+                    res
+                )
+                .unwrap();
+            }
+        });
+        format!("http://{}/", addr)
+    }
+
+    fn rpc(logs: &[Vec<u8>], root: Fr) -> EvmRpc {
+        let logs: Vec<Value> = logs.iter().map(|d| json!({ "data": to_hex(d) })).collect();
+        let url = serve(HashMap::from([
+            ("eth_blockNumber", json!("0x10")),
+            ("eth_getLogs", Value::Array(logs)),
+            ("eth_call", json!(to_hex(&encode_word(root)))),
+        ]));
+        EvmRpc::new(url, [7; 20])
+    }
+
+    // Tests that the ABI encoding of calls decodes as the matching event data
+    #[test]
+    fn evm_abi_roundtrip() {
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+
+        let head = [Fr::from(1), -Fr::from(2)];
+        let array = [Fr::from(3), Fr::from(4), Fr::from(5)];
+        let call = encode_call(APPEND_OBJECT, &head, &array, 2);
+        assert_eq!(&call[..4], &selector(APPEND_OBJECT));
+        assert_eq!(
+            decode_event::<Fr>(&call[4..], 3, 2),
+            Some((head.to_vec(), array.to_vec()))
+        );
+
+        let call = EvmCallbackBul::<Fr, 4>::append_calldata(
+            FakeSigPubkey::new(Fr::from(9)),
+            Fr::from(8),
+            Fr::from(7),
+        );
+        assert_eq!(
+            decode_event::<Fr>(&call[4..], 3, 1),
+            Some((vec![Fr::from(9), Fr::from(7)], vec![Fr::from(8)]))
+        );
+
+        // Truncated data and oversized lengths do not decode
+        assert_eq!(decode_event::<Fr>(&call[4..call.len() - 1], 3, 1), None);
+        assert_eq!(decode_usize(&[0xff; 32]), None);
+        assert_eq!(from_hex(&to_hex(&call)), Some(call));
+        assert_eq!(from_hex("0x123"), None);
+    }
+
+    // Tests that syncing reads the object logs into the local tree, and checks the root
+    #[test]
+    fn evm_obj_sync() {
+        let objs = [(Fr::from(11), Fr::from(12)), (Fr::from(21), Fr::from(22))];
+        let logs: Vec<_> = objs
+            .iter()
+            .map(|(c, n)| EvmObjBul::<Fr, 4>::append_calldata(*c, *n, &[Fr::from(3)])[4..].to_vec())
+            .collect();
+        let mut expected = MerkleObjStore::<Fr, 4>::new();
+        for (c, n) in objs {
+            expected.push(c, n, vec![Fr::from(3)]).unwrap();
+        }
+
+        let mut bul = EvmObjBul::<Fr, 4>::new(rpc(&logs, expected.get_root()), 0);
+        bul.sync().unwrap();
+        assert_eq!(bul.next_block, 0x11);
+        assert_eq!(bul.store.get_root(), expected.get_root());
+        let (root, path) =
+            <EvmObjBul<Fr, 4> as PublicUserBul<Fr, Fr>>::get_membership_data(&bul, objs[1].0)
+                .unwrap();
+        assert!(path.verify::<Poseidon<2>>(objs[1].0, root));
+
+        let mut bad = EvmObjBul::<Fr, 4>::new(rpc(&logs, Fr::from(0)), 0);
+        assert!(matches!(bad.sync(), Err(EvmError::RootMismatch)));
+    }
+
+    // Tests that syncing reads the called tickets into the local indexed tree
+    #[test]
+    fn evm_callback_sync() {
+        let calls = [(Fr::from(30), Fr::from(1)), (Fr::from(10), Fr::from(2))];
+        let logs: Vec<_> = calls
+            .iter()
+            .map(|(t, a)| {
+                EvmCallbackBul::<Fr, 4>::append_calldata(FakeSigPubkey::new(*t), *a, Fr::from(5))
+                    [4..]
+                    .to_vec()
+            })
+            .collect();
+        let mut expected = IndexedCallbackStore::<Fr, Fr, 4>::new();
+        for (t, a) in calls {
+            expected
+                .insert(FakeSigPubkey::new(t), a, Fr::from(5))
+                .unwrap();
+        }
+
+        let mut bul = EvmCallbackBul::<Fr, 4>::new(rpc(&logs, expected.get_root()), 0);
+        bul.sync().unwrap();
+        assert_eq!(
+            bul.verify_in(FakeSigPubkey::new(Fr::from(10))),
+            Some((Fr::from(2), Fr::from(5)))
+        );
+        assert!(bul.verify_not_in(FakeSigPubkey::new(Fr::from(20))));
+        assert!(!bul.verify_not_in(FakeSigPubkey::new(Fr::from(30))));
+
+        let mut bad = EvmCallbackBul::<Fr, 4>::new(rpc(&logs, Fr::from(0)), 0);
+        assert!(matches!(bad.sync(), Err(EvmError::RootMismatch)));
+    }
+}
@@ -1,5 +1,23 @@
 /// The necessary cryptography for callback tickets in a decentralized setting.
 pub mod crypto;
 
+/// Rerandomizable BBS+ signatures, for signing multi-attribute callback arguments.
+pub mod bbs;
+
+/// Exponential ElGamal over embedded curves, for public-key encryption of callback arguments which
+/// can be decrypted natively in-circuit.
+pub mod elgamal;
+
 /// Data structures in the decentralized setting.
 pub mod ds;
+
+/// Callback bulletins which keep tickets and arguments on a data-availability layer or IPFS, and
+/// only content identifiers locally.
+pub mod da;
+
+/// Object and callback bulletins whose roots are stored in a smart contract on an EVM chain, read
+/// through JSON-RPC.
+#[cfg(feature = "evm")]
+#[cfg(any(feature = "evm", doc))]
+#[doc(cfg(feature = "evm"))]
+pub mod evm;
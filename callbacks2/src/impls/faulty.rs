@@ -0,0 +1,520 @@
+use crate::{
+    crypto::enc::{AECipherSigZK, CPACipher},
+    generic::{
+        bulletin::{
+            BulletinDelta, CallbackBul, JoinableBulletin, PublicCallbackBul, PublicUserBul, UserBul,
+        },
+        callbacks::CallbackCom,
+        object::{Com, ComVar, Nul, Time, TimeVar},
+        service::{Called, ServiceProvider},
+        user::{ExecutedMethod, UserData},
+    },
+};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_r1cs_std::{alloc::AllocVar, prelude::Boolean};
+use ark_relations::r1cs::SynthesisError;
+use ark_snark::SNARK;
+use std::marker::PhantomData;
+
+/// Which faults a [`FaultyBul`] injects.
+///
+/// Faults may be toggled at any time, so a test can run part of a flow honestly and then inject a
+/// fault at the step under test.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Faults {
+    /// Report appends (and joins) as successful, but never store them.
+    pub drop_appends: bool,
+    /// Fail appends (and joins) with [`FaultError::Injected`].
+    pub reject_appends: bool,
+    /// Serve reads (membership data, updates, and callback lookups) from the last
+    /// [`snapshot`](`FaultyBul::snapshot`) instead of the current bulletin.
+    pub stale_reads: bool,
+}
+
+/// An error from a [`FaultyBul`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaultError<E> {
+    /// The error was injected.
+    Injected,
+    /// The inner bulletin failed.
+    Inner(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for FaultError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FaultError::Injected => write!(f, "injected fault"),
+            FaultError::Inner(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for FaultError<E> {}
+
+/// A bulletin wrapper which injects faults.
+///
+/// This wraps any user or callback bulletin, and behaves exactly as the inner bulletin unless a
+/// fault is enabled in [`Faults`]. It allows applications to test how their interaction logic
+/// handles lost writes, failed writes, and clients holding stale roots.
+///
+/// Stale reads are served from a snapshot taken with [`snapshot`](`FaultyBul::snapshot`), so a
+/// test controls exactly how stale the data is. Verification and nullifier checks always use the
+/// current bulletin, as an honest verifier would.
+///
+/// # Example
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use rand::thread_rng;
+/// # use zk_callbacks::generic::bulletin::{JoinableBulletin, PublicUserBul};
+/// # use zk_callbacks::generic::reputation::Reputation;
+/// # use zk_callbacks::impls::centralized::ds::sigstore::GRSchnorrObjStore;
+/// # use zk_callbacks::impls::faulty::{FaultyBul, Faults};
+/// type Bul = FaultyBul<GRSchnorrObjStore>;
+/// type U = Reputation<Fr>;
+///
+/// let mut rng = thread_rng();
+/// let mut bul = Bul::new(GRSchnorrObjStore::new(&mut rng));
+///
+/// // A dropped join reports success, but the object never enters the bulletin.
+/// bul.faults.drop_appends = true;
+/// <Bul as JoinableBulletin<Fr, U>>::join_bul(&mut bul, Fr::from(1), ()).unwrap();
+/// assert!(<Bul as PublicUserBul<Fr, U>>::get_membership_data(&bul, Fr::from(1)).is_none());
+/// assert_eq!(bul.dropped, 1);
+///
+/// // With stale reads, a joined object is not visible until the next snapshot.
+/// bul.faults = Faults { stale_reads: true, ..Faults::default() };
+/// bul.snapshot();
+/// <Bul as JoinableBulletin<Fr, U>>::join_bul(&mut bul, Fr::from(2), ()).unwrap();
+/// assert!(<Bul as PublicUserBul<Fr, U>>::get_membership_data(&bul, Fr::from(2)).is_none());
+/// bul.snapshot();
+/// assert!(<Bul as PublicUserBul<Fr, U>>::get_membership_data(&bul, Fr::from(2)).is_some());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FaultyBul<B> {
+    /// The inner bulletin.
+    pub inner: B,
+    /// The faults to inject.
+    pub faults: Faults,
+    /// The number of appends (and joins) which were dropped.
+    pub dropped: usize,
+    stale: Option<B>,
+}
+
+impl<B> FaultyBul<B> {
+    /// Wrap a bulletin, injecting no faults.
+    pub fn new(inner: B) -> Self {
+        Self::with_faults(inner, Faults::default())
+    }
+
+    /// Wrap a bulletin, injecting some faults.
+    pub fn with_faults(inner: B, faults: Faults) -> Self {
+        Self {
+            inner,
+            faults,
+            dropped: 0,
+            stale: None,
+        }
+    }
+
+    /// Forget the snapshot, so stale reads are served from the current bulletin.
+    pub fn clear_snapshot(&mut self) {
+        self.stale = None;
+    }
+
+    fn view(&self) -> &B {
+        match &self.stale {
+            Some(stale) if self.faults.stale_reads => stale,
+            _ => &self.inner,
+        }
+    }
+
+    fn write<E>(&mut self, f: impl FnOnce(&mut B) -> Result<(), E>) -> Result<(), FaultError<E>> {
+        if self.faults.reject_appends {
+            return Err(FaultError::Injected);
+        }
+        if self.faults.drop_appends {
+            self.dropped += 1;
+            return Ok(());
+        }
+        f(&mut self.inner).map_err(FaultError::Inner)
+    }
+}
+
+impl<B: Clone> FaultyBul<B> {
+    /// Take a snapshot of the current bulletin, to serve stale reads from.
+    pub fn snapshot(&mut self) {
+        self.stale = Some(self.inner.clone());
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, B: PublicUserBul<F, U>> PublicUserBul<F, U>
+    for FaultyBul<B>
+{
+    type MembershipWitness = B::MembershipWitness;
+    type MembershipWitnessVar = B::MembershipWitnessVar;
+    type MembershipPub = B::MembershipPub;
+    type MembershipPubVar = B::MembershipPubVar;
+
+    fn verify_in<PubArgs: ToConstraintField<F>, Snark: SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        args: PubArgs,
+        proof: Snark::Proof,
+        memb_data: Self::MembershipPub,
+        verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        self.inner.verify_in::<PubArgs, Snark, NUMCBS>(
+            object,
+            old_nul,
+            cb_com_list,
+            args,
+            proof,
+            memb_data,
+            verif_key,
+        )
+    }
+
+    fn get_membership_data(
+        &self,
+        object: Com<F>,
+    ) -> Option<(Self::MembershipPub, Self::MembershipWitness)> {
+        self.view().get_membership_data(object)
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_membership_of(data_var, extra_witness, extra_pub)
+    }
+
+    fn get_updates_since(
+        &self,
+        version: u64,
+    ) -> Option<BulletinDelta<F, Self::MembershipPub, Self::MembershipWitness>> {
+        self.view().get_updates_since(version)
+    }
+
+    fn anonymity_set_size(&self) -> Option<u64> {
+        self.view().anonymity_set_size()
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, B: UserBul<F, U>> UserBul<F, U> for FaultyBul<B> {
+    type Error = FaultError<B::Error>;
+
+    fn has_never_received_nul(&self, nul: &Nul<F>) -> bool {
+        self.inner.has_never_received_nul(nul)
+    }
+
+    fn append_value<PubArgs: ToConstraintField<F>, Snark: SNARK<F>, const NUMCBS: usize>(
+        &mut self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        args: PubArgs,
+        proof: Snark::Proof,
+        memb_data: Option<Self::MembershipPub>,
+        verif_key: &Snark::VerifyingKey,
+    ) -> Result<(), Self::Error> {
+        self.write(|b| {
+            b.append_value::<PubArgs, Snark, NUMCBS>(
+                object,
+                old_nul,
+                cb_com_list,
+                args,
+                proof,
+                memb_data,
+                verif_key,
+            )
+        })
+    }
+}
+
+impl<F: PrimeField + Absorb, U: UserData<F>, B: JoinableBulletin<F, U>> JoinableBulletin<F, U>
+    for FaultyBul<B>
+{
+    type PubData = B::PubData;
+
+    fn join_bul(&mut self, object: Com<F>, pub_data: B::PubData) -> Result<(), Self::Error> {
+        self.write(|b| b.join_bul(object, pub_data))
+    }
+}
+
+impl<
+        F: PrimeField,
+        Args: Clone,
+        Crypto: AECipherSigZK<F, Args>,
+        B: PublicCallbackBul<F, Args, Crypto>,
+    > PublicCallbackBul<F, Args, Crypto> for FaultyBul<B>
+{
+    type MembershipWitness = B::MembershipWitness;
+    type MembershipWitnessVar = B::MembershipWitnessVar;
+    type NonMembershipWitness = B::NonMembershipWitness;
+    type NonMembershipWitnessVar = B::NonMembershipWitnessVar;
+    type MembershipPub = B::MembershipPub;
+    type MembershipPubVar = B::MembershipPubVar;
+    type NonMembershipPub = B::NonMembershipPub;
+    type NonMembershipPubVar = B::NonMembershipPubVar;
+
+    fn verify_in(&self, tik: Crypto::SigPK) -> Option<(Crypto::Ct, Time<F>)> {
+        self.view().verify_in(tik)
+    }
+
+    fn verify_not_in(&self, tik: Crypto::SigPK) -> bool {
+        self.view().verify_not_in(tik)
+    }
+
+    fn get_membership_data(
+        &self,
+        tik: Crypto::SigPK,
+    ) -> (
+        Self::MembershipPub,
+        Self::MembershipWitness,
+        Self::NonMembershipPub,
+        Self::NonMembershipWitness,
+    ) {
+        self.view().get_membership_data(tik)
+    }
+
+    fn enforce_membership_of(
+        tikvar: (
+            Crypto::SigPKV,
+            <Crypto::EncKey as CPACipher<F>>::CV,
+            TimeVar<F>,
+        ),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_membership_of(tikvar, extra_witness, extra_pub)
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: Crypto::SigPKV,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        B::enforce_nonmembership_of(tikvar, extra_witness, extra_pub)
+    }
+}
+
+impl<
+        F: PrimeField,
+        Args: Clone,
+        Crypto: AECipherSigZK<F, Args>,
+        B: CallbackBul<F, Args, Crypto>,
+    > CallbackBul<F, Args, Crypto> for FaultyBul<B>
+{
+    type Error = FaultError<B::Error>;
+
+    fn has_never_received_tik(&self, tik: &Crypto::SigPK) -> bool {
+        self.inner.has_never_received_tik(tik)
+    }
+
+    fn append_value(
+        &mut self,
+        tik: Crypto::SigPK,
+        enc_args: Crypto::Ct,
+        signature: Crypto::Sig,
+        time: Time<F>,
+    ) -> Result<(), Self::Error> {
+        self.write(|b| b.append_value(tik, enc_args, signature, time))
+    }
+}
+
+/// Which faults a [`TestService`] injects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServiceFaults<Args> {
+    /// Report interactions as stored, but never store them.
+    pub drop_interactions: bool,
+    /// Call every callback with these arguments, instead of the arguments asked for.
+    ///
+    /// This models a malicious or buggy service, for example one passing out-of-range arguments
+    /// to a callback.
+    pub adversarial_args: Option<Args>,
+}
+
+impl<Args> Default for ServiceFaults<Args> {
+    fn default() -> Self {
+        Self {
+            drop_interactions: false,
+            adversarial_args: None,
+        }
+    }
+}
+
+/// An interaction stored by a [`TestService`], along with its callback tickets and the randomness
+/// of their commitments.
+pub type StoredInteraction<F, Args, Crypto, D> = (
+    D,
+    Vec<(
+        CallbackCom<F, Args, Crypto>,
+        <Crypto as AECipherSigZK<F, Args>>::Rand,
+    )>,
+);
+
+/// An in-memory service provider which injects faults.
+///
+/// The service stores every interaction along with its callback tickets, and otherwise behaves as
+/// an honest service unless a fault is enabled in [`ServiceFaults`].
+///
+/// # Example
+/// ```rust
+/// # use ark_bn254::Fr;
+/// # use ark_r1cs_std::fields::fp::FpVar;
+/// # use zk_callbacks::impls::centralized::crypto::NoSigOTP;
+/// # use zk_callbacks::impls::faulty::TestService;
+/// type Service = TestService<Fr, Fr, FpVar<Fr>, NoSigOTP<Fr>, String>;
+///
+/// let mut service = Service::new();
+/// assert_eq!(service.args_for(Fr::from(1)), Fr::from(1));
+/// service.faults.adversarial_args = Some(-Fr::from(1000));
+/// assert_eq!(service.args_for(Fr::from(1)), -Fr::from(1000));
+/// ```
+pub struct TestService<
+    F: PrimeField + Absorb,
+    Args: Clone,
+    ArgsVar: AllocVar<Args, F>,
+    Crypto: AECipherSigZK<F, Args>,
+    D,
+> {
+    /// The stored interactions, along with their callback tickets.
+    pub interactions: Vec<StoredInteraction<F, Args, Crypto, D>>,
+    /// The faults to inject.
+    pub faults: ServiceFaults<Args>,
+    _av: PhantomData<ArgsVar>,
+}
+
+impl<
+        F: PrimeField + Absorb,
+        Args: Clone,
+        ArgsVar: AllocVar<Args, F>,
+        Crypto: AECipherSigZK<F, Args>,
+        D,
+    > TestService<F, Args, ArgsVar, Crypto, D>
+{
+    /// Create a new empty service, injecting no faults.
+    pub fn new() -> Self {
+        Self::with_faults(ServiceFaults::default())
+    }
+
+    /// Create a new empty service, injecting some faults.
+    pub fn with_faults(faults: ServiceFaults<Args>) -> Self {
+        Self {
+            interactions: vec![],
+            faults,
+            _av: PhantomData,
+        }
+    }
+
+    /// Get the arguments a callback is actually called with, given the requested arguments.
+    pub fn args_for(&self, arguments: Args) -> Args {
+        self.faults.adversarial_args.clone().unwrap_or(arguments)
+    }
+
+    /// Get all stored callback tickets, in the order they were received.
+    pub fn tickets(&self) -> impl Iterator<Item = &CallbackCom<F, Args, Crypto>> + '_ {
+        self.interactions
+            .iter()
+            .flat_map(|(_, tiks)| tiks.iter().map(|(cb, _)| cb))
+    }
+}
+
+impl<
+        F: PrimeField + Absorb,
+        Args: Clone,
+        ArgsVar: AllocVar<Args, F>,
+        Crypto: AECipherSigZK<F, Args>,
+        D,
+    > Default for TestService<F, Args, ArgsVar, Crypto, D>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<
+        F: PrimeField + Absorb,
+        Args: Clone,
+        ArgsVar: AllocVar<Args, F>,
+        Crypto: AECipherSigZK<F, Args>,
+        D,
+    > ServiceProvider<F, Args, ArgsVar, Crypto> for TestService<F, Args, ArgsVar, Crypto, D>
+{
+    type Error = ();
+    type InteractionData = D;
+
+    fn call(
+        &self,
+        ticket: CallbackCom<F, Args, Crypto>,
+        arguments: Args,
+        sk: Crypto::SigSK,
+    ) -> Result<Called<F, Args, Crypto>, Self::Error> {
+        let (enc, sig) =
+            Crypto::encrypt_and_sign(self.args_for(arguments), ticket.cb_entry.enc_key, sk);
+        Ok((ticket.cb_entry.tik, enc, sig))
+    }
+
+    fn has_never_received_tik(&self, ticket: Crypto::SigPK) -> bool {
+        self.tickets().all(|cb| cb.cb_entry.tik != ticket)
+    }
+
+    fn store_interaction<U: UserData<F>, Snark: SNARK<F>, const NUMCBS: usize>(
+        &mut self,
+        interaction: ExecutedMethod<F, Snark, Args, Crypto, NUMCBS>,
+        data: D,
+    ) -> Result<(), Self::Error> {
+        if !self.faults.drop_interactions {
+            self.interactions
+                .push((data, interaction.cb_tik_list.to_vec()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::impls::decentralized::ds::treestore::{MerkleObjStore, TreeStoreError};
+    use ark_bn254::Fr;
+
+    type Bul = FaultyBul<MerkleObjStore<Fr, 1>>;
+
+    fn join(bul: &mut Bul, object: u64) -> Result<(), FaultError<TreeStoreError>> {
+        <Bul as JoinableBulletin<Fr, Fr>>::join_bul(bul, Fr::from(object), ())
+    }
+
+    // Tests that rejected writes fail without reaching the bulletin, that dropped writes succeed
+    // without reaching it, and that errors of the bulletin itself are passed through
+    #[test]
+    fn injected_faults() {
+        let mut bul = Bul::new(MerkleObjStore::new());
+
+        bul.faults.reject_appends = true;
+        assert_eq!(join(&mut bul, 1), Err(FaultError::Injected));
+        assert!(bul.inner.coms.is_empty());
+        assert_eq!(bul.dropped, 0);
+
+        bul.faults = Faults {
+            drop_appends: true,
+            ..Faults::default()
+        };
+        assert_eq!(join(&mut bul, 1), Ok(()));
+        assert!(bul.inner.coms.is_empty());
+        assert_eq!(bul.dropped, 1);
+
+        // The tree holds two objects
+        bul.faults = Faults::default();
+        join(&mut bul, 1).unwrap();
+        join(&mut bul, 2).unwrap();
+        assert_eq!(
+            join(&mut bul, 3),
+            Err(FaultError::Inner(TreeStoreError::Full))
+        );
+        assert_eq!(bul.inner.coms, vec![Fr::from(1), Fr::from(2)]);
+    }
+}
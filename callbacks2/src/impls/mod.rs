@@ -14,7 +14,15 @@ pub mod decentralized;
 
 /// Testing "dummy" object and callback storage to test bulletin and proof code.
 pub mod dummy;
+/// In-memory services and bulletin wrappers which inject faults, to test applications against
+/// failure modes.
+pub mod faulty;
 /// Objects that implement [`HasherZK`](`super::crypto::hash::HasherZK`).
 pub mod hash;
+/// Objects that implement [`MsmBackend`](`super::crypto::msm::MsmBackend`), and a Groth16 prover
+/// using them.
+pub mod msm;
 #[doc(hidden)]
 pub mod userdata;
+/// Objects that implement [`VrfZK`](`super::crypto::vrf::VrfZK`).
+pub mod vrf;
@@ -0,0 +1,257 @@
+use crate::{crypto::msm::MsmBackend, util::span};
+use ark_ec::{pairing::Pairing, CurveGroup, VariableBaseMSM};
+use ark_ff::{UniformRand, Zero};
+use ark_groth16::{
+    r1cs_to_qap::{LibsnarkReduction, R1CSToQAP},
+    Groth16, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey,
+};
+use ark_poly::GeneralEvaluationDomain;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode,
+};
+use ark_snark::SNARK;
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+
+/// The MSM backend arkworks uses, on the CPU.
+///
+/// With the `parallel` feature, this is multi-threaded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuMsm;
+
+impl<E: Pairing> MsmBackend<E> for CpuMsm {
+    fn msm_g1(bases: &[E::G1Affine], scalars: &[E::ScalarField]) -> E::G1 {
+        E::G1::msm_unchecked(bases, scalars)
+    }
+
+    fn msm_g2(bases: &[E::G2Affine], scalars: &[E::ScalarField]) -> E::G2 {
+        E::G2::msm_unchecked(bases, scalars)
+    }
+}
+
+/// Groth16, with the MSMs of the prover done by an [`MsmBackend`].
+///
+/// Keys, proofs, and verification are those of [`Groth16`], so keys generated with `Groth16`
+/// may be used to prove with any backend, and the proofs verify under `Groth16`. Only proving
+/// differs. This may be used anywhere a [`SNARK`] is, for example as the proof system of a scan.
+///
+/// # Example
+/// ```rust
+/// # use ark_bn254::{Bn254 as E, Fr};
+/// # use ark_groth16::Groth16;
+/// # use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+/// # use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
+/// # use ark_snark::SNARK;
+/// # use rand::thread_rng;
+/// # use zk_callbacks::impls::msm::{CpuMsm, MsmGroth16};
+/// struct Square(Fr);
+///
+/// impl ConstraintSynthesizer<Fr> for Square {
+///     fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+///         let x = FpVar::new_witness(cs.clone(), || Ok(self.0))?;
+///         let y = FpVar::new_input(cs, || Ok(self.0 * self.0))?;
+///         (&x * &x).enforce_equal(&y)
+///     }
+/// }
+///
+/// fn main() {
+///     let mut rng = thread_rng();
+///     let (pk, vk) = Groth16::<E>::circuit_specific_setup(Square(Fr::from(0)), &mut rng).unwrap();
+///
+///     let proof = MsmGroth16::<E, CpuMsm>::prove(&pk, Square(Fr::from(3)), &mut rng).unwrap();
+///     assert!(Groth16::<E>::verify(&vk, &[Fr::from(9)], &proof).unwrap());
+/// }
+/// ```
+pub struct MsmGroth16<E: Pairing, M: MsmBackend<E> = CpuMsm> {
+    _e: PhantomData<E>,
+    _m: PhantomData<M>,
+}
+
+impl<E: Pairing, M: MsmBackend<E>> MsmGroth16<E, M> {
+    /// Computes `initial + query[0] + vk_param + sum_i assignment[i] * query[i + 1]`.
+    fn calculate_coeff_g1(
+        initial: E::G1,
+        query: &[E::G1Affine],
+        vk_param: E::G1Affine,
+        assignment: &[E::ScalarField],
+    ) -> E::G1 {
+        initial + query[0] + vk_param + M::msm_g1(&query[1..], assignment)
+    }
+
+    fn calculate_coeff_g2(
+        initial: E::G2,
+        query: &[E::G2Affine],
+        vk_param: E::G2Affine,
+        assignment: &[E::ScalarField],
+    ) -> E::G2 {
+        initial + query[0] + vk_param + M::msm_g2(&query[1..], assignment)
+    }
+
+    /// Create a proof with the given randomness `r` and `s`.
+    pub fn create_proof<C: ConstraintSynthesizer<E::ScalarField>>(
+        pk: &ProvingKey<E>,
+        circuit: C,
+        r: E::ScalarField,
+        s: E::ScalarField,
+    ) -> Result<Proof<E>, SynthesisError> {
+        let cs = ConstraintSystem::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        cs.set_mode(SynthesisMode::Prove {
+            construct_matrices: true,
+        });
+        span!("constraint synthesis").in_scope(|| {
+            circuit.generate_constraints(cs.clone())?;
+            cs.finalize();
+            Ok::<_, SynthesisError>(())
+        })?;
+
+        let h =
+            span!("witness map").in_scope(|| {
+                LibsnarkReduction::witness_map::<
+                    E::ScalarField,
+                    GeneralEvaluationDomain<E::ScalarField>,
+                >(cs.clone())
+            })?;
+        let prover = cs.borrow().ok_or(SynthesisError::MissingCS)?;
+        let input_assignment = &prover.instance_assignment[1..];
+        let aux_assignment = &prover.witness_assignment;
+
+        let _msm = span!("msm").entered();
+        let h_acc = M::msm_g1(&pk.h_query, &h[..pk.h_query.len()]);
+        let l_aux_acc = M::msm_g1(&pk.l_query, aux_assignment);
+        let r_s_delta_g1 = pk.delta_g1 * (r * s);
+
+        let assignment = [input_assignment, &aux_assignment[..]].concat();
+
+        let g_a =
+            Self::calculate_coeff_g1(pk.delta_g1 * r, &pk.a_query, pk.vk.alpha_g1, &assignment);
+        let s_g_a = g_a * s;
+
+        let g1_b = if r.is_zero() {
+            E::G1::zero()
+        } else {
+            Self::calculate_coeff_g1(pk.delta_g1 * s, &pk.b_g1_query, pk.beta_g1, &assignment)
+        };
+        let r_g1_b = g1_b * r;
+
+        let g2_b = Self::calculate_coeff_g2(
+            pk.vk.delta_g2 * s,
+            &pk.b_g2_query,
+            pk.vk.beta_g2,
+            &assignment,
+        );
+
+        let g_c = s_g_a + r_g1_b - r_s_delta_g1 + l_aux_acc + h_acc;
+
+        Ok(Proof {
+            a: g_a.into_affine(),
+            b: g2_b.into_affine(),
+            c: g_c.into_affine(),
+        })
+    }
+}
+
+impl<E: Pairing, M: MsmBackend<E>> SNARK<E::ScalarField> for MsmGroth16<E, M> {
+    type ProvingKey = ProvingKey<E>;
+    type VerifyingKey = VerifyingKey<E>;
+    type Proof = Proof<E>;
+    type ProcessedVerifyingKey = PreparedVerifyingKey<E>;
+    type Error = SynthesisError;
+
+    fn circuit_specific_setup<C: ConstraintSynthesizer<E::ScalarField>, R: RngCore + CryptoRng>(
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), Self::Error> {
+        Groth16::<E>::circuit_specific_setup(circuit, rng)
+    }
+
+    fn prove<C: ConstraintSynthesizer<E::ScalarField>, R: RngCore + CryptoRng>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<Self::Proof, Self::Error> {
+        let r = E::ScalarField::rand(rng);
+        let s = E::ScalarField::rand(rng);
+        Self::create_proof(pk, circuit, r, s)
+    }
+
+    fn process_vk(
+        circuit_vk: &Self::VerifyingKey,
+    ) -> Result<Self::ProcessedVerifyingKey, Self::Error> {
+        Groth16::<E>::process_vk(circuit_vk)
+    }
+
+    fn verify_with_processed_vk(
+        circuit_pvk: &Self::ProcessedVerifyingKey,
+        x: &[E::ScalarField],
+        proof: &Self::Proof,
+    ) -> Result<bool, Self::Error> {
+        Groth16::<E>::verify_with_processed_vk(circuit_pvk, x, proof)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bn254::{Bn254, Fr};
+    use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
+    use ark_relations::r1cs::ConstraintSystemRef;
+    use rand::thread_rng;
+
+    // Proves knowledge of `x` with `x^3 + x + 5 == y`, for a public `y`
+    #[derive(Clone)]
+    struct Cubic(Fr);
+
+    impl ConstraintSynthesizer<Fr> for Cubic {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let y = self.0 * self.0 * self.0 + self.0 + Fr::from(5);
+            let x = FpVar::new_witness(cs.clone(), || Ok(self.0))?;
+            let y = FpVar::new_input(cs, || Ok(y))?;
+            (&x * &x * &x + &x + FpVar::Constant(Fr::from(5))).enforce_equal(&y)
+        }
+    }
+
+    // An MSM computed term by term, standing in for an external backend
+    struct NaiveMsm;
+
+    impl<E: Pairing> MsmBackend<E> for NaiveMsm {
+        fn msm_g1(bases: &[E::G1Affine], scalars: &[E::ScalarField]) -> E::G1 {
+            bases.iter().zip(scalars).map(|(b, s)| *b * s).sum()
+        }
+
+        fn msm_g2(bases: &[E::G2Affine], scalars: &[E::ScalarField]) -> E::G2 {
+            bases.iter().zip(scalars).map(|(b, s)| *b * s).sum()
+        }
+    }
+
+    // Tests that proofs with any backend are exactly the proofs of the arkworks prover, for the
+    // same randomness
+    #[test]
+    fn msm_groth16_matches_arkworks() {
+        let mut rng = thread_rng();
+        let (pk, vk) =
+            Groth16::<Bn254>::circuit_specific_setup(Cubic(Fr::from(0)), &mut rng).unwrap();
+
+        for (r, s) in [
+            (Fr::rand(&mut rng), Fr::rand(&mut rng)),
+            (Fr::from(0), Fr::rand(&mut rng)),
+        ] {
+            let circ = Cubic(Fr::from(3));
+            let expected =
+                Groth16::<Bn254>::create_proof_with_reduction(circ.clone(), &pk, r, s).unwrap();
+            assert_eq!(
+                MsmGroth16::<Bn254, CpuMsm>::create_proof(&pk, circ.clone(), r, s).unwrap(),
+                expected
+            );
+            assert_eq!(
+                MsmGroth16::<Bn254, NaiveMsm>::create_proof(&pk, circ, r, s).unwrap(),
+                expected
+            );
+        }
+
+        let proof =
+            MsmGroth16::<Bn254, NaiveMsm>::prove(&pk, Cubic(Fr::from(3)), &mut rng).unwrap();
+        assert!(Groth16::<Bn254>::verify(&vk, &[Fr::from(35)], &proof).unwrap());
+        assert!(!Groth16::<Bn254>::verify(&vk, &[Fr::from(36)], &proof).unwrap());
+    }
+}
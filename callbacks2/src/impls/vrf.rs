@@ -0,0 +1,93 @@
+use crate::crypto::{hash::FieldHash, vrf::VrfZK};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_relations::r1cs::SynthesisError;
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+
+const VRF_PUBKEY_DOMAIN: u64 = 0x767266706b; // "vrfpk"
+const VRF_EVAL_DOMAIN: u64 = 0x7672666576; // "vrfev"
+
+/// A VRF built from a field hash `H`. Implements [`VrfZK`].
+///
+/// The secret key is a field element `sk`, the public key is `H(pk_tag, sk)`, and the output on an
+/// input `x` is `H(eval_tag, sk, x)`. Outputs are checked against the public key with a SNARK (see
+/// [`VrfZK::prove_output`]), so this only costs a couple of hashes in-circuit.
+#[derive(Clone, Default, Debug)]
+pub struct PoseidonVrf<H: FieldHash<F>, F: PrimeField + Absorb> {
+    _phantom: PhantomData<(H, F)>,
+}
+
+impl<H: FieldHash<F>, F: PrimeField + Absorb> VrfZK<F> for PoseidonVrf<H, F> {
+    type Sk = F;
+
+    type SkVar = FpVar<F>;
+
+    fn keygen(rng: &mut (impl CryptoRng + RngCore)) -> F {
+        F::rand(rng)
+    }
+
+    fn pubkey(sk: &F) -> F {
+        H::hash(&[F::from(VRF_PUBKEY_DOMAIN), *sk])
+    }
+
+    fn pubkey_in_zk(sk: &FpVar<F>) -> Result<FpVar<F>, SynthesisError> {
+        H::hash_in_zk(&[FpVar::Constant(F::from(VRF_PUBKEY_DOMAIN)), sk.clone()])
+    }
+
+    fn evaluate(sk: &F, input: &[F]) -> F {
+        H::hash(&[&[F::from(VRF_EVAL_DOMAIN), *sk][..], input].concat())
+    }
+
+    fn evaluate_in_zk(sk: &FpVar<F>, input: &[FpVar<F>]) -> Result<FpVar<F>, SynthesisError> {
+        H::hash_in_zk(
+            &[
+                &[FpVar::Constant(F::from(VRF_EVAL_DOMAIN)), sk.clone()][..],
+                input,
+            ]
+            .concat(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::impls::hash::Poseidon;
+    use ark_bn254::{Bn254, Fr};
+    use ark_groth16::Groth16;
+    use rand::thread_rng;
+
+    type Vrf = PoseidonVrf<Poseidon<2>, Fr>;
+
+    // Tests that outputs prove and verify only under the right key and input
+    #[test]
+    fn poseidon_vrf_prove() {
+        let mut rng = thread_rng();
+        let (pk, vk) = Vrf::generate_vrf_keys::<Groth16<Bn254>>(&mut rng, 2);
+
+        let sk = Vrf::keygen(&mut rng);
+        let input = [Fr::from(3u8), Fr::from(4u8)];
+        let (output, proof) =
+            Vrf::prove_output::<Groth16<Bn254>>(&sk, &input, &pk, &mut rng).unwrap();
+        assert_eq!(output, Vrf::evaluate(&sk, &input));
+
+        let vrf_pk = Vrf::pubkey(&sk);
+        assert!(Vrf::verify_output::<Groth16<Bn254>>(
+            vrf_pk, &input, output, &vk, &proof
+        ));
+        assert!(!Vrf::verify_output::<Groth16<Bn254>>(
+            vrf_pk,
+            &input,
+            output + Fr::from(1u8),
+            &vk,
+            &proof
+        ));
+
+        let other = Vrf::pubkey(&Vrf::keygen(&mut rng));
+        assert!(!Vrf::verify_output::<Groth16<Bn254>>(
+            other, &input, output, &vk, &proof
+        ));
+    }
+}
@@ -32,6 +32,29 @@
 //! the bulletins, such as a [`SigObjStore`](`impls::centralized::ds::sigstore::SigObjStore`) and
 //! some more cryptography.
 //!
+//! ## WebAssembly
+//!
+//! The library builds for `wasm32-unknown-unknown`, with randomness drawn from the browser. Build
+//! with `default-features = false` to leave out the filesystem-backed
+//! [`KeyStore`](`generic::keystore::KeyStore`), and without the network-backed features (`http`,
+//! `ipfs`, `evm`, `sled`, and `redis`). Keys and bulletin data are then passed in as serialized
+//! bytes.
+//!
+//! ## Tracing
+//!
+//! With the `tracing` feature, proving, scanning, key generation, and proof verification emit
+//! [`tracing`](https://docs.rs/tracing) spans. Proving is split into witness generation,
+//! constraint synthesis, and the SNARK prover (and, with
+//! [`MsmGroth16`](`impls::msm::MsmGroth16`), the witness map and MSMs), so any `tracing`
+//! subscriber can report a timing breakdown.
+//!
+//! ## Benchmarks
+//!
+//! The `benches/` suite measures proving interactions (with 0, 1, and 4 callbacks), scans (of 1,
+//! 4, and 8 tickets), signature against Merkle bulletins, and Poseidon against Poseidon2. Run it
+//! with `cargo bench --features bench`. Setting `WISPY_BENCH_JSON=<path>` additionally writes the
+//! mean, confidence interval, and standard deviation of each benchmark to `<path>` as JSON.
+//!
 //! # Examples
 //!
 //! For a first example, see `examples/simple.rs`, which gives a walkthrough of a single
@@ -51,12 +74,16 @@ pub mod impls;
 #[doc(hidden)]
 pub mod util;
 
+#[doc(inline)]
+pub use generic::verify::{verify_execution, VerifyError};
+
 /// Struct macro to construct in-circuit representations, derive `UserData`, and add necessary
 /// implementations for scanning.
 ///
 /// This macro takes in one argument (the field), and a second optional argument (the in-circuit
 /// representation). If the second argument is not provided, this macro will construct an
-/// in-circuit representation of the structure.
+/// in-circuit representation of the structure. As with [`zk_object`], a field may be marked
+/// `#[rate_limit_key]`.
 ///
 /// ```rust
 /// use ark_bls12_381::Fr;
@@ -154,6 +181,22 @@ pub use zk_object::scannable_zk_object;
 /// }
 /// ```
 ///
+/// A field of the field type may be marked `#[rate_limit_key]` to make it the key rate-limit tags
+/// of the user are computed with. Every method and scan must then keep that field unchanged.
+///
+/// ```rust
+/// use ark_bls12_381::Fr;
+/// use zk_callbacks::zk_object;
+///
+/// #[zk_object(Fr)]
+/// #[derive(Default)]
+/// struct Data {
+///     karma: Fr,
+///     #[rate_limit_key]
+///     key: Fr,
+/// }
+/// ```
+///
 /// If an in-circuit representation already exists, one may use the additional argument to pass
 /// this in.
 ///
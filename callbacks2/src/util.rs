@@ -1,13 +1,15 @@
+use crate::impls::hash::{Poseidon2Config, RescueConfig};
 use ark_crypto_primitives::sponge::poseidon::{
     find_poseidon_ark_and_mds, PoseidonConfig, PoseidonDefaultConfigEntry,
 };
-use ark_ff::PrimeField;
+use ark_ff::{PrimeField, Zero};
 use ark_r1cs_std::{
     prelude::{AllocVar, AllocationMode},
     R1CSVar,
 };
 use ark_relations::r1cs::{ConstraintSystemRef, Field, Namespace, SynthesisError};
 use core::borrow::Borrow;
+use num_bigint::BigUint;
 
 // Generates Poseidon params for BLS12-381. This is copied from
 //     https://github.com/arkworks-rs/crypto-primitives/blob/54b3ac24b8943fbd984863558c749997e96ff399/src/sponge/poseidon/traits.rs#L69
@@ -17,6 +19,32 @@ pub(crate) fn gen_poseidon_params<F: PrimeField>(
     rate: usize,
     optimized_for_weights: bool,
 ) -> PoseidonConfig<F> {
+    let param = poseidon_default_entry(rate, optimized_for_weights);
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(
+        F::MODULUS_BIT_SIZE as u64,
+        rate,
+        param.full_rounds as u64,
+        param.partial_rounds as u64,
+        param.skip_matrices as u64,
+    );
+
+    PoseidonConfig {
+        full_rounds: param.full_rounds,
+        partial_rounds: param.partial_rounds,
+        alpha: param.alpha as u64,
+        ark,
+        mds,
+        rate: param.rate,
+        capacity: 1,
+    }
+    // F::get_default_poseidon_parameters(rate, optimized_for_weights).unwrap()
+}
+
+// The default round numbers and sbox exponent of Poseidon for a given rate.
+pub(crate) fn poseidon_default_entry(
+    rate: usize,
+    optimized_for_weights: bool,
+) -> PoseidonDefaultConfigEntry {
     let params_set = if !optimized_for_weights {
         [
             PoseidonDefaultConfigEntry::new(2, 17, 8, 31, 0),
@@ -39,30 +67,10 @@ pub(crate) fn gen_poseidon_params<F: PrimeField>(
         ]
     };
 
-    for param in params_set.iter() {
-        if param.rate == rate {
-            let (ark, mds) = find_poseidon_ark_and_mds::<F>(
-                F::MODULUS_BIT_SIZE as u64,
-                rate,
-                param.full_rounds as u64,
-                param.partial_rounds as u64,
-                param.skip_matrices as u64,
-            );
-
-            return PoseidonConfig {
-                full_rounds: param.full_rounds,
-                partial_rounds: param.partial_rounds,
-                alpha: param.alpha as u64,
-                ark,
-                mds,
-                rate: param.rate,
-                capacity: 1,
-            };
-        }
-    }
-
-    panic!("could not generate poseidon params");
-    // F::get_default_poseidon_parameters(rate, optimized_for_weights).unwrap()
+    params_set
+        .into_iter()
+        .find(|param| param.rate == rate)
+        .expect("could not generate poseidon params")
 }
 
 #[derive(Clone)]
@@ -98,3 +106,107 @@ impl<I, F: Field, A: AllocVar<I, F>, const N: usize> AllocVar<[I; N], F> for Arr
         })?))
     }
 }
+
+// Finds the inverse of `alpha` modulo `p - 1`, so that `x -> x^alpha` is a permutation of `F` with
+// inverse `x -> x^alpha_inv`.
+pub(crate) fn sbox_inverse_exponent<F: PrimeField>(alpha: u64) -> Vec<u64> {
+    let modulus: BigUint = F::MODULUS.into();
+    let order = modulus - 1u32;
+    for k in 1..alpha {
+        let v = &order * k + 1u32;
+        if (&v % alpha).is_zero() {
+            return (v / alpha).to_u64_digits();
+        }
+    }
+    panic!("sbox exponent is not a permutation of the field");
+}
+
+// Generates Poseidon2 params with alpha = 5. The round numbers are from
+//     https://eprint.iacr.org/2023/323 (Table 1)
+// for 254 and 255 bit fields, and the round constants are sampled with the same Grain LFSR as
+// Poseidon. The internal matrix diagonals for widths 2 and 3 are those of the paper, and for
+// larger widths are sampled with the round constants.
+pub(crate) fn gen_poseidon2_params<F: PrimeField>(rate: usize) -> Poseidon2Config<F> {
+    let width = rate + 1;
+    let partial_rounds = match width {
+        2..=4 => 56,
+        8 | 12 | 16 | 20 | 24 => 57,
+        _ => panic!("could not generate poseidon2 params"),
+    };
+    let full_rounds = 8;
+    sbox_inverse_exponent::<F>(5);
+
+    let (mut ark, _) = find_poseidon_ark_and_mds::<F>(
+        F::MODULUS_BIT_SIZE as u64,
+        rate,
+        full_rounds as u64,
+        partial_rounds as u64 + 1,
+        0,
+    );
+    let internal_diag = match width {
+        2 => vec![F::ONE, F::from(2u8)],
+        3 => vec![F::ONE, F::ONE, F::from(2u8)],
+        _ => ark.pop().unwrap(),
+    };
+    ark.truncate(full_rounds + partial_rounds);
+
+    Poseidon2Config {
+        full_rounds,
+        partial_rounds,
+        alpha: 5,
+        ark,
+        internal_diag,
+        rate,
+    }
+}
+
+// Generates Rescue-Prime params with alpha = 5. The round constants and the (Cauchy) MDS matrix
+// are sampled with the same Grain LFSR as Poseidon. The number of rounds is a conservative choice
+// for 128 bit security over 254 and 255 bit fields, see
+//     https://eprint.iacr.org/2020/1143
+pub(crate) fn gen_rescue_params<F: PrimeField>(rate: usize) -> RescueConfig<F> {
+    if !(1..=8).contains(&rate) {
+        panic!("could not generate rescue params");
+    }
+    let rounds = 12;
+    let alpha_inv = sbox_inverse_exponent::<F>(5);
+
+    let (ark, mds) =
+        find_poseidon_ark_and_mds::<F>(F::MODULUS_BIT_SIZE as u64, rate, 2 * rounds, 0, 0);
+
+    RescueConfig {
+        rounds: rounds as usize,
+        alpha: 5,
+        alpha_inv,
+        ark,
+        mds,
+        rate,
+    }
+}
+
+// A `tracing` span, if the `tracing` feature is enabled. Otherwise, this is a no-op with the same
+// `in_scope` method, so call sites need no feature gates.
+macro_rules! span {
+    ($($args:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!($($args)*);
+        #[cfg(not(feature = "tracing"))]
+        let span = $crate::util::NoSpan;
+        span
+    }};
+}
+pub(crate) use span;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+#[cfg(not(feature = "tracing"))]
+impl NoSpan {
+    pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        f()
+    }
+
+    pub(crate) fn entered(self) -> Self {
+        self
+    }
+}
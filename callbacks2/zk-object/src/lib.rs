@@ -17,6 +17,31 @@ fn add_trait_bounds(mut generics: Generics, field_type: &TokenStream) -> Generic
     generics
 }
 
+/// Take the `#[rate_limit_key]` attribute off the field it marks, and return the methods of
+/// `UserData` reading that field as the rate-limit key of the user.
+fn take_rate_limit_key(ast: &mut DeriveInput, ft: &TokenStream) -> TokenStream {
+    let Data::Struct(ref mut data) = ast.data else {
+        return quote! {};
+    };
+    for field in data.fields.iter_mut() {
+        let attrs = field.attrs.len();
+        field.attrs.retain(|a| !a.path().is_ident("rate_limit_key"));
+        if field.attrs.len() < attrs {
+            let name = &field.ident;
+            return quote! {
+                fn rate_limit_key(&self) -> Option<zk_callbacks::generic::object::PrfKey<#ft>> {
+                    Some(self.#name)
+                }
+
+                fn rate_limit_key_in_zk(user_var: &Self::UserDataVar) -> Result<Option<zk_callbacks::generic::object::PrfKeyVar<#ft>>, ark_relations::r1cs::SynthesisError> {
+                    Ok(Some(user_var.#name.clone()))
+                }
+            };
+        }
+    }
+    quote! {}
+}
+
 fn derive_userdata_and_zk(
     data: &Data,
     ft: TokenStream,
@@ -81,7 +106,7 @@ fn derive_userdata_and_zk(
                     let ty = &f.ty;
                     let lit = proc_macro2::Literal::string(&(name.clone()).unwrap().to_string());
                     quote_spanned! {f.span() =>
-                    let #name = <#ty as zk_callbacks::generic::user::UserData<#ft>>::UserDataVar::new_variable(ark_relations::ns!(cs, #lit), || Ok(rec.#name.clone()), mode)?
+                    let #name = <<#ty as zk_callbacks::generic::user::UserData<#ft>>::UserDataVar as ark_r1cs_std::alloc::AllocVar<#ty, #ft>>::new_variable(ark_relations::ns!(cs, #lit), || Ok(rec.#name.clone()), mode)?
                     }
                 });
 
@@ -130,7 +155,7 @@ pub fn zk_object(
     args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let ast = parse_macro_input!(input as DeriveInput);
+    let mut ast = parse_macro_input!(input as DeriveInput);
 
     let args = parse_macro_input!(args with Punctuated::<Type, syn::Token![,]>::parse_terminated);
 
@@ -156,6 +181,8 @@ pub fn zk_object(
     let zk_var_name = Ident::new(&struct_name, ast.ident.span());
 
     // Add trait bounds to elements in the original struct:
+    let key = take_rate_limit_key(&mut ast, &field_type);
+
     let generics = add_trait_bounds(ast.generics.clone(), &field_type);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
@@ -182,6 +209,8 @@ pub fn zk_object(
                         #s2
                         Ok(buf)
                     }
+
+                    #key
                 }
             }
         }
@@ -230,6 +259,8 @@ pub fn zk_object(
                         #s2
                         Ok(buf)
                     }
+
+                    #key
                 }
             }
         }
@@ -243,7 +274,7 @@ pub fn scannable_zk_object(
     args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let ast = parse_macro_input!(input as DeriveInput);
+    let mut ast = parse_macro_input!(input as DeriveInput);
 
     let args = parse_macro_input!(args with Punctuated::<Type, syn::Token![,]>::parse_terminated);
 
@@ -269,6 +300,8 @@ pub fn scannable_zk_object(
     let zk_var_name = Ident::new(&struct_name, ast.ident.span());
 
     // Add trait bounds to elements in the original struct:
+    let key = take_rate_limit_key(&mut ast, &field_type);
+
     let generics = add_trait_bounds(ast.generics.clone(), &field_type);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
@@ -295,6 +328,8 @@ pub fn scannable_zk_object(
                         #s2
                         Ok(buf)
                     }
+
+                    #key
                 }
             }
         }
@@ -365,6 +400,8 @@ pub fn scannable_zk_object(
                         #s2
                         Ok(buf)
                     }
+
+                    #key
                 }
             }
         }
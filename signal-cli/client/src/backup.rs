@@ -0,0 +1,227 @@
+//! Backups of the client state.
+//!
+//! `export` writes the user, the pseudonyms and the synced thread contexts into a single
+//! archive, and `import` restores them on another device (or in another profile), keeping the
+//! user's reputation and pseudonyms. An archive starts with [`MAGIC`], a version byte and a flag
+//! byte telling whether the rest is encrypted with a passphrase; the rest is the [`Backup`] as
+//! JSON.
+//!
+//! Only the user the server has acknowledged is exported: interactions still queued in the outbox
+//! are not.
+
+use crate::config::{data_path, settings};
+use crate::helpers::ContextCache;
+use crate::keystore as secrets;
+use crate::outbox;
+use crate::pseudonyms::{Pseudonyms, PSEUDO_LOG};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const MAGIC: &[u8; 8] = b"WISPYBAK";
+/// The version of the archives written. Archives of version 1 hold the pseudonym log of older
+/// clients rather than the pseudonyms.
+const VERSION: u8 = 2;
+const ENCRYPTED: u8 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    /// When the backup was made, in seconds since the Unix epoch.
+    pub created: u64,
+    /// The serialized user, in hex.
+    pub user: String,
+    #[serde(default)]
+    pub pseudonyms: Pseudonyms,
+    /// The pseudonym log of an archive of version 1, one pseudonym per line.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pseudo_log: String,
+    #[serde(default)]
+    pub contexts: ContextCache,
+}
+
+/// Whether an archive is encrypted, so a passphrase should be asked for.
+pub fn is_encrypted(archive: &[u8]) -> Result<bool> {
+    Ok(header(archive)?.1 & ENCRYPTED != 0)
+}
+
+fn header(archive: &[u8]) -> Result<(u8, u8, &[u8])> {
+    let Some(rest) = archive.strip_prefix(&MAGIC[..]) else {
+        bail!("not a wispy backup");
+    };
+    match rest {
+        [version, flags, body @ ..] => Ok((*version, *flags, body)),
+        _ => bail!("the backup is truncated"),
+    }
+}
+
+/// Write a backup of the client state to `out`, encrypted with `passphrase` if one is given.
+pub fn export(out: &Path, passphrase: Option<&str>) -> Result<()> {
+    let user = secrets::read(&settings().user_file).context("failed to read the user")?;
+    let pseudonyms = Pseudonyms::load()?;
+    let queued = outbox::list()?.len();
+    if queued > 0 {
+        eprintln!(
+            "Warning: the {} submissions queued in the outbox are not backed up",
+            queued
+        );
+    }
+
+    let backup = Backup {
+        created: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        user: hex::encode(user),
+        pseudonyms,
+        pseudo_log: String::new(),
+        contexts: ContextCache::load(),
+    };
+    let body = serde_json::to_vec(&backup)?;
+    let (flags, body) = match passphrase {
+        Some(passphrase) => (ENCRYPTED, secrets::seal(passphrase, &body)?),
+        None => (0, body),
+    };
+
+    let archive = [&MAGIC[..], &[VERSION, flags], &body].concat();
+    fs::write(out, archive).with_context(|| format!("failed to write {}", out.display()))
+}
+
+/// Read a backup, decrypting it with `passphrase` if it is encrypted.
+pub fn read(archive: &[u8], passphrase: Option<&str>) -> Result<Backup> {
+    let (version, flags, body) = header(archive)?;
+    if version > VERSION {
+        bail!(
+            "the backup is of version {}, this client reads up to version {}",
+            version,
+            VERSION
+        );
+    }
+    let body = if flags & ENCRYPTED != 0 {
+        secrets::open(
+            passphrase.context("the backup is encrypted, and needs a passphrase")?,
+            body,
+        )?
+    } else {
+        body.to_vec()
+    };
+    serde_json::from_slice(&body).context("invalid backup")
+}
+
+/// Restore a backup, replacing the user, pseudonyms and contexts, and dropping anything queued
+/// for the old user. Refuses to replace an existing user unless `force`.
+pub fn import(backup: Backup, force: bool) -> Result<()> {
+    let user_file = &settings().user_file;
+    if user_file.exists() && !force {
+        bail!(
+            "{} already holds a user, which importing would replace (pass --force)",
+            user_file.display()
+        );
+    }
+    let user = hex::decode(&backup.user).context("invalid user in the backup")?;
+
+    outbox::clear()?;
+    secrets::write(user_file, &user)?;
+    let pseudonyms = if backup.pseudo_log.is_empty() {
+        backup.pseudonyms
+    } else {
+        Pseudonyms::parse_log(&backup.pseudo_log)
+    };
+    pseudonyms.save()?;
+    match fs::remove_file(data_path(PSEUDO_LOG)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    backup.contexts.save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::helpers::contexts_path;
+    use crate::pseudonyms::pseudonyms_path;
+    use crate::testing;
+    use common::F;
+
+    fn archive(version: u8, flags: u8, body: &[u8]) -> Vec<u8> {
+        [&MAGIC[..], &[version, flags], body].concat()
+    }
+
+    // A backup restores the user, pseudonyms and contexts, and only replaces a user when forced
+    #[test]
+    fn export_import() {
+        let _data = testing::data_dir();
+        fs::write(&settings().user_file, b"user").unwrap();
+        let mut pseudonyms = Pseudonyms::default();
+        pseudonyms.add(F::from(1), F::from(2), Some("main".to_string())).unwrap();
+        pseudonyms.save().unwrap();
+        let mut contexts = ContextCache::default();
+        contexts.contexts.insert("thread".to_string(), "3".to_string());
+        contexts.save().unwrap();
+
+        let out = data_path("backup.wispy");
+        export(&out, None).unwrap();
+        let archive = fs::read(&out).unwrap();
+        assert!(!is_encrypted(&archive).unwrap());
+
+        let err = import(read(&archive, None).unwrap(), false).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+        fs::remove_file(&settings().user_file).unwrap();
+        fs::remove_file(pseudonyms_path()).unwrap();
+        fs::remove_file(contexts_path()).unwrap();
+
+        import(read(&archive, None).unwrap(), false).unwrap();
+        assert_eq!(fs::read(&settings().user_file).unwrap(), b"user");
+        assert_eq!(Pseudonyms::load().unwrap().find("main").unwrap().claimed, "2");
+        assert_eq!(ContextCache::load().contexts["thread"], "3");
+        import(read(&archive, None).unwrap(), true).unwrap();
+    }
+
+    // An encrypted backup needs its passphrase
+    #[test]
+    fn encrypted_backup() {
+        let _data = testing::data_dir();
+        fs::write(&settings().user_file, b"user").unwrap();
+        let out = data_path("backup.wispy");
+        export(&out, Some("secret")).unwrap();
+        let archive = fs::read(&out).unwrap();
+        assert!(is_encrypted(&archive).unwrap());
+
+        let err = read(&archive, None).err().unwrap();
+        assert!(err.to_string().contains("needs a passphrase"));
+        assert!(read(&archive, Some("wrong")).is_err());
+        let backup = read(&archive, Some("secret")).unwrap();
+        assert_eq!(hex::decode(backup.user).unwrap(), b"user");
+    }
+
+    // Archives of newer clients, or which are not backups, are refused
+    #[test]
+    fn invalid_archives() {
+        let err = |archive: &[u8]| read(archive, None).err().unwrap().to_string();
+        assert_eq!(err(b"not a backup"), "not a wispy backup");
+        assert_eq!(err(MAGIC), "the backup is truncated");
+        assert!(err(&archive(VERSION + 1, 0, b"{}")).contains("this client reads up to version"));
+        assert_eq!(err(&archive(VERSION, 0, b"{")), "invalid backup");
+    }
+
+    // Archives of version 1 hold the pseudonym log, which is read into the pseudonyms
+    #[test]
+    fn version_one() {
+        let _data = testing::data_dir();
+        fs::write(data_path(PSEUDO_LOG), "{}\n").unwrap();
+        let body = serde_json::json!({
+            "created": 0,
+            "user": hex::encode(b"user"),
+            "pseudo_log": "{\"claimed\":\"5\",\"context\":\"6\"}\n",
+        });
+        let archive = archive(1, 0, body.to_string().as_bytes());
+        import(read(&archive, None).unwrap(), false).unwrap();
+
+        let pseudonyms = Pseudonyms::load().unwrap();
+        assert_eq!(pseudonyms.get(1).unwrap().context, "6");
+        assert!(!data_path(PSEUDO_LOG).exists());
+    }
+}
@@ -48,6 +48,54 @@ impl BulNet {
         }
     }
 
+    /// The commitment and consumed nullifier of every entry of the user bulletin.
+    pub fn user_entries(&self) -> Result<Vec<(Com<F>, Nul<F>)>, String> {
+        let url = self.api.join("api/user/bulletin").map_err(|e| e.to_string())?;
+        let bul = self
+            .client
+            .get(url)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.bytes())
+            .map_err(|e| e.to_string())?;
+
+        let db = <Vec<(
+            Com<F>,
+            Nul<F>,
+            Vec<Com<F>>,
+            <Self as PublicUserBul<F, MsgUser>>::MembershipWitness,
+        )>>::deserialize_with_mode(&*bul, Compress::No, Validate::Yes)
+        .map_err(|e| e.to_string())?;
+
+        Ok(db.into_iter().map(|(com, nul, _, _)| (com, nul)).collect())
+    }
+
+    /// The ticket and encrypted argument of every callback called so far, from the callback
+    /// bulletin.
+    pub fn called_callbacks(&self) -> Result<Vec<(FakeSigPubkey<F>, Args)>, String> {
+        let url = self
+            .api
+            .join("api/callbacks/bulletin")
+            .map_err(|e| e.to_string())?;
+        let bul = self
+            .client
+            .get(url)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.bytes())
+            .map_err(|e| e.to_string())?;
+
+        let db = <Vec<(
+            FakeSigPubkey<F>,
+            Args,
+            Time<F>,
+            <Self as PublicCallbackBul<F, Args, Cr>>::MembershipWitness,
+        )>>::deserialize_with_mode(&*bul, Compress::No, Validate::Yes)
+        .map_err(|e| e.to_string())?;
+
+        Ok(db.into_iter().map(|(tik, ct, _, _)| (tik, ct)).collect())
+    }
+
     pub fn post(
         &self,
         endpoint: &str,
@@ -151,6 +199,18 @@ impl PublicUserBul<F, MsgUser> for BulNet {
             extra_pub,
         )
     }
+
+    fn anonymity_set_size(&self) -> Option<u64> {
+        let res: serde_json::Value = self
+            .client
+            .get(self.api.join("api/user/anonymity").ok()?)
+            .send()
+            .ok()?
+            .json()
+            .ok()?;
+
+        res["size"].as_u64()
+    }
 }
 
 impl PublicCallbackBul<F, Args, Cr> for BulNet {
@@ -0,0 +1,244 @@
+//! Client configuration.
+//!
+//! The configuration is read from `~/.config/wispy/config.toml` (under `$XDG_CONFIG_HOME` if it
+//! is set), or the file given with `--config`. It may be missing, in which case the client talks
+//! to a server on `http://127.0.0.1:3000` and keeps its data in `client`:
+//!
+//! ```toml
+//! server = "http://127.0.0.1:3000"
+//! data_dir = "client"
+//! # Used by commands given no -g
+//! group_id = "<group id>"
+//! # Where the user's state is shared with other devices using it, see `sync`
+//! sync_dir = "/home/me/Sync/wispy"
+//!
+//! # Selected with --profile work
+//! [profiles.work]
+//! server = "https://wispy.example.com"
+//! group_id = "<group id>"
+//! ```
+//!
+//! A profile inherits every setting it does not set from the top of the file, except the data
+//! directory: a profile keeps its user and pseudonyms in `<data_dir>/<profile>` unless it sets
+//! `data_dir`, so profiles never share an identity.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use url::Url;
+
+const DEFAULT_SERVER: &str = "http://127.0.0.1:3000";
+const DEFAULT_DATA_DIR: &str = "client";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The URL of the server.
+    pub server: Option<String>,
+    /// The directory the user, pseudonyms, contexts and keys are kept in.
+    pub data_dir: Option<PathBuf>,
+    /// The Signal group used by commands given no group.
+    pub group_id: Option<String>,
+    /// The directory shared with other devices using the same user.
+    pub sync_dir: Option<PathBuf>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Settings which override the top of the configuration when the profile is selected.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub server: Option<String>,
+    pub data_dir: Option<PathBuf>,
+    pub group_id: Option<String>,
+    pub sync_dir: Option<PathBuf>,
+}
+
+/// The settings every command runs with, from the configuration and the selected profile.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub server: Url,
+    pub data_dir: PathBuf,
+    pub group_id: Option<String>,
+    pub sync_dir: Option<PathBuf>,
+    /// The file the user is kept in, `user.bin` in the data directory unless given with
+    /// `--user`.
+    pub user_file: PathBuf,
+}
+
+/// Where the configuration is read from when `--config` is not given.
+pub fn default_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("wispy").join("config.toml"))
+}
+
+impl Config {
+    /// Load the configuration from `path`, or from [`default_path`] if it exists when no path is
+    /// given.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path().filter(|p| p.exists()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        let file = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        toml::from_str(&file).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    /// The settings of `profile`, or of the top of the configuration if no profile is given.
+    pub fn settings(&self, profile: Option<&str>, user_file: Option<PathBuf>) -> Result<Settings> {
+        let data_dir = self
+            .data_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+        let (server, data_dir, group_id, sync_dir) = match profile {
+            Some(name) => {
+                let profile = self
+                    .profiles
+                    .get(name)
+                    .with_context(|| format!("no profile {:?} in the config", name))?;
+                (
+                    profile.server.as_ref().or(self.server.as_ref()),
+                    profile
+                        .data_dir
+                        .clone()
+                        .unwrap_or_else(|| data_dir.join(name)),
+                    profile.group_id.as_ref().or(self.group_id.as_ref()),
+                    profile.sync_dir.as_ref().or(self.sync_dir.as_ref()),
+                )
+            }
+            None => (
+                self.server.as_ref(),
+                data_dir,
+                self.group_id.as_ref(),
+                self.sync_dir.as_ref(),
+            ),
+        };
+
+        let mut server = server.map_or(DEFAULT_SERVER, String::as_str).to_string();
+        // Endpoints are joined onto the server URL, which keeps its path only with a final '/'
+        if !server.ends_with('/') {
+            server.push('/');
+        }
+        let server = Url::parse(&server).with_context(|| format!("invalid server {}", server))?;
+        Ok(Settings {
+            server,
+            user_file: user_file.unwrap_or_else(|| data_dir.join("user.bin")),
+            data_dir,
+            group_id: group_id.cloned(),
+            sync_dir: sync_dir.cloned(),
+        })
+    }
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Run every command with `settings`. Only the first call has an effect, so this should be
+/// called on startup.
+pub fn init(settings: Settings) {
+    let _ = SETTINGS.set(settings);
+}
+
+/// The settings commands run with, the defaults if [`init`] was not called.
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| {
+        Config::default()
+            .settings(None, None)
+            .expect("the default settings are valid")
+    })
+}
+
+/// The URL of the server.
+pub fn server() -> Url {
+    settings().server.clone()
+}
+
+/// The URL of the server endpoint `path`, like `api/jsonrpc`.
+pub fn endpoint(path: &str) -> Url {
+    settings().server.join(path).expect("valid endpoint")
+}
+
+/// The path of `name` in the data directory.
+pub fn data_path(name: &str) -> PathBuf {
+    settings().data_dir.join(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        server = "https://wispy.example.com/chat"
+        data_dir = "data"
+        group_id = "main"
+
+        [profiles.work]
+        server = "https://work.example.com"
+
+        [profiles.home]
+        data_dir = "elsewhere"
+        group_id = "family"
+    "#;
+
+    // Without a config, the client talks to a local server and keeps its data in `client`
+    #[test]
+    fn default_settings() {
+        let settings = Config::default().settings(None, None).unwrap();
+        assert_eq!(settings.server.as_str(), "http://127.0.0.1:3000/");
+        assert_eq!(settings.data_dir, Path::new("client"));
+        assert_eq!(settings.user_file, Path::new("client/user.bin"));
+        assert_eq!(settings.group_id, None);
+        assert!(Config::default().settings(Some("work"), None).is_err());
+    }
+
+    // Profiles inherit the top of the config, but keep their data apart
+    #[test]
+    fn profiles() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let settings = config.settings(None, None).unwrap();
+        assert_eq!(settings.server.as_str(), "https://wispy.example.com/chat/");
+        assert_eq!(settings.data_dir, Path::new("data"));
+        assert_eq!(settings.group_id.as_deref(), Some("main"));
+
+        let work = config.settings(Some("work"), None).unwrap();
+        assert_eq!(work.server.as_str(), "https://work.example.com/");
+        assert_eq!(work.data_dir, Path::new("data/work"));
+        assert_eq!(work.user_file, Path::new("data/work/user.bin"));
+        assert_eq!(work.group_id.as_deref(), Some("main"));
+
+        let home = config.settings(Some("home"), Some("me.bin".into())).unwrap();
+        assert_eq!(home.server.as_str(), "https://wispy.example.com/chat/");
+        assert_eq!(home.data_dir, Path::new("elsewhere"));
+        assert_eq!(home.user_file, Path::new("me.bin"));
+        assert_eq!(home.group_id.as_deref(), Some("family"));
+
+        assert!(config.settings(Some("missing"), None).is_err());
+    }
+
+    // A config given explicitly must exist and only hold known settings
+    #[test]
+    fn load_config() {
+        let dir = std::env::temp_dir().join(format!("wispy-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, CONFIG).unwrap();
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.profiles.len(), 2);
+
+        std::fs::write(&path, "serer = \"http://localhost\"\n").unwrap();
+        assert!(Config::load(Some(&path)).is_err());
+        std::fs::write(&path, "server = \"not a url\"\n").unwrap();
+        assert!(Config::load(Some(&path)).unwrap().settings(None, None).is_err());
+        assert!(Config::load(Some(&dir.join("missing.toml"))).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! The proving daemon.
+//!
+//! Every proof needs the user and a proving key of hundreds of megabytes, which take seconds to
+//! load. `daemon` keeps both loaded and proves on behalf of other commands, which send it their
+//! requests over the Unix socket `daemon.sock` in the data directory; commands run on their own
+//! when no daemon is listening. Requests are proved one at a time, so the user is never updated
+//! by two proofs at once.
+//!
+//! While proving, the daemon reports each [`Stage`] of the proof to the command it proves for.
+//!
+//! Proving keys are fetched again whenever the server reports a new digest for them, and the
+//! user whenever the user file changes. A daemon stays unlocked after `keys lock`: stop it too.
+
+use crate::config::data_path;
+use crate::helpers::{
+    gen_cb_for_msg, make_authorship_proof, make_badge_proof, make_dm_proof, make_rep_proof,
+    on_progress, preload, progress, pseudo_proof_vote, pseudo_proof_with_msg,
+    rate_pseudo_proof_with_msg, scan, string_to_f, Stage,
+};
+use crate::say;
+use anyhow::{anyhow, Context, Result};
+use ark_ff::PrimeField;
+use common::F;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+/// A proof for the daemon to make. Field elements are given in decimal.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    Post {
+        digest: String,
+    },
+    PostPseudo {
+        claimed: String,
+        context: String,
+    },
+    PostPseudoRate {
+        claimed: String,
+        context: String,
+        i: String,
+    },
+    Vote {
+        claimed: String,
+        context: String,
+    },
+    Scan,
+    /// Prove ownership of the pseudonym numbered `i`, for the direct message request with the
+    /// context `context`.
+    Dm {
+        i: usize,
+        context: String,
+    },
+    Authorship {
+        indices: Vec<usize>,
+    },
+    Badge {
+        i: usize,
+        badge: String,
+    },
+    Reputation {
+        i: usize,
+        at_least: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    /// A step of the proof, sent before the proof or the error.
+    Progress(Stage),
+    /// The proof, in hex.
+    Proof(String),
+    Error(String),
+}
+
+/// The command the daemon is proving for.
+static CLIENT: Mutex<Option<UnixStream>> = Mutex::new(None);
+
+/// A field element as sent in a [`Request`].
+pub fn field(f: F) -> String {
+    f.into_bigint().to_string()
+}
+
+impl Request {
+    /// Make the proof in this process.
+    fn run(&self) -> Result<Vec<u8>> {
+        let proof = match self {
+            Request::Post { digest } => gen_cb_for_msg(string_to_f(digest)),
+            Request::PostPseudo { claimed, context } => {
+                pseudo_proof_with_msg(string_to_f(claimed), string_to_f(context))
+            }
+            Request::PostPseudoRate {
+                claimed,
+                context,
+                i,
+            } => rate_pseudo_proof_with_msg(
+                string_to_f(claimed),
+                string_to_f(context),
+                string_to_f(i),
+            ),
+            Request::Vote { claimed, context } => {
+                pseudo_proof_vote(string_to_f(claimed), string_to_f(context))
+            }
+            Request::Scan => scan(),
+            Request::Dm { i, context } => return make_dm_proof(*i, string_to_f(context)),
+            Request::Authorship { indices } => return make_authorship_proof(indices),
+            Request::Badge { i, badge } => make_badge_proof(*i, string_to_f(badge)),
+            Request::Reputation { i, at_least } => return make_rep_proof(*i, *at_least),
+        };
+        proof.map_err(|e| anyhow!("{:?}", e))
+    }
+}
+
+fn socket_path() -> PathBuf {
+    data_path("daemon.sock")
+}
+
+/// Make the proof, through the daemon if one is listening.
+pub fn prove(request: Request) -> Result<Vec<u8>> {
+    let stream = match UnixStream::connect(socket_path()) {
+        Ok(stream) => stream,
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return request.run();
+        }
+        Err(e) => return Err(e).context("failed to connect to the daemon"),
+    };
+    say!("[USER] Proving with the daemon...");
+    serde_json::to_writer(&stream, &request)?;
+    (&stream).write_all(b"\n")?;
+
+    let mut reader = BufReader::new(&stream);
+    loop {
+        let mut line = String::new();
+        reader
+            .read_line(&mut line)
+            .context("failed to read from the daemon")?;
+        match serde_json::from_str(&line).context("invalid response from the daemon")? {
+            Response::Progress(stage) => progress(stage),
+            Response::Proof(proof) => return Ok(hex::decode(proof)?),
+            Response::Error(e) => return Err(anyhow!("the daemon failed to prove: {}", e)),
+        }
+    }
+}
+
+fn respond(mut stream: &UnixStream, response: &Response) -> io::Result<()> {
+    serde_json::to_writer(&mut stream, response)?;
+    stream.write_all(b"\n")
+}
+
+/// Load the proving keys and the user, and prove requests until the process is stopped.
+pub fn serve() -> Result<()> {
+    let path = socket_path();
+    if UnixStream::connect(&path).is_ok() {
+        anyhow::bail!("a daemon is already listening on {}", path.display());
+    }
+    // Left behind by a daemon which did not stop cleanly
+    let _ = fs::remove_file(&path);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    on_progress(|stage| {
+        if let Some(stream) = CLIENT.lock().unwrap().as_ref() {
+            // The command may have given up on the proof
+            let _ = respond(stream, &Response::Progress(stage));
+        }
+    });
+    say!("[DAEMON] Loading proving keys and the user...");
+    preload();
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("failed to listen on {}", path.display()))?;
+    say!("[DAEMON] Listening on {}", path.display());
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle(stream) {
+                    eprintln!("[DAEMON] {:#}", e);
+                }
+            }
+            Err(e) => eprintln!("[DAEMON] Failed to accept a connection: {}", e),
+        }
+    }
+    Ok(())
+}
+
+fn handle(stream: UnixStream) -> Result<()> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => {
+            say!("[DAEMON] {:?}", request);
+            *CLIENT.lock().unwrap() = stream.try_clone().ok();
+            // Proving panics on some failures, which should not stop the daemon
+            let response = match panic::catch_unwind(AssertUnwindSafe(|| request.run())) {
+                Ok(Ok(proof)) => Response::Proof(hex::encode(proof)),
+                Ok(Err(e)) => Response::Error(format!("{:#}", e)),
+                Err(_) => Response::Error("proving panicked".to_string()),
+            };
+            *CLIENT.lock().unwrap() = None;
+            response
+        }
+        Err(e) => Response::Error(format!("invalid request: {}", e)),
+    };
+    respond(&stream, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+
+    // Requests are tagged with their operation, and the daemon answers anything else with an error
+    #[test]
+    fn requests() {
+        let request = Request::Authorship { indices: vec![0, 2] };
+        let line = serde_json::to_string(&request).unwrap();
+        assert_eq!(line, r#"{"op":"authorship","indices":[0,2]}"#);
+
+        let (command, daemon) = UnixStream::pair().unwrap();
+        (&command).write_all(b"{\"op\":\"prove everything\"}\n").unwrap();
+        handle(daemon).unwrap();
+        let mut line = String::new();
+        BufReader::new(&command).read_line(&mut line).unwrap();
+        match serde_json::from_str(&line).unwrap() {
+            Response::Error(e) => assert!(e.starts_with("invalid request")),
+            _ => panic!("expected an error, got {}", line),
+        }
+    }
+
+    // The command reports each step the daemon sends before the proof
+    #[test]
+    fn relays_progress() {
+        let _data = testing::data_dir();
+        static STAGES: Mutex<Vec<Stage>> = Mutex::new(vec![]);
+        on_progress(|stage| STAGES.lock().unwrap().push(stage));
+        fs::create_dir_all(socket_path().parent().unwrap()).unwrap();
+        let listener = UnixListener::bind(socket_path()).unwrap();
+        let daemon = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            for stage in [Stage::Witness, Stage::LoadKey, Stage::Prove] {
+                respond(&stream, &Response::Progress(stage)).unwrap();
+            }
+            respond(&stream, &Response::Proof("4871".to_string())).unwrap();
+        });
+
+        let proof = prove(Request::Scan).unwrap();
+        daemon.join().unwrap();
+        assert_eq!(proof, [0x48, 0x71]);
+        assert_eq!(*STAGES.lock().unwrap(), [Stage::Witness, Stage::LoadKey, Stage::Prove]);
+    }
+}
@@ -1,41 +1,42 @@
 use std::{
+    collections::BTreeMap,
     fs::{File, OpenOptions},
-    io::{BufRead, BufReader, BufWriter, Write},
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use ark_bn254::Fr;
-use ark_ff::{BigInteger, BigInteger256, PrimeField, ToConstraintField};
+use ark_ff::{BigInteger256, PrimeField, ToConstraintField};
 use ark_groth16::{Groth16, ProvingKey};
 use ark_relations::r1cs::SynthesisError;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
 use ark_std::{fs, result::Result::Ok, UniformRand};
 use common::{
-    E, F,
+    with_authors, Args, Cr, E, F, Vrf,
     zk::{
         authorship_pred, badge_pred, exec_pseudo_rate_standint, exec_pseudo_standint, exec_scanint, exec_standint,
-        BadgesArgs, BadgesArgsVar, MsgUser, PseudonymArgs, PseudonymArgsPair, PseudonymArgsPairVar,
-        PseudonymArgsRate, PseudonymArgsVar, pseudonym_pred,
+        BadgesArgs, BadgesArgsVar, MsgUser, PseudonymArgs, PseudonymArgsRate, PseudonymArgsVar,
+        RepArgs, RepArgsVar, SameAuthorArgs, SameAuthorArgsVar, pseudonym_pred, reputation_pred,
     },
 };
 use identicon_rs::Identicon;
-use petname::{Generator, Petnames};
-use rand::{
-    rngs::{OsRng, StdRng},
-    SeedableRng,
-};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use url::Url;
 use zk_callbacks::{
-    crypto::hash::HasherZK,
+    crypto::{enc::CPACipher, vrf::VrfZK},
     generic::{
+        anonymity::{AnonymityGuard, Guardrail},
         bulletin::PublicUserBul,
         callbacks::CallbackCom,
+        keystore::load_key_mmap,
         object::{Com, Time},
+        rng::ZkRng,
         user::User,
+        wire::write_envelope,
     },
     impls::{
         centralized::{crypto::PlainTikCrypto, ds::sigstore::GRSchnorrObjStore},
@@ -44,14 +45,12 @@ use zk_callbacks::{
 };
 
 use crate::bul::BulNet;
+use crate::config::{data_path, server, settings};
+use crate::keystore as secrets;
+use crate::pseudonyms::{pseudonyms_path, Pseudonym, Pseudonyms, PSEUDO_LOG};
+use crate::say;
 
 
-#[derive(Serialize, Deserialize)]
-pub struct PseudonymProofEntry {
-    pub context: String,
-    pub claimed: String,
-}
-
 #[derive(Serialize, Deserialize)]
 pub struct PollPseudonymProofEntry {
     pub context: String,
@@ -59,9 +58,38 @@ pub struct PollPseudonymProofEntry {
     pub timestamp: u64,
 }
 
-fn save_struct(user: &User<F, MsgUser>) -> std::io::Result<()> {
-    let file = File::create("client/user.bin")?;
-    let mut writer = BufWriter::new(file);
+/// The user last loaded or saved by this process, with the file it is in and its modification
+/// time then, so a long-running process only reads the file again once something else writes it.
+static USER: Mutex<Option<(PathBuf, SystemTime, User<F, MsgUser>)>> = Mutex::new(None);
+
+/// The user after an interaction the server has yet to acknowledge, kept next to the user file
+/// until the [`outbox`](crate::outbox) makes it the user.
+pub fn pending_user_file() -> PathBuf {
+    settings().user_file.with_extension("pending")
+}
+
+/// The file the current user is in: the pending user if there is one.
+fn current_user_file() -> PathBuf {
+    let pending = pending_user_file();
+    if pending.exists() {
+        pending
+    } else {
+        settings().user_file.clone()
+    }
+}
+
+/// Save a user the server has acknowledged.
+pub(crate) fn save_struct(user: &User<F, MsgUser>) -> std::io::Result<()> {
+    write_user(&settings().user_file, user)
+}
+
+/// Save the user an interaction leads to, until the server acknowledges it.
+fn save_pending(user: &User<F, MsgUser>) -> std::io::Result<()> {
+    write_user(&pending_user_file(), user)
+}
+
+fn write_user(path: &Path, user: &User<F, MsgUser>) -> std::io::Result<()> {
+    let mut writer = Vec::new();
     user.data
         .serialize_with_mode(&mut writer, Compress::No)
         .unwrap();
@@ -77,24 +105,37 @@ fn save_struct(user: &User<F, MsgUser>) -> std::io::Result<()> {
     user.in_progress_cbs
         .serialize_with_mode(&mut writer, Compress::No)
         .unwrap();
-    writer.flush()?;
+    secrets::write(path, &writer)?;
+    let modified = fs::metadata(path)?.modified()?;
+    *USER.lock().unwrap() = Some((path.to_path_buf(), modified, user.clone()));
     Ok(())
 }
 
 fn load_struct() -> std::io::Result<User<F, MsgUser>> {
-    let file = File::open("client/user.bin")?;
-    let mut reader = BufReader::new(file);
-    let obj = User::<F, MsgUser>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)
-        .map_err(|e| {
-        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:?}", e))
-    })?;
+    let path = current_user_file();
+    let modified = fs::metadata(&path)?.modified()?;
+    if let Some((loaded_path, loaded, user)) = USER.lock().unwrap().as_ref() {
+        if *loaded_path == path && *loaded == modified {
+            return Ok(user.clone());
+        }
+    }
+    let obj = read_user(&path)?;
+    *USER.lock().unwrap() = Some((path, modified, obj.clone()));
     Ok(obj)
 }
 
+/// Read the user saved in `path`.
+pub(crate) fn read_user(path: &Path) -> std::io::Result<User<F, MsgUser>> {
+    let bytes = secrets::read(path)?;
+    User::<F, MsgUser>::deserialize_with_mode(&bytes[..], Compress::No, Validate::Yes).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:?}", e))
+    })
+}
+
 pub fn join2() -> Result<()> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
+    let bul = BulNet::new(server());
 
-    let mut rng = OsRng;
+    let mut rng = ZkRng::from_env();
     let sk = F::rand(&mut rng);
 
     // Create a new user
@@ -113,19 +154,22 @@ pub fn join2() -> Result<()> {
     );
 
     let commit: Com<F> = user.commit::<Poseidon<2>>();
-    println!("{:?}", commit.clone());
+    say!("{:?}", commit.clone());
 
     let _ = bul.join_bul(commit);
 
-    println!("{:?}", user);
+    say!("{:?}", user);
+    // Whatever the old user had queued or pending is meaningless to the new one
+    crate::outbox::clear()?;
     let _ = save_struct(&user);
 
-    // Delete pseudo_log.jsonl if it exists
-    let _ = fs::remove_file("client/pseudo_log.jsonl");
-    let _ = fs::remove_file("client/contexts.jsonl");
+    // The pseudonyms of the old user are not the new one's
+    let _ = fs::remove_file(data_path(PSEUDO_LOG));
+    let _ = fs::remove_file(pseudonyms_path());
+    let _ = fs::remove_file(contexts_path());
 
     // Generate new pseudo proof
-    gen_pseudo();
+    gen_pseudo(None)?;
 
     Ok(())
 }
@@ -152,18 +196,33 @@ pub fn write_timing(id: &str, start: SystemTime, end: SystemTime, path: &str) ->
     std::fs::write(path, json.to_string())
 }
 
+static TIMINGS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Write timing measurements under `dir` rather than `json_files`. Only the first call has an
+/// effect, so this should be called on startup.
+pub fn set_timings_dir(dir: impl Into<PathBuf>) {
+    let _ = TIMINGS_DIR.set(dir.into());
+}
+
+/// The directory timing measurements labelled `label` are written to.
+fn timings_dir(label: &str) -> PathBuf {
+    TIMINGS_DIR
+        .get()
+        .map_or(Path::new("json_files"), PathBuf::as_path)
+        .join(label)
+}
+
 /// Saves just the start time in UNIX milliseconds for a given label.
 pub fn save_start_time(label: &str) -> std::io::Result<()> {
-    let path = format!("json_files/{}/start_time.json", label);
+    let path = timings_dir(label).join("start_time.json");
     let start = SystemTime::now();
     let start_ms = start.duration_since(UNIX_EPOCH).unwrap().as_millis();
-    // std::fs::create_dir_all(format!("json_files/{}", label))?;
     std::fs::write(path, json!({ "start_ms": start_ms }).to_string())
 }
 
 /// Loads start time from a previously saved UNIX milliseconds file.
 pub fn load_start_time(label: &str) -> Result<SystemTime, std::io::Error> {
-    let path = format!("json_files/{}/start_time.json", label);
+    let path = timings_dir(label).join("start_time.json");
     let contents = std::fs::read_to_string(path)?;
     let val: serde_json::Value = serde_json::from_str(&contents)?;
     let start_ms = val["start_ms"].as_u64().expect("start_ms must be a u64");
@@ -180,12 +239,12 @@ pub fn append_timing_line(label: &str, start: SystemTime, end: SystemTime) -> Re
         "duration_ms": duration_ms,
     }).to_string();
 
-    let dir = format!("json_files/{}", label);
+    let dir = timings_dir(label);
     fs::create_dir_all(&dir)?;
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(format!("{}/timings.jsonl", dir))?;
+        .open(dir.join("timings.jsonl"))?;
     writeln!(file, "{}", line)?;
     Ok(())
 }
@@ -199,12 +258,12 @@ pub fn append_timing_line_features(label: &str, start: SystemTime, end: SystemTi
         "duration_ms": duration_ms,
     }).to_string();
 
-    let dir = format!("json_files/{}", label);
+    let dir = timings_dir(label);
     fs::create_dir_all(&dir)?;
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(format!("{}/features_timings.jsonl", dir))?;
+        .open(dir.join("features_timings.jsonl"))?;
     writeln!(file, "{}", line)?;
     Ok(())
 }
@@ -218,12 +277,12 @@ pub fn append_timing_line_call_cb(label: &str, start: SystemTime, end: SystemTim
         "duration_ms": duration_ms,
     }).to_string();
 
-    let dir = format!("json_files/{}", label);
+    let dir = timings_dir(label);
     fs::create_dir_all(&dir)?;
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(format!("{}/call_timings.jsonl", dir))?;
+        .open(dir.join("call_timings.jsonl"))?;
     writeln!(file, "{}", line)?;
     Ok(())
 }
@@ -237,12 +296,12 @@ pub fn append_timing_line_epoch(label: &str, start: SystemTime, end: SystemTime)
         "duration_ms": duration_ms,
     }).to_string();
 
-    let dir = format!("json_files/{}", label);
+    let dir = timings_dir(label);
     fs::create_dir_all(&dir)?;
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(format!("{}/epoch_timings.jsonl", dir))?;
+        .open(dir.join("epoch_timings.jsonl"))?;
     writeln!(file, "{}", line)?;
     Ok(())
 }
@@ -257,24 +316,94 @@ pub fn append_timing_line_verify(label: &str, start: SystemTime, end: SystemTime
         "duration_ms": duration_ms,
     }).to_string();
 
-    let dir = format!("json_files/{}", label);
+    let dir = timings_dir(label);
     fs::create_dir_all(&dir)?;
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(format!("{}/verify_timings.jsonl", dir))?;
+        .open(dir.join("verify_timings.jsonl"))?;
     writeln!(file, "{}", line)?;
     Ok(())
 }
 
+/// A step of making a proof, as reported to the function given to [`on_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Loading the user and fetching what the witness is built from off the bulletins.
+    Witness,
+    /// Loading the proving key, downloading it if the server has a new one.
+    LoadKey,
+    /// Generating the witness and proving.
+    Prove,
+}
+
+impl Stage {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Stage::Witness => "Gathering the witness",
+            Stage::LoadKey => "Loading the proving key",
+            Stage::Prove => "Proving",
+        }
+    }
+}
+
+static PROGRESS: OnceLock<Box<dyn Fn(Stage) + Send + Sync>> = OnceLock::new();
+
+/// Call `f` with each step of the proofs made in this process. Only the first call has an
+/// effect, so this should be called on startup.
+pub fn on_progress(f: impl Fn(Stage) + Send + Sync + 'static) {
+    let _ = PROGRESS.set(Box::new(f));
+}
+
+/// Report a step of the proof being made.
+pub(crate) fn progress(stage: Stage) {
+    if let Some(f) = PROGRESS.get() {
+        f(stage);
+    }
+}
+
+/// The k-anonymity guard for interactions, configured by `WISPY_MIN_ANONYMITY` (default 5) and
+/// `WISPY_ANONYMITY_MODE` (`warn` or `refuse`, default `warn`).
+pub fn anonymity_guard() -> AnonymityGuard {
+    let k = std::env::var("WISPY_MIN_ANONYMITY")
+        .ok()
+        .and_then(|k| k.parse().ok())
+        .unwrap_or(5);
+    let mode = match std::env::var("WISPY_ANONYMITY_MODE").as_deref() {
+        Ok("refuse") => Guardrail::Refuse,
+        _ => Guardrail::Warn,
+    };
+    AnonymityGuard::new(k, mode)
+}
+
+/// Check the anonymity set of the bulletin before interacting. Warns, or refuses with an
+/// unsatisfiable error, depending on the guard.
+pub fn check_anonymity(bul: &BulNet) -> Result<(), SynthesisError> {
+    let guard = anonymity_guard();
+    let check = guard.check::<F, MsgUser, _>(bul);
+    if let Err(e) = &check {
+        eprintln!("[USER] Warning: {}", e);
+    }
+    if guard.permits(&check) {
+        Ok(())
+    } else {
+        eprintln!("[USER] Refusing to interact (set WISPY_ANONYMITY_MODE=warn to override)");
+        Err(SynthesisError::Unsatisfiable)
+    }
+}
 
-pub fn gen_cb_for_msg() -> Result<Vec<u8>, SynthesisError> {
+/// Prove a standard post. `digest` is the
+/// [`attachment_digest`](common::zk::attachment_digest) of the files sent with it.
+pub fn gen_cb_for_msg(digest: F) -> Result<Vec<u8>, SynthesisError> {
+    progress(Stage::Witness);
     
-    println!("[USER] Interacting (proving)...");
+    say!("[USER] Interacting (proving)...");
 
 
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let mut rng = OsRng;
+    let bul = BulNet::new(server());
+    check_anonymity(&bul)?;
+    let mut rng = ZkRng::from_env();
 
     let mut user: User<F, MsgUser> = load_struct().unwrap();
 
@@ -283,6 +412,8 @@ pub fn gen_cb_for_msg() -> Result<Vec<u8>, SynthesisError> {
     let mut pub_inputs = vec![];
     pub_inputs.extend::<Vec<F>>(().to_field_elements().unwrap());
 
+    progress(Stage::Prove);
+
     // Start (1)
     let start = SystemTime::now();
 
@@ -292,7 +423,7 @@ pub fn gen_cb_for_msg() -> Result<Vec<u8>, SynthesisError> {
         &bul,
         &pk_standard,
         Time::from(0),
-        F::from(0),
+        digest,
         (),
     )
     .unwrap();
@@ -304,51 +435,109 @@ pub fn gen_cb_for_msg() -> Result<Vec<u8>, SynthesisError> {
         eprintln!("Failed to write timing file for proof gen: {}", e);
     }
     
-    println!("[USER] Executed interaction! New user: {:?} \n", user);
+    say!("[USER] Executed interaction! New user: {:?} \n", user);
 
-    println!("[BULLETIN / SERVER] Verifying and storing...");
+    say!("[BULLETIN / SERVER] Verifying and storing...");
 
     let mut payload = vec![];
-    exec.serialize_with_mode(&mut payload, Compress::No)
-        .unwrap();
+    exec.write_to(&mut payload, Compress::No).unwrap();
 
-    let _ = save_struct(&user);
-    println!("{:?}", user);
+    let _ = save_pending(&user);
+    say!("{:?}", user);
 
 
     Ok(payload)
 }
 
 pub fn scan() -> Result<Vec<u8>, SynthesisError> {
-    println!("[USER] Scanning a ticket... ");
+    progress(Stage::Witness);
+    say!("[USER] Scanning a ticket... ");
 
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let mut rng = OsRng;
+    let bul = BulNet::new(server());
+    let mut rng = ZkRng::from_env();
 
     let mut user: User<F, MsgUser> = load_struct().unwrap();
 
     let pk_scan = get_scanning_proving_key();
+    progress(Stage::Prove);
     let scan_one = exec_scanint(&mut user, &mut rng, &bul, &pk_scan, &bul, Time::from(0)).unwrap();
 
-    println!("[USER] Scanned single ticket... {:?} \n", user);
+    say!("[USER] Scanned single ticket... {:?} \n", user);
 
-    println!("[BULLETIN / SERVER] Verifying and storing scan...");
+    say!("[BULLETIN / SERVER] Verifying and storing scan...");
 
     let mut payload2 = vec![];
-    scan_one
-        .serialize_with_mode(&mut payload2, Compress::No)
-        .unwrap();
+    scan_one.write_to(&mut payload2, Compress::No).unwrap();
 
-    println!("[BULLETIN / SERVER] Verifying and storing scan...");
+    say!("[BULLETIN / SERVER] Verifying and storing scan...");
 
-    let _ = save_struct(&user);
-    println!("{:?}", user);
+    let _ = save_pending(&user);
+    say!("{:?}", user);
 
     Ok(payload2)
 }
 
+/// The callbacks called on the user which a scan has yet to apply.
+#[derive(Serialize)]
+pub struct ScanStatus {
+    /// How many of the user's callbacks have been called.
+    pub called: usize,
+    /// Whether the user is part way through scanning, when no other interaction can be made.
+    pub scanning: bool,
+    /// How many scans it takes to apply them, each scanning one callback.
+    pub scans: usize,
+}
+
+impl ScanStatus {
+    /// The scans `user` needs when `called` of its callbacks have been called.
+    pub fn of(user: &User<F, MsgUser>, called: usize) -> Self {
+        // A scan goes through every callback the user holds, one per proof
+        let scans = match user.scan_index {
+            Some(index) => user.num_outstanding_callbacks() - index,
+            None if called > 0 => user.num_outstanding_callbacks(),
+            None => 0,
+        };
+        ScanStatus {
+            called,
+            scanning: user.is_scanning(),
+            scans,
+        }
+    }
+
+    /// Whether the user should scan before interacting.
+    pub fn pending(&self) -> bool {
+        self.scanning || self.called > 0
+    }
+}
+
+/// The callbacks of `user` which have been called, by index, with the argument each was called
+/// with, from the callback bulletin.
+pub fn called_callbacks(user: &User<F, MsgUser>) -> Result<Vec<(usize, Args)>> {
+    let called = BulNet::new(server())
+        .called_callbacks()
+        .map_err(|e| anyhow::anyhow!("failed to fetch the callback bulletin: {}", e))?;
+    Ok((0..user.num_outstanding_callbacks())
+        .filter_map(|i| {
+            let cb = user.get_cb::<Args, Cr>(i);
+            let ticket = cb.get_ticket();
+            called
+                .iter()
+                .find(|(tik, _)| *tik == ticket)
+                .map(|(_, ct)| (i, cb.cb_entry.enc_key.decrypt(*ct)))
+        })
+        .collect())
+}
+
+/// Check the callback bulletin for callbacks called on the user since their last scan.
+pub fn scan_status() -> Result<ScanStatus> {
+    let user = load_struct()?;
+    let called = called_callbacks(&user)?.len();
+    Ok(ScanStatus::of(&user, called))
+}
+
+
 pub fn send_callback_to_endpoint(timestamp: u64, endpoint: &str) -> Result<()> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
+    let bul = BulNet::new(server());
 
     // Create JSON body
     let body = serde_json::to_vec(&serde_json::json!({ "timestamp": timestamp }))?;
@@ -373,8 +562,20 @@ pub fn send_callback_to_endpoint(timestamp: u64, endpoint: &str) -> Result<()> {
     let mut buf = Vec::new();
     cb.serialize_with_mode(&mut buf, Compress::No)?;
 
+    // Banning and updating reputation require a moderator's API key
     let target_url = bul.api.join(endpoint)?;
-    bul.client.post(target_url).body(buf).send()?;
+    let mut request = bul.client.post(target_url).body(buf);
+    if let Ok(key) = std::env::var("WISPY_API_KEY") {
+        request = request.bearer_auth(key);
+    }
+    let resp = request.send()?;
+    if !resp.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "Server rejected the request ({}): {}",
+            resp.status(),
+            resp.text().unwrap_or_default()
+        ));
+    }
 
     Ok(())
 }
@@ -388,12 +589,12 @@ pub fn rep(timestamp: u64) -> Result<()> {
 }
 
 pub fn prf(sk: &F, ctx: &F) -> F {
-    Poseidon::<2>::hash(&[*sk, *ctx])
+    Vrf::evaluate(sk, &[*ctx])
 }
 
 pub fn prf2(ctx: &F, i: &F) -> F {
     let user = load_struct().unwrap();
-    Poseidon::<2>::hash(&[user.data.sk, *ctx, *i])
+    Vrf::evaluate(&user.data.sk, &[*ctx, *i])
 }
 
 pub fn compute_pseudo_for_poll(context: &F) -> F {
@@ -401,40 +602,34 @@ pub fn compute_pseudo_for_poll(context: &F) -> F {
     prf(&user.data.sk, context)
 }
 
-pub fn gen_pseudo() {
-    let mut rng = OsRng;
-    let user = load_struct().unwrap();
+/// Make a new pseudonym, in a context drawn at random, and add it to the store.
+pub fn gen_pseudo(label: Option<String>) -> Result<Pseudonym> {
+    let mut rng = ZkRng::from_env();
+    let user = load_struct()?;
     let context: F = F::rand(&mut rng);
-    let claimed = Poseidon::<2>::hash(&[user.data.sk, context]);
-
-    let entry = PseudonymProofEntry {
-        context: context.into_bigint().to_string(),
-        claimed: claimed.into_bigint().to_string(),
-    };
-
-    // Append to file as JSONL
-    let mut file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("client/pseudo_log.jsonl")
-        .unwrap();
+    let claimed = Vrf::evaluate(&user.data.sk, &[context]);
 
-    writeln!(file, "{}", serde_json::to_string(&entry).unwrap()).unwrap();
-    let _ = save_struct(&user);
+    let mut pseudonyms = Pseudonyms::load()?;
+    let pseudonym = pseudonyms.add(context, claimed, label)?.clone();
+    pseudonyms.save()?;
+    Ok(pseudonym)
 }
 
 pub fn pseudo_proof_with_msg(claimed: F, context: F) -> Result<Vec<u8>, SynthesisError> {
-    println!("[USER] Interacting (proving)...");
+    progress(Stage::Witness);
+    say!("[USER] Interacting (proving)...");
 
     let mut user: User<F, MsgUser> = load_struct().unwrap();
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let mut rng = OsRng;
+    let bul = BulNet::new(server());
+    check_anonymity(&bul)?;
+    let mut rng = ZkRng::from_env();
 
     let pk_standard = get_standard_pseudo_proving_key();
 
     let pseudo = PseudonymArgs { context, claimed };
-    println!("[USER] Generating pseudonym proof with {:?}", pseudo);
+    say!("[USER] Generating pseudonym proof with {:?}", pseudo);
 
+    progress(Stage::Prove);
     let start = SystemTime::now();
 
     // Execute standard interaction
@@ -456,37 +651,39 @@ pub fn pseudo_proof_with_msg(claimed: F, context: F) -> Result<Vec<u8>, Synthesi
         eprintln!("Failed to write timing file for proof gen: {}", e);
     }
 
-    println!("[USER] Executed interaction");
+    say!("[USER] Executed interaction");
 
     // Serialize all three components: exec, proof, pub_inputs
     let mut payload = vec![];
-    exec.serialize_with_mode(&mut payload, Compress::No)
-        .unwrap();
+    exec.write_to(&mut payload, Compress::No).unwrap();
     vec![context, claimed]
         .serialize_with_mode(&mut payload, Compress::No)
         .unwrap();
 
-    let _ = save_struct(&user);
-    println!("{:?}", user);
+    let _ = save_pending(&user);
+    say!("{:?}", user);
     Ok(payload)
 }
 
 pub fn rate_pseudo_proof_with_msg(claimed: F, context: F, i: F) -> Result<Vec<u8>, SynthesisError> {
-    println!("[USER] Interacting (proving)...");
+    progress(Stage::Witness);
+    say!("[USER] Interacting (proving)...");
 
     let mut user: User<F, MsgUser> = load_struct().unwrap();
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let mut rng = OsRng;
+    let bul = BulNet::new(server());
+    check_anonymity(&bul)?;
+    let mut rng = ZkRng::from_env();
 
     let pk_standard = get_standard_pseudor_proving_key();
 
     let pseudo = PseudonymArgsRate {
         context,
         claimed,
-        i,
+        index: i,
     };
-    println!("[USER] Generating pseudonym proof with {:?}", pseudo);
+    say!("[USER] Generating pseudonym proof with {:?}", pseudo);
 
+    progress(Stage::Prove);
     let start = SystemTime::now();
 
     // Execute standard interaction
@@ -507,55 +704,80 @@ pub fn rate_pseudo_proof_with_msg(claimed: F, context: F, i: F) -> Result<Vec<u8
         eprintln!("Failed to write timing file for proof gen: {}", e);
     }
 
-    println!("[USER] Executed interaction");
+    say!("[USER] Executed interaction");
 
     // Serialize all three components: exec, proof, pub_inputs
     let mut payload = vec![];
-    exec.serialize_with_mode(&mut payload, Compress::No)
-        .unwrap();
+    exec.write_to(&mut payload, Compress::No).unwrap();
     vec![context, claimed, i]
         .serialize_with_mode(&mut payload, Compress::No)
         .unwrap();
 
-    let _ = save_struct(&user);
-    println!("{:?}", user);
+    let _ = save_pending(&user);
+    say!("{:?}", user);
     Ok(payload)
 }
 
 pub fn pseudo_proof_vote(claimed: F, context: F) -> Result<Vec<u8>, SynthesisError> {
-    println!("[USER] Interacting (proving)...");
+    let mut proofs = pseudo_proof_votes(&[(claimed, context)])?;
+    Ok(proofs.remove(0))
+}
+
+/// Prove votes for several polls, as `(claimed, context)` pairs, in parallel. Votes leave the
+/// user as it is, so they all use the membership data fetched once for its commitment.
+pub fn pseudo_proof_votes(votes: &[(F, F)]) -> Result<Vec<Vec<u8>>, SynthesisError> {
+    progress(Stage::Witness);
+    say!("[USER] Interacting (proving)...");
 
     let user: User<F, MsgUser> = load_struct().unwrap();
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let mut rng = OsRng;
+    let bul = BulNet::new(server());
+    let mut rng = ZkRng::from_env();
 
     // Get signature and key for arbitrary predicate proof
     let commit = user.commit::<Poseidon<2>>();
     let (pubkey, sig) = bul.get_membership_data(commit).unwrap();
     let pk_arb_pred = get_arbitrary_pred_pk();
 
-    let pseudo = PseudonymArgs { context, claimed };
-    println!("[USER] Generating pseudonym proof with {:?}", pseudo);
+    // One RNG for each proof, so a seeded run stays reproducible
+    let rngs: Vec<ZkRng> = votes.iter().map(|_| rng.fork()).collect();
 
+    progress(Stage::Prove);
     let start = SystemTime::now();
 
-    let proof = user.prove_statement_and_in::<
-        Poseidon<2>,
-        PseudonymArgs<F>,
-        PseudonymArgsVar<F>,
-        (),
-        (),
-        Groth16<E>,
-        GRSchnorrObjStore,
-    >(
-        &mut rng,
-        pseudonym_pred,
-        &pk_arb_pred,
-        (sig, pubkey),
-        true,
-        pseudo.clone(),
-        (),
-    )?;
+    let proofs = votes
+        .par_iter()
+        .zip(rngs)
+        .map(|(&(claimed, context), mut rng)| {
+            let pseudo = PseudonymArgs { context, claimed };
+            say!("[USER] Generating pseudonym proof with {:?}", pseudo);
+
+            let proof = user.prove_statement_and_in::<
+                Poseidon<2>,
+                PseudonymArgs<F>,
+                PseudonymArgsVar<F>,
+                (),
+                (),
+                Groth16<E>,
+                GRSchnorrObjStore,
+            >(
+                &mut rng,
+                pseudonym_pred,
+                &pk_arb_pred,
+                (sig.clone(), pubkey.clone()),
+                true,
+                pseudo,
+                (),
+            )?;
+
+            // Serialize all three components: exec, proof, pub_inputs
+            let mut payload = vec![];
+            write_envelope(&proof, &mut payload, None, Compress::No).unwrap();
+            vec![context, claimed]
+                .serialize_with_mode(&mut payload, Compress::No)
+                .unwrap();
+            Ok(payload)
+        })
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
 
     let end = SystemTime::now();
 
@@ -563,132 +785,104 @@ pub fn pseudo_proof_vote(claimed: F, context: F) -> Result<Vec<u8>, SynthesisErr
         eprintln!("Failed to write timing file for proof gen: {}", e);
     }
 
-    // Serialize all three components: exec, proof, pub_inputs
-    let mut payload = vec![];
-    proof
-        .serialize_with_mode(&mut payload, Compress::No)
-        .unwrap();
-    vec![context, claimed]
-        .serialize_with_mode(&mut payload, Compress::No)
-        .unwrap();
-
-    let _ = save_struct(&user);
-    Ok(payload)
+    Ok(proofs)
 }
 
-pub fn get_pseudo_from_claimed(claimed_str: String) -> String {
-    // Convert claimed_str back to field element
-    let claimed = F::from_bigint(BigInteger256::from_str(&claimed_str).unwrap()).unwrap();
-
-    // Convert to bytes
-    let claimed_bytes = claimed.into_bigint().to_bytes_le();
-
-    // Pad or truncate to 32 bytes
-    let mut seed = [0u8; 32];
-    let len = claimed_bytes.len().min(32);
-    seed[..len].copy_from_slice(&claimed_bytes[..len]);
-
-    // Seed RNG and generate name
-    let mut rng = StdRng::from_seed(seed);
-    let g = Petnames::default();
-    g.generate(&mut rng, 2, " ")
-        .unwrap_or_else(|| "anonymous user".to_string())
+/// Retrieves the `claimed` and `context` strings of the pseudonym numbered `index`
+pub fn get_claimed_context_by_index(index: usize) -> Option<(String, String)> {
+    let pseudonyms = Pseudonyms::load().ok()?;
+    let pseudonym = pseudonyms.get(index)?;
+    Some((pseudonym.claimed.clone(), pseudonym.context.clone()))
 }
 
-pub fn list_all_pseudos_from_log() {
-    let file = File::open("client/pseudo_log.jsonl").expect("Failed to open pseudo_log.jsonl");
-    let reader = BufReader::new(file);
-
-    for (i, line_result) in reader.lines().enumerate() {
-        let line = line_result.expect("Failed to read line");
-        let json: Value = serde_json::from_str(&line).expect("Invalid JSON");
-
-        if let Some(claimed_str) = json["claimed"].as_str() {
-            let pseudo = get_pseudo_from_claimed(claimed_str.to_string());
-            println!("\"{}\"; index = {}", pseudo, i + 1);
-        } else {
-            eprintln!("Line {} missing 'claimed' field", i + 1);
-        }
+/// Prove the pseudonyms numbered `indices` belong to the same user.
+///
+/// The server's keys link a fixed number of pseudonyms, read off the proving key, so fewer are
+/// padded by repeating the last one.
+pub fn make_authorship_proof(indices: &[usize]) -> Result<Vec<u8>> {
+    let pseudonyms = Pseudonyms::load()?;
+    let mut args = vec![];
+    for &i in indices {
+        let pseudonym = pseudonyms
+            .get(i)
+            .with_context(|| format!("no pseudonym {}", i))?;
+        args.push(PseudonymArgs {
+            context: pseudonym.context(),
+            claimed: pseudonym.claimed(),
+        });
     }
+    prove_same_author(args)
 }
 
-/// Retrieves the `claimed` and `context` strings at the given 1-based line index
-pub fn get_claimed_context_by_index(index: usize) -> Option<(String, String)> {
-    let file = File::open("client/pseudo_log.jsonl").expect("Failed to open pseudo_log.jsonl");
-    let reader = BufReader::new(file);
-
-    for (i, line_result) in reader.lines().enumerate() {
-        if i + 1 == index {
-            let line = line_result.ok()?;
-            let json: Value = serde_json::from_str(&line).ok()?;
-            let claimed = json["claimed"].as_str()?.to_string();
-            let context = json["context"].as_str()?.to_string();
-            return Some((claimed, context));
-        }
-    }
-
-    None // Return None if index is out of bounds or data is malformed
+/// Prove the user owns the pseudonym numbered `i`, for the direct message request whose
+/// [`dm_context`](common::zk::dm_context) is `context`: the pseudonym is linked to the one the
+/// user claims in that context, as an authorship proof links pseudonyms.
+pub fn make_dm_proof(i: usize, context: F) -> Result<Vec<u8>> {
+    let pseudonyms = Pseudonyms::load()?;
+    let pseudonym = pseudonyms
+        .get(i)
+        .with_context(|| format!("no pseudonym {}", i))?;
+    let user: User<F, MsgUser> = load_struct()?;
+    let args = vec![
+        PseudonymArgs {
+            context: pseudonym.context(),
+            claimed: pseudonym.claimed(),
+        },
+        PseudonymArgs {
+            context,
+            claimed: prf(&user.data.sk, &context),
+        },
+    ];
+    prove_same_author(args)
 }
 
-pub fn make_authorship_proof(i1: usize, i2: usize) -> Result<Vec<u8>, SynthesisError> {
+/// Prove the pseudonyms `args` are the same user's, padding them to as many as the server links
+/// by repeating the last.
+fn prove_same_author(mut args: Vec<PseudonymArgs<F>>) -> Result<Vec<u8>> {
+    progress(Stage::Witness);
     let user = load_struct().unwrap();
-    let mut rng = OsRng;
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
+    let mut rng = ZkRng::from_env();
+    let bul = BulNet::new(server());
 
     // Get signature and key for arbitrary predicate proof
     let commit = user.commit::<Poseidon<2>>();
     let (pubkey, sig) = bul.get_membership_data(commit).unwrap();
     let pk_arb_pred = get_arbitrary_pred_pk2();
 
-    let (claimed_str1, context_str1) =
-        get_claimed_context_by_index(i1).expect("Invalid index or malformed line");
-    let (claimed_str2, context_str2) =
-        get_claimed_context_by_index(i2).expect("Invalid index or malformed line");
-
-    let context1 = string_to_f(&context_str1);
-    let claimed1 = string_to_f(&claimed_str1);
-    let context2 = string_to_f(&context_str2);
-    let claimed2 = string_to_f(&claimed_str2);
-
-    let pseudo = PseudonymArgs {
-        context: context1,
-        claimed: claimed1,
-    };
-    println!("[USER] Generating pseudonym proof with {:?}", pseudo);
-    let pseudo2 = PseudonymArgs {
-        context: context2,
-        claimed: claimed2,
-    };
-    println!("[USER] Generating pseudonym proof with {:?}", pseudo2);
-
-    let pair = PseudonymArgsPair {
-        a: pseudo.clone(),
-        b: pseudo2.clone(),
-    };
+    // The public inputs are a context and a pseudonym for each pseudonym linked
+    let k = (pk_arb_pred.vk.gamma_abc_g1.len() - 1) / 2;
+    if args.len() > k {
+        bail!("the server links at most {} pseudonyms, not {}", k, args.len());
+    }
+    for pseudo in &args {
+        say!("[USER] Generating pseudonym proof with {:?}", pseudo);
+    }
+    let last = *args.last().context("no pseudonyms to link")?;
+    args.resize(k, last);
 
     // Strart author
 
+    progress(Stage::Prove);
     let start = SystemTime::now();
 
-    let proof = user
+    let proof = with_authors!(k, N => user
     .prove_statement_and_in::<
         Poseidon<2>,
-        PseudonymArgsPair<F>,         // pub args type
-        PseudonymArgsPairVar<F>,      // pub args var type
+        SameAuthorArgs<F, N>,         // pub args type
+        SameAuthorArgsVar<F, N>,      // pub args var type
         (),
         (),
         Groth16<E>,
         GRSchnorrObjStore,
     >(
         &mut rng,
-        authorship_pred,                // your statement predicate
+        authorship_pred::<N>,           // your statement predicate
         &pk_arb_pred,             // proving key
         (sig.clone(), pubkey),    // membership proof
         true,                     // whether to include object commitment
-        pair.clone(),                  // your user object
+        SameAuthorArgs { pseudonyms: std::array::from_fn(|i| args[i]) },
         (),                 // public inputs go here
-    )
-    .unwrap();
+    )?);
 
     let end = SystemTime::now();
 
@@ -697,15 +891,13 @@ pub fn make_authorship_proof(i1: usize, i2: usize) -> Result<Vec<u8>, SynthesisE
     }
 
     let mut payload = vec![];
-    proof
-        .serialize_with_mode(&mut payload, Compress::No)
-        .unwrap();
-    vec![context1, claimed1, context2, claimed2]
+    write_envelope(&proof, &mut payload, None, Compress::No).unwrap();
+    args.iter()
+        .flat_map(|p| [p.context, p.claimed])
+        .collect::<Vec<F>>()
         .serialize_with_mode(&mut payload, Compress::No)
         .unwrap();
 
-    let _ = save_struct(&user);
-
     Ok(payload)
 }
 
@@ -714,9 +906,10 @@ pub fn string_to_f(s: &str) -> F {
 }
 
 pub fn make_badge_proof(i: usize, badge: F) -> Result<Vec<u8>, SynthesisError> {
+    progress(Stage::Witness);
     let user = load_struct().unwrap();
-    let mut rng = OsRng;
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
+    let mut rng = ZkRng::from_env();
+    let bul = BulNet::new(server());
 
     // Get signature and key for arbitrary predicate proof
     let commit = user.commit::<Poseidon<2>>();
@@ -731,6 +924,7 @@ pub fn make_badge_proof(i: usize, badge: F) -> Result<Vec<u8>, SynthesisError> {
         claimed: badge.clone(),
     };
 
+    progress(Stage::Prove);
     let start= SystemTime::now();
 
     let proof = user
@@ -760,14 +954,74 @@ pub fn make_badge_proof(i: usize, badge: F) -> Result<Vec<u8>, SynthesisError> {
     }
 
     let mut payload = vec![];
-    proof
-        .serialize_with_mode(&mut payload, Compress::No)
-        .unwrap();
+    write_envelope(&proof, &mut payload, None, Compress::No).unwrap();
     vec![i_f, badge]
         .serialize_with_mode(&mut payload, Compress::No)
         .unwrap();
 
-    let _ = save_struct(&user);
+    Ok(payload)
+}
+
+/// Prove the user has a reputation of at least `at_least`, under the pseudonym numbered `index`,
+/// without revealing the reputation.
+pub fn make_rep_proof(index: usize, at_least: u64) -> Result<Vec<u8>> {
+    progress(Stage::Witness);
+    let user = load_struct().unwrap();
+    // The proof would be made, but not verify
+    if user.data.reputation < F::from(at_least) {
+        bail!("the reputation of the user is below {}", at_least);
+    }
+    let mut rng = ZkRng::from_env();
+    let bul = BulNet::new(server());
+
+    let commit = user.commit::<Poseidon<2>>();
+    let (pubkey, sig) = bul.get_membership_data(commit).unwrap();
+    let pk = get_reputation_pk();
+
+    let pseudonym = Pseudonyms::load()?
+        .get(index)
+        .map(|p| PseudonymArgs {
+            context: p.context(),
+            claimed: p.claimed(),
+        })
+        .with_context(|| format!("no pseudonym {}", index))?;
+    let args = RepArgs {
+        pseudonym,
+        at_least: F::from(at_least),
+    };
+
+    progress(Stage::Prove);
+    let start = SystemTime::now();
+
+    let proof = user.prove_statement_and_in::<
+        Poseidon<2>,
+        RepArgs<F>,
+        RepArgsVar<F>,
+        (),
+        (),
+        Groth16<E>,
+        GRSchnorrObjStore,
+    >(
+        &mut rng,
+        reputation_pred,
+        &pk,
+        (sig, pubkey),
+        true,
+        args.clone(),
+        (),
+    )?;
+
+    let end = SystemTime::now();
+
+    if let Err(e) = append_timing_line("rep_proof", start, end) {
+        eprintln!("Failed to write timing file for proof gen: {}", e);
+    }
+
+    let mut payload = vec![];
+    write_envelope(&proof, &mut payload, None, Compress::No).unwrap();
+    vec![pseudonym.context, pseudonym.claimed, args.at_least]
+        .serialize_with_mode(&mut payload, Compress::No)
+        .unwrap();
 
     Ok(payload)
 }
@@ -790,9 +1044,9 @@ pub fn generate_badges(b1: F, b2: F, b3: F) {
     badge3.push_str(k);
     badge3.push_str(&b3.to_string());
 
-    let path1 = format!("client/badge{}.png", i);
-    let path2 = format!("client/badge{}.png", j);
-    let path3 = format!("client/badge{}.png", k);
+    let path1 = data_path(&format!("badge{}.png", i));
+    let path2 = data_path(&format!("badge{}.png", j));
+    let path3 = data_path(&format!("badge{}.png", k));
 
     let avatar_path_1 = std::env::current_dir().unwrap().join(path1);
     let avatar_path_2 = std::env::current_dir().unwrap().join(path2);
@@ -812,153 +1066,309 @@ pub fn generate_badges(b1: F, b2: F, b3: F) {
 
 }
 
-pub fn get_standard_proving_key() -> ProvingKey<E> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let url = bul
-        .api
-        .join("api/interaction/standard/proving_key")
-        .unwrap();
+/// The proving keys loaded by this process, by name, with the digest each was loaded for.
+static PROVING_KEYS: Mutex<BTreeMap<String, (String, Arc<ProvingKey<E>>)>> =
+    Mutex::new(BTreeMap::new());
 
-    let bytes = bul
+/// The digests of the server's proving keys, by name, from `/api/keys/meta`. Empty if the server
+/// does not report them.
+pub(crate) fn proving_key_digests(bul: &BulNet) -> BTreeMap<String, String> {
+    let meta = bul
         .client
-        .get(url)
+        .get(bul.api.join("api/keys/meta").unwrap())
         .send()
-        .expect("failed to send request")
-        .bytes()
-        .expect("failed to get response bytes");
-
-    ProvingKey::<E>::deserialize_with_mode(&*bytes, Compress::No, Validate::No)
-        .expect("failed to deserialize proving key")
+        .and_then(|resp| resp.error_for_status())
+        .and_then(|resp| resp.json::<Vec<Value>>());
+    match meta {
+        Ok(keys) => keys
+            .iter()
+            .filter_map(|key| {
+                let name = key["name"].as_str()?;
+                let digest = key["proving_key_digest"].as_str()?;
+                Some((name.to_string(), digest.to_string()))
+            })
+            .collect(),
+        Err(e) => {
+            eprintln!("[USER] Warning: failed to fetch key digests, not caching keys: {}", e);
+            BTreeMap::new()
+        }
+    }
 }
 
-pub fn get_standard_pseudo_proving_key() -> ProvingKey<E> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let url = bul
-        .api
-        .join("api/interaction/standard/pseudo/proving_key")
-        .unwrap();
-
-    let bytes = bul
-        .client
-        .get(url)
-        .send()
-        .expect("failed to send request")
-        .bytes()
-        .expect("failed to get response bytes");
+/// The file in `keys/` the proving key `name` is cached in, named after its digest if the server
+/// reports one.
+pub(crate) fn proving_key_file(name: &str, digest: Option<&str>) -> String {
+    match digest {
+        Some(digest) => format!("{}-{}.pk", name, &digest[..digest.len().min(16)]),
+        None => format!("{}.pk", name),
+    }
+}
 
-    ProvingKey::<E>::deserialize_with_mode(&*bytes, Compress::No, Validate::No)
-        .expect("failed to deserialize proving key")
+/// Whether `file` caches some version of the proving key `name`.
+pub(crate) fn is_proving_key_file(name: &str, file: &str) -> bool {
+    file == format!("{}.pk", name) || (file.starts_with(&format!("{}-", name)) && file.ends_with(".pk"))
 }
 
-pub fn get_standard_pseudor_proving_key() -> ProvingKey<E> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let url = bul
-        .api
-        .join("api/interaction/standard/pseudor/proving_key")
-        .unwrap();
+/// Fetches a proving key, from memory or `keys/<name>-<digest>.pk` in the data directory if the
+/// server still has the same key, or else by downloading it there, and loads it from the mapped
+/// file.
+///
+/// The response is streamed to disk rather than buffered, so loading a key of hundreds of
+/// megabytes never holds it in memory twice. Copies of older keys are removed. If the server
+/// does not report the digest of the key, it is downloaded again on every call.
+fn fetch_proving_key(endpoint: &str, name: &str) -> Arc<ProvingKey<E>> {
+    progress(Stage::LoadKey);
+    let bul = BulNet::new(server());
+    let digest = proving_key_digests(&bul).remove(name);
+    if let Some(digest) = &digest {
+        if let Some((loaded, pk)) = PROVING_KEYS.lock().unwrap().get(name) {
+            if loaded == digest {
+                return pk.clone();
+            }
+        }
+    }
 
-    let bytes = bul
-        .client
-        .get(url)
-        .send()
-        .expect("failed to send request")
-        .bytes()
-        .expect("failed to get response bytes");
+    let dir = data_path("keys");
+    fs::create_dir_all(&dir).expect("failed to create key directory");
+    let file_name = proving_key_file(name, digest.as_deref());
+    let path = dir.join(&file_name);
+
+    if digest.is_none() || !path.exists() {
+        let url = bul.api.join(endpoint).unwrap();
+        let mut resp = bul
+            .client
+            .get(url)
+            .send()
+            .expect("failed to send request")
+            .error_for_status()
+            .expect("failed to fetch proving key");
+        // Download next to the key, so an interrupted download is never taken for the key
+        let part = path.with_extension("part");
+        let mut file = BufWriter::new(File::create(&part).expect("failed to create key file"));
+        resp.copy_to(&mut file).expect("failed to download proving key");
+        file.flush().expect("failed to write key file");
+        drop(file);
+        fs::rename(&part, &path).expect("failed to write key file");
+
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let other = entry.file_name().to_string_lossy().into_owned();
+            if is_proving_key_file(name, &other) && other != file_name {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
 
-    ProvingKey::<E>::deserialize_with_mode(&*bytes, Compress::No, Validate::No)
-        .expect("failed to deserialize proving key")
+    let pk = Arc::new(
+        load_key_mmap::<ProvingKey<E>>(&path, Validate::No)
+            .expect("failed to deserialize proving key"),
+    );
+    if let Some(digest) = digest {
+        PROVING_KEYS
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), (digest, pk.clone()));
+    }
+    pk
 }
 
-pub fn get_scanning_proving_key() -> ProvingKey<E> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let url = bul.api.join("api/interaction/scan/proving_key").unwrap();
-
-    let bytes = bul
-        .client
-        .get(url)
-        .send()
-        .expect("failed to send request")
-        .bytes()
-        .expect("failed to get response bytes");
+/// Fetch every proving key and load the user, so later proofs in this process start at once.
+pub fn preload() {
+    get_standard_proving_key();
+    get_standard_pseudo_proving_key();
+    get_standard_pseudor_proving_key();
+    get_scanning_proving_key();
+    get_arbitrary_pred_pk();
+    get_arbitrary_pred_pk2();
+    get_arbitrary_pred_pk3();
+    get_reputation_pk();
+    if let Err(e) = load_struct() {
+        eprintln!("[USER] Not loading the user: {}", e);
+    }
+}
 
-    ProvingKey::<E>::deserialize_with_mode(&*bytes, Compress::No, Validate::No)
-        .expect("failed to deserialize proving key")
+pub fn get_standard_proving_key() -> Arc<ProvingKey<E>> {
+    fetch_proving_key("api/interaction/standard/proving_key", "standard")
 }
 
-pub fn get_arbitrary_pred_pk() -> ProvingKey<E> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let url = bul.api.join("api/user/arbitrary_pred_proving_key").unwrap();
+pub fn get_standard_pseudo_proving_key() -> Arc<ProvingKey<E>> {
+    fetch_proving_key("api/interaction/standard/pseudo/proving_key", "standard_pseudo")
+}
 
-    let bytes = bul
-        .client
-        .get(url)
-        .send()
-        .expect("failed to send request")
-        .bytes()
-        .expect("failed to get response bytes");
+pub fn get_standard_pseudor_proving_key() -> Arc<ProvingKey<E>> {
+    fetch_proving_key("api/interaction/standard/pseudor/proving_key", "standard_pseudor")
+}
 
-    ProvingKey::<E>::deserialize_with_mode(&*bytes, Compress::No, Validate::No)
-        .expect("failed to deserialize proving key")
+pub fn get_scanning_proving_key() -> Arc<ProvingKey<E>> {
+    fetch_proving_key("api/interaction/scan/proving_key", "scan")
 }
 
-pub fn get_arbitrary_pred_pk2() -> ProvingKey<E> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let url = bul
-        .api
-        .join("api/user/arbitrary_pred_proving_key2")
-        .unwrap();
+pub fn get_arbitrary_pred_pk() -> Arc<ProvingKey<E>> {
+    fetch_proving_key("api/user/arbitrary_pred_proving_key", "pseudonym_pred")
+}
 
-    let bytes = bul
-        .client
-        .get(url)
-        .send()
-        .expect("failed to send request")
-        .bytes()
-        .expect("failed to get response bytes");
+pub fn get_arbitrary_pred_pk2() -> Arc<ProvingKey<E>> {
+    fetch_proving_key("api/user/arbitrary_pred_proving_key2", "authorship_pred")
+}
 
-    ProvingKey::<E>::deserialize_with_mode(&*bytes, Compress::No, Validate::No)
-        .expect("failed to deserialize proving key")
+pub fn get_arbitrary_pred_pk3() -> Arc<ProvingKey<E>> {
+    fetch_proving_key("api/user/arbitrary_pred_proving_key3", "badge_pred")
 }
 
-pub fn get_arbitrary_pred_pk3() -> ProvingKey<E> {
-    let bul = BulNet::new(Url::parse("http://127.0.0.1:3000").unwrap());
-    let url = bul
-        .api
-        .join("api/user/arbitrary_pred_proving_key3")
-        .unwrap();
+pub fn get_reputation_pk() -> Arc<ProvingKey<E>> {
+    fetch_proving_key("api/user/reputation_proving_key", "reputation_pred")
+}
 
-    let bytes = bul
-        .client
-        .get(url)
-        .send()
-        .expect("failed to send request")
-        .bytes()
-        .expect("failed to get response bytes");
+/// Where the thread contexts synced by `get-contexts` are kept.
+pub fn contexts_path() -> PathBuf {
+    data_path("contexts.json")
+}
 
-    ProvingKey::<E>::deserialize_with_mode(&*bytes, Compress::No, Validate::No)
-        .expect("failed to deserialize proving key")
+/// The thread contexts fetched from the server.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ContextCache {
+    /// The id of the last thread fetched, so the next sync only fetches newer threads.
+    pub cursor: Option<u64>,
+    /// The context of each thread, by thread.
+    pub contexts: BTreeMap<String, String>,
 }
 
-#[derive(Deserialize)]
-struct ContextJson {
-    thread: String,
-    context: String,
+impl ContextCache {
+    /// Load the cache, or an empty cache if there is none yet.
+    pub fn load() -> Self {
+        fs::read(contexts_path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::create_dir_all(&settings().data_dir)?;
+        fs::write(contexts_path(), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
 }
 
 pub fn lookup_context(thread: &str) -> Option<F> {
-    let file = File::open("client/contexts.jsonl").ok()?;
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            if let Ok(entry) = serde_json::from_str::<ContextJson>(&line) {
-                if entry.thread == thread {
-                    let bigint = BigInteger256::from_str(&entry.context).ok()?;
-                    return F::from_bigint(bigint);
-                }
+    let cache = ContextCache::load();
+    let bigint = BigInteger256::from_str(cache.contexts.get(thread)?).ok()?;
+    F::from_bigint(bigint)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::testing;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef};
+    use ark_snark::SNARK;
+    use axum::http::StatusCode;
+    use zk_callbacks::generic::bulletin::PublicCallbackBul;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Empty;
+
+    impl ConstraintSynthesizer<F> for Empty {
+        fn generate_constraints(self, _: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+    }
+
+    fn key_digest(digest: Option<&'static str>) {
+        testing::route("api/keys/meta", move |_| match digest {
+            Some(digest) => {
+                let meta = json!([{ "name": "test", "proving_key_digest": digest }]);
+                (StatusCode::OK, meta.to_string().into_bytes())
             }
+            None => (StatusCode::NOT_FOUND, vec![]),
+        });
+    }
+
+    /// A user holding callbacks with the tickets `tickets`.
+    pub(crate) fn user_with_callbacks(tickets: &[u64]) -> User<F, MsgUser> {
+        let mut user = User::create(MsgUser::default(), &mut rand::thread_rng());
+        for &tik in tickets {
+            let mut cb = CallbackCom::<F, Args, Cr>::default();
+            cb.cb_entry.tik = PlainTikCrypto::new(F::from(tik));
+            let mut bytes = vec![];
+            cb.serialize_compressed(&mut bytes).unwrap();
+            user.callbacks.push(bytes);
         }
+        user
+    }
+
+    /// Put the callbacks with the tickets `called` on the callback bulletin, called with the
+    /// argument `arg`.
+    pub(crate) fn call(called: &[u64], arg: u64) {
+        type Witness = <BulNet as PublicCallbackBul<F, Args, Cr>>::MembershipWitness;
+        let entries: Vec<_> = called
+            .iter()
+            .map(|&tik| {
+                let tik = PlainTikCrypto::new(F::from(tik));
+                (tik, F::from(arg), F::from(0), Witness::default())
+            })
+            .collect();
+        let mut bytes = vec![];
+        entries.serialize_with_mode(&mut bytes, Compress::No).unwrap();
+        testing::route("api/callbacks/bulletin", move |_| (StatusCode::OK, bytes.clone()));
     }
 
-    None
+    // Callbacks on the bulletin are found among the user's, and take a scan of every callback
+    #[test]
+    fn called_before_posting() {
+        let _data = testing::data_dir();
+        let mut user = user_with_callbacks(&[1, 2, 3]);
+        save_struct(&user).unwrap();
+
+        call(&[2, 9], 5);
+        assert_eq!(called_callbacks(&user).unwrap(), [(1, F::from(5))]);
+        let status = scan_status().unwrap();
+        assert_eq!((status.called, status.scanning, status.scans), (1, false, 3));
+        assert!(status.pending());
+
+        user.scan_index = Some(1);
+        let status = ScanStatus::of(&user, 0);
+        assert_eq!((status.scanning, status.scans), (true, 2));
+        assert!(status.pending());
+        assert!(!ScanStatus::of(&user_with_callbacks(&[1]), 0).pending());
+
+        testing::route("api/callbacks/bulletin", |_| (StatusCode::BAD_GATEWAY, vec![]));
+        let err = scan_status().err().unwrap();
+        assert!(err.to_string().starts_with("failed to fetch the callback bulletin"));
+    }
+
+    // Proving keys are kept until the server reports a new digest for them
+    #[test]
+    fn cached_proving_key() {
+        let _data = testing::data_dir();
+        let (pk, _) = Groth16::<E>::circuit_specific_setup(Empty, &mut rand::thread_rng()).unwrap();
+        let mut bytes = vec![];
+        pk.serialize_with_mode(&mut bytes, Compress::No).unwrap();
+        static DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+        testing::route("api/test/proving_key", move |_| {
+            DOWNLOADS.fetch_add(1, Ordering::SeqCst);
+            (StatusCode::OK, bytes.clone())
+        });
+        let keys = |name: &str| data_path("keys").join(name).exists();
+
+        key_digest(Some("aaaa"));
+        let first = fetch_proving_key("api/test/proving_key", "test");
+        assert_eq!(*first, pk);
+        let again = fetch_proving_key("api/test/proving_key", "test");
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(DOWNLOADS.load(Ordering::SeqCst), 1);
+        assert!(keys("test-aaaa.pk"));
+
+        key_digest(Some("bbbb"));
+        fetch_proving_key("api/test/proving_key", "test");
+        assert_eq!(DOWNLOADS.load(Ordering::SeqCst), 2);
+        assert!(keys("test-bbbb.pk"));
+        assert!(!keys("test-aaaa.pk"));
+
+        // Without digests, keys are downloaded every time
+        key_digest(None);
+        fetch_proving_key("api/test/proving_key", "test");
+        fetch_proving_key("api/test/proving_key", "test");
+        assert_eq!(DOWNLOADS.load(Ordering::SeqCst), 4);
+        assert!(keys("test.pk"));
+        assert!(!keys("test-bbbb.pk"));
+    }
 }
@@ -0,0 +1,470 @@
+//! The encrypted keystore.
+//!
+//! The user file holds the user's secret key and the randomness of their commitment and
+//! nullifier, and the pseudonym log links their pseudonyms, so once `keys init` is run both are
+//! kept encrypted with ChaCha20-Poly1305, under a key derived from a passphrase with Argon2id.
+//!
+//! Reading or writing them needs the keystore to be unlocked: `keys unlock` asks for the
+//! passphrase and keeps the derived key in a session file readable only by the user, in a
+//! directory of the user's own under `$XDG_RUNTIME_DIR` (or the temporary directory), until it
+//! expires or `keys lock` removes it. The directory is refused if anyone else could write to it.
+//! Scripts may set `WISPY_PASSPHRASE` instead. The keystore of each data directory, and so of
+//! each profile, is separate.
+//!
+//! Each file is encrypted with its name as associated data, so encrypted files cannot be swapped
+//! for one another, and once the keystore is initialized, a file which is not encrypted is
+//! refused rather than read as it is.
+
+use crate::config::{data_path, settings};
+use anyhow::{bail, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The start of every encrypted file.
+const MAGIC: &[u8; 9] = b"WISPYENC1";
+const NONCE_LEN: usize = 12;
+/// Encrypted with the key in the keystore file, to check a passphrase.
+const CHECK: &[u8] = b"wispy keystore";
+
+/// The keystore file, in the data directory. The keystore is initialized if it exists.
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    /// The Argon2id salt, in hex.
+    salt: String,
+    /// [`CHECK`] encrypted with the key, in hex.
+    check: String,
+}
+
+/// The key of an unlocked keystore, kept until it expires.
+#[derive(Serialize, Deserialize)]
+struct Session {
+    key: String,
+    /// When the session expires, in seconds since the Unix epoch.
+    expires: u64,
+}
+
+/// Whether the keystore is initialized, and unlocked until when.
+pub enum Status {
+    Plaintext,
+    Locked,
+    Unlocked { expires: SystemTime },
+}
+
+fn keystore_path() -> PathBuf {
+    data_path("keystore.json")
+}
+
+/// The directory session files are kept in under `base`, created if it is missing. It belongs
+/// to the user and only they may use it, so no one else can plant or read a session file.
+fn session_dir(base: &Path) -> io::Result<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+        let uid = unsafe { libc::geteuid() };
+        let dir = base.join(format!("wispy-{}", uid));
+        match fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+            _ => (),
+        }
+        let meta = fs::symlink_metadata(&dir)?;
+        if !meta.is_dir() || meta.uid() != uid || meta.permissions().mode() & 0o777 != 0o700 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} is not a directory only the user may use, refusing to keep the \
+                     keystore session in it",
+                    dir.display()
+                ),
+            ));
+        }
+        Ok(dir)
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(base.to_path_buf())
+    }
+}
+
+/// The session file of the keystore of the data directory.
+fn session_path() -> io::Result<PathBuf> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|d| !d.is_empty())
+        .map_or_else(std::env::temp_dir, PathBuf::from);
+    let data_dir =
+        fs::canonicalize(&settings().data_dir).unwrap_or_else(|_| settings().data_dir.clone());
+    let id = Sha256::digest(data_dir.to_string_lossy().as_bytes());
+    Ok(session_dir(&base)?.join(format!("{}.key", hex::encode(&id[..8]))))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The session of the keystore, if it is unlocked.
+fn session() -> Option<Session> {
+    fs::read(session_path().ok()?)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Session>(&bytes).ok())
+        .filter(|s| s.expires > now())
+}
+
+fn load_keystore() -> Result<Option<KeystoreFile>> {
+    match fs::read(keystore_path()) {
+        Ok(bytes) => Ok(Some(
+            serde_json::from_slice(&bytes).context("invalid keystore file")?,
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("failed to derive the key: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt(key: &[u8; 32], aad: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: data, aad })
+        .expect("encryption does not fail");
+    [&MAGIC[..], &nonce, &ciphertext].concat()
+}
+
+/// Decrypt `data`, or `None` if it was not encrypted with `key` and `aad`.
+fn decrypt(key: &[u8; 32], aad: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let data = data.strip_prefix(&MAGIC[..])?;
+    if data.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .ok()
+}
+
+/// The associated data a file kept in the keystore is encrypted with: its name.
+fn file_aad(path: &Path) -> &[u8] {
+    path.file_name().map_or(&[], |name| name.as_encoded_bytes())
+}
+
+/// Encrypt `data` under `passphrase` alone, with a salt of its own, for data which leaves the
+/// keystore like backups.
+pub fn seal(passphrase: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+    Ok([&salt[..], &encrypt(&key, &[], data)].concat())
+}
+
+/// Decrypt data encrypted with [`seal`].
+pub fn open(passphrase: &str, data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < 16 {
+        bail!("the data is too short to be encrypted");
+    }
+    let (salt, data) = data.split_at(16);
+    let key = derive_key(passphrase, salt)?;
+    decrypt(&key, &[], data).context("wrong passphrase, or the data is corrupt")
+}
+
+/// Check `passphrase` against the keystore and derive its key.
+fn unlock_with(keystore: &KeystoreFile, passphrase: &str) -> Result<[u8; 32]> {
+    let key = derive_key(passphrase, &hex::decode(&keystore.salt)?)?;
+    match decrypt(&key, &[], &hex::decode(&keystore.check)?) {
+        Some(check) if check == CHECK => Ok(key),
+        _ => bail!("wrong passphrase"),
+    }
+}
+
+/// The key of the keystore, if it is initialized: from the session, or from `WISPY_PASSPHRASE`.
+fn key() -> io::Result<Option<[u8; 32]>> {
+    let Some(keystore) = load_keystore().map_err(io::Error::other)? else {
+        return Ok(None);
+    };
+    let key = session().and_then(|s| hex::decode(s.key).ok()?.try_into().ok());
+    if let Some(key) = key {
+        return Ok(Some(key));
+    }
+    match std::env::var("WISPY_PASSPHRASE") {
+        Ok(passphrase) => unlock_with(&keystore, &passphrase)
+            .map(Some)
+            .map_err(io::Error::other),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the keystore is locked: run `keys unlock` or set WISPY_PASSPHRASE",
+        )),
+    }
+}
+
+/// Read a file kept in the keystore, decrypting it if the keystore is initialized. Once it is,
+/// a file which is not encrypted, or was encrypted under another name, is refused.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    let invalid = |what| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} {}", path.display(), what),
+        )
+    };
+    match key()? {
+        Some(key) if data.starts_with(&MAGIC[..]) => {
+            decrypt(&key, file_aad(path), &data).ok_or_else(|| invalid("could not be decrypted"))
+        }
+        Some(_) => Err(invalid("is not encrypted, but the keystore is initialized")),
+        None if data.starts_with(&MAGIC[..]) => {
+            Err(invalid("is encrypted but there is no keystore"))
+        }
+        None => Ok(data),
+    }
+}
+
+/// Write a file kept in the keystore, encrypting it if the keystore is initialized.
+pub fn write(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    match key()? {
+        Some(key) => fs::write(path, encrypt(&key, file_aad(path), data)),
+        None => fs::write(path, data),
+    }
+}
+
+/// Copy a file kept in the keystore, encrypting it under its new name.
+pub fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    write(to, &read(from)?)
+}
+
+/// Move a file kept in the keystore, encrypting it under its new name. The file at `to` is
+/// replaced at once, as with [`fs::rename`].
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    let Some(key) = key()? else {
+        return fs::rename(from, to);
+    };
+    let data = read(from)?;
+    let part = to.with_extension("part");
+    fs::write(&part, encrypt(&key, file_aad(to), &data))?;
+    fs::rename(&part, to)?;
+    fs::remove_file(from)
+}
+
+/// The files kept in the keystore.
+fn protected_files() -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![
+        settings().user_file.clone(),
+        crate::helpers::pending_user_file(),
+        crate::pseudonyms::pseudonyms_path(),
+        data_path(crate::pseudonyms::PSEUDO_LOG),
+    ];
+    match fs::read_dir(crate::outbox::outbox_dir()) {
+        Ok(entries) => {
+            for entry in entries {
+                files.push(entry?.path());
+            }
+        }
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        Err(_) => (),
+    }
+    Ok(files)
+}
+
+/// Initialize the keystore with `passphrase`, encrypting the files written so far, and unlock
+/// it for `ttl`.
+pub fn init(passphrase: &str, ttl: Duration) -> Result<()> {
+    if load_keystore()?.is_some() {
+        bail!("the keystore is already initialized");
+    }
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+
+    // Read the files before the keystore exists, so they are read as they are
+    let files = protected_files()?
+        .into_iter()
+        .filter(|p| p.exists())
+        .map(|p| Ok((fs::read(&p)?, p)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let keystore = KeystoreFile {
+        salt: hex::encode(salt),
+        check: hex::encode(encrypt(&key, &[], CHECK)),
+    };
+    fs::create_dir_all(&settings().data_dir)?;
+    fs::write(keystore_path(), serde_json::to_vec_pretty(&keystore)?)?;
+    for (data, path) in files {
+        if !data.starts_with(&MAGIC[..]) {
+            fs::write(&path, encrypt(&key, file_aad(&path), &data))
+                .with_context(|| format!("failed to encrypt {}", path.display()))?;
+        }
+    }
+    start_session(&key, ttl)
+}
+
+/// Unlock the keystore for `ttl`.
+pub fn unlock(passphrase: &str, ttl: Duration) -> Result<()> {
+    let keystore = load_keystore()?.context("the keystore is not initialized: run `keys init`")?;
+    let key = unlock_with(&keystore, passphrase)?;
+    start_session(&key, ttl)
+}
+
+fn start_session(key: &[u8; 32], ttl: Duration) -> Result<()> {
+    let session = Session {
+        key: hex::encode(key),
+        expires: now() + ttl.as_secs(),
+    };
+    let path = session_path()?;
+    write_session(&path, &session)
+        .with_context(|| format!("failed to create session {}", path.display()))
+}
+
+/// Write `session` to a new file at `path` readable only by the user, replacing any file there
+/// rather than writing through it.
+fn write_session(path: &Path, session: &Session) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => (),
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW);
+    }
+    serde_json::to_writer(options.open(path)?, session)?;
+    Ok(())
+}
+
+/// Lock the keystore, forgetting its key.
+pub fn lock() -> Result<()> {
+    match fs::remove_file(session_path()?) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn status() -> Result<Status> {
+    if load_keystore()?.is_none() {
+        return Ok(Status::Plaintext);
+    }
+    Ok(match session() {
+        Some(s) => Status::Unlocked {
+            expires: UNIX_EPOCH + Duration::from_secs(s.expires),
+        },
+        None => Status::Locked,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wispy-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::symlink_metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    // Sessions are kept in a directory only the user may use
+    #[test]
+    fn private_session_dir() {
+        let base = temp_dir("session-dir");
+        let dir = session_dir(&base).unwrap();
+        assert_eq!(mode(&dir), 0o700);
+        assert_eq!(session_dir(&base).unwrap(), dir);
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        let err = session_dir(&base).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        // A directory planted as a link to somewhere else is refused too
+        fs::remove_dir(&dir).unwrap();
+        let elsewhere = temp_dir("session-elsewhere");
+        fs::set_permissions(&elsewhere, fs::Permissions::from_mode(0o700)).unwrap();
+        symlink(&elsewhere, &dir).unwrap();
+        let err = session_dir(&base).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    // A session file replaces whatever is at its path instead of writing through it
+    #[test]
+    fn session_not_followed() {
+        let dir = temp_dir("session-file");
+        let session = || Session {
+            key: "00".to_string(),
+            expires: 1,
+        };
+        let target = dir.join("target");
+        fs::write(&target, "untouched").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o644)).unwrap();
+        let path = dir.join("session.key");
+        symlink(&target, &path).unwrap();
+
+        write_session(&path, &session()).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "untouched");
+        assert!(fs::symlink_metadata(&path).unwrap().is_file());
+        assert_eq!(mode(&path), 0o600);
+
+        fs::remove_file(&path).unwrap();
+        fs::copy(&target, &path).unwrap();
+        write_session(&path, &session()).unwrap();
+        assert_eq!(mode(&path), 0o600);
+        let written: Session = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written.key, "00");
+    }
+
+    // Once the keystore is initialized, a file which is not encrypted, or was encrypted under
+    // another name, is refused, and a file copied or moved is encrypted under its new name
+    #[test]
+    fn bound_to_name() {
+        let _data = crate::testing::data_dir();
+        let user = &settings().user_file;
+        fs::write(user, b"user").unwrap();
+        init("passphrase", Duration::from_secs(60)).unwrap();
+        assert!(fs::read(user).unwrap().starts_with(&MAGIC[..]));
+        assert_eq!(read(user).unwrap(), b"user");
+
+        let (queued, moved) = (data_path("000001.user"), data_path("moved.user"));
+        copy(user, &queued).unwrap();
+        assert_eq!(read(&queued).unwrap(), b"user");
+        rename(&queued, &moved).unwrap();
+        assert!(!queued.exists());
+        assert_eq!(read(&moved).unwrap(), b"user");
+
+        write(&queued, b"other").unwrap();
+        fs::copy(&queued, user).unwrap();
+        assert_eq!(read(user).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::write(user, b"planted").unwrap();
+        assert_eq!(read(user).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        lock().unwrap();
+    }
+}
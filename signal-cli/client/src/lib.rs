@@ -1,3 +1,83 @@
+pub mod backup;
 pub mod bul;
+pub mod config;
+pub mod daemon;
 pub mod helpers;
+pub mod keystore;
+pub mod outbox;
+pub mod output;
 pub mod parse;
+pub mod pseudonyms;
+pub mod status;
+pub mod sync;
+
+/// What the tests share: one data directory, and a fake server answering the routes they set.
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::config::{self, Config};
+    use axum::{body::Bytes, http::StatusCode, http::Uri, Router};
+    use std::{
+        collections::BTreeMap,
+        path::PathBuf,
+        sync::{Mutex, MutexGuard, Once},
+    };
+
+    type Route = Box<dyn Fn(&[u8]) -> (StatusCode, Vec<u8>) + Send>;
+
+    static ROUTES: Mutex<BTreeMap<String, Route>> = Mutex::new(BTreeMap::new());
+    static DATA: Mutex<()> = Mutex::new(());
+
+    /// Run the client against the fake server, keeping its data in a directory of the tests' own.
+    pub fn init() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.set_nonblocking(true).unwrap();
+            let server = format!("http://{}", listener.local_addr().unwrap());
+            std::thread::spawn(move || {
+                let runtime = tokio::runtime::Runtime::new().unwrap();
+                runtime.block_on(async move {
+                    let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                    let router = Router::new().fallback(|uri: Uri, body: Bytes| async move {
+                        let path = uri.path().trim_start_matches('/');
+                        match ROUTES.lock().unwrap().get(path) {
+                            Some(route) => route(&body),
+                            None => (StatusCode::NOT_FOUND, vec![]),
+                        }
+                    });
+                    axum::serve(listener, router).await.unwrap();
+                });
+            });
+
+            let dir = std::env::temp_dir().join(format!("wispy-client-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            let config = Config {
+                server: Some(server),
+                sync_dir: Some(dir.join("sync")),
+                data_dir: Some(dir),
+                ..Config::default()
+            };
+            config::init(config.settings(None, None).unwrap());
+        });
+    }
+
+    /// Answer requests to `path`, like `api/jsonrpc`, with `route`.
+    pub fn route(
+        path: &str,
+        route: impl Fn(&[u8]) -> (StatusCode, Vec<u8>) + Send + 'static,
+    ) {
+        init();
+        ROUTES.lock().unwrap().insert(path.to_string(), Box::new(route));
+    }
+
+    /// Hold the data directory, for a test which reads or writes the user or other state kept
+    /// there. Clears the directory.
+    pub fn data_dir() -> MutexGuard<'static, ()> {
+        init();
+        let guard = DATA.lock().unwrap_or_else(|e| e.into_inner());
+        let dir: PathBuf = config::settings().data_dir.clone();
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        guard
+    }
+}
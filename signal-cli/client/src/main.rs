@@ -1,39 +1,85 @@
 pub mod parse;
 
-use crate::parse::{Cli, Command};
+use crate::parse::{Cli, Command, DmCommand, KeysCommand, OutboxCommand, OutputFormat, PseudoCommand};
 
 use std::{
-    fs::File,
-    io::Write,
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
     str::FromStr,
-    time::SystemTime,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime},
     usize,
 };
 use ark_ff::{BigInteger256, PrimeField};
 use ark_std::result::Result::Ok;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
+use client::backup;
+use client::config::{endpoint, settings, Config};
+use client::daemon::{field, prove, Request};
+use client::keystore::{self, Status};
+use client::outbox::{self, Body, Outcome};
+use client::output::{self, Category, CliError};
+use client::pseudonyms::{Pseudonym, Pseudonyms};
+use client::say;
+use client::status::{self, KeyCache};
+use client::sync;
 use client::helpers::{
-    append_timing_line_features, ban, compute_pseudo_for_poll, gen_cb_for_msg, gen_pseudo, get_claimed_context_by_index,
-    join2, list_all_pseudos_from_log, lookup_context, make_authorship_proof, make_badge_proof, prf2,
-    pseudo_proof_vote, pseudo_proof_with_msg, rate_pseudo_proof_with_msg, rep, save_start_time, scan, string_to_f,
+    append_timing_line_features, ban, ContextCache, contexts_path, compute_pseudo_for_poll, gen_pseudo,
+    join2, lookup_context, on_progress, prf2, pseudo_proof_votes, rep, save_start_time,
+    scan_status, string_to_f, Stage,
 };
-use common::F;
-use reqwest::Client;
+use common::{zk::{attachment_digest, dm_context, CallbackEffect, REP_BOUNDS}, F};
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
-use tokio::task::spawn_blocking;
+use serde_json::{json, Value};
+use tokio::task::{spawn_blocking, JoinHandle};
 
 
 #[derive(Serialize)]
 pub struct JsonRpcInput {
     message: String,
     group_id: String,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
+    attachments: Vec<Attachment>,
+}
+
+#[derive(Serialize)]
+pub struct Attachment {
+    filename: Option<String>,
+    content_type: String,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
+    data: Vec<u8>,
+}
+
+/// Read a file to attach to a post, guessing its type from its extension.
+fn read_attachment(path: &Path) -> std::io::Result<Attachment> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let content_type = match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("mp4") => "video/mp4",
+        Some("pdf") => "application/pdf",
+        Some("txt") => "text/plain",
+        _ => "application/octet-stream",
+    };
+    Ok(Attachment {
+        filename: path.file_name().map(|n| n.to_string_lossy().into_owned()),
+        content_type: content_type.to_string(),
+        data: std::fs::read(path)?,
+    })
 }
 
 #[derive(Serialize)]
 pub struct JsonRpcInputPseudo {
     message: String,
     group_id: String,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
 }
 
@@ -49,6 +95,7 @@ pub struct JsonRpcReply {
     group_id: String,
     message: String,
     timestamp: u64,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
 }
 
@@ -57,6 +104,7 @@ pub struct JsonRpcReplyPseudo {
     group_id: String,
     message: String,
     timestamp: u64,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
 }
 
@@ -66,6 +114,7 @@ pub struct JsonRpcVote {
     emoji: String,
     timestamp: u64,
     claimed: String,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
 }
 
@@ -90,17 +139,52 @@ pub struct JsonRpcCountVotes {
 
 #[derive(Serialize)]
 pub struct JsonAuthorship {
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
     group_id: String,
 }
 
 #[derive(Serialize)]
 pub struct JsonBadge {
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
     group_id: String,
 
 }
 
+#[derive(Serialize)]
+pub struct JsonRepProof {
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
+    proof: Vec<u8>,
+    group_id: String,
+}
+
+#[derive(Serialize)]
+pub struct JsonDmRegister {
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
+    proof: Vec<u8>,
+    recipient: Option<String>,
+    #[serde(flatten)]
+    nonce: DmNonce,
+}
+
+#[derive(Serialize)]
+pub struct JsonDm {
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
+    proof: Vec<u8>,
+    to: String,
+    message: String,
+    #[serde(flatten)]
+    nonce: DmNonce,
+}
+
+/// A nonce from `/api/dm/nonce`, which the proof of a direct message request is bound to.
+#[derive(Serialize, Deserialize)]
+pub struct DmNonce {
+    nonce: String,
+    expires: u64,
+}
+
 #[derive(Deserialize)]
 struct ContextResponse {
     context: String,
@@ -111,95 +195,774 @@ pub struct ContextRequest {
     thread: String,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ContextJson {
+#[derive(Deserialize)]
+pub struct PostsPage {
+    posts: Vec<ListedPost>,
+    next: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ListedPost {
+    timestamp: u64,
+    message: String,
+    #[serde(default)]
+    attachments: usize,
+    pseudonym: Option<String>,
+    thread: Option<String>,
+    reply_to: Option<u64>,
+    #[serde(default)]
+    reputation: i32,
+    poll: Option<ListedPoll>,
+}
+
+#[derive(Deserialize)]
+pub struct ListedPoll {
+    ban: Option<u64>,
+    open: bool,
+    yes: usize,
+    no: usize,
+    deadline: Option<u64>,
+}
+
+/// Print a post as listed by `view-posts`.
+fn print_post(post: &ListedPost) {
+    let sent = chrono::DateTime::from_timestamp_millis(post.timestamp as i64)
+        .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default();
+    let mut header = format!("[{}] {}", post.timestamp, sent);
+    if let Some(pseudonym) = &post.pseudonym {
+        header.push_str(&format!(" from {}", pseudonym));
+    }
+    if let Some(thread) = &post.thread {
+        header.push_str(&format!(" in {}", thread));
+    }
+    if let Some(parent) = post.reply_to {
+        header.push_str(&format!(", replying to {}", parent));
+    }
+    header.push_str(&format!(" (reputation {})", post.reputation));
+    println!("{}", header);
+    for line in post.message.lines() {
+        println!("    {}", line);
+    }
+    if post.attachments > 0 {
+        println!("    [{} attachments]", post.attachments);
+    }
+    if let Some(poll) = &post.poll {
+        let (yes, no) = if poll.ban.is_some() { ("ban", "keep") } else { ("yes", "no") };
+        let state = if !poll.open {
+            "closed".to_string()
+        } else if let Some(closes) =
+            poll.deadline.and_then(|d| chrono::DateTime::from_timestamp(d as i64, 0))
+        {
+            format!("open until {}", closes.format("%Y-%m-%d %H:%M UTC"))
+        } else {
+            "open".to_string()
+        };
+        println!("    Poll {}: {} {}, {} {}", state, poll.yes, yes, poll.no, no);
+    }
+    println!();
+}
+
+#[derive(Deserialize)]
+pub struct ContextsPage {
+    contexts: Vec<ListedContext>,
+    next: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ListedContext {
+    id: u64,
     thread: String,
     context: String,
 }
 
+/// Ask for the passphrase of the keystore, twice if `confirm`.
+fn prompt_passphrase(confirm: bool) -> Result<String, CliError> {
+    let passphrase = rpassword::prompt_password("Passphrase: ")
+        .map_err(|e| CliError::new(Category::Failed, format!("Failed to read the passphrase: {}", e)))?;
+    if confirm {
+        let again = rpassword::prompt_password("Passphrase again: ").unwrap_or_default();
+        if again != passphrase {
+            return Err(CliError::new(Category::Usage, "The passphrases do not match"));
+        }
+    }
+    Ok(passphrase)
+}
+
+fn keys(command: KeysCommand) -> CmdResult {
+    match command {
+        KeysCommand::Init { minutes } => {
+            let passphrase = prompt_passphrase(true)?;
+            if passphrase.is_empty() {
+                return Err(CliError::new(Category::Usage, "the passphrase is empty"));
+            }
+            keystore::init(&passphrase, Duration::from_secs(minutes * 60))?;
+            say!("Keystore initialized and unlocked for {} minutes", minutes);
+            Ok(json!({ "status": "unlocked", "minutes": minutes }))
+        }
+        KeysCommand::Unlock { minutes } => {
+            keystore::unlock(&prompt_passphrase(false)?, Duration::from_secs(minutes * 60))?;
+            say!("Keystore unlocked for {} minutes", minutes);
+            Ok(json!({ "status": "unlocked", "minutes": minutes }))
+        }
+        KeysCommand::Lock => {
+            keystore::lock()?;
+            say!("Keystore locked");
+            Ok(json!({ "status": "locked" }))
+        }
+        KeysCommand::Status => match keystore::status()? {
+            Status::Plaintext => {
+                say!("No keystore: the user file is not encrypted");
+                Ok(json!({ "status": "plaintext" }))
+            }
+            Status::Locked => {
+                say!("Locked");
+                Ok(json!({ "status": "locked" }))
+            }
+            Status::Unlocked { expires } => {
+                let expires: chrono::DateTime<chrono::Local> = expires.into();
+                say!("Unlocked until {}", expires.format("%Y-%m-%d %H:%M:%S"));
+                Ok(json!({ "status": "unlocked", "expires": expires.timestamp() }))
+            }
+        },
+    }
+}
+
+/// What a command printed with `--output json` holds, or why it failed.
+type CmdResult = Result<Value, CliError>;
+
+/// How long to wait for a proof, from `--timeout`.
+static PROVE_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// The step of the proof being made, and when it began.
+static STAGE: Mutex<Option<(Stage, Instant)>> = Mutex::new(None);
+
+/// Show the step of the proof being made on stderr: as a spinner in a terminal, and otherwise as
+/// a line for each step.
+async fn show_progress() {
+    let terminal = std::io::stderr().is_terminal();
+    let mut shown = None;
+    let mut ticks = tokio::time::interval(Duration::from_millis(100));
+    for frame in ['|', '/', '-', '\\'].iter().cycle() {
+        ticks.tick().await;
+        let Some((stage, since)) = *STAGE.lock().unwrap() else {
+            continue;
+        };
+        if terminal {
+            eprint!("\r{} {} ({}s)\x1b[K", frame, stage.describe(), since.elapsed().as_secs());
+        } else if shown != Some(stage) {
+            eprintln!("{}...", stage.describe());
+        }
+        shown = Some(stage);
+    }
+}
+
+/// Wait for the proof made on a blocking task, showing its progress. Gives up after
+/// `--timeout`, leaving the user as it was: a proof finished later is dropped before the next.
+async fn proved<T>(task: JoinHandle<anyhow::Result<T>>) -> Result<T, CliError> {
+    let progress = tokio::spawn(show_progress());
+    let res = match PROVE_TIMEOUT.get() {
+        Some(&limit) => tokio::time::timeout(limit, task).await,
+        None => Ok(task.await),
+    };
+    progress.abort();
+    if STAGE.lock().unwrap().take().is_some() && std::io::stderr().is_terminal() {
+        eprint!("\r\x1b[K");
+    }
+    let Ok(res) = res else {
+        return Err(CliError::new(
+            Category::Timeout,
+            format!(
+                "Gave up on the proof after {} seconds, without submitting anything",
+                PROVE_TIMEOUT.get().map_or(0, Duration::as_secs)
+            ),
+        ));
+    };
+    match res {
+        Ok(Ok(proof)) => Ok(proof),
+        Ok(Err(e)) => Err(CliError::new(Category::Proof, format!("{:#}", e))),
+        Err(join_err) => Err(CliError::new(Category::Proof, format!("Panic inside task: {}", join_err))),
+    }
+}
+
+/// Send a request, returning the body of the response if the server accepted it.
+async fn send(request: RequestBuilder) -> Result<String, CliError> {
+    let res = request.send().await?;
+    let status = res.status();
+    let text = res.text().await?;
+    if !status.is_success() {
+        return Err(CliError::rejected(status, &text));
+    }
+    Ok(text)
+}
+
+/// A response of the server, as JSON if it is.
+fn response_value(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+/// Send a request, and report what the server responded.
+async fn send_and_report(request: RequestBuilder) -> CmdResult {
+    let text = send(request).await?;
+    say!("Server responded: {}", text);
+    Ok(json!({ "response": response_value(&text) }))
+}
+
+/// Prove and submit one scan, returning what the server responded.
+async fn submit_scan(client: &Client) -> CmdResult {
+    let proof_bytes = proved(spawn_blocking(|| prove(Request::Scan))).await?;
+    // Sends raw binary, as expected
+    submitted(outbox::submit(client, "api/interact/scan", Body::binary(&proof_bytes)).await)
+}
+
+/// How many times queued submissions are tried before a new interaction.
+const SUBMIT_ATTEMPTS: u32 = 3;
+
+/// Submit whatever is queued before proving a new interaction, failing if it cannot be.
+/// Refuses to go on if another device has used the user since it was synced, too.
+async fn prepare_interaction(client: &Client) -> Result<(), CliError> {
+    outbox::prepare(client, SUBMIT_ATTEMPTS)
+        .await
+        .map_err(|e| CliError::new(Category::Queued, format!("{:#}", e)))?;
+    match spawn_blocking(sync::is_current).await.unwrap() {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(CliError::new(
+                Category::State,
+                "Another device has used this user since it was last synced: run `sync` first",
+            ));
+        }
+        Err(e) => eprintln!("Warning: could not check the user against the bulletin: {:#}", e),
+    }
+    Ok(())
+}
+
+/// Report what became of a submission.
+fn submitted(outcome: anyhow::Result<Outcome>) -> CmdResult {
+    match outcome.map_err(|e| CliError::new(Category::Failed, format!("Failed to submit: {:#}", e)))? {
+        Outcome::Accepted(text) => {
+            say!("Server responded: {}", text);
+            let response = response_value(&text);
+            Ok(json!({
+                "proof_submitted": true,
+                "timestamp": response.get("timestamp"),
+                "response": response,
+            }))
+        }
+        Outcome::Queued(seq) => Err(CliError::new(
+            Category::Queued,
+            format!(
+                "Server unreachable: queued as submission {}, retried before the next interaction or with `outbox flush`",
+                seq
+            ),
+        )),
+        Outcome::Rejected(e) => {
+            let (status, body) = e.split_once(": ").unwrap_or(("", e.as_str()));
+            Err(CliError::rejected(status, body))
+        }
+    }
+}
+
+/// Check for callbacks called since the last scan before posting. Scans them first with
+/// `auto_scan`, and otherwise warns, or refuses to post if a scan is part way through.
+async fn scan_before_post(client: &Client, auto_scan: bool) -> Result<(), CliError> {
+    prepare_interaction(client).await?;
+    let status = match spawn_blocking(scan_status).await.unwrap() {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("Warning: could not check for callbacks to scan: {:#}", e);
+            return Ok(());
+        }
+    };
+    if !status.pending() {
+        return Ok(());
+    }
+
+    if !auto_scan {
+        eprintln!(
+            "{} of your callbacks have been called since your last scan; run `scan` first, or pass --auto-scan",
+            status.called
+        );
+        if status.scanning {
+            return Err(CliError::new(
+                Category::State,
+                format!("Refusing to post part way through a scan ({} scans left)", status.scans),
+            ));
+        }
+        return Ok(());
+    }
+
+    say!("Scanning {} callbacks before posting...", status.scans);
+    for i in 1..=status.scans {
+        match submit_scan(client).await {
+            Ok(_) => say!("Scan {}/{} verified", i, status.scans),
+            Err(e) => {
+                return Err(CliError {
+                    message: format!("Scan {}/{} failed: {}", i, status.scans, e.message),
+                    ..e
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the status of the user.
+fn print_status(status: &status::Status) {
+    let membership = if status.superseded {
+        "moved on by another device, run `sync`"
+    } else if status.on_bulletin {
+        "on the bulletin"
+    } else {
+        "not on the bulletin"
+    };
+    println!("Commitment:   {} ({})", &status.commit[..16], membership);
+    if status.pending || status.queued > 0 {
+        println!("Waiting:      {} queued submissions{}", status.queued,
+            if status.pending { ", and a proved interaction not yet acknowledged" } else { "" });
+    }
+
+    println!("Callbacks:    {} outstanding, {} called", status.outstanding, status.called.len());
+    for called in &status.called {
+        let effect = match called.effect {
+            CallbackEffect::Ban => "bans you".to_string(),
+            CallbackEffect::Badge(slot) => format!("issues badge {}", slot),
+            CallbackEffect::Reputation(delta) => format!("changes your reputation by {:+}", delta),
+        };
+        println!("  callback {} {}", called.index, effect);
+    }
+    if status.scan.scanning {
+        println!("Scan:         part way through, {} scans left", status.scan.scans);
+    } else if status.scan.pending() {
+        println!("Scan:         {} scans to apply the called callbacks", status.scan.scans);
+    } else {
+        println!("Scan:         nothing to scan");
+    }
+
+    println!("Reputation:   {}", status.reputation);
+    println!("Banned:       {}", if status.banned { "yes" } else { "no" });
+    let badges: Vec<String> = status.badges.iter().map(|slot| slot.to_string()).collect();
+    println!("Badges:       {}", if badges.is_empty() { "none".to_string() } else { badges.join(", ") });
+
+    if status.keys.is_empty() {
+        println!("Proving keys: the server does not report key digests");
+    }
+    for (name, cache) in &status.keys {
+        let cache = match cache {
+            KeyCache::Fresh => "cached",
+            KeyCache::Stale => "stale, downloaded again on the next proof",
+            KeyCache::Missing => "not cached",
+        };
+        println!("Proving key:  {} {}", name, cache);
+    }
+}
+
+/// Prove and submit a standard post, returning what the server responded.
+async fn post(
+    client: &Client,
+    auto_scan: bool,
+    group_id: String,
+    message: String,
+    attachment: &[PathBuf],
+) -> CmdResult {
+    scan_before_post(client, auto_scan).await?;
+    let attachments = attachment
+        .iter()
+        .map(|path| read_attachment(path))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| CliError::new(Category::Usage, format!("Failed to read attachment: {}", e)))?;
+    let digest = attachment_digest(attachments.iter().map(|a| &a.data[..]));
+
+    let proof_bytes =
+        proved(spawn_blocking(move || prove(Request::Post { digest: field(digest) }))).await?;
+    let payload = JsonRpcInput {
+        message,
+        group_id,
+        proof: proof_bytes, 
+        attachments,
+    };
+
+    if let Err(e) = save_start_time("3") {
+        eprintln!("Failed to save start time: {}", e);
+    }
+
+    submitted(outbox::submit(client, "api/jsonrpc", Body::json(&payload)).await)
+}
+
+/// The context of the poll sent at `timestamp`, which votes in it are pseudonymous in.
+async fn poll_context(client: &Client, timestamp: u64) -> Result<F, CliError> {
+    let payload = serde_json::json!({ "timestamp": timestamp });
+
+    let text = send(client.post(endpoint("api/context")).json(&payload)).await?;
+    let context_resp: ContextResponse = serde_json::from_str(&text)
+        .map_err(|e| CliError::new(Category::Failed, format!("Invalid context response: {}", e)))?;
+
+    let context_str = context_resp.context;
+    Ok(F::from_bigint(BigInteger256::from_str(&context_str).unwrap()).unwrap())
+}
+
+/// The non-empty lines of a file given with `--from-file`.
+fn read_lines(path: &Path) -> Result<Vec<String>, CliError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| CliError::new(Category::Usage, format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The votes in a file given to `vote-batch --from-file`, one `<timestamp> <emoji>` per line.
+fn read_votes(path: &Path) -> Result<Vec<(u64, String)>, CliError> {
+    read_lines(path)?
+        .into_iter()
+        .map(|line| {
+            line.split_once(char::is_whitespace)
+                .and_then(|(t, e)| Some((t.parse::<u64>().ok()?, e.trim().to_string())))
+                .ok_or_else(|| {
+                    let message = format!(
+                        "Invalid vote `{}` in {}: expected `<timestamp> <emoji>`",
+                        line,
+                        path.display()
+                    );
+                    CliError::new(Category::Usage, message)
+                })
+        })
+        .collect()
+}
+
+/// A proof that the user owns the pseudonym named `name`, by index or label, bound to the direct
+/// message request `request` and to a nonce fetched for it, which is returned with the proof.
+async fn ownership_proof(
+    client: &Client,
+    name: &str,
+    allow_retired: bool,
+    request: &[&str],
+) -> Result<(Vec<u8>, DmNonce), CliError> {
+    let pseudonyms = Pseudonyms::load()?;
+    let pseudonym = pseudonyms.find(name).map_err(|e| CliError::new(Category::Usage, e))?;
+    if pseudonym.retired && !allow_retired {
+        return Err(CliError::new(
+            Category::Usage,
+            format!("Pseudonym {} ({}) is retired", pseudonym.index, pseudonym.name()),
+        ));
+    }
+    let text = send(client.post(endpoint("api/dm/nonce"))).await?;
+    let nonce: DmNonce = serde_json::from_str(&text)
+        .map_err(|e| CliError::new(Category::Failed, format!("Invalid nonce response: {}", e)))?;
+    let nonce_f = F::from_str(&nonce.nonce)
+        .map_err(|_| CliError::new(Category::Failed, "Invalid nonce response"))?;
+    let context = field(dm_context(nonce_f, request));
+    let i = pseudonym.index;
+    let proof = proved(spawn_blocking(move || prove(Request::Dm { i, context }))).await?;
+    Ok((proof, nonce))
+}
+
+/// Send a direct message as the pseudonym `pseudonym`, or run a direct message command.
+async fn dm(
+    client: &Client,
+    to: Option<String>,
+    message: Option<String>,
+    pseudonym: String,
+    command: Option<DmCommand>,
+) -> CmdResult {
+    match command {
+        Some(DmCommand::Register { signal, pseudonym }) => {
+            let request = ["register", signal.as_str()];
+            let (proof, nonce) = ownership_proof(client, &pseudonym, false, &request).await?;
+            let payload = JsonDmRegister { proof, recipient: Some(signal), nonce };
+            send_and_report(client.post(endpoint("api/dm/register")).json(&payload)).await
+        }
+        Some(DmCommand::Unregister { pseudonym }) => {
+            // A retired pseudonym may still be receiving them
+            let request = ["unregister", ""];
+            let (proof, nonce) = ownership_proof(client, &pseudonym, true, &request).await?;
+            let payload = JsonDmRegister { proof, recipient: None, nonce };
+            send_and_report(client.post(endpoint("api/dm/register")).json(&payload)).await
+        }
+        None => {
+            let to = to.expect("clap requires --to");
+            let message = message.expect("clap requires a message");
+            let request = ["send", to.as_str(), message.as_str()];
+            let (proof, nonce) = ownership_proof(client, &pseudonym, false, &request).await?;
+            let payload = JsonDm {
+                proof,
+                to,
+                message,
+                nonce,
+            };
+            send_and_report(client.post(endpoint("api/dm")).json(&payload)).await
+        }
+    }
+}
+
+/// The group given on the command line, or the configured group.
+fn group_or_default(group_id: Option<String>) -> Result<String, CliError> {
+    group_id
+        .or_else(|| settings().group_id.clone())
+        .ok_or_else(|| {
+            CliError::new(Category::Usage, "No group given: pass -g, or set group_id in the client config")
+        })
+}
+
+/// The claimed pseudonym and context of the pseudonym numbered `pseudo_idx`, or of the first
+/// one if there is no such pseudonym. Refuses retired pseudonyms.
+fn pseudonym_at(pseudo_idx: usize) -> Result<(F, F), CliError> {
+    let pseudonyms = Pseudonyms::load()?;
+    let pseudonym = pseudonyms
+        .get(pseudo_idx)
+        .or_else(|| pseudonyms.get(1))
+        .ok_or_else(|| CliError::new(Category::Usage, "No pseudonym yet: run `pseudo new` first"))?;
+    if pseudonym.retired {
+        return Err(CliError::new(
+            Category::Usage,
+            format!("Pseudonym {} ({}) is retired", pseudonym.index, pseudonym.name()),
+        ));
+    }
+    Ok((pseudonym.claimed(), pseudonym.context()))
+}
+
+/// The indices of the pseudonyms named, by index or label, to prove the authorship of. Retired
+/// pseudonyms may be named, as their posts are still the user's.
+fn authors(names: impl IntoIterator<Item = String>) -> Result<Vec<usize>, CliError> {
+    let pseudonyms = Pseudonyms::load()?;
+    let mut indices = vec![];
+    for name in names {
+        let index = pseudonyms.find(&name).map_err(|e| CliError::new(Category::Usage, e))?.index;
+        if !indices.contains(&index) {
+            indices.push(index);
+        }
+    }
+    if indices.len() < 2 {
+        return Err(CliError::new(Category::Usage, "Name at least two different pseudonyms"));
+    }
+    Ok(indices)
+}
+
+/// A pseudonym as printed with `--output json`.
+fn pseudonym_value(pseudonym: &Pseudonym) -> Value {
+    json!({
+        "index": pseudonym.index,
+        "name": pseudonym.name(),
+        "label": pseudonym.label,
+        "created": pseudonym.created,
+        "retired": pseudonym.retired,
+        "links": pseudonym.links,
+    })
+}
+
+/// Print a pseudonym as listed by `pseudo list`.
+fn print_pseudonym(pseudonym: &Pseudonym) {
+    let mut line = format!("{}. {}", pseudonym.index, pseudonym.name());
+    if let Some(label) = &pseudonym.label {
+        line.push_str(&format!(" [{}]", label));
+    }
+    if pseudonym.retired {
+        line.push_str(" (retired)");
+    }
+    println!("{}", line);
+    for (key, value) in &pseudonym.links {
+        println!("    {}: {}", key, value);
+    }
+}
+
+fn pseudo(command: PseudoCommand) -> CmdResult {
+    match command {
+        PseudoCommand::New { label } => {
+            let pseudonym = gen_pseudo(label)?;
+            say!("Made pseudonym {}: {}", pseudonym.index, pseudonym.name());
+            Ok(pseudonym_value(&pseudonym))
+        }
+        PseudoCommand::List { all } => {
+            let pseudonyms = Pseudonyms::load()?;
+            let listed: Vec<&Pseudonym> = pseudonyms.pseudonyms.iter().filter(|p| all || !p.retired).collect();
+            if !output::is_json() {
+                if listed.is_empty() {
+                    println!("No pseudonyms: make one with `pseudo new`");
+                }
+                for pseudonym in &listed {
+                    print_pseudonym(pseudonym);
+                }
+            }
+            Ok(json!(listed.into_iter().map(pseudonym_value).collect::<Vec<_>>()))
+        }
+        PseudoCommand::Retire { pseudonym } => {
+            let mut pseudonyms = Pseudonyms::load()?;
+            let retired = pseudonyms.find_mut(&pseudonym).map_err(|e| CliError::new(Category::Usage, e))?;
+            retired.retired = true;
+            let retired = retired.clone();
+            pseudonyms.save()?;
+            say!("Retired pseudonym {}: {}", retired.index, retired.name());
+            Ok(pseudonym_value(&retired))
+        }
+        PseudoCommand::Link { pseudonym, key, value } => {
+            let mut pseudonyms = Pseudonyms::load()?;
+            let linked = pseudonyms.find_mut(&pseudonym).map_err(|e| CliError::new(Category::Usage, e))?;
+            say!("Linked {} to pseudonym {}", key, linked.index);
+            linked.links.insert(key, value);
+            let linked = linked.clone();
+            pseudonyms.save()?;
+            Ok(pseudonym_value(&linked))
+        }
+        PseudoCommand::Unlink { pseudonym, key } => {
+            let mut pseudonyms = Pseudonyms::load()?;
+            let unlinked = pseudonyms.find_mut(&pseudonym).map_err(|e| CliError::new(Category::Usage, e))?;
+            if unlinked.links.remove(&key).is_none() {
+                return Err(CliError::new(
+                    Category::Usage,
+                    format!("Pseudonym {} has no {}", unlinked.index, key),
+                ));
+            }
+            say!("Unlinked {} from pseudonym {}", key, unlinked.index);
+            let unlinked = unlinked.clone();
+            pseudonyms.save()?;
+            Ok(pseudonym_value(&unlinked))
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
-    let client = Client::new();
-    match cli.command {
-        Command::ViewPosts => {
-            println!("Fetching posts...");
-        }
-        
-        Command::Post { message, group_id } => {      
-
-            match spawn_blocking(|| gen_cb_for_msg()).await {
-                Ok(Ok(proof_bytes)) => {
-
-                    let payload = JsonRpcInput {
-                        message,
-                        group_id,
-                        proof: proof_bytes, 
-                    };
-        
-                    if let Err(e) = save_start_time("3") {
-                        eprintln!("Failed to save start time: {}", e);
-                    }
+    let matches = Cli::command().get_matches();
+    let name = matches.subcommand_name().unwrap_or_default().to_string();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    output::init(cli.output == OutputFormat::Json);
+
+    // The daemon survives failed proofs, which would otherwise end it here
+    if !matches!(cli.command, Command::Daemon) {
+        let command = name.clone();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !output::is_json() {
+                default_hook(info);
+                std::process::exit(Category::Failed.exit_code());
+            }
+            output::finish(&command, Err(CliError::new(Category::Failed, format!("panicked: {}", info))));
+        }));
+        on_progress(|stage| *STAGE.lock().unwrap() = Some((stage, Instant::now())));
+    }
+    if let Some(timeout) = cli.timeout {
+        let _ = PROVE_TIMEOUT.set(Duration::from_secs(timeout));
+    }
 
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/jsonrpc")
-                        .json(&payload)
-                        .send()
-                        .await
-                        .unwrap();
+    let loaded = Config::load(cli.config.as_deref())
+        .and_then(|config| config.settings(cli.profile.as_deref(), cli.user));
+    match loaded {
+        Ok(settings) => client::config::init(settings),
+        Err(e) => output::finish(&name, Err(CliError::new(Category::Usage, format!("{:#}", e)))),
+    }
+    let client = Client::new();
+    let result = run(cli.command, &client, cli.auto_scan).await;
+    output::finish(&name, result)
+}
 
-                    println!("Server responded: {}", res.text().await.unwrap());
+async fn run(command: Command, client: &Client, auto_scan: bool) -> CmdResult {
+    match command {
+        Command::ViewPosts {
+            group_id,
+            thread,
+            limit,
+            mut before,
+        } => {
+            let interactive = !output::is_json()
+                && std::io::stdin().is_terminal()
+                && std::io::stdout().is_terminal();
+            loop {
+                let mut query = vec![("limit", limit.to_string())];
+                if let Some(before) = before {
+                    query.push(("before", before.to_string()));
+                }
+                if let Some(group_id) = &group_id {
+                    query.push(("group_id", group_id.clone()));
                 }
-                Ok(Err(e)) => {
-                    eprintln!("[gen_cb_for_msg] Error: {:?}", e);
+                if let Some(thread) = &thread {
+                    query.push(("thread", thread.clone()));
                 }
-                Err(join_err) => {
-                    eprintln!("[gen_cb_for_msg] Panic inside task: {:?}", join_err);
+                let text = send(client.get(endpoint("api/posts")).query(&query)).await?;
+                if output::is_json() {
+                    return Ok(response_value(&text));
                 }
+                let page: PostsPage = serde_json::from_str(&text)
+                    .map_err(|e| CliError::new(Category::Failed, format!("Invalid response: {}", e)))?;
+                if page.posts.is_empty() && before.is_none() {
+                    println!("No posts yet");
+                }
+                for post in &page.posts {
+                    print_post(post);
+                }
+                let Some(next) = page.next else {
+                    break;
+                };
+                if !interactive {
+                    println!("More with --before {}", next);
+                    break;
+                }
+                print!("-- Enter for more, q to stop -- ");
+                let _ = std::io::stdout().flush();
+                let mut answer = String::new();
+                if std::io::stdin().read_line(&mut answer).is_err() || answer.trim() == "q" {
+                    break;
+                }
+                before = Some(next);
             }
+            Ok(Value::Null)
         }
 
-        Command::PostPseudo {
+        Command::Post {
             message,
             group_id,
-            pseudo_idx,
+            attachment,
+            from_file,
         } => {
+            let group_id = group_or_default(group_id)?;
+            let Some(from_file) = from_file else {
+                let message = message.expect("clap requires a message without --from-file");
+                return post(client, auto_scan, group_id, message, &attachment).await;
+            };
 
-            let (claimed_str, context_str) = get_claimed_context_by_index(pseudo_idx)
-                .or_else(|| get_claimed_context_by_index(1))
-                .expect("Both provided index and fallback index 1 failed");
-
-            let context_f = F::from_bigint(BigInteger256::from_str(&context_str).unwrap()).unwrap();
-            let claimed_f = F::from_bigint(BigInteger256::from_str(&claimed_str).unwrap()).unwrap();
+            let messages = read_lines(&from_file)?;
+            let mut results = vec![];
+            for (i, message) in messages.iter().enumerate() {
+                say!("Posting {}/{}...", i + 1, messages.len());
+                // Every post moves the user to a new commitment, so they are proved one at a time
+                match post(client, auto_scan, group_id.clone(), message.clone(), &[]).await {
+                    Ok(result) => results.push(result),
+                    Err(e) => {
+                        return Err(CliError {
+                            message: format!("Post {}/{} failed: {}", i + 1, messages.len(), e.message),
+                            ..e
+                        });
+                    }
+                }
+            }
+            Ok(json!({ "posts": results }))
+        }
 
-            match spawn_blocking(move || pseudo_proof_with_msg(claimed_f, context_f)).await {
-                Ok(Ok(proof)) => {
-                    let payload = JsonRpcInputPseudo {
-                        message,
-                        group_id,
-                        proof,
-                    };
+        Command::PostPseudo {
+            message,
+            group_id,
+            pseudo_idx,
+        } => {
+            let group_id = group_or_default(group_id)?;
+            scan_before_post(client, auto_scan).await?;
 
-                    if let Err(e) = save_start_time("pseudo_msg") {
-                        eprintln!("Failed to save start time: {}", e);
-                    }
+            let (claimed_f, context_f) = pseudonym_at(pseudo_idx)?;
 
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/jsonrpc/pseudo")
-                        .json(&payload)
-                        .send()
-                        .await
-                        .unwrap();
+            let proof = proved(spawn_blocking(move || {
+                prove(Request::PostPseudo { claimed: field(claimed_f), context: field(context_f) })
+            })).await?;
+            let payload = JsonRpcInputPseudo {
+                message,
+                group_id,
+                proof,
+            };
 
-                    println!("Server responded: {}", res.text().await.unwrap());
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[pseudo_proof2] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[pseudo_proof2] Panic inside task: {:?}", join_err);
-                }
+            if let Err(e) = save_start_time("pseudo_msg") {
+                eprintln!("Failed to save start time: {}", e);
             }
+
+            submitted(outbox::submit(client, "api/jsonrpc/pseudo", Body::json(&payload)).await)
         }
 
         Command::PostPseudoRate {
@@ -208,216 +971,169 @@ async fn main() {
             thread,
             pseudo_idx,
         } => {
+            let group_id = group_or_default(group_id)?;
+            scan_before_post(client, auto_scan).await?;
 
-            let context_f = lookup_context(&thread)
-                .expect("Could not find matching context for thread in local file");
+            let context_f = lookup_context(&thread).ok_or_else(|| {
+                CliError::new(Category::Usage, "Could not find matching context for thread in local file")
+            })?;
 
             let i = F::from(pseudo_idx as u32);
 
             let claimed_f = prf2(&context_f, &i);
 
-            match spawn_blocking(move || rate_pseudo_proof_with_msg(claimed_f, context_f, i)).await
-            {
-                Ok(Ok(proof)) => {
-                    let payload = JsonRpcInputPseudo {
-                        message,
-                        group_id,
-                        proof,
-                    };
-
-                    if let Err(e) = save_start_time("rate_pseudo") {
-                        eprintln!("Failed to save start time: {}", e);
-                    }
-
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/jsonrpc/pseudo/rate")
-                        .json(&payload)
-                        .send()
-                        .await
-                        .unwrap();
+            let proof = proved(spawn_blocking(move || {
+                prove(Request::PostPseudoRate {
+                    claimed: field(claimed_f),
+                    context: field(context_f),
+                    i: field(i),
+                })
+            }))
+            .await?;
+            let payload = JsonRpcInputPseudo {
+                message,
+                group_id,
+                proof,
+            };
 
-                    println!("Server responded: {}", res.text().await.unwrap());
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[pseudo_proof_rate] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[pseudo_proof_rate] Panic inside task: {:?}", join_err);
-                }
+            if let Err(e) = save_start_time("rate_pseudo") {
+                eprintln!("Failed to save start time: {}", e);
             }
-        }
 
-        Command::GenPseudo {} => {
-            spawn_blocking(|| gen_pseudo()).await.unwrap();
-        }
-        Command::PseudoIndex {} => {
-            spawn_blocking(|| list_all_pseudos_from_log())
-                .await
-                .unwrap();
+            submitted(outbox::submit(client, "api/jsonrpc/pseudo/rate", Body::json(&payload)).await)
         }
 
+        Command::GenPseudo {} => pseudo(PseudoCommand::New { label: None }),
+        Command::PseudoIndex {} => pseudo(PseudoCommand::List { all: true }),
+        Command::Pseudo { command } => pseudo(command),
+
         Command::NewThreadCxt { message } => {
             let req = ContextRequest {
                 thread: message.clone(),
             };
 
-            let res = client
-                .post("http://127.0.0.1:3000/api/pseudo/new_thread_context")
-                .json(&req)
-                .send()
-                .await
-                .unwrap();
-
-            println!("Server responded: {}", res.text().await.unwrap());
+            send_and_report(client.post(endpoint("api/pseudo/new_thread_context")).json(&req)).await
         }
 
-        Command::GetContexts => {
-            let res = client
-                .get("http://127.0.0.1:3000/api/pseudo/get_all_contexts")
-                .send()
-                .await
-                .expect("Request failed");
-
-            let status = res.status();
-            let body = res.text().await.unwrap();
-
-            if !status.is_success() {
-                eprintln!("Server error ({}): {}", status, body);
-                return;
+        Command::GetContexts { search } => {
+            let url = endpoint("api/contexts");
+
+            if let Some(search) = search {
+                let text = send(client.get(url).query(&[("search", &search)])).await?;
+                let page: ContextsPage = serde_json::from_str(&text)
+                    .map_err(|e| CliError::new(Category::Failed, format!("Invalid response: {}", e)))?;
+                let mut found = serde_json::Map::new();
+                for listed in page.contexts {
+                    say!("{}: {}", listed.thread, listed.context);
+                    found.insert(listed.thread, Value::String(listed.context));
+                }
+                return Ok(Value::Object(found));
             }
 
-            // Ensure client directory exists
-            std::fs::create_dir_all("client").expect("Failed to create client dir");
-
-            // Write the full context.jsonl content to the client's file
-            let mut file = File::create("client/contexts.jsonl").expect("Failed to create file");
-            file.write_all(body.as_bytes())
-                .expect("Failed to write file");
-
-            println!("Downloaded all contexts to client/contexts.jsonl");
-        }
-
-        Command::Scan {} => {
-            println!("Scanning...");
-
-            match spawn_blocking(|| scan()).await {
-                Ok(Ok(proof_bytes)) => {
-             
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/interact/scan")
-                        .body(proof_bytes) // sends raw binary, as expected
-                        .send()
-                        .await
-                        .unwrap();
-
-                    println!("Server responded: {}", res.text().await.unwrap());
+            // Only fetch the threads created since the last sync
+            let mut cache = ContextCache::load();
+            let mut fetched = 0;
+            loop {
+                let mut request = client.get(url.clone());
+                if let Some(after) = cache.cursor {
+                    request = request.query(&[("after", after)]);
                 }
-                Ok(Err(e)) => {
-                    eprintln!("[gen_cb_for_msg] Error: {:?}", e);
+                let text = send(request).await?;
+                let page: ContextsPage = serde_json::from_str(&text)
+                    .map_err(|e| CliError::new(Category::Failed, format!("Invalid response: {}", e)))?;
+                for listed in page.contexts {
+                    cache.cursor = Some(listed.id);
+                    cache.contexts.insert(listed.thread, listed.context);
+                    fetched += 1;
                 }
-                Err(join_err) => {
-                    eprintln!("[gen_cb_for_msg] Panic inside task: {:?}", join_err);
+                if page.next.is_none() {
+                    break;
                 }
             }
+
+            cache.save()?;
+            say!(
+                "Fetched {} new contexts ({} in {})",
+                fetched,
+                cache.contexts.len(),
+                contexts_path().display()
+            );
+            Ok(json!({ "fetched": fetched, "total": cache.contexts.len() }))
+        }
+
+        Command::Scan {} => {
+            say!("Scanning...");
+            prepare_interaction(client).await?;
+
+            submit_scan(client).await
         }
 
         Command::Ban { t } => {
-            println!("Banning...");
+            say!("Banning...");
 
             let start = SystemTime::now();
 
-            match spawn_blocking(move || ban(t)).await {
-                Ok(Ok(())) => {
-                    // Success
-                    let end = SystemTime::now();
+            spawn_blocking(move || ban(t)).await.unwrap()?;
+            let end = SystemTime::now();
 
-                    if let Err(e) = append_timing_line_features("ban", start, end) {
-                        eprintln!("Failed to write timing file for proof gen: {}", e);
-                    }
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[gen_cb_for_msg] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[gen_cb_for_msg] Panic inside task: {:?}", join_err);
-                }
+            if let Err(e) = append_timing_line_features("ban", start, end) {
+                eprintln!("Failed to write timing file for proof gen: {}", e);
             }
 
-            println!("Banned");
+            say!("Banned");
+            Ok(json!({ "timestamp": t }))
         }
 
         Command::Rep { t } => {
-            println!("Recording rep...");
+            say!("Recording rep...");
 
             let start = SystemTime::now();
 
-            match spawn_blocking(move || rep(t)).await {
-                Ok(Ok(())) => {
-                    // Success
-                    let end = SystemTime::now();
+            spawn_blocking(move || rep(t)).await.unwrap()?;
+            let end = SystemTime::now();
 
-                    if let Err(e) = append_timing_line_features("rep", start, end) {
-                        eprintln!("Failed to write timing file for proof gen: {}", e);
-                    }
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[gen_cb_for_msg] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[gen_cb_for_msg] Panic inside task: {:?}", join_err);
-                }
+            if let Err(e) = append_timing_line_features("rep", start, end) {
+                eprintln!("Failed to write timing file for proof gen: {}", e);
             }
 
-            println!("Recorded");
+            say!("Recorded");
+            Ok(json!({ "timestamp": t }))
         }
 
         Command::Join {} => {
-            println!("Joining bul...");
+            say!("Joining bul...");
 
-            match spawn_blocking(|| join2()).await {
-                Ok(Ok(())) => {
-                    // success
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[gen_cb_for_msg] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[gen_cb_for_msg] Panic inside task: {:?}", join_err);
-                }
-            }
+            spawn_blocking(|| join2()).await.unwrap()?;
 
-            println!("Joined");
+            say!("Joined");
+            Ok(Value::Null)
         }
 
-        Command::Pseudonym {} => {
-            let res = client
-                .get("http://127.0.0.1:3000/api/pseudonym")
-                .send()
-                .await
-                .unwrap();
-
-            println!("Server responded: {}", res.text().await.unwrap());
+        Command::Pseudonym { group_id } => {
+            let group_id = group_id.or_else(|| settings().group_id.clone());
+            send_and_report(client.get(endpoint("api/pseudonym")).query(&[("group_id", group_id)])).await
         }
 
+        Command::Dm {
+            to,
+            message,
+            pseudonym,
+            command,
+        } => dm(client, to, message, pseudonym, command).await,
+
         Command::Reaction {
             group_id,
             emoji,
             timestamp,
         } => {
+            let group_id = group_or_default(group_id)?;
             let payload = JsonRpcReact {
                 group_id,
                 emoji,
                 timestamp,
             };
 
-            let res = client
-                .post("http://127.0.0.1:3000/api/react")
-                .json(&payload)
-                .send()
-                .await
-                .unwrap();
-
-            println!("Server responded: {}", res.text().await.unwrap());
+            send_and_report(client.post(endpoint("api/react")).json(&payload)).await
         }
 
         Command::Reply {
@@ -425,32 +1141,18 @@ async fn main() {
             message,
             timestamp,
         } => {
-            match spawn_blocking(|| gen_cb_for_msg()).await {
-                Ok(Ok(proof_bytes)) => {
-
-                    let payload = JsonRpcReply {
-                        group_id,
-                        message,
-                        timestamp,
-                        proof: proof_bytes,
-                    };
-
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/reply")
-                        .json(&payload)
-                        .send()
-                        .await
-                        .unwrap();
-
-                    println!("Server responded: {}", res.text().await.unwrap());
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[gen_cb_for_msg] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[gen_cb_for_msg] Panic inside task: {:?}", join_err);
-                }
-            }
+            let group_id = group_or_default(group_id)?;
+            scan_before_post(client, auto_scan).await?;
+            let proof_bytes =
+                proved(spawn_blocking(|| prove(Request::Post { digest: field(F::from(0)) }))).await?;
+            let payload = JsonRpcReply {
+                group_id,
+                message,
+                timestamp,
+                proof: proof_bytes,
+            };
+
+            submitted(outbox::submit(client, "api/reply", Body::json(&payload)).await)
         }
 
         Command::ReplyPseudo {
@@ -459,39 +1161,22 @@ async fn main() {
             timestamp,
             pseudo_idx,
         } => {
+            let group_id = group_or_default(group_id)?;
+            scan_before_post(client, auto_scan).await?;
             // Load the pseudonym context and claimed fields from log
-            let (claimed_str, context_str) = get_claimed_context_by_index(pseudo_idx)
-                .or_else(|| get_claimed_context_by_index(1))
-                .expect("Both provided index and fallback index 1 failed");
-
-            let context_f = F::from_bigint(BigInteger256::from_str(&context_str).unwrap()).unwrap();
-            let claimed_f = F::from_bigint(BigInteger256::from_str(&claimed_str).unwrap()).unwrap();
-
-            match spawn_blocking(move || pseudo_proof_with_msg(claimed_f, context_f)).await {
-                Ok(Ok(proof)) => {
-                    let payload = JsonRpcReplyPseudo {
-                        group_id,
-                        message,
-                        timestamp,
-                        proof,
-                    };
-
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/reply/pseudo")
-                        .json(&payload)
-                        .send()
-                        .await
-                        .unwrap();
-
-                    println!("Server responded: {}", res.text().await.unwrap());
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[pseudo_proof2] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[pseudo_proof2] Panic inside task: {:?}", join_err);
-                }
-            }
+            let (claimed_f, context_f) = pseudonym_at(pseudo_idx)?;
+
+            let proof = proved(spawn_blocking(move || {
+                prove(Request::PostPseudo { claimed: field(claimed_f), context: field(context_f) })
+            })).await?;
+            let payload = JsonRpcReplyPseudo {
+                group_id,
+                message,
+                timestamp,
+                proof,
+            };
+
+            submitted(outbox::submit(client, "api/reply/pseudo", Body::json(&payload)).await)
         }
 
         Command::Vote {
@@ -499,79 +1184,92 @@ async fn main() {
             emoji,
             timestamp,
         } => {
-            let payload = serde_json::json!({ "timestamp": timestamp });
-
-            let response = client
-                .post("http://127.0.0.1:3000/api/context")
-                .json(&payload)
-                .send()
-                .await
-                .expect("Failed to reach context endpoint");
-
-            let context_resp: ContextResponse = response
-                .json()
-                .await
-                .expect("Failed to parse JSON from context server");
-
-            let context_str = context_resp.context;
-            let context_f = F::from_bigint(BigInteger256::from_str(&context_str).unwrap()).unwrap();
+            let group_id = group_or_default(group_id)?;
+            let context_f = poll_context(client, timestamp).await?;
             let claimed_f = compute_pseudo_for_poll(&context_f);
 
-            match spawn_blocking(move || pseudo_proof_vote(claimed_f, context_f)).await {
-                Ok(Ok(proof)) => {
-                    let payload = JsonRpcVote {
-                        group_id,
-                        emoji,
-                        timestamp,
-                        claimed: claimed_f.to_string(),
-                        proof,
-                    };
-
-                    if let Err(e) = save_start_time("pseudo_vote") {
-                        eprintln!("Failed to save start time: {}", e);
-                    }
+            let proof = proved(spawn_blocking(move || {
+                prove(Request::Vote { claimed: field(claimed_f), context: field(context_f) })
+            })).await?;
+            let payload = JsonRpcVote {
+                group_id,
+                emoji,
+                timestamp,
+                claimed: claimed_f.to_string(),
+                proof,
+            };
 
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/vote")
-                        .json(&payload)
-                        .send()
-                        .await
-                        .unwrap();
+            if let Err(e) = save_start_time("pseudo_vote") {
+                eprintln!("Failed to save start time: {}", e);
+            }
 
-                    println!("Server responded: {}", res.text().await.unwrap());
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[pseudo_proof2] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[pseudo_proof2] Panic inside task: {:?}", join_err);
+            send_and_report(client.post(endpoint("api/vote")).json(&payload)).await
+        }
+
+        Command::VoteBatch {
+            group_id,
+            timestamp,
+            emoji,
+            from_file,
+        } => {
+            let group_id = group_or_default(group_id)?;
+            let mut votes: Vec<(u64, String)> = timestamp
+                .into_iter()
+                .map(|t| (t, emoji.clone().expect("clap requires an emoji with -t")))
+                .collect();
+            if let Some(from_file) = from_file {
+                votes.extend(read_votes(&from_file)?);
+            }
+
+            let mut fields = vec![];
+            for (timestamp, _) in &votes {
+                let context_f = poll_context(client, *timestamp).await?;
+                fields.push((compute_pseudo_for_poll(&context_f), context_f));
+            }
+
+            say!("Proving {} votes...", votes.len());
+            if let Err(e) = save_start_time("pseudo_vote") {
+                eprintln!("Failed to save start time: {}", e);
+            }
+            let claimed: Vec<F> = fields.iter().map(|(claimed_f, _)| *claimed_f).collect();
+            let proofs = proved(spawn_blocking(move || {
+                pseudo_proof_votes(&fields).map_err(|e| anyhow::anyhow!("{:?}", e))
+            })).await?;
+
+            let mut results = vec![];
+            let count = votes.len();
+            for (i, (((timestamp, emoji), claimed_f), proof)) in votes.into_iter().zip(claimed).zip(proofs).enumerate() {
+                let payload = JsonRpcVote {
+                    group_id: group_id.clone(),
+                    emoji,
+                    timestamp,
+                    claimed: claimed_f.to_string(),
+                    proof,
+                };
+                match send_and_report(client.post(endpoint("api/vote")).json(&payload)).await {
+                    Ok(result) => results.push(json!({ "timestamp": timestamp, "response": result["response"] })),
+                    Err(e) => {
+                        return Err(CliError {
+                            message: format!("Vote {}/{} (poll {}) failed: {}", i + 1, count, timestamp, e.message),
+                            ..e
+                        });
+                    }
                 }
             }
+            Ok(json!({ "votes": results }))
         }
 
         Command::CountVotes {
             group_id,
             timestamp,
         } => {
+            let group_id = group_or_default(group_id)?;
             let payload = JsonRpcCountVotes {
                 group_id,
                 timestamp,
             };
 
-            let res = client
-                .post("http://127.0.0.1:3000/api/votecount")
-                .json(&payload)
-                .send()
-                .await;
-
-            match res {
-                Ok(response) => {
-                    println!("Server responded: {}", response.text().await.unwrap());
-                }
-                Err(e) => {
-                    eprintln!("Request failed: {}", e);
-                }
-            }
+            send_and_report(client.post(endpoint("api/votecount")).json(&payload)).await
         }
 
         Command::BanPoll {
@@ -579,97 +1277,231 @@ async fn main() {
             group_id,
             timestamp,
         } => {
+            let group_id = group_or_default(group_id)?;
             let payload = JsonRpcBanPoll {
                 message,
                 group_id,
                 timestamp,
             };
 
-            let res = client
-                .post("http://127.0.0.1:3000/api/banpoll")
-                .json(&payload)
-                .send()
-                .await
-                .unwrap();
-
-            println!("Server responded: {}", res.text().await.unwrap());
+            send_and_report(client.post(endpoint("api/banpoll")).json(&payload)).await
         }
         Command::Poll { message, group_id } => {
+            let group_id = group_or_default(group_id)?;
             let payload = JsonRpcPoll { message, group_id };
 
-            let res = client
-                .post("http://127.0.0.1:3000/api/poll")
-                .json(&payload)
-                .send()
-                .await
-                .unwrap();
-
-            println!("Server responded: {}", res.text().await.unwrap());
+            send_and_report(client.post(endpoint("api/poll")).json(&payload)).await
         }
 
         Command::Authorship {
+            pseudonyms,
             pseudo_idx1,
             pseudo_idx2,
             group_id,
         } => {
-            let payload_result =
-                spawn_blocking(move || make_authorship_proof(pseudo_idx1, pseudo_idx2)).await;
+            let group_id = group_or_default(group_id)?;
+            let names = pseudo_idx1
+                .into_iter()
+                .chain(pseudo_idx2)
+                .map(|i| i.to_string())
+                .chain(pseudonyms);
+            let indices = authors(names)?;
+            let proof = proved(spawn_blocking(move || prove(Request::Authorship { indices }))).await?;
+            let payload = JsonAuthorship { proof, group_id };
+
+            if let Err(e) = save_start_time("author") {
+                eprintln!("Failed to save start time: {}", e);
+            }
 
-            match payload_result {
-                Ok(Ok(proof)) => {
-                    let payload = JsonAuthorship { proof, group_id };
+            send_and_report(client.post(endpoint("api/authorship")).json(&payload)).await
+        }
 
-                    if let Err(e) = save_start_time("author") {
-                        eprintln!("Failed to save start time: {}", e);
-                    }
+        Command::Badge { i, claimed, group_id } => {
+            let group_id = group_or_default(group_id)?;
+            let claimed_f = string_to_f(&claimed);
+            let proof =
+                proved(spawn_blocking(move || prove(Request::Badge { i, badge: field(claimed_f) }))).await?;
+            let payload = JsonBadge {proof, group_id};
+
+            if let Err(e) = save_start_time("badge") {
+                eprintln!("Failed to save start time: {}", e);
+            }
 
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/authorship")
-                        .json(&payload)
-                        .send()
-                        .await
-                        .unwrap();
+            send_and_report(client.post(endpoint("api/badges")).json(&payload)).await
+        }
 
-                    println!("Server responded: {}", res.text().await.unwrap());
-                }
-                Ok(Err(e)) => {
-                    eprintln!("[make_authorship_proof] Error: {:?}", e);
-                }
-                Err(join_err) => {
-                    eprintln!("[make_authorship_proof] Panic inside task: {:?}", join_err);
-                }
+        Command::ProveRep {
+            at_least,
+            pseudonym,
+            group_id,
+        } => {
+            let group_id = group_or_default(group_id)?;
+            if at_least > REP_BOUNDS.max {
+                return Err(CliError::new(
+                    Category::Usage,
+                    format!("Reputation is at most {}", REP_BOUNDS.max),
+                ));
+            }
+            let i = Pseudonyms::load()?
+                .find(&pseudonym)
+                .map_err(|e| CliError::new(Category::Usage, e))?
+                .index;
+            let proof = proved(spawn_blocking(move || prove(Request::Reputation { i, at_least }))).await?;
+            let payload = JsonRepProof { proof, group_id };
+
+            if let Err(e) = save_start_time("rep_proof") {
+                eprintln!("Failed to save start time: {}", e);
             }
+
+            send_and_report(client.post(endpoint("api/reputation/proof")).json(&payload)).await
         }
 
-        Command::Badge { i, claimed, group_id } => {
-            let claimed_f = string_to_f(&claimed);
-            let payload_result = spawn_blocking(move || make_badge_proof(i, claimed_f)).await;
+        Command::Daemon => {
+            spawn_blocking(client::daemon::serve).await.unwrap()?;
+            Ok(Value::Null)
+        }
 
-            match payload_result {
-                Ok(Ok(proof)) => {
-                    let payload = JsonBadge {proof, group_id};
+        Command::Export { out, encrypt } => {
+            let passphrase = if encrypt { Some(prompt_passphrase(true)?) } else { None };
+            backup::export(&out, passphrase.as_deref())?;
+            say!("Backed up to {}", out.display());
+            Ok(json!({ "file": out }))
+        }
 
-                    if let Err(e) = save_start_time("badge") {
-                        eprintln!("Failed to save start time: {}", e);
-                    }
+        Command::Import { file, force } => {
+            let archive = std::fs::read(&file)?;
+            let passphrase = if backup::is_encrypted(&archive)? {
+                Some(prompt_passphrase(false)?)
+            } else {
+                None
+            };
+            let restored = backup::read(&archive, passphrase.as_deref())?;
+            backup::import(restored, force)?;
+            say!("Restored {}", file.display());
+            Ok(json!({ "file": file }))
+        }
 
-                    let res = client
-                        .post("http://127.0.0.1:3000/api/badges")
-                        .json(&payload)
-                        .send()
-                        .await
-                        .unwrap();
+        Command::Sync => {
+            let applied = spawn_blocking(sync::sync).await.unwrap()?;
+            if applied == 0 {
+                say!("Already up to date");
+            } else {
+                say!("Applied {} changes from other devices", applied);
+            }
+            Ok(json!({ "applied": applied }))
+        }
+
+        Command::Status => {
+            let status = spawn_blocking(status::status).await.unwrap()?;
+            if !output::is_json() {
+                print_status(&status);
+            }
+            Ok(serde_json::to_value(&status).expect("the status serializes"))
+        }
 
-                    println!("Server responded: {}", res.text().await.unwrap());
+        Command::Outbox { command } => match command {
+            OutboxCommand::List => {
+                let queued = outbox::list()?;
+                if queued.is_empty() {
+                    say!("Nothing is queued");
                 }
-                Ok(Err(e)) => {
-                    eprintln!("[make_authorship_proof] Error: {:?}", e);
+                for (seq, submission) in &queued {
+                    say!("{}: {} (submitted at {})", seq, submission.endpoint, submission.submitted);
                 }
-                Err(join_err) => {
-                    eprintln!("[make_authorship_proof] Panic inside task: {:?}", join_err);
+                Ok(json!(queued
+                    .into_iter()
+                    .map(|(seq, submission)| json!({
+                        "seq": seq,
+                        "endpoint": submission.endpoint,
+                        "submitted": submission.submitted,
+                    }))
+                    .collect::<Vec<_>>()))
+            }
+            OutboxCommand::Flush { attempts } => match outbox::flush(client, attempts).await? {
+                0 => {
+                    say!("Nothing is left queued");
+                    Ok(json!({ "queued": 0 }))
                 }
+                left => Err(CliError::new(
+                    Category::Queued,
+                    format!("{} submissions are still queued", left),
+                )),
+            },
+            OutboxCommand::Clear => {
+                outbox::clear()?;
+                say!("Dropped every queued submission");
+                Ok(Value::Null)
             }
-        }
+        },
+
+        Command::Keys { command } => keys(command),
     }
 }
 
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::Parser;
+
+    fn file(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("wispy-{}-{}", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    // Files of posts and votes skip blank lines, and a vote needs a timestamp and an emoji
+    #[test]
+    fn from_file() {
+        let posts = file("posts", "first\n\n  second  \n");
+        assert_eq!(read_lines(&posts).unwrap(), ["first", "second"]);
+
+        let votes = file("votes", "4870001 👍\n\n4870002\t👎\n");
+        let expected = [(4870001, "👍".to_string()), (4870002, "👎".to_string())];
+        assert_eq!(read_votes(&votes).unwrap(), expected);
+        for line in ["4870001", "yesterday 👍"] {
+            let err = read_votes(&file("votes", line)).unwrap_err();
+            assert_eq!(err.category, Category::Usage);
+            assert!(err.message.starts_with(&format!("Invalid vote `{}`", line)));
+        }
+
+        let missing = std::env::temp_dir().join("wispy-no-such-file");
+        assert_eq!(read_lines(&missing).unwrap_err().category, Category::Usage);
+    }
+
+    // A post takes a message or a file, and a batch of votes an emoji for its timestamps
+    #[test]
+    fn batch_arguments() {
+        let parse = |args: &[&str]| Cli::try_parse_from([&["wispy"], args].concat());
+        assert!(parse(&["post", "-m", "hi"]).is_ok());
+        assert!(parse(&["post", "--from-file", "posts.txt"]).is_ok());
+        assert!(parse(&["post"]).is_err());
+        assert!(parse(&["post", "-m", "hi", "--from-file", "posts.txt"]).is_err());
+        assert!(parse(&["post", "-a", "a.png", "--from-file", "posts.txt"]).is_err());
+
+        assert!(parse(&["vote-batch", "-t", "1", "-t", "2", "-e", "👍"]).is_ok());
+        assert!(parse(&["vote-batch", "--from-file", "votes.txt"]).is_ok());
+        assert!(parse(&["vote-batch", "-t", "1"]).is_err());
+        assert!(parse(&["vote-batch"]).is_err());
+    }
+
+    // A proof which fails is a proof error, and one past the timeout is given up on
+    #[test]
+    fn proof_timeout() {
+        let _ = PROVE_TIMEOUT.set(Duration::from_millis(200));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            assert_eq!(proved(tokio::spawn(async { Ok(4871) })).await.unwrap(), 4871);
+            let failed = tokio::spawn(async { Err::<(), _>(anyhow::anyhow!("no witness")) });
+            let err = proved(failed).await.unwrap_err();
+            assert_eq!((err.category, err.message.as_str()), (Category::Proof, "no witness"));
+
+            let slow = tokio::spawn(async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            });
+            let err = proved(slow).await.unwrap_err();
+            assert_eq!(err.category, Category::Timeout);
+            assert!(err.message.starts_with("Gave up on the proof after 0 seconds"));
+        });
+    }
+}
@@ -0,0 +1,361 @@
+//! The outbox: interactions waiting for the server.
+//!
+//! Proving an interaction moves the user to a new state, which only becomes the user once the
+//! server acknowledges the interaction; until then it is the pending user, next to the user file.
+//! An interaction the server cannot be reached for (or is too busy for) is queued in `outbox/` in
+//! the data directory, with its payload and the user it leads to, and retried with backoff before
+//! the next interaction or with `outbox flush`. Nothing new can be proved while the queue is not
+//! empty, as it would build on a user the server does not know yet.
+//!
+//! If the server rejects an interaction, it is dropped along with everything queued after it, and
+//! the user goes back to the last state the server acknowledged.
+
+use crate::config::{data_path, endpoint, settings};
+use crate::helpers::{pending_user_file, read_user};
+use crate::keystore as secrets;
+use crate::say;
+use crate::sync;
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The longest wait between two attempts.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Body {
+    Json(Value),
+    /// Raw bytes, in hex.
+    Binary(String),
+}
+
+impl Body {
+    pub fn json(payload: &impl Serialize) -> Self {
+        Body::Json(serde_json::to_value(payload).expect("payloads serialize"))
+    }
+
+    pub fn binary(bytes: &[u8]) -> Self {
+        Body::Binary(hex::encode(bytes))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Submission {
+    /// The endpoint, like `api/jsonrpc`.
+    pub endpoint: String,
+    pub body: Body,
+    /// When it was first submitted, in seconds since the Unix epoch.
+    pub submitted: u64,
+}
+
+/// What became of a submission.
+pub enum Outcome {
+    /// The server acknowledged it, responding with this.
+    Accepted(String),
+    /// The server could not be reached, so it was queued with this number.
+    Queued(u64),
+    /// The server rejected it, with this error.
+    Rejected(String),
+}
+
+enum Attempt {
+    Accepted(String),
+    Retry(String),
+    Rejected(String),
+}
+
+async fn send(client: &Client, submission: &Submission) -> Result<Attempt> {
+    let request = client.post(endpoint(&submission.endpoint));
+    let request = match &submission.body {
+        Body::Json(json) => request.json(json),
+        Body::Binary(bytes) => request.body(hex::decode(bytes)?),
+    };
+    let res = match request.send().await {
+        Ok(res) => res,
+        Err(e) => return Ok(Attempt::Retry(e.to_string())),
+    };
+    let status = res.status();
+    let text = res.text().await.unwrap_or_default();
+    Ok(if status.is_success() {
+        Attempt::Accepted(text)
+    } else if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        Attempt::Retry(format!("{}: {}", status, text))
+    } else {
+        Attempt::Rejected(format!("{}: {}", status, text))
+    })
+}
+
+pub(crate) fn outbox_dir() -> PathBuf {
+    data_path("outbox")
+}
+
+/// The payload (`json`) or user (`user`) file of a queued submission.
+fn entry_path(seq: u64, ext: &str) -> PathBuf {
+    outbox_dir().join(format!("{:06}.{}", seq, ext))
+}
+
+/// The numbers of the queued submissions, oldest first.
+fn queued() -> io::Result<Vec<u64>> {
+    let entries = match fs::read_dir(outbox_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut seqs = vec![];
+    for entry in entries {
+        let name = entry?.file_name();
+        if let Some(seq) = name
+            .to_str()
+            .and_then(|n| n.strip_suffix(".json"))
+            .and_then(|n| n.parse().ok())
+        {
+            seqs.push(seq);
+        }
+    }
+    seqs.sort_unstable();
+    Ok(seqs)
+}
+
+fn read_entry(seq: u64) -> Result<Submission> {
+    let bytes = secrets::read(&entry_path(seq, "json"))?;
+    serde_json::from_slice(&bytes).with_context(|| format!("invalid queued submission {}", seq))
+}
+
+fn remove_entry(seq: u64) -> io::Result<()> {
+    for ext in ["json", "user"] {
+        match fs::remove_file(entry_path(seq, ext)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Make `user` (the pending user, or a queued one) the user once the server acknowledges it, and
+/// publish the change for other devices.
+fn acknowledge(user: &Path) -> Result<()> {
+    let parent = read_user(&settings().user_file).ok();
+    secrets::rename(user, &settings().user_file)?;
+    if let Some(parent) = parent {
+        if let Err(e) = sync::publish(&parent) {
+            eprintln!(
+                "Warning: failed to publish the change for other devices: {:#}",
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+fn discard_pending() -> io::Result<()> {
+    match fs::remove_file(pending_user_file()) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// The queued submissions, oldest first.
+pub fn list() -> Result<Vec<(u64, Submission)>> {
+    queued()?
+        .into_iter()
+        .map(|seq| Ok((seq, read_entry(seq)?)))
+        .collect()
+}
+
+/// Drop every queued submission and the pending user, going back to the last user the server
+/// acknowledged.
+pub fn clear() -> io::Result<()> {
+    for seq in queued()? {
+        remove_entry(seq)?;
+    }
+    discard_pending()
+}
+
+/// Submit an interaction to `endpoint`, making the pending user the user once the server
+/// acknowledges it, and queueing it if the server cannot be reached.
+pub async fn submit(client: &Client, endpoint: &str, body: Body) -> Result<Outcome> {
+    let submission = Submission {
+        endpoint: endpoint.to_string(),
+        body,
+        submitted: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    };
+    match send(client, &submission).await? {
+        Attempt::Accepted(text) => {
+            let pending = pending_user_file();
+            if pending.exists() {
+                acknowledge(&pending)?;
+            }
+            Ok(Outcome::Accepted(text))
+        }
+        Attempt::Retry(e) => {
+            eprintln!("Failed to submit to {}: {}", endpoint, e);
+            fs::create_dir_all(outbox_dir())?;
+            let seq = queued()?.last().map_or(1, |seq| seq + 1);
+            secrets::copy(&pending_user_file(), &entry_path(seq, "user"))
+                .context("there is no pending user to queue")?;
+            secrets::write(&entry_path(seq, "json"), &serde_json::to_vec(&submission)?)?;
+            Ok(Outcome::Queued(seq))
+        }
+        Attempt::Rejected(e) => {
+            discard_pending()?;
+            Ok(Outcome::Rejected(e))
+        }
+    }
+}
+
+/// Submit the queued submissions in order, trying each up to `attempts` times with growing
+/// waits in between, until the server cannot be reached. Returns how many are still queued.
+pub async fn flush(client: &Client, attempts: u32) -> Result<usize> {
+    let seqs = queued()?;
+    for (i, &seq) in seqs.iter().enumerate() {
+        let submission = read_entry(seq)?;
+        let mut delay = Duration::from_secs(1);
+        let mut attempt = 1;
+        loop {
+            match send(client, &submission).await? {
+                Attempt::Accepted(text) => {
+                    say!("Queued submission {} accepted: {}", seq, text);
+                    acknowledge(&entry_path(seq, "user"))?;
+                    remove_entry(seq)?;
+                    if i + 1 == seqs.len() {
+                        // The pending user is the one the last submission leads to
+                        discard_pending()?;
+                    }
+                    break;
+                }
+                Attempt::Retry(e) if attempt < attempts => {
+                    eprintln!(
+                        "Queued submission {} failed ({}), retrying in {}s",
+                        seq,
+                        e,
+                        delay.as_secs()
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(MAX_DELAY);
+                    attempt += 1;
+                }
+                Attempt::Retry(e) => {
+                    eprintln!("Queued submission {} failed: {}", seq, e);
+                    return Ok(seqs.len() - i);
+                }
+                Attempt::Rejected(e) => {
+                    eprintln!(
+                        "Queued submission {} was rejected ({}), dropping it and the {} queued after it",
+                        seq,
+                        e,
+                        seqs.len() - i - 1
+                    );
+                    clear()?;
+                    return Ok(0);
+                }
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// Get ready to prove a new interaction: submit whatever is queued, and drop a pending user left
+/// by an interaction which was never submitted. Fails if anything is still queued.
+pub async fn prepare(client: &Client, attempts: u32) -> Result<()> {
+    let left = flush(client, attempts).await?;
+    if left > 0 {
+        bail!(
+            "{} submissions are still queued, and the server has to accept them before anything new is proved (see `outbox list`)",
+            left
+        );
+    }
+    if pending_user_file().exists() {
+        eprintln!("Warning: dropping the user of an interaction which was never submitted");
+        discard_pending()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+    use axum::http::StatusCode as Status;
+
+    fn answer(path: &str, status: Status) {
+        testing::route(path, move |_| (status, b"done".to_vec()));
+    }
+
+    fn submit_pending(endpoint: &str, user: &[u8]) -> Outcome {
+        fs::write(pending_user_file(), user).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime
+            .block_on(submit(&Client::new(), endpoint, Body::binary(b"proof")))
+            .unwrap()
+    }
+
+    // The pending user becomes the user once the server accepts the interaction, and is dropped
+    // if it rejects it
+    #[test]
+    fn submit_outcomes() {
+        let _data = testing::data_dir();
+        answer("api/outbox-accept", Status::OK);
+        answer("api/outbox-reject", Status::BAD_REQUEST);
+
+        let outcome = submit_pending("api/outbox-accept", b"accepted");
+        assert!(matches!(outcome, Outcome::Accepted(text) if text == "done"));
+        assert_eq!(fs::read(&settings().user_file).unwrap(), b"accepted");
+        assert!(!pending_user_file().exists());
+
+        let outcome = submit_pending("api/outbox-reject", b"rejected");
+        assert!(matches!(outcome, Outcome::Rejected(e) if e.starts_with("400")));
+        assert_eq!(fs::read(&settings().user_file).unwrap(), b"accepted");
+        assert!(!pending_user_file().exists());
+        assert!(list().unwrap().is_empty());
+    }
+
+    // Submissions the server is too busy for are queued, and submitted in order later
+    #[test]
+    fn queue_and_flush() {
+        let _data = testing::data_dir();
+        answer("api/outbox-busy", Status::SERVICE_UNAVAILABLE);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let client = Client::new();
+
+        assert!(matches!(submit_pending("api/outbox-busy", b"first"), Outcome::Queued(1)));
+        assert!(matches!(submit_pending("api/outbox-busy", b"second"), Outcome::Queued(2)));
+        let queued = list().unwrap();
+        assert_eq!(queued.len(), 2);
+        assert_eq!(queued[0].1.endpoint, "api/outbox-busy");
+
+        // Nothing new is proved while the server has yet to accept them
+        let err = runtime.block_on(prepare(&client, 1)).unwrap_err();
+        assert!(err.to_string().contains("2 submissions are still queued"));
+
+        answer("api/outbox-busy", Status::OK);
+        assert_eq!(runtime.block_on(flush(&client, 1)).unwrap(), 0);
+        assert_eq!(fs::read(&settings().user_file).unwrap(), b"second");
+        assert!(!pending_user_file().exists());
+        assert!(list().unwrap().is_empty());
+    }
+
+    // A rejected submission is dropped along with everything queued after it
+    #[test]
+    fn rejected_drops_queue() {
+        let _data = testing::data_dir();
+        answer("api/outbox-later", Status::TOO_MANY_REQUESTS);
+        assert!(matches!(submit_pending("api/outbox-later", b"first"), Outcome::Queued(1)));
+        assert!(matches!(submit_pending("api/outbox-later", b"second"), Outcome::Queued(2)));
+
+        answer("api/outbox-later", Status::FORBIDDEN);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        assert_eq!(runtime.block_on(flush(&Client::new(), 1)).unwrap(), 0);
+        assert!(list().unwrap().is_empty());
+        assert!(!pending_user_file().exists());
+        assert!(!settings().user_file.exists());
+    }
+}
@@ -0,0 +1,208 @@
+//! Output for scripts.
+//!
+//! By default, commands print what they do as text. With `--output json`, a command prints a
+//! single JSON object on stdout once it is done, `{"ok": true, "command": ..., "result": ...}` or
+//! `{"ok": false, "command": ..., "error": {"category": ..., "message": ...}}`, and its progress
+//! messages go to stderr instead. Either way, the exit code tells the [`Category`] of a failure.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{fmt, sync::OnceLock};
+
+static JSON: OnceLock<bool> = OnceLock::new();
+
+/// Set whether the output is JSON. This must be called once on startup, before anything is
+/// printed.
+pub fn init(json: bool) {
+    let _ = JSON.set(json);
+}
+
+/// Whether the output is JSON, so only the result goes to stdout.
+pub fn is_json() -> bool {
+    JSON.get().copied().unwrap_or(false)
+}
+
+/// Print a progress message: to stdout, or to stderr with `--output json`.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::output::is_json() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// What went wrong, which decides the exit code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// Anything else, like a file which could not be read.
+    Failed,
+    /// Invalid arguments or configuration.
+    Usage,
+    /// The server could not be reached.
+    Network,
+    /// The server rejected the request.
+    Rejected,
+    /// The proof could not be made.
+    Proof,
+    /// The submission was queued in the outbox, to be retried.
+    Queued,
+    /// The user has to `scan` or `sync` first.
+    State,
+    /// A proof took longer than `--timeout`.
+    Timeout,
+}
+
+impl Category {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Category::Failed => 1,
+            Category::Usage => 2,
+            Category::Network => 3,
+            Category::Rejected => 4,
+            Category::Proof => 5,
+            Category::Queued => 6,
+            Category::State => 7,
+            Category::Timeout => 8,
+        }
+    }
+}
+
+/// A failed command.
+#[derive(Debug)]
+pub struct CliError {
+    pub category: Category,
+    pub message: String,
+    /// The error the server responded with, if it rejected the request.
+    pub server_error: Option<String>,
+}
+
+impl CliError {
+    pub fn new(category: Category, message: impl fmt::Display) -> Self {
+        Self {
+            category,
+            message: message.to_string(),
+            server_error: None,
+        }
+    }
+
+    /// The server rejected the request with `status` and `body`, which names the error if it is
+    /// one of the server's JSON errors.
+    pub fn rejected(status: impl fmt::Display, body: &str) -> Self {
+        let server_error = serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(str::to_string));
+        Self {
+            category: Category::Rejected,
+            message: format!("{}: {}", status, body),
+            server_error,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<anyhow::Error> for CliError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(Category::Failed, format!("{:#}", e))
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(e: std::io::Error) -> Self {
+        Self::new(Category::Failed, e)
+    }
+}
+
+impl From<reqwest::Error> for CliError {
+    fn from(e: reqwest::Error) -> Self {
+        let category = if e.is_decode() {
+            Category::Failed
+        } else {
+            Category::Network
+        };
+        Self::new(category, e)
+    }
+}
+
+/// Print the outcome of `command` and exit with its code.
+pub fn finish(command: &str, result: Result<Value, CliError>) -> ! {
+    let code = match &result {
+        Ok(_) => 0,
+        Err(e) => e.category.exit_code(),
+    };
+    if is_json() {
+        let out = match result {
+            Ok(result) => json!({ "ok": true, "command": command, "result": result }),
+            Err(e) => json!({
+                "ok": false,
+                "command": command,
+                "error": {
+                    "category": e.category,
+                    "message": e.message,
+                    "server_error": e.server_error,
+                },
+            }),
+        };
+        println!("{}", out);
+    } else if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+    std::process::exit(code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Context;
+
+    // Every category exits with its own code, and none with success
+    #[test]
+    fn exit_codes() {
+        let categories = [
+            Category::Failed,
+            Category::Usage,
+            Category::Network,
+            Category::Rejected,
+            Category::Proof,
+            Category::Queued,
+            Category::State,
+            Category::Timeout,
+        ];
+        let mut codes: Vec<_> = categories.iter().map(|c| c.exit_code()).collect();
+        codes.dedup();
+        assert_eq!(codes, (1..=8).collect::<Vec<_>>());
+        assert_eq!(serde_json::to_value(Category::Rejected).unwrap(), "rejected");
+    }
+
+    // A rejection names the server's error when the server sent one
+    #[test]
+    fn rejected() {
+        let e = CliError::rejected(409, r#"{"error":"already_voted","detail":"voted"}"#);
+        assert_eq!(e.category, Category::Rejected);
+        assert_eq!(e.server_error.as_deref(), Some("already_voted"));
+        assert!(e.to_string().starts_with("409: "));
+        assert!(CliError::rejected(502, "bad gateway").server_error.is_none());
+    }
+
+    // Errors keep their context, and an unreachable server is a network failure
+    #[test]
+    fn categories() {
+        let e: CliError = Err::<(), _>(anyhow::anyhow!("no such file"))
+            .context("failed to read the user")
+            .unwrap_err()
+            .into();
+        assert_eq!(e.category, Category::Failed);
+        assert_eq!(e.message, "failed to read the user: no such file");
+
+        let e: CliError = reqwest::blocking::get("http://127.0.0.1:1").unwrap_err().into();
+        assert_eq!(e.category, Category::Network);
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 /// CLI entry point for the anonymous group chat application.
@@ -8,25 +8,75 @@ pub struct Cli {
     #[arg(short, long, value_name = "FILE")]
     pub user: Option<PathBuf>,
 
+    /// Configuration file (defaults to ~/.config/wispy/config.toml, if it exists)
+    #[arg(long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Profile of the configuration to use
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Scan any callbacks called since the last scan before posting, rather than only warning
+    #[arg(long, global = true)]
+    pub auto_scan: bool,
+
+    /// Give up on a proof which takes longer than this, without submitting anything
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// How to print results: as text, or as one JSON object for scripts
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
     #[command(subcommand)]
     pub command: Command,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
 /// Enum of available CLI commands.
 #[derive(Subcommand)]
 pub enum Command {
-    /// View messages that have been posted
-    ViewPosts,
+    /// View messages that have been posted, newest first
+    ViewPosts {
+        /// Only posts sent to this group
+        #[arg(long, short = 'g')]
+        group_id: Option<String>,
+
+        /// Only posts in this pseudonymous thread
+        #[arg(long, short = 't')]
+        thread: Option<String>,
+
+        /// Posts per page
+        #[arg(long, short = 'n', default_value_t = 20)]
+        limit: usize,
+
+        /// Only posts sent before this timestamp, to continue from an earlier page
+        #[arg(long)]
+        before: Option<u64>,
+    },
 
     /// Send a message anonymously with a callback
     Post {
         /// Message content
-        #[arg(long, short = 'm')]
-        message: String,
+        #[arg(long, short = 'm', required_unless_present = "from_file")]
+        message: Option<String>,
 
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
+
+        /// File to attach (may be given several times)
+        #[arg(long, short = 'a', value_name = "FILE")]
+        attachment: Vec<PathBuf>,
+
+        /// Send every non-empty line of this file as a message, one after another
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["message", "attachment"])]
+        from_file: Option<PathBuf>,
     },
 
     /// Send a message using a pseudonym
@@ -35,9 +85,9 @@ pub enum Command {
         #[arg(long, short = 'm')]
         message: String,
 
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
 
         /// Index of pseudonym to use
         #[arg(long = "pseudo-idx", short = 'i')]
@@ -50,9 +100,9 @@ pub enum Command {
         #[arg(long, short = 'm')]
         message: String,
 
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
 
         /// Thread ID for the rate-limited pseudonym
         #[arg(long, short = 't')]
@@ -63,7 +113,8 @@ pub enum Command {
         pseudo_idx: usize,
     },
 
-    /// Generate a new pseudonym
+    /// Generate a new pseudonym (see `pseudo new`)
+    #[command(hide = true)]
     GenPseudo,
 
     /// Send a scan interaction
@@ -71,9 +122,9 @@ pub enum Command {
 
     /// Submit a vote for a poll
     Vote {
-        /// Group ID of the poll message
+        /// Group ID of the poll message (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
 
         /// Timestamp of the poll message
         #[arg(long, short = 't')]
@@ -84,11 +135,30 @@ pub enum Command {
         emoji: String,
     },
 
+    /// Vote in several polls at once, proving the votes in parallel
+    VoteBatch {
+        /// Group ID of the poll messages (defaults to the configured group)
+        #[arg(long, short = 'g')]
+        group_id: Option<String>,
+
+        /// Timestamp of a poll message (may be given several times)
+        #[arg(long, short = 't', requires = "emoji")]
+        timestamp: Vec<u64>,
+
+        /// Emoji representing your vote in every poll given with -t
+        #[arg(long, short = 'e')]
+        emoji: Option<String>,
+
+        /// File of votes, one `<timestamp> <emoji>` per line
+        #[arg(long, value_name = "FILE", required_unless_present = "timestamp")]
+        from_file: Option<PathBuf>,
+    },
+
     /// Count votes for a poll or ban poll
     CountVotes {
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
 
         /// Timestamp of the poll message
         #[arg(long, short = 't')]
@@ -101,9 +171,9 @@ pub enum Command {
         #[arg(long, short = 'm')]
         message: Option<String>,
 
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
 
         /// Timestamp of the message to ban
         #[arg(long, short = 't')]
@@ -127,14 +197,40 @@ pub enum Command {
     /// Join a group (e.g., fetch callback object)
     Join,
 
-    /// Print pseudonym index information
+    /// Print pseudonym index information (see `pseudo list`)
+    #[command(hide = true)]
     PseudoIndex,
 
+    /// Manage your pseudonyms
+    Pseudo {
+        #[command(subcommand)]
+        command: PseudoCommand,
+    },
+
+    /// Send a direct message to a pseudonym, or manage receiving them
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Dm {
+        /// The pseudonym to write to, by the name it is shown with
+        #[arg(long, required = true)]
+        to: Option<String>,
+
+        /// Message content
+        #[arg(long, short = 'm', required = true)]
+        message: Option<String>,
+
+        /// Your pseudonym to write as, by index or label
+        #[arg(long, short = 'i', default_value = "1")]
+        pseudonym: String,
+
+        #[command(subcommand)]
+        command: Option<DmCommand>,
+    },
+
     /// React to a message with an emoji
     Reaction {
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
 
         /// Emoji to react with
         #[arg(long, short = 'e')]
@@ -147,9 +243,9 @@ pub enum Command {
 
     /// Reply to a message
     Reply {
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
 
         /// Message content
         #[arg(long, short = 'm')]
@@ -162,9 +258,9 @@ pub enum Command {
 
     /// Reply to a message using a pseudonym
     ReplyPseudo {
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
 
         /// Message content
         #[arg(long, short = 'm')]
@@ -179,8 +275,12 @@ pub enum Command {
         pseudo_idx: usize,
     },
 
-    /// Fetch all poll contexts from the server
-    GetContexts,
+    /// Fetch the thread contexts created since the last fetch, or search them
+    GetContexts {
+        /// Only list the threads whose name contains this, without saving them
+        #[arg(long, short = 's')]
+        search: Option<String>,
+    },
 
     /// Start a new poll
     Poll {
@@ -188,24 +288,27 @@ pub enum Command {
         #[arg(long, short = 'm')]
         message: String,
 
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
     },
 
-    /// Claim authorship of a message using two pseudonyms
+    /// Prove that several pseudonyms belong to the same user
     Authorship {
-        /// First pseudonym index
-        #[arg(long = "pseudo-idx1", short = 'i')]
-        pseudo_idx1: usize,
+        /// The pseudonyms, by index or label; as many as the server's keys link
+        pseudonyms: Vec<String>,
+
+        /// First pseudonym index, before pseudonyms could be listed
+        #[arg(long = "pseudo-idx1", short = 'i', hide = true)]
+        pseudo_idx1: Option<usize>,
 
-        /// Second pseudonym index
-        #[arg(long = "pseudo-idx2", short = 'j')]
-        pseudo_idx2: usize,
+        /// Second pseudonym index, before pseudonyms could be listed
+        #[arg(long = "pseudo-idx2", short = 'j', hide = true)]
+        pseudo_idx2: Option<usize>,
 
-        /// Group ID
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
     },
 
     /// Claim a badge under a pseudonym
@@ -218,9 +321,24 @@ pub enum Command {
         #[arg(long, short = 'b')]
         claimed: String,
 
-        /// Group ID
+        /// Group ID (defaults to the configured group)
+        #[arg(long, short = 'g')]
+        group_id: Option<String>,
+    },
+
+    /// Prove a pseudonym has at least some reputation, without revealing it
+    ProveRep {
+        /// The reputation to prove the user has at least
+        #[arg(long)]
+        at_least: u64,
+
+        /// The pseudonym, by index or label
+        #[arg(long, short = 'i', default_value = "1")]
+        pseudonym: String,
+
+        /// Group ID (defaults to the configured group)
         #[arg(long, short = 'g')]
-        group_id: String,
+        group_id: Option<String>,
     },
 
     /// Start a new thread context
@@ -231,6 +349,146 @@ pub enum Command {
     },
 
     /// Generate a new pseudonym (legacy command, for compatibility)
-    Pseudonym,
+    Pseudonym {
+        /// Group ID whose bot account gets the pseudonym (defaults to the configured group, or
+        /// the server's default bot)
+        #[arg(long, short = 'g')]
+        group_id: Option<String>,
+    },
+
+    /// Keep the proving keys and user loaded, and prove for other commands until stopped
+    Daemon,
+
+    /// Back up the user, pseudonym log and thread contexts into a single file
+    Export {
+        /// File to write the backup to
+        #[arg(long, short = 'o', value_name = "FILE")]
+        out: PathBuf,
+
+        /// Encrypt the backup with a passphrase
+        #[arg(long)]
+        encrypt: bool,
+    },
+
+    /// Restore a backup made with `export`
+    Import {
+        /// Backup file
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Replace the existing user
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Bring the user up to date with the changes other devices published in the sync directory
+    Sync,
+
+    /// Show the user's commitment, callbacks, reputation, badges and cached proving keys
+    Status,
+
+    /// Manage submissions queued while the server could not be reached
+    Outbox {
+        #[command(subcommand)]
+        command: OutboxCommand,
+    },
+
+    /// Manage the encrypted keystore of the user file and pseudonyms
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+}
+
+/// Outbox commands.
+#[derive(Subcommand)]
+pub enum OutboxCommand {
+    /// List the queued submissions
+    List,
+
+    /// Submit the queued submissions, retrying with backoff
+    Flush {
+        /// Times to try each submission
+        #[arg(long, default_value_t = 8)]
+        attempts: u32,
+    },
+
+    /// Drop the queued submissions, going back to the last user the server acknowledged
+    Clear,
+}
+
+/// Direct message commands. Pseudonyms are given by their index or their label.
+#[derive(Subcommand)]
+pub enum DmCommand {
+    /// Receive the direct messages to a pseudonym at a Signal account
+    Register {
+        /// The Signal account (phone number) to deliver them to
+        #[arg(long)]
+        signal: String,
+
+        #[arg(long, short = 'i', default_value = "1")]
+        pseudonym: String,
+    },
+
+    /// Stop receiving direct messages to a pseudonym
+    Unregister {
+        #[arg(long, short = 'i', default_value = "1")]
+        pseudonym: String,
+    },
+}
+
+/// Pseudonym commands. Pseudonyms are given by their index or their label.
+#[derive(Subcommand)]
+pub enum PseudoCommand {
+    /// Make a new pseudonym
+    New {
+        /// A label to refer to the pseudonym by
+        #[arg(long, short = 'l')]
+        label: Option<String>,
+    },
+
+    /// List the pseudonyms, by the names they are shown as
+    List {
+        /// Include retired pseudonyms
+        #[arg(long, short = 'a')]
+        all: bool,
+    },
+
+    /// Stop posting with a pseudonym. It is kept, to prove authorship of its posts
+    Retire { pseudonym: String },
+
+    /// Link display metadata, such as an avatar or a bio, to a pseudonym
+    Link {
+        pseudonym: String,
+        key: String,
+        value: String,
+    },
+
+    /// Remove display metadata from a pseudonym
+    Unlink { pseudonym: String, key: String },
+}
+
+/// Keystore commands.
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// Encrypt the user file and pseudonyms with a new passphrase
+    Init {
+        /// Minutes to stay unlocked for afterwards
+        #[arg(long, default_value_t = 30)]
+        minutes: u64,
+    },
+
+    /// Unlock the keystore for a while
+    Unlock {
+        /// Minutes to stay unlocked for
+        #[arg(long, default_value_t = 30)]
+        minutes: u64,
+    },
+
+    /// Lock the keystore
+    Lock,
+
+    /// Show whether the keystore is locked
+    Status,
 }
 
@@ -0,0 +1,237 @@
+//! The pseudonyms of the user.
+//!
+//! Each pseudonym is a context drawn at random and the pseudonym the user's key claims in it. They
+//! are kept in `pseudonyms.json` in the data directory (encrypted by the keystore, if there is
+//! one), numbered from 1 in the order they were made, with an optional label, display metadata
+//! such as an avatar or a bio, and whether the user has retired them. A retired pseudonym is kept,
+//! so old posts can still be proved to be the user's, but is not posted with any more.
+//!
+//! Clients before the store kept the pseudonyms one per line in `pseudo_log.jsonl`, which is
+//! read into the store the first time it is loaded.
+
+use crate::config::data_path;
+use crate::helpers::string_to_f;
+use crate::keystore as secrets;
+use anyhow::{bail, Context, Result};
+use ark_ff::PrimeField;
+use common::{zk::petname, F};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The pseudonym log of older clients, one pseudonym per line, in the data directory.
+pub(crate) const PSEUDO_LOG: &str = "pseudo_log.jsonl";
+
+/// Where the pseudonyms are kept.
+pub fn pseudonyms_path() -> PathBuf {
+    data_path("pseudonyms.json")
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Pseudonym {
+    /// The number of the pseudonym, from 1, which it is referred to by.
+    pub index: usize,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// The context of the pseudonym, in decimal.
+    pub context: String,
+    /// The pseudonym claimed in the context, in decimal.
+    pub claimed: String,
+    /// When the pseudonym was made, in seconds since the Unix epoch, if it is known.
+    #[serde(default)]
+    pub created: Option<u64>,
+    #[serde(default)]
+    pub retired: bool,
+    /// Display metadata, such as an avatar or a bio, by key.
+    #[serde(default)]
+    pub links: BTreeMap<String, String>,
+}
+
+impl Pseudonym {
+    pub fn context(&self) -> F {
+        string_to_f(&self.context)
+    }
+
+    pub fn claimed(&self) -> F {
+        string_to_f(&self.claimed)
+    }
+
+    /// The petname the server shows the pseudonym as.
+    pub fn name(&self) -> String {
+        petname(self.claimed()).unwrap_or_else(|| "anonymous user".to_string())
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Pseudonyms {
+    pub pseudonyms: Vec<Pseudonym>,
+}
+
+impl Pseudonyms {
+    /// Load the pseudonyms, from the log of an older client if there is no store yet.
+    pub fn load() -> Result<Self> {
+        match secrets::read(&pseudonyms_path()) {
+            Ok(store) => serde_json::from_slice(&store).context("invalid pseudonym store"),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::from_log(),
+            Err(e) => Err(e).context("failed to read the pseudonyms"),
+        }
+    }
+
+    fn from_log() -> Result<Self> {
+        let log = match secrets::read(&data_path(PSEUDO_LOG)) {
+            Ok(log) => log,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).context("failed to read the pseudonym log"),
+        };
+        Ok(Self::parse_log(&String::from_utf8_lossy(&log)))
+    }
+
+    /// Read the pseudonym log of an older client, skipping malformed lines.
+    pub fn parse_log(log: &str) -> Self {
+        let mut pseudonyms = vec![];
+        for (i, line) in log.lines().enumerate() {
+            let json: Value = match serde_json::from_str(line) {
+                Ok(json) => json,
+                Err(_) => {
+                    eprintln!("Skipping malformed line {} of {}", i + 1, PSEUDO_LOG);
+                    continue;
+                }
+            };
+            let (Some(claimed), Some(context)) =
+                (json["claimed"].as_str(), json["context"].as_str())
+            else {
+                eprintln!(
+                    "Line {} of {} is missing 'claimed' or 'context'",
+                    i + 1,
+                    PSEUDO_LOG
+                );
+                continue;
+            };
+            pseudonyms.push(Pseudonym {
+                index: i + 1,
+                label: None,
+                context: context.to_string(),
+                claimed: claimed.to_string(),
+                created: None,
+                retired: false,
+                links: BTreeMap::new(),
+            });
+        }
+        Self { pseudonyms }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        secrets::write(&pseudonyms_path(), &serde_json::to_vec_pretty(self)?)
+            .context("failed to write the pseudonyms")
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Pseudonym> {
+        self.pseudonyms.iter().find(|p| p.index == index)
+    }
+
+    /// The pseudonym numbered or labelled `name`.
+    pub fn find(&self, name: &str) -> Result<&Pseudonym> {
+        let index = self.position(name)?;
+        Ok(&self.pseudonyms[index])
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Result<&mut Pseudonym> {
+        let index = self.position(name)?;
+        Ok(&mut self.pseudonyms[index])
+    }
+
+    fn position(&self, name: &str) -> Result<usize> {
+        let found = match name.parse::<usize>() {
+            Ok(index) => self.pseudonyms.iter().position(|p| p.index == index),
+            Err(_) => self
+                .pseudonyms
+                .iter()
+                .position(|p| p.label.as_deref() == Some(name)),
+        };
+        found.with_context(|| format!("no pseudonym {}", name))
+    }
+
+    /// Add a pseudonym, numbered after the others.
+    pub fn add(&mut self, context: F, claimed: F, label: Option<String>) -> Result<&Pseudonym> {
+        if let Some(label) = &label {
+            if label.parse::<usize>().is_ok() {
+                bail!("a label cannot be a number, which would be taken for an index");
+            }
+            if self.find(label).is_ok() {
+                bail!("a pseudonym is already labelled {}", label);
+            }
+        }
+        let index = self.pseudonyms.iter().map(|p| p.index).max().unwrap_or(0) + 1;
+        self.pseudonyms.push(Pseudonym {
+            index,
+            label,
+            context: context.into_bigint().to_string(),
+            claimed: claimed.into_bigint().to_string(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs()),
+            retired: false,
+            links: BTreeMap::new(),
+        });
+        Ok(self.pseudonyms.last().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+    use std::fs;
+
+    // Pseudonyms are numbered in order, and found by number or by a label which is no number
+    #[test]
+    fn add_and_find() {
+        let mut pseudonyms = Pseudonyms::default();
+        pseudonyms.add(F::from(1), F::from(2), None).unwrap();
+        let added = pseudonyms.add(F::from(3), F::from(4), Some("main".to_string())).unwrap();
+        assert_eq!((added.index, added.claimed()), (2, F::from(4)));
+        assert_eq!(added.name(), petname(F::from(4)).unwrap());
+
+        let err = pseudonyms.add(F::from(5), F::from(6), Some("main".to_string()));
+        assert_eq!(err.err().unwrap().to_string(), "a pseudonym is already labelled main");
+        let err = pseudonyms.add(F::from(5), F::from(6), Some("7".to_string()));
+        assert!(err.is_err());
+
+        assert_eq!(pseudonyms.find("1").unwrap().context(), F::from(1));
+        assert_eq!(pseudonyms.find("main").unwrap().index, 2);
+        assert_eq!(pseudonyms.find("3").err().unwrap().to_string(), "no pseudonym 3");
+        pseudonyms.find_mut("main").unwrap().retired = true;
+        assert!(pseudonyms.get(2).unwrap().retired);
+    }
+
+    // The log of an older client is read into the store, numbered by line, without its
+    // malformed lines
+    #[test]
+    fn from_log() {
+        let _data = testing::data_dir();
+        let log = [
+            r#"{"claimed": "2", "context": "1"}"#,
+            "not json",
+            r#"{"claimed": "4"}"#,
+            r#"{"claimed": "6", "context": "5"}"#,
+        ];
+        fs::create_dir_all(data_path("")).unwrap();
+        fs::write(data_path(PSEUDO_LOG), log.join("\n")).unwrap();
+
+        let mut pseudonyms = Pseudonyms::load().unwrap();
+        let indices: Vec<_> = pseudonyms.pseudonyms.iter().map(|p| p.index).collect();
+        assert_eq!(indices, [1, 4]);
+        assert_eq!(pseudonyms.get(4).unwrap().claimed(), F::from(6));
+        assert_eq!(pseudonyms.add(F::from(7), F::from(8), None).unwrap().index, 5);
+
+        pseudonyms.save().unwrap();
+        fs::remove_file(data_path(PSEUDO_LOG)).unwrap();
+        assert_eq!(Pseudonyms::load().unwrap().pseudonyms.len(), 3);
+    }
+}
@@ -0,0 +1,186 @@
+//! The health of the user, for `status`: where it stands on the user bulletin, the callbacks it
+//! holds and which of them have been called, what a scan will do to it, and whether the proving
+//! keys it needs are cached.
+
+use crate::bul::BulNet;
+use crate::config::{data_path, server, settings};
+use crate::helpers::{
+    called_callbacks, is_proving_key_file, pending_user_file, proving_key_digests,
+    proving_key_file, read_user, ScanStatus,
+};
+use crate::outbox;
+use anyhow::{Context, Result};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalSerialize, Compress};
+use common::{
+    zk::{callback_effect, CallbackEffect, MsgUser, BADGE_HELD},
+    F,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, fs};
+use zk_callbacks::{generic::user::User, impls::hash::Poseidon};
+
+/// Whether a proving key is cached in the data directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCache {
+    /// The key the server has now is cached.
+    Fresh,
+    /// An older key is cached, and the next proof downloads the new one.
+    Stale,
+    /// No key is cached.
+    Missing,
+}
+
+/// A callback of the user which has been called.
+#[derive(Serialize)]
+pub struct CalledCallback {
+    pub index: usize,
+    pub effect: CallbackEffect,
+}
+
+#[derive(Serialize)]
+pub struct Status {
+    /// The commitment of the user the server last acknowledged, in hex.
+    pub commit: String,
+    /// Whether that commitment is on the user bulletin.
+    pub on_bulletin: bool,
+    /// Whether the bulletin has moved the user on from it, through another device.
+    pub superseded: bool,
+    /// Whether an interaction has been proved but not yet acknowledged.
+    pub pending: bool,
+    /// How many submissions are queued in the outbox.
+    pub queued: usize,
+    /// How many callbacks the user holds.
+    pub outstanding: usize,
+    pub called: Vec<CalledCallback>,
+    pub scan: ScanStatus,
+    pub reputation: u64,
+    pub banned: bool,
+    /// Which badge slots, numbered from 1, hold a badge.
+    pub badges: Vec<u64>,
+    /// How each proving key the server reports is cached, by name. Empty if the server does not
+    /// report key digests.
+    pub keys: BTreeMap<String, KeyCache>,
+}
+
+fn small(f: F) -> u64 {
+    f.into_bigint().as_ref()[0]
+}
+
+fn key_cache(name: &str, digest: &str) -> KeyCache {
+    let dir = data_path("keys");
+    if dir.join(proving_key_file(name, Some(digest))).exists() {
+        return KeyCache::Fresh;
+    }
+    let cached = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|entry| is_proving_key_file(name, &entry.file_name().to_string_lossy()));
+    if cached {
+        KeyCache::Stale
+    } else {
+        KeyCache::Missing
+    }
+}
+
+/// Gather the status of the user, from the user file and the bulletins.
+pub fn status() -> Result<Status> {
+    let user: User<F, MsgUser> =
+        read_user(&settings().user_file).context("failed to read the user")?;
+    let bul = BulNet::new(server());
+    let entries = bul
+        .user_entries()
+        .map_err(|e| anyhow::anyhow!("failed to fetch the user bulletin: {}", e))?;
+    let com = user.commit::<Poseidon<2>>();
+    let mut commit = vec![];
+    com.serialize_with_mode(&mut commit, Compress::No)?;
+
+    let called: Vec<_> = called_callbacks(&user)?
+        .into_iter()
+        .map(|(index, argument)| CalledCallback {
+            index,
+            effect: callback_effect(argument),
+        })
+        .collect();
+    let badges = [user.data.badge1, user.data.badge2, user.data.badge3];
+
+    Ok(Status {
+        commit: hex::encode(commit),
+        on_bulletin: entries.iter().any(|(c, _)| *c == com),
+        superseded: entries.iter().any(|(_, nul)| *nul == user.zk_fields.nul),
+        pending: pending_user_file().exists(),
+        queued: outbox::list()?.len(),
+        outstanding: user.num_outstanding_callbacks(),
+        scan: ScanStatus::of(&user, called.len()),
+        called,
+        reputation: small(user.data.reputation),
+        banned: user.data.banned != F::from(0),
+        badges: (1..)
+            .zip(badges)
+            .filter(|(_, badge)| *badge == F::from(BADGE_HELD))
+            .map(|(i, _)| i)
+            .collect(),
+        keys: proving_key_digests(&bul)
+            .into_iter()
+            .map(|(name, digest)| {
+                let cache = key_cache(&name, &digest);
+                (name, cache)
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::helpers::save_struct;
+    use crate::helpers::test::{call, user_with_callbacks};
+    use crate::sync::test::bulletin;
+    use crate::testing;
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    // The status follows the user onto the bulletin, through its called callbacks and the keys
+    // cached for it
+    #[test]
+    fn user_status() {
+        let _data = testing::data_dir();
+        let mut user = user_with_callbacks(&[1, 2]);
+        user.data.reputation = F::from(7);
+        user.data.badge2 = F::from(BADGE_HELD);
+        save_struct(&user).unwrap();
+        call(&[2], 5);
+        bulletin(&[]);
+        testing::route("api/keys/meta", |_| {
+            let meta = json!([{ "name": "test", "proving_key_digest": "aaaa" }]);
+            (StatusCode::OK, meta.to_string().into_bytes())
+        });
+
+        let health = status().unwrap();
+        assert!(!health.on_bulletin && !health.superseded && !health.pending);
+        assert_eq!((health.queued, health.outstanding), (0, 2));
+        assert_eq!(health.called.len(), 1);
+        assert_eq!(health.called[0].index, 1);
+        assert_eq!(health.called[0].effect, CallbackEffect::Reputation(5));
+        assert_eq!((health.scan.called, health.scan.scans), (1, 2));
+        assert_eq!((health.reputation, health.banned), (7, false));
+        assert_eq!(health.badges, [2]);
+        assert_eq!(health.keys["test"], KeyCache::Missing);
+
+        bulletin(&[&user]);
+        let keys = data_path("keys");
+        fs::create_dir_all(&keys).unwrap();
+        fs::write(keys.join("test.pk"), []).unwrap();
+        let health = status().unwrap();
+        assert!(health.on_bulletin);
+        assert_eq!(health.keys["test"], KeyCache::Stale);
+        fs::write(keys.join(proving_key_file("test", Some("aaaa"))), []).unwrap();
+        assert_eq!(status().unwrap().keys["test"], KeyCache::Fresh);
+
+        testing::route("api/user/bulletin", |_| (StatusCode::BAD_GATEWAY, vec![]));
+        let err = status().err().unwrap();
+        assert!(err.to_string().starts_with("failed to fetch the user bulletin"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Sharing one user between devices.
+//!
+//! Every interaction consumes the nullifier of the user and puts a new commitment on the user
+//! bulletin, so two devices holding the same user must take turns: a device which interacts from
+//! a state the other one has already moved on from forks the user, and the server rejects it.
+//!
+//! With `sync_dir` set in the client configuration, to a directory the devices share (through a
+//! file sync service, say), every interaction the server acknowledges writes what it changed in
+//! the user there, named after the commitment it started from. `sync` follows those changes from
+//! the local user along the chain of commitments on the bulletin, and refuses any change which is
+//! not on it. Before every interaction, the client checks that the bulletin has not moved the
+//! user on from the local state, and asks for a `sync` if it has.
+//!
+//! Changes are encrypted with `WISPY_SYNC_PASSPHRASE` if it is set.
+
+use crate::bul::BulNet;
+use crate::config::{server, settings};
+use crate::helpers::{pending_user_file, read_user, save_struct};
+use crate::keystore as secrets;
+use crate::outbox;
+use anyhow::{bail, Context, Result};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::{zk::MsgUser, F};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use zk_callbacks::{
+    generic::{
+        object::{Com, Nul},
+        user::User,
+    },
+    impls::hash::Poseidon,
+};
+
+/// What an interaction changed in the user: the parts of the user it changed, serialized in hex.
+#[derive(Serialize, Deserialize)]
+pub struct StateDiff {
+    /// The commitment of the user the interaction started from, in hex.
+    pub parent: String,
+    /// The commitment of the user it led to, in hex.
+    pub commit: String,
+    /// When the change was published, in seconds since the Unix epoch.
+    pub created: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zk_fields: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    callbacks: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scan_index: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    in_progress_cbs: Option<String>,
+}
+
+fn to_hex(value: &impl CanonicalSerialize) -> String {
+    let mut bytes = vec![];
+    value
+        .serialize_with_mode(&mut bytes, Compress::No)
+        .expect("serializing to a vector does not fail");
+    hex::encode(bytes)
+}
+
+fn commit(user: &User<F, MsgUser>) -> Com<F> {
+    user.commit::<Poseidon<2>>()
+}
+
+/// The part of the user `new` changed from `old`, if any.
+fn changed<T: CanonicalSerialize + PartialEq>(old: &T, new: &T) -> Option<String> {
+    (old != new).then(|| to_hex(new))
+}
+
+fn apply_part<T: CanonicalDeserialize>(part: &Option<String>, field: &mut T) -> Result<()> {
+    if let Some(part) = part {
+        *field = T::deserialize_with_mode(&hex::decode(part)?[..], Compress::No, Validate::Yes)?;
+    }
+    Ok(())
+}
+
+impl StateDiff {
+    pub fn between(old: &User<F, MsgUser>, new: &User<F, MsgUser>) -> Self {
+        Self {
+            parent: to_hex(&commit(old)),
+            commit: to_hex(&commit(new)),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            data: changed(&old.data, &new.data),
+            zk_fields: changed(&old.zk_fields, &new.zk_fields),
+            callbacks: changed(&old.callbacks, &new.callbacks),
+            scan_index: changed(&old.scan_index, &new.scan_index),
+            in_progress_cbs: changed(&old.in_progress_cbs, &new.in_progress_cbs),
+        }
+    }
+
+    /// The user this change leads to from `user`, which has to be the user it started from.
+    pub fn apply(&self, user: &User<F, MsgUser>) -> Result<User<F, MsgUser>> {
+        if to_hex(&commit(user)) != self.parent {
+            bail!("the change does not start from this user");
+        }
+        let mut next = user.clone();
+        apply_part(&self.data, &mut next.data)?;
+        apply_part(&self.zk_fields, &mut next.zk_fields)?;
+        apply_part(&self.callbacks, &mut next.callbacks)?;
+        apply_part(&self.scan_index, &mut next.scan_index)?;
+        apply_part(&self.in_progress_cbs, &mut next.in_progress_cbs)?;
+        if to_hex(&commit(&next)) != self.commit {
+            bail!("the change does not lead to the user it claims to");
+        }
+        Ok(next)
+    }
+}
+
+fn sync_passphrase() -> Option<String> {
+    std::env::var("WISPY_SYNC_PASSPHRASE")
+        .ok()
+        .filter(|p| !p.is_empty())
+}
+
+/// The file the change from the user committed to as `parent` (in hex) is published in.
+fn diff_path(dir: &Path, parent: &str) -> PathBuf {
+    dir.join(format!("{}.diff", parent))
+}
+
+fn read_diff(path: &Path) -> Result<StateDiff> {
+    let bytes = fs::read(path)?;
+    let bytes = if bytes.starts_with(b"{") {
+        bytes
+    } else {
+        let passphrase =
+            sync_passphrase().context("the change is encrypted: set WISPY_SYNC_PASSPHRASE")?;
+        secrets::open(&passphrase, &bytes)?
+    };
+    serde_json::from_slice(&bytes).with_context(|| format!("invalid change {}", path.display()))
+}
+
+/// Publish the change from `parent` to the user the server has just acknowledged, if `sync_dir`
+/// is set.
+pub fn publish(parent: &User<F, MsgUser>) -> Result<()> {
+    let Some(dir) = &settings().sync_dir else {
+        return Ok(());
+    };
+    let user = read_user(&settings().user_file)?;
+    let diff = StateDiff::between(parent, &user);
+    let mut bytes = serde_json::to_vec(&diff)?;
+    if let Some(passphrase) = sync_passphrase() {
+        bytes = secrets::seal(&passphrase, &bytes)?;
+    }
+    fs::create_dir_all(dir)?;
+    let path = diff_path(dir, &diff.parent);
+    fs::write(&path, bytes).with_context(|| format!("failed to publish {}", path.display()))
+}
+
+/// The commitment which consumed the nullifier `nul` on the bulletin, if any.
+fn successor(entries: &[(Com<F>, Nul<F>)], nul: Nul<F>) -> Option<Com<F>> {
+    entries
+        .iter()
+        .find(|(_, consumed)| *consumed == nul)
+        .map(|(com, _)| *com)
+}
+
+fn user_entries() -> Result<Vec<(Com<F>, Nul<F>)>> {
+    BulNet::new(server())
+        .user_entries()
+        .map_err(|e| anyhow::anyhow!("failed to fetch the user bulletin: {}", e))
+}
+
+/// Whether the local user is the latest on the bulletin, rather than a state the bulletin has
+/// moved on from, which interacting from would fork.
+pub fn is_current() -> Result<bool> {
+    let user = read_user(&settings().user_file)?;
+    Ok(successor(&user_entries()?, user.zk_fields.nul).is_none())
+}
+
+/// Bring the local user up to date with the changes published by other devices, following the
+/// bulletin. Returns how many changes were applied.
+pub fn sync() -> Result<usize> {
+    let dir = settings()
+        .sync_dir
+        .clone()
+        .context("no sync_dir is set in the client config")?;
+    if pending_user_file().exists() || !outbox::list()?.is_empty() {
+        bail!("interactions are waiting for the server: run `outbox flush` (or `outbox clear`) before syncing");
+    }
+
+    let entries = user_entries()?;
+    let mut user = read_user(&settings().user_file)?;
+    let mut applied = 0;
+    while let Some(next) = successor(&entries, user.zk_fields.nul) {
+        let parent = to_hex(&commit(&user));
+        let path = diff_path(&dir, &parent);
+        let diff = read_diff(&path).with_context(|| {
+            format!(
+                "the bulletin has moved the user on from {}, but the change is not in {}",
+                &parent[..16],
+                dir.display()
+            )
+        })?;
+        if diff.commit != to_hex(&next) {
+            bail!(
+                "the change from {} is not the one on the bulletin, refusing to fork the user",
+                &parent[..16]
+            );
+        }
+        user = diff.apply(&user)?;
+        applied += 1;
+    }
+
+    if !entries.iter().any(|(com, _)| *com == commit(&user)) {
+        bail!("the user is not on the bulletin");
+    }
+    if applied > 0 {
+        save_struct(&user)?;
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::testing;
+    use axum::http::StatusCode;
+    use zk_callbacks::generic::bulletin::PublicUserBul;
+
+    /// A user, and the user an interaction leads to from it.
+    fn users() -> (User<F, MsgUser>, User<F, MsgUser>) {
+        let user = User::create(MsgUser::default(), &mut rand::thread_rng());
+        let mut next = user.clone();
+        next.data.reputation = F::from(3);
+        next.zk_fields.nul = F::from(4866);
+        next.zk_fields.com_rand = F::from(4867);
+        (user, next)
+    }
+
+    /// Put the users on the user bulletin, each consuming the nullifier of the one before.
+    pub(crate) fn bulletin(users: &[&User<F, MsgUser>]) {
+        type Witness = <BulNet as PublicUserBul<F, MsgUser>>::MembershipWitness;
+        let mut nul = F::from(0);
+        let mut entries = vec![];
+        for user in users {
+            entries.push((commit(user), nul, Vec::<Com<F>>::new(), Witness::default()));
+            nul = user.zk_fields.nul;
+        }
+        let mut bytes = vec![];
+        entries.serialize_with_mode(&mut bytes, Compress::No).unwrap();
+        testing::route("api/user/bulletin", move |_| (StatusCode::OK, bytes.clone()));
+    }
+
+    // A change only applies to the user it starts from, and has to lead where it claims to
+    #[test]
+    fn apply_diff() {
+        let (user, next) = users();
+        let diff = StateDiff::between(&user, &next);
+        assert!(diff.callbacks.is_none());
+        assert_eq!(commit(&diff.apply(&user).unwrap()), commit(&next));
+        assert!(diff.apply(&next).is_err());
+
+        let forged = StateDiff {
+            commit: to_hex(&commit(&user)),
+            ..StateDiff::between(&user, &next)
+        };
+        let err = forged.apply(&user).err().unwrap();
+        assert_eq!(err.to_string(), "the change does not lead to the user it claims to");
+    }
+
+    // Another device's changes are followed along the bulletin
+    #[test]
+    fn follow_bulletin() {
+        let _data = testing::data_dir();
+        let (user, next) = users();
+        save_struct(&next).unwrap();
+        publish(&user).unwrap();
+        save_struct(&user).unwrap();
+
+        bulletin(&[&user, &next]);
+        assert!(!is_current().unwrap());
+        assert_eq!(sync().unwrap(), 1);
+        assert_eq!(commit(&read_user(&settings().user_file).unwrap()), commit(&next));
+        assert!(is_current().unwrap());
+        assert_eq!(sync().unwrap(), 0);
+
+        // Nothing is synced while an interaction waits for the server
+        fs::write(pending_user_file(), b"pending").unwrap();
+        assert!(sync().is_err());
+    }
+
+    // A change other than the one on the bulletin, or a missing one, is refused
+    #[test]
+    fn refuse_fork() {
+        let _data = testing::data_dir();
+        let (user, next) = users();
+        save_struct(&user).unwrap();
+
+        bulletin(&[&user, &next]);
+        let err = sync().err().unwrap();
+        assert!(err.to_string().contains("but the change is not in"));
+
+        let mut other = next.clone();
+        other.data.reputation = F::from(5);
+        save_struct(&other).unwrap();
+        publish(&user).unwrap();
+        save_struct(&user).unwrap();
+        let err = sync().err().unwrap();
+        assert!(err.to_string().contains("refusing to fork the user"));
+
+        bulletin(&[&next]);
+        let err = sync().err().unwrap();
+        assert_eq!(err.to_string(), "the user is not on the bulletin");
+    }
+}
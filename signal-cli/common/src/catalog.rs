@@ -0,0 +1,48 @@
+//! Stable ids for the wispy interactions, callbacks, and predicates.
+//!
+//! The server stores interactions under these ids, and the callback ids are the method ids used
+//! in-circuit, so they must not change once keys have been generated.
+
+use zk_callbacks::generic::interaction::InteractionRegistry;
+
+/// The callback attached to every message.
+pub const STANDARD_CALLBACK: u64 = 0;
+
+/// Posting or replying to a message.
+pub const STANDARD: u64 = 332;
+/// Posting under a pseudonym.
+pub const STANDARD_PSEUDO: u64 = 333;
+/// Posting under a rate-limited pseudonym.
+pub const STANDARD_PSEUDO_RATE: u64 = 334;
+/// Scanning a callback.
+pub const SCAN: u64 = 442;
+
+/// Proving ownership of a pseudonym.
+pub const PSEUDONYM_PRED: u64 = 501;
+/// Proving two messages were written by the same pseudonym.
+pub const AUTHORSHIP_PRED: u64 = 502;
+/// Proving a badge is held.
+pub const BADGE_PRED: u64 = 503;
+/// Proving a pseudonym has at least some reputation.
+pub const REPUTATION_PRED: u64 = 504;
+
+/// The registry of every wispy interaction. The names are also the key store names.
+pub fn interaction_registry<VK>() -> InteractionRegistry<VK> {
+    let mut registry = InteractionRegistry::new();
+    for (id, name) in [
+        (STANDARD_CALLBACK, "standard_callback"),
+        (STANDARD, "standard"),
+        (STANDARD_PSEUDO, "standard_pseudo"),
+        (STANDARD_PSEUDO_RATE, "standard_pseudor"),
+        (SCAN, "scan"),
+        (PSEUDONYM_PRED, "pseudonym_pred"),
+        (AUTHORSHIP_PRED, "authorship_pred"),
+        (BADGE_PRED, "badge_pred"),
+        (REPUTATION_PRED, "reputation_pred"),
+    ] {
+        registry
+            .register(id, name)
+            .expect("interaction ids and names are unique");
+    }
+    registry
+}
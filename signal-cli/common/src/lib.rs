@@ -5,9 +5,13 @@ use ark_relations::r1cs::Result as ArkResult;
 use zk_callbacks::impls::{
     centralized::{
         crypto::NoSigOTP,
-        ds::sigstore::{GRSchnorrCallbackStore, GRSchnorrObjStore, GRSchnorrStore},
+        ds::{
+            groups::GRSchnorrGroupedStore,
+            sigstore::{GRSchnorrCallbackStore, GRSchnorrObjStore, GRSchnorrStore},
+        },
     },
     hash::Poseidon,
+    vrf::PoseidonVrf,
 };
 
 pub type F = ark_bn254::Fr;
@@ -22,14 +26,19 @@ pub type ArgsVar = FpVar<F>;
 pub type Store = GRSchnorrStore<Args>;
 pub type CStore = GRSchnorrCallbackStore<Args>;
 pub type OStore = GRSchnorrObjStore;
+pub type GStore = GRSchnorrGroupedStore<Args>;
 
 pub type Cr = NoSigOTP<F>;
 
 pub type H = Poseidon<2>;
 
+pub type Vrf = PoseidonVrf<H, F>;
+
 pub type Snark = Groth16<E>;
 
 pub type PK = ProvingKey<E>;
 pub type VK = VerifyingKey<E>;
 
+pub mod catalog;
+pub mod snapshot;
 pub mod zk;
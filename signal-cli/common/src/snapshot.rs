@@ -0,0 +1,208 @@
+//! A client-side copy of the server bulletins.
+//!
+//! Clients that cannot reach the server's stores directly (the browser, mobile apps) fetch the
+//! bulletins over HTTP and build a [`Snapshot`] from the response bytes, which can then be used as
+//! both the user and callback bulletin when proving.
+
+use ark_r1cs_std::prelude::Boolean;
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalDeserialize, Compress, SerializationError, Validate};
+use ark_snark::SNARK;
+use zk_callbacks::{
+    generic::{
+        bulletin::{PublicCallbackBul, PublicUserBul},
+        object::{Com, ComVar, Nul, Time, TimeVar},
+    },
+    impls::centralized::crypto::{FakeSigPubkey, FakeSigPubkeyVar},
+};
+
+use crate::{zk::MsgUser, Args, ArgsVar, CStore, Cr, OStore, F};
+
+fn de<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, SerializationError> {
+    T::deserialize_with_mode(bytes, Compress::No, Validate::Yes)
+}
+
+type UserWitness = <OStore as PublicUserBul<F, MsgUser>>::MembershipWitness;
+type UserPub = <OStore as PublicUserBul<F, MsgUser>>::MembershipPub;
+type CbWitness = <CStore as PublicCallbackBul<F, Args, Cr>>::MembershipWitness;
+type CbNmembWitness = <CStore as PublicCallbackBul<F, Args, Cr>>::NonMembershipWitness;
+type CbPub = <CStore as PublicCallbackBul<F, Args, Cr>>::MembershipPub;
+type CbNmembPub = <CStore as PublicCallbackBul<F, Args, Cr>>::NonMembershipPub;
+
+/// A snapshot of the server bulletins, built from the bytes served by the server.
+#[derive(Clone)]
+pub struct Snapshot {
+    user_pubkey: UserPub,
+    users: Vec<(Com<F>, Nul<F>, Vec<Com<F>>, UserWitness)>,
+    cb_pubkey: CbPub,
+    cb_nmemb_pubkey: CbNmembPub,
+    callbacks: Vec<(FakeSigPubkey<F>, Args, Time<F>, CbWitness)>,
+    nmemb: Vec<CbNmembWitness>,
+}
+
+impl Snapshot {
+    /// Takes the responses of `api/user/pubkey`, `api/user/bulletin`,
+    /// `api/callbacks/membership_pubkey`, `api/callbacks/nonmembership_pubkey`,
+    /// `api/callbacks/bulletin`, and `api/callbacks/nmemb_bulletin`.
+    pub fn new(
+        user_pubkey: &[u8],
+        user_bulletin: &[u8],
+        cb_pubkey: &[u8],
+        cb_nmemb_pubkey: &[u8],
+        cb_bulletin: &[u8],
+        cb_nmemb_bulletin: &[u8],
+    ) -> Result<Snapshot, SerializationError> {
+        Ok(Snapshot {
+            user_pubkey: de(user_pubkey)?,
+            users: de(user_bulletin)?,
+            cb_pubkey: de(cb_pubkey)?,
+            cb_nmemb_pubkey: de(cb_nmemb_pubkey)?,
+            callbacks: de(cb_bulletin)?,
+            nmemb: de(cb_nmemb_bulletin)?,
+        })
+    }
+}
+
+// The signed ranges of the non-membership witnesses have no `Debug`, so the bulletins are only
+// counted
+impl std::fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("users", &self.users.len())
+            .field("callbacks", &self.callbacks.len())
+            .field("nmemb", &self.nmemb.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PublicUserBul<F, MsgUser> for Snapshot {
+    type MembershipWitness = UserWitness;
+
+    type MembershipWitnessVar = <OStore as PublicUserBul<F, MsgUser>>::MembershipWitnessVar;
+
+    type MembershipPub = UserPub;
+
+    type MembershipPubVar = <OStore as PublicUserBul<F, MsgUser>>::MembershipPubVar;
+
+    fn verify_in<PubArgs, Snark: SNARK<F>, const NUMCBS: usize>(
+        &self,
+        object: Com<F>,
+        old_nul: Nul<F>,
+        cb_com_list: [Com<F>; NUMCBS],
+        _args: PubArgs,
+        _proof: Snark::Proof,
+        _memb_data: Self::MembershipPub,
+        _verif_key: &Snark::VerifyingKey,
+    ) -> bool {
+        self.users
+            .iter()
+            .any(|(c, n, l, _)| *c == object && *n == old_nul && *l == cb_com_list)
+    }
+
+    fn get_membership_data(
+        &self,
+        object: Com<F>,
+    ) -> Option<(Self::MembershipPub, Self::MembershipWitness)> {
+        self.users
+            .iter()
+            .find(|(c, _, _, _)| *c == object)
+            .map(|(_, _, _, s)| (self.user_pubkey.clone(), s.clone()))
+    }
+
+    fn enforce_membership_of(
+        data_var: ComVar<F>,
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <OStore as PublicUserBul<F, MsgUser>>::enforce_membership_of(
+            data_var,
+            extra_witness,
+            extra_pub,
+        )
+    }
+}
+
+impl PublicCallbackBul<F, Args, Cr> for Snapshot {
+    type MembershipWitness = CbWitness;
+
+    type MembershipWitnessVar = <CStore as PublicCallbackBul<F, Args, Cr>>::MembershipWitnessVar;
+
+    type NonMembershipWitness = CbNmembWitness;
+
+    type NonMembershipWitnessVar =
+        <CStore as PublicCallbackBul<F, Args, Cr>>::NonMembershipWitnessVar;
+
+    type MembershipPub = CbPub;
+
+    type MembershipPubVar = <CStore as PublicCallbackBul<F, Args, Cr>>::MembershipPubVar;
+
+    type NonMembershipPub = CbNmembPub;
+
+    type NonMembershipPubVar = <CStore as PublicCallbackBul<F, Args, Cr>>::NonMembershipPubVar;
+
+    fn verify_in(&self, tik: FakeSigPubkey<F>) -> Option<(Args, Time<F>)> {
+        self.callbacks
+            .iter()
+            .find(|(t, _, _, _)| *t == tik)
+            .map(|(_, arg, time, _)| (*arg, *time))
+    }
+
+    fn verify_not_in(&self, tik: FakeSigPubkey<F>) -> bool {
+        !self.callbacks.iter().any(|(t, _, _, _)| *t == tik)
+    }
+
+    fn get_membership_data(
+        &self,
+        tik: FakeSigPubkey<F>,
+    ) -> (
+        Self::MembershipPub,
+        Self::MembershipWitness,
+        Self::NonMembershipPub,
+        Self::NonMembershipWitness,
+    ) {
+        if let Some((_, _, _, s)) = self.callbacks.iter().find(|(t, _, _, _)| *t == tik) {
+            return (
+                self.cb_pubkey.clone(),
+                s.clone(),
+                self.cb_nmemb_pubkey.clone(),
+                Self::NonMembershipWitness::default(),
+            );
+        }
+
+        let r = self
+            .nmemb
+            .iter()
+            .find(|r| r.is_in_range(tik.to()))
+            .expect("the nonmembership bulletin covers every ticket");
+        (
+            self.cb_pubkey.clone(),
+            Self::MembershipWitness::default(),
+            self.cb_nmemb_pubkey.clone(),
+            r.clone(),
+        )
+    }
+
+    fn enforce_membership_of(
+        tikvar: (FakeSigPubkeyVar<F>, ArgsVar, TimeVar<F>),
+        extra_witness: Self::MembershipWitnessVar,
+        extra_pub: Self::MembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <CStore as PublicCallbackBul<F, Args, Cr>>::enforce_membership_of(
+            tikvar,
+            extra_witness,
+            extra_pub,
+        )
+    }
+
+    fn enforce_nonmembership_of(
+        tikvar: FakeSigPubkeyVar<F>,
+        extra_witness: Self::NonMembershipWitnessVar,
+        extra_pub: Self::NonMembershipPubVar,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        <CStore as PublicCallbackBul<F, Args, Cr>>::enforce_nonmembership_of(
+            tikvar,
+            extra_witness,
+            extra_pub,
+        )
+    }
+}
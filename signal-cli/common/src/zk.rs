@@ -1,25 +1,34 @@
 use ark_bn254::Fr as F;
 use ark_bn254::Fr;
-use ark_ff::{PrimeField, ToConstraintField};
+use ark_ff::BigInteger;
+use ark_ff::PrimeField;
 use ark_ff::fields::AdditiveGroup;
 use ark_r1cs_std::{
     boolean::Boolean,
     eq::EqGadget,
     fields::{fp::FpVar, FieldVar},
-    prelude::{AllocVar, AllocationMode},
+    prelude::AllocVar,
     select::CondSelectGadget,
+    R1CSVar,
 };
 use ark_relations::r1cs::{Namespace, Result as ArkResult, SynthesisError};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::vec::Vec;
-use rand::{CryptoRng, RngCore};
+use petname::{Generator, Petnames};
+use rand::{CryptoRng, RngCore, SeedableRng, rngs::StdRng};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::borrow::Borrow;
 use zk_callbacks::{
-    crypto::hash::HasherZK,
+    crypto::vrf::VrfZK,
     generic::{
+        badges::select_badge,
         bulletin::{PublicCallbackBul, PublicUserBul},
         interaction::{Callback, Interaction},
         object::{Id, Time},
+        predicates::{counter_below, not_banned, unchanged},
+        pseudonym::{pseudonym_predicate, same_author_predicate, HasPseudonyms},
+        reputation::{Reputation, ReputationBounds, ReputationVar},
         scan::{self, PrivScanArgs, PrivScanArgsVar, PubScanArgs, PubScanArgsVar},
         user::{ExecutedMethod, User, UserVar},
     },
@@ -30,11 +39,28 @@ use zk_callbacks::{
     scannable_zk_object,
 };
 
-use crate::{Args, ArgsVar, Cr, H, PK, Snark};
+use crate::{Args, ArgsVar, Cr, H, PK, Snark, Vrf};
+
+pub use zk_callbacks::generic::pseudonym::{
+    PseudonymArgs, PseudonymArgsVar, RatedPseudonymArgs as PseudonymArgsRate,
+    RatedPseudonymArgsVar as PseudonymArgsRateVar, SameAuthorArgs, SameAuthorArgsVar,
+};
 
 pub const NUM_INTS_BEFORE_SCAN: usize = 505;
 pub const MAX_PSEUDO: usize = 4;
+/// The most pseudonyms an authorship proof may link. The server picks how many its keys link,
+/// from 2 up to this.
+pub const MAX_AUTHORS: usize = 8;
 const BAN_FLAG: u64 = 999999999;
+/// Callback arguments just below [`BAN_FLAG`] issue badges: `BADGE_FLAG + i` issues badge `i`.
+const BADGE_FLAG: u64 = 999999000;
+/// The number of badge slots of a user, numbered from 1.
+pub const NUM_BADGES: u64 = 3;
+/// The value of a badge which has been issued.
+pub const BADGE_HELD: u64 = 1;
+pub const REP_BOUNDS: ReputationBounds = ReputationBounds::new(0, 1_000_000);
+/// The bits a reputation within [`REP_BOUNDS`] fits in.
+pub const REP_BITS: usize = 20;
 
 #[scannable_zk_object(F)]
 #[derive(Default, CanonicalSerialize, CanonicalDeserialize)]
@@ -49,31 +75,6 @@ pub struct MsgUser {
     pub badge3: F,
 }
 
-#[derive(Clone, Debug, Default, CanonicalDeserialize, CanonicalSerialize)]
-pub struct PseudonymArgs<F: PrimeField> {
-    pub context: F,
-    pub claimed: F,
-}
-
-#[derive(Clone)]
-pub struct PseudonymArgsVar<F: PrimeField> {
-    pub context: FpVar<F>,
-    pub claimed: FpVar<F>,
-}
-#[derive(Clone, Debug, Default, CanonicalDeserialize, CanonicalSerialize)]
-pub struct PseudonymArgsRate<F: PrimeField> {
-    pub context: F,
-    pub claimed: F,
-    pub i: F,
-}
-
-#[derive(Clone)]
-pub struct PseudonymArgsRateVar<F: PrimeField> {
-    pub context: FpVar<F>,
-    pub claimed: FpVar<F>,
-    pub i: FpVar<F>,
-}
-
 #[derive(Clone, Debug, Default, CanonicalDeserialize, CanonicalSerialize)]
 pub struct BadgesArgs<F: PrimeField> {
     pub i: F,
@@ -86,78 +87,58 @@ pub struct BadgesArgsVar<F: PrimeField> {
     pub claimed: FpVar<F>,
 }
 
-#[derive(Clone, Default)]
-pub struct PseudonymArgsPair<F: PrimeField> {
-    pub a: PseudonymArgs<F>,
-    pub b: PseudonymArgs<F>,
+/// The public arguments of a reputation proof: a pseudonym of the user, and the reputation the
+/// user has at least.
+#[derive(Clone, Debug, Default, CanonicalDeserialize, CanonicalSerialize)]
+pub struct RepArgs<F: PrimeField> {
+    pub pseudonym: PseudonymArgs<F>,
+    pub at_least: F,
 }
 
 #[derive(Clone)]
-pub struct PseudonymArgsPairVar<F: PrimeField> {
-    pub a: PseudonymArgsVar<F>,
-    pub b: PseudonymArgsVar<F>,
+pub struct RepArgsVar<F: PrimeField> {
+    pub pseudonym: PseudonymArgsVar<F>,
+    pub at_least: FpVar<F>,
 }
 
-impl<F: PrimeField> AllocVar<PseudonymArgs<F>, F> for PseudonymArgsVar<F> {
-    fn new_variable<T: Borrow<PseudonymArgs<F>>>(
-        cs: impl Into<Namespace<F>>,
-        f: impl FnOnce() -> Result<T, SynthesisError>,
-        mode: AllocationMode,
-    ) -> Result<Self, SynthesisError> {
-        let ns = cs.into();
-        let cs = ns.cs(); // ConstraintSystemRef<F>
-        let PseudonymArgs { context, claimed } = *f()?.borrow();
-        Ok(Self {
-            context: FpVar::new_variable(cs.clone(), || Ok(context), mode)?,
-            claimed: FpVar::new_variable(cs, || Ok(claimed), mode)?,
-        })
+impl HasPseudonyms<F, Vrf> for MsgUser {
+    fn pseudonym_secret(&self) -> &F {
+        &self.sk
     }
-}
 
-impl<F: PrimeField> ToConstraintField<F> for PseudonymArgs<F> {
-    fn to_field_elements(&self) -> Option<Vec<F>> {
-        Some(vec![self.context, self.claimed])
+    fn pseudonym_secret_var(var: &MsgUserZKVar) -> &FpVar<F> {
+        &var.sk
     }
 }
 
+pub type PseudonymArgsPair<F> = SameAuthorArgs<F, 2>;
+pub type PseudonymArgsPairVar<F> = SameAuthorArgsVar<F, 2>;
+
+/// Evaluate `$body` with `$n` bound to the number of pseudonyms `$k` as a constant, so the
+/// authorship types can be picked at run time. `$k` must be from 2 to [`MAX_AUTHORS`].
+#[macro_export]
+macro_rules! with_authors {
+    ($k:expr, $n:ident => $body:expr) => {
+        match $k {
+            2 => { const $n: usize = 2; $body }
+            3 => { const $n: usize = 3; $body }
+            4 => { const $n: usize = 4; $body }
+            5 => { const $n: usize = 5; $body }
+            6 => { const $n: usize = 6; $body }
+            7 => { const $n: usize = 7; $body }
+            8 => { const $n: usize = 8; $body }
+            k => panic!("authorship proofs link 2 to {} pseudonyms, not {}", $crate::zk::MAX_AUTHORS, k),
+        }
+    };
+}
+
 pub fn pseudonym_pred<'a, 'b>(
     tu: &'a UserVar<F, MsgUser>,
-    _com: &'b FpVar<F>,
+    com: &'b FpVar<F>,
     pub_args: PseudonymArgsVar<F>,
-    _priv_args: (),
+    priv_args: (),
 ) -> ArkResult<Boolean<F>> {
-    let context = pub_args.context;
-    let claimed = pub_args.claimed;
-    let derived = Poseidon::<2>::hash_in_zk(&[tu.data.sk.clone(), context.clone()])?;
-    derived.is_eq(&claimed)
-}
-
-impl<F: PrimeField> AllocVar<PseudonymArgsRate<F>, F> for PseudonymArgsRateVar<F> {
-    fn new_variable<T: Borrow<PseudonymArgsRate<F>>>(
-        cs: impl Into<Namespace<F>>,
-        f: impl FnOnce() -> Result<T, SynthesisError>,
-        mode: AllocationMode,
-    ) -> Result<Self, SynthesisError> {
-        let ns = cs.into();
-        let cs = ns.cs(); // ConstraintSystemRef<F>
-
-        let PseudonymArgsRate {
-            context,
-            claimed,
-            i,
-        } = *f()?.borrow();
-        Ok(Self {
-            context: FpVar::new_variable(cs.clone(), || Ok(context), mode)?,
-            claimed: FpVar::new_variable(cs.clone(), || Ok(claimed), mode)?,
-            i: FpVar::new_variable(cs, || Ok(i), mode)?,
-        })
-    }
-}
-
-impl<F: PrimeField> ToConstraintField<F> for PseudonymArgsRate<F> {
-    fn to_field_elements(&self) -> Option<Vec<F>> {
-        Some(vec![self.context, self.claimed, self.i])
-    }
+    pseudonym_predicate::<F, Vrf, MsgUser>(tu, com, pub_args, priv_args)
 }
 
 impl<F: PrimeField> AllocVar<BadgesArgs<F>, F> for BadgesArgsVar<F> {
@@ -183,64 +164,77 @@ pub fn badge_pred<'a, 'b>(
     pub_args: BadgesArgsVar<F>,
     _priv_args: (),
 ) -> ArkResult<Boolean<F>> {
-    let claimed = pub_args.claimed;
+    let badges = [
+        tu.data.badge1.clone(),
+        tu.data.badge2.clone(),
+        tu.data.badge3.clone(),
+    ];
 
-    let is_one = pub_args.i.is_eq(&FpVar::Constant(F::from(1)))?;
-    let is_two = pub_args.i.is_eq(&FpVar::Constant(F::from(2)))?;
+    // Badges are numbered from 1 in wispy
+    let slot = pub_args.i - FpVar::Constant(F::from(1));
+    let (badge, valid) = select_badge(&badges, &slot)?;
 
-    let badge12 = FpVar::conditionally_select(&is_one, &tu.data.badge1, &tu.data.badge2)?;
-    let badge = FpVar::conditionally_select(&is_two, &badge12, &tu.data.badge3)?;
+    let x1 = badge.is_eq(&pub_args.claimed)?;
 
-    let x1 = badge.is_eq(&claimed)?;
+    Ok(x1 & valid)
+}
 
-    Ok(x1)
+/// Whether all `N` pseudonyms are the user's.
+pub fn authorship_pred<'a, 'b, const N: usize>(
+    tu: &'a UserVar<F, MsgUser>,
+    com: &'b FpVar<F>,
+    pub_args: SameAuthorArgsVar<F, N>,
+    priv_args: (),
+) -> ArkResult<Boolean<F>> {
+    same_author_predicate::<F, Vrf, MsgUser, N>(tu, com, pub_args, priv_args)
 }
 
-impl<F: PrimeField> AllocVar<PseudonymArgsPair<F>, F> for PseudonymArgsPairVar<F> {
-    fn new_variable<T: Borrow<PseudonymArgsPair<F>>>(
+impl<F: PrimeField> AllocVar<RepArgs<F>, F> for RepArgsVar<F> {
+    fn new_variable<T: Borrow<RepArgs<F>>>(
         cs: impl Into<Namespace<F>>,
         f: impl FnOnce() -> Result<T, SynthesisError>,
-        mode: AllocationMode,
+        mode: ark_r1cs_std::alloc::AllocationMode,
     ) -> Result<Self, SynthesisError> {
         let ns = cs.into();
         let cs = ns.cs();
 
-        let pair = f()?;
-        let pair = pair.borrow();
-
-        let a_var = PseudonymArgsVar {
-            context: FpVar::new_variable(cs.clone(), || Ok(pair.a.context), mode)?,
-            claimed: FpVar::new_variable(cs.clone(), || Ok(pair.a.claimed), mode)?,
-        };
-
-        let b_var = PseudonymArgsVar {
-            context: FpVar::new_variable(cs.clone(), || Ok(pair.b.context), mode)?,
-            claimed: FpVar::new_variable(cs, || Ok(pair.b.claimed), mode)?,
-        };
-
-        Ok(Self { a: a_var, b: b_var })
+        let RepArgs { pseudonym, at_least } = f()?.borrow().clone();
+        Ok(Self {
+            pseudonym: PseudonymArgsVar::new_variable(cs.clone(), || Ok(pseudonym), mode)?,
+            at_least: FpVar::new_variable(cs, || Ok(at_least), mode)?,
+        })
     }
 }
 
-pub fn authorship_pred<'a, 'b>(
+/// Whether `value >= lo`, for `value` and `lo` below `2^BITS` (with `BITS` below 64).
+///
+/// `value - lo + 2^BITS` is split into `BITS + 1` bits, the top one of which is set exactly when
+/// the difference is not negative. This takes `BITS + 1` constraints, where comparing the field
+/// elements takes one for every bit of the field.
+pub fn at_least<G: PrimeField, const BITS: usize>(
+    value: &FpVar<G>,
+    lo: &FpVar<G>,
+) -> ArkResult<Boolean<G>> {
+    let diff = value - lo + FpVar::Constant(G::from(1u64 << BITS));
+    let cs = diff.cs();
+    let bits = (0..=BITS)
+        .map(|i| Boolean::new_witness(cs.clone(), || Ok(diff.value()?.into_bigint().get_bit(i))))
+        .collect::<ArkResult<Vec<_>>>()?;
+    Boolean::le_bits_to_fp(&bits)?.enforce_equal(&diff)?;
+    Ok(bits[BITS].clone())
+}
+
+/// Whether the pseudonym is the user's, and the user has at least the reputation claimed, which
+/// the verifier must check is within [`REP_BOUNDS`].
+pub fn reputation_pred<'a, 'b>(
     tu: &'a UserVar<F, MsgUser>,
-    _com: &'b FpVar<F>,
-    pub_args: PseudonymArgsPairVar<F>,
+    com: &'b FpVar<F>,
+    pub_args: RepArgsVar<F>,
     _priv_args: (),
 ) -> ArkResult<Boolean<F>> {
-    let context = pub_args.a.context;
-    let claimed = pub_args.a.claimed;
-    let derived = Poseidon::<2>::hash_in_zk(&[tu.data.sk.clone(), context.clone()])?;
-
-    let x1 = derived.is_eq(&claimed)?;
-
-    let context2 = pub_args.b.context;
-    let claimed2 = pub_args.b.claimed;
-    let derived2 = Poseidon::<2>::hash_in_zk(&[tu.data.sk.clone(), context2.clone()])?;
-
-    let x2 = derived2.is_eq(&claimed2)?;
-
-    Ok(x1 & x2)
+    let owns = pseudonym_pred(tu, com, pub_args.pseudonym, ())?;
+    let enough = at_least::<F, REP_BITS>(&tu.data.reputation, &pub_args.at_least)?;
+    Ok(owns & enough)
 }
 
 fn standard_method(tu: &User<F, MsgUser>, _args: F, _priv: ()) -> User<F, MsgUser> {
@@ -275,24 +269,24 @@ fn standard_predicate<'a>(
     _args: FpVar<F>,
     _priv: (),
 ) -> ArkResult<Boolean<F>> {
-    let x1 = tu_new.data.num_interactions_since_last_scan.is_eq(
-        &(tu_old.data.num_interactions_since_last_scan.clone() + FpVar::Constant(F::from(1))),
+    let x1 = counter_below(
+        &tu_old.data.num_interactions_since_last_scan,
+        &tu_new.data.num_interactions_since_last_scan,
+        NUM_INTS_BEFORE_SCAN as u64,
     )?;
-    let x2 = tu_new.data.reputation.is_eq(&tu_old.data.reputation)?;
-    let x3 = tu_new
-        .data
-        .num_interactions_since_last_scan
-        .is_neq(&FpVar::Constant(F::from(NUM_INTS_BEFORE_SCAN as u64)))?;
 
     // Make sure user is not banned
-    let x4 = tu_new.data.banned.is_eq(&FpVar::Constant(F::from(0)))?;
-    let x5 = tu_new.data.sk.is_eq(&tu_old.data.sk)?;
+    let x2 = not_banned(&tu_new.data.banned)?;
 
-    let x6 = tu_new.data.badge1.is_eq(&tu_old.data.badge1)?;
-    let x7 = tu_new.data.badge2.is_eq(&tu_old.data.badge2)?;
-    let x8 = tu_new.data.badge3.is_eq(&tu_old.data.badge3)?;
+    let x3 = unchanged(&[
+        (&tu_old.data.reputation, &tu_new.data.reputation),
+        (&tu_old.data.sk, &tu_new.data.sk),
+        (&tu_old.data.badge1, &tu_new.data.badge1),
+        (&tu_old.data.badge2, &tu_new.data.badge2),
+        (&tu_old.data.badge3, &tu_new.data.badge3),
+    ])?;
 
-    Ok(x1 & x2 & x3 & x4 & x5 & x6 & x7 & x8)
+    Ok(x1 & x2 & x3)
 }
 
 fn standard_pseudo_predicate<'a>(
@@ -301,30 +295,30 @@ fn standard_pseudo_predicate<'a>(
     pub_args: PseudonymArgsVar<F>,
     _priv: (),
 ) -> ArkResult<Boolean<F>> {
-    let x1 = tu_new.data.num_interactions_since_last_scan.is_eq(
-        &(tu_old.data.num_interactions_since_last_scan.clone() + FpVar::Constant(F::from(1))),
+    let x1 = counter_below(
+        &tu_old.data.num_interactions_since_last_scan,
+        &tu_new.data.num_interactions_since_last_scan,
+        NUM_INTS_BEFORE_SCAN as u64,
     )?;
-    let x2 = tu_new.data.reputation.is_eq(&tu_old.data.reputation)?;
-    let x3 = tu_new
-        .data
-        .num_interactions_since_last_scan
-        .is_neq(&FpVar::Constant(F::from(NUM_INTS_BEFORE_SCAN as u64)))?;
 
     // Make sure user is not banned
-    let x4 = tu_new.data.banned.is_eq(&FpVar::Constant(F::from(0)))?;
-    let x5 = tu_new.data.sk.is_eq(&tu_old.data.sk)?;
+    let x2 = not_banned(&tu_new.data.banned)?;
 
-    let x6 = tu_new.data.badge1.is_eq(&tu_old.data.badge1)?;
-    let x7 = tu_new.data.badge2.is_eq(&tu_old.data.badge2)?;
-    let x8 = tu_new.data.badge3.is_eq(&tu_old.data.badge3)?;
+    let x3 = unchanged(&[
+        (&tu_old.data.reputation, &tu_new.data.reputation),
+        (&tu_old.data.sk, &tu_new.data.sk),
+        (&tu_old.data.badge1, &tu_new.data.badge1),
+        (&tu_old.data.badge2, &tu_new.data.badge2),
+        (&tu_old.data.badge3, &tu_new.data.badge3),
+    ])?;
 
     // Pseudonym check
     let context = pub_args.context;
     let claimed = pub_args.claimed;
-    let derived = Poseidon::<2>::hash_in_zk(&[tu_new.data.sk.clone(), context.clone()])?;
-    let x9 = derived.is_eq(&claimed)?;
+    let derived = Vrf::evaluate_in_zk(&tu_new.data.sk, &[context.clone()])?;
+    let x4 = derived.is_eq(&claimed)?;
 
-    Ok(x1 & x2 & x3 & x4 & x5 & x6 & x7 & x8 & x9)
+    Ok(x1 & x2 & x3 & x4)
 }
 
 fn standard_pseudo_rate_predicate<'a>(
@@ -333,34 +327,34 @@ fn standard_pseudo_rate_predicate<'a>(
     pub_args: PseudonymArgsRateVar<F>,
     _priv: (),
 ) -> ArkResult<Boolean<F>> {
-    let x1 = tu_new.data.num_interactions_since_last_scan.is_eq(
-        &(tu_old.data.num_interactions_since_last_scan.clone() + FpVar::Constant(F::from(1))),
+    let x1 = counter_below(
+        &tu_old.data.num_interactions_since_last_scan,
+        &tu_new.data.num_interactions_since_last_scan,
+        NUM_INTS_BEFORE_SCAN as u64,
     )?;
-    let x2 = tu_new.data.reputation.is_eq(&tu_old.data.reputation)?;
-    let x3 = tu_new
-        .data
-        .num_interactions_since_last_scan
-        .is_neq(&FpVar::Constant(F::from(NUM_INTS_BEFORE_SCAN as u64)))?;
 
     // Make sure user is not banned
-    let x4 = tu_new.data.banned.is_eq(&FpVar::Constant(F::from(0)))?;
-    let x5 = tu_new.data.sk.is_eq(&tu_old.data.sk)?;
+    let x2 = not_banned(&tu_new.data.banned)?;
 
-    let x6 = tu_new.data.badge1.is_eq(&tu_old.data.badge1)?;
-    let x7 = tu_new.data.badge2.is_eq(&tu_old.data.badge2)?;
-    let x8 = tu_new.data.badge3.is_eq(&tu_old.data.badge3)?;
+    let x3 = unchanged(&[
+        (&tu_old.data.reputation, &tu_new.data.reputation),
+        (&tu_old.data.sk, &tu_new.data.sk),
+        (&tu_old.data.badge1, &tu_new.data.badge1),
+        (&tu_old.data.badge2, &tu_new.data.badge2),
+        (&tu_old.data.badge3, &tu_new.data.badge3),
+    ])?;
 
     // Pseudonym check
     let context = pub_args.context;
     let claimed = pub_args.claimed;
-    let i = pub_args.i;
+    let i = pub_args.index;
 
-    let x9 = i.is_neq(&FpVar::Constant(F::from(MAX_PSEUDO as u64)))?;
+    let x4 = i.is_neq(&FpVar::Constant(F::from(MAX_PSEUDO as u64)))?;
 
-    let derived = Poseidon::<2>::hash_in_zk(&[tu_new.data.sk.clone(), context, i])?;
-    let x10 = derived.is_eq(&claimed)?;
+    let derived = Vrf::evaluate_in_zk(&tu_new.data.sk, &[context, i])?;
+    let x5 = derived.is_eq(&claimed)?;
 
-    Ok(x1 & x2 & x3 & x4 & x5 & x6 & x7 & x8 & x9 & x10)
+    Ok(x1 & x2 & x3 & x4 & x5)
 }
 
 pub fn arg_rep(n: i64) -> Fr {
@@ -371,13 +365,98 @@ pub fn arg_ban() -> Fr {
     F::from(999999999)
 }
 
+/// The callback argument issuing badge `i`, numbered from 1 up to [`NUM_BADGES`].
+pub fn arg_badge(i: u64) -> Fr {
+    F::from(BADGE_FLAG + i)
+}
+
+/// The badge slot a callback argument issues, if it issues one.
+fn badge_slot(argument: F) -> Option<usize> {
+    (1..=NUM_BADGES)
+        .find(|&i| argument == arg_badge(i))
+        .map(|i| i as usize - 1)
+}
+
+/// What calling a callback with some argument does to the user, once a scan applies it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallbackEffect {
+    Ban,
+    /// Issues the badge in this slot, numbered from 1.
+    Badge(u64),
+    /// Changes the reputation by this much, within [`REP_BOUNDS`].
+    Reputation(i64),
+}
+
+/// What calling a callback with `argument` does, as [`standard_callback_method`] applies it.
+pub fn callback_effect(argument: F) -> CallbackEffect {
+    if argument == F::from(BAN_FLAG) {
+        return CallbackEffect::Ban;
+    }
+    if let Some(slot) = badge_slot(argument) {
+        return CallbackEffect::Badge(slot as u64 + 1);
+    }
+    // Small negations are negative deltas, as in `Reputation::saturating_add_signed`
+    let neg = (-argument).into_bigint();
+    if neg.num_bits() <= 64 {
+        CallbackEffect::Reputation(i64::try_from(neg.as_ref()[0]).map_or(i64::MIN, |n| -n))
+    } else {
+        CallbackEffect::Reputation(i64::try_from(argument.into_bigint().as_ref()[0]).unwrap_or(i64::MAX))
+    }
+}
+
+/// The public argument of a standard post, binding its proof to the attachments sent with it.
+/// A post with no attachments has the argument 0, as posts had before attachments.
+pub fn attachment_digest<'a>(attachments: impl IntoIterator<Item = &'a [u8]>) -> Fr {
+    let mut hasher = Sha256::new();
+    let mut empty = true;
+    for data in attachments {
+        empty = false;
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(data);
+    }
+    if empty {
+        return F::ZERO;
+    }
+    F::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+/// The context a direct message request is proved in, binding its proof to the parts of the
+/// request, such as the action and its recipient, and to the single-use `nonce` the server issued
+/// for it, so the proof cannot be replayed.
+pub fn dm_context(nonce: F, request: &[&str]) -> Fr {
+    let mut hasher = Sha256::new();
+    hasher.update(nonce.into_bigint().to_bytes_le());
+    for part in request {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    F::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+/// The petname a pseudonym is shown as, derived deterministically from it, so the server and the
+/// client show a pseudonym by the same name.
+pub fn petname(pseudonym: F) -> Option<String> {
+    let bytes = pseudonym.into_bigint().to_bytes_le();
+    let mut seed = [0u8; 32];
+    seed[..bytes.len()].copy_from_slice(&bytes); // zero-pad to 32 bytes
+
+    // Use as RNG seed
+    let mut rng = StdRng::from_seed(seed);
+    Petnames::default().generate(&mut rng, 2, " ")
+}
+
 fn standard_callback_method(user: &User<F, MsgUser>, argument: F) -> User<F, MsgUser> {
     let mut u = user.clone();
-    let raw = argument.into_bigint().0[0];
-    if raw == BAN_FLAG {
+    if argument == F::from(BAN_FLAG) {
         u.data.banned = F::from(1);
+    } else if let Some(slot) = badge_slot(argument) {
+        let badges = [&mut u.data.badge1, &mut u.data.badge2, &mut u.data.badge3];
+        *badges[slot] = F::from(BADGE_HELD);
     } else {
-        u.data.reputation += F::from(raw);
+        u.data.reputation = Reputation(u.data.reputation)
+            .saturating_add_signed(argument, &REP_BOUNDS)
+            .0;
     }
     u.data.num_interactions_since_last_scan = F::ZERO;
     u
@@ -391,10 +470,22 @@ fn standard_callback_predicate(
 
     let is_ban = argument.is_eq(&FpVar::Constant(F::from(0)))?;
 
-    // Update reputation: if not banned, rep = rep + argument
-    let new_rep = &user.data.reputation + &argument;
+    // Update reputation: if not banned, rep = rep + argument, clamped to the bounds
+    let new_rep = ReputationVar(user.data.reputation.clone())
+        .saturating_add_signed(&argument, &REP_BOUNDS)?
+        .0;
     u.data.reputation = FpVar::conditionally_select(&is_ban, &user.data.reputation, &new_rep)?;
 
+    // Badge arguments set their badge instead, leaving the reputation as it was
+    let badges = [&mut u.data.badge1, &mut u.data.badge2, &mut u.data.badge3];
+    for (i, badge) in (1..=NUM_BADGES).zip(badges) {
+        let is_badge = argument.is_eq(&FpVar::Constant(arg_badge(i)))?;
+        *badge =
+            FpVar::conditionally_select(&is_badge, &FpVar::Constant(F::from(BADGE_HELD)), badge)?;
+        u.data.reputation =
+            FpVar::conditionally_select(&is_badge, &user.data.reputation, &u.data.reputation)?;
+    }
+
     u.data.num_interactions_since_last_scan = FpVar::zero();
     Ok(u)
 }
@@ -441,7 +532,7 @@ fn scan_predicate<CBul: PublicCallbackBul<F, Args, Cr> + Clone>(
 
 pub fn get_callbacks() -> Vec<Callback<F, MsgUser, Args, ArgsVar>> {
     let standard_callback = Callback {
-        method_id: Id::from(0),
+        method_id: Id::from(crate::catalog::STANDARD_CALLBACK),
         expirable: false,
         expiration: Time::from(10),
         method: standard_callback_method,
@@ -582,7 +673,7 @@ pub fn get_scan_interaction<CBul: PublicCallbackBul<F, Args, Cr> + Clone>() -> S
 
 pub fn exec_scanint<
     Bul: PublicUserBul<F, MsgUser>,
-    CBul: PublicCallbackBul<F, Args, Cr> + std::fmt::Debug + Clone,
+    CBul: PublicCallbackBul<F, Args, Cr> + Clone,
 >(
     user: &mut User<F, MsgUser>,
     rng: &mut (impl CryptoRng + RngCore),
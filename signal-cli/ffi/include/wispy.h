@@ -0,0 +1,67 @@
+#ifndef WISPY_H
+#define WISPY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum WispyStatus {
+  WISPY_STATUS_OK = 0,
+  WISPY_STATUS_NULL_POINTER = 1,
+  WISPY_STATUS_INVALID_INPUT = 2,
+  WISPY_STATUS_PROOF_FAILED = 3,
+  WISPY_STATUS_PANIC = 4,
+} WispyStatus;
+
+typedef struct WispyUser WispyUser;
+typedef struct WispyProvingKey WispyProvingKey;
+typedef struct WispySnapshot WispySnapshot;
+
+typedef struct WispyBytes {
+  const uint8_t *data;
+  size_t len;
+} WispyBytes;
+
+typedef struct WispyBuffer {
+  uint8_t *data;
+  size_t len;
+  size_t cap;
+} WispyBuffer;
+
+WispyStatus wispy_user_create(WispyUser **out);
+WispyStatus wispy_user_from_bytes(WispyBytes bytes_in, WispyUser **out);
+WispyStatus wispy_user_to_bytes(const WispyUser *user, WispyBuffer *out);
+WispyStatus wispy_user_commit(const WispyUser *user, WispyBuffer *out);
+void wispy_user_free(WispyUser *user);
+
+WispyStatus wispy_proving_key_from_bytes(WispyBytes bytes_in, WispyProvingKey **out);
+void wispy_proving_key_free(WispyProvingKey *pk);
+
+WispyStatus wispy_snapshot_new(WispyBytes user_pubkey,
+                               WispyBytes user_bulletin,
+                               WispyBytes cb_pubkey,
+                               WispyBytes cb_nmemb_pubkey,
+                               WispyBytes cb_bulletin,
+                               WispyBytes cb_nmemb_bulletin,
+                               WispySnapshot **out);
+void wispy_snapshot_free(WispySnapshot *bul);
+
+WispyStatus wispy_interact(WispyUser *user,
+                           const WispyProvingKey *pk,
+                           const WispySnapshot *bul,
+                           WispyBuffer *out);
+WispyStatus wispy_scan(WispyUser *user,
+                       const WispyProvingKey *pk,
+                       const WispySnapshot *bul,
+                       WispyBuffer *out);
+
+void wispy_buffer_free(WispyBuffer buf);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* WISPY_H */
@@ -0,0 +1,334 @@
+//! C bindings for the wispy client.
+//!
+//! Everything crosses the boundary either as an opaque handle or as a byte buffer, in the same
+//! encodings as the HTTP routes. Handles are created by the `*_new`, `*_create`, and
+//! `*_from_bytes` functions and must be released with the matching `*_free`. Buffers returned
+//! through a `WispyBuffer *out` are owned by the caller and released with
+//! [`wispy_buffer_free`]. Every fallible function returns a [`WispyStatus`]; outputs are only
+//! written on [`WispyStatus::Ok`].
+//!
+//! The header is at `include/wispy.h`.
+
+use std::{panic::catch_unwind, ptr, slice};
+
+use ark_ff::UniformRand;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::{
+    snapshot::Snapshot,
+    zk::{exec_scanint, exec_standint, MsgUser},
+    F, H, PK,
+};
+use rand::rngs::OsRng;
+use zk_callbacks::generic::{keystore::read_key, object::Time, user::User};
+
+/// The result of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WispyStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer was null.
+    NullPointer = 1,
+    /// Input bytes could not be deserialized.
+    InvalidInput = 2,
+    /// The proof could not be made, e.g. the user is not on the bulletin.
+    ProofFailed = 3,
+    /// The call panicked. Handles passed to it should not be used again.
+    Panic = 4,
+}
+
+/// A user, owned by the caller.
+pub struct WispyUser(User<F, MsgUser>);
+
+/// A proving key, owned by the caller.
+pub struct WispyProvingKey(PK);
+
+/// A snapshot of the server bulletins, owned by the caller.
+pub struct WispySnapshot(Snapshot);
+
+/// Borrowed bytes passed in by the caller.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct WispyBytes {
+    /// The start of the bytes. May be null if `len` is 0.
+    pub data: *const u8,
+    /// The number of bytes.
+    pub len: usize,
+}
+
+/// Bytes returned to the caller, released with [`wispy_buffer_free`].
+#[repr(C)]
+pub struct WispyBuffer {
+    /// The start of the bytes.
+    pub data: *mut u8,
+    /// The number of bytes.
+    pub len: usize,
+    /// The allocated capacity, needed to release the buffer.
+    pub cap: usize,
+}
+
+impl WispyBuffer {
+    fn from_vec(v: Vec<u8>) -> Self {
+        let mut v = std::mem::ManuallyDrop::new(v);
+        WispyBuffer {
+            data: v.as_mut_ptr(),
+            len: v.len(),
+            cap: v.capacity(),
+        }
+    }
+}
+
+unsafe fn bytes<'a>(b: WispyBytes) -> Result<&'a [u8], WispyStatus> {
+    if b.len == 0 {
+        return Ok(&[]);
+    }
+    if b.data.is_null() {
+        return Err(WispyStatus::NullPointer);
+    }
+    Ok(slice::from_raw_parts(b.data, b.len))
+}
+
+fn de<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, WispyStatus> {
+    T::deserialize_with_mode(bytes, Compress::No, Validate::Yes)
+        .map_err(|_| WispyStatus::InvalidInput)
+}
+
+fn ser<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![];
+    value.serialize_with_mode(&mut bytes, Compress::No).unwrap();
+    bytes
+}
+
+/// Run `f`, turning panics into [`WispyStatus::Panic`] so they do not unwind into C.
+fn guard(f: impl FnOnce() -> Result<(), WispyStatus>) -> WispyStatus {
+    match catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(())) => WispyStatus::Ok,
+        Ok(Err(s)) => s,
+        Err(_) => WispyStatus::Panic,
+    }
+}
+
+unsafe fn write_out<T>(out: *mut T, value: T) -> Result<(), WispyStatus> {
+    if out.is_null() {
+        return Err(WispyStatus::NullPointer);
+    }
+    ptr::write(out, value);
+    Ok(())
+}
+
+/// Create a new user with a random secret key.
+///
+/// # Safety
+///
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_user_create(out: *mut *mut WispyUser) -> WispyStatus {
+    guard(|| {
+        let mut rng = OsRng;
+        let user = User::create(
+            MsgUser {
+                sk: F::rand(&mut rng),
+                ..Default::default()
+            },
+            &mut rng,
+        );
+        write_out(out, Box::into_raw(Box::new(WispyUser(user))))
+    })
+}
+
+/// Load a user saved with [`wispy_user_to_bytes`].
+///
+/// # Safety
+///
+/// `bytes_in` must point to readable bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_user_from_bytes(
+    bytes_in: WispyBytes,
+    out: *mut *mut WispyUser,
+) -> WispyStatus {
+    guard(|| {
+        let user = de(bytes(bytes_in)?)?;
+        write_out(out, Box::into_raw(Box::new(WispyUser(user))))
+    })
+}
+
+/// Serialize a user, to be saved by the app between calls.
+///
+/// # Safety
+///
+/// `user` must be a live handle, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_user_to_bytes(
+    user: *const WispyUser,
+    out: *mut WispyBuffer,
+) -> WispyStatus {
+    guard(|| {
+        let user = user.as_ref().ok_or(WispyStatus::NullPointer)?;
+        write_out(out, WispyBuffer::from_vec(ser(&user.0)))
+    })
+}
+
+/// The commitment to send to `api/user/join`.
+///
+/// # Safety
+///
+/// `user` must be a live handle, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_user_commit(
+    user: *const WispyUser,
+    out: *mut WispyBuffer,
+) -> WispyStatus {
+    guard(|| {
+        let user = user.as_ref().ok_or(WispyStatus::NullPointer)?;
+        write_out(out, WispyBuffer::from_vec(ser(&user.0.commit::<H>())))
+    })
+}
+
+/// Release a user. Null is ignored.
+///
+/// # Safety
+///
+/// `user` must be null or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_user_free(user: *mut WispyUser) {
+    if !user.is_null() {
+        drop(Box::from_raw(user));
+    }
+}
+
+/// Load a proving key as served by the server, raw or zstd compressed.
+///
+/// # Safety
+///
+/// `bytes_in` must point to readable bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_proving_key_from_bytes(
+    bytes_in: WispyBytes,
+    out: *mut *mut WispyProvingKey,
+) -> WispyStatus {
+    guard(|| {
+        let pk = read_key(bytes(bytes_in)?, Validate::Yes).map_err(|_| WispyStatus::InvalidInput)?;
+        write_out(out, Box::into_raw(Box::new(WispyProvingKey(pk))))
+    })
+}
+
+/// Release a proving key. Null is ignored.
+///
+/// # Safety
+///
+/// `pk` must be null or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_proving_key_free(pk: *mut WispyProvingKey) {
+    if !pk.is_null() {
+        drop(Box::from_raw(pk));
+    }
+}
+
+/// Build a bulletin snapshot from the responses of `api/user/pubkey`, `api/user/bulletin`,
+/// `api/callbacks/membership_pubkey`, `api/callbacks/nonmembership_pubkey`,
+/// `api/callbacks/bulletin`, and `api/callbacks/nmemb_bulletin`.
+///
+/// # Safety
+///
+/// Every `WispyBytes` must point to readable bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_snapshot_new(
+    user_pubkey: WispyBytes,
+    user_bulletin: WispyBytes,
+    cb_pubkey: WispyBytes,
+    cb_nmemb_pubkey: WispyBytes,
+    cb_bulletin: WispyBytes,
+    cb_nmemb_bulletin: WispyBytes,
+    out: *mut *mut WispySnapshot,
+) -> WispyStatus {
+    guard(|| {
+        let snapshot = Snapshot::new(
+            bytes(user_pubkey)?,
+            bytes(user_bulletin)?,
+            bytes(cb_pubkey)?,
+            bytes(cb_nmemb_pubkey)?,
+            bytes(cb_bulletin)?,
+            bytes(cb_nmemb_bulletin)?,
+        )
+        .map_err(|_| WispyStatus::InvalidInput)?;
+        write_out(out, Box::into_raw(Box::new(WispySnapshot(snapshot))))
+    })
+}
+
+/// Release a snapshot. Null is ignored.
+///
+/// # Safety
+///
+/// `bul` must be null or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_snapshot_free(bul: *mut WispySnapshot) {
+    if !bul.is_null() {
+        drop(Box::from_raw(bul));
+    }
+}
+
+/// Execute the standard interaction, updating `user`. The output is the executed method to
+/// `POST` with a message.
+///
+/// # Safety
+///
+/// The handles must be live, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_interact(
+    user: *mut WispyUser,
+    pk: *const WispyProvingKey,
+    bul: *const WispySnapshot,
+    out: *mut WispyBuffer,
+) -> WispyStatus {
+    guard(|| {
+        let user = user.as_mut().ok_or(WispyStatus::NullPointer)?;
+        let pk = pk.as_ref().ok_or(WispyStatus::NullPointer)?;
+        let bul = bul.as_ref().ok_or(WispyStatus::NullPointer)?;
+        let exec = exec_standint(
+            &mut user.0,
+            &mut OsRng,
+            &bul.0,
+            &pk.0,
+            Time::from(0),
+            F::from(0),
+            (),
+        )
+        .map_err(|_| WispyStatus::ProofFailed)?;
+        write_out(out, WispyBuffer::from_vec(exec.to_bytes(Compress::No)))
+    })
+}
+
+/// Scan the next callback, updating `user`. The output is the executed method to `POST` to
+/// `api/interact/scan`.
+///
+/// # Safety
+///
+/// The handles must be live, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_scan(
+    user: *mut WispyUser,
+    pk: *const WispyProvingKey,
+    bul: *const WispySnapshot,
+    out: *mut WispyBuffer,
+) -> WispyStatus {
+    guard(|| {
+        let user = user.as_mut().ok_or(WispyStatus::NullPointer)?;
+        let pk = pk.as_ref().ok_or(WispyStatus::NullPointer)?;
+        let bul = bul.as_ref().ok_or(WispyStatus::NullPointer)?;
+        let exec = exec_scanint(&mut user.0, &mut OsRng, &bul.0, &pk.0, &bul.0, Time::from(0))
+            .map_err(|_| WispyStatus::ProofFailed)?;
+        write_out(out, WispyBuffer::from_vec(exec.to_bytes(Compress::No)))
+    })
+}
+
+/// Release a buffer returned by this library.
+///
+/// # Safety
+///
+/// `buf` must have been returned by this library and not released before.
+#[no_mangle]
+pub unsafe extern "C" fn wispy_buffer_free(buf: WispyBuffer) {
+    if !buf.data.is_null() {
+        drop(Vec::from_raw_parts(buf.data, buf.len, buf.cap));
+    }
+}
@@ -0,0 +1,295 @@
+//! Python bindings for the wispy user-side API.
+//!
+//! Users, keys, and executed methods cross the boundary as bytes, in the same encodings the
+//! server uses, so a notebook can drive users against a local [`Bulletin`] or save the bytes and
+//! send them to a running server. Proving is done in Rust.
+
+use ark_ff::UniformRand;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::{
+    zk::{
+        exec_scanint, exec_standint, get_callbacks, get_extra_pubdata_for_scan,
+        get_scan_interaction, get_standard_interaction, MsgUser, PubScan,
+    },
+    Args, CStore, Cr, OStore, Snark, Store, F, H, PK, VK,
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use rand::rngs::OsRng;
+use zk_callbacks::{
+    generic::{
+        bulletin::{CallbackBul, JoinableBulletin, UserBul},
+        keystore::{read_key, write_key, KeyEncoding},
+        object::Time,
+        service::ServiceProvider,
+        user::{ExecutedMethod, User as ZkUser},
+    },
+    impls::centralized::crypto::FakeSigPrivkey,
+};
+
+fn err(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn de<T: CanonicalDeserialize>(bytes: &[u8]) -> PyResult<T> {
+    T::deserialize_with_mode(bytes, Compress::No, Validate::Yes).map_err(err)
+}
+
+fn ser<'py, T: CanonicalSerialize>(py: Python<'py>, value: &T) -> Bound<'py, PyBytes> {
+    let mut bytes = vec![];
+    value.serialize_with_mode(&mut bytes, Compress::No).unwrap();
+    PyBytes::new_bound(py, &bytes)
+}
+
+/// A Groth16 proving key.
+#[pyclass]
+#[derive(Clone)]
+pub struct ProvingKey(PK);
+
+#[pymethods]
+impl ProvingKey {
+    /// Read a key in the key store encoding (raw or zstd), as served by the server.
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        read_key(bytes, Validate::Yes).map(Self).map_err(err)
+    }
+
+    /// Write the key, compressed with zstd if `level` is given.
+    #[pyo3(signature = (level=None))]
+    fn to_bytes<'py>(&self, py: Python<'py>, level: Option<i32>) -> PyResult<Bound<'py, PyBytes>> {
+        let encoding = level.map_or(KeyEncoding::Raw, KeyEncoding::Zstd);
+        let mut bytes = vec![];
+        write_key(&self.0, &mut bytes, encoding).map_err(err)?;
+        Ok(PyBytes::new_bound(py, &bytes))
+    }
+}
+
+/// A Groth16 verifying key.
+#[pyclass]
+#[derive(Clone)]
+pub struct VerifyingKey(VK);
+
+#[pymethods]
+impl VerifyingKey {
+    /// Read a key in the key store encoding (raw or zstd).
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        read_key(bytes, Validate::Yes).map(Self).map_err(err)
+    }
+
+    /// Write the key, compressed with zstd if `level` is given.
+    #[pyo3(signature = (level=None))]
+    fn to_bytes<'py>(&self, py: Python<'py>, level: Option<i32>) -> PyResult<Bound<'py, PyBytes>> {
+        let encoding = level.map_or(KeyEncoding::Raw, KeyEncoding::Zstd);
+        let mut bytes = vec![];
+        write_key(&self.0, &mut bytes, encoding).map_err(err)?;
+        Ok(PyBytes::new_bound(py, &bytes))
+    }
+}
+
+/// An in-process bulletin and service, for running experiments without a server.
+#[pyclass(unsendable)]
+pub struct Bulletin(Store);
+
+#[pymethods]
+impl Bulletin {
+    #[new]
+    fn new() -> Self {
+        Self(Store::new(&mut OsRng))
+    }
+
+    /// Generate the keys for the standard interaction.
+    fn standard_keys(&self) -> (ProvingKey, VerifyingKey) {
+        let (pk, vk) = get_standard_interaction().generate_keys::<H, Snark, Cr, OStore>(
+            &mut OsRng,
+            Some(self.0.obj_bul.get_pubkey()),
+            None,
+            false,
+        );
+        (ProvingKey(pk), VerifyingKey(vk))
+    }
+
+    /// Generate the keys for the scan interaction.
+    fn scan_keys(&self) -> (ProvingKey, VerifyingKey) {
+        let (pk, vk) = get_scan_interaction().generate_keys::<H, Snark, Cr, OStore>(
+            &mut OsRng,
+            Some(self.0.obj_bul.get_pubkey()),
+            Some(self.scan_args()),
+            true,
+        );
+        (ProvingKey(pk), VerifyingKey(vk))
+    }
+
+    /// Add a user to the bulletin.
+    fn join(&mut self, user: &User) -> PyResult<()> {
+        <OStore as JoinableBulletin<F, MsgUser>>::join_bul(
+            &mut self.0.obj_bul,
+            user.0.commit::<H>(),
+            (),
+        )
+        .map_err(|_| err("user could not join the bulletin"))
+    }
+
+    /// Verify a standard interaction and store it, returning whether it was accepted.
+    fn append_standard(&mut self, exec: &[u8], vk: &VerifyingKey) -> PyResult<bool> {
+        let exec: ExecutedMethod<F, Snark, Args, Cr, 1> =
+            ExecutedMethod::from_bytes(exec).map_err(err)?;
+        Ok(self.verify_and_store(exec, F::from(0), Time::from(0), &vk.0))
+    }
+
+    /// Verify a scan and store it, returning whether it was accepted.
+    fn append_scan(&mut self, exec: &[u8], vk: &VerifyingKey) -> PyResult<bool> {
+        let exec: ExecutedMethod<F, Snark, Args, Cr, 0> =
+            ExecutedMethod::from_bytes(exec).map_err(err)?;
+        let args = self.scan_args();
+        let time = self.0.callback_bul.get_epoch();
+        Ok(self.verify_and_store(exec, args, time, &vk.0))
+    }
+}
+
+impl Bulletin {
+    fn scan_args(&self) -> PubScan<CStore> {
+        get_extra_pubdata_for_scan(
+            &self.0.callback_bul,
+            self.0.callback_bul.get_pubkey(),
+            self.0.callback_bul.nmemb_bul.get_pubkey(),
+            F::from(0),
+        )
+    }
+
+    fn verify_and_store<
+        PubArgs: Clone + ark_ff::ToConstraintField<F>,
+        const NUMCBS: usize,
+    >(
+        &mut self,
+        exec: ExecutedMethod<F, Snark, Args, Cr, NUMCBS>,
+        args: PubArgs,
+        time: Time<F>,
+        vk: &VK,
+    ) -> bool {
+        let bul = self.0.obj_bul.clone();
+        let appended = <OStore as UserBul<F, MsgUser>>::verify_interact_and_append::<
+            PubArgs,
+            Snark,
+            NUMCBS,
+        >(
+            &mut self.0.obj_bul,
+            exec.new_object.clone(),
+            exec.old_nullifier.clone(),
+            args.clone(),
+            exec.cb_com_list.clone(),
+            exec.proof.clone(),
+            None,
+            vk,
+        );
+        let stored = self
+            .0
+            .approve_interaction_and_store::<MsgUser, Snark, PubArgs, OStore, H, NUMCBS>(
+                exec,
+                FakeSigPrivkey::sk(),
+                args,
+                &bul,
+                get_callbacks(),
+                time,
+                bul.get_pubkey(),
+                true,
+                vk,
+                0,
+            );
+        appended.is_ok() && stored.is_ok()
+    }
+}
+
+/// A wispy user.
+#[pyclass]
+#[derive(Clone)]
+pub struct User(ZkUser<F, MsgUser>);
+
+#[pymethods]
+impl User {
+    /// Create a new user with a random secret key.
+    #[staticmethod]
+    fn create() -> Self {
+        let mut rng = OsRng;
+        Self(ZkUser::create(
+            MsgUser {
+                sk: F::rand(&mut rng),
+                ..Default::default()
+            },
+            &mut rng,
+        ))
+    }
+
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        de(bytes).map(Self)
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        ser(py, &self.0)
+    }
+
+    /// The commitment to the user, as sent to `api/user/join`.
+    fn commit<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        ser(py, &self.0.commit::<H>())
+    }
+
+    /// Execute the standard interaction, returning the executed method as a wire envelope.
+    fn interact<'py>(
+        &mut self,
+        py: Python<'py>,
+        pk: &ProvingKey,
+        bul: &Bulletin,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let exec = exec_standint(
+            &mut self.0,
+            &mut OsRng,
+            &bul.0.obj_bul,
+            &pk.0,
+            Time::from(0),
+            F::from(0),
+            (),
+        )
+        .map_err(err)?;
+        Ok(PyBytes::new_bound(py, &exec.to_bytes(Compress::No)))
+    }
+
+    /// Scan the next callback, returning the executed method as a wire envelope.
+    fn scan_callbacks<'py>(
+        &mut self,
+        py: Python<'py>,
+        pk: &ProvingKey,
+        bul: &Bulletin,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let exec = exec_scanint(
+            &mut self.0,
+            &mut OsRng,
+            &bul.0.obj_bul,
+            &pk.0,
+            &bul.0.callback_bul,
+            bul.0.callback_bul.get_epoch(),
+        )
+        .map_err(err)?;
+        Ok(PyBytes::new_bound(py, &exec.to_bytes(Compress::No)))
+    }
+}
+
+/// The proof in an executed standard interaction or scan, serialized.
+#[pyfunction]
+fn proof_of<'py>(py: Python<'py>, exec: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+    if let Ok(exec) = ExecutedMethod::<F, Snark, Args, Cr, 1>::from_bytes(exec) {
+        return Ok(ser(py, &exec.proof));
+    }
+    let exec: ExecutedMethod<F, Snark, Args, Cr, 0> =
+        ExecutedMethod::from_bytes(exec).map_err(err)?;
+    Ok(ser(py, &exec.proof))
+}
+
+#[pymodule]
+fn zk_callbacks_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<User>()?;
+    m.add_class::<Bulletin>()?;
+    m.add_class::<ProvingKey>()?;
+    m.add_class::<VerifyingKey>()?;
+    m.add_function(wrap_pyfunction!(proof_of, m)?)?;
+    Ok(())
+}
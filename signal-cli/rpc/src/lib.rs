@@ -0,0 +1,17 @@
+//! gRPC interface to the wispy bulletin.
+//!
+//! The service is defined in `proto/wispy.proto`, so services written in other languages can
+//! generate their own clients from it. This crate holds the generated Rust client and server.
+
+/// Generated types for the `wispy.bulletin.v1` package.
+pub mod bulletin {
+    tonic::include_proto!("wispy.bulletin.v1");
+}
+
+pub use bulletin::{
+    bulletin_client::BulletinClient,
+    bulletin_server::{Bulletin, BulletinServer},
+};
+
+/// The address the server listens on for gRPC by default.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:50051";
@@ -0,0 +1,267 @@
+//! Endpoints for the operators of the server, wrapped by the `wispy-admin` CLI.
+//!
+//! The routes are nested under `/api/admin`, and every request must carry an API key with the
+//! [`Scope::Admin`](crate::auth::Scope::Admin) scope.
+
+use crate::auth::{self, Guard};
+use crate::error::ApiError;
+use crate::helpers::{
+    all_callbacks, find_callback_by_timestamp, get_reputation_by_cb, pending_callback_count,
+    record_applied_reputation,
+};
+use crate::persist;
+use crate::server::{call_ticket, find_group, GroupQuery, ServerLock};
+use ark_bn254::Fr;
+use ark_serialize::{CanonicalDeserialize, Compress, Validate};
+use axum::{
+    extract::{Json, Query, State},
+    middleware,
+    routing::{get, post},
+    Router,
+};
+use common::{
+    zk::{arg_ban, arg_rep, MsgUser},
+    F,
+};
+use hex::FromHex;
+use rand::rngs::OsRng;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::info;
+use zk_callbacks::{
+    generic::{bulletin::PublicUserBul, callbacks::CallbackCom},
+    impls::centralized::{crypto::PlainTikCrypto, ds::sigstore::GRSchnorrObjStore},
+};
+
+type Ticket = CallbackCom<Fr, Fr, PlainTikCrypto<Fr>>;
+
+/// The admin routes, answered only for requests passing `guard`.
+pub fn router(guard: Guard) -> Router<ServerLock> {
+    Router::new()
+        .route("/callbacks", get(handle_list_callbacks))
+        .route("/callbacks/force", post(handle_force_callback))
+        .route("/ban", post(handle_ban_ticket))
+        .route("/bulletin", get(handle_bulletin_stats))
+        .route("/epoch", post(handle_advance_epoch))
+        .route("/snapshot", get(handle_snapshot))
+        .route_layer(middleware::from_fn_with_state(guard, auth::require))
+}
+
+fn decode_ticket(bytes: &[u8]) -> Result<Ticket, ApiError> {
+    Ok(Ticket::deserialize_with_mode(
+        bytes,
+        Compress::No,
+        Validate::Yes,
+    )?)
+}
+
+/// Call a ticket in a group and advance the epoch, so the call is seen by the next scan.
+async fn call_and_advance(
+    state: &ServerLock,
+    group: &str,
+    ticket: Ticket,
+    arg: Fr,
+) -> Result<Json<Value>, ApiError> {
+    let group_state = find_group(state, group)?;
+    let mut bulletins = group_state.bulletins.write().await;
+    let db = &mut bulletins.store;
+    call_ticket(db, ticket, arg)?;
+    db.callback_bul.update_epoch(&mut OsRng);
+    state.events.epoch_updated(group, db, true);
+    let epoch = db.callback_bul.get_epoch();
+    bulletins.persist(group);
+    Ok(Json(serde_json::json!({ "epoch": epoch.to_string() })))
+}
+
+/// The callbacks attached to sent messages, and how many are waiting for their message.
+#[tracing::instrument(skip_all)]
+pub async fn handle_list_callbacks() -> Result<Json<Value>, ApiError> {
+    info!("[ADMIN] List callbacks");
+    let callbacks: Vec<Value> = all_callbacks()?
+        .into_iter()
+        .map(|(timestamp, cb, reputation)| {
+            serde_json::json!({ "timestamp": timestamp, "callback": cb, "reputation": reputation })
+        })
+        .collect();
+    Ok(Json(serde_json::json!({
+        "callbacks": callbacks,
+        "pending": pending_callback_count()?,
+    })))
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CallAction {
+    #[default]
+    Ban,
+    Rep,
+}
+
+#[derive(Deserialize)]
+pub struct ForceCallback {
+    /// The timestamp of the message whose callback is called.
+    timestamp: u64,
+    #[serde(default)]
+    action: CallAction,
+    /// The reputation to call a `rep` callback with; the stored reputation of the message when
+    /// not given.
+    reputation: Option<i64>,
+}
+
+/// Call the callback attached to a message, as a ban or a reputation update.
+#[tracing::instrument(skip_all)]
+pub async fn handle_force_callback(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+    Json(input): Json<ForceCallback>,
+) -> Result<Json<Value>, ApiError> {
+    info!("[ADMIN] Force callback of {}", input.timestamp);
+    let bytes = find_callback_by_timestamp(input.timestamp)
+        .map_err(|e| ApiError::NotFound(e.to_string()))?;
+    let ticket = decode_ticket(&bytes)?;
+    let cb_hex = hex::encode(&bytes);
+    let rep = match input.action {
+        CallAction::Ban => None,
+        CallAction::Rep => Some(match input.reputation {
+            Some(rep) => rep,
+            None => get_reputation_by_cb(&cb_hex).map_err(|e| ApiError::NotFound(e.to_string()))?,
+        }),
+    };
+    let arg = rep.map_or_else(arg_ban, arg_rep);
+    let response = call_and_advance(&state, &query.group, ticket, arg).await?;
+    if let Some(rep) = rep {
+        record_applied_reputation(&cb_hex, &query.group, rep)?;
+    }
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct BanTicket {
+    /// The hex encoded callback ticket.
+    ticket: String,
+}
+
+/// Ban the user holding a ticket, for tickets not attached to a stored message.
+#[tracing::instrument(skip_all)]
+pub async fn handle_ban_ticket(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+    Json(input): Json<BanTicket>,
+) -> Result<Json<Value>, ApiError> {
+    info!("[ADMIN] Ban by ticket");
+    let bytes = Vec::from_hex(input.ticket.trim()).map_err(ApiError::malformed)?;
+    let ticket = decode_ticket(&bytes)?;
+    call_and_advance(&state, &query.group, ticket, arg_ban()).await
+}
+
+/// The sizes of a group's bulletins.
+#[tracing::instrument(skip_all)]
+pub async fn handle_bulletin_stats(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+) -> Result<Json<Value>, ApiError> {
+    info!("[ADMIN] Bulletin stats");
+    let group = find_group(&state, &query.group)?;
+    let bulletins = group.bulletins.read().await;
+    let db = &bulletins.store;
+    let anonymity_set =
+        <GRSchnorrObjStore as PublicUserBul<F, MsgUser>>::anonymity_set_size(&db.obj_bul);
+    Ok(Json(serde_json::json!({
+        "group": query.group,
+        "objects": db.obj_bul.coms.len(),
+        "anonymity_set": anonymity_set,
+        "log_head": db.obj_bul.log_head().to_string(),
+        "tickets": db.cb_tickets.len(),
+        "called_callbacks": db.callback_bul.memb_called_cbs.len(),
+        "tombstones": db.callback_bul.tombstones.len(),
+        "epochs": db.callback_bul.epochs.len(),
+        "epoch": db.callback_bul.get_epoch().to_string(),
+    })))
+}
+
+/// Advance the epoch of a group's callback bulletin.
+#[tracing::instrument(skip_all)]
+pub async fn handle_advance_epoch(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+) -> Result<Json<Value>, ApiError> {
+    info!("[ADMIN] Advance epoch of {}", query.group);
+    let group = find_group(&state, &query.group)?;
+    let mut bulletins = group.bulletins.write().await;
+    bulletins.store.callback_bul.update_epoch(&mut OsRng);
+    state
+        .events
+        .epoch_updated(&query.group, &bulletins.store, false);
+    let epoch = bulletins.store.callback_bul.get_epoch();
+    bulletins.persist(&query.group);
+    Ok(Json(serde_json::json!({ "epoch": epoch.to_string() })))
+}
+
+/// Every tree of the database, hex encoded, along with the hosted groups.
+#[tracing::instrument(skip_all)]
+pub async fn handle_snapshot(State(state): State<ServerLock>) -> Result<Json<Value>, ApiError> {
+    info!("[ADMIN] Snapshot");
+    let trees = tokio::task::spawn_blocking(persist::snapshot)
+        .await
+        .map_err(ApiError::internal)??;
+    Ok(Json(serde_json::json!({
+        "groups": state.groups(),
+        "trees": trees,
+    })))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::Config, testing};
+    use ark_serialize::CanonicalSerialize;
+    use axum::http::StatusCode;
+
+    // A banned ticket is called on the bulletin and moves the group to a new epoch
+    #[tokio::test]
+    async fn ban_ticket() {
+        let state = testing::server(Config::default(), "admin");
+        testing::add_group(&state, "admin", 2);
+        let query = |group: &str| Query(GroupQuery { group: group.to_string() });
+        let ban = |group: &str, ticket: String| {
+            handle_ban_ticket(State(state.clone()), query(group), Json(BanTicket { ticket }))
+        };
+        let mut ticket = vec![];
+        Ticket::default()
+            .serialize_with_mode(&mut ticket, Compress::No)
+            .unwrap();
+
+        let err = ban("admin", "not hex".to_string()).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        let err = ban("missing", hex::encode(&ticket)).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let Json(before) = handle_bulletin_stats(State(state.clone()), query("admin"))
+            .await
+            .unwrap();
+        let Json(banned) = ban("admin", hex::encode(&ticket)).await.unwrap();
+        assert_ne!(banned["epoch"], before["epoch"]);
+        let Json(after) = handle_bulletin_stats(State(state.clone()), query("admin"))
+            .await
+            .unwrap();
+        assert_eq!(after["called_callbacks"], 1);
+        assert_eq!(after["epoch"], banned["epoch"]);
+    }
+
+    // Only messages with a stored callback can have it called
+    #[tokio::test]
+    async fn force_unknown_callback() {
+        let state = testing::server(Config::default(), "admin-force");
+        testing::add_group(&state, "admin-force", 2);
+        let input = ForceCallback {
+            timestamp: 4846001,
+            action: CallAction::Rep,
+            reputation: None,
+        };
+        let query = Query(GroupQuery { group: "admin-force".to_string() });
+        let err = handle_force_callback(State(state), query, Json(input))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+}
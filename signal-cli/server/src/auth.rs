@@ -0,0 +1,293 @@
+//! Authentication of moderators and operators, and rate limiting of proof submissions.
+//!
+//! Sensitive routes (bans, reputation updates, key rotation, group creation, and the admin API)
+//! require an API key, sent as `Authorization: Bearer <key>`. Every key has a name and a set of
+//! [`Scope`]s; the keys are read from a JSON file holding only their SHA-256 digests:
+//!
+//! ```json
+//! [{ "name": "alice", "key_sha256": "<hex>", "scopes": ["moderate"] }]
+//! ```
+//!
+//! Proof submissions are open to everyone, but limited per client IP by [`IpRateLimit`].
+
+use crate::error::ApiError;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use hex::FromHex;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+/// What an API key may do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Ban users and update their reputation.
+    Moderate,
+    /// Rotate SNARK keys.
+    Keys,
+    /// Create groups.
+    Groups,
+    /// Use the admin API.
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::Moderate, Scope::Keys, Scope::Groups, Scope::Admin];
+}
+
+#[derive(Deserialize)]
+struct KeyEntry {
+    name: String,
+    key_sha256: String,
+    scopes: BTreeSet<Scope>,
+}
+
+struct ApiKey {
+    name: String,
+    scopes: BTreeSet<Scope>,
+}
+
+/// The API keys the server accepts, by the digest of the key.
+#[derive(Default)]
+pub struct ApiKeys {
+    keys: HashMap<[u8; 32], ApiKey>,
+}
+
+impl ApiKeys {
+    /// Load the keys in the file at `path`. A missing file holds no keys.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut keys = Self::default();
+        if !path.exists() {
+            return Ok(keys);
+        }
+        let file = std::fs::read(path)
+            .with_context(|| format!("failed to read API keys from {}", path.display()))?;
+        let entries: Vec<KeyEntry> = serde_json::from_slice(&file)
+            .with_context(|| format!("invalid API keys in {}", path.display()))?;
+        for entry in entries {
+            let digest = <[u8; 32]>::from_hex(entry.key_sha256.trim())
+                .with_context(|| format!("invalid digest for API key {}", entry.name))?;
+            keys.keys.insert(
+                digest,
+                ApiKey {
+                    name: entry.name,
+                    scopes: entry.scopes,
+                },
+            );
+        }
+        Ok(keys)
+    }
+
+    /// Accept `key` for the given scopes.
+    pub fn insert(&mut self, name: &str, key: &str, scopes: impl IntoIterator<Item = Scope>) {
+        self.keys.insert(
+            Sha256::digest(key.as_bytes()).into(),
+            ApiKey {
+                name: name.to_string(),
+                scopes: scopes.into_iter().collect(),
+            },
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Check the bearer token of a request grants `scope`, returning the name of its key.
+    ///
+    /// Keys are looked up by their digest, so a lookup does not leak how much of a key was right.
+    fn authorize(&self, request: &Request, scope: Scope) -> Result<&str, ApiError> {
+        let token = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| ApiError::Unauthorized("missing API key".to_string()))?;
+        let digest: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        let key = self
+            .keys
+            .get(&digest)
+            .ok_or_else(|| ApiError::Unauthorized("unknown API key".to_string()))?;
+        if !key.scopes.contains(&scope) {
+            return Err(ApiError::Forbidden(format!(
+                "API key {} may not {:?}",
+                key.name, scope
+            )));
+        }
+        Ok(&key.name)
+    }
+
+    /// A guard for routes which require `scope`, for [`require`].
+    pub fn guard(self: &Arc<Self>, scope: Scope) -> Guard {
+        Guard {
+            keys: self.clone(),
+            scope,
+        }
+    }
+}
+
+/// The keys and scope a route is guarded by.
+#[derive(Clone)]
+pub struct Guard {
+    keys: Arc<ApiKeys>,
+    scope: Scope,
+}
+
+/// Middleware rejecting requests whose API key does not grant the scope of the guard.
+pub async fn require(
+    State(guard): State<Guard>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let name = guard.keys.authorize(&request, guard.scope)?;
+    info!(
+        "[AUTH] {} {} as {}",
+        request.method(),
+        request.uri().path(),
+        name
+    );
+    Ok(next.run(request).await)
+}
+
+/// How many clients [`IpRateLimit`] tracks before it forgets idle ones.
+const MAX_TRACKED_IPS: usize = 10_000;
+
+/// Limits each client IP to `max_requests` within every `window`.
+pub struct IpRateLimit {
+    max_requests: usize,
+    window: Duration,
+    hits: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+}
+
+impl IpRateLimit {
+    pub fn new(max_requests: usize, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a request from `ip`, returning whether it is within the limit.
+    fn admit(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+        if hits.len() > MAX_TRACKED_IPS {
+            hits.retain(|_, h| {
+                h.back()
+                    .is_some_and(|t| now.duration_since(*t) < self.window)
+            });
+        }
+        let times = hits.entry(ip).or_default();
+        while times
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= self.window)
+        {
+            times.pop_front();
+        }
+        if times.len() >= self.max_requests {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+/// Middleware rejecting requests from clients which exceeded the rate limit.
+pub async fn rate_limit(
+    State(limit): State<Arc<IpRateLimit>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !limit.admit(addr.ip()) {
+        return Err(ApiError::RateLimited(format!(
+            "at most {} proofs may be submitted every {}s",
+            limit.max_requests,
+            limit.window.as_secs()
+        )));
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{body::Body, http::StatusCode};
+
+    fn request(token: Option<&str>) -> Request {
+        let mut request = Request::builder().uri("/api/ban");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    // A key must be sent, known, and grant the scope of the route
+    #[test]
+    fn authorize_scopes() {
+        let mut keys = ApiKeys::default();
+        keys.insert("alice", "secret", [Scope::Moderate]);
+
+        let status = |token, scope| {
+            keys.authorize(&request(token), scope)
+                .map_err(|e| e.status())
+        };
+        assert_eq!(status(None, Scope::Moderate), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(Some("guess"), Scope::Moderate), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(status(Some("secret"), Scope::Admin), Err(StatusCode::FORBIDDEN));
+        assert_eq!(status(Some("secret"), Scope::Moderate), Ok("alice"));
+    }
+
+    // Keys are loaded from their digests, and a missing file holds none
+    #[test]
+    fn load_digests() {
+        let dir = std::env::temp_dir().join(format!("wispy-auth-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(ApiKeys::load(dir.join("missing.json")).unwrap().is_empty());
+
+        let path = dir.join("keys.json");
+        let digest = hex::encode(Sha256::digest(b"secret"));
+        let entries = format!(
+            r#"[{{ "name": "bob", "key_sha256": "{}", "scopes": ["keys", "groups"] }}]"#,
+            digest
+        );
+        std::fs::write(&path, entries).unwrap();
+        let keys = ApiKeys::load(&path).unwrap();
+        assert_eq!(keys.authorize(&request(Some("secret")), Scope::Groups).ok(), Some("bob"));
+
+        std::fs::write(&path, r#"[{ "name": "bob", "key_sha256": "zz", "scopes": [] }]"#).unwrap();
+        assert!(ApiKeys::load(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Every client IP has its own window of requests
+    #[test]
+    fn limit_per_ip() {
+        let limit = IpRateLimit::new(2, Duration::from_secs(60));
+        let a: IpAddr = [10, 0, 0, 1].into();
+        let b: IpAddr = [10, 0, 0, 2].into();
+        assert!(limit.admit(a));
+        assert!(limit.admit(a));
+        assert!(!limit.admit(a));
+        assert!(limit.admit(b));
+
+        let limit = IpRateLimit::new(1, Duration::ZERO);
+        assert!(limit.admit(a));
+        assert!(limit.admit(a));
+    }
+}
@@ -0,0 +1,199 @@
+//! Earning badges.
+//!
+//! A badge is issued to the author of a message once the message has enough of a reaction, by
+//! calling the callback of the message with the badge's argument: the badge is written into the
+//! author's user state on their next scan, after which they can prove they hold it with
+//! `/api/badges`. The badges which can be earned are set by `badges` in the server
+//! configuration, and listed by `/api/badges/criteria`:
+//!
+//! ```toml
+//! [[badges]]
+//! badge = 1
+//! name = "trusted poster"
+//! description = "Wrote a post with 100 upvotes"
+//! reaction = "upvote"
+//! count = 100
+//! ```
+
+use crate::error::ApiError;
+use crate::helpers::{claim_badge, release_badge};
+use crate::server::{call_message, emoji_to_name, string_to_emoji, ServerLock};
+use crate::ServerState;
+use anyhow::{bail, Result};
+use axum::extract::{Json, State};
+use common::zk::{arg_badge, BADGE_HELD, NUM_BADGES};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Badge {
+    /// The badge slot, numbered from 1.
+    pub badge: u64,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The reaction counted, as an emoji or its name.
+    #[serde(default = "upvote")]
+    pub reaction: String,
+    /// How many of the reaction a message needs for its author to earn the badge.
+    pub count: u32,
+}
+
+fn upvote() -> String {
+    "upvote".to_string()
+}
+
+/// The badges earned when no others are configured.
+pub fn default_badges() -> Vec<Badge> {
+    vec![Badge {
+        badge: 1,
+        name: "trusted poster".to_string(),
+        description: "Wrote a post with 100 upvotes".to_string(),
+        reaction: upvote(),
+        count: 100,
+    }]
+}
+
+/// The name of a reaction given as an emoji or a name, `unknown` if it is neither.
+fn reaction_name(reaction: &str) -> &'static str {
+    emoji_to_name(string_to_emoji(reaction))
+}
+
+/// Check that every badge is in a slot of its own and is earned with a known reaction.
+pub fn check(badges: &[Badge]) -> Result<()> {
+    for (i, badge) in badges.iter().enumerate() {
+        if !(1..=NUM_BADGES).contains(&badge.badge) {
+            bail!(
+                "badge {:?} is in slot {}, badges are numbered 1 to {}",
+                badge.name,
+                badge.badge,
+                NUM_BADGES
+            );
+        }
+        if badges[..i].iter().any(|b| b.badge == badge.badge) {
+            bail!("more than one badge is in slot {}", badge.badge);
+        }
+        if reaction_name(&badge.reaction) == "unknown" {
+            bail!(
+                "badge {:?} is earned with unknown reaction {:?}",
+                badge.name,
+                badge.reaction
+            );
+        }
+        if badge.count == 0 {
+            bail!("badge {:?} has a count of 0", badge.name);
+        }
+    }
+    Ok(())
+}
+
+/// Issue the badges the count of `reaction` (by name) on the message sent at `timestamp` going
+/// from `before` to `after` earns its author, calling the callback of the message in `group`.
+/// Each badge is issued once per message.
+pub(crate) async fn issue_earned(
+    state: &ServerState,
+    group: &str,
+    timestamp: u64,
+    reaction: &str,
+    before: u32,
+    after: u32,
+) -> Result<(), ApiError> {
+    let earned =
+        |b: &&Badge| reaction_name(&b.reaction) == reaction && before < b.count && after >= b.count;
+    for badge in state.config.badges.iter().filter(earned) {
+        if !claim_badge(timestamp, badge.badge)? {
+            continue;
+        }
+        info!(
+            "[BADGES] Issuing {:?} for message {}",
+            badge.name, timestamp
+        );
+        let issued = call_message(state, group, timestamp, arg_badge(badge.badge)).await;
+        if let Err(e) = issued {
+            // Messages without a callback, like polls, earn nothing
+            warn!(
+                "[BADGES] Failed to issue {:?} for message {}: {}",
+                badge.name, timestamp, e
+            );
+            release_badge(timestamp, badge.badge)?;
+        }
+    }
+    Ok(())
+}
+
+/// The badges which can be earned, and the value a held badge has, to claim with
+/// `/api/badges`.
+#[tracing::instrument(skip_all)]
+pub async fn handle_badge_criteria(State(state): State<ServerLock>) -> Json<Value> {
+    Json(json!({
+        "badges": state.config.badges,
+        "value": BADGE_HELD,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::helpers::attach_callback;
+    use crate::{persist, testing};
+
+    fn badge(badge: u64, reaction: &str, count: u32) -> Badge {
+        Badge {
+            badge,
+            name: format!("badge {}", badge),
+            description: String::new(),
+            reaction: reaction.to_string(),
+            count,
+        }
+    }
+
+    // Badges are in slots of their own, earned with a known reaction
+    #[test]
+    fn check_badges() {
+        check(&default_badges()).unwrap();
+        check(&[badge(1, "👍", 1), badge(NUM_BADGES, "hatespeech", 5)]).unwrap();
+        assert!(check(&[badge(0, "upvote", 1)]).is_err());
+        assert!(check(&[badge(NUM_BADGES + 1, "upvote", 1)]).is_err());
+        assert!(check(&[badge(1, "upvote", 1), badge(1, "downvote", 1)]).is_err());
+        assert!(check(&[badge(1, "🦀", 1)]).is_err());
+        assert!(check(&[badge(1, "upvote", 0)]).is_err());
+    }
+
+    // A badge is issued once, when a message reaches its count
+    #[tokio::test]
+    async fn issue_once() {
+        persist::open_temp();
+        attach_callback(4859001, testing::ticket(4859001)).unwrap();
+        let config = Config {
+            badges: vec![badge(1, "upvote", 2), badge(2, "downvote", 1)],
+            ..Config::default()
+        };
+        let state = testing::server(config, "badges");
+        testing::add_group(&state, "badges", 2);
+        let called = || async {
+            let group = state.group("badges").unwrap();
+            let called = group.bulletins.read().await.store.callback_bul.memb_called_cbs.len();
+            called
+        };
+
+        issue_earned(&state, "badges", 4859001, "upvote", 0, 1).await.unwrap();
+        assert_eq!(called().await, 0);
+        issue_earned(&state, "badges", 4859001, "upvote", 1, 2).await.unwrap();
+        assert_eq!(called().await, 1);
+        issue_earned(&state, "badges", 4859001, "upvote", 1, 2).await.unwrap();
+        assert_eq!(called().await, 1);
+
+        // A message without a callback earns nothing, and may earn the badge once it has one
+        issue_earned(&state, "badges", 4859002, "downvote", 0, 1).await.unwrap();
+        assert_eq!(called().await, 1);
+        assert!(claim_badge(4859002, 2).unwrap());
+
+        let Json(criteria) = handle_badge_criteria(State(state.clone())).await;
+        assert_eq!(criteria["badges"][0]["name"], "badge 1");
+        assert_eq!(criteria["badges"][1]["reaction"], "downvote");
+        assert_eq!(criteria["value"], BADGE_HELD);
+    }
+}
@@ -0,0 +1,160 @@
+//! A command line wrapper around the admin API of the wispy server.
+//!
+//! The token is read from `--token`, or from `SERVER_ADMIN_TOKEN` when not given, so the same
+//! environment can run both the server and this tool.
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde_json::Value;
+use std::{fs, path::PathBuf};
+
+/// Administer a wispy server.
+#[derive(Parser)]
+#[command(name = "wispy-admin")]
+struct Cli {
+    /// Address of the server
+    #[arg(long, default_value = "http://127.0.0.1:3000")]
+    server: String,
+
+    /// API key with the admin scope (defaults to SERVER_ADMIN_TOKEN)
+    #[arg(long)]
+    token: Option<String>,
+
+    /// Group to act on
+    #[arg(long, short = 'g', default_value = "default")]
+    group: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the callbacks attached to sent messages
+    Callbacks,
+
+    /// Call the callback attached to a message
+    ForceCallback {
+        /// Timestamp of the message
+        #[arg(long, short = 't')]
+        timestamp: u64,
+
+        /// Call it as a reputation update rather than a ban
+        #[arg(long)]
+        rep: bool,
+
+        /// Reputation to call it with (defaults to the reputation of the message)
+        #[arg(long, requires = "rep")]
+        reputation: Option<i64>,
+    },
+
+    /// Ban the user holding a callback ticket
+    Ban {
+        /// Hex encoded ticket
+        ticket: String,
+    },
+
+    /// Show the sizes of the group's bulletins
+    Bulletin,
+
+    /// Advance the epoch of the group's callback bulletin
+    Epoch,
+
+    /// Export a snapshot of the server database
+    Snapshot {
+        /// File to write the snapshot to (defaults to stdout)
+        #[arg(long, short = 'o', value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+}
+
+struct Admin {
+    client: Client,
+    server: String,
+    token: String,
+    group: String,
+}
+
+impl Admin {
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/admin{}", self.server.trim_end_matches('/'), path)
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.client.get(self.url(path))
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.client.post(self.url(path))
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<Value> {
+        let response = request
+            .bearer_auth(&self.token)
+            .query(&[("group", &self.group)])
+            .send()
+            .context("failed to reach the server")?;
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .context("the server sent an invalid response")?;
+        if !status.is_success() {
+            bail!(
+                "{} ({}): {}",
+                body["error"].as_str().unwrap_or("error"),
+                status,
+                body["detail"].as_str().unwrap_or_default()
+            );
+        }
+        Ok(body)
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let token = match cli.token {
+        Some(token) => token,
+        None => std::env::var("SERVER_ADMIN_TOKEN")
+            .context("no admin token, pass --token or set SERVER_ADMIN_TOKEN")?,
+    };
+    let admin = Admin {
+        client: Client::new(),
+        server: cli.server,
+        token,
+        group: cli.group,
+    };
+
+    let body = match cli.command {
+        Command::Callbacks => admin.send(admin.get("/callbacks"))?,
+        Command::ForceCallback {
+            timestamp,
+            rep,
+            reputation,
+        } => admin.send(admin.post("/callbacks/force").json(&serde_json::json!({
+            "timestamp": timestamp,
+            "action": if rep { "rep" } else { "ban" },
+            "reputation": reputation,
+        })))?,
+        Command::Ban { ticket } => admin.send(
+            admin
+                .post("/ban")
+                .json(&serde_json::json!({ "ticket": ticket })),
+        )?,
+        Command::Bulletin => admin.send(admin.get("/bulletin"))?,
+        Command::Epoch => admin.send(admin.post("/epoch"))?,
+        Command::Snapshot { out } => {
+            let body = admin.send(admin.get("/snapshot"))?;
+            if let Some(out) = out {
+                fs::write(&out, serde_json::to_string_pretty(&body)?)
+                    .with_context(|| format!("failed to write {}", out.display()))?;
+                println!("Wrote snapshot to {}", out.display());
+                return Ok(());
+            }
+            body
+        }
+    };
+
+    println!("{}", serde_json::to_string_pretty(&body)?);
+    Ok(())
+}
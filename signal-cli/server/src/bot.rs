@@ -0,0 +1,320 @@
+//! The Signal bot, reached over the JSON-RPC interface of the signal-cli daemon.
+//!
+//! Requests are spread over a small pool of TCP connections, each opened on first use and reopened
+//! when the daemon drops it, so sending a message costs one round trip rather than spawning a
+//! process.
+//!
+//! The bot may be several Signal accounts, all registered with the same daemon: each Signal
+//! group is sent to by the account configured for it in `group_accounts`, or by `bot_number`.
+
+use crate::config::SignalConfig;
+use crate::error::ApiError;
+use jsonrpsee::{
+    async_client::Client,
+    core::{
+        client::{ClientT, Error, Subscription, SubscriptionClientT},
+        params::ObjectParams,
+    },
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// How long to wait before the first retry; each further retry waits twice as long.
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// What signal-cli answers to `send`.
+#[derive(Deserialize)]
+struct SendResponse {
+    timestamp: u64,
+}
+
+pub struct Bot {
+    config: SignalConfig,
+    connections: Vec<Mutex<Option<Arc<Client>>>>,
+    next: AtomicUsize,
+}
+
+impl Bot {
+    /// A bot which connects to the daemon on its first request.
+    pub fn new(config: SignalConfig) -> Self {
+        let connections = (0..config.connections.max(1))
+            .map(|_| Mutex::new(None))
+            .collect();
+        Self {
+            config,
+            connections,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The account which sends to the Signal group `group_id`.
+    pub fn account(&self, group_id: &str) -> &str {
+        self.config
+            .group_accounts
+            .get(group_id)
+            .unwrap_or(&self.config.bot_number)
+    }
+
+    /// The account used when no group is given.
+    pub fn default_account(&self) -> &str {
+        &self.config.bot_number
+    }
+
+    /// Every account of the bot.
+    pub fn accounts(&self) -> BTreeSet<&str> {
+        self.config
+            .group_accounts
+            .values()
+            .map(String::as_str)
+            .chain([self.default_account()])
+            .collect()
+    }
+
+    /// The next connection of the pool, opening it if it is not open.
+    async fn connection(&self) -> Result<Arc<Client>, ApiError> {
+        let slot = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        let mut connection = self.connections[slot].lock().await;
+        if let Some(client) = connection.as_ref().filter(|c| c.is_connected()) {
+            return Ok(client.clone());
+        }
+        let client = Arc::new(self.connect().await?);
+        *connection = Some(client.clone());
+        Ok(client)
+    }
+
+    async fn connect(&self) -> Result<Client, ApiError> {
+        info!("[SIGNAL] Connecting to {}", self.config.tcp);
+        signal_cli_client::connect_tcp_with_timeout(
+            self.config.tcp.as_str(),
+            Duration::from_secs(self.config.timeout_secs),
+        )
+        .await
+        .map_err(|e| ApiError::Signal(format!("failed to connect to signal-cli: {}", e)))
+    }
+
+    /// Subscribe to the messages `account` receives, on a connection of its own. The
+    /// subscription ends when the connection is dropped, so the connection is returned along
+    /// with it.
+    pub async fn subscribe_receive(
+        &self,
+        account: &str,
+    ) -> Result<(Client, Subscription<Value>), ApiError> {
+        let client = self.connect().await?;
+        let mut params = ObjectParams::new();
+        params
+            .insert("account", account)
+            .map_err(ApiError::internal)?;
+        let subscription = client
+            .subscribe("subscribeReceive", params, "unsubscribeReceive")
+            .await
+            .map_err(|e| ApiError::Signal(e.to_string()))?;
+        Ok((client, subscription))
+    }
+
+    /// Call `method` as `account`, retrying when the connection fails. Errors returned by
+    /// signal-cli itself are not retried, as the request may have gone through.
+    async fn call<R: DeserializeOwned>(
+        &self,
+        account: &str,
+        method: &str,
+        params: &[(&str, Value)],
+    ) -> Result<R, ApiError> {
+        let mut backoff = RETRY_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            let mut object = ObjectParams::new();
+            object
+                .insert("account", account)
+                .map_err(ApiError::internal)?;
+            for (name, value) in params {
+                object.insert(name, value).map_err(ApiError::internal)?;
+            }
+
+            let error = match self.connection().await {
+                Ok(client) => match client.request(method, object).await {
+                    Ok(response) => return Ok(response),
+                    Err(Error::Call(e)) => return Err(ApiError::Signal(e.message().to_string())),
+                    Err(e) => ApiError::Signal(e.to_string()),
+                },
+                Err(e) => e,
+            };
+            if attempt >= self.config.retries {
+                return Err(error);
+            }
+            warn!("[SIGNAL] {} failed, retrying: {}", method, error);
+            attempt += 1;
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    /// Send `message` to the Signal group `group_id` as the group's account, quoting its message
+    /// sent at `quote` if given, and return the timestamp of the sent message.
+    pub async fn send(
+        &self,
+        group_id: &str,
+        message: &str,
+        quote: Option<u64>,
+    ) -> Result<u64, ApiError> {
+        self.send_with_attachments(group_id, message, quote, &[])
+            .await
+    }
+
+    /// Like [`Bot::send`], along with `attachments`, given as paths or as data URIs
+    /// (`data:<type>;filename=<name>;base64,<data>`).
+    pub async fn send_with_attachments(
+        &self,
+        group_id: &str,
+        message: &str,
+        quote: Option<u64>,
+        attachments: &[String],
+    ) -> Result<u64, ApiError> {
+        let account = self.account(group_id);
+        let mut params = vec![("groupId", json(group_id)), ("message", json(message))];
+        if !attachments.is_empty() {
+            params.push(("attachments", json(attachments)));
+        }
+        if let Some(ts) = quote {
+            params.extend([
+                ("quoteTimestamp", json(ts)),
+                ("quoteAuthor", json(account)),
+                ("quoteMessage", json("")),
+            ]);
+        }
+        let sent: SendResponse = self.call(account, "send", &params).await?;
+        info!("[SIGNAL] Sent message {} as {}", sent.timestamp, account);
+        Ok(sent.timestamp)
+    }
+
+    /// Send `message` to the Signal account `recipient` alone, as the default account, and return
+    /// the timestamp of the sent message.
+    pub async fn send_direct(&self, recipient: &str, message: &str) -> Result<u64, ApiError> {
+        let account = self.default_account();
+        let params = [("recipient", json([recipient])), ("message", json(message))];
+        let sent: SendResponse = self.call(account, "send", &params).await?;
+        info!(
+            "[SIGNAL] Sent direct message {} as {}",
+            sent.timestamp, account
+        );
+        Ok(sent.timestamp)
+    }
+
+    /// Set the name, about text and avatar `account` is shown with.
+    pub async fn update_profile(
+        &self,
+        account: &str,
+        given_name: &str,
+        family_name: &str,
+        about: &str,
+        avatar: &str,
+    ) -> Result<Value, ApiError> {
+        self.call(
+            account,
+            "updateProfile",
+            &[
+                ("givenName", json(given_name)),
+                ("familyName", json(family_name)),
+                ("about", json(about)),
+                ("avatar", json(avatar)),
+            ],
+        )
+        .await
+    }
+}
+
+fn json(value: impl Serialize) -> Value {
+    serde_json::to_value(value).expect("plain values serialize")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+    use axum::http::StatusCode;
+
+    fn config(tcp: String, retries: u32) -> SignalConfig {
+        SignalConfig {
+            tcp,
+            retries,
+            group_accounts: [("g2".to_string(), "+2".to_string())].into(),
+            ..SignalConfig::default()
+        }
+    }
+
+    // Each group is sent to by its own account, or by the default one
+    #[test]
+    fn accounts_by_group() {
+        let bot = Bot::new(config(String::new(), 0));
+        assert_eq!(bot.account("g2"), "+2");
+        assert_eq!(bot.account("g1"), bot.default_account());
+        assert_eq!(bot.accounts(), BTreeSet::from(["+15712811486", "+2"]));
+    }
+
+    // Messages are sent as the account of their group and quote as that account
+    #[tokio::test]
+    async fn send_as_group_account() {
+        let (tcp, requests) =
+            testing::signal_daemon(|_| Some(Ok(serde_json::json!({ "timestamp": 42 })))).await;
+        let bot = Bot::new(config(tcp, 0));
+
+        assert_eq!(bot.send("g1", "hi", Some(7)).await.unwrap(), 42);
+        assert_eq!(bot.send("g2", "hey", None).await.unwrap(), 42);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0]["method"], "send");
+        let params = &requests[0]["params"];
+        assert_eq!(params["account"], "+15712811486");
+        assert_eq!(params["groupId"], "g1");
+        assert_eq!(params["message"], "hi");
+        assert_eq!(params["quoteTimestamp"], 7);
+        assert_eq!(params["quoteAuthor"], "+15712811486");
+        assert_eq!(requests[1]["params"]["account"], "+2");
+        assert!(requests[1]["params"].get("quoteTimestamp").is_none());
+    }
+
+    // An error from signal-cli is not retried, as the message may have been sent
+    #[tokio::test]
+    async fn call_error_not_retried() {
+        let (tcp, requests) =
+            testing::signal_daemon(|_| Some(Err("Unregistered user".to_string()))).await;
+        let bot = Bot::new(config(tcp, 3));
+        let err = bot.send_direct("+3", "hi").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+        assert!(err.to_string().contains("Unregistered user"));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    // A dropped connection is reopened and the request retried
+    #[tokio::test]
+    async fn dropped_connection_retried() {
+        let calls = AtomicUsize::new(0);
+        let (tcp, requests) = testing::signal_daemon(move |_| {
+            let first = calls.fetch_add(1, Ordering::Relaxed) == 0;
+            (!first).then(|| Ok(serde_json::json!({ "timestamp": 9 })))
+        })
+        .await;
+        let bot = Bot::new(config(tcp, 1));
+        assert_eq!(bot.send("g1", "hi", None).await.unwrap(), 9);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    // A daemon which cannot be reached fails once the retries run out
+    #[tokio::test]
+    async fn unreachable_daemon() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tcp = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let bot = Bot::new(config(tcp, 1));
+        let err = bot.send("g1", "hi", None).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,399 @@
+//! Server configuration.
+//!
+//! The configuration is read from a TOML file (`server/config.toml`, or the file given with
+//! `--config`), and every setting can be overridden by an environment variable. A setting which
+//! is in neither keeps its default, so the server runs with no configuration at all:
+//!
+//! ```toml
+//! listen = "127.0.0.1:3000"
+//! rpc_listen = "127.0.0.1:50051"
+//! db = "server/db"
+//! keydir = "server/keys"
+//! api_keys = "server/api_keys.json"
+//! # The key direct message recipients are encrypted under, generated if missing
+//! dm_key = "server/dm.key"
+//! log_level = "info"
+//! log_file = "server/server.log"
+//! timings_dir = "json_files"
+//! joins_per_minute = 60
+//! proofs_per_minute = 30
+//! job_queue = 256
+//! max_post_bytes = 16777216
+//! # Polls are tallied on their own after this long; 0 keeps them open until counted
+//! poll_duration_secs = 86400
+//! # Only these groups may be created; any group may be when empty
+//! groups = ["default", "book-club"]
+//! # How many pseudonyms an authorship proof links, from 2 to 8; changing it regenerates the keys
+//! authorship_pseudonyms = 2
+//!
+//! # What reactions do, if not given by [moderation] below (see `moderation`)
+//! moderation_file = "server/moderation.toml"
+//!
+//! [moderation]
+//! rules = [
+//!     { reaction = "upvote", reputation = 1 },
+//!     { reaction = "downvote", count = 10, reputation = -3 },
+//!     { reaction = "hatespeech", count = 5, ban_poll = true },
+//! ]
+//!
+//! # Badges earned by the authors of messages, see `badges`
+//! [[badges]]
+//! badge = 1
+//! name = "trusted poster"
+//! description = "Wrote a post with 100 upvotes"
+//! reaction = "upvote"
+//! count = 100
+//!
+//! [reputation_decay]
+//! # 0 turns decay off
+//! interval_secs = 86400
+//! rate = 0.1
+//! # Rates of groups which decay faster or slower than `rate`, by group name
+//! groups = { "book-club" = 0.05 }
+//!
+//! [signal]
+//! bot_number = "+15712811486"
+//! # Groups sent to by another bot account than bot_number, by Signal group id
+//! group_accounts = { "<group id>" = "+15550100" }
+//! tcp = "127.0.0.1:7583"
+//! connections = 4
+//! retries = 3
+//! timeout_secs = 30
+//! receive = true
+//! ```
+
+use crate::badges::{self, default_badges, Badge};
+use crate::moderation::ModerationPolicy;
+use anyhow::{bail, Context, Result};
+use common::zk::MAX_AUTHORS;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Where the configuration is read from when `--config` is not given. It may be missing.
+pub const DEFAULT_PATH: &str = "server/config.toml";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The address the HTTP API listens on (`SERVER_LISTEN`).
+    pub listen: SocketAddr,
+    /// The address the gRPC service listens on (`SERVER_RPC_LISTEN`).
+    pub rpc_listen: SocketAddr,
+    /// The database directory (`SERVER_DB`).
+    pub db: PathBuf,
+    /// The SNARK key store directory (`SERVER_KEYDIR`).
+    pub keydir: PathBuf,
+    /// The file listing API keys (`SERVER_API_KEYS`).
+    pub api_keys: PathBuf,
+    /// The key the Signal accounts direct messages are delivered to are encrypted under, kept
+    /// apart from the database (`SERVER_DM_KEY`).
+    pub dm_key: PathBuf,
+    /// The level the server logs at (`SERVER_LOG`).
+    pub log_level: String,
+    /// A file to write the log to, besides stdout (`SERVER_LOG_FILE`).
+    pub log_file: Option<PathBuf>,
+    /// The directory timing measurements are written to (`SERVER_TIMINGS_DIR`).
+    pub timings_dir: PathBuf,
+    /// The number of users who may join within a minute (`SERVER_JOINS_PER_MINUTE`).
+    pub joins_per_minute: usize,
+    /// The number of proofs a client IP may submit within a minute (`SERVER_PROOFS_PER_MINUTE`).
+    pub proofs_per_minute: usize,
+    /// The number of verification workers, the number of CPUs if not set (`SERVER_WORKERS`).
+    pub workers: Option<usize>,
+    /// The number of jobs which may wait for a worker (`SERVER_JOB_QUEUE`).
+    pub job_queue: usize,
+    /// The largest request accepted for a post, attachments included, in bytes
+    /// (`SERVER_MAX_POST_BYTES`).
+    pub max_post_bytes: usize,
+    /// How long polls stay open before they are tallied, in seconds, or 0 to keep them open
+    /// until counted (`SERVER_POLL_DURATION_SECS`).
+    pub poll_duration_secs: u64,
+    /// The groups which may be created, or any group if empty (`SERVER_GROUPS`, comma separated).
+    pub groups: Vec<String>,
+    /// The number of pseudonyms an authorship proof links, which the keys are generated for
+    /// (`SERVER_AUTHORSHIP_PSEUDONYMS`). Clients may link fewer by repeating one.
+    pub authorship_pseudonyms: usize,
+    pub signal: SignalConfig,
+    pub reputation_decay: DecayConfig,
+    /// What reactions do to the messages they are on.
+    pub moderation: ModerationPolicy,
+    /// A TOML or JSON file to read `moderation` from instead (`SERVER_MODERATION_FILE`).
+    pub moderation_file: Option<PathBuf>,
+    /// The badges which can be earned.
+    pub badges: Vec<Badge>,
+}
+
+/// How reputation drifts back toward zero.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DecayConfig {
+    /// How often reputation decays, in seconds, or 0 for never (`SERVER_DECAY_INTERVAL_SECS`).
+    pub interval_secs: u64,
+    /// The share of the reputation a message has given its author which is taken back each
+    /// interval (`SERVER_DECAY_RATE`).
+    pub rate: f64,
+    /// The rate of each group which does not decay at `rate`, by group name.
+    pub groups: BTreeMap<String, f64>,
+}
+
+impl DecayConfig {
+    /// The rate reputation decays at in `group`.
+    pub fn rate(&self, group: &str) -> f64 {
+        self.groups.get(group).copied().unwrap_or(self.rate)
+    }
+}
+
+/// How the server reaches Signal.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignalConfig {
+    /// The number of the bot which sends messages to groups not in `group_accounts`
+    /// (`SERVER_BOT_NUMBER`).
+    pub bot_number: String,
+    /// The bot account which sends the messages of each Signal group, by group id
+    /// (`SERVER_SIGNAL_GROUP_ACCOUNTS`, as comma separated `<group id>=<number>`).
+    pub group_accounts: BTreeMap<String, String>,
+    /// The JSON-RPC TCP address of the signal-cli daemon (`SERVER_SIGNAL_CLI_TCP`).
+    pub tcp: String,
+    /// The number of connections kept open to the daemon (`SERVER_SIGNAL_CONNECTIONS`).
+    pub connections: usize,
+    /// How many times a request is retried when its connection fails (`SERVER_SIGNAL_RETRIES`).
+    pub retries: u32,
+    /// How long to wait for the daemon to answer a request (`SERVER_SIGNAL_TIMEOUT_SECS`).
+    pub timeout_secs: u64,
+    /// Whether to listen for reactions and replies sent from the Signal app
+    /// (`SERVER_SIGNAL_RECEIVE`).
+    pub receive: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen: SocketAddr::from(([127, 0, 0, 1], 3000)),
+            rpc_listen: wispy_rpc::DEFAULT_ADDR
+                .parse()
+                .expect("valid default address"),
+            db: "server/db".into(),
+            keydir: "server/keys".into(),
+            api_keys: "server/api_keys.json".into(),
+            dm_key: "server/dm.key".into(),
+            log_level: "info".to_string(),
+            log_file: None,
+            timings_dir: "json_files".into(),
+            joins_per_minute: 60,
+            proofs_per_minute: 30,
+            workers: None,
+            job_queue: 256,
+            max_post_bytes: 16 * 1024 * 1024,
+            poll_duration_secs: 24 * 60 * 60,
+            groups: vec![],
+            authorship_pseudonyms: 2,
+            signal: SignalConfig::default(),
+            reputation_decay: DecayConfig::default(),
+            moderation: ModerationPolicy::default(),
+            moderation_file: None,
+            badges: default_badges(),
+        }
+    }
+}
+
+impl Default for DecayConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 0,
+            rate: 0.1,
+            groups: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for SignalConfig {
+    fn default() -> Self {
+        Self {
+            bot_number: "+15712811486".to_string(),
+            group_accounts: BTreeMap::new(),
+            tcp: "127.0.0.1:7583".to_string(),
+            connections: 4,
+            retries: 3,
+            timeout_secs: 30,
+            receive: true,
+        }
+    }
+}
+
+/// Override `value` with the environment variable `var`, if it is set.
+fn env<T: FromStr>(var: &str, value: &mut T) -> Result<()>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    if let Ok(v) = std::env::var(var) {
+        *value = v.parse().with_context(|| format!("invalid {}", var))?;
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Load the configuration from `path`, or from [`DEFAULT_PATH`] if it exists when no path is
+    /// given, and apply the environment overrides.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut config = match path {
+            Some(path) => Self::read(path)?,
+            None if Path::new(DEFAULT_PATH).exists() => Self::read(Path::new(DEFAULT_PATH))?,
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        if let Some(path) = &config.moderation_file {
+            config.moderation = ModerationPolicy::read(path)?;
+        }
+        config.moderation.check()?;
+        badges::check(&config.badges)?;
+        if !(2..=MAX_AUTHORS).contains(&config.authorship_pseudonyms) {
+            bail!(
+                "authorship_pseudonyms must be from 2 to {}, not {}",
+                MAX_AUTHORS,
+                config.authorship_pseudonyms
+            );
+        }
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        let file = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        toml::from_str(&file).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        env("SERVER_LISTEN", &mut self.listen)?;
+        env("SERVER_RPC_LISTEN", &mut self.rpc_listen)?;
+        env("SERVER_DB", &mut self.db)?;
+        env("SERVER_KEYDIR", &mut self.keydir)?;
+        env("SERVER_API_KEYS", &mut self.api_keys)?;
+        env("SERVER_DM_KEY", &mut self.dm_key)?;
+        env("SERVER_LOG", &mut self.log_level)?;
+        if let Ok(v) = std::env::var("SERVER_LOG_FILE") {
+            self.log_file = Some(v.into());
+        }
+        env("SERVER_TIMINGS_DIR", &mut self.timings_dir)?;
+        env("SERVER_JOINS_PER_MINUTE", &mut self.joins_per_minute)?;
+        env("SERVER_PROOFS_PER_MINUTE", &mut self.proofs_per_minute)?;
+        if let Ok(v) = std::env::var("SERVER_WORKERS") {
+            self.workers = Some(v.parse().context("invalid SERVER_WORKERS")?);
+        }
+        env("SERVER_JOB_QUEUE", &mut self.job_queue)?;
+        env("SERVER_MAX_POST_BYTES", &mut self.max_post_bytes)?;
+        env("SERVER_POLL_DURATION_SECS", &mut self.poll_duration_secs)?;
+        if let Ok(v) = std::env::var("SERVER_GROUPS") {
+            self.groups = v
+                .split(',')
+                .map(str::trim)
+                .filter(|g| !g.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        env(
+            "SERVER_AUTHORSHIP_PSEUDONYMS",
+            &mut self.authorship_pseudonyms,
+        )?;
+        env("SERVER_BOT_NUMBER", &mut self.signal.bot_number)?;
+        if let Ok(v) = std::env::var("SERVER_SIGNAL_GROUP_ACCOUNTS") {
+            self.signal.group_accounts = v
+                .split(',')
+                .map(str::trim)
+                .filter(|e| !e.is_empty())
+                .map(|e| {
+                    // Group ids are base64 and may end in '=', numbers never hold one
+                    let (group, number) = e
+                        .rsplit_once('=')
+                        .context("invalid SERVER_SIGNAL_GROUP_ACCOUNTS")?;
+                    Ok((group.to_string(), number.to_string()))
+                })
+                .collect::<Result<_>>()?;
+        }
+        env("SERVER_SIGNAL_CLI_TCP", &mut self.signal.tcp)?;
+        env("SERVER_SIGNAL_CONNECTIONS", &mut self.signal.connections)?;
+        env("SERVER_SIGNAL_RETRIES", &mut self.signal.retries)?;
+        env("SERVER_SIGNAL_TIMEOUT_SECS", &mut self.signal.timeout_secs)?;
+        env("SERVER_SIGNAL_RECEIVE", &mut self.signal.receive)?;
+        env(
+            "SERVER_DECAY_INTERVAL_SECS",
+            &mut self.reputation_decay.interval_secs,
+        )?;
+        env("SERVER_DECAY_RATE", &mut self.reputation_decay.rate)?;
+        if let Ok(v) = std::env::var("SERVER_MODERATION_FILE") {
+            self.moderation_file = Some(v.into());
+        }
+        Ok(())
+    }
+
+    /// Whether `group` may be created. The default group always may.
+    pub fn allows_group(&self, group: &str) -> bool {
+        self.groups.is_empty()
+            || group == crate::DEFAULT_GROUP
+            || self.groups.iter().any(|g| g == group)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wispy-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // The example at the top of this module is a valid configuration
+    #[test]
+    fn documented_example() {
+        let doc: String = include_str!("config.rs")
+            .lines()
+            .take_while(|l| l.starts_with("//!"))
+            .map(|l| format!("{}\n", l.trim_start_matches("//!").trim_start()))
+            .collect();
+        let example = doc.split("```toml\n").nth(1).unwrap().split("```").next().unwrap();
+        let config = Config::read(&write("example.toml", example)).unwrap();
+        assert_eq!(config.groups, ["default", "book-club"]);
+        assert_eq!(config.reputation_decay.groups["book-club"], 0.05);
+        assert_eq!(config.signal.group_accounts["<group id>"], "+15550100");
+    }
+
+    // Environment variables override the file, and bad settings are refused
+    #[test]
+    fn env_overrides() {
+        let path = write("env.toml", "joins_per_minute = 5\njob_queue = 7\n");
+        std::env::set_var("SERVER_JOINS_PER_MINUTE", "9");
+        std::env::set_var("SERVER_GROUPS", "a, b,");
+        std::env::set_var("SERVER_SIGNAL_GROUP_ACCOUNTS", "aGk==+1555, b2s=+1666");
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.joins_per_minute, 9);
+        assert_eq!(config.job_queue, 7);
+        assert_eq!(config.groups, ["a", "b"]);
+        assert_eq!(config.signal.group_accounts["aGk="], "+1555");
+        assert_eq!(config.signal.group_accounts["b2s"], "+1666");
+        assert!(config.allows_group(crate::DEFAULT_GROUP));
+        assert!(!config.allows_group("c"));
+
+        std::env::set_var("SERVER_JOINS_PER_MINUTE", "many");
+        assert!(Config::load(Some(&path)).is_err());
+        for var in [
+            "SERVER_JOINS_PER_MINUTE",
+            "SERVER_GROUPS",
+            "SERVER_SIGNAL_GROUP_ACCOUNTS",
+        ] {
+            std::env::remove_var(var);
+        }
+
+        assert!(Config::load(Some(&write("unknown.toml", "listen_port = 1\n"))).is_err());
+        let authors = write("authors.toml", "authorship_pseudonyms = 1\n");
+        assert!(Config::load(Some(&authors)).is_err());
+    }
+}
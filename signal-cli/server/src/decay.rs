@@ -0,0 +1,138 @@
+//! Reputation decay.
+//!
+//! Reputation is added to a user by calling the callbacks of their messages, so it otherwise
+//! only ever reflects the sum of everything they posted. With `reputation_decay` configured, the
+//! server calls those callbacks again every interval to take back a share of what each message
+//! added, at the rate configured for the group the message was rewarded in, until nothing is
+//! left. Users pick the calls up with their next scan, like any other.
+
+use crate::helpers::{applied_reputations, set_applied_reputation, AppliedReputation};
+use crate::server::{call_ticket, find_group, ServerLock};
+use ark_bn254::Fr;
+use ark_serialize::{CanonicalDeserialize, Compress, Validate};
+use common::zk::arg_rep;
+use hex::FromHex;
+use rand::rngs::OsRng;
+use std::{collections::BTreeMap, time::Duration};
+use tracing::{info, warn};
+use zk_callbacks::{generic::callbacks::CallbackCom, impls::centralized::crypto::PlainTikCrypto};
+
+type Ticket = CallbackCom<Fr, Fr, PlainTikCrypto<Fr>>;
+
+/// Decay reputation every configured interval until the server stops. Does nothing if decay is
+/// turned off.
+pub fn spawn(state: ServerLock) {
+    let interval_secs = state.config.reputation_decay.interval_secs;
+    if interval_secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        // The first tick completes at once, and reputation should not decay on every restart
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = decay(&state).await {
+                warn!("[DECAY] {}", e);
+            }
+        }
+    });
+}
+
+async fn decay(state: &ServerLock) -> anyhow::Result<()> {
+    let mut by_group: BTreeMap<String, Vec<AppliedReputation>> = BTreeMap::new();
+    for entry in applied_reputations()? {
+        by_group.entry(entry.group.clone()).or_default().push(entry);
+    }
+
+    for (group, entries) in by_group {
+        let rate = state.config.reputation_decay.rate(&group);
+        if rate <= 0.0 {
+            continue;
+        }
+        let group_state = match find_group(state, &group) {
+            Ok(group_state) => group_state,
+            Err(e) => {
+                warn!("[DECAY] Skipping group {}: {}", group, e);
+                continue;
+            }
+        };
+        let mut bulletins = group_state.bulletins.write().await;
+        let db = &mut bulletins.store;
+
+        let mut decayed = 0;
+        for entry in entries {
+            // Take back at least one point, so small amounts still reach zero
+            let amount = ((entry.applied as f64 * rate).floor() as i64).clamp(1, entry.applied);
+            let result = Vec::from_hex(&entry.cb)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| {
+                    Ok(Ticket::deserialize_with_mode(
+                        &bytes[..],
+                        Compress::No,
+                        Validate::Yes,
+                    )?)
+                })
+                .and_then(|ticket| Ok(call_ticket(db, ticket, arg_rep(-amount))?))
+                .and_then(|()| set_applied_reputation(entry.timestamp, entry.applied - amount));
+            match result {
+                Ok(()) => decayed += 1,
+                Err(e) => warn!(
+                    "[DECAY] Failed to decay the reputation of {}: {}",
+                    entry.timestamp, e
+                ),
+            }
+        }
+
+        if decayed > 0 {
+            db.callback_bul.update_epoch(&mut OsRng);
+            state.events.epoch_updated(&group, db, true);
+            bulletins.persist(&group);
+            info!(
+                "[DECAY] Decayed the reputation of {} messages in {}",
+                decayed, group
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::helpers::{attach_callback, record_applied_reputation};
+    use crate::{persist, testing};
+
+    // Each interval takes back a share of the reputation given, and at least one point
+    #[tokio::test]
+    async fn decay_reputation() {
+        persist::open_temp();
+        let (large, small) = (testing::ticket(4857000), testing::ticket(4857001));
+        attach_callback(4857000, large.clone()).unwrap();
+        attach_callback(4857001, small.clone()).unwrap();
+        record_applied_reputation(&large, "decay", 10).unwrap();
+        record_applied_reputation(&small, "decay", 1).unwrap();
+
+        let mut config = Config::default();
+        config.reputation_decay.groups.insert("decay".to_string(), 0.5);
+        let state = testing::server(config, "decay");
+        testing::add_group(&state, "decay", 2);
+        let group = state.group("decay").unwrap();
+        let epoch = group.bulletins.read().await.store.callback_bul.get_epoch();
+        decay(&state).await.unwrap();
+
+        let applied = |timestamp| {
+            applied_reputations()
+                .unwrap()
+                .into_iter()
+                .find(|entry| entry.timestamp == timestamp)
+                .map(|entry| entry.applied)
+        };
+        assert_eq!(applied(4857000), Some(5));
+        assert_eq!(applied(4857001), None);
+        let bulletins = group.bulletins.read().await;
+        assert_eq!(bulletins.store.callback_bul.memb_called_cbs.len(), 2);
+        assert_ne!(bulletins.store.callback_bul.get_epoch(), epoch);
+    }
+}
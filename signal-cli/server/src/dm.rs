@@ -0,0 +1,530 @@
+//! Anonymous direct messages.
+//!
+//! A user who wants direct messages registers the Signal account to deliver them to under one of
+//! their pseudonyms, proving they own it, with `/api/dm/register`. Anyone in a group can then
+//! write to the pseudonym with `/api/dm`, proving they own the pseudonym they write as, and the
+//! bot delivers the message to the account, which is never revealed to the sender.
+//!
+//! Each request is bound to a single-use nonce from `/api/dm/nonce`. Its proof links the
+//! pseudonym to the one the user claims in the [`dm_context`] of the nonce and the request, as
+//! authorship proofs link pseudonyms, so a proof cannot be replayed, nor used for another request.
+//!
+//! Only a pseudonym which has posted can register or write, proved in the context it posted in,
+//! so a user cannot make pseudonyms in contexts of their choosing until one has the petname of
+//! another's. The `dm_names` tree finds a pseudonym which has posted by its petname. A petname
+//! shared by two of them finds neither, and they have to be written to by their value in decimal.
+//!
+//! The accounts are kept encrypted with ChaCha20-Poly1305 in the `dm_recipients` tree, under a
+//! key kept outside the database (`dm_key`), so a copy of the database, such as an admin
+//! snapshot, does not link pseudonyms to accounts.
+
+use crate::error::ApiError;
+use crate::persist::tree;
+use crate::server::{find_group, ServerLock};
+use crate::DEFAULT_GROUP;
+use anyhow::{anyhow, bail, Context, Result};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_groth16::Groth16;
+use ark_serialize::{CanonicalDeserialize, Compress, Validate};
+use ark_snark::SNARK;
+use axum::{extract::State, http::StatusCode, Json};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use common::{
+    catalog,
+    zk::{dm_context, petname},
+    E, F,
+};
+use rand::{CryptoRng, RngCore};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+    str::FromStr,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+use zk_callbacks::generic::wire::read_envelope;
+
+const NONCE_LEN: usize = 12;
+
+/// The pseudonyms which have posted, keyed by petname, or nothing for a petname shared by two.
+const DM_NAMES: &str = "dm_names";
+/// The contexts the pseudonyms which have posted did so in, keyed by pseudonym.
+const DM_CONTEXTS: &str = "dm_contexts";
+/// The nonces issued for requests, keyed by when they expire and the nonce, both big-endian.
+const DM_NONCES: &str = "dm_nonces";
+/// How long a nonce can be used for, in seconds.
+const DM_NONCE_SECS: u64 = 600;
+
+/// The key the accounts are encrypted under.
+static KEY: OnceLock<Key> = OnceLock::new();
+
+/// Load the key the accounts are encrypted under from `path`, generating it on first start. This
+/// must be called once on startup, before direct messages are sent.
+pub fn open(path: &Path, rng: &mut (impl CryptoRng + RngCore)) -> Result<()> {
+    let key = match fs::read(path) {
+        Ok(key) if key.len() == 32 => *Key::from_slice(&key),
+        Ok(_) => bail!("{} is not a 32 byte key", path.display()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mut key = Key::default();
+            rng.fill_bytes(&mut key);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(path)
+                .and_then(|mut file| file.write_all(&key))
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            key
+        }
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    KEY.set(key)
+        .map_err(|_| anyhow!("direct message key loaded twice"))
+}
+
+fn cipher() -> Result<ChaCha20Poly1305> {
+    Ok(ChaCha20Poly1305::new(
+        KEY.get().context("direct message key is not loaded")?,
+    ))
+}
+
+/// The database key of a pseudonym.
+fn pseudonym_key(claimed: F) -> Vec<u8> {
+    claimed.into_bigint().to_bytes_le()
+}
+
+/// Deliver the direct messages to `claimed` to the Signal account `recipient`.
+pub fn register(claimed: F, recipient: &str, rng: &mut (impl CryptoRng + RngCore)) -> Result<()> {
+    let key = pseudonym_key(claimed);
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill_bytes(&mut nonce);
+    // Bound to the pseudonym, so an entry cannot be moved to another
+    let ciphertext = cipher()?
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: recipient.as_bytes(),
+                aad: &key,
+            },
+        )
+        .map_err(|_| anyhow!("failed to encrypt the recipient"))?;
+    tree("dm_recipients")?.insert(&key, [&nonce[..], &ciphertext].concat())?;
+    Ok(())
+}
+
+/// Stop delivering direct messages to `claimed`. Returns whether it was registered.
+pub fn unregister(claimed: F) -> Result<bool> {
+    Ok(tree("dm_recipients")?.remove(pseudonym_key(claimed))?.is_some())
+}
+
+/// Record that the pseudonym `claimed` posted in the context `context`, so it can register, and
+/// find it by its petname, unless another pseudonym which has posted has the same one.
+pub fn record_pseudonym(context: F, claimed: F) -> Result<()> {
+    let key = pseudonym_key(claimed);
+    tree(DM_CONTEXTS)?.insert(&key, pseudonym_key(context))?;
+    let Some(name) = petname(claimed) else {
+        return Ok(());
+    };
+    let names = tree(DM_NAMES)?;
+    if let Err(taken) = names.compare_and_swap(&name, None as Option<&[u8]>, Some(&key[..]))? {
+        if taken.current.as_deref() != Some(&key[..]) {
+            names.insert(name, &[])?;
+        }
+    }
+    Ok(())
+}
+
+/// The context the pseudonym `claimed` posted in, if it has posted.
+fn posted_in(claimed: F) -> Result<Option<F>> {
+    Ok(tree(DM_CONTEXTS)?
+        .get(pseudonym_key(claimed))?
+        .map(|context| F::from_le_bytes_mod_order(&context)))
+}
+
+/// The Signal account the direct messages to `claimed` are delivered to, if it is registered.
+pub fn recipient(claimed: F) -> Result<Option<String>> {
+    let key = pseudonym_key(claimed);
+    let Some(entry) = tree("dm_recipients")?.get(&key)? else {
+        return Ok(None);
+    };
+    if entry.len() < NONCE_LEN {
+        bail!("corrupt direct message recipient");
+    }
+    let (nonce, ciphertext) = entry.split_at(NONCE_LEN);
+    let recipient = cipher()?
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &key,
+            },
+        )
+        .map_err(|_| anyhow!("failed to decrypt the recipient; was dm_key replaced?"))?;
+    Ok(Some(String::from_utf8(recipient)?))
+}
+
+/// The pseudonym named `name`, by its value in decimal or the petname of one which has posted.
+/// A petname shared by two pseudonyms is refused.
+pub fn find(name: &str) -> Result<Option<F>, ApiError> {
+    if let Ok(claimed) = F::from_str(name) {
+        return Ok(Some(claimed));
+    }
+    match tree(DM_NAMES)?.get(name).map_err(ApiError::internal)? {
+        Some(key) if key.is_empty() => Err(ApiError::malformed(format!(
+            "{} is the petname of more than one pseudonym; write to its value in decimal",
+            name
+        ))),
+        key => Ok(key.map(|key| F::from_le_bytes_mod_order(&key))),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The database key of a nonce issued to expire at `expires`.
+fn nonce_key(nonce: F, expires: u64) -> Vec<u8> {
+    [&expires.to_be_bytes()[..], &nonce.into_bigint().to_bytes_be()].concat()
+}
+
+/// Issue a nonce for one request, which expires after [`DM_NONCE_SECS`], and drop the nonces
+/// which have expired. Returns the nonce and when it expires.
+pub fn issue_nonce(rng: &mut (impl CryptoRng + RngCore)) -> Result<(F, u64)> {
+    let nonces = tree(DM_NONCES)?;
+    let now = now();
+    for key in nonces.range(..now.to_be_bytes()).keys() {
+        nonces.remove(key?)?;
+    }
+    let (nonce, expires) = (F::rand(rng), now + DM_NONCE_SECS);
+    nonces.insert(nonce_key(nonce, expires), &[])?;
+    Ok((nonce, expires))
+}
+
+/// Use up the nonce `nonce`, which expires at `expires`. Returns whether it was issued and has
+/// neither been used nor expired.
+pub fn use_nonce(nonce: F, expires: u64) -> Result<bool> {
+    let issued = tree(DM_NONCES)?
+        .remove(nonce_key(nonce, expires))?
+        .is_some();
+    Ok(issued && expires > now())
+}
+
+/// Verify a proof that the user owns a pseudonym, made for the request `request` with the nonce
+/// `nonce`, which expires at `expires`, and return the pseudonym. The nonce is used up.
+///
+/// The public inputs are the (context, pseudonym) pairs of an authorship proof: the pseudonym in
+/// the context it posted in, then the pseudonym the user claims in the context of the request,
+/// repeated to pad the proof.
+fn verify_ownership(
+    state: &ServerLock,
+    group: &str,
+    proof: &[u8],
+    (nonce, expires): (&str, u64),
+    request: &[&str],
+) -> Result<F, ApiError> {
+    let nonce = F::from_str(nonce).map_err(|_| ApiError::malformed("invalid nonce"))?;
+    let mut reader = proof;
+    let (proof, _): (<Groth16<E> as SNARK<F>>::Proof, _) = read_envelope(&mut reader)?;
+    let pub_inputs: Vec<F> =
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
+    let [posted, claimed, ref bound @ ..] = pub_inputs[..] else {
+        return Err(ApiError::malformed("expected a context and a pseudonym"));
+    };
+    let context = dm_context(nonce, request);
+    if bound.len() < 2 || bound.chunks(2).any(|pair| pair[0] != context) {
+        return Err(ApiError::malformed("the proof is not bound to this request"));
+    }
+
+    let verified = find_group(state, group)?
+        .state
+        .verifying_keys(catalog::AUTHORSHIP_PRED)
+        .iter()
+        .any(|vki| Groth16::<E>::verify(vki, &pub_inputs, &proof).unwrap_or(false));
+    info!("[DM] Verification result: {}", verified);
+    if !verified {
+        return Err(ApiError::BadProof(
+            "pseudonym proof did not verify".to_string(),
+        ));
+    }
+    if posted_in(claimed)? != Some(posted) {
+        return Err(ApiError::Forbidden(
+            "the pseudonym has not posted in the context it was proved in".to_string(),
+        ));
+    }
+    if !use_nonce(nonce, expires)? {
+        return Err(ApiError::Forbidden(
+            "the nonce was not issued, or has been used or expired".to_string(),
+        ));
+    }
+    Ok(claimed)
+}
+
+fn default_group() -> String {
+    DEFAULT_GROUP.to_string()
+}
+
+#[derive(Deserialize)]
+pub struct JsonDmRegister {
+    /// A proof of ownership of the pseudonym to receive direct messages under, bound to the
+    /// recipient.
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
+    proof: Vec<u8>,
+    /// The Signal account to deliver them to, or none to stop receiving them.
+    recipient: Option<String>,
+    /// The nonce the proof is bound to, in decimal, and when it expires.
+    nonce: String,
+    expires: u64,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
+}
+
+#[derive(Deserialize)]
+pub struct JsonDm {
+    /// A proof of ownership of the pseudonym the message is from, bound to the message and whom
+    /// it is to.
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
+    proof: Vec<u8>,
+    /// The pseudonym the message is to, by its petname or its value in decimal.
+    to: String,
+    message: String,
+    /// The nonce the proof is bound to, in decimal, and when it expires.
+    nonce: String,
+    expires: u64,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
+}
+
+/// Issue a nonce for a direct message request, as `{"nonce": <decimal>, "expires": <seconds>}`.
+#[tracing::instrument(skip_all)]
+pub async fn handle_dm_nonce() -> Result<Json<Value>, ApiError> {
+    let (nonce, expires) = issue_nonce(&mut rand::thread_rng())?;
+    Ok(Json(json!({
+        "nonce": nonce.into_bigint().to_string(),
+        "expires": expires,
+    })))
+}
+
+/// Register (or unregister) the Signal account the direct messages to a pseudonym go to.
+#[tracing::instrument(skip_all)]
+pub async fn handle_dm_register(
+    State(state): State<ServerLock>,
+    Json(input): Json<JsonDmRegister>,
+) -> Result<StatusCode, ApiError> {
+    let request = match &input.recipient {
+        Some(recipient) => ["register", recipient.as_str()],
+        None => ["unregister", ""],
+    };
+    let nonce = (input.nonce.as_str(), input.expires);
+    let claimed = verify_ownership(&state, &input.group, &input.proof, nonce, &request)?;
+    match input.recipient {
+        Some(recipient) => {
+            register(claimed, &recipient, &mut rand::thread_rng())?;
+            info!("[DM] Registered a recipient");
+        }
+        None => {
+            if !unregister(claimed)? {
+                return Err(ApiError::NotFound(
+                    "the pseudonym does not receive direct messages".to_string(),
+                ));
+            }
+            info!("[DM] Unregistered a recipient");
+        }
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Deliver a direct message to the account registered under a pseudonym.
+#[tracing::instrument(skip_all)]
+pub async fn forward_dm(
+    State(state): State<ServerLock>,
+    Json(input): Json<JsonDm>,
+) -> Result<StatusCode, ApiError> {
+    let request = ["send", input.to.as_str(), input.message.as_str()];
+    let nonce = (input.nonce.as_str(), input.expires);
+    let from = verify_ownership(&state, &input.group, &input.proof, nonce, &request)?;
+    let not_found = || ApiError::NotFound(format!("{} does not receive direct messages", input.to));
+    let to = find(&input.to)?.ok_or_else(not_found)?;
+    let recipient = recipient(to)?.ok_or_else(not_found)?;
+
+    let name = |claimed| petname(claimed).unwrap_or_else(|| "anonymous user".to_string());
+    let message = format!(
+        "DIRECT MESSAGE\n\nFrom {} to {}:\n\n{}",
+        name(from),
+        name(to),
+        input.message
+    );
+    state.bot.send_direct(&recipient, &message).await?;
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::Config, testing};
+
+    /// A proof that the user owning `claimed`, which posted in the context 1, made in the context
+    /// `context`, as the stand-in keys verify it.
+    fn proof(pk: &common::PK, claimed: F, context: F) -> Vec<u8> {
+        testing::proof(pk, &[F::from(1), claimed, context, F::from(2)])
+    }
+
+    // A request has to be proved for itself, by a pseudonym which has posted, with a nonce which
+    // is used up, and the recipient is found by the petname of its pseudonym
+    #[tokio::test]
+    async fn bound_to_request() {
+        let (tcp, requests) =
+            testing::signal_daemon(|_| Some(Ok(json!({ "timestamp": 4875001 })))).await;
+        let mut config = Config::default();
+        config.signal.tcp = tcp;
+        let state = testing::server(config, "dm");
+        let key = std::env::temp_dir().join(format!("wispy-dm-{}.key", std::process::id()));
+        let _ = open(&key, &mut rand::thread_rng());
+        let (_, pk) = testing::add_group(&state, "dm", 2);
+
+        let issue = || async {
+            let Json(issued) = handle_dm_nonce().await.unwrap();
+            let nonce = issued["nonce"].as_str().unwrap().to_string();
+            (nonce, issued["expires"].as_u64().unwrap())
+        };
+        let bound = |(nonce, _): &(String, u64), request: &[&str]| {
+            dm_context(F::from_str(nonce).unwrap(), request)
+        };
+        let register = |proof, recipient: Option<&str>, (nonce, expires): (String, u64)| {
+            let input = JsonDmRegister {
+                proof,
+                recipient: recipient.map(str::to_string),
+                nonce,
+                expires,
+                group: "dm".to_string(),
+            };
+            handle_dm_register(State(state.clone()), Json(input))
+        };
+        let send = |proof, to: &str, (nonce, expires): (String, u64)| {
+            let input = JsonDm {
+                proof,
+                to: to.to_string(),
+                message: "hi".to_string(),
+                nonce,
+                expires,
+                group: "dm".to_string(),
+            };
+            forward_dm(State(state.clone()), Json(input))
+        };
+        let (alice, bob) = (F::from(48751), F::from(48752));
+        let alice_name = petname(alice).unwrap();
+        record_pseudonym(F::from(1), alice).unwrap();
+        record_pseudonym(F::from(1), bob).unwrap();
+
+        let nonce = issue().await;
+        let registration = proof(&pk, alice, bound(&nonce, &["register", "+1555"]));
+        let registered = register(registration.clone(), Some("+1555"), nonce.clone()).await;
+        assert_eq!(registered.unwrap(), StatusCode::OK);
+        assert_eq!(find(&alice_name).unwrap(), Some(alice));
+        assert_eq!(find("no such name").unwrap(), None);
+        let err = register(registration, Some("+1555"), nonce).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+
+        // A proof for one request cannot be used for another, and a proof of ownership alone, as
+        // made for votes, is not bound to any
+        let nonce = issue().await;
+        let registration = proof(&pk, alice, bound(&nonce, &["register", "+1555"]));
+        let err = register(registration, Some("+1666"), nonce.clone()).await.unwrap_err();
+        assert_eq!(err.kind(), "malformed_payload");
+        let unregistration = proof(&pk, alice, bound(&nonce, &["unregister", ""]));
+        let err = register(unregistration, Some("+1555"), nonce.clone()).await.unwrap_err();
+        assert_eq!(err.kind(), "malformed_payload");
+        let vote = testing::proof(&testing::keys(2).0, &[F::from(1), alice]);
+        let err = register(vote, None, nonce.clone()).await.unwrap_err();
+        assert_eq!(err.kind(), "malformed_payload");
+        let (other, _) = testing::keys(4);
+        let forged = proof(&other, alice, bound(&nonce, &["unregister", ""]));
+        let err = register(forged, None, nonce.clone()).await.unwrap_err();
+        assert_eq!(err.kind(), "bad_proof");
+
+        // Pseudonyms which have not posted, or not in the context proved in, are refused
+        let (carol, dave) = (F::from(48753), F::from(48754));
+        record_pseudonym(F::from(3), dave).unwrap();
+        for pseudonym in [carol, dave] {
+            let registration = proof(&pk, pseudonym, bound(&nonce, &["register", "+1777"]));
+            let err = register(registration, Some("+1777"), nonce.clone()).await.unwrap_err();
+            assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        }
+
+        // Nonces the server did not issue, or which expired, are refused
+        let made_up = ("4875".to_string(), now() + 60);
+        let err = register(proof(&pk, alice, bound(&made_up, &["unregister", ""])), None, made_up)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        let expired = (F::from(4876), now() - 1);
+        tree(DM_NONCES).unwrap().insert(nonce_key(expired.0, expired.1), &[]).unwrap();
+        let expired = ("4876".to_string(), expired.1);
+        let err = register(proof(&pk, alice, bound(&expired, &["unregister", ""])), None, expired)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+
+        // The nonce refused above was not used up
+        let message = proof(&pk, bob, bound(&nonce, &["send", &alice_name, "hi"]));
+        assert_eq!(send(message, &alice_name, nonce).await.unwrap(), StatusCode::OK);
+        let sent = requests.lock().unwrap()[0]["params"].clone();
+        assert_eq!(sent["recipient"], json!(["+1555"]));
+        let header = format!("From {} to {}:", petname(bob).unwrap(), alice_name);
+        assert!(sent["message"].as_str().unwrap().contains(&header));
+
+        let nonce = issue().await;
+        let message = proof(&pk, bob, bound(&nonce, &["send", "48754", "hi"]));
+        let err = send(message, "48754", nonce).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let nonce = issue().await;
+        let unregistration = proof(&pk, alice, bound(&nonce, &["unregister", ""]));
+        assert_eq!(register(unregistration, None, nonce).await.unwrap(), StatusCode::OK);
+        assert_eq!(recipient(alice).unwrap(), None);
+        let nonce = issue().await;
+        let unregistration = proof(&pk, alice, bound(&nonce, &["unregister", ""]));
+        let err = register(unregistration, None, nonce).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    // Two pseudonyms which have posted with the same petname cannot be written to by it, so a
+    // user who finds a pseudonym with another's petname does not receive their messages
+    #[test]
+    fn shared_petname() {
+        crate::persist::open_temp();
+        let mut named = std::collections::HashMap::new();
+        let (first, second) = (48755000u64..)
+            .map(F::from)
+            .find_map(|pseudonym| {
+                let first = named.insert(petname(pseudonym).unwrap(), pseudonym)?;
+                Some((first, pseudonym))
+            })
+            .unwrap();
+        let name = petname(first).unwrap();
+
+        record_pseudonym(F::from(1), first).unwrap();
+        assert_eq!(find(&name).unwrap(), Some(first));
+        record_pseudonym(F::from(2), second).unwrap();
+        assert_eq!(find(&name).unwrap_err().kind(), "malformed_payload");
+        record_pseudonym(F::from(1), first).unwrap();
+        assert_eq!(find(&name).unwrap_err().kind(), "malformed_payload");
+        assert_eq!(find(&second.to_string()).unwrap(), Some(second));
+        assert_eq!(posted_in(second).unwrap(), Some(F::from(2)));
+    }
+}
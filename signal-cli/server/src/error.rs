@@ -0,0 +1,173 @@
+//! Errors returned by the HTTP handlers.
+//!
+//! Every handler returns `Result<_, ApiError>`, so a bad request is answered with a status code
+//! and a JSON body `{"error": <kind>, "detail": <message>}` instead of panicking the handler task.
+
+use ark_serialize::SerializationError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use zk_callbacks::{generic::wire::WireError, impls::centralized::join::PolicyError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    /// The request body or a field in it could not be decoded.
+    #[error("malformed payload: {0}")]
+    MalformedPayload(String),
+    /// The proof in the request did not verify, or the interaction was not approved.
+    #[error("bad proof: {0}")]
+    BadProof(String),
+    /// The proof was made with a key which was rotated out and whose grace period has ended.
+    #[error("the proof was made with a key which is no longer accepted; fetch the current key")]
+    StaleKey,
+    /// The old nullifier of the interaction was already used by a different interaction.
+    #[error("the old nullifier of this interaction was already used")]
+    NullifierConsumed,
+    /// The pseudonym already voted on the poll.
+    #[error("this pseudonym already voted on the poll")]
+    AlreadyVoted,
+    #[error("unknown group {0}")]
+    UnknownGroup(String),
+    /// The request is missing credentials, or they are wrong.
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    /// The credentials of the request do not allow it.
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    RateLimited(String),
+    /// Sending through signal-cli failed.
+    #[error("signal-cli failed: {0}")]
+    Signal(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn malformed(e: impl ToString) -> Self {
+        Self::MalformedPayload(e.to_string())
+    }
+
+    pub fn bad_proof(e: impl std::fmt::Debug) -> Self {
+        Self::BadProof(format!("{:?}", e))
+    }
+
+    pub fn internal(e: impl ToString) -> Self {
+        Self::Internal(e.to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::MalformedPayload(_) | Self::BadProof(_) => StatusCode::BAD_REQUEST,
+            Self::StaleKey => StatusCode::PRECONDITION_FAILED,
+            Self::NullifierConsumed | Self::AlreadyVoted => StatusCode::CONFLICT,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::UnknownGroup(_) | Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Signal(_) => StatusCode::BAD_GATEWAY,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// A short machine-readable name for the error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MalformedPayload(_) => "malformed_payload",
+            Self::BadProof(_) => "bad_proof",
+            Self::StaleKey => "stale_key",
+            Self::NullifierConsumed => "nullifier_consumed",
+            Self::AlreadyVoted => "already_voted",
+            Self::UnknownGroup(_) => "unknown_group",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::NotFound(_) => "not_found",
+            Self::RateLimited(_) => "rate_limited",
+            Self::Signal(_) => "signal",
+            Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        tracing::info!("[SERVER] Request failed: {}", self);
+        let body = serde_json::json!({ "error": self.kind(), "detail": self.to_string() });
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+impl From<WireError> for ApiError {
+    fn from(e: WireError) -> Self {
+        Self::malformed(e)
+    }
+}
+
+impl From<SerializationError> for ApiError {
+    fn from(e: SerializationError) -> Self {
+        Self::malformed(e)
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::malformed(e)
+    }
+}
+
+/// A user the join policy turned away: too many joins is worth retrying later, a bad or spent
+/// credential is not.
+impl From<PolicyError> for ApiError {
+    fn from(e: PolicyError) -> Self {
+        match e {
+            PolicyError::RateLimited => Self::RateLimited(e.to_string()),
+            PolicyError::BadInvite | PolicyError::BadToken | PolicyError::TokenSpent => {
+                Self::Forbidden(e.to_string())
+            }
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::internal(format!("{:#}", e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Only a rate limit asks the client to retry a join
+    #[test]
+    fn policy_rejections() {
+        assert_eq!(
+            ApiError::from(PolicyError::RateLimited).status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        for e in [
+            PolicyError::BadInvite,
+            PolicyError::BadToken,
+            PolicyError::TokenSpent,
+        ] {
+            assert_eq!(ApiError::from(e).status(), StatusCode::FORBIDDEN);
+        }
+    }
+
+    // Errors are answered with their status and kind
+    #[tokio::test]
+    async fn error_response() {
+        let response = ApiError::malformed("bad field").into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "malformed_payload");
+        assert_eq!(body["detail"], "malformed payload: bad field");
+    }
+}
@@ -0,0 +1,183 @@
+//! Updates pushed to subscribed clients.
+//!
+//! Clients otherwise learn about new epochs, called callbacks, threads and polls by polling the
+//! REST endpoints. `GET /api/subscribe` instead streams an [`Event`] as soon as one happens, as
+//! server-sent events, so a client can scan or refresh its witnesses right away.
+
+use crate::server::ServerLock;
+use axum::{
+    extract::{Query, State},
+    response::sse::{self, KeepAlive, Sse},
+};
+use common::Store;
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::{convert::Infallible, time::Duration};
+use tokio::sync::broadcast;
+use tracing::info;
+
+/// How many events a slow subscriber may fall behind by before it misses some.
+const CAPACITY: usize = 1024;
+
+/// Something which changed on the server.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The callback bulletin of a group moved to a new epoch, so scans should be redone.
+    Epoch { group: String, epoch: String },
+    /// A callback was called in a group. `version` is the new version of the callback bulletin.
+    Called { group: String, version: u64 },
+    /// A pseudonymous thread was created.
+    Context { thread: String, context: String },
+    /// A poll was opened. `ban` is the timestamp of the message a ban poll is about.
+    Poll {
+        timestamp: u64,
+        ban: Option<u64>,
+        context: String,
+    },
+}
+
+impl Event {
+    /// The group the event happened in, for events which belong to a group.
+    fn group(&self) -> Option<&str> {
+        match self {
+            Event::Epoch { group, .. } | Event::Called { group, .. } => Some(group),
+            Event::Context { .. } | Event::Poll { .. } => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Epoch { .. } => "epoch",
+            Event::Called { .. } => "called",
+            Event::Context { .. } => "context",
+            Event::Poll { .. } => "poll",
+        }
+    }
+}
+
+/// The channel events are published on.
+pub struct Events {
+    tx: broadcast::Sender<Event>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        Self {
+            tx: broadcast::channel(CAPACITY).0,
+        }
+    }
+
+    /// Send an event to every subscriber. Events with no subscribers are dropped.
+    pub fn publish(&self, event: Event) {
+        let _ = self.tx.send(event);
+    }
+
+    /// Announce a new epoch of a group's callback bulletin, after calling callbacks if `called`.
+    pub fn epoch_updated(&self, group: &str, db: &Store, called: bool) {
+        if called {
+            self.publish(Event::Called {
+                group: group.to_string(),
+                version: db.callback_bul.get_version(),
+            });
+        }
+        self.publish(Event::Epoch {
+            group: group.to_string(),
+            epoch: db.callback_bul.get_epoch().to_string(),
+        });
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SubscribeQuery {
+    /// Only send the events of this group (events which belong to no group are always sent).
+    group: Option<String>,
+}
+
+/// Stream events as server-sent events. Each event is named by its type, with the event as JSON
+/// data. A subscriber which falls too far behind gets a `lagged` event with the number of events
+/// it missed, and should refetch the state it tracks.
+#[tracing::instrument(skip_all)]
+pub async fn handle_subscribe(
+    State(state): State<ServerLock>,
+    Query(query): Query<SubscribeQuery>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    info!("[SERVER] New subscriber");
+    let rx = state.events.subscribe();
+    let stream = stream::unfold((rx, query.group), |(mut rx, group)| async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    let lagged = sse::Event::default()
+                        .event("lagged")
+                        .data(missed.to_string());
+                    return Some((Ok(lagged), (rx, group)));
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            if let (Some(want), Some(got)) = (&group, event.group()) {
+                if want != got {
+                    continue;
+                }
+            }
+            let data = serde_json::to_string(&event).expect("events serialize");
+            let sent = sse::Event::default().event(event.name()).data(data);
+            return Some((Ok(sent), (rx, group)));
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::Config, testing};
+    use axum::response::IntoResponse;
+    use futures_util::StreamExt;
+
+    // Subscribers to a group get its events and the events of no group, named by their type
+    #[tokio::test]
+    async fn subscribe_group() {
+        let state = testing::server(Config::default(), "events");
+        let query = SubscribeQuery {
+            group: Some("ev-a".to_string()),
+        };
+        let sse = handle_subscribe(State(state.clone()), Query(query)).await;
+        let mut body = sse.into_response().into_body().into_data_stream();
+
+        let epoch = |group: &str| Event::Epoch {
+            group: group.to_string(),
+            epoch: "3".to_string(),
+        };
+        state.events.publish(epoch("ev-b"));
+        state.events.publish(Event::Context {
+            thread: "t".to_string(),
+            context: "5".to_string(),
+        });
+        state.events.publish(epoch("ev-a"));
+
+        let mut frames = vec![];
+        for _ in 0..2 {
+            let frame = body.next().await.unwrap().unwrap();
+            frames.push(String::from_utf8(frame.to_vec()).unwrap());
+        }
+        assert_eq!(
+            frames,
+            [
+                "event: context\ndata: {\"type\":\"context\",\"thread\":\"t\",\"context\":\"5\"}\n\n",
+                "event: epoch\ndata: {\"type\":\"epoch\",\"group\":\"ev-a\",\"epoch\":\"3\"}\n\n",
+            ]
+        );
+    }
+}
@@ -1,21 +1,28 @@
+use crate::persist;
 use anyhow::{Context, Result};
-use ark_std::fs;
-use ark_std::result::Result::Ok;
 use hex::FromHex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{
-    collections::HashMap,
-    fs::{File, OpenOptions},
-    io::{BufRead, BufReader, BufWriter, Write},
-    path::Path,
-};
 
 #[derive(Serialize, Deserialize, Clone)]
 struct ReputationEntry {
     cb: String,
     reputation: i32,
     timestamp: u64,
+    /// The reputation the callback has added to its author so far, net of decay.
+    #[serde(default)]
+    applied: i64,
+    /// The group the callback was called in.
+    #[serde(default)]
+    group: Option<String>,
+}
+
+/// A message whose callback added reputation to its author, see [`applied_reputations`].
+pub struct AppliedReputation {
+    pub timestamp: u64,
+    pub cb: String,
+    pub group: String,
+    pub applied: i64,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -31,160 +38,356 @@ struct PollEntry {
     votes: Vec<Vote>,
     ban: i64,
     context: String,
+    /// The Signal group the poll was sent to.
+    #[serde(default)]
+    group_id: String,
+    /// The group of the bulletins the message of a ban poll was posted against.
+    #[serde(default = "default_group")]
+    group: String,
+    /// When the poll closes, in seconds since the Unix epoch, if it closes on its own.
+    #[serde(default)]
+    deadline: Option<u64>,
 }
 
-pub fn update_reaction_log(new_ts: u64, new_delta: i32) -> std::io::Result<()> {
-    let mut entries: HashMap<u64, ReputationEntry> = HashMap::new();
+fn default_group() -> String {
+    crate::DEFAULT_GROUP.to_string()
+}
 
-    if Path::new("server/zkpair_log.jsonl").exists() {
-        let file = File::open("server/zkpair_log.jsonl")?;
-        let reader = BufReader::new(file);
+/// Where a poll was sent, for tallying it.
+pub struct PollInfo {
+    /// The Signal group the poll was sent to.
+    pub group_id: String,
+    /// The group of the bulletins the message of a ban poll was posted against.
+    pub group: String,
+}
 
-        for line in reader.lines() {
-            let line = line?;
-            let json: Value = serde_json::from_str(&line)?;
-            if json.get("timestamp").is_some()
-                && json.get("reputation").is_some()
-                && json.get("cb").is_some()
-            {
-                let entry: ReputationEntry = serde_json::from_value(json)?;
-                entries.insert(entry.timestamp, entry);
-            }
-        }
+/// Callbacks attached to sent messages, keyed by message timestamp.
+const MESSAGES: &str = "messages";
+/// Callbacks of verified posts which have not been sent yet, keyed by arrival.
+const PENDING: &str = "pending_callbacks";
+/// Open polls, keyed by poll timestamp.
+const POLLS: &str = "polls";
+/// The pseudonyms which voted on each open poll, keyed by poll timestamp and pseudonym.
+const POLL_PSEUDO: &str = "poll_pseudo";
+/// The context of each pseudonymous thread, keyed by thread.
+const CONTEXTS: &str = "contexts";
+/// Every pseudonymous thread, keyed by an id increasing in the order they were created.
+const CONTEXT_LOG: &str = "context_log";
+/// The reaction each Signal user left on a bot message from the app, keyed by message timestamp
+/// and user.
+const NATIVE_REACTIONS: &str = "native_reactions";
+/// How many of each reaction every bot message has, keyed by message timestamp and reaction
+/// name.
+const REACTION_COUNTS: &str = "reaction_counts";
+/// The messages the moderation policy opened a ban poll on, keyed by message timestamp.
+const AUTO_BAN_POLLS: &str = "auto_ban_polls";
+/// The badges issued to the author of each message, keyed by message timestamp and badge.
+const ISSUED_BADGES: &str = "issued_badges";
+/// Every message the bot sent for a client, keyed by message timestamp.
+const POSTS: &str = "posts";
+
+fn get_json<T: for<'a> Deserialize<'a>>(tree: &str, key: impl AsRef<[u8]>) -> Result<Option<T>> {
+    match persist::tree(tree)?.get(key)? {
+        Some(v) => Ok(Some(serde_json::from_slice(&v)?)),
+        None => Ok(None),
     }
+}
+
+fn insert_json<T: Serialize>(tree: &str, key: impl AsRef<[u8]>, value: &T) -> Result<()> {
+    persist::tree(tree)?.insert(key.as_ref(), serde_json::to_vec(value)?)?;
+    persist::flush()
+}
+
+fn get_poll(timestamp: u64) -> Result<Option<PollEntry>> {
+    get_json(POLLS, timestamp.to_be_bytes())
+}
+
+/// Queue the callback of a verified post, to be attached to the message once it is sent.
+pub fn push_pending_callback(cb_hex: &str) -> Result<()> {
+    let id = persist::next_id()?;
+    persist::tree(PENDING)?.insert(id.to_be_bytes(), cb_hex.as_bytes())?;
+    persist::flush()
+}
+
+/// Attach the most recently queued callback to the message sent at `timestamp`.
+pub fn attach_pending_callback(timestamp: u64) -> Result<()> {
+    let (_, cb) = persist::tree(PENDING)?
+        .pop_max()?
+        .context("Expected a pending callback before the message")?;
+    attach_callback(timestamp, String::from_utf8(cb.to_vec())?)
+}
+
+/// Attach the callback `cb_hex` to the message sent at `timestamp`.
+pub(crate) fn attach_callback(timestamp: u64, cb_hex: String) -> Result<()> {
+    let entry = ReputationEntry {
+        cb: cb_hex,
+        reputation: 0,
+        timestamp,
+        applied: 0,
+        group: None,
+    };
+    insert_json(MESSAGES, timestamp.to_be_bytes(), &entry)
+}
 
+pub fn update_reaction_log(new_ts: u64, new_delta: i32) -> Result<()> {
     // Update or insert the reputation entry
-    entries
-        .entry(new_ts)
-        .and_modify(|e| {
+    let entry = match get_json::<ReputationEntry>(MESSAGES, new_ts.to_be_bytes())? {
+        Some(mut e) => {
             e.reputation = (e.reputation + new_delta).max(0); // clamp to zero
-        })
-        .or_insert_with(|| ReputationEntry {
-            cb: String::from(""), // You may want to populate this meaningfully
+            e
+        }
+        None => ReputationEntry {
+            cb: String::from(""),
             reputation: new_delta.max(0),
             timestamp: new_ts,
-            
-        });
-
-    // Sort entries by timestamp
-    let mut sorted_entries: Vec<_> = entries.values().cloned().collect();
-    sorted_entries.sort_by_key(|e| e.timestamp);
+            applied: 0,
+            group: None,
+        },
+    };
+    insert_json(MESSAGES, new_ts.to_be_bytes(), &entry)
+}
 
-    // Rewrite the full file in timestamp order
-    let mut file = File::create("server/zkpair_log.jsonl")?;
-    for entry in sorted_entries {
-        let line = serde_json::to_string(&entry)?;
-        writeln!(file, "{}", line)?;
+pub fn get_reputation_by_cb(cb_hex: &str) -> Result<i64> {
+    for value in persist::tree(MESSAGES)?.iter().values() {
+        let entry: ReputationEntry = serde_json::from_slice(&value?)?;
+        if entry.cb == cb_hex {
+            return Ok(entry.reputation as i64);
+        }
     }
 
-    Ok(())
+    Err(anyhow::anyhow!("No entry found for cb {}", cb_hex))
 }
 
-pub fn get_reputation_by_cb(cb_hex: &str) -> Result<i64> {
-    let file = File::open("server/zkpair_log.jsonl").context("Failed to open log file")?;
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        let line = line?;
-        let json: Value = serde_json::from_str(&line)?;
-
-        if json.get("cb").and_then(|v| v.as_str()) == Some(cb_hex) {
-            return json
-                .get("reputation")
-                .and_then(|r| r.as_i64())
-                // .map(|r| r as i32)
-                .context("Missing or invalid reputation field");
+/// Record that the callback `cb_hex` was called in `group` to add `amount` to the reputation of
+/// its author.
+pub fn record_applied_reputation(cb_hex: &str, group: &str, amount: i64) -> Result<()> {
+    for value in persist::tree(MESSAGES)?.iter().values() {
+        let mut entry: ReputationEntry = serde_json::from_slice(&value?)?;
+        if entry.cb == cb_hex {
+            entry.applied = (entry.applied + amount).max(0);
+            entry.group = Some(group.to_string());
+            return insert_json(MESSAGES, entry.timestamp.to_be_bytes(), &entry);
         }
     }
 
     Err(anyhow::anyhow!("No entry found for cb {}", cb_hex))
 }
 
-pub fn delete_poll_pseudo_entry_by_timestamp(
-    timestamp: u64,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let path = "server/poll_pseudo_log.jsonl";
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-
-    let filtered_lines: Vec<String> = reader
-        .lines()
-        .filter_map(|line| {
-            let line = line.ok()?;
-            let json: Value = serde_json::from_str(&line).ok()?;
-            let ts = json["timestamp"].as_u64()?;
-            if ts != timestamp {
-                Some(line)
-            } else {
-                None
-            }
-        })
-        .collect();
+/// The messages whose callbacks have added reputation which has not decayed yet.
+pub fn applied_reputations() -> Result<Vec<AppliedReputation>> {
+    let mut applied = vec![];
+    for value in persist::tree(MESSAGES)?.iter().values() {
+        let entry: ReputationEntry = serde_json::from_slice(&value?)?;
+        if let (true, Some(group)) = (entry.applied > 0, entry.group) {
+            applied.push(AppliedReputation {
+                timestamp: entry.timestamp,
+                cb: entry.cb,
+                group,
+                applied: entry.applied,
+            });
+        }
+    }
+    Ok(applied)
+}
 
-    // Rewrite the file with only the remaining lines
-    let output = OpenOptions::new().write(true).truncate(true).open(path)?;
-    let mut writer = BufWriter::new(output);
-    for line in filtered_lines {
-        writeln!(writer, "{}", line)?;
+/// Set the reputation the callback of the message sent at `timestamp` has added, after decay.
+pub fn set_applied_reputation(timestamp: u64, applied: i64) -> Result<()> {
+    let Some(mut entry) = get_json::<ReputationEntry>(MESSAGES, timestamp.to_be_bytes())? else {
+        return Ok(());
+    };
+    entry.applied = applied;
+    insert_json(MESSAGES, timestamp.to_be_bytes(), &entry)
+}
+
+pub fn delete_poll_pseudo_entry_by_timestamp(timestamp: u64) -> Result<()> {
+    let tree = persist::tree(POLL_PSEUDO)?;
+    for key in tree.scan_prefix(timestamp.to_be_bytes()).keys() {
+        tree.remove(key?)?;
     }
+    persist::flush()
+}
+
+fn poll_voter_key(timestamp: u64, claimed: &str) -> Vec<u8> {
+    let mut key = timestamp.to_be_bytes().to_vec();
+    key.extend_from_slice(claimed.as_bytes());
+    key
+}
+
+/// Record that the pseudonym `claimed` voted on the poll sent at `timestamp`, returning `false`
+/// if it already had. Concurrent votes of one pseudonym are recorded only once.
+pub fn claim_poll_vote(timestamp: u64, claimed: &str) -> Result<bool> {
+    let swapped = persist::tree(POLL_PSEUDO)?.compare_and_swap(
+        poll_voter_key(timestamp, claimed),
+        None::<&[u8]>,
+        Some(&[][..]),
+    )?;
+    persist::flush()?;
+    Ok(swapped.is_ok())
+}
 
-    Ok(())
+/// Undo [`claim_poll_vote`], for a vote which could not be cast.
+pub fn release_poll_vote(timestamp: u64, claimed: &str) -> Result<()> {
+    persist::tree(POLL_PSEUDO)?.remove(poll_voter_key(timestamp, claimed))?;
+    persist::flush()
 }
 
-pub fn append_vote(
+/// Open a poll sent at `timestamp` to the Signal group `info.group_id`. `ban` is the timestamp of
+/// the message a ban poll is about, or 0 for other polls. The poll closes at `deadline` if given.
+pub fn append_poll(
     timestamp: u64,
-    pseudonym: &str,
-    seed: String,
-    emoji: &str,
-) -> std::io::Result<()> {
-    let path = "server/poll_log.jsonl";
-
-    // Step 1: Read all lines
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
-
-    let mut updated_lines = Vec::new();
-    let mut found = false;
-
-    for line in lines {
-        let mut entry: PollEntry = serde_json::from_str(&line)?;
-
-        if entry.timestamp == timestamp {
-            found = true;
-
-            // Step 2: Remove existing vote from same pseudonym and seed
-            entry
-                .votes
-                .retain(|v| !(v.poll_pseudonym == pseudonym && v.seed == seed));
-
-            // Step 3: Add the new vote (clone seed here because it's moved)
-            entry.votes.push(Vote {
-                poll_pseudonym: pseudonym.to_string(),
-                seed: seed.clone(), // <-- clone it only here
-                emoji: emoji.to_string(),
-            });
+    ban: i64,
+    context: &str,
+    info: PollInfo,
+    deadline: Option<u64>,
+) -> Result<()> {
+    let entry = PollEntry {
+        timestamp,
+        votes: vec![],
+        ban,
+        context: context.to_string(),
+        group_id: info.group_id,
+        group: info.group,
+        deadline,
+    };
+    insert_json(POLLS, timestamp.to_be_bytes(), &entry)
+}
 
-            // Serialize back to JSON
-            let updated_line = serde_json::to_string(&entry)?;
-            updated_lines.push(updated_line);
-        } else {
-            updated_lines.push(line);
+/// Where the open poll sent at `timestamp` was sent.
+pub fn get_poll_info(timestamp: u64) -> Result<Option<PollInfo>> {
+    Ok(get_poll(timestamp)?.map(|entry| PollInfo {
+        group_id: entry.group_id,
+        group: entry.group,
+    }))
+}
+
+/// The timestamps of the open polls whose deadline is at or before `now`.
+pub fn polls_due(now: u64) -> Result<Vec<u64>> {
+    let mut due = vec![];
+    for value in persist::tree(POLLS)?.iter().values() {
+        let entry: PollEntry = serde_json::from_slice(&value?)?;
+        if entry.deadline.is_some_and(|d| d <= now) {
+            due.push(entry.timestamp);
         }
     }
+    Ok(due)
+}
 
-    if !found {
+pub fn append_vote(timestamp: u64, pseudonym: &str, seed: String, emoji: &str) -> Result<()> {
+    let Some(mut entry) = get_poll(timestamp)? else {
         eprintln!("No poll entry found for timestamp {}", timestamp);
         return Ok(());
-    }
+    };
 
-    // Step 4: Rewrite the whole file
-    let mut file = OpenOptions::new().write(true).truncate(true).open(path)?;
-    for line in updated_lines {
-        writeln!(file, "{}", line)?;
-    }
+    // Remove existing vote from same pseudonym and seed
+    entry
+        .votes
+        .retain(|v| !(v.poll_pseudonym == pseudonym && v.seed == seed));
+
+    entry.votes.push(Vote {
+        poll_pseudonym: pseudonym.to_string(),
+        seed,
+        emoji: emoji.to_string(),
+    });
+
+    insert_json(POLLS, timestamp.to_be_bytes(), &entry)
+}
+
+/// Whether a poll was sent at `timestamp` and is still open.
+pub fn is_open_poll(timestamp: u64) -> Result<bool> {
+    Ok(persist::tree(POLLS)?.contains_key(timestamp.to_be_bytes())?)
+}
+
+/// Withdraw the vote of `pseudonym` with `seed` from the poll sent at `timestamp`.
+pub fn remove_vote(timestamp: u64, pseudonym: &str, seed: &str) -> Result<()> {
+    let Some(mut entry) = get_poll(timestamp)? else {
+        return Ok(());
+    };
+    entry
+        .votes
+        .retain(|v| !(v.poll_pseudonym == pseudonym && v.seed == seed));
+    insert_json(POLLS, timestamp.to_be_bytes(), &entry)
+}
+
+/// Record the reaction `author` left in the Signal app on the message sent at `timestamp`, or
+/// that they removed it, returning the reaction it replaces. Signal keeps one reaction per user
+/// and message, so a new one replaces the last rather than adding to it.
+pub fn swap_native_reaction(
+    timestamp: u64,
+    author: &str,
+    emoji: Option<&str>,
+) -> Result<Option<String>> {
+    let mut key = timestamp.to_be_bytes().to_vec();
+    key.extend_from_slice(author.as_bytes());
+    let tree = persist::tree(NATIVE_REACTIONS)?;
+    let previous = match emoji {
+        Some(emoji) => tree.insert(key, emoji.as_bytes())?,
+        None => tree.remove(key)?,
+    };
+    persist::flush()?;
+    Ok(previous.map(|e| String::from_utf8_lossy(&e).into_owned()))
+}
 
-    Ok(())
+/// Count a `reaction` (by name) being added to the message sent at `timestamp`, or removed from
+/// it when `added` is false, returning the count before and after.
+pub fn count_reaction(timestamp: u64, reaction: &str, added: bool) -> Result<(u32, u32)> {
+    let mut key = timestamp.to_be_bytes().to_vec();
+    key.extend_from_slice(reaction.as_bytes());
+    let step = |count: u32| {
+        if added {
+            count.saturating_add(1)
+        } else {
+            count.saturating_sub(1)
+        }
+    };
+    let decode =
+        |v: Option<&[u8]>| v.map_or(0, |v| u32::from_be_bytes(v.try_into().unwrap_or_default()));
+    let before = persist::tree(REACTION_COUNTS)?
+        .fetch_and_update(key, |old| Some(step(decode(old)).to_be_bytes().to_vec()))?;
+    persist::flush()?;
+    let before = decode(before.as_deref());
+    Ok((before, step(before)))
+}
+
+/// Record that the moderation policy opens a ban poll on the message sent at `timestamp`,
+/// returning `false` if it already has.
+pub fn claim_auto_ban_poll(timestamp: u64) -> Result<bool> {
+    let swapped = persist::tree(AUTO_BAN_POLLS)?.compare_and_swap(
+        timestamp.to_be_bytes(),
+        None::<&[u8]>,
+        Some(&[][..]),
+    )?;
+    persist::flush()?;
+    Ok(swapped.is_ok())
+}
+
+/// Undo [`claim_auto_ban_poll`], for a ban poll which could not be opened.
+pub fn release_auto_ban_poll(timestamp: u64) -> Result<()> {
+    persist::tree(AUTO_BAN_POLLS)?.remove(timestamp.to_be_bytes())?;
+    persist::flush()
+}
+
+fn badge_key(timestamp: u64, badge: u64) -> Vec<u8> {
+    let mut key = timestamp.to_be_bytes().to_vec();
+    key.extend_from_slice(&badge.to_be_bytes());
+    key
+}
+
+/// Record that `badge` is issued for the message sent at `timestamp`, returning `false` if it
+/// already was.
+pub fn claim_badge(timestamp: u64, badge: u64) -> Result<bool> {
+    let swapped = persist::tree(ISSUED_BADGES)?.compare_and_swap(
+        badge_key(timestamp, badge),
+        None::<&[u8]>,
+        Some(&[][..]),
+    )?;
+    persist::flush()?;
+    Ok(swapped.is_ok())
+}
+
+/// Undo [`claim_badge`], for a badge which could not be issued.
+pub fn release_badge(timestamp: u64, badge: u64) -> Result<()> {
+    persist::tree(ISSUED_BADGES)?.remove(badge_key(timestamp, badge))?;
+    persist::flush()
 }
 
 pub fn emoji_to_name(emoji: &str) -> &'static str {
@@ -226,123 +429,325 @@ pub fn count_votes(val: &Value) -> (usize, usize) {
 }
 
 pub fn count_votes_by_timestamp(target_ts: i64) -> (usize, usize) {
-    let file = File::open("server/poll_log.jsonl").expect("Failed to open file");
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        let line = line.expect("Failed to read line");
-        let val: Value = serde_json::from_str(&line).expect("Invalid JSON");
-
-        if val["timestamp"].as_i64() == Some(target_ts) {
-            return count_votes(&val);
-        }
+    match get_poll(target_ts as u64) {
+        Ok(Some(entry)) => count_votes(&serde_json::to_value(entry).expect("Invalid poll entry")),
+        _ => (0, 0), // not found
     }
-
-    (0, 0) // not found
 }
 
 pub fn is_ban_poll_by_timestamp(target_ts: i64) -> bool {
-    let file = File::open("server/poll_log.jsonl").expect("Failed to open file");
-    let reader = BufReader::new(file);
+    // not found or ban flag is 0
+    get_ban_from_timestamp(target_ts).unwrap_or(0) != 0
+}
+
+pub fn get_ban_from_timestamp(target_ts: i64) -> Option<i64> {
+    Some(get_poll(target_ts as u64).ok()??.ban)
+}
 
-    for line in reader.lines() {
-        let line = line.expect("Failed to read line");
-        let val: Value = serde_json::from_str(&line).expect("Invalid JSON");
+pub fn get_context_from_timestamp(target_ts: i64) -> Option<String> {
+    Some(get_poll(target_ts as u64).ok()??.context)
+}
 
-        if val["timestamp"].as_i64() == Some(target_ts) {
-            return val["ban"].as_i64().unwrap_or(0) != 0;
-        }
+pub fn delete_poll_entry_by_timestamp(target_ts: u64) -> Result<()> {
+    if let Some(entry) = persist::tree(POLLS)?.remove(target_ts.to_be_bytes())? {
+        println!("Deleting poll entry with timestamp: {}", target_ts);
+        let (yes, no) = count_votes(&serde_json::from_slice(&entry)?);
+        close_poll_post(target_ts, yes, no)?;
     }
+    persist::flush()
+}
 
-    false // not found or ban flag is 0
+pub fn find_callback_by_timestamp(timestamp: u64) -> Result<Vec<u8>> {
+    let entry: ReputationEntry = get_json(MESSAGES, timestamp.to_be_bytes())?
+        .with_context(|| format!("Callback not found for timestamp {}", timestamp))?;
+    Ok(Vec::from_hex(entry.cb)?)
 }
 
-pub fn get_ban_from_timestamp(target_ts: i64) -> Option<i64> {
-    let file = File::open("server/poll_log.jsonl").ok()?;
-    let reader = BufReader::new(file);
+/// Every callback attached to a sent message, as `(timestamp, callback hex, reputation)`, in
+/// order of timestamp.
+pub fn all_callbacks() -> Result<Vec<(u64, String, i32)>> {
+    persist::tree(MESSAGES)?
+        .iter()
+        .values()
+        .map(|v| {
+            let entry: ReputationEntry = serde_json::from_slice(&v?)?;
+            Ok((entry.timestamp, entry.cb, entry.reputation))
+        })
+        .collect()
+}
 
-    for line in reader.lines() {
-        let line = line.ok()?;
-        let val: Value = serde_json::from_str(&line).ok()?;
+/// The number of callbacks of verified posts still waiting for their message to be sent.
+pub fn pending_callback_count() -> Result<usize> {
+    Ok(persist::tree(PENDING)?.len())
+}
 
-        if val["timestamp"].as_i64() == Some(target_ts) {
-            return val["ban"].as_i64();
-        }
+/// Store the context of a new pseudonymous thread. If the thread already exists, its context is
+/// kept and returned.
+pub fn insert_thread_context(thread: &str, context: &str) -> Result<Option<String>> {
+    let swapped = persist::tree(CONTEXTS)?.compare_and_swap(
+        thread,
+        None::<&[u8]>,
+        Some(context.as_bytes()),
+    )?;
+    if let Err(existing) = swapped {
+        return Ok(existing
+            .current
+            .map(|c| String::from_utf8_lossy(&c).into_owned()));
     }
-
-    None // timestamp not found
+    let id = persist::next_id()?;
+    persist::tree(CONTEXT_LOG)?.insert(id.to_be_bytes(), thread.as_bytes())?;
+    persist::flush()?;
+    Ok(None)
 }
 
-pub fn get_context_from_timestamp(target_ts: i64) -> Option<String> {
-    let file = File::open("server/poll_log.jsonl").ok()?;
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        let line = line.ok()?;
-        let val: Value = serde_json::from_str(&line).ok()?;
+/// A pseudonymous thread, as listed by [`list_thread_contexts`].
+#[derive(Serialize)]
+pub struct ThreadContext {
+    /// Increases in the order threads were created, so a client can fetch only newer threads.
+    pub id: u64,
+    pub thread: String,
+    pub context: String,
+}
 
-        if val["timestamp"].as_i64() == Some(target_ts) {
-            return val["context"].as_str().map(|s| s.to_string());
+/// Up to `limit` threads created after the thread `after`, in the order they were created. With
+/// `search`, only threads whose name contains it (ignoring case).
+pub fn list_thread_contexts(
+    after: Option<u64>,
+    search: Option<&str>,
+    limit: usize,
+) -> Result<Vec<ThreadContext>> {
+    let start = after.map_or(0, |id| id.saturating_add(1));
+    let search = search.map(str::to_lowercase);
+    let contexts = persist::tree(CONTEXTS)?;
+    let mut threads = vec![];
+    for entry in persist::tree(CONTEXT_LOG)?.range(start.to_be_bytes()..) {
+        if threads.len() >= limit {
+            break;
+        }
+        let (id, thread) = entry?;
+        let thread = String::from_utf8(thread.to_vec())?;
+        if search
+            .as_ref()
+            .is_some_and(|s| !thread.to_lowercase().contains(s))
+        {
+            continue;
         }
+        let Some(context) = contexts.get(&thread)? else {
+            continue;
+        };
+        threads.push(ThreadContext {
+            id: u64::from_be_bytes(id.as_ref().try_into()?),
+            thread,
+            context: String::from_utf8(context.to_vec())?,
+        });
     }
+    Ok(threads)
+}
 
-    None
+/// Give the threads stored before threads were logged an id, so they are listed.
+pub fn index_thread_contexts() -> Result<()> {
+    let log = persist::tree(CONTEXT_LOG)?;
+    if !log.is_empty() {
+        return Ok(());
+    }
+    for thread in persist::tree(CONTEXTS)?.iter().keys() {
+        log.insert(persist::next_id()?.to_be_bytes(), thread?)?;
+    }
+    persist::flush()
 }
 
-pub fn delete_poll_entry_by_timestamp(target_ts: u64) -> std::io::Result<()> {
-    let path = "server/poll_log.jsonl";
+/// Get every pseudonymous thread along with its context.
+pub fn get_all_contexts() -> Result<Vec<(String, String)>> {
+    persist::tree(CONTEXTS)?
+        .iter()
+        .map(|e| {
+            let (thread, context) = e?;
+            Ok((
+                String::from_utf8(thread.to_vec())?,
+                String::from_utf8(context.to_vec())?,
+            ))
+        })
+        .collect()
+}
 
-    // Step 1: Read all entries
-    let file = fs::File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut remaining_lines = Vec::new();
+/// Find the thread with the given context.
+pub fn find_thread_by_context(context: &str) -> Result<Option<String>> {
+    Ok(get_all_contexts()?
+        .into_iter()
+        .find(|(_, c)| c == context)
+        .map(|(thread, _)| thread))
+}
 
-    for line in reader.lines() {
-        let line = line?;
-        let entry: PollEntry = match serde_json::from_str(&line) {
-            Ok(e) => e,
-            Err(_) => {
-                eprintln!("Skipping invalid line: {}", line);
-                continue;
-            }
-        };
+/// How a poll stands, as listed with its message.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PollState {
+    /// The timestamp of the message a ban poll is about.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ban: Option<u64>,
+    pub open: bool,
+    /// Votes to ban on a ban poll, and for yes on others.
+    pub yes: usize,
+    /// Votes to keep on a ban poll, and for no on others.
+    pub no: usize,
+    /// When the poll closes, in seconds since the Unix epoch, if it closes on its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
+}
 
-        if entry.timestamp != target_ts {
-            remaining_lines.push(line);
-        } else {
-            println!("Deleting poll entry with timestamp: {}", target_ts);
+/// A message the bot sent for a client, as listed by [`list_posts`].
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Post {
+    pub timestamp: u64,
+    /// The Signal group it was sent to.
+    pub group_id: String,
+    /// The message, without the header naming its pseudonym.
+    pub message: String,
+    /// How many files were attached to it.
+    #[serde(default)]
+    pub attachments: usize,
+    /// The pseudonym it was posted under, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pseudonym: Option<String>,
+    /// The pseudonymous thread it was posted in, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+    /// The timestamp of the message it replies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<u64>,
+    /// The reputation the reactions on it have given its author so far.
+    #[serde(default)]
+    pub reputation: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll: Option<PollState>,
+}
+
+impl Post {
+    /// A message sent to `group_id` at `timestamp`, in no thread.
+    pub fn new(timestamp: u64, group_id: &str, message: &str) -> Self {
+        Self {
+            timestamp,
+            group_id: group_id.to_string(),
+            message: message.to_string(),
+            attachments: 0,
+            pseudonym: None,
+            thread: None,
+            reply_to: None,
+            reputation: 0,
+            poll: None,
         }
     }
+}
 
-    // Step 2: Rewrite the file with filtered entries
-    let mut file = OpenOptions::new().write(true).truncate(true).open(path)?;
-    for line in remaining_lines {
-        writeln!(file, "{}", line)?;
+/// Record a message the bot sent for a client. A reply in no thread is put in the thread of the
+/// message it replies to.
+pub fn record_post(mut post: Post) -> Result<()> {
+    if let (None, Some(parent)) = (&post.thread, post.reply_to) {
+        post.thread = get_json::<Post>(POSTS, parent.to_be_bytes())?.and_then(|p| p.thread);
     }
+    insert_json(POSTS, post.timestamp.to_be_bytes(), &post)
+}
 
-    Ok(())
+/// Record the final votes of the poll sent at `timestamp`, once it closes.
+fn close_poll_post(timestamp: u64, yes: usize, no: usize) -> Result<()> {
+    let Some(mut post) = get_json::<Post>(POSTS, timestamp.to_be_bytes())? else {
+        return Ok(());
+    };
+    if let Some(poll) = &mut post.poll {
+        poll.open = false;
+        poll.yes = yes;
+        poll.no = no;
+    }
+    insert_json(POSTS, timestamp.to_be_bytes(), &post)
 }
 
-pub fn find_callback_by_timestamp(timestamp: u64) -> Result<Vec<u8>> {
-    let file = File::open("server/zkpair_log.jsonl").context("Failed to open zkpair_log.jsonl")?;
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        let line = line?;
-        let json: Value = serde_json::from_str(&line)?;
-
-        if json.get("timestamp").and_then(|t| t.as_u64()) == Some(timestamp) {
-            let cb_str = json
-                .get("cb")
-                .and_then(|v| v.as_str())
-                .context("Missing `cb` field")?;
-            let cb_bytes = Vec::from_hex(cb_str)?;
-            return Ok(cb_bytes);
+/// Up to `limit` messages sent before the timestamp `before`, newest first, with their
+/// reputation and the votes on open polls so far. With `group_id` or `thread`, only the messages
+/// sent to that Signal group or posted in that thread.
+pub fn list_posts(
+    before: Option<u64>,
+    group_id: Option<&str>,
+    thread: Option<&str>,
+    limit: usize,
+) -> Result<Vec<Post>> {
+    let end = before.unwrap_or(u64::MAX);
+    let mut posts = vec![];
+    for value in persist::tree(POSTS)?
+        .range(..end.to_be_bytes())
+        .values()
+        .rev()
+    {
+        if posts.len() >= limit {
+            break;
+        }
+        let mut post: Post = serde_json::from_slice(&value?)?;
+        if group_id.is_some_and(|g| g != post.group_id)
+            || thread.is_some_and(|t| post.thread.as_deref() != Some(t))
+        {
+            continue;
+        }
+        if let Some(entry) = get_json::<ReputationEntry>(MESSAGES, post.timestamp.to_be_bytes())? {
+            post.reputation = entry.reputation;
         }
+        if let (Some(poll), Some(entry)) = (&mut post.poll, get_poll(post.timestamp)?) {
+            (poll.yes, poll.no) = count_votes(&serde_json::to_value(entry)?);
+        }
+        posts.push(post);
+    }
+    Ok(posts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Queued callbacks are attached to the message sent after them, and its reputation is kept
+    #[test]
+    fn pending_callback_attached() {
+        persist::open_temp();
+        push_pending_callback("0a0b").unwrap();
+        attach_pending_callback(4839001).unwrap();
+        assert_eq!(find_callback_by_timestamp(4839001).unwrap(), vec![0x0a, 0x0b]);
+
+        update_reaction_log(4839001, 3).unwrap();
+        update_reaction_log(4839001, -5).unwrap();
+        assert_eq!(get_reputation_by_cb("0a0b").unwrap(), 0);
+        assert!(find_callback_by_timestamp(4839002).is_err());
     }
 
-    Err(anyhow::anyhow!(
-        "Callback not found for timestamp {}",
-        timestamp
-    ))
+    // A poll keeps one vote per pseudonym and seed, and is gone once deleted
+    #[test]
+    fn poll_votes() {
+        persist::open_temp();
+        let info = PollInfo {
+            group_id: "signal-group".to_string(),
+            group: crate::DEFAULT_GROUP.to_string(),
+        };
+        append_poll(4839010, 0, "42", info, None).unwrap();
+        assert_eq!(get_context_from_timestamp(4839010).as_deref(), Some("42"));
+        assert!(!is_ban_poll_by_timestamp(4839010));
+
+        append_vote(4839010, "alice", "1".to_string(), "👍").unwrap();
+        append_vote(4839010, "alice", "1".to_string(), "👎").unwrap();
+        append_vote(4839010, "bob", "2".to_string(), "👎").unwrap();
+        assert_eq!(count_votes_by_timestamp(4839010), (0, 2));
+
+        delete_poll_entry_by_timestamp(4839010).unwrap();
+        assert!(!is_open_poll(4839010).unwrap());
+        assert_eq!(get_context_from_timestamp(4839010), None);
+    }
+
+    // A thread keeps the context it was first stored with
+    #[test]
+    fn thread_context_kept() {
+        persist::open_temp();
+        assert_eq!(insert_thread_context("helpers-thread", "1").unwrap(), None);
+        assert_eq!(
+            insert_thread_context("helpers-thread", "2").unwrap().as_deref(),
+            Some("1")
+        );
+        assert_eq!(
+            find_thread_by_context("1").unwrap().as_deref(),
+            Some("helpers-thread")
+        );
+    }
 }
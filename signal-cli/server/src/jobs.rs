@@ -0,0 +1,193 @@
+//! A queue of interactions verified in the background.
+//!
+//! Verifying an interaction and appending it to a bulletin runs a Groth16 verification under the
+//! group's bulletin lock. Rather than doing that inline in a request, clients can submit the
+//! interaction to this queue: the request returns at once with a job id, a fixed pool of workers
+//! runs the verifications, and `/api/status/{id}` reports the outcome.
+
+use crate::error::ApiError;
+use crate::server::{verify_and_store_scan, verify_and_store_standard, ServerLock};
+use axum::body::Bytes;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::sync::mpsc;
+use tracing::info;
+
+/// How many finished jobs the queue remembers the outcome of.
+const MAX_STATUSES: usize = 10_000;
+
+/// An interaction to verify and store.
+pub enum Job {
+    Standard { group: String, body: Bytes },
+    Scan { group: String, body: Bytes },
+}
+
+impl Job {
+    async fn run(self, state: &ServerLock) -> Result<bool, ApiError> {
+        match self {
+            Job::Standard { group, body } => verify_and_store_standard(state, &group, &body).await,
+            Job::Scan { group, body } => verify_and_store_scan(state, &group, &body).await,
+        }
+    }
+}
+
+/// The state of a submitted job.
+#[derive(Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    /// The job finished; `accepted` is whether the interaction was stored.
+    Done {
+        accepted: bool,
+    },
+    /// The job failed, with the kind and message of the [`ApiError`] it failed with.
+    Failed {
+        error: String,
+        detail: String,
+    },
+}
+
+/// A handle to the job queue, shared by the handlers.
+#[derive(Clone)]
+pub struct JobQueue {
+    tx: mpsc::Sender<(u64, Job)>,
+    statuses: Arc<Mutex<BTreeMap<u64, JobStatus>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl JobQueue {
+    /// Start `workers` workers, holding up to `capacity` jobs which are waiting to run.
+    pub fn start(state: ServerLock, workers: usize, capacity: usize) -> Self {
+        let (tx, rx) = mpsc::channel::<(u64, Job)>(capacity);
+        let rx = Arc::new(tokio::sync::Mutex::new(rx));
+        let queue = Self {
+            tx,
+            statuses: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        };
+
+        for _ in 0..workers.max(1) {
+            let rx = rx.clone();
+            let state = state.clone();
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                loop {
+                    let Some((id, job)) = rx.lock().await.recv().await else {
+                        break;
+                    };
+                    queue.set(id, JobStatus::Running);
+                    let status = match job.run(&state).await {
+                        Ok(accepted) => JobStatus::Done { accepted },
+                        Err(e) => JobStatus::Failed {
+                            error: e.kind().to_string(),
+                            detail: e.to_string(),
+                        },
+                    };
+                    info!("[SERVER] Job {} finished", id);
+                    queue.set(id, status);
+                }
+            });
+        }
+
+        queue
+    }
+
+    /// Queue a job, returning its id.
+    pub fn submit(&self, job: Job) -> Result<u64, ApiError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.set(id, JobStatus::Queued);
+        self.tx.try_send((id, job)).map_err(|e| {
+            self.statuses.lock().unwrap().remove(&id);
+            match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    ApiError::RateLimited("the job queue is full".to_string())
+                }
+                mpsc::error::TrySendError::Closed(_) => {
+                    ApiError::internal("the job queue is closed")
+                }
+            }
+        })?;
+        info!("[SERVER] Queued job {}", id);
+        Ok(id)
+    }
+
+    /// The state of a job, if it is known.
+    pub fn status(&self, id: u64) -> Option<JobStatus> {
+        self.statuses.lock().unwrap().get(&id).cloned()
+    }
+
+    fn set(&self, id: u64, status: JobStatus) {
+        let mut statuses = self.statuses.lock().unwrap();
+        statuses.insert(id, status);
+        while statuses.len() > MAX_STATUSES {
+            statuses.pop_first();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::Config, testing};
+    use axum::http::StatusCode;
+    use std::time::Duration;
+
+    // A job which cannot be read fails with the kind of its error
+    #[tokio::test]
+    async fn failed_job() {
+        let state = testing::server(Config::default(), "jobs");
+        let jobs = JobQueue::start(state, 1, 4);
+        let id = jobs
+            .submit(Job::Standard {
+                group: "jobs".to_string(),
+                body: Bytes::from_static(&[1, 2, 3]),
+            })
+            .unwrap();
+        assert!(jobs.status(id + 1).is_none());
+
+        let status = loop {
+            match jobs.status(id).unwrap() {
+                JobStatus::Queued | JobStatus::Running => {
+                    tokio::time::sleep(Duration::from_millis(10)).await
+                }
+                status => break status,
+            }
+        };
+        let JobStatus::Failed { error, .. } = status else {
+            panic!("the job did not fail");
+        };
+        assert_eq!(error, "malformed_payload");
+    }
+
+    // Jobs past the capacity of the queue are turned away and forgotten
+    #[test]
+    fn full_queue() {
+        let (tx, rx) = mpsc::channel(1);
+        let jobs = JobQueue {
+            tx,
+            statuses: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        };
+        let job = || Job::Scan {
+            group: "jobs".to_string(),
+            body: Bytes::new(),
+        };
+
+        let id = jobs.submit(job()).unwrap();
+        assert!(matches!(jobs.status(id), Some(JobStatus::Queued)));
+        let err = jobs.submit(job()).unwrap_err();
+        assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(jobs.status(id + 1).is_none());
+
+        drop(rx);
+        let err = jobs.submit(job()).unwrap_err();
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,296 @@
+//! Reactions and replies sent from the Signal app.
+//!
+//! Without this, only reactions and votes submitted through the server count. The listener
+//! subscribes to the messages the bot receives and applies the ones aimed at a bot message the
+//! same way: a reaction on a post is applied by the moderation policy, and a reaction on a poll
+//! is counted as a vote. A reply whose text is one of those emojis (or their
+//! names, as accepted by `/api/react`) counts as the reaction.
+//!
+//! Signal keeps one reaction per user and message, so every user counts once per message: a new
+//! reaction replaces their last one, and removing it withdraws it.
+
+use crate::error::ApiError;
+use crate::helpers::{append_vote, is_open_poll, remove_vote, swap_native_reaction};
+use crate::moderation::apply_reaction;
+use crate::server::{emoji_to_name, string_to_emoji, ServerLock};
+use crate::{ServerState, DEFAULT_GROUP};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// The longest the listener waits before reconnecting to the daemon.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct Received {
+    envelope: Envelope,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    source_number: Option<String>,
+    source_uuid: Option<String>,
+    data_message: Option<DataMessage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataMessage {
+    message: Option<String>,
+    reaction: Option<Reaction>,
+    quote: Option<Quote>,
+    group_info: Option<GroupInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupInfo {
+    group_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reaction {
+    emoji: String,
+    target_author_number: Option<String>,
+    target_sent_timestamp: u64,
+    #[serde(default)]
+    is_remove: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Quote {
+    id: u64,
+    author_number: Option<String>,
+}
+
+/// Listen for the incoming messages of every bot account until the server stops, reconnecting
+/// when the daemon goes away.
+pub fn spawn(state: ServerLock) {
+    for account in state.bot.accounts() {
+        let account = account.to_string();
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut backoff = Duration::from_secs(1);
+            loop {
+                match listen(&state, &account).await {
+                    Ok(()) => {
+                        warn!(
+                            "[LISTENER] signal-cli closed the subscription of {}",
+                            account
+                        );
+                        backoff = Duration::from_secs(1);
+                    }
+                    Err(e) => warn!("[LISTENER] {}: {}", account, e),
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+    }
+}
+
+async fn listen(state: &ServerLock, account: &str) -> Result<(), ApiError> {
+    let (_client, mut subscription) = state.bot.subscribe_receive(account).await?;
+    info!(
+        "[LISTENER] Listening for reactions and replies to {}",
+        account
+    );
+    while let Some(received) = subscription.next().await {
+        let received = received.map_err(|e| ApiError::Signal(e.to_string()))?;
+        let Ok(received) = serde_json::from_value::<Received>(received) else {
+            continue;
+        };
+        if let Err(e) = handle(state, account, received.envelope).await {
+            warn!("[LISTENER] Failed to apply a message: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Apply a message received by the bot account `bot`, if it reacts or replies to a message
+/// of `bot`.
+async fn handle(state: &ServerState, bot: &str, envelope: Envelope) -> Result<(), ApiError> {
+    // The bot's own messages include the reactions it forwards for `/api/react`, which were
+    // already applied.
+    if envelope.source_number.as_deref() == Some(bot) {
+        return Ok(());
+    }
+    let Some(author) = envelope.source_uuid.or(envelope.source_number) else {
+        return Ok(());
+    };
+    let Some(data) = envelope.data_message else {
+        return Ok(());
+    };
+    let group_id = data.group_info.map(|g| g.group_id);
+
+    let (timestamp, emoji) = if let Some(reaction) = data.reaction {
+        if reaction.target_author_number.as_deref() != Some(bot) {
+            return Ok(());
+        }
+        let emoji = (!reaction.is_remove).then_some(reaction.emoji);
+        (reaction.target_sent_timestamp, emoji)
+    } else if let (Some(quote), Some(text)) = (data.quote, data.message) {
+        if quote.author_number.as_deref() != Some(bot) {
+            return Ok(());
+        }
+        let emoji = string_to_emoji(text.trim());
+        if emoji == "❓" {
+            return Ok(());
+        }
+        (quote.id, Some(emoji.to_string()))
+    } else {
+        return Ok(());
+    };
+
+    if is_open_poll(timestamp)? {
+        vote(timestamp, &author, emoji.as_deref())
+    } else {
+        react(
+            state,
+            group_id.as_deref(),
+            timestamp,
+            &author,
+            emoji.as_deref(),
+        )
+        .await
+    }
+}
+
+/// Count a reaction on a poll as the author's vote, replacing their last one.
+fn vote(timestamp: u64, author: &str, emoji: Option<&str>) -> Result<(), ApiError> {
+    let voter = format!("signal:{}", author);
+    match emoji {
+        Some(emoji) => match emoji_to_name(emoji) {
+            "upvote" | "downvote" | "ban" | "not ban" => {
+                info!("[LISTENER] Vote {} on poll {}", emoji, timestamp);
+                append_vote(timestamp, &voter, String::new(), emoji)?;
+            }
+            _ => (),
+        },
+        None => remove_vote(timestamp, &voter, "")?,
+    }
+    Ok(())
+}
+
+/// Replace the author's last reaction to a message sent to the Signal group `group_id` with
+/// `emoji`, withdrawing the last one from the moderation policy and applying the new one.
+/// Messages read from the app do not say which bulletin group they were posted against, so a ban
+/// poll opened by the policy is about the default group.
+async fn react(
+    state: &ServerState,
+    group_id: Option<&str>,
+    timestamp: u64,
+    author: &str,
+    emoji: Option<&str>,
+) -> Result<(), ApiError> {
+    let previous = swap_native_reaction(timestamp, author, emoji)?;
+    if previous.as_deref() == emoji {
+        return Ok(());
+    }
+    if let Some(previous) = previous {
+        apply_reaction(state, group_id, DEFAULT_GROUP, timestamp, &previous, false).await?;
+    }
+    if let Some(emoji) = emoji {
+        info!("[LISTENER] Reaction {} on message {}", emoji, timestamp);
+        apply_reaction(state, group_id, DEFAULT_GROUP, timestamp, emoji, true).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::helpers::{
+        append_poll, attach_callback, count_votes_by_timestamp, get_reputation_by_cb, PollInfo,
+    };
+    use crate::testing;
+    use serde_json::json;
+
+    const BOT: &str = "+15712811486";
+
+    fn reaction(source: &str, emoji: &str, target: &str, timestamp: u64, remove: bool) -> Envelope {
+        serde_json::from_value(json!({
+            "sourceNumber": source,
+            "dataMessage": {
+                "reaction": {
+                    "emoji": emoji,
+                    "targetAuthorNumber": target,
+                    "targetSentTimestamp": timestamp,
+                    "isRemove": remove,
+                },
+                "groupInfo": { "groupId": "signal-group" },
+            },
+        }))
+        .unwrap()
+    }
+
+    fn reply(source: &str, text: &str, timestamp: u64) -> Envelope {
+        serde_json::from_value(json!({
+            "sourceNumber": source,
+            "dataMessage": {
+                "message": text,
+                "quote": { "id": timestamp, "authorNumber": BOT },
+            },
+        }))
+        .unwrap()
+    }
+
+    // Each user's reaction on a post counts once, and a new one replaces it
+    #[tokio::test]
+    async fn native_reactions() {
+        let state = testing::server(Config::default(), "listener");
+        attach_callback(4851001, "4851aa".to_string()).unwrap();
+        let reputation = || get_reputation_by_cb("4851aa").unwrap();
+
+        handle(&state, BOT, reaction("+9", "👍", BOT, 4851001, false)).await.unwrap();
+        assert_eq!(reputation(), 1);
+        handle(&state, BOT, reaction("+9", "👍", BOT, 4851001, false)).await.unwrap();
+        assert_eq!(reputation(), 1);
+
+        // Reactions by the bot, or to someone else's message, are not applied
+        handle(&state, BOT, reaction(BOT, "👍", BOT, 4851001, false)).await.unwrap();
+        handle(&state, BOT, reaction("+8", "👍", "+7", 4851001, false)).await.unwrap();
+        assert_eq!(reputation(), 1);
+
+        handle(&state, BOT, reply("+8", "upvote", 4851001)).await.unwrap();
+        assert_eq!(reputation(), 2);
+        handle(&state, BOT, reaction("+9", "👍", BOT, 4851001, true)).await.unwrap();
+        assert_eq!(reputation(), 1);
+    }
+
+    // Every bot account applies the reactions to its own messages only
+    #[tokio::test]
+    async fn reactions_per_account() {
+        let state = testing::server(Config::default(), "listener-accounts");
+        attach_callback(4853001, "4853aa".to_string()).unwrap();
+        let reputation = || get_reputation_by_cb("4853aa").unwrap();
+
+        handle(&state, BOT, reaction("+9", "👍", "+2", 4853001, false)).await.unwrap();
+        assert_eq!(reputation(), 0);
+        handle(&state, "+2", reaction("+9", "👍", "+2", 4853001, false)).await.unwrap();
+        assert_eq!(reputation(), 1);
+    }
+
+    // Reactions and replies on a poll are the user's vote
+    #[tokio::test]
+    async fn native_votes() {
+        let state = testing::server(Config::default(), "listener-votes");
+        let info = PollInfo {
+            group_id: "signal-group".to_string(),
+            group: DEFAULT_GROUP.to_string(),
+        };
+        append_poll(4851010, 0, "5", info, None).unwrap();
+
+        handle(&state, BOT, reaction("+9", "👍", BOT, 4851010, false)).await.unwrap();
+        assert_eq!(count_votes_by_timestamp(4851010), (1, 0));
+        handle(&state, BOT, reply("+9", "downvote", 4851010)).await.unwrap();
+        handle(&state, BOT, reaction("+8", "🤬", BOT, 4851010, false)).await.unwrap();
+        assert_eq!(count_votes_by_timestamp(4851010), (0, 1));
+        handle(&state, BOT, reaction("+9", "👎", BOT, 4851010, true)).await.unwrap();
+        assert_eq!(count_votes_by_timestamp(4851010), (0, 0));
+    }
+}
@@ -1,43 +1,91 @@
+mod admin;
+mod auth;
+mod badges;
+mod bot;
+mod config;
+mod decay;
+mod dm;
+mod error;
+mod events;
 mod helpers;
+mod jobs;
+mod listener;
+mod moderation;
+mod persist;
+mod polls;
+mod rpc;
 mod server;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use ark_groth16::Groth16;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
-use axum::{routing::{get, post}, Router};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use axum::{extract::DefaultBodyLimit, middleware, routing::{get, post}, Extension, Router};
 use common::{
-    Cr, E, F, H, OStore, PK, Snark, Store, VK,
+    catalog, with_authors, Cr, E, F, H, OStore, PK, Snark, Store, VK,
     zk::{
         get_extra_pubdata_for_scan, get_scan_interaction, get_standard_interaction,
         get_standard_pseudo_interaction, get_standard_pseudo_rate_interaction,
-        pseudonym_pred, authorship_pred, badge_pred, MsgUser,
+        pseudonym_pred, authorship_pred, badge_pred, reputation_pred, MsgUser,
         PseudonymArgs, PseudonymArgsVar, PseudonymArgsRate,
-        PseudonymArgsPair, PseudonymArgsPairVar,
-        BadgesArgs, BadgesArgsVar,
+        SameAuthorArgs, SameAuthorArgsVar,
+        BadgesArgs, BadgesArgsVar, RepArgs, RepArgsVar,
     }
 };
+use auth::{ApiKeys, IpRateLimit, Scope};
+use clap::Parser;
+use client::helpers::set_timings_dir;
+use bot::Bot;
+use config::Config;
+use events::{handle_subscribe, Events};
+use jobs::JobQueue;
 use server::{
     forward_authorship, forward_badges, forward_ban_poll, forward_callback, forward_context_ts,
     forward_jsonrpc, forward_jsonrpc_pseudo, forward_jsonrpc_pseudo_rate, forward_poll,
     forward_reaction, forward_reply, forward_reply_pseudo, forward_vote, forward_vote_count,
-    handle_get_all_contexts, handle_get_arbitrary_pred_proving_key,
+    handle_create_group, handle_get_all_contexts, handle_list_contexts, handle_list_posts, handle_get_anonymity, handle_get_arbitrary_pred_proving_key,
     handle_get_arbitrary_pred_proving_key2, handle_get_arbitrary_pred_proving_key3,
-    handle_get_callback_bulletin, handle_get_callback_nmemb_bulletin,
+    handle_get_reputation_proving_key, forward_reputation_proof,
+    handle_get_callback_bulletin, handle_get_callback_nmemb_bulletin, handle_get_group_roots,
+    handle_get_groups, handle_get_key_meta, handle_rotate_key,
     handle_get_membership_pubkey, handle_get_nonmembership_pubkey,
     handle_get_posts_scan, handle_get_posts_standard,
     handle_get_scan_proving_key, handle_get_standard_proving_key,
     handle_get_standard_pseudo_proving_key, handle_get_standard_pseudor_proving_key,
-    handle_get_user_bulletin, handle_get_user_pubkey, handle_post_context_and_store,
+    handle_get_user_bulletin, handle_get_user_checkpoint, handle_get_user_log_pubkey,
+    handle_get_user_pubkey, handle_post_context_and_store,
+    handle_get_job_status, handle_queue_scan, handle_queue_standard,
     handle_send_ban_request, handle_send_rep_request, handle_user_join, handle_verify_arb_pred,
     pseudonym,
 };
-use std::{fs::File, net::SocketAddr, sync::Arc};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
 use tokio::{signal, sync::RwLock};
 use tracing::{info, info_span};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 use zk_callbacks::{
-    generic::interaction::generate_keys_for_statement_in,
-    impls::{centralized::ds::sigstore::GRSchnorrObjStore, hash::Poseidon},
+    generic::{
+        anonymity::AnonymityLog,
+        bulletin::PublicUserBul,
+        interaction::{generate_keys_for_statement_in_cached, InteractionRegistry},
+        keystore::{KeyEncoding, KeyMeta, KeyStore},
+    },
+    impls::{
+        centralized::{
+            ds::{
+                sig::{gr_schnorr::GrumpkinSchnorr, Signature},
+                sigstore::GRSchnorrObjStore,
+            },
+            join::RateLimitedPolicy,
+        },
+        hash::Poseidon,
+    },
 };
 
 #[derive(CanonicalDeserialize, CanonicalSerialize)]
@@ -52,48 +100,315 @@ pub struct ServerKeys {
     pub authorship_pred_verifying_key: VK,
     pub badge_pred_proving_key: PK,
     pub badge_pred_verifying_key: VK,
+    pub reputation_pred_proving_key: PK,
+    pub reputation_pred_verifying_key: VK,
     pub standard_pseudo_proving_key: PK,
     pub standard_pseudo_verifying_key: VK,
     pub standard_pseudor_proving_key: PK,
     pub standard_pseudor_verifying_key: VK,
 }
 
-pub struct ServerState {
-    pub db: Store,
+/// The group requests are made in when they do not name one.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// The SNARK keys of a single group.
+///
+/// The circuits fix the public keys of a group's bulletins, so every group has its own keys.
+pub struct GroupState {
     pub keys: ServerKeys,
+    pub interactions: InteractionRegistry<VK>,
+    /// Verifying keys replaced by [`ServerState::rotate_key`], still accepted until they expire.
+    pub retired: Vec<RetiredKey>,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let keyfile_path = std::env::var("SERVER_KEYFILE").unwrap_or("server/keyfile.bin".to_string());
-    let log_level = std::env::var("SERVER_LOG").unwrap_or("info".to_string());
+/// A verifying key which was rotated out, accepted until `until` so clients holding the old
+/// proving key have time to fetch the new one.
+#[derive(Clone)]
+pub struct RetiredKey {
+    pub id: u64,
+    pub verifying_key: VK,
+    pub meta: Option<KeyMeta>,
+    pub until: SystemTime,
+}
 
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::new(format!("server={}", log_level)))
-        .init();
+/// How long a rotated key is still accepted, unless the rotation asks otherwise.
+pub const DEFAULT_KEY_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
 
-    let mut rng = rand::thread_rng();
+impl GroupState {
+    /// The verifying keys accepted for an interaction: the current key, followed by any retired
+    /// keys still within their grace period.
+    pub fn verifying_keys(&self, id: u64) -> Vec<VK> {
+        let now = SystemTime::now();
+        self.interactions
+            .verifying_key(id)
+            .into_iter()
+            .chain(
+                self.retired
+                    .iter()
+                    .filter(|k| k.id == id && k.until > now)
+                    .map(|k| &k.verifying_key),
+            )
+            .cloned()
+            .collect()
+    }
 
-    // Database Creation
-    let span = info_span!("db_generation").entered();
-    info!("Creating database...");
-    let db = Store::new(&mut rng);
-    info!("Created!");
-    span.exit();
+    /// The retired keys of interaction `id` whose grace period has ended.
+    pub fn stale_keys(&self, id: u64) -> impl Iterator<Item = &VK> {
+        let now = SystemTime::now();
+        self.retired
+            .iter()
+            .filter(move |k| k.id == id && k.until <= now)
+            .map(|k| &k.verifying_key)
+    }
+}
 
-    // Snark Key Generation
-    let span = info_span!("snark_key_generation").entered();
+/// The bulletins of a single group, and the history of its anonymity set.
+pub struct Bulletins {
+    pub store: Store,
+    pub anonymity: AnonymityLog<F>,
+}
+
+impl Bulletins {
+    fn new(store: Store) -> Self {
+        Self {
+            store,
+            anonymity: AnonymityLog::new(),
+        }
+    }
+
+    /// Write the changes to the bulletins to the database.
+    ///
+    /// Failures are logged rather than returned, as the change has already been made in memory.
+    pub fn persist(&self, group: &str) {
+        if let Err(e) = persist::sync_group(group, &self.store) {
+            tracing::error!("Failed to persist group {}: {}", group, e);
+        }
+    }
+
+    /// Record the current anonymity set size of the user bulletin in the current epoch.
+    pub fn record_anonymity(&mut self) {
+        if let Some(size) =
+            <GRSchnorrObjStore as PublicUserBul<F, MsgUser>>::anonymity_set_size(&self.store.obj_bul)
+        {
+            let epoch = self.store.callback_bul.get_epoch();
+            self.anonymity.record(epoch, size);
+        }
+    }
+}
+
+/// A group hosted by the server.
+///
+/// The keys of a group are only read once generated, so they are shared behind an `Arc` and
+/// replaced as a whole when a key is rotated; fetching keys never waits on a verification. The
+/// bulletins have a lock of their own, so requests in different groups never wait on each other.
+#[derive(Clone)]
+pub struct Group {
+    pub state: Arc<GroupState>,
+    pub bulletins: Arc<RwLock<Bulletins>>,
+}
+
+impl Group {
+    fn new(keys: GroupState, bulletins: Bulletins) -> Self {
+        Self {
+            state: Arc::new(keys),
+            bulletins: Arc::new(RwLock::new(bulletins)),
+        }
+    }
+}
+
+pub struct ServerState {
+    pub config: Config,
+    /// Every group, each with their own bulletins and keys. This lock is only held to look up,
+    /// add or replace a group, never across a request.
+    groups: std::sync::RwLock<BTreeMap<String, Group>>,
+    pub key_store: KeyStore,
+    pub join_policy: Mutex<RateLimitedPolicy<()>>,
+    /// Signs checkpoints of the user bulletin, so clients can check it only ever grows.
+    pub log_key: <GrumpkinSchnorr as Signature<F>>::Privkey,
+    /// Updates streamed to subscribers of `/api/subscribe`.
+    pub events: Events,
+    /// The bot messages are sent as.
+    pub bot: Bot,
+}
+
+impl ServerState {
+    /// Construct a server with no groups.
+    pub fn new(
+        config: Config,
+        key_store: KeyStore,
+        join_policy: RateLimitedPolicy<()>,
+        log_key: <GrumpkinSchnorr as Signature<F>>::Privkey,
+    ) -> Self {
+        Self {
+            bot: Bot::new(config.signal.clone()),
+            config,
+            groups: std::sync::RwLock::new(BTreeMap::new()),
+            key_store,
+            join_policy: Mutex::new(join_policy),
+            log_key,
+            events: Events::new(),
+        }
+    }
+
+    /// Get a group, if it exists.
+    pub fn group(&self, group: &str) -> Option<Group> {
+        self.groups.read().unwrap().get(group).cloned()
+    }
+
+    /// The identifiers of all groups, in order.
+    pub fn groups(&self) -> Vec<String> {
+        self.groups.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Create a group with fresh bulletins and keys. Does nothing if the group already exists.
+    ///
+    /// This generates (or loads) the SNARK keys of the group, so may take a while. Fails if the
+    /// group is not in the allowlist of the configuration.
+    pub fn create_group(&self, group: &str, rng: &mut (impl CryptoRng + RngCore)) -> Result<()> {
+        if self.group(group).is_some() {
+            return Ok(());
+        }
+        if !self.config.allows_group(group) {
+            anyhow::bail!("group {} is not allowed", group);
+        }
+        let keys = persist::GroupKeys::generate(rng);
+        let db = keys.store(rng)?;
+        let state = generate_group_keys(
+            &self.key_store,
+            group,
+            &db,
+            self.config.authorship_pseudonyms,
+            rng,
+        )?;
+
+        // Another request may have created the group while the keys were generated
+        let mut groups = self.groups.write().unwrap();
+        if groups.contains_key(group) {
+            return Ok(());
+        }
+        persist::save_group(group, &keys, &db)?;
+        groups.insert(group.to_string(), Group::new(state, Bulletins::new(db)));
+        info!("Created group {}", group);
+        Ok(())
+    }
+
+    /// Restore the groups stored in the database, regenerating (or loading) their SNARK keys.
+    pub fn restore_groups(&self, rng: &mut (impl CryptoRng + RngCore)) -> Result<()> {
+        let mut stores = persist::load_groups()?;
+        let names: Vec<String> = stores.groups().map(String::from).collect();
+        for group in names {
+            let db = stores.remove(&group).unwrap();
+            let state = generate_group_keys(
+                &self.key_store,
+                &group,
+                &db,
+                self.config.authorship_pseudonyms,
+                rng,
+            )?;
+            let mut bulletins = Bulletins::new(db);
+            bulletins.record_anonymity();
+            self.groups
+                .write()
+                .unwrap()
+                .insert(group.clone(), Group::new(state, bulletins));
+            info!("Restored group {}", group);
+        }
+        Ok(())
+    }
+
+    /// Replace the SNARK keys of an interaction in a group with freshly generated keys.
+    ///
+    /// Proofs made with the old keys are still accepted for `grace`, after which only the new keys
+    /// verify. The new keys are stored in the key store, so they are kept across restarts, but
+    /// the retired keys are only held in memory, so a restart ends their grace period.
+    pub async fn rotate_key(
+        &self,
+        group: &str,
+        name: &str,
+        grace: Duration,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<()> {
+        let old = self.group(group).context("unknown group")?;
+        let id = old.state.interactions.id(name).context("unknown interaction")?;
+        let verifying_key = old
+            .state
+            .interactions
+            .verifying_key(id)
+            .context("interaction has no keys")?
+            .clone();
+
+        let key_id = group_key_id(group, name);
+        let meta = self.key_store.meta(&key_id)?;
+        self.key_store.remove(&key_id)?;
+        let mut state = {
+            let bulletins = old.bulletins.read().await;
+            generate_group_keys(
+                &self.key_store,
+                group,
+                &bulletins.store,
+                self.config.authorship_pseudonyms,
+                rng,
+            )?
+        };
+
+        let now = SystemTime::now();
+        state.retired = old
+            .state
+            .retired
+            .iter()
+            .filter(|k| k.until > now)
+            .cloned()
+            .collect();
+        state.retired.push(RetiredKey {
+            id,
+            verifying_key,
+            meta,
+            until: now + grace,
+        });
+        if let Some(current) = self.groups.write().unwrap().get_mut(group) {
+            current.state = Arc::new(state);
+        }
+        info!("Rotated key {} of group {}", name, group);
+        Ok(())
+    }
+}
+
+/// The id the keys of an interaction are stored under in the key store for a group.
+///
+/// The default group keeps the plain interaction names, so existing key directories stay valid.
+/// Other groups are suffixed with a hash of the group id, as group ids need not be valid file
+/// names.
+fn group_key_id(group: &str, name: &str) -> String {
+    if group == DEFAULT_GROUP {
+        name.to_string()
+    } else {
+        format!("{}-{}", name, hex::encode(&Sha256::digest(group.as_bytes())[..8]))
+    }
+}
+
+/// Generate (or load from the key store) the SNARK keys for a group's bulletins, with
+/// authorship proofs linking `authorship_pseudonyms` pseudonyms.
+fn generate_group_keys(
+    key_store: &KeyStore,
+    group: &str,
+    db: &Store,
+    authorship_pseudonyms: usize,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<GroupState> {
+    let mut interactions = catalog::interaction_registry::<VK>();
+    let key_id = |id: u64| group_key_id(group, interactions.name(id).unwrap());
 
     // Standard interaction keys
     let standard_interaction = get_standard_interaction();
     let (standard_proving_key, standard_verifying_key) = standard_interaction
-        .generate_keys::<H, Snark, Cr, OStore>(
-            &mut rng,
+        .generate_keys_cached::<H, Snark, Cr, OStore>(
+            key_store,
+            &key_id(catalog::STANDARD),
+            rng,
             Some(db.obj_bul.get_pubkey()),
             None,
             false,
-        );
+        )?;
 
     let context = F::from(1234);
     let claimed = F::from(5678);
@@ -102,34 +417,40 @@ async fn main() -> Result<()> {
 
     let standard_pseudo_interaction = get_standard_pseudo_interaction();
     let (standard_pseudo_proving_key, standard_pseudo_verifying_key) = standard_pseudo_interaction
-        .generate_keys::<H, Snark, Cr, OStore>(
-        &mut rng,
+        .generate_keys_cached::<H, Snark, Cr, OStore>(
+        key_store,
+        &key_id(catalog::STANDARD_PSEUDO),
+        rng,
         Some(db.obj_bul.get_pubkey()),
         Some(pseudo.clone()),
         false,
-    );
+    )?;
 
     let i = F::from(1);
     let pseudor = PseudonymArgsRate {
         context,
         claimed,
-        i,
+        index: i,
     };
 
     let standard_pseudo_rate_interaction = get_standard_pseudo_rate_interaction();
     let (standard_pseudor_proving_key, standard_pseudor_verifying_key) =
-        standard_pseudo_rate_interaction.generate_keys::<H, Snark, Cr, OStore>(
-            &mut rng,
+        standard_pseudo_rate_interaction.generate_keys_cached::<H, Snark, Cr, OStore>(
+            key_store,
+            &key_id(catalog::STANDARD_PSEUDO_RATE),
+            rng,
             Some(db.obj_bul.get_pubkey()),
             Some(pseudor.clone()),
             false,
-        );
+        )?;
 
     // Scan interaction keys
     let scan_interaction = get_scan_interaction();
     let (scan_proving_key, scan_verifying_key) = scan_interaction
-        .generate_keys::<H, Snark, Cr, OStore>(
-            &mut rng,
+        .generate_keys_cached::<H, Snark, Cr, OStore>(
+            key_store,
+            &key_id(catalog::SCAN),
+            rng,
             Some(db.obj_bul.get_pubkey()),
             Some(get_extra_pubdata_for_scan(
                 &db.callback_bul,
@@ -138,9 +459,9 @@ async fn main() -> Result<()> {
                 F::from(0),
             )),
             true,
-        );
+        )?;
 
-    let (pseudonym_pred_proving_key, pseudonym_pred_verifying_key) = generate_keys_for_statement_in::<
+    let (pseudonym_pred_proving_key, pseudonym_pred_verifying_key) = generate_keys_for_statement_in_cached::<
         F,
         Poseidon<2>,
         MsgUser,
@@ -151,49 +472,41 @@ async fn main() -> Result<()> {
         Groth16<E>,
         GRSchnorrObjStore,
     >(
-        &mut rng,
+        key_store,
+        &key_id(catalog::PSEUDONYM_PRED),
+        rng,
         pseudonym_pred,
         Some(db.obj_bul.get_pubkey()),
         Some(pseudo.clone()),
-    );
-
-    let context2 = F::from(9012);
-    let claimed2 = F::from(3456);
-
-    let pseudo2 = PseudonymArgs {
-        context: context2,
-        claimed: claimed2,
-    };
-
-    let pair = PseudonymArgsPair {
-        a: pseudo,
-        b: pseudo2,
-    };
+    )?;
 
+    // The key store regenerates the keys if the number of pseudonyms changed, as the circuit does
     let (authorship_pred_proving_key, authorship_pred_verifying_key) =
-        generate_keys_for_statement_in::<
+        with_authors!(authorship_pseudonyms, N => generate_keys_for_statement_in_cached::<
             F,
             Poseidon<2>,
             MsgUser,
-            PseudonymArgsPair<F>,
-            PseudonymArgsPairVar<F>,
+            SameAuthorArgs<F, N>,
+            SameAuthorArgsVar<F, N>,
             (),
             (),
             Groth16<E>,
             GRSchnorrObjStore,
         >(
-            &mut rng,
-            authorship_pred,
+            key_store,
+            &key_id(catalog::AUTHORSHIP_PRED),
+            rng,
+            authorship_pred::<N>,
             Some(db.obj_bul.get_pubkey()),
-            Some(pair),
-        );
+            Some(SameAuthorArgs { pseudonyms: [pseudo; N] }),
+        )?);
 
     let badge_var = BadgesArgs {
         i: F::from(1),
         claimed: F::from(0),
     };
 
-    let (badge_pred_proving_key, badge_pred_verifying_key) = generate_keys_for_statement_in::<
+    let (badge_pred_proving_key, badge_pred_verifying_key) = generate_keys_for_statement_in_cached::<
         F,
         Poseidon<2>,
         MsgUser,
@@ -204,11 +517,33 @@ async fn main() -> Result<()> {
         Groth16<E>,
         GRSchnorrObjStore,
     >(
-        &mut rng,
+        key_store,
+        &key_id(catalog::BADGE_PRED),
+        rng,
         badge_pred,
         Some(db.obj_bul.get_pubkey()),
         Some(badge_var),
-    );
+    )?;
+
+    let (reputation_pred_proving_key, reputation_pred_verifying_key) =
+        generate_keys_for_statement_in_cached::<
+            F,
+            Poseidon<2>,
+            MsgUser,
+            RepArgs<F>,
+            RepArgsVar<F>,
+            (),
+            (),
+            Groth16<E>,
+            GRSchnorrObjStore,
+        >(
+            key_store,
+            &key_id(catalog::REPUTATION_PRED),
+            rng,
+            reputation_pred,
+            Some(db.obj_bul.get_pubkey()),
+            None,
+        )?;
 
     let keys = ServerKeys {
         standard_proving_key,
@@ -221,25 +556,148 @@ async fn main() -> Result<()> {
         authorship_pred_verifying_key,
         badge_pred_proving_key,
         badge_pred_verifying_key,
+        reputation_pred_proving_key,
+        reputation_pred_verifying_key,
         standard_pseudo_proving_key,
         standard_pseudo_verifying_key,
         standard_pseudor_proving_key,
         standard_pseudor_verifying_key,
     };
 
-    info!("Writing keys to file...");
-    {
-        let mut keyfile = File::create(&keyfile_path)?; // <- create new file
-        keys.serialize_with_mode(&mut keyfile, Compress::No)?;
+    for (id, vk) in [
+        (catalog::STANDARD, &keys.standard_verifying_key),
+        (catalog::STANDARD_PSEUDO, &keys.standard_pseudo_verifying_key),
+        (catalog::STANDARD_PSEUDO_RATE, &keys.standard_pseudor_verifying_key),
+        (catalog::SCAN, &keys.scan_verifying_key),
+        (catalog::PSEUDONYM_PRED, &keys.pseudonym_pred_verifying_key),
+        (catalog::AUTHORSHIP_PRED, &keys.authorship_pred_verifying_key),
+        (catalog::BADGE_PRED, &keys.badge_pred_verifying_key),
+        (catalog::REPUTATION_PRED, &keys.reputation_pred_verifying_key),
+    ] {
+        interactions.set_verifying_key(id, vk.clone())?;
     }
-    let keyfile = File::open(&keyfile_path)?;
-    let keys = ServerKeys::deserialize_with_mode(keyfile, Compress::No, Validate::No)?;
 
+
+    Ok(GroupState {
+        keys,
+        interactions,
+        retired: vec![],
+    })
+}
+
+/// The wispy server.
+#[derive(Parser)]
+struct Cli {
+    /// Configuration file (defaults to server/config.toml, if it exists)
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+
+    let log_file = match &config.log_file {
+        Some(path) => Some(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open log file {}", path.display()))?,
+        ),
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(log_file.map(|f| fmt::layer().with_ansi(false).with_writer(Mutex::new(f))))
+        .with(EnvFilter::new(format!("server={}", config.log_level)))
+        .init();
+    set_timings_dir(&config.timings_dir);
+
+    let mut rng = rand::thread_rng();
+
+    // Database Opening
+    let span = info_span!("db_generation").entered();
+    info!("Opening database at {}...", config.db.display());
+    persist::open(&config.db)?;
+    dm::open(&config.dm_key, &mut rng)?;
+    helpers::index_thread_contexts()?;
+    info!("Opened!");
+    span.exit();
+
+    let join_policy =
+        RateLimitedPolicy::new((), config.joins_per_minute, Duration::from_secs(60));
+
+    let server = ServerState::new(
+        config.clone(),
+        KeyStore::new(&config.keydir)?.with_encoding(KeyEncoding::Zstd(3)),
+        join_policy,
+        persist::load_log_key(&mut rng)?,
+    );
+
+    // Group Restoration and Snark Key Generation (loaded from the key store if the circuits are
+    // unchanged)
+    let span = info_span!("snark_key_generation").entered();
+    server.restore_groups(&mut rng)?;
+    server.create_group(DEFAULT_GROUP, &mut rng)?;
     info!("Completed!");
     span.exit();
 
     // Application Start
-    let state = Arc::new(RwLock::new(ServerState { db, keys }));
+    let state = Arc::new(server);
+
+    let rpc_addr = config.rpc_listen;
+    let rpc_service = wispy_rpc::BulletinServer::new(rpc::BulletinService {
+        state: state.clone(),
+    });
+    tokio::spawn(async move {
+        info!("gRPC listening on {}", rpc_addr);
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(rpc_service)
+            .serve(rpc_addr)
+            .await
+        {
+            tracing::error!("gRPC server failed: {}", e);
+        }
+    });
+
+    // Tallying of polls past their deadline
+    polls::spawn(state.clone());
+    decay::spawn(state.clone());
+
+    // Reactions and replies sent from the Signal app
+    if config.signal.receive {
+        listener::spawn(state.clone());
+    }
+
+    // Background verification workers
+    let workers = config.workers.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let jobs = JobQueue::start(state.clone(), workers, config.job_queue);
+    info!("Started {} verification workers", workers);
+
+    // API keys of moderators and operators, and the limit on proof submissions
+    let mut api_keys = ApiKeys::load(&config.api_keys)?;
+    if let Ok(token) = std::env::var("SERVER_ADMIN_TOKEN") {
+        if !token.is_empty() {
+            api_keys.insert("admin", &token, Scope::ALL);
+        }
+    }
+    if api_keys.is_empty() {
+        info!(
+            "No API keys in {}, moderation and admin routes will reject every request",
+            config.api_keys.display()
+        );
+    }
+    let api_keys = Arc::new(api_keys);
+    let proof_limit = Arc::new(IpRateLimit::new(
+        config.proofs_per_minute,
+        Duration::from_secs(60),
+    ));
 
     let span = info_span!("start_application").entered();
     info!("Starting application...");
@@ -253,71 +711,103 @@ async fn main() -> Result<()> {
         .route("/api/user/arbitrary_pred_proving_key", get(handle_get_arbitrary_pred_proving_key))
         .route("/api/user/arbitrary_pred_proving_key2", get(handle_get_arbitrary_pred_proving_key2))
         .route("/api/user/arbitrary_pred_proving_key3", get(handle_get_arbitrary_pred_proving_key3))
+        .route("/api/user/reputation_proving_key", get(handle_get_reputation_proving_key))
         .route("/api/user/pubkey", get(handle_get_user_pubkey))
         .route("/api/user/bulletin", get(handle_get_user_bulletin))
+        .route("/api/user/checkpoint", get(handle_get_user_checkpoint))
+        .route("/api/user/log_pubkey", get(handle_get_user_log_pubkey))
         .route("/api/user/join", post(handle_user_join))
+        .route("/api/user/anonymity", get(handle_get_anonymity))
+
+        .route("/api/groups", get(handle_get_groups))
+        .route("/api/badges/criteria", get(badges::handle_badge_criteria))
+        .route("/api/group/roots", get(handle_get_group_roots))
+
+        .route("/api/keys/meta", get(handle_get_key_meta))
 
         .route("/api/callbacks/membership_pubkey", get(handle_get_membership_pubkey))
         .route("/api/callbacks/nonmembership_pubkey", get(handle_get_nonmembership_pubkey))
         .route("/api/callbacks/bulletin", get(handle_get_callback_bulletin))
         .route("/api/callbacks/nmemb_bulletin", get(handle_get_callback_nmemb_bulletin))
 
-        .route("/api/interact/standard", post(handle_get_posts_standard))
-        .route("/api/interact/scan", post(handle_get_posts_scan))
-        .route("/api/interact/arbitrary_pred", post(handle_verify_arb_pred))
+        .route("/api/status/{id}", get(handle_get_job_status))
+        .route("/api/subscribe", get(handle_subscribe))
 
-        .route("/api/jsonrpc", post(forward_jsonrpc))
-        .route("/api/jsonrpc/pseudo", post(forward_jsonrpc_pseudo))
-        .route("/api/jsonrpc/pseudo/rate", post(forward_jsonrpc_pseudo_rate))
-
-        .route("/api/ban", post(handle_send_ban_request))
         .route("/api/pseudonym", get(pseudonym))
-        .route("/api/reputation", post(handle_send_rep_request))
-
         .route("/api/react", post(forward_reaction))
-        .route("/api/reply", post(forward_reply))
-        .route("/api/reply/pseudo", post(forward_reply_pseudo))
 
         .route("/api/poll", post(forward_poll))
         .route("/api/banpoll", post(forward_ban_poll))
-        .route("/api/vote", post(forward_vote))
         .route("/api/votecount", post(forward_vote_count))
 
-        .route("/api/authorship", post(forward_authorship))
         .route("/api/context", post(forward_context_ts))
-        .route("/api/badges", post(forward_badges))
         .route("/api/cb", post(forward_callback))
 
         .route("/api/pseudo/new_thread_context", post(handle_post_context_and_store))
         .route("/api/pseudo/get_all_contexts", get(handle_get_all_contexts))
+        .route("/api/contexts", get(handle_list_contexts))
+        .route("/api/posts", get(handle_list_posts));
+
+    // Proof submissions, limited per client IP
+    let proofs = Router::new()
+        .route("/api/interact/standard", post(handle_get_posts_standard))
+        .route("/api/interact/scan", post(handle_get_posts_scan))
+        .route("/api/interact/arbitrary_pred", post(handle_verify_arb_pred))
+        .route("/api/jobs/standard", post(handle_queue_standard))
+        .route("/api/jobs/scan", post(handle_queue_scan))
+        .route(
+            "/api/jsonrpc",
+            post(forward_jsonrpc).layer(DefaultBodyLimit::max(config.max_post_bytes)),
+        )
+        .route("/api/jsonrpc/pseudo", post(forward_jsonrpc_pseudo))
+        .route("/api/jsonrpc/pseudo/rate", post(forward_jsonrpc_pseudo_rate))
+        .route("/api/reply", post(forward_reply))
+        .route("/api/reply/pseudo", post(forward_reply_pseudo))
+        .route("/api/vote", post(forward_vote))
+        .route("/api/authorship", post(forward_authorship))
+        .route("/api/badges", post(forward_badges))
+        .route("/api/reputation/proof", post(forward_reputation_proof))
+        .route("/api/dm/nonce", post(dm::handle_dm_nonce))
+        .route("/api/dm/register", post(dm::handle_dm_register))
+        .route("/api/dm", post(dm::forward_dm))
+        .route_layer(middleware::from_fn_with_state(proof_limit, auth::rate_limit));
+
+    // Routes which require an API key with the matching scope
+    let moderation = Router::new()
+        .route("/api/ban", post(handle_send_ban_request))
+        .route("/api/reputation", post(handle_send_rep_request))
+        .route_layer(middleware::from_fn_with_state(api_keys.guard(Scope::Moderate), auth::require));
+    let key_management = Router::new()
+        .route("/api/keys/rotate", post(handle_rotate_key))
+        .route_layer(middleware::from_fn_with_state(api_keys.guard(Scope::Keys), auth::require));
+    let group_management = Router::new()
+        .route("/api/group", post(handle_create_group))
+        .route_layer(middleware::from_fn_with_state(api_keys.guard(Scope::Groups), auth::require));
+
+    let app = app
+        .merge(proofs)
+        .merge(moderation)
+        .merge(key_management)
+        .merge(group_management)
+        .nest("/api/admin", admin::router(api_keys.guard(Scope::Admin)))
+        .layer(Extension(jobs))
         .with_state(state);
 
     span.exit();
 
 
     let span = info_span!("web_server").entered();
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app)
+    let listener = tokio::net::TcpListener::bind(&config.listen).await?;
+    info!("Listening on {}", config.listen);
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(async {
             signal::ctrl_c()
                 .await
                 .expect("failed to listen for shutdown signal");
             println!("Shutting down server...");
 
-            // Cleanup zkpair_log.jsonl
-            if let Err(e) = std::fs::remove_file("server/zkpair_log.jsonl") {
-                eprintln!("Failed to delete zkpair_log.jsonl: {}", e);
-            }
-            if let Err(e) = std::fs::remove_file("server/poll_log.jsonl") {
-                eprintln!("Failed to delete poll_log.jsonl: {}", e);
-            }
-
-            // Cleanup SNARK key file
-            let keyfile_path =
-                std::env::var("SERVER_KEYFILE").unwrap_or("server/keyfile.bin".to_string());
-            if let Err(e) = std::fs::remove_file(&keyfile_path) {
-                eprintln!("Failed to delete keyfile {}: {}", keyfile_path, e);
+            if let Err(e) = persist::flush() {
+                eprintln!("Failed to flush database: {}", e);
             }
         })
         .await
@@ -327,3 +817,221 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Servers for the handler tests, over the temporary database and with stand-in keys.
+///
+/// Proving with the keys of the real circuits takes minutes, so every interaction of a test
+/// group is verified under the keys of a circuit which only takes public inputs: a proof verifies
+/// for the inputs it was made for and no others, which is all the handlers check.
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+    use ark_serialize::Compress;
+    use ark_snark::SNARK;
+    use rand::thread_rng;
+    use serde_json::{json, Value};
+    use server::ServerLock;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use zk_callbacks::{
+        generic::{callbacks::CallbackCom, wire::write_envelope},
+        impls::centralized::crypto::PlainTikCrypto,
+    };
+
+    /// A circuit with public inputs and no constraints.
+    struct Inputs(Vec<F>);
+
+    impl ConstraintSynthesizer<F> for Inputs {
+        fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+            for x in self.0 {
+                let _ = FpVar::new_input(cs.clone(), || Ok(x))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Keys for proofs with `n` public inputs.
+    pub fn keys(n: usize) -> (PK, VK) {
+        Groth16::<E>::circuit_specific_setup(Inputs(vec![F::from(0); n]), &mut thread_rng())
+            .unwrap()
+    }
+
+    /// A proof for `inputs`.
+    pub fn prove(pk: &PK, inputs: &[F]) -> <Snark as SNARK<F>>::Proof {
+        Groth16::<E>::prove(pk, Inputs(inputs.to_vec()), &mut thread_rng()).unwrap()
+    }
+
+    /// A proof for `inputs`, followed by the inputs, as clients send predicate proofs.
+    pub fn proof(pk: &PK, inputs: &[F]) -> Vec<u8> {
+        let proof = prove(pk, inputs);
+        let mut payload = vec![];
+        write_envelope(&proof, &mut payload, None, Compress::No).unwrap();
+        inputs
+            .to_vec()
+            .serialize_with_mode(&mut payload, Compress::No)
+            .unwrap();
+        payload
+    }
+
+    /// A callback ticket told apart by `seed`, hex encoded as messages store them.
+    pub fn ticket(seed: u64) -> String {
+        let mut ticket = vec![];
+        let mut com = CallbackCom::<F, F, PlainTikCrypto<F>>::default();
+        com.cb_entry.tik = PlainTikCrypto::new(F::from(seed));
+        com.serialize_with_mode(&mut ticket, Compress::No).unwrap();
+        hex::encode(ticket)
+    }
+
+    /// Verify the interaction `id` of `group` under `vk`.
+    pub fn set_key(state: &ServerState, group: &str, id: u64, vk: VK) {
+        let mut groups = state.groups.write().unwrap();
+        let group = groups.get_mut(group).unwrap();
+        let keys = Arc::get_mut(&mut group.state).unwrap();
+        keys.interactions.set_verifying_key(id, vk).unwrap();
+    }
+
+    /// The requests a fake signal-cli daemon received.
+    pub type Requests = Arc<std::sync::Mutex<Vec<Value>>>;
+
+    /// Start a fake signal-cli daemon, which answers each JSON-RPC request with the result or
+    /// error message `answer` gives for it, or drops the connection when it gives none. Returns
+    /// the address to set as `signal.tcp`.
+    pub async fn signal_daemon(
+        answer: impl Fn(&Value) -> Option<Result<Value, String>> + Send + Sync + 'static,
+    ) -> (String, Requests) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let requests = Requests::default();
+        let answer = Arc::new(answer);
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (answer, seen) = (answer.clone(), seen.clone());
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let request: Value = serde_json::from_str(&line).unwrap();
+                        seen.lock().unwrap().push(request.clone());
+                        let id = &request["id"];
+                        let reply = match answer(&request) {
+                            Some(Ok(result)) => {
+                                json!({ "jsonrpc": "2.0", "id": id, "result": result })
+                            }
+                            Some(Err(message)) => json!({
+                                "jsonrpc": "2.0",
+                                "id": id,
+                                "error": { "code": -1, "message": message },
+                            }),
+                            None => return,
+                        };
+                        let reply = format!("{}\n", reply);
+                        if write.write_all(reply.as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        (addr, requests)
+    }
+
+    /// A server with no groups over the temporary database, with its key store and timings in
+    /// `dir`.
+    pub fn server(config: Config, dir: &str) -> ServerLock {
+        persist::open_temp();
+        let dir = std::env::temp_dir().join(format!("wispy-{}-{}", dir, std::process::id()));
+        client::helpers::set_timings_dir(dir.join("timings"));
+        let join_policy =
+            RateLimitedPolicy::new((), config.joins_per_minute, Duration::from_secs(60));
+        Arc::new(ServerState::new(
+            config,
+            KeyStore::new(dir.join("keys")).unwrap(),
+            join_policy,
+            GrumpkinSchnorr::gen_key(&mut thread_rng()),
+        ))
+    }
+
+    /// Add the group `group`, whose predicate proofs have two public inputs and whose authorship
+    /// proofs link `authors` pseudonyms. Returns the proving keys of the predicates and of
+    /// authorship.
+    pub fn add_group(state: &ServerState, group: &str, authors: usize) -> (PK, PK) {
+        let mut rng = thread_rng();
+        let (pk, vk) = keys(2);
+        let (author_pk, author_vk) = keys(2 * authors);
+        let mut interactions = catalog::interaction_registry::<VK>();
+        for (id, _) in catalog::interaction_registry::<VK>().iter() {
+            let vk = if id == catalog::AUTHORSHIP_PRED { &author_vk } else { &vk };
+            interactions.set_verifying_key(id, vk.clone()).unwrap();
+        }
+        let keys = ServerKeys {
+            standard_proving_key: pk.clone(),
+            standard_verifying_key: vk.clone(),
+            scan_proving_key: pk.clone(),
+            scan_verifying_key: vk.clone(),
+            pseudonym_pred_proving_key: pk.clone(),
+            pseudonym_pred_verifying_key: vk.clone(),
+            authorship_pred_proving_key: author_pk.clone(),
+            authorship_pred_verifying_key: author_vk,
+            badge_pred_proving_key: pk.clone(),
+            badge_pred_verifying_key: vk.clone(),
+            reputation_pred_proving_key: pk.clone(),
+            reputation_pred_verifying_key: vk.clone(),
+            standard_pseudo_proving_key: pk.clone(),
+            standard_pseudo_verifying_key: vk.clone(),
+            standard_pseudor_proving_key: pk.clone(),
+            standard_pseudor_verifying_key: vk,
+        };
+        let store = persist::GroupKeys::generate(&mut rng).store(&mut rng).unwrap();
+        let state_keys = GroupState {
+            keys,
+            interactions,
+            retired: vec![],
+        };
+        state
+            .groups
+            .write()
+            .unwrap()
+            .insert(group.to_string(), Group::new(state_keys, Bulletins::new(store)));
+        (pk, author_pk)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Retired keys are accepted until their grace period ends, and are stale after
+    #[test]
+    fn retired_keys() {
+        let state = testing::server(Config::default(), "retired");
+        testing::add_group(&state, "retired", 2);
+        let (_, old) = testing::keys(2);
+        let (_, expired) = testing::keys(2);
+        {
+            let mut groups = state.groups.write().unwrap();
+            let group = groups.get_mut("retired").unwrap();
+            let keys = Arc::get_mut(&mut group.state).unwrap();
+            let now = SystemTime::now();
+            let retired = |verifying_key: &VK, until| RetiredKey {
+                id: catalog::STANDARD,
+                verifying_key: verifying_key.clone(),
+                meta: None,
+                until,
+            };
+            keys.retired = vec![
+                retired(&old, now + Duration::from_secs(60)),
+                retired(&expired, now - Duration::from_secs(1)),
+            ];
+        }
+
+        let keys = state.group("retired").unwrap().state;
+        let accepted = keys.verifying_keys(catalog::STANDARD);
+        assert_eq!(accepted.len(), 2);
+        assert_eq!(accepted[1], old);
+        assert_eq!(keys.stale_keys(catalog::STANDARD).collect::<Vec<_>>(), [&expired]);
+        assert_eq!(keys.verifying_keys(catalog::SCAN).len(), 1);
+        assert_eq!(keys.stale_keys(catalog::SCAN).count(), 0);
+    }
+}
@@ -0,0 +1,306 @@
+//! The moderation policy: what reactions to a bot message do.
+//!
+//! Each rule counts one kind of reaction on a message and acts whenever the count reaches a
+//! multiple of its `count`: it adds `reputation` to the reputation the callback of the message is
+//! called with, and with `ban_poll` opens a ban poll on the message (once per message). A removed
+//! reaction counts down, taking back the reputation of a multiple the count falls below.
+//!
+//! The policy is the `[moderation]` section of the server configuration, or the TOML or JSON file
+//! given as `moderation_file`. Without one, an upvote is worth 1 and a downvote -1. For example:
+//!
+//! ```toml
+//! [[moderation.rules]]
+//! reaction = "upvote"
+//! reputation = 1
+//!
+//! # 10 downvotes cost 3
+//! [[moderation.rules]]
+//! reaction = "downvote"
+//! count = 10
+//! reputation = -3
+//!
+//! # 5 × 🤬 open a ban poll
+//! [[moderation.rules]]
+//! reaction = "🤬"
+//! count = 5
+//! ban_poll = true
+//! ```
+
+use crate::badges::issue_earned;
+use crate::error::ApiError;
+use crate::helpers::{
+    claim_auto_ban_poll, count_reaction, release_auto_ban_poll, update_reaction_log,
+};
+use crate::server::{emoji_to_name, open_ban_poll, string_to_emoji};
+use crate::ServerState;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use tracing::{info, warn};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModerationPolicy {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// The reaction counted, as an emoji or its name (`upvote`, `downvote`, `hatespeech`, `ban`
+    /// or `not ban`).
+    pub reaction: String,
+    /// How many reactions it takes for the rule to act.
+    #[serde(default = "one")]
+    pub count: u32,
+    /// The reputation the message gains each time the rule acts.
+    #[serde(default)]
+    pub reputation: i32,
+    /// Whether to open a ban poll on the message the first time the rule acts.
+    #[serde(default)]
+    pub ban_poll: bool,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// What a change in the reactions to a message does.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub reputation: i32,
+    pub ban_poll: bool,
+}
+
+impl Default for ModerationPolicy {
+    fn default() -> Self {
+        let rule = |reaction: &str, reputation| Rule {
+            reaction: reaction.to_string(),
+            count: 1,
+            reputation,
+            ban_poll: false,
+        };
+        Self {
+            rules: vec![rule("upvote", 1), rule("downvote", -1)],
+        }
+    }
+}
+
+/// The name of a reaction given as an emoji or a name, `unknown` if it is neither.
+fn reaction_name(reaction: &str) -> &'static str {
+    emoji_to_name(string_to_emoji(reaction))
+}
+
+impl ModerationPolicy {
+    /// Read a policy from a TOML file, or a JSON file if its extension is `.json`.
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read moderation policy {}", path.display()))?;
+        let policy = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&file).map_err(anyhow::Error::from)
+        } else {
+            toml::from_str(&file).map_err(anyhow::Error::from)
+        };
+        policy.with_context(|| format!("invalid moderation policy {}", path.display()))
+    }
+
+    /// Check that every rule counts a known reaction at least once.
+    pub fn check(&self) -> Result<()> {
+        for rule in &self.rules {
+            if reaction_name(&rule.reaction) == "unknown" {
+                bail!("moderation rule for unknown reaction {:?}", rule.reaction);
+            }
+            if rule.count == 0 {
+                bail!("moderation rule for {:?} has a count of 0", rule.reaction);
+            }
+        }
+        Ok(())
+    }
+
+    /// What the count of `reaction` (by name) on a message going from `before` to `after` does.
+    pub fn evaluate(&self, reaction: &str, before: u32, after: u32) -> Outcome {
+        let mut outcome = Outcome::default();
+        let rules = self
+            .rules
+            .iter()
+            .filter(|r| reaction_name(&r.reaction) == reaction);
+        for rule in rules {
+            let crossed = (after / rule.count) as i32 - (before / rule.count) as i32;
+            outcome.reputation += crossed * rule.reputation;
+            outcome.ban_poll |= rule.ban_poll && crossed > 0;
+        }
+        outcome
+    }
+}
+
+/// Count `emoji` being added to the message sent at `timestamp` to the Signal group `group_id`,
+/// or removed from it when `added` is false, and apply what the policy makes of it, issuing any
+/// badge the message earns. A ban poll or badge is about the callback of the message in `group`;
+/// no ban poll is opened without `group_id`.
+pub(crate) async fn apply_reaction(
+    state: &ServerState,
+    group_id: Option<&str>,
+    group: &str,
+    timestamp: u64,
+    emoji: &str,
+    added: bool,
+) -> Result<(), ApiError> {
+    let name = emoji_to_name(emoji);
+    if name == "unknown" {
+        return Ok(());
+    }
+    let (before, after) = count_reaction(timestamp, name, added)?;
+    let outcome = state.config.moderation.evaluate(name, before, after);
+    issue_earned(state, group, timestamp, name, before, after).await?;
+
+    if outcome.reputation != 0 {
+        info!(
+            "[MODERATION] Reputation {:+} for message {}",
+            outcome.reputation, timestamp
+        );
+        update_reaction_log(timestamp, outcome.reputation)?;
+    }
+
+    if outcome.ban_poll {
+        let Some(group_id) = group_id else {
+            warn!(
+                "[MODERATION] Cannot open a ban poll on {}: its Signal group is unknown",
+                timestamp
+            );
+            return Ok(());
+        };
+        if !claim_auto_ban_poll(timestamp)? {
+            return Ok(());
+        }
+        info!(
+            "[MODERATION] {} × {} on {}, opening a ban poll",
+            after, emoji, timestamp
+        );
+        let message = format!("It got {} × {} reactions.", after, string_to_emoji(name));
+        let opened = open_ban_poll(
+            state,
+            group_id,
+            timestamp,
+            &message,
+            group.to_string(),
+            None,
+        )
+        .await;
+        if let Err(e) = opened {
+            // Let the next reaction try again
+            release_auto_ban_poll(timestamp)?;
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::helpers::is_open_poll;
+    use crate::testing;
+    use axum::http::StatusCode;
+
+    fn policy() -> ModerationPolicy {
+        toml::from_str(
+            r#"
+            rules = [
+                { reaction = "upvote", reputation = 1 },
+                { reaction = "👎", count = 3, reputation = -2 },
+                { reaction = "hatespeech", count = 2, ban_poll = true },
+            ]
+            "#,
+        )
+        .unwrap()
+    }
+
+    // Rules act each time the count reaches a multiple of theirs, and take back when it falls below
+    #[test]
+    fn evaluate_counts() {
+        let policy = policy();
+        policy.check().unwrap();
+        assert_eq!(policy.evaluate("upvote", 0, 1).reputation, 1);
+        assert_eq!(policy.evaluate("upvote", 1, 0).reputation, -1);
+        assert_eq!(policy.evaluate("downvote", 1, 2).reputation, 0);
+        assert_eq!(policy.evaluate("downvote", 2, 3).reputation, -2);
+        assert_eq!(policy.evaluate("downvote", 3, 2).reputation, 2);
+        assert!(!policy.evaluate("hatespeech", 0, 1).ban_poll);
+        assert!(policy.evaluate("hatespeech", 1, 2).ban_poll);
+        assert!(!policy.evaluate("hatespeech", 2, 1).ban_poll);
+        assert_eq!(policy.evaluate("unknown", 0, 1).reputation, 0);
+    }
+
+    // Rules must count a known reaction, at least once
+    #[test]
+    fn invalid_rules() {
+        let rule = |reaction: &str, count| Rule {
+            reaction: reaction.to_string(),
+            count,
+            reputation: 1,
+            ban_poll: false,
+        };
+        let policy = ModerationPolicy { rules: vec![rule("🦀", 1)] };
+        assert!(policy.check().is_err());
+        let policy = ModerationPolicy { rules: vec![rule("upvote", 0)] };
+        assert!(policy.check().is_err());
+    }
+
+    // Policies are read from TOML, or JSON by their extension
+    #[test]
+    fn read_policy() {
+        let dir = std::env::temp_dir().join(format!("wispy-moderation-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let json = dir.join("policy.json");
+        std::fs::write(&json, r#"{ "rules": [{ "reaction": "ban", "count": 4 }] }"#).unwrap();
+        let policy = ModerationPolicy::read(&json).unwrap();
+        assert_eq!(policy.rules[0].count, 4);
+
+        let toml = dir.join("policy.toml");
+        std::fs::write(&toml, "[[rules]]\nreaction = \"ban\"\nbogus = 1\n").unwrap();
+        assert!(ModerationPolicy::read(&toml).is_err());
+        assert!(ModerationPolicy::read(&dir.join("missing.toml")).is_err());
+    }
+
+    // A ban poll is opened once per message, and again when it could not be sent
+    #[tokio::test]
+    async fn ban_poll_once() {
+        let mut config = Config {
+            moderation: policy(),
+            ..Config::default()
+        };
+        config.signal.retries = 0;
+        config.signal.tcp = "127.0.0.1:1".to_string();
+        let offline = testing::server(config.clone(), "moderation-offline");
+        let (tcp, requests) =
+            testing::signal_daemon(|_| Some(Ok(serde_json::json!({ "timestamp": 4858100 })))).await;
+        config.signal.tcp = tcp;
+        let state = testing::server(config, "moderation");
+
+        async fn react(state: &ServerState) -> Result<(), ApiError> {
+            let hatespeech = string_to_emoji("hatespeech");
+            apply_reaction(state, Some("signal-group"), "mod", 4858001, hatespeech, true).await
+        }
+        react(&offline).await.unwrap();
+        let err = react(&offline).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+
+        react(&state).await.unwrap();
+        assert!(requests.lock().unwrap().is_empty());
+        react(&state).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert!(is_open_poll(4858100).unwrap());
+
+        react(&state).await.unwrap();
+        react(&state).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // Without its Signal group, no poll can be opened
+        let hatespeech = string_to_emoji("hatespeech");
+        for _ in 0..2 {
+            apply_reaction(&state, None, "mod", 4858002, hatespeech, true).await.unwrap();
+        }
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,352 @@
+//! Persistent server state.
+//!
+//! Everything the server needs to survive a restart lives in one sled database: the keys and
+//! bulletins of every group, the callbacks attached to sent messages, polls, and thread contexts.
+//! Bulletins are written through after every request which changes them, and rebuilt from the
+//! database on startup, so existing users keep their membership across restarts.
+
+use anyhow::{anyhow, Context, Result};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::{Args, GStore, Store, F};
+use rand::{CryptoRng, RngCore};
+use std::{collections::BTreeMap, path::Path, sync::OnceLock};
+use zk_callbacks::{
+    generic::object::{Com, Nul, Time},
+    impls::centralized::{
+        crypto::FakeSigPubkey,
+        ds::{
+            sig::{gr_schnorr::GrumpkinSchnorr, Signature},
+            sigrange::{SigRangeStore, SignedRange},
+            sigstore::{CallbackStore, CentralStore, SigObjStore},
+        },
+    },
+};
+
+pub type Privkey = <GrumpkinSchnorr as Signature<F>>::Privkey;
+type Sig = <GrumpkinSchnorr as Signature<F>>::Sig;
+
+static DB: OnceLock<sled::Db> = OnceLock::new();
+
+/// Open the database at `path`. This must be called once on startup, before the state is used.
+pub fn open(path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let db = sled::open(path)
+        .with_context(|| format!("failed to open database at {}", path.display()))?;
+    DB.set(db).map_err(|_| anyhow!("database opened twice"))
+}
+
+/// Open a tree of the database.
+pub fn tree(name: &str) -> Result<sled::Tree> {
+    Ok(DB.get().context("database is not open")?.open_tree(name)?)
+}
+
+/// Flush all trees to disk.
+pub fn flush() -> Result<()> {
+    DB.get().context("database is not open")?.flush()?;
+    Ok(())
+}
+
+/// Generate a fresh, increasing id.
+pub fn next_id() -> Result<u64> {
+    Ok(DB.get().context("database is not open")?.generate_id()?)
+}
+
+/// Every tree of the database, with keys and values hex encoded.
+pub fn snapshot() -> Result<BTreeMap<String, BTreeMap<String, String>>> {
+    let db = DB.get().context("database is not open")?;
+    db.flush()?;
+    let mut trees = BTreeMap::new();
+    for name in db.tree_names() {
+        let entries = db
+            .open_tree(&name)?
+            .iter()
+            .map(|e| {
+                let (key, value) = e?;
+                Ok((hex::encode(key), hex::encode(value)))
+            })
+            .collect::<Result<_>>()?;
+        trees.insert(String::from_utf8_lossy(&name).into_owned(), entries);
+    }
+    Ok(trees)
+}
+
+fn encode<T: CanonicalSerialize>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    value.serialize_with_mode(&mut bytes, Compress::No)?;
+    Ok(bytes)
+}
+
+fn decode<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T> {
+    Ok(T::deserialize_with_mode(
+        bytes,
+        Compress::No,
+        Validate::Yes,
+    )?)
+}
+
+fn load<T: CanonicalDeserialize>(tree: &sled::Tree) -> Result<Vec<T>> {
+    tree.iter().values().map(|v| decode(&v?)).collect()
+}
+
+/// Append the entries from `tree.len()` to `len`, rewriting the tree if it holds more entries
+/// than `len` (for example, after pruning).
+fn append_from<T: CanonicalSerialize>(
+    tree: &sled::Tree,
+    len: usize,
+    entry: impl Fn(usize) -> T,
+) -> Result<()> {
+    let mut from = tree.len();
+    if from > len {
+        tree.clear()?;
+        from = 0;
+    }
+    let mut batch = sled::Batch::default();
+    for i in from..len {
+        batch.insert(&(i as u64).to_be_bytes()[..], encode(&entry(i))?);
+    }
+    tree.apply_batch(batch)?;
+    Ok(())
+}
+
+/// The signing keys of a group's bulletins.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct GroupKeys {
+    pub obj: Privkey,
+    pub callback: Privkey,
+    pub nmemb: Privkey,
+}
+
+impl GroupKeys {
+    /// Generate fresh keys.
+    pub fn generate(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        Self {
+            obj: GrumpkinSchnorr::gen_key(rng),
+            callback: GrumpkinSchnorr::gen_key(rng),
+            nmemb: GrumpkinSchnorr::gen_key(rng),
+        }
+    }
+
+    /// Construct an empty store signing under these keys.
+    pub fn store(&self, rng: &mut (impl CryptoRng + RngCore)) -> Result<Store> {
+        let mut store = Store::new(rng);
+        store
+            .obj_bul
+            .rotate_key(self.obj.clone())
+            .map_err(|_| anyhow!("failed to set user bulletin key"))?;
+        store
+            .callback_bul
+            .rotate_key(self.callback.clone())
+            .map_err(|_| anyhow!("failed to set callback bulletin key"))?;
+        store
+            .callback_bul
+            .nmemb_bul
+            .rotate_key(self.nmemb.clone())
+            .map_err(|_| anyhow!("failed to set nonmembership key"))?;
+        Ok(store)
+    }
+}
+
+/// The parts of a group's store which are overwritten rather than appended to.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct GroupMeta {
+    num_joined: Option<u64>,
+    epoch: F,
+    ranges: Vec<SignedRange<F, GrumpkinSchnorr>>,
+}
+
+fn group_trees(group: &str) -> Result<(sled::Tree, sled::Tree, sled::Tree)> {
+    Ok((
+        tree(&format!("users/{}", group))?,
+        tree(&format!("calls/{}", group))?,
+        tree(&format!("tickets/{}", group))?,
+    ))
+}
+
+/// Store the keys of a group, and write its store from scratch.
+///
+/// This is called when a group is created, and whenever its keys change.
+pub fn save_group(group: &str, keys: &GroupKeys, store: &Store) -> Result<()> {
+    let (users, calls, tickets) = group_trees(group)?;
+    users.clear()?;
+    calls.clear()?;
+    tickets.clear()?;
+    tree("groups")?.insert(group, encode(keys)?)?;
+    sync_group(group, store)
+}
+
+/// Write the changes to a group's store since it was last written, and flush.
+pub fn sync_group(group: &str, store: &Store) -> Result<()> {
+    let (users, calls, tickets) = group_trees(group)?;
+    let obj = &store.obj_bul;
+    append_from(&users, obj.coms.len(), |i| {
+        (
+            obj.coms[i],
+            obj.old_nuls[i],
+            obj.cb_com_lists[i].clone(),
+            obj.sigs[i].clone(),
+        )
+    })?;
+
+    let cbs = &store.callback_bul;
+    append_from(&calls, cbs.memb_called_cbs.len(), |i| {
+        let (tik, args, time) = cbs.memb_called_cbs[i].clone();
+        (tik, args, time, cbs.memb_cbs_sigs[i].clone())
+    })?;
+
+    append_from(&tickets, store.cb_tickets.len(), |i| {
+        (store.interaction_ids[i], store.cb_tickets[i].clone())
+    })?;
+
+    let meta = GroupMeta {
+        num_joined: obj.num_joined,
+        epoch: cbs.nmemb_bul.epoch,
+        ranges: cbs.nmemb_bul.get_db(),
+    };
+    tree("meta")?.insert(group, encode(&meta)?)?;
+
+    flush()
+}
+
+/// Rebuild the stores of every group in the database.
+///
+/// The callback bulletins restart their epoch roots from the restored tickets, so roots from
+/// before the restart are not kept.
+pub fn load_groups() -> Result<GStore> {
+    let mut groups = GStore::new();
+    let meta_tree = tree("meta")?;
+
+    for entry in tree("groups")?.iter() {
+        let (group, group_keys) = entry?;
+        let group = String::from_utf8(group.to_vec())?;
+        let group_keys: GroupKeys = decode(&group_keys)?;
+        let meta: GroupMeta = decode(
+            &meta_tree
+                .get(&group)?
+                .with_context(|| format!("missing state of group {}", group))?,
+        )?;
+        let (users, calls, tickets) = group_trees(&group)?;
+
+        let mut obj_bul = SigObjStore::from(
+            group_keys.obj.clone(),
+            load::<(Com<F>, Nul<F>, Vec<Com<F>>, Sig)>(&users)?,
+        );
+        obj_bul.num_joined = meta.num_joined;
+
+        let nmemb_bul = SigRangeStore::from(group_keys.nmemb.clone(), meta.ranges, meta.epoch);
+        let callback_bul = CallbackStore::from(
+            group_keys.callback.clone(),
+            load::<(FakeSigPubkey<F>, Args, Time<F>, Sig)>(&calls)?,
+            nmemb_bul,
+        );
+
+        let (interaction_ids, cb_tickets) =
+            load::<(u64, Vec<Vec<u8>>)>(&tickets)?.into_iter().unzip();
+
+        groups.insert(
+            &group,
+            CentralStore {
+                obj_bul,
+                callback_bul,
+                interaction_ids,
+                cb_tickets,
+            },
+        );
+    }
+
+    Ok(groups)
+}
+
+/// Load the key checkpoints are signed under, generating and storing it on first start.
+pub fn load_log_key(rng: &mut (impl CryptoRng + RngCore)) -> Result<Privkey> {
+    let server = tree("server")?;
+    if let Some(key) = server.get("log_key")? {
+        return decode(&key);
+    }
+    let key = GrumpkinSchnorr::gen_key(rng);
+    server.insert("log_key", encode(&key)?)?;
+    flush()?;
+    Ok(key)
+}
+
+/// Open a fresh database in the temporary directory, once for all the tests of this process.
+#[cfg(test)]
+pub fn open_temp() {
+    static OPEN: std::sync::Once = std::sync::Once::new();
+    OPEN.call_once(|| {
+        let path =
+            std::env::temp_dir().join(format!("wispy-server-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        open(&path).unwrap();
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use common::zk::MsgUser;
+    use rand::thread_rng;
+    use zk_callbacks::generic::bulletin::JoinableBulletin;
+
+    // A group's bulletins are rebuilt from the database as they were written
+    #[test]
+    fn group_reopens() {
+        open_temp();
+        let mut rng = thread_rng();
+        let keys = GroupKeys::generate(&mut rng);
+        let mut store = keys.store(&mut rng).unwrap();
+        save_group("persist-reopen", &keys, &store).unwrap();
+
+        for i in 0..3 {
+            <SigObjStore<F, GrumpkinSchnorr> as JoinableBulletin<F, MsgUser>>::join_bul(
+                &mut store.obj_bul,
+                F::from(i),
+                (),
+            )
+            .unwrap();
+        }
+        store.obj_bul.num_joined = Some(3);
+        sync_group("persist-reopen", &store).unwrap();
+
+        let mut groups = load_groups().unwrap();
+        let loaded = groups.remove("persist-reopen").unwrap();
+        assert_eq!(loaded.obj_bul.coms, store.obj_bul.coms);
+        assert_eq!(loaded.obj_bul.old_nuls, store.obj_bul.old_nuls);
+        assert_eq!(loaded.obj_bul.num_joined, Some(3));
+        assert_eq!(loaded.obj_bul.get_pubkey(), store.obj_bul.get_pubkey());
+        assert_eq!(
+            loaded.callback_bul.get_epoch(),
+            store.callback_bul.get_epoch()
+        );
+    }
+
+    // Saving a group again rewrites its bulletins rather than appending to them
+    #[test]
+    fn save_rewrites() {
+        open_temp();
+        let mut rng = thread_rng();
+        let keys = GroupKeys::generate(&mut rng);
+        let mut store = keys.store(&mut rng).unwrap();
+        <SigObjStore<F, GrumpkinSchnorr> as JoinableBulletin<F, MsgUser>>::join_bul(
+            &mut store.obj_bul,
+            F::from(7),
+            (),
+        )
+        .unwrap();
+        save_group("persist-rewrite", &keys, &store).unwrap();
+
+        let fresh = keys.store(&mut rng).unwrap();
+        save_group("persist-rewrite", &keys, &fresh).unwrap();
+        let loaded = load_groups().unwrap().remove("persist-rewrite").unwrap();
+        assert!(loaded.obj_bul.coms.is_empty());
+    }
+
+    // The checkpoint key is generated once and kept
+    #[test]
+    fn log_key_kept() {
+        open_temp();
+        let mut rng = thread_rng();
+        let first = encode(&load_log_key(&mut rng).unwrap()).unwrap();
+        let second = encode(&load_log_key(&mut rng).unwrap()).unwrap();
+        assert_eq!(first, second);
+    }
+}
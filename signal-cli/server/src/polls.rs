@@ -0,0 +1,108 @@
+//! Closing polls at their deadline.
+//!
+//! A poll opened with a deadline (see `poll_duration_secs`) is tallied by the server once the
+//! deadline passes, as if `/api/votecount` had been called: the results are posted, and a
+//! ban poll which passed bans the author of its message.
+
+use crate::error::ApiError;
+use crate::helpers::{
+    delete_poll_entry_by_timestamp, delete_poll_pseudo_entry_by_timestamp, get_poll_info, polls_due,
+};
+use crate::server::{tally_poll, ServerLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// How often the scheduler looks for polls past their deadline.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Tally polls past their deadline until the server stops.
+pub fn spawn(state: ServerLock) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = close_due(&state).await {
+                warn!("[POLLS] {}", e);
+            }
+        }
+    });
+}
+
+async fn close_due(state: &ServerLock) -> anyhow::Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    for timestamp in polls_due(now)? {
+        let Some(info) = get_poll_info(timestamp)? else {
+            continue;
+        };
+        info!("[POLLS] Closing poll {}", timestamp);
+        match tally_poll(state, &info.group_id, timestamp).await {
+            Ok(_) => (),
+            // Signal may be back by the next check
+            Err(ApiError::Signal(e)) => {
+                warn!("[POLLS] Failed to post the results of {}: {}", timestamp, e);
+                continue;
+            }
+            Err(e) => warn!("[POLLS] Failed to tally poll {}: {}", timestamp, e),
+        }
+        // Close the poll even if nobody voted, so it is not tallied again
+        delete_poll_entry_by_timestamp(timestamp)?;
+        delete_poll_pseudo_entry_by_timestamp(timestamp)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use crate::helpers::{append_poll, append_vote, attach_callback, is_open_poll, PollInfo};
+    use crate::{persist, testing};
+
+    // Polls past their deadline are tallied, and stay open while the results cannot be posted
+    #[tokio::test]
+    async fn close_due_polls() {
+        let info = || PollInfo {
+            group_id: "signal-group".to_string(),
+            group: "polls".to_string(),
+        };
+        persist::open_temp();
+        attach_callback(4854000, testing::ticket(4854000)).unwrap();
+        append_poll(4854001, 4854000, "1", info(), Some(1)).unwrap();
+        append_poll(4854002, 0, "2", info(), Some(u64::MAX)).unwrap();
+        append_vote(4854001, "alice", String::new(), "❌").unwrap();
+        append_vote(4854001, "bob", String::new(), "❌").unwrap();
+
+        let mut config = Config::default();
+        config.signal.retries = 0;
+        config.signal.tcp = "127.0.0.1:1".to_string();
+        let offline = testing::server(config, "polls-offline");
+        testing::add_group(&offline, "polls", 2);
+        close_due(&offline).await.unwrap();
+        assert!(is_open_poll(4854001).unwrap());
+
+        let (tcp, requests) =
+            testing::signal_daemon(|_| Some(Ok(serde_json::json!({ "timestamp": 4854003 })))).await;
+        let mut config = Config::default();
+        config.signal.tcp = tcp;
+        let state = testing::server(config, "polls");
+        testing::add_group(&state, "polls", 2);
+        close_due(&state).await.unwrap();
+        assert!(!is_open_poll(4854001).unwrap());
+        assert!(is_open_poll(4854002).unwrap());
+
+        let results = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|r| r["params"]["quoteTimestamp"] == 4854001)
+            .cloned()
+            .unwrap();
+        let message = results["params"]["message"].as_str().unwrap();
+        assert!(message.contains("❌ Ban: 2 (100.0%)"));
+        assert!(message.contains("Majority voted to *Ban*"));
+
+        // The author of the message the poll was about is banned
+        let group = state.group("polls").unwrap();
+        assert_eq!(group.bulletins.read().await.store.callback_bul.memb_called_cbs.len(), 1);
+    }
+}
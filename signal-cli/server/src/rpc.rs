@@ -0,0 +1,151 @@
+use crate::helpers::find_callback_by_timestamp;
+use crate::server::{verify_and_store_scan, verify_and_store_standard, ServerLock};
+use crate::DEFAULT_GROUP;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::{zk::MsgUser, F};
+use tonic::{Request, Response, Status};
+use tracing::info;
+use wispy_rpc::bulletin::{
+    CallCallbackRequest, CallCallbackResponse, GetProvingKeyRequest, GetProvingKeyResponse,
+    InteractRequest, InteractResponse, JoinRequest, JoinResponse, KeyKind, ScanRequest,
+    ScanResponse,
+};
+use wispy_rpc::Bulletin;
+use zk_callbacks::{
+    generic::{
+        bulletin::{JoinError, JoinableBulletin},
+        object::Com,
+    },
+    impls::centralized::{
+        ds::{sig::gr_schnorr::GrumpkinSchnorr, sigstore::SigObjStore},
+        join::PolicyError,
+    },
+};
+
+/// The gRPC bulletin service, sharing its state with the HTTP routes.
+pub struct BulletinService {
+    pub state: ServerLock,
+}
+
+/// The group a request is made in, where an empty group is the default group.
+fn group_of(group: &str) -> &str {
+    if group.is_empty() {
+        DEFAULT_GROUP
+    } else {
+        group
+    }
+}
+
+#[tonic::async_trait]
+impl Bulletin for BulletinService {
+    async fn join(&self, request: Request<JoinRequest>) -> Result<Response<JoinResponse>, Status> {
+        info!("[RPC] Join");
+        let request = request.into_inner();
+        let group = group_of(&request.group);
+        let object =
+            Com::<F>::deserialize_with_mode(&request.object[..], Compress::No, Validate::Yes)
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let group_state = self
+            .state
+            .group(group)
+            .ok_or_else(|| Status::not_found(format!("unknown group {}", group)))?;
+        let mut bulletins = group_state.bulletins.write().await;
+        <SigObjStore<F, GrumpkinSchnorr> as JoinableBulletin<F, MsgUser>>::join_with_policy(
+            &mut bulletins.store.obj_bul,
+            &mut *self.state.join_policy.lock().unwrap(),
+            object,
+            &(),
+            (),
+        )
+        .map_err(|e| match e {
+            JoinError::Rejected(e @ PolicyError::RateLimited) => {
+                Status::resource_exhausted(e.to_string())
+            }
+            JoinError::Rejected(e) => Status::permission_denied(e.to_string()),
+            JoinError::Bulletin(_) => {
+                Status::failed_precondition("object could not join the bulletin")
+            }
+        })?;
+        bulletins.record_anonymity();
+        bulletins.persist(group);
+
+        Ok(Response::new(JoinResponse {}))
+    }
+
+    async fn interact(
+        &self,
+        request: Request<InteractRequest>,
+    ) -> Result<Response<InteractResponse>, Status> {
+        info!("[RPC] Interact");
+        let request = request.into_inner();
+        let accepted = verify_and_store_standard(
+            &self.state,
+            group_of(&request.group),
+            &request.executed_method,
+        )
+        .await
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(Response::new(InteractResponse { accepted }))
+    }
+
+    async fn scan(&self, request: Request<ScanRequest>) -> Result<Response<ScanResponse>, Status> {
+        info!("[RPC] Scan");
+        let request = request.into_inner();
+        let accepted = verify_and_store_scan(
+            &self.state,
+            group_of(&request.group),
+            &request.executed_method,
+        )
+        .await
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(Response::new(ScanResponse { accepted }))
+    }
+
+    async fn call_callback(
+        &self,
+        request: Request<CallCallbackRequest>,
+    ) -> Result<Response<CallCallbackResponse>, Status> {
+        info!("[RPC] Call callback");
+        let ticket = find_callback_by_timestamp(request.into_inner().timestamp)
+            .map_err(|e| Status::not_found(e.to_string()))?;
+
+        Ok(Response::new(CallCallbackResponse { ticket }))
+    }
+
+    async fn get_proving_key(
+        &self,
+        request: Request<GetProvingKeyRequest>,
+    ) -> Result<Response<GetProvingKeyResponse>, Status> {
+        let request = request.into_inner();
+        let kind = request.kind();
+        let group = group_of(&request.group);
+        info!("[RPC] Proving key {:?}", kind);
+
+        let group_state = self
+            .state
+            .group(group)
+            .ok_or_else(|| Status::not_found(format!("unknown group {}", group)))?
+            .state;
+        let keys = &group_state.keys;
+        let pk = match kind {
+            KeyKind::Standard => &keys.standard_proving_key,
+            KeyKind::StandardPseudo => &keys.standard_pseudo_proving_key,
+            KeyKind::StandardPseudoRate => &keys.standard_pseudor_proving_key,
+            KeyKind::Scan => &keys.scan_proving_key,
+            KeyKind::PseudonymPred => &keys.pseudonym_pred_proving_key,
+            KeyKind::AuthorshipPred => &keys.authorship_pred_proving_key,
+            KeyKind::BadgePred => &keys.badge_pred_proving_key,
+            KeyKind::ReputationPred => &keys.reputation_pred_proving_key,
+            KeyKind::Unspecified => return Err(Status::invalid_argument("no key kind given")),
+        };
+
+        let mut key = Vec::new();
+        pk.serialize_with_mode(&mut key, Compress::No)
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(GetProvingKeyResponse { key }))
+    }
+}
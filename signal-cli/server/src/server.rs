@@ -1,61 +1,69 @@
 use crate::helpers::{
-    append_vote, count_votes_by_timestamp, delete_poll_entry_by_timestamp,
-    delete_poll_pseudo_entry_by_timestamp, find_callback_by_timestamp, get_ban_from_timestamp,
-    get_context_from_timestamp, get_reputation_by_cb, is_ban_poll_by_timestamp,
-    update_reaction_log,
+    append_poll, append_vote, attach_pending_callback, claim_poll_vote, count_votes_by_timestamp,
+    delete_poll_entry_by_timestamp, delete_poll_pseudo_entry_by_timestamp,
+    find_callback_by_timestamp, find_thread_by_context, get_all_contexts, get_ban_from_timestamp,
+    get_context_from_timestamp, get_poll_info, get_reputation_by_cb, insert_thread_context,
+    is_ban_poll_by_timestamp, list_posts, list_thread_contexts, push_pending_callback,
+    record_applied_reputation, record_post, release_poll_vote, PollInfo, PollState, Post,
 };
-use crate::ServerState;
+use crate::dm;
+use crate::error::ApiError;
+use crate::events::Event;
+use crate::jobs::{Job, JobQueue, JobStatus};
+use crate::moderation::apply_reaction;
+use crate::{group_key_id, Group, GroupState, ServerState, DEFAULT_GROUP, DEFAULT_KEY_GRACE};
 use ark_bn254::Fr;
-use ark_ff::{BigInteger, PrimeField};
-use ark_groth16::{Groth16, VerifyingKey};
+use ark_ff::{PrimeField, ToConstraintField};
+use ark_groth16::Groth16;
 use ark_r1cs_std::fields::fp::FpVar;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
 use ark_snark::SNARK;
 use ark_std::UniformRand;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use axum::{
     body::Bytes,
-    extract::{Json, State},
-    http::StatusCode,
-    response::{ErrorResponse, IntoResponse, Response},
+    extract::{Extension, Json, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
 };
 use client::helpers::{append_timing_line, append_timing_line_call_cb, append_timing_line_epoch, append_timing_line_features, append_timing_line_verify, load_start_time};
 use common::{
-    zk::{arg_ban, arg_rep, get_callbacks, get_extra_pubdata_for_scan2, MsgUser},
-    Args, Cr, Snark, E, F,
+    catalog,
+    zk::{
+        arg_ban, arg_rep, attachment_digest, get_callbacks, get_extra_pubdata_for_scan2, petname,
+        MsgUser, REP_BOUNDS,
+    },
+    Args, Cr, Snark, Store, E, F, VK,
 };
 use identicon_rs::Identicon;
 use petname::{Generator, Petnames};
-use rand::{
-    rngs::{OsRng, StdRng},
-    SeedableRng,
-};
+use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{
-    fs::{File, OpenOptions, read_to_string},
-    io::{BufRead, BufReader, Write}, 
-    process::Stdio, 
     str::FromStr, 
     string::ToString, 
     sync::Arc
 };
-use tokio::{process::Command, sync::RwLock};
 use tracing::info;
 use zk_callbacks::{
     generic::{
-        bulletin::{CallbackBul, JoinableBulletin, UserBul},
+        bulletin::{CallbackBul, JoinError, JoinableBulletin, PublicUserBul, UserBul},
         callbacks::CallbackCom,
+        keystore::KeyMeta,
         object::{Com, Time},
         scan::PubScanArgs,
         service::ServiceProvider,
         user::ExecutedMethod,
+        verify::verify_execution,
+        wire::read_envelope,
     },
     impls::{
         centralized::{
             crypto::{FakeSigPrivkey, PlainTikCrypto},
             ds::{
-                sig::gr_schnorr::GrumpkinSchnorr,
+                sig::{gr_schnorr::GrumpkinSchnorr, Signature},
                 sigstore::{GRSchnorrCallbackStore, GRSchnorrObjStore, SigObjStore},
             },
         },
@@ -67,20 +75,64 @@ use crate::PseudonymArgs;
 use crate::PseudonymArgsRate;
 
 type PubScan = PubScanArgs<F, MsgUser, F, FpVar<F>, Cr, GRSchnorrCallbackStore<F>, 1>;
-type ServerLock = Arc<RwLock<ServerState>>;
+/// The state shared by the handlers. Locks are only taken per group, see [`Group`].
+pub type ServerLock = Arc<ServerState>;
 
 #[derive(Deserialize)]
 pub struct JsonRpcInput {
     message: String,
     group_id: String,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
+    /// Files sent with the message. The proof is made with their
+    /// [digest](common::zk::attachment_digest) as its argument, so they are covered by the same
+    /// callback as the message.
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+}
+
+/// A file sent with a post.
+#[derive(Deserialize)]
+pub struct Attachment {
+    /// The name the file is shown with.
+    filename: Option<String>,
+    /// The MIME type of the file.
+    #[serde(default = "default_content_type")]
+    content_type: String,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
+    data: Vec<u8>,
+}
+
+fn default_content_type() -> String {
+    "application/octet-stream".to_string()
+}
+
+impl Attachment {
+    /// The attachment as a data URI, the way signal-cli takes attachments which are not files.
+    fn data_uri(&self) -> String {
+        let mut uri = format!("data:{}", self.content_type);
+        if let Some(filename) = &self.filename {
+            uri.push_str(";filename=");
+            uri.push_str(filename);
+        }
+        uri.push_str(";base64,");
+        uri.push_str(&STANDARD.encode(&self.data));
+        uri
+    }
 }
 
 #[derive(Deserialize)]
 pub struct JsonRpcInputPseudo {
     message: String,
     group_id: String,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
 }
 
 #[derive(Deserialize)]
@@ -88,6 +140,12 @@ pub struct JsonRpcBanPoll {
     message: Option<String>,
     group_id: String,
     timestamp: u64,
+    /// The group of the bulletins the message was posted against, whose callback is called if
+    /// the poll bans its author.
+    #[serde(default = "default_group")]
+    group: String,
+    /// How long the poll stays open, in seconds. See [`JsonRpcPoll::duration_secs`].
+    duration_secs: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -95,6 +153,10 @@ pub struct JsonRpcReact {
     group_id: String,
     emoji: String,
     timestamp: u64,
+    /// The group of the bulletins the message was posted against, for a ban poll the reaction
+    /// may open.
+    #[serde(default = "default_group")]
+    group: String,
 }
 
 #[derive(Deserialize)]
@@ -102,7 +164,11 @@ pub struct JsonRpcReply {
     group_id: String,
     message: String,
     timestamp: u64,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
 }
 
 #[derive(Deserialize)]
@@ -110,7 +176,11 @@ pub struct JsonRpcReplyPseudo {
     group_id: String,
     message: String,
     timestamp: u64,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -119,13 +189,20 @@ pub struct JsonRpcVote {
     emoji: String,
     timestamp: u64,
     claimed: String,
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
 }
 
 #[derive(Deserialize)]
 pub struct JsonRpcPoll {
     message: String,
     group_id: String,
+    /// How long the poll stays open, in seconds, after which it is tallied on its own. The
+    /// configured `poll_duration_secs` if not given; with 0 it stays open until counted.
+    duration_secs: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -135,7 +212,7 @@ pub struct JsonRpcCountVotes {
 }
 
 #[derive(Serialize)]
-struct ContextResponse {
+pub struct ContextResponse {
     context: String,
 }
 
@@ -146,8 +223,12 @@ pub struct TimestampRequest {
 
 #[derive(Deserialize)]
 pub struct JsonAuthorship {
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
     group_id: String,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -160,8 +241,22 @@ struct TimingEntry {
 
 #[derive(Deserialize)]
 pub struct JsonBadge {
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
+    proof: Vec<u8>,
+    group_id: String,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
+}
+
+#[derive(Deserialize)]
+pub struct JsonRepProof {
+    #[serde(with = "zk_callbacks::generic::encoding::base64_bytes")]
     proof: Vec<u8>,
     group_id: String,
+    /// The group of the bulletins the proof was made against.
+    #[serde(default = "default_group")]
+    group: String,
 }
 
 #[derive(Deserialize)]
@@ -180,23 +275,113 @@ pub struct TimestampInput {
     timestamp: u64,
 }
 
-fn error_to_response(error: impl ToString) -> ErrorResponse {
-    ErrorResponse::from((StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))
+/// Selects the group a request is made in.
+#[derive(Deserialize)]
+pub struct GroupQuery {
+    #[serde(default = "default_group")]
+    pub(crate) group: String,
+}
+
+fn default_group() -> String {
+    DEFAULT_GROUP.to_string()
+}
+
+fn unknown_group(group: &str) -> ApiError {
+    info!("[SERVER] Unknown group {}", group);
+    ApiError::UnknownGroup(group.to_string())
+}
+
+pub(crate) fn find_group(state: &ServerState, group: &str) -> Result<Group, ApiError> {
+    state.group(group).ok_or_else(|| unknown_group(group))
+}
+
+/// Check a submitted interaction against the nullifiers the bulletin has already consumed.
+///
+/// Resubmitting an interaction which was already stored (for example, a client retrying after a
+/// timeout) is idempotent: the returned response should be sent without storing or sending
+/// anything again. Reusing a consumed nullifier for a different interaction is rejected.
+fn check_replay<const NUMCBS: usize>(
+    obj_bul: &GRSchnorrObjStore,
+    exec: &ExecutedMethod<F, Snark, Args, Cr, NUMCBS>,
+) -> Result<Option<Response>, ApiError> {
+    let Some(i) = obj_bul
+        .old_nuls
+        .iter()
+        .position(|nul| *nul == exec.old_nullifier)
+    else {
+        return Ok(None);
+    };
+
+    if obj_bul.coms[i] == exec.new_object && obj_bul.cb_com_lists[i] == exec.cb_com_list {
+        info!("[SERVER] Interaction was already stored");
+        let body = serde_json::json!({ "status": "duplicate" });
+        return Ok(Some((StatusCode::OK, Json(body)).into_response()));
+    }
+
+    info!("[SERVER] Nullifier was already consumed");
+    Err(ApiError::NullifierConsumed)
+}
+
+/// The verifying key of an interaction which `exec` verifies under, so proofs made with a key
+/// still within its grace period after a rotation are accepted. Falls back to the current key,
+/// which then rejects the proof as usual, unless the proof was made with a key whose grace period
+/// has ended.
+fn accepted_key<A: ToConstraintField<F>, const NUMCBS: usize>(
+    group: &GroupState,
+    id: u64,
+    exec: &ExecutedMethod<F, Snark, Args, Cr, NUMCBS>,
+    pub_args: &A,
+) -> Result<VK, ApiError> {
+    let verifies = |vk: &VK| {
+        verify_execution::<F, Snark, A, Args, Cr, (), NUMCBS>(vk, exec, pub_args, None).is_ok()
+    };
+    let mut keys = group.verifying_keys(id);
+    if keys.len() > 1 || group.stale_keys(id).next().is_some() {
+        if let Some(i) = keys.iter().position(|vk| verifies(vk)) {
+            return Ok(keys.swap_remove(i));
+        }
+        if group.stale_keys(id).any(|vk| verifies(vk)) {
+            return Err(ApiError::StaleKey);
+        }
+    }
+    keys
+        .into_iter()
+        .next()
+        .ok_or_else(|| ApiError::internal(format!("no verifying key for interaction {}", id)))
+}
+
+/// Queue the callback commitments of an interaction until the message it made is sent.
+fn queue_callbacks<C: CanonicalSerialize, R>(cb_tickets: &[(C, R)]) -> Result<(), ApiError> {
+    for (cb_com, _) in cb_tickets.iter() {
+        let mut bytes = vec![];
+        cb_com.serialize_with_mode(&mut bytes, Compress::No)?;
+
+        push_pending_callback(&hex::encode(bytes))?;
+    }
+    Ok(())
+}
+
+/// The petname a pseudonym is shown as, derived deterministically from it.
+fn petname_of(pseudonym: F) -> Result<String, ApiError> {
+    petname(pseudonym).ok_or_else(|| ApiError::internal("no name generated"))
 }
 
 pub async fn forward_jsonrpc(
     State(state): State<ServerLock>,
     Json(input): Json<JsonRpcInput>,
-) -> impl IntoResponse {
+) -> Result<Response, ApiError> {
     info!("[SERVER] Verifying and appending interaction...");
 
-    let mut state2 = state.write().await;
-    let vk = state2.keys.standard_verifying_key.clone();
-    let db = &mut state2.db;
-
+    let group = find_group(&state, &input.group)?;
+    let mut bulletins = group.bulletins.write().await;
+    let db = &mut bulletins.store;
     let mut reader = &input.proof[..];
-    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> =
-        ExecutedMethod::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> = ExecutedMethod::read_from(&mut reader)?;
+    if let Some(response) = check_replay(&db.obj_bul, &exec)? {
+        return Ok(response);
+    }
+    let digest = attachment_digest(input.attachments.iter().map(|a| &a.data[..]));
+    let vk = accepted_key(&group.state, catalog::STANDARD, &exec, &digest)?;
 
     // Start (2)
     let start_time_2 = SystemTime::now();
@@ -206,15 +391,15 @@ pub async fn forward_jsonrpc(
             &mut db.obj_bul,
             exec.new_object.clone(),
             exec.old_nullifier.clone(),
-            F::from(0),
+            digest,
             exec.cb_com_list.clone(), // cb_coms.clone(),
             exec.proof.clone(),
             None,
             &vk,
         );
 
-
     info!("[SERVER] Verification result: {:?}", verified);
+    verified.map_err(ApiError::bad_proof)?;
     info!("[SERVER] Checking proof and storing interaction...");
     let cb_tickets = &exec.cb_tik_list.clone(); // get callback tickets
 
@@ -224,170 +409,92 @@ pub async fn forward_jsonrpc(
             exec,                 // output of interaction
             FakeSigPrivkey::sk(), // for authenticity: verify rerandomization of key produces
             // proper tickets (here it doesn't matter)
-            F::from(0),
+            digest,
             &db.obj_bul.clone(),
             cb_methods.clone(),
             Time::from(0),
             db.obj_bul.get_pubkey(),
             true,
             &vk,
-            332, // interaction number
+            catalog::STANDARD,
         );
 
+    bulletins.persist(&input.group);
+
     // End (2)
     let end_time_2 = SystemTime::now();
 
     
     info!("[SERVER] Verification result: {:?}", res);
-    if verified.is_ok() && res.is_ok() {
-        info!("[SERVER] Verified and added to bulletin!");
-    } else {
+    if let Err(e) = res {
         info!("[SERVER] Verification failed. Not added to bulletin.");
+        return Err(ApiError::bad_proof(e));
     }
+    info!("[SERVER] Verified and added to bulletin!");
 
-    for (cb_com, _) in cb_tickets.iter() {
-
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("server/zkpair_log.jsonl")
-            .unwrap();
-
-        let mut bytes = vec![];
-        cb_com
-            .serialize_with_mode(&mut bytes, Compress::No)
-            .unwrap();
-
-        writeln!(
-            file,
-            "{{\"callback_com\": \"{}\", \"type\": \"cb\"}}",
-            hex::encode(bytes)
-        )
-        .unwrap();
-    }
+    queue_callbacks(cb_tickets)?;
 
-    let message_to_send = input.message;
-
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486") // fixed anon bot number +15712811486 or +491724953171
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(&message_to_send)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
+    let attachments: Vec<String> = input.attachments.iter().map(Attachment::data_uri).collect();
+    let sent = state
+        .bot
+        .send_with_attachments(&input.group_id, &input.message, None, &attachments)
         .await;
 
     // End (3)
     let end_time = SystemTime::now();
 
-    let start_time = match load_start_time("3") {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Failed to load latency start time: {}", e);
-            return;
+    match load_start_time("3") {
+        Ok(start_time) => {
+            if let Err(e) = append_timing_line("3", start_time, end_time) {
+                eprintln!("❌ Failed to write timing file for proof gen: {}", e);
+            }
         }
-    };
-
-    if let Err(e) = append_timing_line("3", start_time, end_time) {
-        eprintln!("❌ Failed to write timing file for proof gen: {}", e);
+        Err(e) => eprintln!("Failed to load latency start time: {}", e),
     }
 
     if let Err(e) = append_timing_line("2", start_time_2, end_time_2) {
         eprintln!("❌ Failed to write timing file for proof gen: {}", e);
     }
 
-    match output {
-        Ok(output) => {
-
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            println!("Raw stdout from signal-cli-client: {}", stdout);
-
-            let parsed: Value = serde_json::from_str(&stdout).unwrap();
-            let ts = parsed["timestamp"].as_i64().unwrap();
-            println!("Timestamp: {}", ts);
-
-            let path = "server/zkpair_log.jsonl";
-            let original_lines = read_to_string(path)
-                .unwrap_or_default()
-                .lines()
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-
-            // Remove last line and parse callback
-            assert!(original_lines.len() >= 1, "Expected at least one cb line before msg");
-
-            let cb_line = &original_lines[original_lines.len() - 1];
-            let cb_val: Value = serde_json::from_str(cb_line).unwrap();
-            let cb = cb_val["callback_com"].as_str().unwrap();
-
-            // Construct new line
-            let new_entry = serde_json::json!({
-                "timestamp": ts,
-                "reputation": 0,
-                "cb": cb,
-            });
+    // Attach the queued callback to the sent message
+    let sent = sent?;
+    attach_pending_callback(sent)?;
+    record_post(Post {
+        attachments: input.attachments.len(),
+        ..Post::new(sent, &input.group_id, &input.message)
+    })?;
 
-            // Combine previous lines (minus last) with new entry
-            let mut all_entries = original_lines[..original_lines.len() - 1]
-                .iter()
-                .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-                .collect::<Vec<_>>();
-
-            all_entries.push(new_entry);
-
-            // Optional: Sort by timestamp if you want strict time order
-            all_entries.sort_by_key(|v| v["timestamp"].as_i64().unwrap_or(0));
-
-            // Rewrite the file
-            let mut file = File::create(path).unwrap();
-            for entry in all_entries {
-                writeln!(file, "{}", entry.to_string()).unwrap();
-            }
-
-            let stderr = String::from_utf8_lossy(&output.stderr);
-
-            if output.status.success() {
-                println!("Sent successfully: {}", stdout)
-            } else {
-                println!("Error sending: {}", stderr)
-            } 
-        }
-        Err(e) => println!("Failed to spawn process: {:?}", e),
-    }
+    Ok(Json(serde_json::json!({ "timestamp": sent })).into_response())
 }
 
 pub async fn forward_jsonrpc_pseudo(
     State(state): State<ServerLock>,
     Json(input): Json<JsonRpcInputPseudo>,
-) -> impl IntoResponse {
+) -> Result<Response, ApiError> {
     info!("[SERVER] Verifying arbitrary predicate...");
 
-    let mut state2 = state.write().await;
-    let vk = state2.keys.standard_pseudo_verifying_key.clone();
-    let db = &mut state2.db;
+    let group = find_group(&state, &input.group)?;
+    let mut bulletins = group.bulletins.write().await;
+    let db = &mut bulletins.store;
 
     let mut reader = &input.proof[..];
 
     // Deserialize components in order
-    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> =
-        ExecutedMethod::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> = ExecutedMethod::read_from(&mut reader)?;
+    if let Some(response) = check_replay(&db.obj_bul, &exec)? {
+        return Ok(response);
+    }
 
     let pub_inputs: Vec<F> =
-        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
+    let [context, claimed, ..] = pub_inputs[..] else {
+        return Err(ApiError::malformed("expected a context and a pseudonym"));
+    };
 
-    let claimed = pub_inputs[1];
     info!("[SERVER] Claimed pseudonym: {}", claimed);
 
-    let pub_args = PseudonymArgs {
-        context: pub_inputs[0],
-        claimed: pub_inputs[1],
-    };
+    let pub_args = PseudonymArgs { context, claimed };
+    let vk = accepted_key(&group.state, catalog::STANDARD_PSEUDO, &exec, &pub_args)?;
 
     let start_verify = SystemTime::now();
 
@@ -405,10 +512,10 @@ pub async fn forward_jsonrpc_pseudo(
         exec.proof.clone(),
         None,
         &vk,
-    )
-    .unwrap();
+    );
 
     info!("[SERVER] Verification result: {:?}", verify_store);
+    verify_store.map_err(ApiError::bad_proof)?;
 
     let cb_tickets = &exec.cb_tik_list.clone(); // get callback tickets
 
@@ -425,48 +532,25 @@ pub async fn forward_jsonrpc_pseudo(
             db.obj_bul.get_pubkey(),
             true,
             &vk,
-            332, // interaction number
+            catalog::STANDARD_PSEUDO,
         );
 
+    bulletins.persist(&input.group);
+
     let end_verify = SystemTime::now();
 
     info!("[SERVER] Verification result: {:?}", res);
-    if res.is_ok() {
-        info!("[SERVER] Verified and added to bulletin!");
-    } else {
+    if let Err(e) = res {
         info!("[SERVER] Verification failed. Not added to bulletin.");
+        return Err(ApiError::bad_proof(e));
     }
+    info!("[SERVER] Verified and added to bulletin!");
 
-    // Write callback commitments to log file
-    for (cb_com, _) in cb_tickets {
-        let mut file = std::fs::OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("server/zkpair_log.jsonl")
-            .unwrap();
-
-        let mut bytes = vec![];
-        cb_com
-            .serialize_with_mode(&mut bytes, Compress::No)
-            .unwrap();
-
-        writeln!(
-            file,
-            "{{\"callback_com\": \"{}\", \"type\": \"cb\"}}",
-            hex::encode(bytes)
-        )
-        .unwrap();
-    }
-
-    let claimed_bytes = claimed.into_bigint().to_bytes_le();
-    let mut seed = [0u8; 32];
-    seed[..claimed_bytes.len()].copy_from_slice(&claimed_bytes); // zero-pad to 32 bytes
+    // Queue callback commitments until the message is sent
+    queue_callbacks(cb_tickets)?;
 
-    // Use as RNG seed
-    let mut rng1 = StdRng::from_seed(seed);
-
-    let g = Petnames::default();
-    let name1 = g.generate(&mut rng1, 2, " ").expect("no name generated");
+    dm::record_pseudonym(context, claimed)?;
+    let name1 = petname_of(claimed)?;
 
     let mut pseudo = String::from("FROM: ");
     pseudo.push_str(&name1);
@@ -475,153 +559,75 @@ pub async fn forward_jsonrpc_pseudo(
 
     println!("Petname: {}", name1);
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486")
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(&pseudo)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let sent = state.bot.send(&input.group_id, &pseudo, None).await;
 
     let end_time = SystemTime::now();
 
-    let start_time = match load_start_time("pseudo_msg") {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Failed to load latency psuedo message start time: {}", e);
-            return;
+    match load_start_time("pseudo_msg") {
+        Ok(start_time) => {
+            if let Err(e) = append_timing_line_features("pseudo_msg", start_time, end_time) {
+                eprintln!("Failed to write timing file for latency psuedo message: {}", e);
+            }
         }
-    };
-
-    if let Err(e) = append_timing_line_features("pseudo_msg", start_time, end_time) {
-        eprintln!("Failed to write timing file for latency psuedo message: {}", e);
+        Err(e) => eprintln!("Failed to load latency psuedo message start time: {}", e),
     }
 
     if let Err(e) = append_timing_line_verify("pseudo_msg", start_verify, end_verify) {
         eprintln!("❌ Failed to write timing file for peudo msg verify: {}", e);
     }
-    
-    match output {
-        Ok(output) => {
-            
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            println!("Raw stdout from signal-cli-client: {}", stdout);
-
-            let parsed: Value = serde_json::from_str(&stdout).unwrap();
-            let ts = parsed["timestamp"].as_i64().unwrap();
-            println!("Timestamp: {}", ts);
-
-            let path = "server/zkpair_log.jsonl";
-            let original_lines = read_to_string(path)
-                .unwrap_or_default()
-                .lines()
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-
-            // Remove last line and parse callback
-            assert!(original_lines.len() >= 1, "Expected at least one cb line before msg");
-
-            let cb_line = &original_lines[original_lines.len() - 1];
-            let cb_val: Value = serde_json::from_str(cb_line).unwrap();
-            let cb = cb_val["callback_com"].as_str().unwrap();
-
-            // Construct new line
-            let new_entry = serde_json::json!({
-                "timestamp": ts,
-                "reputation": 0,
-                "cb": cb,
-            });
-
-            // Combine previous lines (minus last) with new entry
-            let mut all_entries = original_lines[..original_lines.len() - 1]
-                .iter()
-                .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-                .collect::<Vec<_>>();
-
-            all_entries.push(new_entry);
-
-            // Optional: Sort by timestamp if you want strict time order
-            all_entries.sort_by_key(|v| v["timestamp"].as_i64().unwrap_or(0));
-
-            // Rewrite the file
-            let mut file = File::create(path).unwrap();
-            for entry in all_entries {
-                writeln!(file, "{}", entry.to_string()).unwrap();
-            }
 
-            let stderr = String::from_utf8_lossy(&output.stderr);
+    // Attach the queued callback to the sent message
+    let sent = sent?;
+    attach_pending_callback(sent)?;
+    record_post(Post {
+        pseudonym: Some(name1),
+        thread: find_thread_by_context(&context.to_string())?,
+        ..Post::new(sent, &input.group_id, &input.message)
+    })?;
 
-            if output.status.success() {
-                println!("Sent successfully: {}", stdout)
-            } else {
-                println!("Error sending: {}", stderr)
-            } 
-        }
-        Err(e) => println!("Failed to spawn process: {:?}", e),
-    }
+    Ok(Json(serde_json::json!({ "timestamp": sent })).into_response())
 }
 
 
 pub async fn forward_jsonrpc_pseudo_rate(
     State(state): State<ServerLock>,
     Json(input): Json<JsonRpcInputPseudo>,
-) -> impl IntoResponse {
+) -> Result<Response, ApiError> {
     info!("[SERVER] Verifying arbitrary predicate...");
 
-    let mut state2 = state.write().await;
-    let vk = state2.keys.standard_pseudor_verifying_key.clone();
-    let db = &mut state2.db;
+    let group = find_group(&state, &input.group)?;
+    let mut bulletins = group.bulletins.write().await;
+    let db = &mut bulletins.store;
 
     let mut reader = &input.proof[..];
 
     // Deserialize components in order
-    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> =
-        ExecutedMethod::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> = ExecutedMethod::read_from(&mut reader)?;
+    if let Some(response) = check_replay(&db.obj_bul, &exec)? {
+        return Ok(response);
+    }
 
     let pub_inputs: Vec<F> =
-        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
+    let [context, claimed, i, ..] = pub_inputs[..] else {
+        return Err(ApiError::malformed("expected a context, a pseudonym and an index"));
+    };
 
-    let claimed = pub_inputs[1];
     info!("[SERVER] Claimed pseudonym: {}", claimed);
-    let context = pub_inputs[0];
     info!("[SERVER] Context: {}", context);
-    let i = pub_inputs[2];
     info!("[SERVER] i: {}", i);
 
-    // let thread = &input.thread;
-
-    let file = File::open("server/context.jsonl").expect("Failed to open file");
-    let reader = BufReader::new(file);
-
-    let mut matched_thread: Option<String> = None;
-    for line in reader.lines() {
-        let line = line.map_err(error_to_response).unwrap();
-        if let Ok(entry) = serde_json::from_str::<ContextJson>(&line) {
-            if entry.context == context.to_string() {
-                info!("[SERVER] Context matched thread: {}", entry.thread);
-                matched_thread = Some(entry.thread);
-                break;
-            }
-        }
-    }
-    
     // Error if no match found
-    let thread = matched_thread.ok_or_else(|| {
-        error_to_response("Context not found in context.jsonl")
-    }).unwrap();
+    let thread = find_thread_by_context(&context.to_string())?
+        .ok_or_else(|| ApiError::NotFound("Context not found".to_string()))?;
+    info!("[SERVER] Context matched thread: {}", thread);
 
     let pub_args = PseudonymArgsRate {
         context,
         claimed,
-        i,
+        index: i,
     };
+    let vk = accepted_key(&group.state, catalog::STANDARD_PSEUDO_RATE, &exec, &pub_args)?;
 
     let start_verify=SystemTime::now();
 
@@ -641,10 +647,10 @@ pub async fn forward_jsonrpc_pseudo_rate(
         exec.proof.clone(),
         None,
         &vk,
-    )
-    .unwrap();
+    );
 
     info!("[SERVER] Verification result: {:?}", verify_store);
+    verify_store.map_err(ApiError::bad_proof)?;
 
     let cb_tickets = &exec.cb_tik_list.clone();
     
@@ -661,48 +667,25 @@ pub async fn forward_jsonrpc_pseudo_rate(
             db.obj_bul.get_pubkey(),
             true,
             &vk,
-            332, // interaction number
+            catalog::STANDARD_PSEUDO_RATE,
         );
 
+    bulletins.persist(&input.group);
+
     let end_verify = SystemTime::now();
 
     info!("[SERVER] Verification result: {:?}", res);
-    if res.is_ok() {
-        info!("[SERVER] Verified and added to bulletin!");
-    } else {
+    if let Err(e) = res {
         info!("[SERVER] Verification failed. Not added to bulletin.");
+        return Err(ApiError::bad_proof(e));
     }
+    info!("[SERVER] Verified and added to bulletin!");
 
-    // Write callback commitments to log file
-    for (cb_com, _) in cb_tickets {
-        let mut file = std::fs::OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("server/zkpair_log.jsonl")
-            .unwrap();
-
-        let mut bytes = vec![];
-        cb_com
-            .serialize_with_mode(&mut bytes, Compress::No)
-            .unwrap();
-
-        writeln!(
-            file,
-            "{{\"callback_com\": \"{}\", \"type\": \"cb\"}}",
-            hex::encode(bytes)
-        )
-        .unwrap();
-    }
-
-    let claimed_bytes = claimed.into_bigint().to_bytes_le();
-    let mut seed = [0u8; 32];
-    seed[..claimed_bytes.len()].copy_from_slice(&claimed_bytes); // zero-pad to 32 bytes
-
-    // Use as RNG seed
-    let mut rng1 = StdRng::from_seed(seed);
+    // Queue callback commitments until the message is sent
+    queue_callbacks(cb_tickets)?;
 
-    let g = Petnames::default();
-    let name1 = g.generate(&mut rng1, 2, " ").expect("no name generated");
+    dm::record_pseudonym(context, claimed)?;
+    let name1 = petname_of(claimed)?;
 
     let mut pseudo = String::from("FROM: ");
     pseudo.push_str(&name1);
@@ -712,214 +695,127 @@ pub async fn forward_jsonrpc_pseudo_rate(
     println!("Thread: {:?}", &thread);
     println!("Petname: {}", name1);
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486")
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(&pseudo)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let sent = state.bot.send(&input.group_id, &pseudo, None).await;
 
     let end_time = SystemTime::now();
 
-    let start_time = match load_start_time("rate_pseudo") {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Failed to load rate pseudo latency start time: {}", e);
-            return;
+    match load_start_time("rate_pseudo") {
+        Ok(start_time) => {
+            if let Err(e) = append_timing_line_features("rate_pseudo", start_time, end_time) {
+                eprintln!("Failed to write timing file for latency rate pseudo: {}", e);
+            }
         }
-    };
-
-    if let Err(e) = append_timing_line_features("rate_pseudo", start_time, end_time) {
-        eprintln!("Failed to write timing file for latency rate pseudo: {}", e);
+        Err(e) => eprintln!("Failed to load rate pseudo latency start time: {}", e),
     }
 
     if let Err(e) = append_timing_line_verify("rate_pseudo", start_verify, end_verify) {
         eprintln!("❌ Failed to write timing file for rate pseudo verify: {}", e);
     }
 
-    match output {
-        Ok(output) => {
-            
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            println!("Raw stdout from signal-cli-client: {}", stdout);
-
-            let parsed: Value = serde_json::from_str(&stdout).unwrap();
-            let ts = parsed["timestamp"].as_i64().unwrap();
-            println!("Timestamp: {}", ts);
-
-            let path = "server/zkpair_log.jsonl";
-            let original_lines = read_to_string(path)
-                .unwrap_or_default()
-                .lines()
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-
-            // Remove last line and parse callback
-            assert!(original_lines.len() >= 1, "Expected at least one cb line before msg");
-
-            let cb_line = &original_lines[original_lines.len() - 1];
-            let cb_val: Value = serde_json::from_str(cb_line).unwrap();
-            let cb = cb_val["callback_com"].as_str().unwrap();
-
-            // Construct new line
-            let new_entry = serde_json::json!({
-                "timestamp": ts,
-                "reputation": 0,
-                "cb": cb,
-            });
-
-            // Combine previous lines (minus last) with new entry
-            let mut all_entries = original_lines[..original_lines.len() - 1]
-                .iter()
-                .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-                .collect::<Vec<_>>();
-
-            all_entries.push(new_entry);
+    // Attach the queued callback to the sent message
+    let sent = sent?;
+    attach_pending_callback(sent)?;
+    record_post(Post {
+        pseudonym: Some(name1),
+        thread: Some(thread),
+        ..Post::new(sent, &input.group_id, &input.message)
+    })?;
 
-            // Optional: Sort by timestamp if you want strict time order
-            all_entries.sort_by_key(|v| v["timestamp"].as_i64().unwrap_or(0));
-
-            // Rewrite the file
-            let mut file = File::create(path).unwrap();
-            for entry in all_entries {
-                writeln!(file, "{}", entry.to_string()).unwrap();
-            }
+    Ok(Json(serde_json::json!({ "timestamp": sent })).into_response())
+}
 
-            let stderr = String::from_utf8_lossy(&output.stderr);
 
-            if output.status.success() {
-                println!("Sent successfully: {}", stdout)
-            } else {
-                println!("Error sending: {}", stderr)
-            } 
-        }
-        Err(e) => println!("Failed to spawn process: {:?}", e),
-    }
+#[derive(Deserialize)]
+pub struct PseudonymQuery {
+    /// The Signal group whose bot account gets the new profile; the default account if not given.
+    group_id: Option<String>,
 }
 
-
-pub async fn pseudonym() -> impl IntoResponse {
+pub async fn pseudonym(
+    State(state): State<ServerLock>,
+    Query(query): Query<PseudonymQuery>,
+) -> Result<String, ApiError> {
     let petname = Petnames::default();
-    let pseudo_given = petname.generate_one(1, "").unwrap();
-    let pseudo_family = petname.generate_one(1, "").unwrap();
+    let pseudo_given = petname
+        .generate_one(1, "")
+        .ok_or_else(|| ApiError::internal("no name generated"))?;
+    let pseudo_family = petname
+        .generate_one(1, "")
+        .ok_or_else(|| ApiError::internal("no name generated"))?;
 
     let mut input = pseudo_family.clone();
     input.push_str(&pseudo_given);
 
-    let avatar_path = std::env::current_dir().unwrap().join("avatar.png");
-
-    let avatar_path_str = avatar_path.to_str().unwrap();
-
-    let _ = Identicon::new(&input).save_image(&avatar_path_str);
-    assert!(avatar_path.exists(), "avatar.png was not created");
-
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486")
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("updateProfile")
-        .arg("--given-name")
-        .arg(&pseudo_given)
-        .arg("--family-name")
-        .arg(&pseudo_family)
-        .arg("--about")
-        .arg("Anonymous User")
-        .arg("--avatar")
-        .arg(avatar_path_str)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let avatar_path = std::env::current_dir()
+        .map_err(ApiError::internal)?
+        .join("avatar.png");
 
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
+    let avatar_path_str = avatar_path
+        .to_str()
+        .ok_or_else(|| ApiError::internal("avatar path is not valid UTF-8"))?;
 
-            if output.status.success() {
-                format!("Sent successfully: {}", stdout)
-            } else {
-                format!("Error sending: {}", stderr)
-            }
-        }
-        Err(e) => format!("Failed to spawn process: {:?}", e),
-    }
+    Identicon::new(&input)
+        .save_image(avatar_path_str)
+        .map_err(|e| ApiError::internal(format!("avatar.png was not created: {:?}", e)))?;
+
+    let account = match &query.group_id {
+        Some(group_id) => state.bot.account(group_id),
+        None => state.bot.default_account(),
+    };
+    let updated = state
+        .bot
+        .update_profile(
+            account,
+            &pseudo_given,
+            &pseudo_family,
+            "Anonymous User",
+            avatar_path_str,
+        )
+        .await?;
+
+    Ok(format!("Sent successfully: {}", updated))
 }
 
-pub async fn forward_reaction(Json(input): Json<JsonRpcReact>) -> impl IntoResponse {
+pub async fn forward_reaction(
+    State(state): State<ServerLock>,
+    Json(input): Json<JsonRpcReact>,
+) -> Result<String, ApiError> {
     // If "upvote" or "downvote" input as string, convert to an emoji 
     let emoji = string_to_emoji(&input.emoji);
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486")
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(&emoji)
-        .arg("--quote-timestamp")
-        .arg(&input.timestamp.to_string())
-        .arg("--quote-author")
-        .arg("+15712811486")
-        .arg("--quote-message")
-        .arg("")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
+    let sent = state
+        .bot
+        .send(&input.group_id, emoji, Some(input.timestamp))
         .await;
 
-    let emoji_name = emoji_to_name(&emoji);
-
-    match emoji_name {
-        "upvote" => update_reaction_log(input.timestamp, 1).unwrap(), // increase reputation
-        "downvote" => update_reaction_log(input.timestamp, -1).unwrap(), // decrement reputation
-        "hatespeech" => println!("Hate speech flagged."),             // msg flagged for hate speech
-        "ban" => println!("Ban suggested."),                          // anon user ban suggested
-        "not ban" => println!("Do not ban suggested."), // suggestion to not ban anon user
-        "unknown" => println!("Unknown emoji."),
-        _ => (),
-    }
-
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
+    apply_reaction(
+        &state,
+        Some(&input.group_id),
+        &input.group,
+        input.timestamp,
+        emoji,
+        true,
+    )
+    .await?;
 
-            if output.status.success() {
-                format!("Sent successfully: {}", stdout)
-            } else {
-                format!("Error sending: {}", stderr)
-            }
-        }
-        Err(e) => format!("Failed to spawn process: {:?}", e),
-    }
+    Ok(format!("Sent successfully: {}", sent?))
 }
 
 pub async fn forward_reply(
     State(state): State<ServerLock>,
     Json(input): Json<JsonRpcReply>,
-) -> impl IntoResponse {
+) -> Result<Response, ApiError> {
     info!("[SERVER] Verifying and appending interaction...");
 
-    let mut state2 = state.write().await;
-    let vk = state2.keys.standard_verifying_key.clone();
-    let db = &mut state2.db;
-
+    let group = find_group(&state, &input.group)?;
+    let mut bulletins = group.bulletins.write().await;
+    let db = &mut bulletins.store;
     let mut reader = &input.proof[..];
-    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> =
-        ExecutedMethod::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> = ExecutedMethod::read_from(&mut reader)?;
+    if let Some(response) = check_replay(&db.obj_bul, &exec)? {
+        return Ok(response);
+    }
+    let vk = accepted_key(&group.state, catalog::STANDARD, &exec, &F::from(0))?;
 
     let verified =
         <GRSchnorrObjStore as UserBul<F, MsgUser>>::verify_interact_and_append::<F, Groth16<E>, 1>(
@@ -933,32 +829,12 @@ pub async fn forward_reply(
             &vk,
         );
 
-    let cb_tickets = &exec.cb_tik_list; // get callback tickets
-
-    for (cb_com, _) in cb_tickets.iter() {
-
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("server/zkpair_log.jsonl")
-            .unwrap();
-
-        let mut bytes = vec![];
-        cb_com
-            .serialize_with_mode(&mut bytes, Compress::No)
-            .unwrap();
-
-        writeln!(
-            file,
-            "{{\"callback_com\": \"{}\", \"type\": \"cb\"}}",
-            hex::encode(bytes)
-        )
-        .unwrap();
-    }
-
     info!("[SERVER] Verification result: {:?}", verified);
+    verified.map_err(ApiError::bad_proof)?;
     info!("[SERVER] Checking proof and storing interaction...");
 
+    let cb_tickets = &exec.cb_tik_list.clone(); // get callback tickets
+
     let cb_methods = get_callbacks();
     let res = db
         .approve_interaction_and_store::<MsgUser, Groth16<E>, F, GRSchnorrObjStore, Poseidon<2>, 1>(
@@ -972,129 +848,69 @@ pub async fn forward_reply(
             db.obj_bul.get_pubkey(),
             true,
             &vk,
-            332, // interaction number
+            catalog::STANDARD,
         );
 
+    bulletins.persist(&input.group);
+
     info!("[SERVER] Verification result: {:?}", res);
-    if verified.is_ok() && res.is_ok() {
-        info!("[SERVER] Verified and added to bulletin!");
-    } else {
+    if let Err(e) = res {
         info!("[SERVER] Verification failed. Not added to bulletin.");
+        return Err(ApiError::bad_proof(e));
     }
+    info!("[SERVER] Verified and added to bulletin!");
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486")
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(&input.message)
-        .arg("--quote-timestamp")
-        .arg(&input.timestamp.to_string())
-        .arg("--quote-author")
-        .arg("+15712811486")
-        .arg("--quote-message")
-        .arg("")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
-
-    match output {
-        Ok(output) => {
-            
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            println!("Raw stdout from signal-cli-client: {}", stdout);
-
-            let parsed: Value = serde_json::from_str(&stdout).unwrap();
-            let ts = parsed["timestamp"].as_i64().unwrap();
-            println!("Timestamp: {}", ts);
-
-            let path = "server/zkpair_log.jsonl";
-            let original_lines = read_to_string(path)
-                .unwrap_or_default()
-                .lines()
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-
-            // Remove last line and parse callback
-            assert!(original_lines.len() >= 1, "Expected at least one cb line before msg");
-
-            let cb_line = &original_lines[original_lines.len() - 1];
-            let cb_val: Value = serde_json::from_str(cb_line).unwrap();
-            let cb = cb_val["callback_com"].as_str().unwrap();
-
-            // Construct new line
-            let new_entry = serde_json::json!({
-                "timestamp": ts,
-                "reputation": 0,
-                "cb": cb,
-            });
-
-            // Combine previous lines (minus last) with new entry
-            let mut all_entries = original_lines[..original_lines.len() - 1]
-                .iter()
-                .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-                .collect::<Vec<_>>();
-
-            all_entries.push(new_entry);
-
-            // Optional: Sort by timestamp if you want strict time order
-            all_entries.sort_by_key(|v| v["timestamp"].as_i64().unwrap_or(0));
+    queue_callbacks(cb_tickets)?;
 
-            // Rewrite the file
-            let mut file = File::create(path).unwrap();
-            for entry in all_entries {
-                writeln!(file, "{}", entry.to_string()).unwrap();
-            }
+    let ts = state.bot.send(&input.group_id, &input.message, Some(input.timestamp)).await?;
 
-            let stderr = String::from_utf8_lossy(&output.stderr);
+    // Attach the queued callback to the sent message
+    attach_pending_callback(ts)?;
+    record_post(Post {
+        reply_to: Some(input.timestamp),
+        ..Post::new(ts, &input.group_id, &input.message)
+    })?;
 
-            if output.status.success() {
-                println!("Sent successfully: {}", stdout)
-            } else {
-                println!("Error sending: {}", stderr)
-            } 
-        }
-        Err(e) => println!("Failed to spawn process: {:?}", e),
-    }
+    Ok(Json(serde_json::json!({ "timestamp": ts })).into_response())
 }
 
 pub async fn forward_reply_pseudo(
     State(state): State<ServerLock>,
     Json(input): Json<JsonRpcReplyPseudo>,
-) -> impl IntoResponse {
+) -> Result<Response, ApiError> {
     info!("[SERVER] Verifying arbitrary predicate...");
 
-    let mut state2 = state.write().await;
-    let vk = state2.keys.standard_verifying_key.clone();
-    let vki = state2.keys.pseudonym_pred_verifying_key.clone();
-    let db = &mut state2.db;
-
+    let group = find_group(&state, &input.group)?;
+    let mut bulletins = group.bulletins.write().await;
+    let db = &mut bulletins.store;
     let mut reader = &input.proof[..];
 
     // Deserialize components in order
-    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> =
-        ExecutedMethod::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> = ExecutedMethod::read_from(&mut reader)?;
+    if let Some(response) = check_replay(&db.obj_bul, &exec)? {
+        return Ok(response);
+    }
+    let vk = accepted_key(&group.state, catalog::STANDARD, &exec, &F::from(0))?;
 
-    let proof = <Groth16<E> as SNARK<F>>::Proof::deserialize_with_mode(
-        &mut reader,
-        Compress::No,
-        Validate::Yes,
-    )
-    .unwrap();
+    let (proof, _): (<Groth16<E> as SNARK<F>>::Proof, _) = read_envelope(&mut reader)?;
 
     let pub_inputs: Vec<F> =
-        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
 
-    let verified = Groth16::<E>::verify(&vki, &pub_inputs, &proof).unwrap();
+    let verified = group
+        .state
+        .verifying_keys(catalog::PSEUDONYM_PRED)
+        .iter()
+        .any(|vki| Groth16::<E>::verify(vki, &pub_inputs, &proof).unwrap_or(false));
 
     info!("[SERVER] Verification result: {}", verified);
+    if !verified {
+        return Err(ApiError::BadProof("pseudonym proof did not verify".to_string()));
+    }
 
-    let claimed = pub_inputs[1];
+    let claimed = *pub_inputs
+        .get(1)
+        .ok_or_else(|| ApiError::malformed("expected a context and a pseudonym"))?;
     info!("[SERVER] Claimed pseudonym: {}", claimed);
 
     // Store the interaction to the object bulletin board
@@ -1108,42 +924,17 @@ pub async fn forward_reply_pseudo(
             exec.proof.clone(),
             None,
             &vk,
-        )
-        .unwrap();
+        );
 
     info!("[SERVER] Verification result: {:?}", verify_store);
+    verify_store.map_err(ApiError::bad_proof)?;
+    bulletins.persist(&input.group);
 
-    // Write callback commitments to log file
-    for (cb_com, _) in &exec.cb_tik_list {
-        let mut file = std::fs::OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("server/zkpair_log.jsonl")
-            .unwrap();
-
-        let mut bytes = vec![];
-        cb_com
-            .serialize_with_mode(&mut bytes, Compress::No)
-            .unwrap();
-
-        writeln!(
-            file,
-            "{{\"callback_com\": \"{}\", \"type\": \"cb\"}}",
-            hex::encode(bytes)
-        )
-        .unwrap();
-    }
+    // Queue callback commitments until the message is sent
+    queue_callbacks(&exec.cb_tik_list)?;
 
-    // Convert Fp to bytes (deterministic serialization)
-    let claimed_bytes = claimed.into_bigint().to_bytes_le();
-    let mut seed = [0u8; 32];
-    seed[..claimed_bytes.len()].copy_from_slice(&claimed_bytes); // zero-pad to 32 bytes
-
-    // Use as RNG seed
-    let mut rng1 = StdRng::from_seed(seed);
-
-    let g = Petnames::default();
-    let name1 = g.generate(&mut rng1, 2, " ").expect("no name generated");
+    dm::record_pseudonym(pub_inputs[0], claimed)?;
+    let name1 = petname_of(claimed)?;
 
     let mut pseudo = String::from("FROM: ");
     pseudo.push_str(&name1);
@@ -1152,344 +943,239 @@ pub async fn forward_reply_pseudo(
 
     println!("Petname: {}", name1);
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486")
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(&pseudo)
-        .arg("--quote-timestamp")
-        .arg(&input.timestamp.to_string())
-        .arg("--quote-author")
-        .arg("+15712811486")
-        .arg("--quote-message")
-        .arg("")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
-
-    match output {
-        Ok(output) => {
-            
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            println!("Raw stdout from signal-cli-client: {}", stdout);
-
-            let parsed: Value = serde_json::from_str(&stdout).unwrap();
-            let ts = parsed["timestamp"].as_i64().unwrap();
-            println!("Timestamp: {}", ts);
-
-            let path = "server/zkpair_log.jsonl";
-            let original_lines = read_to_string(path)
-                .unwrap_or_default()
-                .lines()
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-
-            // Remove last line and parse callback
-            assert!(original_lines.len() >= 1, "Expected at least one cb line before msg");
-
-            let cb_line = &original_lines[original_lines.len() - 1];
-            let cb_val: Value = serde_json::from_str(cb_line).unwrap();
-            let cb = cb_val["callback_com"].as_str().unwrap();
-
-            // Construct new line
-            let new_entry = serde_json::json!({
-                "timestamp": ts,
-                "reputation": 0,
-                "cb": cb,
-            });
-
-            // Combine previous lines (minus last) with new entry
-            let mut all_entries = original_lines[..original_lines.len() - 1]
-                .iter()
-                .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-                .collect::<Vec<_>>();
-
-            all_entries.push(new_entry);
+    let ts = state.bot.send(&input.group_id, &pseudo, Some(input.timestamp)).await?;
 
-            // Optional: Sort by timestamp if you want strict time order
-            all_entries.sort_by_key(|v| v["timestamp"].as_i64().unwrap_or(0));
+    // Attach the queued callback to the sent message
+    attach_pending_callback(ts)?;
+    record_post(Post {
+        pseudonym: Some(name1),
+        thread: find_thread_by_context(&pub_inputs[0].to_string())?,
+        reply_to: Some(input.timestamp),
+        ..Post::new(ts, &input.group_id, &input.message)
+    })?;
 
-            // Rewrite the file
-            let mut file = File::create(path).unwrap();
-            for entry in all_entries {
-                writeln!(file, "{}", entry.to_string()).unwrap();
-            }
+    Ok(Json(serde_json::json!({ "timestamp": ts })).into_response())
+}
 
-            let stderr = String::from_utf8_lossy(&output.stderr);
+/// When a poll opened now closes, given how long it should stay open.
+fn poll_deadline(state: &ServerState, duration_secs: Option<u64>) -> Option<u64> {
+    let duration = duration_secs.unwrap_or(state.config.poll_duration_secs);
+    if duration == 0 {
+        return None;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(now + duration)
+}
 
-            if output.status.success() {
-                println!("Sent successfully: {}", stdout)
-            } else {
-                println!("Error sending: {}", stderr)
-            } 
-        }
-        Err(e) => println!("Failed to spawn process: {:?}", e),
+/// Tell the group when a poll closes.
+fn push_deadline(poll_message: &mut String, deadline: Option<u64>) {
+    let closes = deadline
+        .and_then(|d| chrono::DateTime::from_timestamp(d as i64, 0))
+        .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string());
+    if let Some(closes) = closes {
+        poll_message.push_str(&format!("\n\n⏰ Closes {}", closes));
     }
 }
 
-pub async fn forward_poll(Json(input): Json<JsonRpcPoll>) -> impl IntoResponse {
+pub async fn forward_poll(
+    State(state): State<ServerLock>,
+    Json(input): Json<JsonRpcPoll>,
+) -> Result<String, ApiError> {
     // Compose the poll message with a standard header and instructions
     let mut poll_message = String::from("📊 *Poll Time!*\n");
     poll_message.push_str("React with 👍 for *Yes*, 👎 for *No*\n\n");
     poll_message.push_str(&input.message);
+    let deadline = poll_deadline(&state, input.duration_secs);
+    push_deadline(&mut poll_message, deadline);
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486") // your fixed sending bot number
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id) // dynamic group id
-        .arg("-m")
-        .arg(&poll_message) // dynamic message
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let ts = state.bot.send(&input.group_id, &poll_message, None).await?;
 
     let context_str = generate_context_string::<F>();
 
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            println!("Raw stdout from signal-cli-client: {}", stdout);
-
-            // Step 0: Parse signal-cli-client output
-            let parsed: serde_json::Value = match serde_json::from_str(&stdout) {
-                Ok(val) => val,
-                Err(e) => {
-                    eprintln!("Failed to parse stdout as JSON: {}", e);
-                    return format!("Error parsing output");
-                }
-            };
-            let ts = parsed["timestamp"]
-                .as_i64()
-                .expect("Missing timestamp field");
-            println!("Timestamp: {}", ts);
-
-            let path = "server/poll_log.jsonl";
-
-            // Step 1: Append new poll entry to last line of file
-            let mut file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(path)
-                .expect("Failed to open file for appending");
-
-            // Step 2: Append the merged record
-            let merged_entry = serde_json::json!({
-                "timestamp": ts,
-                "votes": [],
-                "ban": 0,
-                "context": context_str
-            });
-            writeln!(file, "{}", merged_entry).expect("Failed to write merged entry");
-
-            // Optional: print stderr if any
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if output.status.success() {
-                format!("Sent successfully: {}", stdout)
-            } else {
-                format!("Error sending: {}", stderr)
-            }
-        }
-        Err(e) => format!("Failed to spawn process: {:?}", e),
-    }
+    // Open the poll, with no votes yet
+    let info = PollInfo {
+        group_id: input.group_id,
+        group: DEFAULT_GROUP.to_string(),
+    };
+    record_post(Post {
+        poll: Some(PollState {
+            ban: None,
+            open: true,
+            yes: 0,
+            no: 0,
+            deadline,
+        }),
+        ..Post::new(ts, &info.group_id, &input.message)
+    })?;
+    append_poll(ts, 0, &context_str, info, deadline)?;
+    state.events.publish(Event::Poll {
+        timestamp: ts,
+        ban: None,
+        context: context_str,
+    });
+
+    Ok(format!("Sent successfully: {}", ts))
+}
+
+pub async fn forward_ban_poll(
+    State(state): State<ServerLock>,
+    Json(input): Json<JsonRpcBanPoll>,
+) -> Result<String, ApiError> {
+    // Use the message if provided, otherwise fall back to a default
+    let msg_text = input.message.as_deref().unwrap_or("");
+    let ts = open_ban_poll(
+        &state,
+        &input.group_id,
+        input.timestamp,
+        msg_text,
+        input.group,
+        input.duration_secs,
+    )
+    .await?;
+
+    Ok(format!("Sent successfully: {}", ts))
 }
 
-pub async fn forward_ban_poll(Json(input): Json<JsonRpcBanPoll>) -> impl IntoResponse {
+/// Open a poll in the Signal group `group_id` on banning the author of its message sent at
+/// `timestamp`, posted against `group`, and return the timestamp of the poll.
+pub(crate) async fn open_ban_poll(
+    state: &ServerState,
+    group_id: &str,
+    timestamp: u64,
+    message: &str,
+    group: String,
+    duration_secs: Option<u64>,
+) -> Result<u64, ApiError> {
     // Compose the poll message with a standard header and instructions
     let mut poll_message = String::from("📊 *Ban Poll Initiated*\n");
     poll_message.push_str("React with ❌ to *Ban* or ✅ to *Keep* this user.\n\n");
     poll_message.push_str("This poll was triggered because the following message may contain harmful, inappropriate, or spam content:\n\n");
+    poll_message.push_str(message);
+    let deadline = poll_deadline(state, duration_secs);
+    push_deadline(&mut poll_message, deadline);
 
-    // Use the message if provided, otherwise fall back to a default
-    let msg_text = input.message.as_deref().unwrap_or("");
-    poll_message.push_str(msg_text);
-
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486") // your fixed sending bot number
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id) // dynamic group id
-        .arg("-m")
-        .arg(&poll_message) // dynamic message
-        .arg("--quote-timestamp")
-        .arg(&input.timestamp.to_string())
-        .arg("--quote-author")
-        .arg("+15712811486")
-        .arg("--quote-message")
-        .arg("")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let ts = state.bot.send(group_id, &poll_message, Some(timestamp)).await?;
 
     let context_str = generate_context_string::<F>();
 
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            println!("Raw stdout from signal-cli-client: {}", stdout);
-
-            // Step 0: Parse signal-cli-client output
-            let parsed: serde_json::Value = match serde_json::from_str(&stdout) {
-                Ok(val) => val,
-                Err(e) => {
-                    eprintln!("Failed to parse stdout as JSON: {}", e);
-                    return format!("Error parsing output");
-                }
-            };
-            let ts = parsed["timestamp"]
-                .as_i64()
-                .expect("Missing timestamp field");
-            println!("Timestamp: {}", ts);
-
-            let path = "server/poll_log.jsonl";
-
-            // Step 1: Append new poll entry to last line of file
-            let mut file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(path)
-                .expect("Failed to open file for appending");
-
-            // Step 2: Append the merged record
-            let merged_entry = serde_json::json!({
-                "timestamp": ts,
-                "votes": [],
-                "ban": input.timestamp.clone(),
-                "context": context_str
-            });
-            writeln!(file, "{}", merged_entry).expect("Failed to write merged entry");
-
-            // Optional: print stderr if any
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if output.status.success() {
-                format!("Sent successfully: {}", stdout)
-            } else {
-                format!("Error sending: {}", stderr)
-            }
-        }
-        Err(e) => format!("Failed to spawn process: {:?}", e),
-    }
+    // Open the poll, with no votes yet
+    let info = PollInfo {
+        group_id: group_id.to_string(),
+        group,
+    };
+    record_post(Post {
+        reply_to: Some(timestamp),
+        poll: Some(PollState {
+            ban: Some(timestamp),
+            open: true,
+            yes: 0,
+            no: 0,
+            deadline,
+        }),
+        ..Post::new(ts, group_id, message)
+    })?;
+    append_poll(ts, timestamp as i64, &context_str, info, deadline)?;
+    state.events.publish(Event::Poll {
+        timestamp: ts,
+        ban: Some(timestamp),
+        context: context_str,
+    });
+
+    Ok(ts)
 }
 
 pub async fn forward_vote(
     State(state): State<ServerLock>,
     Json(input): Json<JsonRpcVote>,
-) -> impl IntoResponse {
-    let vki = state
-        .write()
-        .await
-        .keys
-        .pseudonym_pred_verifying_key
-        .clone();
+) -> Result<String, ApiError> {
+    let vkis = find_group(&state, &input.group)?
+        .state
+        .verifying_keys(catalog::PSEUDONYM_PRED);
 
     let claimed_str = &input.claimed;
 
     // Use fully-qualified syntax to access BigInt
-    let claimed_bigint =
-        <F as PrimeField>::BigInt::from_str(claimed_str).expect("Invalid claimed string");
-    let claimed_fp = F::from_bigint(claimed_bigint).expect("Failed to convert to field element");
-    // Convert field element to bytes
-    let claimed_bytes = claimed_fp.into_bigint().to_bytes_le(); // or to_bytes_be()
-
-    // Create a 32-byte seed from the field element bytes
-    let mut seed = [0u8; 32];
-    let copy_len = claimed_bytes.len().min(32);
-    seed[..copy_len].copy_from_slice(&claimed_bytes[..copy_len]);
-
-    // Use as RNG seed
-    let mut rng1 = StdRng::from_seed(seed);
-
-    let g = Petnames::default();
-    let name1 = g.generate(&mut rng1, 2, " ").expect("no name generated");
+    let claimed_bigint = <F as PrimeField>::BigInt::from_str(claimed_str)
+        .map_err(|_| ApiError::malformed("invalid claimed pseudonym"))?;
+    let claimed_fp = F::from_bigint(claimed_bigint)
+        .ok_or_else(|| ApiError::malformed("claimed pseudonym is not a field element"))?;
+    let name1 = petname_of(claimed_fp)?;
+
+    // The pseudonym must be derived for this poll, so it is the same for every vote of a user
+    let ts: i64 = input
+        .timestamp
+        .try_into()
+        .map_err(|_| ApiError::malformed("timestamp out of range"))?;
+    let context = get_context_from_timestamp(ts)
+        .ok_or_else(|| ApiError::NotFound(format!("no open poll at {}", input.timestamp)))?;
+    let context_fp = <F as PrimeField>::BigInt::from_str(&context)
+        .ok()
+        .and_then(F::from_bigint)
+        .ok_or_else(|| ApiError::malformed("the poll does not take pseudonym votes"))?;
 
     // Pseudo proof
     let mut reader = &input.proof[..];
 
-    let proof = <Groth16<E> as SNARK<F>>::Proof::deserialize_with_mode(
-        &mut reader,
-        Compress::No,
-        Validate::Yes,
-    )
-    .unwrap();
+    let (proof, _): (<Groth16<E> as SNARK<F>>::Proof, _) = read_envelope(&mut reader)?;
 
     let pub_inputs: Vec<F> =
-        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
+    if pub_inputs != [context_fp, claimed_fp] {
+        return Err(ApiError::BadProof(
+            "the proof is not for the claimed pseudonym in the context of the poll".to_string(),
+        ));
+    }
 
     let start_verify = SystemTime::now();
 
-    let verified = Groth16::<E>::verify(&vki, &pub_inputs, &proof).unwrap();
+    let verified = vkis
+        .iter()
+        .any(|vki| Groth16::<E>::verify(vki, &pub_inputs, &proof).unwrap_or(false));
 
     let end_verify= SystemTime::now();
 
     info!("Server result: {}", verified);
+    if !verified {
+        return Err(ApiError::BadProof("pseudonym proof did not verify".to_string()));
+    }
 
     // If "upvote", "downvote", "ban", "not ban" is input as string, convert to an emoji 
     let emoji = string_to_emoji(&input.emoji);
+    let emoji_name = emoji_to_name(emoji);
+    let counted = matches!(emoji_name, "upvote" | "downvote" | "ban" | "not ban");
+    if counted && !claim_poll_vote(input.timestamp, &input.claimed)? {
+        return Err(ApiError::AlreadyVoted);
+    }
 
     let mut pseudo = String::from("VOTE FROM: ");
     pseudo.push_str(&name1);
     pseudo.push_str("\n\n");
     pseudo.push_str(emoji);
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486")
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(pseudo)
-        .arg("--quote-timestamp")
-        .arg(&input.timestamp.to_string())
-        .arg("--quote-author")
-        .arg("+15712811486")
-        .arg("--quote-message")
-        .arg("")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let sent = state.bot.send(&input.group_id, &pseudo, Some(input.timestamp)).await;
+    if counted && sent.is_err() {
+        release_poll_vote(input.timestamp, &input.claimed)?;
+    }
 
     let end_time = SystemTime::now();
 
-    let start_time = match load_start_time("pseudo_vote") {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Failed to load latency psuedo vote start time: {}", e);
-            return;
+    match load_start_time("pseudo_vote") {
+        Ok(start_time) => {
+            if let Err(e) = append_timing_line_features("pseudo_vote", start_time, end_time) {
+                eprintln!("Failed to write timing file for latency psuedo vote: {}", e);
+            }
         }
-    };
-
-    if let Err(e) = append_timing_line_features("pseudo_vote", start_time, end_time) {
-        eprintln!("Failed to write timing file for latency psuedo vote: {}", e);
+        Err(e) => eprintln!("Failed to load latency psuedo vote start time: {}", e),
     }
 
     if let Err(e) = append_timing_line_verify("pseudo_vote", start_verify, end_verify) {
         eprintln!(" Failed to write timing file for pseudo vote verify: {}", e);
     }
 
-    let emoji_name = emoji_to_name(emoji);
-
+    let sent = sent?;
     match emoji_name {
         "upvote" | "downvote" | "ban" | "not ban" => {
-            append_vote(input.timestamp, &name1, input.claimed.clone(), emoji)
-                .expect("Failed to append vote");
+            append_vote(input.timestamp, &name1, input.claimed.clone(), emoji)?;
         }
         "hatespeech" => {
             println!("Hate speech flagged.");
@@ -1500,23 +1186,28 @@ pub async fn forward_vote(
         _ => (),
     }
 
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
+    Ok(format!("Sent successfully: {}", sent))
+}
 
-            if output.status.success() {
-                println!("Sent successfully: {}", stdout)
-            } else {
-                println!("Error sending: {}", stderr)
-            }
-        }
-        Err(e) => println!("Failed to spawn process: {:?}", e),
-    }
+pub async fn forward_vote_count(
+    State(state): State<ServerLock>,
+    Json(input): Json<JsonRpcCountVotes>,
+) -> Result<String, ApiError> {
+    let sent = tally_poll(&state, &input.group_id, input.timestamp).await?;
+    Ok(format!("Vote count done successfully: {}", sent))
 }
 
-pub async fn forward_vote_count(Json(input): Json<JsonRpcCountVotes>) -> impl IntoResponse {
-    let ts = input.timestamp.try_into().unwrap();
+/// Post the results of the poll sent at `timestamp` to `group_id`, and close it if anyone voted.
+/// When a ban poll passes, the author of the message it is about is banned. Returns the
+/// timestamp of the results message.
+pub(crate) async fn tally_poll(
+    state: &ServerState,
+    group_id: &str,
+    timestamp: u64,
+) -> Result<u64, ApiError> {
+    let ts: i64 = timestamp
+        .try_into()
+        .map_err(|_| ApiError::malformed("timestamp out of range"))?;
 
     let (yes, no) = count_votes_by_timestamp(ts);
     let is_ban = is_ban_poll_by_timestamp(ts);
@@ -1576,66 +1267,28 @@ pub async fn forward_vote_count(Json(input): Json<JsonRpcCountVotes>) -> impl In
         result_message.push_str("🤷 It's a tie!");
     }
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486") // your fixed sending bot number
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id) // dynamic group id
-        .arg("-m")
-        .arg(result_message) // dynamic message
-        .arg("--quote-timestamp")
-        .arg(&input.timestamp.to_string())
-        .arg("--quote-author")
-        .arg("+15712811486")
-        .arg("--quote-message")
-        .arg("")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let sent = state.bot.send(group_id, &result_message, Some(timestamp)).await?;
 
     if yes > no && is_ban {
-        if let Some(ban_flag) = get_ban_from_timestamp(input.timestamp.try_into().unwrap()) {
-            if ban_flag >= 0 {
-                // Fix this later --> server cannot call client-side function in this way
-                // let timestamp = ban_flag as u64;
-                // tokio::task::spawn_blocking(move || {
-                //     if let Err(e) = ban(timestamp) {
-                //         eprintln!("Ban error: {:?}", e);
-                //     }
-                // });
+        match (get_ban_from_timestamp(ts), get_poll_info(timestamp)?) {
+            (Some(ban_ts), Some(info)) if ban_ts > 0 => {
+                ban_message(state, &info.group, ban_ts as u64).await?;
             }
-        } else {
-            eprintln!("No ban flag found for timestamp {}", input.timestamp);
+            _ => eprintln!("No ban flag found for timestamp {}", timestamp),
         }
     }
 
     if total != 0 {
-        if let Err(e) = delete_poll_entry_by_timestamp(input.timestamp) {
+        if let Err(e) = delete_poll_entry_by_timestamp(timestamp) {
             eprintln!(
                 "Failed to delete poll entry for timestamp {}: {}",
-                input.timestamp, e
+                timestamp, e
             );
         }
-        let _ = delete_poll_pseudo_entry_by_timestamp(input.timestamp);
+        let _ = delete_poll_pseudo_entry_by_timestamp(timestamp);
     }
 
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-
-            if output.status.success() {
-                format!("Vote count done successfully: {}", stdout)
-            } else {
-                format!("Error sending poll: {}", stderr)
-            }
-        }
-        Err(e) => format!("Failed to spawn process: {:?}", e),
-    }
+    Ok(sent)
 }
 
 pub fn emoji_to_name(emoji: &str) -> &'static str {
@@ -1679,207 +1332,219 @@ pub fn string_to_emoji(input: &str) -> &str {
 pub async fn forward_authorship(
     State(state): State<ServerLock>,
     Json(input): Json<JsonAuthorship>,
-) -> impl IntoResponse {
-    let vki = state
-        .write()
-        .await
-        .keys
-        .authorship_pred_verifying_key
-        .clone();
+) -> Result<StatusCode, ApiError> {
+    let vkis = find_group(&state, &input.group)?
+        .state
+        .verifying_keys(catalog::AUTHORSHIP_PRED);
 
     let mut reader = &input.proof[..];
 
-    let proof = <Groth16<E> as SNARK<F>>::Proof::deserialize_with_mode(
-        &mut reader,
-        Compress::No,
-        Validate::Yes,
-    )
-    .unwrap();
+    let (proof, _): (<Groth16<E> as SNARK<F>>::Proof, _) = read_envelope(&mut reader)?;
 
     let pub_inputs: Vec<F> =
-        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
 
 
     let start_verify = SystemTime::now();
 
-    let verified = Groth16::<E>::verify(&vki, &pub_inputs, &proof).unwrap();
+    let verified = vkis
+        .iter()
+        .any(|vki| Groth16::<E>::verify(vki, &pub_inputs, &proof).unwrap_or(false));
 
     let end_verify = SystemTime::now();
 
     info!("[SERVER] Verification result: {}", verified);
+    if !verified {
+        return Err(ApiError::BadProof("authorship proof did not verify".to_string()));
+    }
 
-    let claimed1 = pub_inputs[0];
-    let claimed2 = pub_inputs[2];
-
-    // Generate petname for claimed1
-    let bytes1 = claimed1.into_bigint().to_bytes_le();
-    let mut seed1 = [0u8; 32];
-    seed1[..bytes1.len()].copy_from_slice(&bytes1[..]);
-    let mut rng1 = StdRng::from_seed(seed1);
-
-    let g = Petnames::default();
-    let name1 = g.generate(&mut rng1, 2, " ").expect("no name generated");
-
-    // Generate petname for claimed2
-    let bytes2 = claimed2.into_bigint().to_bytes_le();
-    let mut seed2 = [0u8; 32];
-    seed2[..bytes2.len()].copy_from_slice(&bytes2[..]);
-    let mut rng2 = StdRng::from_seed(seed2);
-
-    let name2 = g.generate(&mut rng2, 2, " ").expect("no name generated");
+    // The public inputs are the (context, pseudonym) pairs, with any repeated to pad the proof
+    let mut claimed: Vec<F> = vec![];
+    for pair in pub_inputs.chunks_exact(2) {
+        if !claimed.contains(&pair[1]) {
+            claimed.push(pair[1]);
+        }
+    }
+    if claimed.len() < 2 {
+        return Err(ApiError::malformed("expected at least two pseudonyms"));
+    }
+    let names = claimed
+        .into_iter()
+        .map(petname_of)
+        .collect::<Result<Vec<_>, _>>()?;
 
     let mut message = String::new();
     message.push_str("CLAIMED AUTHORSHIP INITIATED\n\n");
-    message.push_str("This message proves that the following two pseudonyms belong to the same anonymous user:\n\n");
-    message.push_str(&format!("• {}\n• {}\n\n", name1, name2));
+    message.push_str(&format!(
+        "This message proves that the following {} pseudonyms belong to the same anonymous user:\n\n",
+        names.len()
+    ));
+    for name in &names {
+        message.push_str(&format!("• {}\n", name));
+    }
+    message.push('\n');
     message.push_str("This demonstrates authorship continuity without revealing identity.\n\n");
 
     println!("Claimed authorship message:\n{}", message);
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486") // fixed anon bot number
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(&message)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let sent = state.bot.send(&input.group_id, &message, None).await;
 
     let end_time = SystemTime::now();
 
-    let start_time = match load_start_time("author") {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Failed to load latency author start time: {}", e);
-            return;
+    match load_start_time("author") {
+        Ok(start_time) => {
+            if let Err(e) = append_timing_line_features("author", start_time, end_time) {
+                eprintln!("Failed to write timing file for latency author: {}", e);
+            }
         }
-    };
-
-    if let Err(e) = append_timing_line_features("author", start_time, end_time) {
-        eprintln!("Failed to write timing file for latency author: {}", e);
+        Err(e) => eprintln!("Failed to load latency author start time: {}", e),
     }
 
     if let Err(e) = append_timing_line_verify("author", start_verify, end_verify) {
         eprintln!(" Failed to write timing file for author verify: {}", e);
     }
 
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-
-            if output.status.success() {
-                println!("Sent successfully: {}", stdout)
-            } else {
-                println!("Error sending: {}", stderr)
-            }
-        }
-        Err(e) => println!("Failed to spawn process: {:?}", e),
-    }
+    sent?;
+    Ok(StatusCode::OK)
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn forward_badges(
     State(state): State<ServerLock>,
     Json(input): Json<JsonBadge>,
-) -> impl IntoResponse {
-    let vki = state.write().await.keys.badge_pred_verifying_key.clone();
+) -> Result<StatusCode, ApiError> {
+    let vkis = find_group(&state, &input.group)?
+        .state
+        .verifying_keys(catalog::BADGE_PRED);
 
     let mut reader = &input.proof[..];
 
-    let proof = <Groth16<E> as SNARK<F>>::Proof::deserialize_with_mode(
-        &mut reader,
-        Compress::No,
-        Validate::Yes,
-    )
-    .unwrap();
+    let (proof, _): (<Groth16<E> as SNARK<F>>::Proof, _) = read_envelope(&mut reader)?;
 
     let pub_inputs: Vec<F> =
-        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
 
     let start_verify = SystemTime::now();
 
-    let verified = Groth16::<E>::verify(&vki, &pub_inputs, &proof).unwrap();
+    let verified = vkis
+        .iter()
+        .any(|vki| Groth16::<E>::verify(vki, &pub_inputs, &proof).unwrap_or(false));
 
     let end_verify = SystemTime::now();
 
     info!("[SERVER] Verification result: {}", verified);
+    if !verified {
+        return Err(ApiError::BadProof("badge proof did not verify".to_string()));
+    }
 
-    let badge_str = pub_inputs[1].to_string();
+    let badge_str = pub_inputs
+        .get(1)
+        .ok_or_else(|| ApiError::malformed("expected a badge"))?
+        .to_string();
 
     let mut message = String::new();
     message.push_str("CLAIMED BADGE INITIATED\n\n");
     message.push_str("This message demonstrates that the following badge belongs to anonymous user:\n\n");
     message.push_str(&badge_str);
 
-    let output = Command::new("signal-cli-client")
-        .arg("-a")
-        .arg("+15712811486")
-        .arg("--json-rpc-tcp")
-        .arg("127.0.0.1:7583")
-        .arg("send")
-        .arg("-g")
-        .arg(&input.group_id)
-        .arg("-m")
-        .arg(&message)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+    let sent = state.bot.send(&input.group_id, &message, None).await;
 
     let end_time = SystemTime::now();
 
-    let start_time = match load_start_time("badge") {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Failed to load latency badge start time: {}", e);
-            return;
+    match load_start_time("badge") {
+        Ok(start_time) => {
+            if let Err(e) = append_timing_line_features("badge", start_time, end_time) {
+                eprintln!("Failed to write timing file for latency badge: {}", e);
+            }
         }
-    };
-    
-    if let Err(e) = append_timing_line_features("badge", start_time, end_time) {
-        eprintln!("Failed to write timing file for latency badge: {}", e);
+        Err(e) => eprintln!("Failed to load latency badge start time: {}", e),
     }
 
     if let Err(e) = append_timing_line_verify("badge", start_verify, end_verify) {
         eprintln!(" Failed to write timing file for badge verify: {}", e);
     }
 
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
+    sent?;
+    Ok(StatusCode::OK)
+}
+
+/// Verify that a pseudonym has at least some reputation, and announce it in the group. The
+/// reputation itself is not revealed.
+#[tracing::instrument(skip_all)]
+pub async fn forward_reputation_proof(
+    State(state): State<ServerLock>,
+    Json(input): Json<JsonRepProof>,
+) -> Result<StatusCode, ApiError> {
+    let vkis = find_group(&state, &input.group)?
+        .state
+        .verifying_keys(catalog::REPUTATION_PRED);
+
+    let mut reader = &input.proof[..];
+
+    let (proof, _): (<Groth16<E> as SNARK<F>>::Proof, _) = read_envelope(&mut reader)?;
+
+    let pub_inputs: Vec<F> =
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
+
+    let [_, claimed, at_least] = pub_inputs[..] else {
+        return Err(ApiError::malformed("expected a pseudonym and a reputation"));
+    };
+    // The range check in the circuit only holds for reputations within the bounds
+    if at_least > F::from(REP_BOUNDS.max) {
+        return Err(ApiError::malformed(format!(
+            "reputation must be at most {}",
+            REP_BOUNDS.max
+        )));
+    }
+
+    let start_verify = SystemTime::now();
+
+    let verified = vkis
+        .iter()
+        .any(|vki| Groth16::<E>::verify(vki, &pub_inputs, &proof).unwrap_or(false));
+
+    let end_verify = SystemTime::now();
+
+    info!("[SERVER] Verification result: {}", verified);
+    if !verified {
+        return Err(ApiError::BadProof("reputation proof did not verify".to_string()));
+    }
+
+    let mut message = String::new();
+    message.push_str("CLAIMED REPUTATION\n\n");
+    message.push_str(&format!(
+        "This message proves that {} has a reputation of at least {}, without revealing it.\n",
+        petname_of(claimed)?,
+        at_least
+    ));
+
+    let sent = state.bot.send(&input.group_id, &message, None).await;
+
+    let end_time = SystemTime::now();
 
-            if output.status.success() {
-                println!("Sent successfully: {}", stdout)
-            } else {
-                println!("Error sending: {}", stderr)
+    match load_start_time("rep_proof") {
+        Ok(start_time) => {
+            if let Err(e) = append_timing_line_features("rep_proof", start_time, end_time) {
+                eprintln!("Failed to write timing file for latency rep_proof: {}", e);
             }
         }
-        Err(e) => println!("Failed to spawn process: {:?}", e),
+        Err(e) => eprintln!("Failed to load latency rep_proof start time: {}", e),
     }
-}
 
-pub async fn forward_context_ts(Json(payload): Json<TimestampRequest>) -> Response {
-    match get_context_from_timestamp(payload.timestamp) {
-        Some(context) => {
-            let response = ContextResponse { context };
-            Json(response).into_response()
-        }
-        None => (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({
-                "error": "No context found for that timestamp"
-            })),
-        )
-            .into_response(),
+    if let Err(e) = append_timing_line_verify("rep_proof", start_verify, end_verify) {
+        eprintln!(" Failed to write timing file for rep_proof verify: {}", e);
     }
+
+    sent?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn forward_context_ts(
+    Json(payload): Json<TimestampRequest>,
+) -> Result<Json<ContextResponse>, ApiError> {
+    let context = get_context_from_timestamp(payload.timestamp)
+        .ok_or_else(|| ApiError::NotFound("No context found for that timestamp".to_string()))?;
+    Ok(Json(ContextResponse { context }))
 }
 
 /// Generate a random field element and return it as a string.
@@ -1892,41 +1557,212 @@ pub fn generate_context_string<F: PrimeField + UniformRand>() -> String {
 #[tracing::instrument(skip_all)]
 pub async fn handle_user_join(
     State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
     payload: Bytes,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<impl IntoResponse, ApiError> {
     info!("[SERVER] handle_user_join called!");
 
     let mut cursor = std::io::Cursor::new(payload);
-    let object = Com::<F>::deserialize_with_mode(&mut cursor, Compress::No, Validate::Yes)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let object = Com::<F>::deserialize_with_mode(&mut cursor, Compress::No, Validate::Yes)?;
+
+    let group = find_group(&state, &query.group)?;
+    let mut bulletins = group.bulletins.write().await;
+
+    let result =
+        <SigObjStore<F, GrumpkinSchnorr> as JoinableBulletin<F, MsgUser>>::join_with_policy(
+            &mut bulletins.store.obj_bul,
+            &mut *state.join_policy.lock().unwrap(),
+            object,
+            &(),
+            (),
+        );
+
+    match result {
+        Ok(()) => {
+            bulletins.record_anonymity();
+            bulletins.persist(&query.group);
+            Ok(StatusCode::OK)
+        }
+        Err(JoinError::Rejected(e)) => Err(e.into()),
+        Err(JoinError::Bulletin(_)) => Err(ApiError::malformed("object could not join the bulletin")),
+    }
+}
 
-    let mut server = state.write().await;
+#[tracing::instrument(skip_all)]
+pub async fn handle_get_anonymity(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+) -> Result<Json<Value>, ApiError> {
+    info!("[GET] Anonymity set");
+    let group = find_group(&state, &query.group)?;
+    let bulletins = group.bulletins.read().await;
+    let size =
+        <SigObjStore<F, GrumpkinSchnorr> as PublicUserBul<F, MsgUser>>::anonymity_set_size(
+            &bulletins.store.obj_bul,
+        );
+    let epochs: Vec<Value> = bulletins
+        .anonymity
+        .epochs()
+        .map(|(epoch, size)| serde_json::json!({ "epoch": epoch.to_string(), "size": size }))
+        .collect();
 
-    let result = <SigObjStore<F, GrumpkinSchnorr> as JoinableBulletin<F, MsgUser>>::join_bul(
-        &mut server.db.obj_bul,
-        object,
-        (),
-    );
+    Ok(Json(serde_json::json!({ "size": size, "epochs": epochs })))
+}
 
-    match result {
-        Ok(()) => Ok(StatusCode::OK),
-        Err(_) => Err(StatusCode::BAD_REQUEST),
+/// Create a group with fresh bulletins and keys, if it does not already exist.
+#[tracing::instrument(skip_all)]
+pub async fn handle_create_group(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+) -> Result<StatusCode, ApiError> {
+    info!("[SERVER] Creating group {}", query.group);
+    if !state.config.allows_group(&query.group) {
+        return Err(ApiError::Forbidden(format!(
+            "group {} is not in the allowlist",
+            query.group
+        )));
     }
+    // Generating the keys of a group takes a while, so keep it off the async workers
+    tokio::task::spawn_blocking(move || state.create_group(&query.group, &mut OsRng))
+        .await
+        .map_err(ApiError::internal)??;
+    Ok(StatusCode::OK)
+}
+
+fn key_meta_json(meta: &KeyMeta) -> Value {
+    let generated = meta
+        .generated
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    serde_json::json!({
+        "circuit": hex::encode(meta.circuit),
+        "proving_key_digest": hex::encode(meta.proving_key_digest),
+        "verifying_key_digest": hex::encode(meta.verifying_key_digest),
+        "generated": generated,
+    })
+}
+
+/// The digests and generation times of a group's keys, along with any retired keys still
+/// accepted, so clients can check they hold the keys the server verifies with.
+#[tracing::instrument(skip_all)]
+pub async fn handle_get_key_meta(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+) -> Result<Json<Value>, ApiError> {
+    info!("[GET] Key metadata");
+    let group = find_group(&state, &query.group)?.state;
+    let now = SystemTime::now();
+
+    let mut keys = vec![];
+    for (id, entry) in group.interactions.iter() {
+        if entry.verifying_key.is_none() {
+            continue;
+        }
+        let Some(meta) = state
+            .key_store
+            .meta(&group_key_id(&query.group, &entry.name))
+            .map_err(ApiError::internal)?
+        else {
+            continue;
+        };
+        let retired: Vec<Value> = group
+            .retired
+            .iter()
+            .filter(|k| k.id == id && k.until > now)
+            .map(|k| {
+                let until = k.until.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let mut json = k.meta.as_ref().map(key_meta_json).unwrap_or_default();
+                json["until"] = until.as_secs().into();
+                json
+            })
+            .collect();
+
+        let mut json = key_meta_json(&meta);
+        json["id"] = id.into();
+        json["name"] = entry.name.clone().into();
+        json["retired"] = retired.into();
+        keys.push(json);
+    }
+
+    Ok(Json(Value::from(keys)))
+}
+
+#[derive(Deserialize)]
+pub struct RotateKeyRequest {
+    #[serde(default = "default_group")]
+    group: String,
+    /// The name of the interaction to rotate the keys of.
+    name: String,
+    /// How long the old keys are still accepted for, in seconds.
+    grace_secs: Option<u64>,
+}
+
+/// Regenerate the keys of an interaction in a group, accepting the old keys for a grace period.
+#[tracing::instrument(skip_all)]
+pub async fn handle_rotate_key(
+    State(state): State<ServerLock>,
+    Json(input): Json<RotateKeyRequest>,
+) -> Result<StatusCode, ApiError> {
+    info!("[SERVER] Rotating key {} of group {}", input.name, input.group);
+    let grace = input
+        .grace_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_KEY_GRACE);
+    let group = find_group(&state, &input.group)?;
+    if group.state.interactions.id(&input.name).is_none() {
+        return Err(ApiError::NotFound(format!("unknown interaction {}", input.name)));
+    }
+    state
+        .rotate_key(&input.group, &input.name, grace, &mut OsRng)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+/// The identifiers of all groups.
+#[tracing::instrument(skip_all)]
+pub async fn handle_get_groups(State(state): State<ServerLock>) -> impl IntoResponse {
+    info!("[GET] Groups");
+    Json(state.groups())
+}
+
+/// The current roots of a group's bulletins, so members can compare their views of the group.
+#[tracing::instrument(skip_all)]
+pub async fn handle_get_group_roots(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+) -> Result<Json<Value>, ApiError> {
+    info!("[GET] Group roots");
+    let group = find_group(&state, &query.group)?;
+    let bulletins = group.bulletins.read().await;
+    let db = &bulletins.store;
+    let epoch = db.callback_bul.get_epoch();
+    let callback_root = db
+        .callback_bul
+        .get_epoch_root(epoch)
+        .ok_or_else(|| ApiError::internal("no root for the current epoch"))?;
+
+    Ok(Json(serde_json::json!({
+        "group": query.group,
+        "user_root": db.obj_bul.log_head().to_string(),
+        "epoch": epoch.to_string(),
+        "callback_root": callback_root.to_string(),
+    })))
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_standard_proving_key(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Standard proving key");
     let mut keybuf = Vec::new();
-    state
-        .read()
-        .await
+    find_group(&state, &query.group)?
+        .state
         .keys
         .standard_proving_key
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -1934,16 +1770,16 @@ pub async fn handle_get_standard_proving_key(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_standard_pseudo_proving_key(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Standard pseudo proving key");
     let mut keybuf = Vec::new();
-    state
-        .read()
-        .await
+    find_group(&state, &query.group)?
+        .state
         .keys
         .standard_pseudo_proving_key
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -1951,16 +1787,16 @@ pub async fn handle_get_standard_pseudo_proving_key(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_standard_pseudor_proving_key(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Standard pseudo proving key");
     let mut keybuf = Vec::new();
-    state
-        .read()
-        .await
+    find_group(&state, &query.group)?
+        .state
         .keys
         .standard_pseudor_proving_key
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -1968,16 +1804,16 @@ pub async fn handle_get_standard_pseudor_proving_key(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_scan_proving_key(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Scan proving key");
     let mut keybuf = Vec::new();
-    state
-        .read()
-        .await
+    find_group(&state, &query.group)?
+        .state
         .keys
         .scan_proving_key
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -1985,16 +1821,16 @@ pub async fn handle_get_scan_proving_key(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_arbitrary_pred_proving_key(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Standard proving key");
     let mut keybuf = Vec::new();
-    state
-        .read()
-        .await
+    find_group(&state, &query.group)?
+        .state
         .keys
         .pseudonym_pred_proving_key
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -2002,16 +1838,16 @@ pub async fn handle_get_arbitrary_pred_proving_key(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_arbitrary_pred_proving_key2(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Standard proving key");
     let mut keybuf = Vec::new();
-    state
-        .read()
-        .await
+    find_group(&state, &query.group)?
+        .state
         .keys
         .authorship_pred_proving_key
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -2019,16 +1855,33 @@ pub async fn handle_get_arbitrary_pred_proving_key2(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_arbitrary_pred_proving_key3(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Standard proving key");
     let mut keybuf = Vec::new();
-    state
-        .read()
-        .await
+    find_group(&state, &query.group)?
+        .state
         .keys
         .badge_pred_proving_key
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
+
+    Ok(keybuf.into())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn handle_get_reputation_proving_key(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
+    info!("[GET] Reputation proving key");
+    let mut keybuf = Vec::new();
+    find_group(&state, &query.group)?
+        .state
+        .keys
+        .reputation_pred_proving_key
+        .serialize_with_mode(&mut keybuf, Compress::No)
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -2036,34 +1889,73 @@ pub async fn handle_get_arbitrary_pred_proving_key3(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_user_pubkey(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Get pubkey");
     let mut buf = Vec::new();
-    state
+    find_group(&state, &query.group)?
+        .bulletins
         .read()
         .await
-        .db
+        .store
         .obj_bul
         .get_pubkey()
         .serialize_with_mode(&mut buf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
+    Ok(buf.into())
+}
+
+/// A signed checkpoint of the user bulletin at its current version.
+#[tracing::instrument(skip_all)]
+pub async fn handle_get_user_checkpoint(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
+    info!("[GET] User bulletin checkpoint");
+    let checkpoint = find_group(&state, &query.group)?
+        .bulletins
+        .read()
+        .await
+        .store
+        .obj_bul
+        .checkpoint(&state.log_key, &mut OsRng)
+        .ok_or_else(|| ApiError::internal("failed to sign checkpoint"))?;
+    let mut buf = Vec::new();
+    checkpoint
+        .serialize_with_mode(&mut buf, Compress::No)
+        .map_err(ApiError::internal)?;
+    Ok(buf.into())
+}
+
+/// The public key checkpoints of the user bulletin are signed under.
+#[tracing::instrument(skip_all)]
+pub async fn handle_get_user_log_pubkey(
+    State(state): State<ServerLock>,
+) -> Result<Bytes, ApiError> {
+    info!("[GET] User bulletin log pubkey");
+    let mut buf = Vec::new();
+    GrumpkinSchnorr::get_pubkey(&state.log_key)
+        .serialize_with_mode(&mut buf, Compress::No)
+        .map_err(ApiError::internal)?;
     Ok(buf.into())
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_membership_pubkey(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Callback membership pubkey");
     let mut keybuf = Vec::new();
-    state
+    find_group(&state, &query.group)?
+        .bulletins
         .read()
         .await
-        .db
+        .store
         .callback_bul
         .get_pubkey()
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -2071,18 +1963,20 @@ pub async fn handle_get_membership_pubkey(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_nonmembership_pubkey(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Callback nonmembership pubkey");
     let mut keybuf = Vec::new();
-    state
+    find_group(&state, &query.group)?
+        .bulletins
         .read()
         .await
-        .db
+        .store
         .callback_bul
         .nmemb_bul
         .get_pubkey()
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -2090,17 +1984,19 @@ pub async fn handle_get_nonmembership_pubkey(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_user_bulletin(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] User bulletin");
     let mut keybuf = Vec::new();
-    state
+    find_group(&state, &query.group)?
+        .bulletins
         .read()
         .await
-        .db
+        .store
         .obj_bul
         .get_db()
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -2108,17 +2004,19 @@ pub async fn handle_get_user_bulletin(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_callback_bulletin(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Callback membership bulletin");
     let mut keybuf = Vec::new();
-    state
+    find_group(&state, &query.group)?
+        .bulletins
         .read()
         .await
-        .db
+        .store
         .callback_bul
         .get_db()
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -2126,18 +2024,20 @@ pub async fn handle_get_callback_bulletin(
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_callback_nmemb_bulletin(
     State(state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
+    Query(query): Query<GroupQuery>,
+) -> Result<Bytes, ApiError> {
     info!("[GET] Callback nonmembership bulletin");
     let mut keybuf = Vec::new();
-    state
+    find_group(&state, &query.group)?
+        .bulletins
         .read()
         .await
-        .db
+        .store
         .callback_bul
         .nmemb_bul
         .get_db()
         .serialize_with_mode(&mut keybuf, Compress::No)
-        .map_err(error_to_response)?;
+        .map_err(ApiError::internal)?;
 
     Ok(keybuf.into())
 }
@@ -2145,40 +2045,61 @@ pub async fn handle_get_callback_nmemb_bulletin(
 #[tracing::instrument(skip_all)]
 pub async fn handle_verify_arb_pred(
     State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
     body: Bytes,
-) -> impl IntoResponse {
+) -> Result<StatusCode, ApiError> {
     info!("[SERVER] Verifying arbitrary predicate...");
 
     let mut reader = &body[..];
-    let proof = <Groth16<E> as SNARK<F>>::Proof::deserialize_with_mode(
-        &mut reader,
-        Compress::No,
-        Validate::Yes,
-    )
-    .unwrap();
+    let (proof, _): (<Groth16<E> as SNARK<F>>::Proof, _) = read_envelope(&mut reader)?;
     let pub_inputs: Vec<F> =
-        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
-    let vki: &VerifyingKey<E> = &state.read().await.keys.pseudonym_pred_verifying_key;
-
-    let verified = Groth16::<E>::verify(vki, &pub_inputs, &proof).unwrap();
+        Vec::<F>::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes)?;
+    let verified = find_group(&state, &query.group)?
+        .state
+        .verifying_keys(catalog::PSEUDONYM_PRED)
+        .iter()
+        .any(|vki| Groth16::<E>::verify(vki, &pub_inputs, &proof).unwrap_or(false));
 
     info!("[SERVER] Verification result: {}", verified);
+    if !verified {
+        return Err(ApiError::BadProof("predicate proof did not verify".to_string()));
+    }
+    Ok(StatusCode::OK)
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_posts_standard(
     State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
     body: Bytes,
-) -> impl IntoResponse {
+) -> Result<StatusCode, ApiError> {
+    if !verify_and_store_standard(&state, &query.group, &body).await? {
+        return Err(ApiError::BadProof("interaction was not accepted".to_string()));
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Verifies a standard interaction and stores its new object and callbacks.
+///
+/// Returns whether the interaction was accepted (which it is not if the group does not exist), or
+/// an error if `body` is not a valid executed method or was proven under a stale key.
+pub async fn verify_and_store_standard(
+    state: &ServerLock,
+    group: &str,
+    body: &[u8],
+) -> Result<bool, ApiError> {
     info!("[SERVER] Verifying and appending interaction...");
 
-    let mut state2 = state.write().await;
-    let vk = state2.keys.standard_verifying_key.clone();
-    let db = &mut state2.db;
+    let mut reader = body;
+    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> = ExecutedMethod::read_from(&mut reader)?;
 
-    let mut reader = &body[..];
-    let exec: ExecutedMethod<F, Snark, Args, Cr, 1> =
-        ExecutedMethod::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+    let Some(group_state) = state.group(group) else {
+        info!("[SERVER] Unknown group {}", group);
+        return Ok(false);
+    };
+    let mut bulletins = group_state.bulletins.write().await;
+    let db = &mut bulletins.store;
+    let vk = accepted_key(&group_state.state, catalog::STANDARD, &exec, &F::from(0))?;
 
     let verified =
         <GRSchnorrObjStore as UserBul<F, MsgUser>>::verify_interact_and_append::<F, Groth16<E>, 1>(
@@ -2192,27 +2113,7 @@ pub async fn handle_get_posts_standard(
             &vk,
         );
 
-    let cb_tickets = &exec.cb_tik_list; // get callback tickets
-
-    for (cb_com, _) in cb_tickets.iter() {
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("server/zkpair_log.jsonl")
-            .unwrap();
-
-        let mut bytes = vec![];
-        cb_com
-            .serialize_with_mode(&mut bytes, Compress::No)
-            .unwrap();
-
-        writeln!(
-            file,
-            "{{\"callback_com\": \"{}\", \"type\": \"cb\"}}",
-            hex::encode(bytes)
-        )
-        .unwrap();
-    }
+    queue_callbacks(&exec.cb_tik_list)?;
 
     info!("[SERVER] Verification result: {:?}", verified);
     info!("[SERVER] Checking proof and storing interaction...");
@@ -2230,35 +2131,99 @@ pub async fn handle_get_posts_standard(
             db.obj_bul.get_pubkey(),
             true,
             &vk,
-            332, // interaction number
+            catalog::STANDARD,
         );
 
+    bulletins.persist(group);
+
     info!("[SERVER] Verification result: {:?}", res);
     if verified.is_ok() && res.is_ok() {
         info!("[SERVER] Verified and added to bulletin!");
+        Ok(true)
     } else {
         info!("[SERVER] Verification failed. Not added to bulletin.");
+        Ok(false)
     }
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_posts_scan(
     State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
     body: Bytes,
-) -> impl IntoResponse {
-    info!("[BULLETIN / SERVER] Verifying and storing scan...");
+) -> Result<impl IntoResponse, ApiError> {
+    if !verify_and_store_scan(&state, &query.group, &body).await? {
+        return Err(ApiError::BadProof("scan was not accepted".to_string()));
+    }
+    Ok((StatusCode::OK, "Scan verified"))
+}
 
-    let mut state2 = state.write().await;
-    let vk = state2.keys.scan_verifying_key.clone(); // clone only small verifying key
-    let db = &mut state2.db; // after cloning needed stuff, now safe to borrow mutably
+/// Queue a standard interaction to be verified and stored in the background, returning the id to
+/// poll `/api/status/{id}` with.
+#[tracing::instrument(skip_all)]
+pub async fn handle_queue_standard(
+    Extension(jobs): Extension<JobQueue>,
+    Query(query): Query<GroupQuery>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    let id = jobs.submit(Job::Standard {
+        group: query.group,
+        body,
+    })?;
+    Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "id": id }))))
+}
 
-    let mut reader = &body[..];
-    let scan_one: ExecutedMethod<F, Snark, Args, Cr, 0> =
-        ExecutedMethod::deserialize_with_mode(&mut reader, Compress::No, Validate::Yes).unwrap();
+/// Queue a scan to be verified and stored in the background, returning the id to poll
+/// `/api/status/{id}` with.
+#[tracing::instrument(skip_all)]
+pub async fn handle_queue_scan(
+    Extension(jobs): Extension<JobQueue>,
+    Query(query): Query<GroupQuery>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    let id = jobs.submit(Job::Scan {
+        group: query.group,
+        body,
+    })?;
+    Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "id": id }))))
+}
+
+/// The state of a queued job.
+#[tracing::instrument(skip_all)]
+pub async fn handle_get_job_status(
+    Extension(jobs): Extension<JobQueue>,
+    Path(id): Path<u64>,
+) -> Result<Json<JobStatus>, ApiError> {
+    let status = jobs
+        .status(id)
+        .ok_or_else(|| ApiError::NotFound(format!("unknown job {}", id)))?;
+    Ok(Json(status))
+}
 
+/// Verifies a scan of the callback bulletin and stores the user's new object.
+///
+/// Returns whether the scan was accepted (which it is not if the group does not exist), or an
+/// error if `body` is not a valid executed method or was proven under a stale key.
+pub async fn verify_and_store_scan(
+    state: &ServerLock,
+    group: &str,
+    body: &[u8],
+) -> Result<bool, ApiError> {
+    info!("[BULLETIN / SERVER] Verifying and storing scan...");
+
+    let mut reader = body;
+    let scan_one: ExecutedMethod<F, Snark, Args, Cr, 0> = ExecutedMethod::read_from(&mut reader)?;
+
+    let Some(group_state) = state.group(group) else {
+        info!("[SERVER] Unknown group {}", group);
+        return Ok(false);
+    };
+    let mut bulletins = group_state.bulletins.write().await;
+    let db = &mut bulletins.store;
     let memb_pub = db.callback_bul.get_pubkey();
     let nmemb_pub = db.callback_bul.nmemb_bul.get_pubkey();
     let ps = get_extra_pubdata_for_scan2(&db.callback_bul, memb_pub, nmemb_pub, F::from(0));
+    let vk = accepted_key(&group_state.state, catalog::SCAN, &scan_one, &ps)?;
 
     let verified = <GRSchnorrObjStore as UserBul<F, MsgUser>>::verify_interact_and_append::<
         PubScan,
@@ -2288,9 +2253,11 @@ pub async fn handle_get_posts_scan(
         db.obj_bul.get_pubkey(),
         true,
         &vk,
-        442,
+        catalog::SCAN,
     );
 
+    bulletins.persist(group);
+
     info!(
         "[BULLETIN] Checking proof and storing new user... Output: {:?}",
         verified
@@ -2302,27 +2269,23 @@ pub async fn handle_get_posts_scan(
 
     if verified.is_ok() && res.is_ok() {
         info!("[SERVER] Scan verified and user successfully stored!");
+        Ok(true)
     } else {
         info!("[SERVER] Scan verification and storage failed.");
+        Ok(false)
     }
-    (StatusCode::OK, "Scan verified")
 }
 
-#[tracing::instrument(skip_all)]
-pub async fn handle_send_ban_request(
-    State(state): State<ServerLock>,
-    bytes: Bytes,
-) -> impl IntoResponse {
-    info!("[SERVER] Banning user...");
-    let db = &mut state.write().await.db;
-    let mut rng = rand::thread_rng();
-
-    let cb: CallbackCom<Fr, Fr, PlainTikCrypto<Fr>> =
-        CanonicalDeserialize::deserialize_with_mode(&bytes[..], Compress::No, Validate::Yes)
-            .expect("Deserialization failed");
-
-    let arg = arg_ban();
-    let called = db.call(cb, arg, FakeSigPrivkey::sk()).unwrap();
+/// Call a callback ticket with `arg`, appending the call to the callback bulletin. The call is only
+/// seen by scans once the epoch is updated.
+pub(crate) fn call_ticket(
+    db: &mut Store,
+    cb: CallbackCom<Fr, Fr, PlainTikCrypto<Fr>>,
+    arg: Fr,
+) -> Result<(), ApiError> {
+    let called = db
+        .call(cb, arg, FakeSigPrivkey::sk())
+        .map_err(|e| ApiError::internal(format!("{:?}", e)))?;
 
     <GRSchnorrCallbackStore<Fr> as CallbackBul<Fr, Fr, Cr>>::verify_call_and_append(
         &mut db.callback_bul,
@@ -2331,30 +2294,88 @@ pub async fn handle_send_ban_request(
         called.2,
         Time::from(0),
     )
-    .unwrap();
+    .map_err(|e| ApiError::internal(format!("{:?}", e)))
+}
+
+/// Ban the author of the message sent at `timestamp`, posted against `group`, by calling the
+/// callback attached to the message. The epoch is advanced so the ban is seen by the next scan.
+pub(crate) async fn ban_message(
+    state: &ServerState,
+    group: &str,
+    timestamp: u64,
+) -> Result<(), ApiError> {
+    info!("[SERVER] Banning the author of {}", timestamp);
+    call_message(state, group, timestamp, arg_ban()).await
+}
+
+/// Call the callback attached to the message sent at `timestamp`, posted against `group`, with
+/// `arg`, and advance the epoch so the call is seen by the next scan.
+pub(crate) async fn call_message(
+    state: &ServerState,
+    group: &str,
+    timestamp: u64,
+    arg: Fr,
+) -> Result<(), ApiError> {
+    let bytes = find_callback_by_timestamp(timestamp)
+        .map_err(|e| ApiError::NotFound(e.to_string()))?;
+    let cb: CallbackCom<Fr, Fr, PlainTikCrypto<Fr>> =
+        CanonicalDeserialize::deserialize_with_mode(&bytes[..], Compress::No, Validate::Yes)?;
+
+    let group_state = find_group(state, group)?;
+    let mut bulletins = group_state.bulletins.write().await;
+    let db = &mut bulletins.store;
+    call_ticket(db, cb, arg)?;
+    db.callback_bul.update_epoch(&mut OsRng);
+    state.events.epoch_updated(group, db, true);
+    bulletins.persist(group);
+    Ok(())
+}
 
+#[tracing::instrument(skip_all)]
+pub async fn handle_send_ban_request(
+    State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
+    bytes: Bytes,
+) -> Result<StatusCode, ApiError> {
+    info!("[SERVER] Banning user...");
+    let group = find_group(&state, &query.group)?;
+    let mut bulletins = group.bulletins.write().await;
+    let db = &mut bulletins.store;
+    let mut rng = rand::thread_rng();
+
+    let cb: CallbackCom<Fr, Fr, PlainTikCrypto<Fr>> =
+        CanonicalDeserialize::deserialize_with_mode(&bytes[..], Compress::No, Validate::Yes)?;
+
+    call_ticket(db, cb, arg_ban())?;
     db.callback_bul.update_epoch(&mut rng);
+    state.events.epoch_updated(&query.group, db, true);
+    bulletins.persist(&query.group);
     info!("[SERVER] Banned");
+    Ok(StatusCode::OK)
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn handle_send_rep_request(
     State(state): State<ServerLock>,
+    Query(query): Query<GroupQuery>,
     bytes: Bytes,
-) -> impl IntoResponse {
+) -> Result<StatusCode, ApiError> {
     info!("[SERVER] Updating user reputation...");
-    let db = &mut state.write().await.db;
+    let group = find_group(&state, &query.group)?;
+    let mut bulletins = group.bulletins.write().await;
+    let db = &mut bulletins.store;
     let mut rng = rand::thread_rng();
 
     let cb: CallbackCom<Fr, Fr, PlainTikCrypto<Fr>> =
-        CanonicalDeserialize::deserialize_with_mode(&bytes[..], Compress::No, Validate::Yes)
-            .expect("Deserialization failed");
+        CanonicalDeserialize::deserialize_with_mode(&bytes[..], Compress::No, Validate::Yes)?;
 
     let cb_hex = hex::encode(&bytes);
-    let rep = get_reputation_by_cb(&cb_hex).unwrap();
+    let rep = get_reputation_by_cb(&cb_hex)?;
     println!("{:?}", &rep);
     let arg = arg_rep(rep);
-    let called = db.call(cb, arg, FakeSigPrivkey::sk()).unwrap();
+    let called = db
+        .call(cb, arg, FakeSigPrivkey::sk())
+        .map_err(|e| ApiError::internal(format!("{:?}", e)))?;
 
 
     // Start time verify for rep
@@ -2367,7 +2388,7 @@ pub async fn handle_send_rep_request(
         called.2,
         Time::from(0),
     )
-    .unwrap();
+    .map_err(|e| ApiError::internal(format!("{:?}", e)))?;
     // end time verify for rep
     let end_call = SystemTime::now();
 
@@ -2375,6 +2396,9 @@ pub async fn handle_send_rep_request(
     let start_epoch  = SystemTime::now();
 
     db.callback_bul.update_epoch(&mut rng);
+    state.events.epoch_updated(&query.group, db, true);
+    bulletins.persist(&query.group);
+    record_applied_reputation(&cb_hex, &query.group, rep)?;
     info!("[SERVER] User reputation now updated!");
 
     // end update epoch time 
@@ -2387,77 +2411,653 @@ pub async fn handle_send_rep_request(
     if let Err(e) = append_timing_line_epoch("rep", start_epoch, end_epoch) {
         eprintln!("Failed to write timing file for proof gen: {}", e);
     }
+    Ok(StatusCode::OK)
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn forward_callback(Json(input): Json<TimestampInput>) -> impl IntoResponse {
-    match find_callback_by_timestamp(input.timestamp) {
-        Ok(cb_bytes) => Response::builder()
-            .status(StatusCode::OK)
-            .header("Content-Type", "application/octet-stream")
-            .body(cb_bytes.into())
-            .unwrap(),
-        Err(e) => {
-            tracing::warn!("Callback retrieval failed: {:?}", e);
-            (StatusCode::NOT_FOUND, e.to_string()).into_response()
-        }
-    }
+pub async fn forward_callback(Json(input): Json<TimestampInput>) -> Result<Response, ApiError> {
+    let cb_bytes = find_callback_by_timestamp(input.timestamp).map_err(|e| {
+        tracing::warn!("Callback retrieval failed: {:?}", e);
+        ApiError::NotFound(e.to_string())
+    })?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/octet-stream")],
+        cb_bytes,
+    )
+        .into_response())
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn handle_post_context_and_store(
-    State(_state): State<ServerLock>,
+    State(state): State<ServerLock>,
     Json(input): Json<ContextRequest>,
-) -> Result<Bytes, ErrorResponse> {
-
-     // Check if thread already exists
-     if let Ok(file) = File::open("server/context.jsonl") {
-        let reader = BufReader::new(file);
-        for line in reader.lines() {
-            let line = line.map_err(error_to_response)?;
-            if let Ok(existing) = serde_json::from_str::<ContextJson>(&line) {
-                if existing.thread == input.thread {
-                    // Already exists — return the existing entry
-                    let response = serde_json::to_string(&existing).map_err(error_to_response)?;
-                    return Ok(Bytes::from(response));
-                }
-            }
-        }
-    }
+) -> Result<Bytes, ApiError> {
 
     // 1. Generate a random field element
     let mut rng = OsRng;
     let context = F::rand(&mut rng);
     let context_str = context.into_bigint().to_string();
 
-    // 2. Create the JSON object
-    let json_obj = ContextJson {
+    // 2. Store the context of the thread, unless the thread already exists
+    let existing = insert_thread_context(&input.thread, &context_str).map_err(ApiError::internal)?;
+    if let Some(context) = existing {
+        // Already exists — return the existing entry
+        let existing = ContextJson {
+            thread: input.thread,
+            context,
+        };
+        let response = serde_json::to_string(&existing).map_err(ApiError::internal)?;
+        return Ok(Bytes::from(response));
+    }
+    state.events.publish(Event::Context {
         thread: input.thread.clone(),
+        context: context_str.clone(),
+    });
+
+    // 3. Create the JSON object
+    let json_obj = ContextJson {
+        thread: input.thread,
         context: context_str,
     };
 
-    // 3. Convert to a single JSON line
+    // 4. Convert to a single JSON line
     let json_line = serde_json::to_string(&json_obj)
-        .map_err(error_to_response)?
+        .map_err(ApiError::internal)?
         + "\n";
 
-    // 4. Append to file
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("server/context.jsonl")
-        .map_err(error_to_response)?;
-    file.write_all(json_line.as_bytes())
-        .map_err(error_to_response)?;
-
     // 5. Return JSON response
     Ok(Bytes::from(json_line))
 }
 
+/// The most threads `/api/contexts` returns at once.
+const MAX_CONTEXTS_PAGE: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct ContextsQuery {
+    /// Only threads created after the thread with this id.
+    after: Option<u64>,
+    /// Only threads whose name contains this, ignoring case.
+    search: Option<String>,
+    #[serde(default = "default_contexts_limit")]
+    limit: usize,
+}
+
+fn default_contexts_limit() -> usize {
+    100
+}
+
+/// A page of threads in the order they were created. `next` is the `after` of the next page, and
+/// is missing on the last page.
+#[tracing::instrument(skip_all)]
+pub async fn handle_list_contexts(
+    Query(query): Query<ContextsQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let limit = query.limit.clamp(1, MAX_CONTEXTS_PAGE);
+    let threads = list_thread_contexts(query.after, query.search.as_deref(), limit)?;
+    let next = match threads.last() {
+        Some(last) if threads.len() == limit => Some(last.id),
+        _ => None,
+    };
+    Ok(Json(serde_json::json!({ "contexts": threads, "next": next })))
+}
+
+/// The most posts `/api/posts` returns at once.
+const MAX_POSTS_PAGE: usize = 200;
+
+#[derive(Deserialize)]
+pub struct PostsQuery {
+    /// Only posts sent before this timestamp.
+    before: Option<u64>,
+    /// Only posts sent to this Signal group.
+    group_id: Option<String>,
+    /// Only posts in this pseudonymous thread.
+    thread: Option<String>,
+    #[serde(default = "default_posts_limit")]
+    limit: usize,
+}
+
+fn default_posts_limit() -> usize {
+    20
+}
+
+/// A page of the posts sent through the server, newest first, with their pseudonyms, reputation
+/// and poll votes. `next` is the `before` of the next page, and is missing on the last page.
+#[tracing::instrument(skip_all)]
+pub async fn handle_list_posts(Query(query): Query<PostsQuery>) -> Result<Json<Value>, ApiError> {
+    let limit = query.limit.clamp(1, MAX_POSTS_PAGE);
+    let posts = list_posts(
+        query.before,
+        query.group_id.as_deref(),
+        query.thread.as_deref(),
+        limit,
+    )?;
+    let next = match posts.last() {
+        Some(last) if posts.len() == limit => Some(last.timestamp),
+        _ => None,
+    };
+    Ok(Json(serde_json::json!({ "posts": posts, "next": next })))
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn handle_get_all_contexts(
     State(_state): State<ServerLock>,
-) -> Result<Bytes, ErrorResponse> {
-    let content = std::fs::read_to_string("server/context.jsonl").map_err(error_to_response)?;
+) -> Result<Bytes, ApiError> {
+    let mut content = String::new();
+    for (thread, context) in get_all_contexts().map_err(ApiError::internal)? {
+        let line = serde_json::to_string(&ContextJson { thread, context })
+            .map_err(ApiError::internal)?;
+        content.push_str(&line);
+        content.push('\n');
+    }
     Ok(Bytes::from(content))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::Config, persist, testing};
+
+    // A message with no callback attached is not found
+    #[tokio::test]
+    async fn callback_not_found() {
+        persist::open_temp();
+        let err = forward_callback(Json(TimestampInput { timestamp: 4839100 }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+
+    // The context of a poll is looked up by its timestamp
+    #[tokio::test]
+    async fn context_by_timestamp() {
+        persist::open_temp();
+        let err = forward_context_ts(Json(TimestampRequest { timestamp: 4839101 }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let info = PollInfo {
+            group_id: "signal-group".to_string(),
+            group: DEFAULT_GROUP.to_string(),
+        };
+        append_poll(4839101, 0, "17", info, None).unwrap();
+        let Json(found) = forward_context_ts(Json(TimestampRequest { timestamp: 4839101 }))
+            .await
+            .unwrap();
+        assert_eq!(found.context, "17");
+    }
+
+    // A resubmitted interaction is a duplicate, a reused nullifier is a conflict
+    #[tokio::test]
+    async fn replayed_interaction() {
+        let exec = |object: u64| ExecutedMethod::<F, Snark, Args, Cr, 1> {
+            new_object: F::from(object),
+            old_nullifier: F::from(1),
+            cb_tik_list: [(Default::default(), F::from(0))],
+            cb_com_list: [F::from(7)],
+            cur_time: F::from(0),
+            proof: Default::default(),
+            rate_limit_tag: None,
+            circuit_digest: None,
+        };
+        let mut obj_bul = GRSchnorrObjStore::new(&mut OsRng);
+        assert!(check_replay(&obj_bul, &exec(2)).unwrap().is_none());
+
+        obj_bul.coms.push(F::from(2));
+        obj_bul.old_nuls.push(F::from(1));
+        obj_bul.cb_com_lists.push(vec![F::from(7)]);
+        let response = check_replay(&obj_bul, &exec(2)).unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, r#"{"status":"duplicate"}"#);
+
+        let err = check_replay(&obj_bul, &exec(3)).unwrap_err();
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+    }
+
+    // Joins past the rate limit are turned away, and only for the group asked
+    #[tokio::test]
+    async fn join_limited() {
+        let config = Config {
+            joins_per_minute: 1,
+            ..Config::default()
+        };
+        let state = testing::server(config, "join");
+        testing::add_group(&state, "join", 2);
+        let join = |group: &str, payload: Vec<u8>| {
+            let query = GroupQuery { group: group.to_string() };
+            handle_user_join(State(state.clone()), Query(query), Bytes::from(payload))
+        };
+        let mut object = vec![];
+        F::from(4842)
+            .serialize_with_mode(&mut object, Compress::No)
+            .unwrap();
+
+        let err = join("join", vec![1, 2, 3]).await.err().unwrap();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        let err = join("missing", object.clone()).await.err().unwrap();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let response = join("join", object.clone()).await.ok().unwrap();
+        assert_eq!(response.into_response().status(), StatusCode::OK);
+        let err = join("join", object).await.err().unwrap();
+        assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    // Jobs are reported until they are forgotten, and queued for any group
+    #[tokio::test]
+    async fn job_status() {
+        let state = testing::server(Config::default(), "job-status");
+        let jobs = JobQueue::start(state, 1, 4);
+        let err = handle_get_job_status(Extension(jobs.clone()), Path(4843))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let query = GroupQuery { group: "missing".to_string() };
+        let response = handle_queue_standard(Extension(jobs.clone()), Query(query), Bytes::new())
+            .await
+            .ok()
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(handle_get_job_status(Extension(jobs), Path(0)).await.is_ok());
+    }
+
+    // Groups keep their own bulletins, and only allowed groups are created
+    #[tokio::test]
+    async fn separate_groups() {
+        let config = Config {
+            groups: vec!["shard-a".to_string(), "shard-b".to_string()],
+            ..Config::default()
+        };
+        let state = testing::server(config, "shards");
+        testing::add_group(&state, "shard-a", 2);
+        testing::add_group(&state, "shard-b", 2);
+        assert_eq!(state.groups(), vec!["shard-a", "shard-b"]);
+
+        let query = |group: &str| GroupQuery { group: group.to_string() };
+        let mut object = vec![];
+        F::from(4844)
+            .serialize_with_mode(&mut object, Compress::No)
+            .unwrap();
+        let joined = handle_user_join(State(state.clone()), Query(query("shard-a")), object.into());
+        assert!(joined.await.is_ok());
+        for (group, size) in [("shard-a", 1), ("shard-b", 0)] {
+            let Json(anonymity) = handle_get_anonymity(State(state.clone()), Query(query(group)))
+                .await
+                .unwrap();
+            assert_eq!(anonymity["size"], size);
+        }
+
+        let err = handle_get_standard_proving_key(State(state.clone()), Query(query("shard-c")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        let err = handle_create_group(State(state), Query(query("shard-c")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+    }
+
+    // Attachments are sent as data URIs, named when they have a name
+    #[test]
+    fn attachment_uri() {
+        let attachment = Attachment {
+            filename: Some("a.txt".to_string()),
+            content_type: "text/plain".to_string(),
+            data: b"hi".to_vec(),
+        };
+        assert_eq!(attachment.data_uri(), "data:text/plain;filename=a.txt;base64,aGk=");
+        let attachment = Attachment {
+            filename: None,
+            ..attachment
+        };
+        assert_eq!(attachment.data_uri(), "data:text/plain;base64,aGk=");
+    }
+
+    // A post's proof covers its attachments, so a post with other attachments is refused
+    #[tokio::test]
+    async fn attachments_bound() {
+        let state = testing::server(Config::default(), "attachments");
+        testing::add_group(&state, "attachments", 2);
+        let (pk, vk) = testing::keys(4);
+        testing::set_key(&state, "attachments", catalog::STANDARD, vk);
+
+        let attachment = |data: &[u8]| Attachment {
+            filename: None,
+            content_type: default_content_type(),
+            data: data.to_vec(),
+        };
+        let (object, nul, cb_com) = (F::from(4852), F::from(1), F::from(7));
+        let digest = attachment_digest([&b"file"[..]]);
+        let exec = ExecutedMethod::<F, Snark, Args, Cr, 1> {
+            new_object: object,
+            old_nullifier: nul,
+            cb_tik_list: [(Default::default(), F::from(0))],
+            cb_com_list: [cb_com],
+            cur_time: F::from(0),
+            proof: testing::prove(&pk, &[object, nul, digest, cb_com]),
+            rate_limit_tag: None,
+            circuit_digest: None,
+        };
+        let post = |attachments| JsonRpcInput {
+            message: "hi".to_string(),
+            group_id: "signal-group".to_string(),
+            proof: exec.to_bytes(Compress::No),
+            group: "attachments".to_string(),
+            attachments,
+        };
+        let stored = || async {
+            let group = state.group("attachments").unwrap();
+            let stored = group.bulletins.read().await.store.obj_bul.coms.contains(&object);
+            stored
+        };
+
+        let err = forward_jsonrpc(State(state.clone()), Json(post(vec![attachment(b"other")])))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert!(!stored().await);
+
+        // The stand-in keys do not satisfy the checks of the callbacks, but the proof of the post
+        // verifies and its object is stored
+        let _ = forward_jsonrpc(State(state.clone()), Json(post(vec![attachment(b"file")]))).await;
+        assert!(stored().await);
+    }
+
+    // Key metadata and rotation are only answered for known groups and interactions
+    #[tokio::test]
+    async fn rotate_unknown_key() {
+        let state = testing::server(Config::default(), "rotate");
+        testing::add_group(&state, "rotate", 2);
+
+        let query = GroupQuery { group: "missing".to_string() };
+        let err = handle_get_key_meta(State(state.clone()), Query(query))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let rotate = |group: &str, name: &str| RotateKeyRequest {
+            group: group.to_string(),
+            name: name.to_string(),
+            grace_secs: None,
+        };
+        let err = handle_rotate_key(State(state.clone()), Json(rotate("missing", "standard")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        let err = handle_rotate_key(State(state.clone()), Json(rotate("rotate", "missing")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        // The stand-in keys have no metadata in the key store
+        let query = GroupQuery { group: "rotate".to_string() };
+        let Json(meta) = handle_get_key_meta(State(state), Query(query)).await.unwrap();
+        assert_eq!(meta, Value::from(Vec::<Value>::new()));
+    }
+
+    // A pseudonym votes once on a poll, and may vote again when its vote could not be sent
+    #[tokio::test]
+    async fn vote_once() {
+        let info = || crate::helpers::PollInfo {
+            group_id: "signal-group".to_string(),
+            group: "votes".to_string(),
+        };
+        persist::open_temp();
+        append_poll(4855001, 0, "42", info(), None).unwrap();
+        append_poll(4855002, 0, "not a field element", info(), None).unwrap();
+
+        let mut config = Config::default();
+        config.signal.retries = 0;
+        config.signal.tcp = "127.0.0.1:1".to_string();
+        let offline = testing::server(config, "votes-offline");
+        let (pk, _) = testing::add_group(&offline, "votes", 2);
+        let (tcp, requests) =
+            testing::signal_daemon(|_| Some(Ok(serde_json::json!({ "timestamp": 4855003 })))).await;
+        let mut config = Config::default();
+        config.signal.tcp = tcp;
+        let state = testing::server(config, "votes");
+        let (online_pk, _) = testing::add_group(&state, "votes", 2);
+
+        let claimed = F::from(4855);
+        let vote = |timestamp, pk: &common::PK, context| JsonRpcVote {
+            group_id: "signal-group".to_string(),
+            emoji: "upvote".to_string(),
+            timestamp,
+            claimed: claimed.into_bigint().to_string(),
+            proof: testing::proof(pk, &[context, claimed]),
+            group: "votes".to_string(),
+        };
+
+        let err = forward_vote(State(offline), Json(vote(4855001, &pk, F::from(42))))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+
+        forward_vote(State(state.clone()), Json(vote(4855001, &online_pk, F::from(42))))
+            .await
+            .unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(count_votes_by_timestamp(4855001), (1, 0));
+
+        let err = forward_vote(State(state.clone()), Json(vote(4855001, &online_pk, F::from(42))))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // The proof must be made in the context of the poll
+        let err = forward_vote(State(state.clone()), Json(vote(4855001, &online_pk, F::from(7))))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        let err = forward_vote(State(state.clone()), Json(vote(4855002, &online_pk, F::from(42))))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        let err = forward_vote(State(state), Json(vote(4855004, &online_pk, F::from(42))))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+
+    // Threads keep their first context, and are listed in pages in the order they were created
+    #[tokio::test]
+    async fn list_contexts() {
+        let state = testing::server(Config::default(), "contexts");
+        let create = |thread: &str| {
+            let request = ContextRequest { thread: thread.to_string() };
+            handle_post_context_and_store(State(state.clone()), Json(request))
+        };
+        let mut contexts = vec![];
+        for thread in ["t4856-a", "T4856-b", "t4856-c"] {
+            let created = create(thread).await.unwrap();
+            contexts.push(serde_json::from_slice::<ContextJson>(&created).unwrap().context);
+        }
+        let again: ContextJson = serde_json::from_slice(&create("t4856-a").await.unwrap()).unwrap();
+        assert_eq!(again.context, contexts[0]);
+
+        let list = |after, limit| {
+            let query = ContextsQuery {
+                after,
+                search: Some("t4856-".to_string()),
+                limit,
+            };
+            async move { handle_list_contexts(Query(query)).await.unwrap().0 }
+        };
+        let page = list(None, 2).await;
+        assert_eq!(page["contexts"][0]["thread"], "t4856-a");
+        assert_eq!(page["contexts"][0]["context"], contexts[0].as_str());
+        assert_eq!(page["contexts"][1]["thread"], "T4856-b");
+        let next = page["next"].as_u64().unwrap();
+        assert_eq!(page["contexts"][1]["id"], next);
+
+        let page = list(Some(next), 2).await;
+        assert_eq!(page["contexts"].as_array().unwrap().len(), 1);
+        assert_eq!(page["contexts"][0]["thread"], "t4856-c");
+        assert!(page["next"].is_null());
+
+        // A page holds at least one thread
+        let page = list(None, 0).await;
+        assert_eq!(page["contexts"].as_array().unwrap().len(), 1);
+        assert!(page["next"].is_u64());
+    }
+
+    // Sent posts are listed newest first in pages, with their threads and how their polls stand
+    #[tokio::test]
+    async fn list_sent_posts() {
+        let (tcp, _) =
+            testing::signal_daemon(|_| Some(Ok(serde_json::json!({ "timestamp": 4868003 })))).await;
+        let mut config = Config::default();
+        config.signal.tcp = tcp;
+        let state = testing::server(config, "posts");
+        let group = "posts-4868";
+        record_post(Post {
+            thread: Some("t4868".to_string()),
+            ..Post::new(4868001, group, "first")
+        })
+        .unwrap();
+        record_post(Post {
+            reply_to: Some(4868001),
+            ..Post::new(4868002, group, "reply")
+        })
+        .unwrap();
+        let poll = JsonRpcPoll {
+            message: "poll".to_string(),
+            group_id: group.to_string(),
+            duration_secs: Some(0),
+        };
+        forward_poll(State(state), Json(poll)).await.unwrap();
+
+        let list = |before, thread: Option<&str>, limit| {
+            let query = PostsQuery {
+                before,
+                group_id: Some(group.to_string()),
+                thread: thread.map(str::to_string),
+                limit,
+            };
+            async move { handle_list_posts(Query(query)).await.unwrap().0 }
+        };
+        let page = list(None, None, 2).await;
+        assert_eq!(page["posts"][0]["timestamp"], 4868003);
+        assert_eq!(page["posts"][0]["poll"]["open"], true);
+        assert_eq!(page["posts"][0]["poll"]["yes"], 0);
+        assert_eq!(page["posts"][1]["message"], "reply");
+        assert_eq!(page["next"], 4868002);
+
+        // A reply is in the thread of the post it replies to
+        let page = list(Some(4868002), None, 2).await;
+        assert_eq!(page["posts"].as_array().unwrap().len(), 1);
+        assert_eq!(page["posts"][0]["message"], "first");
+        assert!(page["next"].is_null());
+        let page = list(None, Some("t4868"), 20).await;
+        assert_eq!(page["posts"].as_array().unwrap().len(), 2);
+        assert_eq!(page["posts"][0]["timestamp"], 4868002);
+
+        delete_poll_entry_by_timestamp(4868003).unwrap();
+        let page = list(None, None, 0).await;
+        assert_eq!(page["posts"].as_array().unwrap().len(), 1);
+        assert_eq!(page["posts"][0]["poll"]["open"], false);
+    }
+
+    // An authorship proof names each pseudonym it links once, and has to link two
+    #[tokio::test]
+    async fn authorship_names() {
+        let (tcp, requests) =
+            testing::signal_daemon(|_| Some(Ok(serde_json::json!({ "timestamp": 4873001 })))).await;
+        let mut config = Config::default();
+        config.signal.tcp = tcp;
+        let state = testing::server(config, "authorship");
+        let (_, pk) = testing::add_group(&state, "authors", 3);
+        let author = |pk: &common::PK, claimed: [u64; 3], group: &str| {
+            let inputs: Vec<F> = claimed
+                .iter()
+                .enumerate()
+                .flat_map(|(i, &c)| [F::from(i as u64), F::from(c)])
+                .collect();
+            let input = JsonAuthorship {
+                proof: testing::proof(pk, &inputs),
+                group_id: "signal-group".to_string(),
+                group: group.to_string(),
+            };
+            forward_authorship(State(state.clone()), Json(input))
+        };
+
+        assert_eq!(author(&pk, [1, 2, 3], "authors").await.unwrap(), StatusCode::OK);
+        assert_eq!(author(&pk, [1, 2, 1], "authors").await.unwrap(), StatusCode::OK);
+        let sent = requests.lock().unwrap().clone();
+        let names = |claimed: &[u64]| {
+            claimed
+                .iter()
+                .map(|&c| format!("• {}\n", petname(F::from(c)).unwrap()))
+                .collect::<String>()
+        };
+        let message = sent[0]["params"]["message"].as_str().unwrap();
+        assert!(message.contains("following 3 pseudonyms"));
+        assert!(message.contains(&names(&[1, 2, 3])));
+        let message = sent[1]["params"]["message"].as_str().unwrap();
+        let linked = format!("the same anonymous user:\n\n{}\n", names(&[1, 2]));
+        assert!(message.contains("following 2 pseudonyms") && message.contains(&linked));
+
+        let err = author(&pk, [1, 1, 1], "authors").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.kind(), "malformed_payload");
+        let (other, _) = testing::keys(6);
+        let err = author(&other, [1, 2, 3], "authors").await.unwrap_err();
+        assert_eq!(err.kind(), "bad_proof");
+        let err = author(&pk, [1, 2, 3], "missing").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    // A reputation proof announces the least reputation it proves, within the bounds
+    #[tokio::test]
+    async fn reputation_at_least() {
+        let (tcp, requests) =
+            testing::signal_daemon(|_| Some(Ok(serde_json::json!({ "timestamp": 4874001 })))).await;
+        let mut config = Config::default();
+        config.signal.tcp = tcp;
+        let state = testing::server(config, "reputation");
+        testing::add_group(&state, "rep", 2);
+        let (pk, vk) = testing::keys(3);
+        testing::set_key(&state, "rep", catalog::REPUTATION_PRED, vk);
+        let prove_rep = |pk: &common::PK, inputs: &[F], group: &str| {
+            let input = JsonRepProof {
+                proof: testing::proof(pk, inputs),
+                group_id: "signal-group".to_string(),
+                group: group.to_string(),
+            };
+            forward_reputation_proof(State(state.clone()), Json(input))
+        };
+        let claimed = F::from(4874);
+
+        let proved = prove_rep(&pk, &[F::from(1), claimed, F::from(10)], "rep").await;
+        assert_eq!(proved.unwrap(), StatusCode::OK);
+        let message = requests.lock().unwrap()[0]["params"]["message"].clone();
+        let expected = format!("{} has a reputation of at least 10,", petname(claimed).unwrap());
+        assert!(message.as_str().unwrap().contains(&expected));
+
+        let too_much = F::from(REP_BOUNDS.max + 1);
+        let err = prove_rep(&pk, &[F::from(1), claimed, too_much], "rep").await.unwrap_err();
+        assert_eq!(err.kind(), "malformed_payload");
+        let (two, _) = testing::keys(2);
+        let err = prove_rep(&two, &[F::from(1), claimed], "rep").await.unwrap_err();
+        assert_eq!(err.kind(), "malformed_payload");
+        let (other, _) = testing::keys(3);
+        let err = prove_rep(&other, &[F::from(1), claimed, F::from(10)], "rep").await.unwrap_err();
+        assert_eq!(err.kind(), "bad_proof");
+        let err = prove_rep(&pk, &[F::from(1), claimed, F::from(10)], "missing").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        let query = GroupQuery { group: "missing".to_string() };
+        let err = handle_get_reputation_proving_key(State(state.clone()), Query(query)).await;
+        assert_eq!(err.unwrap_err().status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,6 +1,6 @@
-use std::path::Path;
+use std::{path::Path, time::Duration};
 
-use jsonrpsee::async_client::ClientBuilder;
+use jsonrpsee::async_client::{Client, ClientBuilder};
 use jsonrpsee::core::client::{Error, SubscriptionClientT};
 use jsonrpsee::http_client::HttpClientBuilder;
 use jsonrpsee::proc_macros::rpc;
@@ -409,6 +409,18 @@ pub async fn connect_tcp(
     Ok(ClientBuilder::default().build_with_tokio(sender, receiver))
 }
 
+/// Connect to a JSON-RPC TCP server, failing requests which take longer than `request_timeout`.
+pub async fn connect_tcp_with_timeout(
+    tcp: impl ToSocketAddrs,
+    request_timeout: Duration,
+) -> Result<Client, std::io::Error> {
+    let (sender, receiver) = super::transports::tcp::connect(tcp).await?;
+
+    Ok(ClientBuilder::default()
+        .request_timeout(request_timeout)
+        .build_with_tokio(sender, receiver))
+}
+
 pub async fn connect_unix(
     socket_path: impl AsRef<Path>,
 ) -> Result<impl SubscriptionClientT, std::io::Error> {
@@ -15,6 +15,8 @@ mod cli;
 mod jsonrpc;
 mod transports;
 
+pub use jsonrpc::connect_tcp_with_timeout;
+
 const DEFAULT_TCP: &str = "127.0.0.1:7583";
 const DEFAULT_SOCKET_SUFFIX: &str = "signal-cli/socket";
 const DEFAULT_HTTP: &str = "http://localhost:8080/api/v1/rpc";
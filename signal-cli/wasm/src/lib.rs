@@ -0,0 +1,119 @@
+//! Browser bindings for the wispy client.
+//!
+//! The browser fetches keys and bulletins over HTTP itself, and passes the response bytes in.
+//! Users are passed around serialized, so they can be kept in browser storage between calls.
+
+use ark_ff::UniformRand;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::{
+    snapshot,
+    zk::{exec_scanint, exec_standint, MsgUser},
+    F, H, PK,
+};
+use rand::rngs::OsRng;
+use wasm_bindgen::prelude::*;
+use zk_callbacks::generic::{object::Time, user::User};
+
+fn de<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, JsError> {
+    T::deserialize_with_mode(bytes, Compress::No, Validate::Yes)
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn ser<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![];
+    value.serialize_with_mode(&mut bytes, Compress::No).unwrap();
+    bytes
+}
+
+/// A snapshot of the server bulletins, built from the bytes served by the server.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Snapshot(snapshot::Snapshot);
+
+#[wasm_bindgen]
+impl Snapshot {
+    /// Takes the responses of `api/user/pubkey`, `api/user/bulletin`,
+    /// `api/callbacks/membership_pubkey`, `api/callbacks/nonmembership_pubkey`,
+    /// `api/callbacks/bulletin`, and `api/callbacks/nmemb_bulletin`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        user_pubkey: &[u8],
+        user_bulletin: &[u8],
+        cb_pubkey: &[u8],
+        cb_nmemb_pubkey: &[u8],
+        cb_bulletin: &[u8],
+        cb_nmemb_bulletin: &[u8],
+    ) -> Result<Snapshot, JsError> {
+        snapshot::Snapshot::new(
+            user_pubkey,
+            user_bulletin,
+            cb_pubkey,
+            cb_nmemb_pubkey,
+            cb_bulletin,
+            cb_nmemb_bulletin,
+        )
+        .map(Snapshot)
+        .map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// The updated user, along with the bytes to send to the server.
+#[wasm_bindgen(getter_with_clone)]
+pub struct Output {
+    /// The serialized user, to be stored by the browser.
+    pub user: Vec<u8>,
+    /// The request body for the server.
+    pub payload: Vec<u8>,
+}
+
+/// Create a new user. The payload is the commitment to `POST` to `api/user/join`.
+#[wasm_bindgen]
+pub fn join() -> Output {
+    let mut rng = OsRng;
+    let user = User::create(
+        MsgUser {
+            sk: F::rand(&mut rng),
+            ..Default::default()
+        },
+        &mut rng,
+    );
+    Output {
+        payload: ser(&user.commit::<H>()),
+        user: ser(&user),
+    }
+}
+
+/// Execute the standard interaction, as when sending a message. The payload is the serialized
+/// executed method.
+#[wasm_bindgen]
+pub fn interact(user: &[u8], proving_key: &[u8], bul: &Snapshot) -> Result<Output, JsError> {
+    let mut user: User<F, MsgUser> = de(user)?;
+    let pk: PK = de(proving_key)?;
+    let exec = exec_standint(
+        &mut user,
+        &mut OsRng,
+        &bul.0,
+        &pk,
+        Time::from(0),
+        F::from(0),
+        (),
+    )
+    .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(Output {
+        user: ser(&user),
+        payload: exec.to_bytes(Compress::No),
+    })
+}
+
+/// Scan a callback. The payload is the serialized executed method.
+#[wasm_bindgen]
+pub fn scan(user: &[u8], proving_key: &[u8], bul: &Snapshot) -> Result<Output, JsError> {
+    let mut user: User<F, MsgUser> = de(user)?;
+    let pk: PK = de(proving_key)?;
+    let exec = exec_scanint(&mut user, &mut OsRng, &bul.0, &pk, &bul.0, Time::from(0))
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(Output {
+        user: ser(&user),
+        payload: exec.to_bytes(Compress::No),
+    })
+}